@@ -1,71 +1,575 @@
-use std::io::{self, Write};
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::collections::BTreeMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
 
-pub fn load_addresses(addresses: &mut Vec<SocketAddr>) {
-    println!("Enter IP addresses and sockets to monitor.");
-    println!("Format: <IP_ADDRESS>:<SOCKET> (e.g., 192.168.1.1:80)");
-    println!("Type 'done' or press Enter on an empty line when finished.");
-    
+use super::config::{self, AlertSettings, ConfigError, ConfigErrors};
+use super::dns_watch::DnsWatcher;
+use super::encrypted_config::{self, KeySource};
+use super::event_bus::MonitorEvent;
+use super::metadata::Metadata;
+use super::monitor::{CheckKind, Monitor};
+use super::pause::Pause;
+use super::target::{CheckSpec, MonitorTarget, millis};
+use super::tunnel::TunnelConfig;
 
-    loop{
-        print!("# ");
-        io::stdout().flush().expect("Failed to flush stdout");
+/// Format version written into address book files. Older files are migrated on load;
+/// newer ones are refused rather than misread.
+pub const ADDRESS_BOOK_VERSION: u32 = 2;
 
+// Each entry upgrades a parsed book from version `index` to `index + 1`.
+const MIGRATIONS: &[fn(&mut JsonValue)] = &[
+    // 0 -> 1: books written before versioning have the same layout, just no version key.
+    |_| {},
+    // 1 -> 2: `paused = true` became a pause with a reason. Old pauses get a placeholder
+    // reason, and "since" the migration, as the real start was never recorded.
+    |book| {
+        let Some(targets) = book.get_mut("target").and_then(JsonValue::as_array_mut) else {
+            return;
+        };
+        for target in targets.iter_mut().filter_map(JsonValue::as_object_mut) {
+            if target.remove("paused").and_then(|p| p.as_bool()) == Some(true) {
+                let pause = Pause::new(LEGACY_PAUSE_REASON).expect("reason is not blank");
+                target.insert("pause".to_string(), serde_json::to_value(pause).expect("pause serializes"));
+            }
+        }
+    },
+];
+const _: () = assert!(MIGRATIONS.len() == ADDRESS_BOOK_VERSION as usize);
+
+/// Reason given to targets paused before pauses had reasons.
+pub const LEGACY_PAUSE_REASON: &str = "paused before pause reasons were recorded";
+
+/// One monitored target as stored in the address book file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressEntry {
+    pub address: SocketAddr,
+    /// The host name the address was resolved from; re-resolved on every start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "is_tcp")]
+    pub check: CheckKind,
+    /// Per-target timeout and interval in milliseconds; unset means the monitor's default.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "millis")]
+    pub timeout: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "millis")]
+    pub interval: Option<Duration>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub retries: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    // Last, as TOML writes them as `[target.spec]`, `[target.metadata]`, `[target.alerts]`
+    // and `[target.pause]` tables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec: Option<CheckSpec>,
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tunnel: Option<TunnelConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pause: Option<Pause>,
+}
+
+impl AddressEntry {
+    fn monitor_target(&self) -> MonitorTarget {
+        MonitorTarget {
+            address: self.address,
+            host: self.host.clone(),
+            check: self.check,
+            spec: self.spec.clone(),
+            timeout: self.timeout,
+            interval: self.interval,
+            retries: self.retries,
+            owner: self.owner.clone(),
+            metadata: self.metadata.clone(),
+            tunnel: self.tunnel.clone(),
+            proxy: self.proxy.clone(),
+            alerts: self.alerts.clone(),
+        }
+    }
+}
+
+fn is_tcp(kind: &CheckKind) -> bool {
+    *kind == CheckKind::Tcp
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// The monitored targets, kept in a TOML (or, by extension, JSON) file so they survive
+/// restarts and can be edited by hand:
+///
+/// ```text
+/// version = 2
+///
+/// [[target]]
+/// address = "10.0.0.5:443"
+/// group = "web"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddressBook {
+    pub version: u32,
+    #[serde(default, rename = "target")]
+    pub targets: Vec<AddressEntry>,
+}
+
+impl Default for AddressBook {
+    fn default() -> Self {
+        Self {
+            version: ADDRESS_BOOK_VERSION,
+            targets: Vec::new(),
+        }
+    }
+}
+
+impl AddressBook {
+    /// `~/.config/rust_npm/targets.toml` (or the platform's equivalent).
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust_npm").join("targets.toml"))
+    }
+
+    /// Reads the book at `path`, migrating older formats; a missing file is an empty book.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let Some(mut value) = read_value(path)? else {
+            return Ok(Self::default());
+        };
+        let version = stored_version(&value);
+        if version > ADDRESS_BOOK_VERSION {
+            return Err(format!(
+                "{} has format version {}, this build only understands up to {}",
+                path.display(),
+                version,
+                ADDRESS_BOOK_VERSION
+            )
+            .into());
+        }
+        for migrate in &MIGRATIONS[version as usize..] {
+            migrate(&mut value);
+        }
+        value["version"] = ADDRESS_BOOK_VERSION.into();
+        let fail = |errors| ConfigErrors {
+            file: path.to_path_buf(),
+            errors,
+        };
+        let book: Self = config::deserialize(value).map_err(|e| fail(vec![e]))?;
+        let errors = book.validate();
+        if !errors.is_empty() {
+            return Err(fail(errors).into());
+        }
+        Ok(book)
+    }
+
+    /// Settings that parse but can't work, like `target[3].interval: must be at least 1s`.
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        for (i, entry) in self.targets.iter().enumerate() {
+            let at = |field: &str| format!("target[{}].{}", i, field);
+            if entry.interval.is_some_and(|interval| interval < Duration::from_secs(1)) {
+                errors.push(ConfigError::new(at("interval"), "must be at least 1s"));
+            }
+            if entry.timeout.is_some_and(|timeout| timeout.is_zero()) {
+                errors.push(ConfigError::new(at("timeout"), "must be longer than 0"));
+            }
+            if let (Some(timeout), Some(interval)) = (entry.timeout, entry.interval)
+                && timeout > interval
+            {
+                errors.push(ConfigError::new(at("timeout"), "must not be longer than the interval"));
+            }
+        }
+        errors
+    }
+
+    /// Format version of the file at `path` without loading it; `None` if there is no file.
+    pub fn file_version(path: &Path) -> Result<Option<u32>, Box<dyn Error>> {
+        Ok(read_value(path)?.map(|value| stored_version(&value)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let content = if is_json(path) {
+            serde_json::to_string_pretty(self)?
+        } else {
+            toml::to_string_pretty(self)?
+        };
+        // A book that was encrypted stays encrypted.
+        if let Some(source) = KeySource::from_env()
+            && fs::read(path).is_ok_and(|data| encrypted_config::is_encrypted(&data))
+        {
+            return encrypted_config::write_encrypted(path, &content, &source);
+        }
+        // Write-and-rename so an interrupted save never leaves a truncated book behind.
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, content)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Snapshot of everything `monitor` currently watches.
+    pub fn from_monitor(monitor: &Monitor) -> Self {
+        let targets = monitor
+            .monitor_targets()
+            .into_iter()
+            .map(|target| AddressEntry {
+                address: target.address,
+                host: target.host,
+                group: monitor.group(target.address),
+                check: target.check,
+                timeout: target.timeout,
+                interval: target.interval,
+                retries: target.retries,
+                owner: target.owner,
+                proxy: target.proxy,
+                spec: target.spec,
+                metadata: target.metadata,
+                tunnel: target.tunnel,
+                alerts: target.alerts,
+                pause: monitor.pause_of(target.address),
+            })
+            .collect();
+        Self {
+            targets,
+            ..Self::default()
+        }
+    }
+
+    /// Adds the book's targets to `monitor`. Returns the ones it refused, with the reason.
+    pub fn apply_to(&self, monitor: &Monitor) -> Vec<(SocketAddr, String)> {
+        let mut refused = Vec::new();
+        for entry in &self.targets {
+            if let Err(e) = monitor.add_monitor_target(entry.monitor_target()) {
+                refused.push((entry.address, e.to_string()));
+                continue;
+            }
+            if entry.group.is_some() {
+                monitor.set_group(entry.address, entry.group.clone()).ok();
+            }
+            if let Some(pause) = &entry.pause {
+                monitor.pause(entry.address, pause.clone()).ok();
+            }
+        }
+        refused
+    }
+}
+
+fn read_value(path: &Path) -> Result<Option<JsonValue>, Box<dyn Error>> {
+    let content = match encrypted_config::read_config(path, KeySource::from_env().as_ref()) {
+        Ok(content) => content,
+        Err(e) if e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::NotFound) => return Ok(None),
+        Err(e) => return Err(format!("cannot read {}: {}", path.display(), e).into()),
+    };
+    let value = if is_json(path) {
+        serde_json::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?
+    } else {
+        toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?
+    };
+    Ok(Some(value))
+}
+
+fn stored_version(value: &JsonValue) -> u32 {
+    value.get("version").and_then(JsonValue::as_u64).unwrap_or(0) as u32
+}
 
-        let mut input = String::new();
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+}
 
-        match io::stdin().read_line(&mut input) {
-            Ok(_) => {
-                let trimmed_input = input.trim();
-                if trimmed_input.is_empty() || trimmed_input.eq_ignore_ascii_case("done") {
-                    break;
+/// Saves the address book to `path` whenever targets are added, paused or regrouped, until
+/// the monitor's event bus closes.
+pub async fn persist_changes(monitor: Arc<Monitor>, path: PathBuf) {
+    let mut events = monitor.bus().subscribe();
+    loop {
+        match events.recv().await {
+            Ok(MonitorEvent::TargetAdded(_)
+                | MonitorEvent::TargetRemoved(_)
+                | MonitorEvent::TargetPaused { .. }
+                | MonitorEvent::TargetGrouped { .. }
+                | MonitorEvent::CheckKindChanged { .. }
+                | MonitorEvent::TargetConfigured(_))
+            | Err(RecvError::Lagged(_)) => {
+                if let Err(e) = AddressBook::from_monitor(&monitor).save(&path) {
+                    eprintln!("Cannot save address book {}: {}", path.display(), e);
                 }
+            }
+            Ok(_) => {}
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
 
+/// A target as the user typed it: a host name or IP address plus port. Host names may
+/// resolve to several addresses, and to different ones over time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostSpec {
+    pub host: String,
+    pub port: u16,
+}
 
-                let parts: Vec<&str> = trimmed_input.split(':').collect();
-
-                if parts.len() == 2 {
-                    let host_str = parts[0].trim();
-                    let port_str = parts[1].trim();
-                    let default_port: u16 = 443; // Default port if parsing fails or is invalid
-
-                    // Trying to create a IPV4Addr object
-                    // Parse the host string into Ipv4Addr
-                    match host_str.parse::<Ipv4Addr>() {
-                        Ok(parsed_ipv4_addr) => {
-                            // IP address parsed successfully.
-                            // Now, parse the port string into u16.
-                            let port_number = match port_str.parse::<u16>() {
-                                Ok(port) => {
-                                    if port == 0 { // Port 0 is often reserved or problematic
-                                        eprintln!(" -> Warning: Port 0 is generally not usable. Using default port {}.", default_port);
-                                        default_port
-                                    } else {
-                                        port
-                                    }
-                                }
-                                Err(e) => {
-                                    eprintln!(" -> Warning: Invalid port number '{}': {}. Using default port {}.", port_str, e, default_port);
-                                    default_port // Use default if parsing fails
-                                }
-                            };
-
-                            let new_socket_addr = SocketAddr::new(IpAddr::V4(parsed_ipv4_addr), port_number);
-                            addresses.push(new_socket_addr);
-                            println!(" -> Added: {}", new_socket_addr);
-                        }
-                        Err(e) => {
-                            eprintln!(" -> Error: Invalid IP address format '{}': {}", host_str, e);
-                        }
-                    }
-                } else {
-                    println!(" -> Invalid format. Please use <IP_ADDRESS>:<PORT> (e.g., 192.168.1.1:80 or google.com:443)");
+impl HostSpec {
+    /// Parses `host:port`, `ip:port` or `[ipv6]:port`. A missing, invalid or zero port falls
+    /// back to `default_port`; the second value then says why.
+    pub fn parse(input: &str, default_port: u16) -> Result<(Self, Option<String>), String> {
+        let input = input.trim();
+        let (host, port_str) = if let Some(rest) = input.strip_prefix('[') {
+            let (host, rest) = rest.split_once(']').ok_or_else(|| format!("missing ']' in '{}'", input))?;
+            (host, rest.strip_prefix(':').unwrap_or(rest))
+        } else {
+            match input.rsplit_once(':') {
+                Some((host, _)) if host.contains(':') => {
+                    return Err(format!("IPv6 addresses need brackets, e.g. [{}]:443", input));
                 }
+                Some((host, port)) => (host, port),
+                None => (input, ""),
+            }
+        };
+        let host = host.trim();
+        if host.is_empty() {
+            return Err(format!("no host in '{}'", input));
+        }
+        let (port, warning) = match port_str.trim().parse::<u16>() {
+            Ok(0) => (default_port, Some(format!("Port 0 is generally not usable. Using default port {}.", default_port))),
+            Ok(port) => (port, None),
+            Err(e) => (default_port, Some(format!("Invalid port number '{}': {}. Using default port {}.", port_str.trim(), e, default_port))),
+        };
+        Ok((
+            Self {
+                host: host.to_string(),
+                port,
+            },
+            warning,
+        ))
+    }
+
+    /// Literal IP addresses need no DNS and are never re-resolved.
+    pub fn is_ip(&self) -> bool {
+        self.host.parse::<IpAddr>().is_ok()
+    }
+}
+
+impl std::fmt::Display for HostSpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]:{}", self.host, self.port)
+        } else {
+            write!(f, "{}:{}", self.host, self.port)
+        }
+    }
+}
+
+/// Adds every address `spec` resolves to as a target with the settings `settings` gives
+/// for it, remembering the host name so `re_resolve` can follow it. Returns the added
+/// addresses; ones already monitored are left as they are.
+pub async fn add_host(monitor: &Monitor, spec: &HostSpec, settings: impl Fn(SocketAddr) -> MonitorTarget) -> Result<Vec<SocketAddr>, Box<dyn Error>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((spec.host.as_str(), spec.port))
+        .await
+        .map_err(|e| format!("cannot resolve {}: {}", spec, e))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("{} has no addresses", spec).into());
+    }
+    let host = (!spec.is_ip()).then(|| spec.host.clone());
+    let known = monitor.targets();
+    let mut added = Vec::new();
+    for addr in addrs.into_iter().filter(|addr| !known.contains(addr)) {
+        monitor.add_monitor_target(MonitorTarget {
+            host: host.clone(),
+            ..settings(addr)
+        })?;
+        added.push(addr);
+    }
+    if added.is_empty() {
+        return Err(format!("{} is already monitored", spec).into());
+    }
+    Ok(added)
+}
+
+/// Re-resolves the host names of `monitor`'s targets every `interval`. Addresses a host
+/// started resolving to are added with the settings and group of its other targets, and
+/// ones it no longer resolves to are removed, so a DNS cutover doesn't leave the old
+/// addresses alarming. Changes are announced as `ResolutionChanged` events. A host that
+/// fails to resolve keeps its targets.
+pub async fn re_resolve(monitor: Arc<Monitor>, interval: Duration) {
+    let watcher = DnsWatcher::new(monitor.bus().clone());
+    while !monitor.is_stopping() {
+        refresh_hosts(&monitor, &watcher).await;
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn refresh_hosts(monitor: &Monitor, watcher: &DnsWatcher) {
+    let mut hosts: BTreeMap<(String, u16), Vec<MonitorTarget>> = BTreeMap::new();
+    for target in monitor.monitor_targets() {
+        if let Some(host) = target.host.clone() {
+            hosts.entry((host, target.address.port())).or_default().push(target);
+        }
+    }
+    for ((host, port), targets) in hosts {
+        let resolved = match watcher.resolve(&host, port).await {
+            Ok(addrs) => addrs,
+            Err(e) => {
+                eprintln!("Cannot re-resolve {}: {}", host, e);
+                continue;
             }
-            Err(error) => {
-                eprintln!("Oopsie made an oopsie. A hundred thousand dollar oops made me loopy: {}", error);
-                break;
+        };
+        if resolved.is_empty() {
+            continue;
+        }
+        let template = &targets[0];
+        let group = monitor.group(template.address);
+        for addr in resolved.iter().filter(|addr| !targets.iter().any(|t| t.address == **addr)) {
+            let target = MonitorTarget {
+                address: *addr,
+                ..template.clone()
+            };
+            // Another host may already have brought the address in.
+            if monitor.add_monitor_target(target).is_ok() {
+                monitor.set_group(*addr, group.clone()).ok();
             }
         }
+        for stale in targets.iter().filter(|t| !resolved.contains(&t.address)) {
+            monitor.remove_target(stale.address).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::event_bus::EventBus;
+    use crate::back_end::ssh::SshTarget;
+
+    #[test]
+    fn test_book_round_trips_through_toml_and_json_and_monitor() {
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let book = AddressBook {
+            version: ADDRESS_BOOK_VERSION,
+            targets: vec![
+                AddressEntry {
+                    address: "10.0.0.5:443".parse().unwrap(),
+                    host: Some("shop.example.com".to_string()),
+                    group: Some("web".to_string()),
+                    check: CheckKind::Http,
+                    timeout: None,
+                    interval: Some(Duration::from_secs(10)),
+                    retries: 0,
+                    owner: Some("web-team".to_string()),
+                    proxy: None,
+                    spec: Some(CheckSpec::Http {
+                        url: "https://shop.example.com/health".to_string(),
+                        expect_status: Some(204),
+                        body_contains: None,
+                    }),
+                    metadata: Metadata::from([("owner".to_string(), "web-team@example.com".to_string())]),
+                    tunnel: Some(TunnelConfig {
+                        bastion: SshTarget::new("bastion.example.com", "monitor"),
+                        jump_hosts: vec!["ops@gateway.example.com:2222".to_string()],
+                        ready_timeout: None,
+                    }),
+                    alerts: Some(AlertSettings {
+                        down_after: 3,
+                        ..AlertSettings::default()
+                    }),
+                    pause: None,
+                },
+                AddressEntry {
+                    address: "10.0.0.6:5432".parse().unwrap(),
+                    host: None,
+                    group: None,
+                    check: CheckKind::Icmp,
+                    timeout: Some(Duration::from_millis(1500)),
+                    interval: None,
+                    retries: 2,
+                    owner: None,
+                    proxy: Some("branch-berlin".to_string()),
+                    spec: None,
+                    metadata: Metadata::new(),
+                    tunnel: None,
+                    alerts: None,
+                    pause: Some(Pause::new("replacing the disk").unwrap().by("ops")),
+                },
+            ],
+        };
+        assert!(book.apply_to(&monitor).is_empty());
+        assert_eq!(AddressBook::from_monitor(&monitor), book);
+
+        let dir = std::env::temp_dir().join(format!("rust_npm_book_{}", uuid::Uuid::new_v4().simple()));
+        for name in ["targets.toml", "targets.json"] {
+            let path = dir.join(name);
+            book.save(&path).unwrap();
+            assert_eq!(AddressBook::load(&path).unwrap(), book);
+        }
+        assert!(fs::read_to_string(dir.join("targets.toml")).unwrap().contains("[[target]]"));
+        assert!(AddressBook::load(&dir.join("missing.toml")).unwrap().targets.is_empty());
+
+        let legacy = dir.join("legacy.toml");
+        fs::write(&legacy, "[[target]]\naddress = \"10.0.0.7:22\"\n").unwrap();
+        assert_eq!(AddressBook::file_version(&legacy).unwrap(), Some(0));
+        assert_eq!(AddressBook::load(&legacy).unwrap().version, ADDRESS_BOOK_VERSION);
+        fs::write(&legacy, "version = 1\n[[target]]\naddress = \"10.0.0.7:22\"\npaused = true\n").unwrap();
+        let pause = AddressBook::load(&legacy).unwrap().targets[0].pause.clone().unwrap();
+        assert_eq!(pause.reason, LEGACY_PAUSE_REASON);
+        fs::write(&legacy, format!("version = {}\n", ADDRESS_BOOK_VERSION + 1)).unwrap();
+        assert!(AddressBook::load(&legacy).is_err());
+        let invalid = format!(
+            "version = {}\n[[target]]\naddress = \"10.0.0.7:22\"\n[[target]]\naddress = \"10.0.0.8:22\"\ninterval = 500\n",
+            ADDRESS_BOOK_VERSION
+        );
+        fs::write(&legacy, invalid).unwrap();
+        let error = AddressBook::load(&legacy).unwrap_err().to_string();
+        assert!(error.ends_with("target[1].interval: must be at least 1s"), "{}", error);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_host_spec_parsing() {
+        let (spec, warning) = HostSpec::parse("localhost:8080", 443).unwrap();
+        assert_eq!((spec.host.as_str(), spec.port, warning), ("localhost", 8080, None));
+        assert!(!spec.is_ip());
+
+        let (spec, warning) = HostSpec::parse("[::1]:0", 443).unwrap();
+        assert_eq!((spec.to_string(), spec.is_ip()), ("[::1]:443".to_string(), true));
+        assert!(warning.unwrap().contains("Port 0"));
+
+        assert!(HostSpec::parse("::1:80", 443).is_err());
+        assert!(HostSpec::parse(":80", 443).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_host_targets_follow_their_dns_records() {
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let spec = HostSpec::parse("localhost:8080", 443).unwrap().0;
+        let added = add_host(&monitor, &spec, |addr| MonitorTarget::new(addr).with_check(CheckKind::Icmp)).await.unwrap();
+        assert!(added.iter().all(|a| a.ip().is_loopback()));
+        assert!(add_host(&monitor, &spec, MonitorTarget::new).await.is_err());
+
+        // Only an address localhost no longer resolves to is left, as after a DNS cutover.
+        let old: SocketAddr = "10.255.0.1:8080".parse().unwrap();
+        let settings = MonitorTarget::new(old).with_check(CheckKind::Icmp);
+        monitor.add_monitor_target(MonitorTarget { host: Some("localhost".to_string()), ..settings }).unwrap();
+        monitor.set_group(old, Some("web".to_string())).unwrap();
+        for addr in &added {
+            monitor.remove_target(*addr).unwrap();
+        }
+
+        refresh_hosts(&monitor, &DnsWatcher::new(monitor.bus().clone())).await;
+        let targets = monitor.monitor_targets();
+        assert_eq!(targets.len(), added.len());
+        assert!(targets.iter().all(|t| added.contains(&t.address) && t.check == CheckKind::Icmp && t.host.as_deref() == Some("localhost")));
+        assert_eq!(monitor.group(added[0]).as_deref(), Some("web"));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,212 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use super::canary::OFFLINE_NOTE;
+use super::check_result::CheckResult;
+use super::config::format_interval;
+use super::state_tracker::{RecoveryRules, StateTracker, TargetState, Transition};
+
+/// The outages and recoveries `rules` would have announced for `results`, in time order.
+/// Failures from while the monitor itself was offline are skipped, as the monitor does.
+pub fn replay(results: &[CheckResult], rules: &RecoveryRules) -> Vec<Transition> {
+    let mut ordered: Vec<&CheckResult> = results.iter().filter(|r| !r.error.as_deref().is_some_and(|e| e.ends_with(OFFLINE_NOTE))).collect();
+    ordered.sort_by_key(|r| r.timestamp);
+    let mut tracker = StateTracker::new(rules.clone());
+    ordered.into_iter().filter_map(|r| tracker.record(r)).collect()
+}
+
+/// Whether an alert fires under the current rules, the proposed ones, or both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    Kept,
+    /// Only the proposed rules announce it.
+    Added,
+    /// Only the current rules announce it.
+    Dropped,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayedAlert {
+    pub transition: Transition,
+    pub change: Change,
+}
+
+/// What a change of the alert rules would have done to the alerts of stored results. An
+/// alert that only moves in time, say with a higher `down_after`, shows up as dropped at
+/// the old time and added at the new one.
+#[derive(Debug, Clone)]
+pub struct DryRun {
+    pub results: usize,
+    pub targets: usize,
+    pub span: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    /// The alerts of both rule sets, in time order.
+    pub alerts: Vec<ReplayedAlert>,
+    /// Zone the report shows times in.
+    pub tz: Tz,
+}
+
+impl DryRun {
+    pub fn new(results: &[CheckResult], current: &RecoveryRules, proposed: &RecoveryRules) -> Self {
+        let key = |t: &Transition| (t.target.clone(), t.to, t.at);
+        let before = replay(results, current);
+        let after = replay(results, proposed);
+        let kept: HashSet<_> = after.iter().map(key).collect();
+        let announced: HashSet<_> = before.iter().map(key).collect();
+
+        let mut alerts: Vec<ReplayedAlert> = before
+            .into_iter()
+            .map(|transition| {
+                let change = if kept.contains(&key(&transition)) { Change::Kept } else { Change::Dropped };
+                ReplayedAlert { transition, change }
+            })
+            .collect();
+        alerts.extend(after.into_iter().filter(|t| !announced.contains(&key(t))).map(|transition| ReplayedAlert {
+            transition,
+            change: Change::Added,
+        }));
+        alerts.sort_by(|a, b| (a.transition.at, &a.transition.target).cmp(&(b.transition.at, &b.transition.target)));
+
+        let from = results.iter().map(|r| r.timestamp).min();
+        let to = results.iter().map(|r| r.timestamp).max();
+        Self {
+            results: results.len(),
+            targets: results.iter().map(|r| r.target.as_str()).collect::<HashSet<_>>().len(),
+            span: from.zip(to),
+            alerts,
+            tz: Tz::UTC,
+        }
+    }
+
+    /// Shows times in `tz` instead of UTC.
+    pub fn in_timezone(mut self, tz: Tz) -> Self {
+        self.tz = tz;
+        self
+    }
+
+    /// Alerts announced under the current (`proposed == false`) or proposed rules.
+    pub fn announced(&self, proposed: bool, to: TargetState) -> usize {
+        let counts = |change: Change| match change {
+            Change::Kept => true,
+            Change::Added => proposed,
+            Change::Dropped => !proposed,
+        };
+        self.alerts.iter().filter(|a| a.transition.to == to && counts(a.change)).count()
+    }
+
+    /// Whether the proposed rules change any alert at all.
+    pub fn changes_anything(&self) -> bool {
+        self.alerts.iter().any(|a| a.change != Change::Kept)
+    }
+}
+
+impl fmt::Display for DryRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Replayed {} results of {} targets", self.results, self.targets)?;
+        if let Some((from, to)) = self.span {
+            let (from, to) = (from.with_timezone(&self.tz), to.with_timezone(&self.tz));
+            write!(f, " from {} to {}", from.format("%Y-%m-%d %H:%M"), to.format("%Y-%m-%d %H:%M %Z"))?;
+        }
+        writeln!(f)?;
+        for (label, proposed) in [("Current rules", false), ("Proposed rules", true)] {
+            let (down, up) = (self.announced(proposed, TargetState::Down), self.announced(proposed, TargetState::Up));
+            writeln!(f, "{:<15} {} outages, {} recoveries announced", format!("{}:", label), down, up)?;
+        }
+        if !self.changes_anything() {
+            return writeln!(f, "No alert would change.");
+        }
+
+        // Alerts per target, current -> proposed, for the targets whose alerts change.
+        let mut per_target: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for alert in &self.alerts {
+            let counts = per_target.entry(alert.transition.target.as_str()).or_default();
+            counts.0 += usize::from(alert.change != Change::Added);
+            counts.1 += usize::from(alert.change != Change::Dropped);
+        }
+        writeln!(f, "\nAlerts per target (current -> proposed):")?;
+        for (target, (before, after)) in per_target.iter().filter(|(_, (before, after))| before != after) {
+            writeln!(f, "  {:<32} {} -> {}", target, before, after)?;
+        }
+
+        writeln!(f, "\nChanged alerts (+ only proposed, - only current):")?;
+        for alert in self.alerts.iter().filter(|a| a.change != Change::Kept) {
+            let t = &alert.transition;
+            let sign = if alert.change == Change::Added { '+' } else { '-' };
+            let what = match (t.to, t.downtime) {
+                (TargetState::Down, _) => "down".to_string(),
+                (TargetState::Up, Some(downtime)) if downtime.as_secs() > 0 => format!("up after {}", format_interval(downtime)),
+                (TargetState::Up, _) => "up".to_string(),
+            };
+            writeln!(f, "  {} {}  {:<32} {}", sign, t.at.with_timezone(&self.tz).format("%Y-%m-%d %H:%M:%S"), t.target, what)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+    use std::time::Duration;
+
+    fn results(target: &str, pattern: &str) -> Vec<CheckResult> {
+        pattern
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                let mut r = if c == '+' {
+                    CheckResult::success(target, Duration::from_millis(5))
+                } else {
+                    CheckResult::failure(target, "refused")
+                };
+                r.timestamp = DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::minutes(i as i64);
+                r
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_dry_run_reports_what_a_rule_change_would_announce() {
+        // db has one-check blips and one real outage; web is steady.
+        let mut stored = results("db:5432", "++-++-+++----++");
+        stored.extend(results("web:443", "+++++++++++++++"));
+        let mut offline = CheckResult::failure("db:5432", format!("timed out {}", OFFLINE_NOTE));
+        offline.timestamp = DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::seconds(30);
+        stored.push(offline);
+
+        let proposed = RecoveryRules {
+            down_after: 3,
+            ..RecoveryRules::default()
+        };
+        let dry_run = DryRun::new(&stored, &RecoveryRules::default(), &proposed);
+        assert_eq!(dry_run.targets, 2);
+        assert_eq!(dry_run.announced(false, TargetState::Down), 3);
+        assert_eq!(dry_run.announced(true, TargetState::Down), 1);
+        assert_eq!(dry_run.announced(true, TargetState::Up), 1);
+
+        let changed: Vec<(Change, TargetState, i64)> = dry_run
+            .alerts
+            .iter()
+            .filter(|a| a.change != Change::Kept)
+            .map(|a| (a.change, a.transition.to, a.transition.at.timestamp() / 60))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                (Change::Dropped, TargetState::Down, 2),
+                (Change::Dropped, TargetState::Up, 3),
+                (Change::Dropped, TargetState::Down, 5),
+                (Change::Dropped, TargetState::Up, 6),
+                (Change::Dropped, TargetState::Down, 9),
+                (Change::Added, TargetState::Down, 11),
+            ]
+        );
+        let report = dry_run.to_string();
+        assert!(report.contains("db:5432                          6 -> 2"), "{}", report);
+        assert!(!report.contains("web:443"));
+        assert!(report.contains("+ 1970-01-01 00:11:00  db:5432"));
+        let report = dry_run.in_timezone(chrono_tz::Asia::Tokyo).to_string();
+        assert!(report.contains("+ 1970-01-01 09:11:00  db:5432"), "{}", report);
+    }
+}
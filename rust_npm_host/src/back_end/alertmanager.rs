@@ -0,0 +1,217 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::event_bus::MonitorEvent;
+use super::metadata::{LabelGuard, LABEL_PREFIX};
+use super::monitor::Monitor;
+use super::severity::Severity;
+use super::state_tracker::{TargetState, Transition};
+
+const ALERT_NAME: &str = "TargetDown";
+// Well within Alertmanager's default `resolve_timeout` of five minutes.
+const RESEND_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Where and how to forward alerts to Prometheus Alertmanager.
+#[derive(Debug, Clone)]
+pub struct AlertmanagerConfig {
+    /// Base URL, e.g. `http://alertmanager:9093`.
+    pub url: String,
+    /// Added to every alert, e.g. `team=noc`, so existing routes and silences match.
+    pub extra_labels: BTreeMap<String, String>,
+    /// Link back to us shown in Alertmanager's UI.
+    pub generator_url: Option<String>,
+    pub timeout: Duration,
+    /// Distinct values a metadata key may take before it is no longer sent as a label, see
+    /// `LabelGuard`. All metadata is sent as annotations regardless.
+    pub max_metadata_values: usize,
+}
+
+/// One alert in the shape of Alertmanager's `POST /api/v2/alerts` body.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AmAlert {
+    pub labels: BTreeMap<String, String>,
+    pub annotations: BTreeMap<String, String>,
+    pub starts_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<DateTime<Utc>>,
+    #[serde(rename = "generatorURL", skip_serializing_if = "Option::is_none")]
+    pub generator_url: Option<String>,
+}
+
+/// Forwards up/down transitions to Alertmanager. Firing alerts have to be re-sent before
+/// Alertmanager's `resolve_timeout` runs out, so `resend_firing` should be called on a timer;
+/// recoveries are sent with `endsAt` so the alert resolves right away.
+pub struct AlertmanagerNotifier {
+    config: AlertmanagerConfig,
+    client: Client,
+    firing: HashMap<String, AmAlert>,
+    labels: LabelGuard,
+}
+
+impl AlertmanagerNotifier {
+    pub fn new(config: AlertmanagerConfig) -> Result<Self, Box<dyn Error>> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self {
+            labels: LabelGuard::new(config.max_metadata_values),
+            config,
+            client,
+            firing: HashMap::new(),
+        })
+    }
+
+    /// Turns a transition into the alert to send and remembers which alerts are firing.
+    pub fn alert_for(&mut self, transition: &Transition) -> AmAlert {
+        match transition.to {
+            TargetState::Down => {
+                let alert = self.build(transition, transition.at, None);
+                self.firing.insert(transition.target.clone(), alert.clone());
+                alert
+            }
+            TargetState::Up => {
+                // Same labels and start as the firing alert, so Alertmanager resolves that one.
+                let firing = self.firing.remove(&transition.target);
+                let starts_at = firing
+                    .as_ref()
+                    .map(|a| a.starts_at)
+                    .or_else(|| transition.downtime.and_then(|d| ChronoDuration::from_std(d).ok()).map(|d| transition.at - d))
+                    .unwrap_or(transition.at);
+                let mut alert = self.build(transition, starts_at, Some(transition.at));
+                if let Some(firing) = firing {
+                    alert.labels = firing.labels;
+                }
+                alert
+            }
+        }
+    }
+
+    pub async fn notify(&mut self, transition: &Transition) -> Result<(), Box<dyn Error>> {
+        let alert = self.alert_for(transition);
+        self.post(&[alert]).await
+    }
+
+    /// Re-sends every alert that is still firing.
+    pub async fn resend_firing(&self) -> Result<(), Box<dyn Error>> {
+        if self.firing.is_empty() {
+            return Ok(());
+        }
+        let alerts: Vec<AmAlert> = self.firing.values().cloned().collect();
+        self.post(&alerts).await
+    }
+
+    async fn post(&self, alerts: &[AmAlert]) -> Result<(), Box<dyn Error>> {
+        let url = format!("{}/api/v2/alerts", self.config.url.trim_end_matches('/'));
+        self.client.post(&url).json(alerts).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    fn build(&mut self, transition: &Transition, starts_at: DateTime<Utc>, ends_at: Option<DateTime<Utc>>) -> AmAlert {
+        let mut labels = self.labels.labels(&transition.metadata);
+        labels.extend(self.config.extra_labels.clone());
+        labels.insert("alertname".to_string(), ALERT_NAME.to_string());
+        labels.insert("instance".to_string(), transition.target.clone());
+        labels.insert("severity".to_string(), transition.severity.unwrap_or(Severity::Critical).to_string());
+
+        let mut annotations = BTreeMap::new();
+        annotations.insert("summary".to_string(), format!("{} is down", transition.target));
+        if !transition.correlation_id.is_empty() {
+            annotations.insert("correlation_id".to_string(), transition.correlation_id.clone());
+        }
+        if let Some(downtime) = transition.downtime {
+            annotations.insert("downtime".to_string(), format!("{:?}", downtime));
+        }
+        // For alert templates, e.g. `{{ .Annotations.meta_owner }}`.
+        for (key, value) in &transition.metadata {
+            annotations.insert(format!("{}{}", LABEL_PREFIX, key), value.clone());
+        }
+        AmAlert {
+            labels,
+            annotations,
+            starts_at,
+            ends_at,
+            generator_url: self.config.generator_url.clone(),
+        }
+    }
+}
+
+/// Forwards the monitor's up/down changes to Alertmanager and keeps the firing alerts
+/// alive, until the event bus closes. Alertmanager groups and throttles on its own, so the
+/// storm guard of the other channels doesn't apply.
+pub async fn deliver(monitor: Arc<Monitor>, mut notifier: AlertmanagerNotifier) {
+    let mut events = monitor.bus().subscribe();
+    let mut resend = tokio::time::interval(RESEND_INTERVAL);
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = resend.tick() => {
+                if let Err(e) = notifier.resend_firing().await {
+                    eprintln!("Alertmanager {} failed for the firing alerts: {}", notifier.config.url, e);
+                }
+                continue;
+            }
+        };
+        match event {
+            Ok(MonitorEvent::Transition(transition)) => {
+                if let Err(e) = notifier.notify(&transition).await {
+                    eprintln!("Alertmanager {} failed for {}: {}", notifier.config.url, transition.target, e);
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => eprintln!("Alertmanager {} missed {} events", notifier.config.url, missed),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn transition(to: TargetState, minute: u32, downtime: Option<Duration>) -> Transition {
+        Transition {
+            target: "db:5432".to_string(),
+            from: None,
+            to,
+            at: Utc.with_ymd_and_hms(2026, 10, 17, 12, minute, 0).unwrap(),
+            downtime,
+            correlation_id: "abc".to_string(),
+            metadata: BTreeMap::from([("owner".to_string(), "dba@example.com".to_string())]),
+            severity: None,
+        }
+    }
+
+    #[test]
+    fn test_recovery_resolves_the_firing_alert() {
+        let mut notifier = AlertmanagerNotifier::new(AlertmanagerConfig {
+            url: "http://alertmanager:9093".to_string(),
+            extra_labels: BTreeMap::from([("team".to_string(), "noc".to_string())]),
+            generator_url: None,
+            timeout: Duration::from_secs(5),
+            max_metadata_values: 50,
+        })
+        .unwrap();
+
+        let firing = notifier.alert_for(&transition(TargetState::Down, 0, None));
+        assert_eq!(firing.labels["alertname"], "TargetDown");
+        assert_eq!(firing.labels["team"], "noc");
+        assert_eq!(firing.labels["meta_owner"], "dba@example.com");
+        assert_eq!(firing.annotations["meta_owner"], "dba@example.com");
+        assert!(firing.ends_at.is_none());
+
+        let resolved = notifier.alert_for(&transition(TargetState::Up, 7, Some(Duration::from_secs(420))));
+        assert_eq!(resolved.labels, firing.labels);
+        assert_eq!(resolved.starts_at, firing.starts_at);
+        assert_eq!(resolved.ends_at, Some(Utc.with_ymd_and_hms(2026, 10, 17, 12, 7, 0).unwrap()));
+
+        let json = serde_json::to_value(&resolved).unwrap();
+        assert!(json.get("startsAt").is_some() && json.get("endsAt").is_some());
+        assert!(json.get("generatorURL").is_none());
+    }
+}
@@ -0,0 +1,187 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::check_result::CheckResult;
+
+/// An operator's note on a span of a target's history, e.g. "ISP maintenance" or "false
+/// positive due to firewall change".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: u64,
+    pub target: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub label: String,
+    /// Results in the span are left out of availability figures and trend analysis.
+    #[serde(default = "default_exclude")]
+    pub exclude: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+fn default_exclude() -> bool {
+    true
+}
+
+impl Annotation {
+    pub fn covers(&self, result: &CheckResult) -> bool {
+        result.target == self.target && self.from <= result.timestamp && result.timestamp < self.to
+    }
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} {} {} .. {}: {}{}",
+            self.id,
+            self.target,
+            self.from.to_rfc3339(),
+            self.to.to_rfc3339(),
+            self.label,
+            if self.exclude { " (excluded)" } else { "" }
+        )
+    }
+}
+
+/// Annotations kept in a JSON file next to the address book.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnnotationStore {
+    annotations: Vec<Annotation>,
+}
+
+impl AnnotationStore {
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust_npm").join("annotations.json"))
+    }
+
+    /// A missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn annotations(&self) -> &[Annotation] {
+        &self.annotations
+    }
+
+    /// Adds an annotation and returns its id.
+    pub fn add(
+        &mut self,
+        target: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        label: &str,
+        exclude: bool,
+    ) -> Result<u64, String> {
+        if to <= from {
+            return Err(format!("span ends ({}) before it starts ({})", to, from));
+        }
+        if label.trim().is_empty() {
+            return Err("label is empty".to_string());
+        }
+        let id = self.annotations.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+        self.annotations.push(Annotation {
+            id,
+            target: target.to_string(),
+            from,
+            to,
+            label: label.trim().to_string(),
+            exclude,
+            created_at: Utc::now(),
+        });
+        Ok(id)
+    }
+
+    /// Returns whether an annotation with `id` existed.
+    pub fn remove(&mut self, id: u64) -> bool {
+        let before = self.annotations.len();
+        self.annotations.retain(|a| a.id != id);
+        self.annotations.len() != before
+    }
+
+    /// Annotations of `target` overlapping `from..to`.
+    pub fn for_target(&self, target: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<&Annotation> {
+        self.annotations
+            .iter()
+            .filter(|a| a.target == target && a.from < to && from < a.to)
+            .collect()
+    }
+
+    pub fn is_excluded(&self, result: &CheckResult) -> bool {
+        self.annotations.iter().any(|a| a.exclude && a.covers(result))
+    }
+
+    /// `results` without those in excluded spans, ready for `trend::analyze` or
+    /// `availability`.
+    pub fn without_excluded(&self, results: &[CheckResult]) -> Vec<CheckResult> {
+        results.iter().filter(|r| !self.is_excluded(r)).cloned().collect()
+    }
+}
+
+/// Share of successful results, or `None` when there are none.
+pub fn availability(results: &[CheckResult]) -> Option<f64> {
+    if results.is_empty() {
+        return None;
+    }
+    Some(results.iter().filter(|r| r.success).count() as f64 / results.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, TimeZone};
+    use std::time::Duration;
+
+    #[test]
+    fn test_excluded_spans_leave_availability() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 2, 0, 0).unwrap();
+        let at = |minutes: i64, mut result: CheckResult| {
+            result.timestamp = start + ChronoDuration::minutes(minutes);
+            result
+        };
+        let results = vec![
+            at(0, CheckResult::success("gw:443", Duration::from_millis(5))),
+            at(10, CheckResult::failure("gw:443", "connection refused")),
+            at(20, CheckResult::failure("gw:443", "connection refused")),
+            at(40, CheckResult::success("gw:443", Duration::from_millis(5))),
+        ];
+        assert_eq!(availability(&results), Some(0.5));
+
+        let mut store = AnnotationStore::default();
+        let id = store
+            .add("gw:443", start + ChronoDuration::minutes(5), start + ChronoDuration::minutes(30), "ISP maintenance", true)
+            .unwrap();
+        store
+            .add("gw:443", start, start + ChronoDuration::hours(1), "firewall change", false)
+            .unwrap();
+        assert!(store.add("gw:443", start, start, "empty", true).is_err());
+
+        assert_eq!(availability(&store.without_excluded(&results)), Some(1.0));
+        assert_eq!(store.for_target("gw:443", start, start + ChronoDuration::minutes(1)).len(), 1);
+
+        let path = std::env::temp_dir().join(format!("rust_npm_annotations_{}.json", uuid::Uuid::new_v4().simple()));
+        store.save(&path).unwrap();
+        let mut loaded = AnnotationStore::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.remove(id));
+        assert_eq!(loaded.annotations().len(), 1);
+        assert_eq!(availability(&loaded.without_excluded(&results)), Some(0.5));
+    }
+}
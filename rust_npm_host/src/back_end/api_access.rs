@@ -0,0 +1,213 @@
+use chrono::Duration as ChronoDuration;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+#[cfg(feature = "ldap-auth")]
+use super::auth::LdapProvider;
+use super::auth::{Authenticator, Identity, LocalTokens, Scope};
+
+/// Path prefixes of the endpoints of `api_server` that only read: the targets with their
+/// history and browser waterfalls, the health check, metrics, caller's scope and budget
+/// usage. Everything else counts as mutating, so a new endpoint is protected until it is
+/// listed here.
+pub const READ_ENDPOINTS: &[&str] = &["/targets", "/health", "/metrics", "/whoami", "/budget"];
+
+/// Read endpoints that need a token even in public read-only mode; `*` stands for one
+/// path segment. The evidence bundle holds full results, annotations and captures; the
+/// budget names the teams.
+pub const PRIVATE_READ_ENDPOINTS: &[&str] = &["/targets/*/evidence", "/budget"];
+
+fn is_private_read(path: &str) -> bool {
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    PRIVATE_READ_ENDPOINTS.iter().any(|pattern| {
+        let pattern: Vec<&str> = pattern.split('/').collect();
+        pattern.len() == segments.len() && pattern.iter().zip(&segments).all(|(p, s)| *p == "*" || p == s)
+    })
+}
+
+/// A locally configured API token. Only its SHA-256 hash is stored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TokenConfig {
+    pub user: String,
+    pub sha256: String,
+    #[serde(default = "admin_scope")]
+    pub scope: Scope,
+}
+
+fn admin_scope() -> Scope {
+    Scope::Admin
+}
+
+/// Login through the company identity provider, see `auth::OidcProvider`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// Where the provider sends the browser back to: this API's `/login/oidc/callback`.
+    pub redirect_uri: String,
+}
+
+/// The `[api]` settings: who may reach which endpoints.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ApiConfig {
+    /// Serve the read endpoints to anyone, e.g. to give customers visibility, without
+    /// target owners and metadata. Mutating endpoints stay limited to localhost and admin
+    /// tokens either way, and `PRIVATE_READ_ENDPOINTS` to localhost and tokens.
+    #[serde(default)]
+    pub public_read_only: bool,
+    #[serde(default)]
+    pub tokens: Vec<TokenConfig>,
+    /// How long a login lasts; 8 hours if not given.
+    #[serde(default)]
+    pub session_hours: Option<i64>,
+    /// Enables `/login/oidc`.
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+    /// Enables `POST /login` with a directory user name and password.
+    #[cfg(feature = "ldap-auth")]
+    #[serde(default)]
+    pub ldap: Option<LdapProvider>,
+}
+
+impl ApiConfig {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn authenticator(&self) -> Authenticator {
+        let mut local = LocalTokens::default();
+        for token in &self.tokens {
+            local.add_hashed(&token.user, &token.sha256, token.scope);
+        }
+        let auth = Authenticator::new(local);
+        match self.session_hours {
+            Some(hours) => auth.with_session_ttl(ChronoDuration::hours(hours)),
+            None => auth,
+        }
+    }
+}
+
+/// Endpoints that only read although they are POSTed to, as in UptimeRobot's API.
+pub const READ_POST_ENDPOINTS: &[&str] = &["/v2/getMonitors"];
+
+pub fn is_read_endpoint(method: &str, path: &str) -> bool {
+    if method.eq_ignore_ascii_case("POST") && READ_POST_ENDPOINTS.contains(&path) {
+        return true;
+    }
+    let reads = method.eq_ignore_ascii_case("GET") || method.eq_ignore_ascii_case("HEAD");
+    reads
+        && READ_ENDPOINTS.iter().any(|prefix| {
+            path.strip_prefix(prefix)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?']))
+        })
+}
+
+/// Who a request was let in as.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    /// A read endpoint in public read-only mode, without credentials.
+    Anonymous,
+    /// A request from this machine; the local GUI and scripts need no token.
+    Localhost,
+    Token(Identity),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Denied {
+    /// No or an unknown token.
+    Unauthorized,
+    /// A valid token whose scope doesn't cover the endpoint.
+    Forbidden,
+}
+
+impl Denied {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Denied::Unauthorized => 401,
+            Denied::Forbidden => 403,
+        }
+    }
+}
+
+impl fmt::Display for Denied {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Denied::Unauthorized => write!(f, "a valid API token is required"),
+            Denied::Forbidden => write!(f, "this token may only use read endpoints"),
+        }
+    }
+}
+
+impl Error for Denied {}
+
+/// Decides whether a request may proceed. `bearer` is the token from the Authorization
+/// header, if any.
+pub fn authorize(
+    config: &ApiConfig,
+    auth: &Authenticator,
+    method: &str,
+    path: &str,
+    peer: IpAddr,
+    bearer: Option<&str>,
+) -> Result<Access, Denied> {
+    let read = is_read_endpoint(method, path);
+    if let Some(identity) = bearer.and_then(|token| auth.verify(token)) {
+        return if read || identity.scope == Scope::Admin {
+            Ok(Access::Token(identity))
+        } else {
+            Err(Denied::Forbidden)
+        };
+    }
+    if peer.is_loopback() {
+        return Ok(Access::Localhost);
+    }
+    if read && config.public_read_only && !is_private_read(path) {
+        return Ok(Access::Anonymous);
+    }
+    Err(Denied::Unauthorized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_public_read_only_keeps_mutations_private() {
+        let config: ApiConfig = toml::from_str(&format!(
+            "public_read_only = true\n\
+             [[tokens]]\nuser = \"acme\"\nsha256 = \"{}\"\nscope = \"read\"\n\
+             [[tokens]]\nuser = \"ops\"\nsha256 = \"{}\"\n",
+            hex::encode(Sha256::digest(b"customer")),
+            hex::encode(Sha256::digest(b"admin")),
+        ))
+        .unwrap();
+        let auth = config.authenticator();
+        let remote: IpAddr = "203.0.113.7".parse().unwrap();
+        let check = |method, path, peer, token| authorize(&config, &auth, method, path, peer, token);
+
+        assert_eq!(check("GET", "/metrics", remote, None), Ok(Access::Anonymous));
+        assert_eq!(check("GET", "/metricsx", remote, None), Err(Denied::Unauthorized));
+        assert_eq!(check("POST", "/targets", remote, None), Err(Denied::Unauthorized));
+        assert_eq!(check("POST", "/v2/getMonitors", remote, Some("customer")).map(|_| ()), Ok(()));
+        assert_eq!(check("DELETE", "/targets/1", remote, Some("customer")), Err(Denied::Forbidden));
+        assert!(matches!(check("DELETE", "/targets/1", remote, Some("admin")), Ok(Access::Token(_))));
+        assert_eq!(check("POST", "/targets", "::1".parse().unwrap(), None), Ok(Access::Localhost));
+        // Evidence bundles hold everything about a target, so they need at least a read token.
+        assert_eq!(check("GET", "/targets/10.0.0.5:443/evidence", remote, None), Err(Denied::Unauthorized));
+        assert!(matches!(check("GET", "/targets/10.0.0.5:443/evidence", remote, Some("customer")), Ok(Access::Token(_))));
+        assert_eq!(check("GET", "/targets/10.0.0.5:443/history", remote, None), Ok(Access::Anonymous));
+
+        let private = ApiConfig::default();
+        assert_eq!(
+            authorize(&private, &auth, "GET", "/metrics", remote, None),
+            Err(Denied::Unauthorized)
+        );
+        assert!(authorize(&private, &auth, "GET", "/metrics", remote, Some("customer")).is_ok());
+    }
+}
@@ -0,0 +1,597 @@
+use axum::{Extension, Json, Router};
+use axum::extract::{ConnectInfo, Form, Path, Query, Request, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{Html, IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use super::annotations::AnnotationStore;
+use super::api_access::{self, Access, ApiConfig};
+use super::auth::{Authenticator, Identity, OidcProvider, PendingLogin, Scope};
+use super::budget::Usage;
+use super::check_result::CheckResult;
+use super::csv_import::parse_interval;
+use super::evidence;
+use super::history::History;
+use super::metadata::LabelGuard;
+use super::monitor::Monitor;
+use super::pause::Pause;
+use super::state_tracker::TargetState;
+use super::target::MonitorTarget;
+use super::uptimerobot::{self, GetMonitors};
+use super::waterfall::Waterfall;
+
+// How long a login sent off to the identity provider may take to come back.
+const PENDING_LOGIN_MINUTES: i64 = 10;
+
+/// What the API serves and who may use it.
+pub struct ApiState {
+    pub monitor: Arc<Monitor>,
+    pub history: Arc<History>,
+    pub config: ApiConfig,
+    pub auth: Authenticator,
+    pub oidc: Option<OidcProvider>,
+    // OIDC logins waiting for the provider's redirect, by their `state`.
+    pending: Mutex<HashMap<String, (PendingLogin, DateTime<Utc>)>>,
+    // Which metadata keys `/metrics` still exports as labels.
+    labels: Mutex<LabelGuard>,
+}
+
+impl ApiState {
+    pub fn new(monitor: Arc<Monitor>, history: Arc<History>, config: ApiConfig) -> Self {
+        let auth = config.authenticator();
+        Self {
+            monitor,
+            history,
+            config,
+            auth,
+            oidc: None,
+            pending: Mutex::new(HashMap::new()),
+            labels: Mutex::new(LabelGuard::default()),
+        }
+    }
+
+    /// Looks up the endpoints of the identity provider in `[oidc]`, if there is one.
+    pub async fn discover_oidc(mut self) -> Result<Self, Box<dyn Error>> {
+        if let Some(oidc) = &self.config.oidc {
+            self.oidc = Some(OidcProvider::discover(&oidc.issuer, &oidc.client_id, &oidc.client_secret, &oidc.redirect_uri).await?);
+        }
+        Ok(self)
+    }
+}
+
+/// A target as listed by `GET /targets`.
+#[derive(Debug, Serialize)]
+struct TargetView {
+    #[serde(flatten)]
+    target: MonitorTarget,
+    group: Option<String>,
+    state: Option<TargetState>,
+    acknowledged: bool,
+    pause: Option<Pause>,
+    /// No result for longer than the target's interval allows; `state` is then out of date.
+    stale: bool,
+    /// Share of successful checks over the last day, 0.0 to 1.0.
+    uptime_24h: Option<f64>,
+}
+
+/// Body of `POST /targets`: the target's settings as in the address book, plus its group.
+#[derive(Debug, Deserialize)]
+struct NewTarget {
+    #[serde(flatten)]
+    target: MonitorTarget,
+    #[serde(default)]
+    group: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EvidenceQuery {
+    /// As `since` of the history; a day back if not given.
+    from: Option<String>,
+    /// RFC 3339 time; now if not given.
+    to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "ldap-auth"), allow(dead_code))]
+struct LoginForm {
+    user: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OidcCallback {
+    code: String,
+    state: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryQuery {
+    /// RFC 3339 time or an interval back from now like `24h`; a day if not given.
+    since: Option<String>,
+}
+
+fn error(status: StatusCode, message: impl ToString) -> Response {
+    (status, Json(json!({ "error": message.to_string() }))).into_response()
+}
+
+/// The API's routes, plus UptimeRobot's `/v2/getMonitors` for tools that speak that
+/// format. Connections must carry `ConnectInfo<SocketAddr>`, as `serve` sets up, for the
+/// localhost rules of `api_access`.
+pub fn router(state: Arc<ApiState>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/metrics", get(metrics))
+        .route("/whoami", get(whoami))
+        .route("/targets", get(list_targets).post(add_target))
+        .route("/targets/{id}", get(get_target).delete(remove_target))
+        .route("/targets/{id}/history", get(target_history))
+        .route("/targets/{id}/evidence", get(target_evidence))
+        .route("/targets/{id}/waterfall", get(target_waterfall))
+        .route("/budget", get(budget))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        // UptimeRobot clients send their key in the form, which `uptimerobot_monitors` checks.
+        .route("/v2/getMonitors", post(uptimerobot_monitors))
+        // Logging in is how a token is obtained; logging out only ends the caller's session.
+        .route("/login", post(login))
+        .route("/login/oidc", get(oidc_login))
+        .route("/login/oidc/callback", get(oidc_callback))
+        .route("/logout", post(logout))
+        .with_state(state)
+}
+
+/// Serves the API on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, state: Arc<ApiState>) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, router(state).into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
+
+fn bearer(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+}
+
+// Handlers find the `Access` the request was let in with among its extensions. Session
+// tokens from `/login` are checked here like API tokens.
+async fn authorize(State(state): State<Arc<ApiState>>, ConnectInfo(peer): ConnectInfo<SocketAddr>, mut request: Request, next: Next) -> Response {
+    let bearer = bearer(request.headers());
+    match api_access::authorize(&state.config, &state.auth, request.method().as_str(), request.uri().path(), peer.ip(), bearer) {
+        Ok(access) => {
+            request.extensions_mut().insert(access);
+            next.run(request).await
+        }
+        Err(denied) => error(StatusCode::from_u16(denied.status_code()).unwrap_or(StatusCode::FORBIDDEN), denied),
+    }
+}
+
+fn session(state: &ApiState, identity: Identity) -> Response {
+    let user = identity.user.clone();
+    let provider = identity.provider.clone();
+    let token = state.auth.start_session(identity);
+    Json(json!({ "token": token, "user": user, "provider": provider })).into_response()
+}
+
+/// LDAP login with the user's directory password; answers a session token.
+#[cfg_attr(not(feature = "ldap-auth"), allow(unused_variables))]
+async fn login(State(state): State<Arc<ApiState>>, Form(form): Form<LoginForm>) -> Response {
+    #[cfg(feature = "ldap-auth")]
+    if let Some(ldap) = &state.config.ldap {
+        return match ldap.authenticate(&form.user, &form.password).await {
+            Ok(identity) => session(&state, identity),
+            Err(e) => error(StatusCode::UNAUTHORIZED, e),
+        };
+    }
+    error(StatusCode::NOT_FOUND, "LDAP login is not configured")
+}
+
+/// Sends the browser to the identity provider, which sends it back to `oidc_callback`.
+async fn oidc_login(State(state): State<Arc<ApiState>>) -> Response {
+    let Some(oidc) = &state.oidc else {
+        return error(StatusCode::NOT_FOUND, "OIDC login is not configured");
+    };
+    let pending = match oidc.begin() {
+        Ok(pending) => pending,
+        Err(e) => return error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+    let url = pending.authorize_url.clone();
+    let now = Utc::now();
+    let mut logins = state.pending.lock().unwrap();
+    logins.retain(|_, (_, started)| now - *started < ChronoDuration::minutes(PENDING_LOGIN_MINUTES));
+    logins.insert(pending.state.clone(), (pending, now));
+    Redirect::to(&url).into_response()
+}
+
+async fn oidc_callback(State(state): State<Arc<ApiState>>, Query(callback): Query<OidcCallback>) -> Response {
+    let Some(oidc) = &state.oidc else {
+        return error(StatusCode::NOT_FOUND, "OIDC login is not configured");
+    };
+    let Some((pending, _)) = state.pending.lock().unwrap().remove(&callback.state) else {
+        return error(StatusCode::BAD_REQUEST, "unknown or expired login");
+    };
+    match oidc.complete(&pending, &callback.code, &callback.state).await {
+        Ok(identity) => session(&state, identity),
+        Err(e) => error(StatusCode::UNAUTHORIZED, e),
+    }
+}
+
+async fn logout(State(state): State<Arc<ApiState>>, headers: HeaderMap) -> Response {
+    match bearer(&headers) {
+        Some(token) => {
+            state.auth.end_session(token);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        None => error(StatusCode::BAD_REQUEST, "no session token given"),
+    }
+}
+
+async fn health(State(state): State<Arc<ApiState>>) -> Json<serde_json::Value> {
+    Json(json!({
+        "status": "ok",
+        "targets": state.monitor.targets().len(),
+        "standby": state.monitor.is_standby(),
+    }))
+}
+
+/// Who the caller is and what they may do, e.g. for a GUI connected to this monitor.
+async fn whoami(Extension(access): Extension<Access>) -> Json<serde_json::Value> {
+    let (user, scope) = match access {
+        Access::Anonymous => (None, Scope::Read),
+        Access::Localhost => (None, Scope::Admin),
+        Access::Token(identity) => (Some(identity.user), identity.scope),
+    };
+    Json(json!({ "user": user, "scope": scope }))
+}
+
+/// Prometheus text format: each target's state and its mean latency over the last five
+/// minutes. Target metadata is added as `meta_` labels as far as `LabelGuard` allows.
+async fn metrics(State(state): State<Arc<ApiState>>, Extension(access): Extension<Access>) -> Response {
+    let since = Utc::now() - ChronoDuration::minutes(5);
+    let mut up = String::from("# HELP rust_npm_target_up Whether the target is up.\n# TYPE rust_npm_target_up gauge\n");
+    let mut latency = String::from(
+        "# HELP rust_npm_target_latency_seconds Mean latency of successful checks over 5 minutes.\n\
+         # TYPE rust_npm_target_latency_seconds gauge\n",
+    );
+    let mut guard = state.labels.lock().unwrap();
+    for target in state.monitor.monitor_targets() {
+        // Like `view`, metadata is kept from anonymous readers.
+        let mut labels = if access == Access::Anonymous { BTreeMap::new() } else { guard.labels(&target.metadata) };
+        labels.insert("target".to_string(), target.address.to_string());
+        let labels = label_set(&labels);
+        if let Some(target_state) = state.monitor.state(target.address) {
+            let _ = writeln!(up, "rust_npm_target_up{} {}", labels, u8::from(target_state == TargetState::Up));
+        }
+        if let Some(mean) = state.history.average_latency(&target.address.to_string(), since) {
+            let _ = writeln!(latency, "rust_npm_target_latency_seconds{} {}", labels, mean.as_secs_f64());
+        }
+    }
+    up.push_str(&latency);
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], up).into_response()
+}
+
+/// Checks and browser minutes each target owner used in the current `[budget]` period.
+async fn budget(State(state): State<Arc<ApiState>>) -> Json<BTreeMap<String, Usage>> {
+    Json(state.monitor.budget_report().into_iter().collect())
+}
+
+fn label_set(labels: &BTreeMap<String, String>) -> String {
+    let pairs: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", name, value)
+        })
+        .collect();
+    format!("{{{}}}", pairs.join(","))
+}
+
+// Anonymous readers of a public API don't get to see who owns a target or its metadata.
+fn view(state: &ApiState, mut target: MonitorTarget, access: &Access, annotations: Option<&AnnotationStore>) -> TargetView {
+    if *access == Access::Anonymous {
+        target.owner = None;
+        target.metadata.clear();
+    }
+    let addr = target.address;
+    TargetView {
+        group: state.monitor.group(addr),
+        state: state.monitor.state(addr),
+        acknowledged: state.monitor.is_acknowledged(addr),
+        pause: state.monitor.pause_of(addr),
+        stale: state.monitor.is_stale(addr),
+        uptime_24h: state.history.uptime(&addr.to_string(), Utc::now() - ChronoDuration::days(1), annotations),
+        target,
+    }
+}
+
+async fn list_targets(State(state): State<Arc<ApiState>>, Extension(access): Extension<Access>) -> Json<Vec<TargetView>> {
+    let annotations = annotations();
+    Json(state.monitor.monitor_targets().into_iter().map(|t| view(&state, t, &access, annotations.as_ref())).collect())
+}
+
+/// The operator's annotations, read per request so `--annotate` edits apply without a restart.
+fn annotations() -> Option<AnnotationStore> {
+    AnnotationStore::default_path().and_then(|path| AnnotationStore::load(&path).ok())
+}
+
+fn parse_id(state: &ApiState, id: &str) -> Result<SocketAddr, (StatusCode, String)> {
+    let addr: SocketAddr = id.parse().map_err(|e| (StatusCode::BAD_REQUEST, format!("{}: {}", id, e)))?;
+    if !state.monitor.targets().contains(&addr) {
+        return Err((StatusCode::NOT_FOUND, format!("{} is not monitored", addr)));
+    }
+    Ok(addr)
+}
+
+async fn get_target(State(state): State<Arc<ApiState>>, Extension(access): Extension<Access>, Path(id): Path<String>) -> Response {
+    match parse_id(&state, &id) {
+        Ok(addr) => {
+            let target = state.monitor.monitor_target(addr).unwrap_or_else(|| MonitorTarget::new(addr));
+            Json(view(&state, target, &access, annotations().as_ref())).into_response()
+        }
+        Err((status, message)) => error(status, message),
+    }
+}
+
+async fn add_target(State(state): State<Arc<ApiState>>, Extension(access): Extension<Access>, Json(new): Json<NewTarget>) -> Response {
+    let addr = new.target.address;
+    let added = state
+        .monitor
+        .add_monitor_target(new.target)
+        .and_then(|()| state.monitor.set_group(addr, new.group));
+    match added {
+        Ok(()) => {
+            let target = state.monitor.monitor_target(addr).unwrap_or_else(|| MonitorTarget::new(addr));
+            (StatusCode::CREATED, Json(view(&state, target, &access, annotations().as_ref()))).into_response()
+        }
+        Err(e) => error(StatusCode::CONFLICT, e),
+    }
+}
+
+async fn remove_target(State(state): State<Arc<ApiState>>, Path(id): Path<String>) -> Response {
+    match parse_id(&state, &id).map(|addr| state.monitor.remove_target(addr)) {
+        Ok(Ok(())) => StatusCode::NO_CONTENT.into_response(),
+        Ok(Err(e)) => error(StatusCode::NOT_FOUND, e),
+        Err((status, message)) => error(status, message),
+    }
+}
+
+async fn uptimerobot_monitors(
+    State(state): State<Arc<ApiState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Form(params): Form<GetMonitors>,
+) -> Response {
+    let access = api_access::authorize(&state.config, &state.auth, "POST", "/v2/getMonitors", peer.ip(), params.api_key.as_deref());
+    // UptimeRobot answers 200 with `"stat": "fail"`, and its clients only look at that.
+    let body = match access {
+        Ok(_) => uptimerobot::get_monitors(&state.monitor, &state.history, annotations().as_ref(), &params, Utc::now()),
+        Err(denied) => uptimerobot::fail("invalid_parameter", &denied.to_string()),
+    };
+    Json(body).into_response()
+}
+
+pub fn parse_since(value: Option<&str>, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let Some(value) = value else {
+        return Ok(now - ChronoDuration::days(1));
+    };
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let back = parse_interval(value)?;
+    ChronoDuration::from_std(back).map(|back| now - back).map_err(|e| e.to_string())
+}
+
+async fn target_history(
+    State(state): State<Arc<ApiState>>,
+    Extension(access): Extension<Access>,
+    Path(id): Path<String>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let addr = match parse_id(&state, &id) {
+        Ok(addr) => addr,
+        Err((status, message)) => return error(status, message),
+    };
+    match parse_since(query.since.as_deref(), Utc::now()) {
+        Ok(since) => {
+            let mut results: Vec<CheckResult> = state.history.results(&addr.to_string(), since);
+            // Like `view`, metadata is kept from anonymous readers, and so are the
+            // correlation IDs that lead into the logs and alerts.
+            if access == Access::Anonymous {
+                for result in &mut results {
+                    result.metadata.clear();
+                    result.correlation_id.clear();
+                }
+            }
+            Json(results).into_response()
+        }
+        Err(e) => error(StatusCode::BAD_REQUEST, format!("since: {}", e)),
+    }
+}
+
+/// The resource waterfall of the target's latest browser check that recorded one, as an
+/// HTML page.
+async fn target_waterfall(State(state): State<Arc<ApiState>>, Path(id): Path<String>) -> Response {
+    let addr = match parse_id(&state, &id) {
+        Ok(addr) => addr,
+        Err((status, message)) => return error(status, message),
+    };
+    let results = state.history.results(&addr.to_string(), DateTime::<Utc>::MIN_UTC);
+    match results.iter().rev().find_map(Waterfall::of_result) {
+        Some(waterfall) => Html(format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{addr}</title><style>\
+             .waterfall td.bar {{ width: 60%; }} .waterfall td.bar div {{ height: 0.8em; background: #4a90d9; }}\
+             </style></head>\n<body>\n{table}\n</body></html>\n",
+            addr = addr,
+            table = waterfall.to_html(),
+        ))
+        .into_response(),
+        None => error(StatusCode::NOT_FOUND, format!("no browser check of {} recorded a waterfall", addr)),
+    }
+}
+
+/// The evidence zip of a target: its results and annotations. Captures, traceroutes and
+/// attachments are only added by the `--evidence` command.
+async fn target_evidence(State(state): State<Arc<ApiState>>, Path(id): Path<String>, Query(query): Query<EvidenceQuery>) -> Response {
+    let addr = match parse_id(&state, &id) {
+        Ok(addr) => addr,
+        Err((status, message)) => return error(status, message),
+    };
+    let now = Utc::now();
+    let from = match parse_since(query.from.as_deref(), now) {
+        Ok(from) => from,
+        Err(e) => return error(StatusCode::BAD_REQUEST, format!("from: {}", e)),
+    };
+    let to = match query.to.as_deref().map(DateTime::parse_from_rfc3339) {
+        Some(Ok(to)) => to.with_timezone(&Utc),
+        Some(Err(e)) => return error(StatusCode::BAD_REQUEST, format!("to: {}", e)),
+        None => now,
+    };
+    let bundle = evidence::collect(&state.history, annotations().as_ref(), &addr.to_string(), from, to, now);
+    match bundle.to_zip() {
+        Ok(zip) => (
+            [
+                (header::CONTENT_TYPE, "application/zip".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", bundle.file_name())),
+            ],
+            zip,
+        )
+            .into_response(),
+        Err(e) => error(StatusCode::INTERNAL_SERVER_ERROR, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::event_bus::EventBus;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_targets_can_be_added_listed_and_queried() {
+        let monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_secs(1)));
+        let history = Arc::new(History::default());
+        history.push(CheckResult::success("10.0.0.5:443", Duration::from_millis(12)));
+        let state = Arc::new(ApiState::new(monitor.clone(), history, ApiConfig::default()));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, router(state).into_make_service_with_connect_info::<SocketAddr>()).await
+        });
+        let client = reqwest::Client::new();
+
+        let health: serde_json::Value = client.get(format!("{}/health", base)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(health["status"], "ok");
+        let added = client
+            .post(format!("{}/targets", base))
+            .json(&json!({"address": "10.0.0.5:443", "interval": 30000, "group": "web", "metadata": {"team": "shop"}}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(added.status(), StatusCode::CREATED.as_u16());
+        assert_eq!(monitor.group("10.0.0.5:443".parse().unwrap()).as_deref(), Some("web"));
+        let again = client.post(format!("{}/targets", base)).json(&json!({"address": "10.0.0.5:443"})).send().await.unwrap();
+        assert_eq!(again.status(), StatusCode::CONFLICT.as_u16());
+
+        let targets: serde_json::Value = client.get(format!("{}/targets", base)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(targets[0]["interval"], 30000);
+        assert_eq!(targets[0]["uptime_24h"], 1.0);
+        let history: serde_json::Value = client
+            .get(format!("{}/targets/10.0.0.5:443/history?since=1h", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(history.as_array().unwrap().len(), 1);
+        let evidence = client.get(format!("{}/targets/10.0.0.5:443/evidence?from=1h", base)).send().await.unwrap();
+        assert_eq!(evidence.headers()[header::CONTENT_TYPE], "application/zip");
+        assert!(evidence.bytes().await.unwrap().starts_with(b"PK"));
+        let waterfall = client.get(format!("{}/targets/10.0.0.5:443/waterfall", base)).send().await.unwrap();
+        assert_eq!(waterfall.status(), StatusCode::NOT_FOUND.as_u16());
+        let robot: serde_json::Value = client
+            .post(format!("{}/v2/getMonitors", base))
+            .form(&[("api_key", "none"), ("format", "json")])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(robot["monitors"][0]["id"], uptimerobot::monitor_id("10.0.0.5:443".parse().unwrap()));
+        let metrics = client.get(format!("{}/metrics", base)).send().await.unwrap().text().await.unwrap();
+        assert!(metrics.contains("rust_npm_target_latency_seconds{meta_team=\"shop\",target=\"10.0.0.5:443\"} 0.012"), "{}", metrics);
+        let missing = client.get(format!("{}/targets/10.0.0.9:22/history", base)).send().await.unwrap();
+        assert_eq!(missing.status(), StatusCode::NOT_FOUND.as_u16());
+        let removed = client.delete(format!("{}/targets/10.0.0.5:443", base)).send().await.unwrap();
+        assert_eq!(removed.status(), StatusCode::NO_CONTENT.as_u16());
+        assert!(monitor.targets().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_oidc_login_hands_out_a_session_until_logout() {
+        // An identity provider that accepts any code.
+        let provider = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", provider.local_addr().unwrap());
+        let endpoints = json!({
+            "authorization_endpoint": format!("{}/authorize", issuer),
+            "token_endpoint": format!("{}/token", issuer),
+            "userinfo_endpoint": format!("{}/userinfo", issuer),
+        });
+        let idp = Router::new()
+            .route("/.well-known/openid-configuration", get(move || async move { Json(endpoints) }))
+            .route("/token", post(|| async { Json(json!({"access_token": "at"})) }))
+            .route("/userinfo", get(|| async { Json(json!({"sub": "1", "preferred_username": "alice"})) }));
+        tokio::spawn(async move { axum::serve(provider, idp).await });
+
+        let config = ApiConfig {
+            oidc: Some(api_access::OidcConfig {
+                issuer,
+                client_id: "monitor".to_string(),
+                client_secret: "secret".to_string(),
+                redirect_uri: "http://monitor.example/login/oidc/callback".to_string(),
+            }),
+            ..ApiConfig::default()
+        };
+        let monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_secs(1)));
+        let state = Arc::new(ApiState::new(monitor, Arc::new(History::default()), config).discover_oidc().await.unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let served = state.clone();
+        tokio::spawn(async move {
+            axum::serve(listener, router(served).into_make_service_with_connect_info::<SocketAddr>()).await
+        });
+        let client = reqwest::Client::builder().redirect(reqwest::redirect::Policy::none()).build().unwrap();
+
+        let redirect = client.get(format!("{}/login/oidc", base)).send().await.unwrap();
+        let location = reqwest::Url::parse(redirect.headers()[header::LOCATION].to_str().unwrap()).unwrap();
+        let login_state = location.query_pairs().find(|(key, _)| key == "state").unwrap().1.to_string();
+        let forged = client.get(format!("{}/login/oidc/callback?code=c&state=forged", base)).send().await.unwrap();
+        assert_eq!(forged.status(), StatusCode::BAD_REQUEST.as_u16());
+        let session: serde_json::Value = client
+            .get(format!("{}/login/oidc/callback?code=c&state={}", base, login_state))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(session["user"], "alice");
+        let token = session["token"].as_str().unwrap();
+        assert_eq!(state.auth.verify(token).unwrap().provider, "oidc");
+
+        let logout = client.post(format!("{}/logout", base)).bearer_auth(token).send().await.unwrap();
+        assert_eq!(logout.status(), StatusCode::NO_CONTENT.as_u16());
+        assert!(state.auth.verify(token).is_none());
+        let ldap = client.post(format!("{}/login", base)).form(&[("user", "alice"), ("password", "pw")]).send().await.unwrap();
+        assert_eq!(ldap.status(), StatusCode::NOT_FOUND.as_u16());
+    }
+}
@@ -0,0 +1,342 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+// Logins through LDAP/OIDC only last this long, so someone removed from the directory
+// loses access within a working day without us having to poll the provider.
+const DEFAULT_SESSION_TTL_HOURS: i64 = 8;
+
+/// What a caller may do through the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Status, history, badges and the status page, e.g. for customers.
+    Read,
+    Admin,
+}
+
+/// Who is calling the web UI/API, and which provider vouched for them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub user: String,
+    pub groups: Vec<String>,
+    pub provider: String,
+    pub scope: Scope,
+}
+
+fn sha256_hex(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Long-lived API tokens configured locally. Only SHA-256 hashes are kept, so a leaked
+/// config file doesn't leak usable tokens.
+#[derive(Debug, Default, Clone)]
+pub struct LocalTokens {
+    hashes: HashMap<String, (String, Scope)>,
+}
+
+impl LocalTokens {
+    /// Adds a token given as its hex SHA-256 hash, as stored in config files.
+    pub fn add_hashed(&mut self, user: &str, hash: &str, scope: Scope) {
+        self.hashes.insert(hash.trim().to_ascii_lowercase(), (user.to_string(), scope));
+    }
+
+    pub fn verify(&self, token: &str) -> Option<Identity> {
+        self.hashes.get(&sha256_hex(token)).map(|(user, scope)| Identity {
+            user: user.clone(),
+            groups: Vec::new(),
+            provider: "local".to_string(),
+            scope: *scope,
+        })
+    }
+}
+
+/// LDAP simple-bind login. The user's own credentials are used for the bind, so no service
+/// account password has to be stored.
+#[cfg(feature = "ldap-auth")]
+#[derive(Debug, Clone, Deserialize)]
+pub struct LdapProvider {
+    /// e.g. `ldaps://ldap.example.com:636`
+    pub url: String,
+    /// DN of the user entry with `{user}` as placeholder, e.g.
+    /// `uid={user},ou=people,dc=example,dc=com`.
+    pub user_dn_template: String,
+    /// When set, only members of this group (matched against `memberOf`) may log in.
+    #[serde(default)]
+    pub required_group: Option<String>,
+}
+
+#[cfg(feature = "ldap-auth")]
+impl LdapProvider {
+    pub async fn authenticate(&self, user: &str, password: &str) -> Result<Identity, Box<dyn Error>> {
+        use ldap3::{LdapConnAsync, Scope as SearchScope, SearchEntry};
+
+        // An empty password is an anonymous bind and would "succeed" for any user.
+        if user.trim().is_empty() || password.is_empty() {
+            return Err("user name and password are required".into());
+        }
+        let dn = self.user_dn_template.replace("{user}", &ldap3::dn_escape(user));
+        let (conn, mut ldap) = LdapConnAsync::new(&self.url).await?;
+        ldap3::drive!(conn);
+        ldap.simple_bind(&dn, password)
+            .await?
+            .success()
+            .map_err(|_| format!("LDAP login failed for {}", user))?;
+
+        let (entries, _) = ldap
+            .search(&dn, SearchScope::Base, "(objectClass=*)", vec!["memberOf"])
+            .await?
+            .success()?;
+        let groups: Vec<String> = entries
+            .into_iter()
+            .flat_map(|entry| SearchEntry::construct(entry).attrs.remove("memberOf").unwrap_or_default())
+            .collect();
+        let _ = ldap.unbind().await;
+
+        if let Some(required) = &self.required_group
+            && !groups.iter().any(|g| g.eq_ignore_ascii_case(required))
+        {
+            return Err(format!("{} is not a member of {}", user, required).into());
+        }
+        Ok(Identity {
+            user: user.to_string(),
+            groups,
+            provider: "ldap".to_string(),
+            scope: Scope::Admin,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct OidcDiscovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UserInfo {
+    sub: String,
+    preferred_username: Option<String>,
+    email: Option<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+/// A login started with `OidcProvider::begin`; kept server-side until the redirect returns.
+#[derive(Debug, Clone)]
+pub struct PendingLogin {
+    pub state: String,
+    pub authorize_url: String,
+    code_verifier: String,
+}
+
+/// OpenID Connect authorization code flow (with PKCE) against the company identity
+/// provider. Identities come from the userinfo endpoint, so no token signatures have to be
+/// checked locally.
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    endpoints: OidcDiscovery,
+    client: reqwest::Client,
+}
+
+impl OidcProvider {
+    /// Reads the endpoints from `{issuer}/.well-known/openid-configuration`.
+    pub async fn discover(issuer: &str, client_id: &str, client_secret: &str, redirect_uri: &str) -> Result<Self, Box<dyn Error>> {
+        let client = reqwest::Client::new();
+        let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+        let endpoints = client.get(&url).send().await?.error_for_status()?.json().await?;
+        Ok(Self {
+            client_id: client_id.to_string(),
+            client_secret: client_secret.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            endpoints,
+            client,
+        })
+    }
+
+    /// Where to send the browser to log in.
+    pub fn begin(&self) -> Result<PendingLogin, Box<dyn Error>> {
+        let state = random_token();
+        let code_verifier = random_token();
+        let challenge = base64_url(&Sha256::digest(code_verifier.as_bytes()));
+        let mut url = reqwest::Url::parse(&self.endpoints.authorization_endpoint)?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_uri)
+            .append_pair("scope", "openid profile email")
+            .append_pair("state", &state)
+            .append_pair("code_challenge", &challenge)
+            .append_pair("code_challenge_method", "S256");
+        Ok(PendingLogin {
+            state,
+            authorize_url: url.to_string(),
+            code_verifier,
+        })
+    }
+
+    /// Completes the login once the provider redirects back with `code` and `state`.
+    pub async fn complete(&self, pending: &PendingLogin, code: &str, state: &str) -> Result<Identity, Box<dyn Error>> {
+        if state != pending.state {
+            return Err("OIDC state mismatch".into());
+        }
+        let tokens: TokenResponse = self
+            .client
+            .post(&self.endpoints.token_endpoint)
+            .basic_auth(&self.client_id, Some(&self.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.redirect_uri.as_str()),
+                ("code_verifier", pending.code_verifier.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let info: UserInfo = self
+            .client
+            .get(&self.endpoints.userinfo_endpoint)
+            .bearer_auth(&tokens.access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(Identity {
+            user: info.preferred_username.or(info.email).unwrap_or(info.sub),
+            groups: info.groups,
+            provider: "oidc".to_string(),
+            scope: Scope::Admin,
+        })
+    }
+}
+
+// Unpadded base64url, as PKCE requires; small enough not to warrant another dependency.
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+struct Session {
+    identity: Identity,
+    expires: DateTime<Utc>,
+}
+
+/// Front door for the web UI/API: accepts local API tokens and the session tokens handed
+/// out after an LDAP or OIDC login.
+pub struct Authenticator {
+    local: LocalTokens,
+    sessions: Mutex<HashMap<String, Session>>,
+    session_ttl: ChronoDuration,
+}
+
+impl Authenticator {
+    pub fn new(local: LocalTokens) -> Self {
+        Self {
+            local,
+            sessions: Mutex::new(HashMap::new()),
+            session_ttl: ChronoDuration::hours(DEFAULT_SESSION_TTL_HOURS),
+        }
+    }
+
+    pub fn with_session_ttl(mut self, ttl: ChronoDuration) -> Self {
+        self.session_ttl = ttl;
+        self
+    }
+
+    /// Issues a session token for an identity a provider has just confirmed.
+    pub fn start_session(&self, identity: Identity) -> String {
+        let token = random_token();
+        let session = Session {
+            identity,
+            expires: Utc::now() + self.session_ttl,
+        };
+        self.sessions.lock().unwrap().insert(sha256_hex(&token), session);
+        token
+    }
+
+    pub fn end_session(&self, token: &str) {
+        self.sessions.lock().unwrap().remove(&sha256_hex(token));
+    }
+
+    /// Resolves a bearer token to an identity; expired sessions are dropped on the way.
+    pub fn verify(&self, token: &str) -> Option<Identity> {
+        if let Some(identity) = self.local.verify(token) {
+            return Some(identity);
+        }
+        let now = Utc::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.expires > now);
+        sessions.get(&sha256_hex(token)).map(|s| s.identity.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_tokens_and_sessions() {
+        let mut local = LocalTokens::default();
+        local.add_hashed("ci", &sha256_hex("s3cret"), Scope::Admin);
+        local.add_hashed("ops", &sha256_hex("other"), Scope::Read);
+        let auth = Authenticator::new(local);
+        assert_eq!(auth.verify("s3cret").unwrap().user, "ci");
+        assert_eq!(auth.verify("s3cret").unwrap().scope, Scope::Admin);
+        assert_eq!(auth.verify("other").unwrap().scope, Scope::Read);
+        assert!(auth.verify("guess").is_none());
+
+        let identity = Identity {
+            user: "alice".to_string(),
+            groups: vec!["noc".to_string()],
+            provider: "oidc".to_string(),
+            scope: Scope::Admin,
+        };
+        let token = auth.start_session(identity.clone());
+        assert_eq!(auth.verify(&token), Some(identity));
+        auth.end_session(&token);
+        assert!(auth.verify(&token).is_none());
+
+        let expired = Authenticator::new(LocalTokens::default()).with_session_ttl(ChronoDuration::zero());
+        let token = expired.start_session(Identity {
+            user: "bob".to_string(),
+            groups: Vec::new(),
+            provider: "ldap".to_string(),
+            scope: Scope::Admin,
+        });
+        assert!(expired.verify(&token).is_none());
+    }
+
+    #[test]
+    fn test_base64_url_matches_pkce_example() {
+        let challenge = base64_url(&Sha256::digest(b"verifier"));
+        assert_eq!(challenge, "iMnq5o6zALKXGivsnlom_0F5_WYda32GHkxlV7mq7hQ");
+        assert_eq!(base64_url(b"ab"), "YWI");
+    }
+}
@@ -1,13 +1,216 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::json;
+use thirtyfour::extensions::cdp::ChromeDevTools;
 use thirtyfour::prelude::*;
+use std::error::Error;
+use std::fmt;
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::check_result::{CheckResult, StepResult, StepStatus};
+use super::clock::ClockGuard;
+use super::csv_import::parse_interval;
+use super::failure_kind::FailureKind;
+use super::navigation_timing::{NAVIGATION_TIMING_SCRIPT, NavigationTiming};
+use super::page_errors::{PAGE_ERRORS_HOOK, PAGE_ERRORS_SCRIPT, PageError};
+use super::selector_check::{SELECTOR_SCRIPT, SelectorValidation};
+use super::waterfall::Waterfall;
+use super::webdriver_reaper::SessionRegistry;
+
+/// Which browser a WebDriver session drives. Each needs its own driver (chromedriver,
+/// msedgedriver, geckodriver, safaridriver) behind the WebDriver URL.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BrowserKind {
+    #[default]
+    Chrome,
+    Edge,
+    Firefox,
+    /// safaridriver has no headless mode and runs one session at a time.
+    Safari,
+}
+
+impl BrowserKind {
+    pub const ALL: [BrowserKind; 4] = [BrowserKind::Chrome, BrowserKind::Edge, BrowserKind::Firefox, BrowserKind::Safari];
+
+    /// Capabilities for a new session. `headless` is ignored for Safari, which can't run
+    /// without a window.
+    pub fn capabilities(self, headless: bool) -> Result<Capabilities, WebDriverError> {
+        match self {
+            BrowserKind::Chrome => {
+                let mut caps = DesiredCapabilities::chrome();
+                if headless {
+                    caps.add_chrome_arg("--headless")?;
+                    caps.add_chrome_arg("--no-sandbox")?; // Often needed in containerized environments
+                    caps.add_chrome_arg("--disable-dev-shm-usage")?; // Overcomes limited resource problems
+                    // You might need more arguments depending on the environment and Chrome version
+                    // e.g., "--disable-gpu", "--window-size=1920,1080"
+                }
+                Ok(caps.into())
+            }
+            BrowserKind::Edge => {
+                // thirtyfour has no helpers for Edge; its options live under `ms:edgeOptions`.
+                // Group policies on managed desktops pop up first-run and sync dialogs unless
+                // told not to.
+                let mut args = vec!["--no-first-run", "--no-default-browser-check"];
+                if headless {
+                    // Old-style `--headless` is gone from current Edge; without a GPU on
+                    // Windows VMs rendering hangs unless it's disabled.
+                    args.extend(["--headless=new", "--disable-gpu", "--window-size=1920,1080"]);
+                }
+                let mut caps: Capabilities = DesiredCapabilities::edge().into();
+                caps.insert("ms:edgeOptions".to_string(), json!({ "args": args }));
+                Ok(caps)
+            }
+            BrowserKind::Firefox => {
+                let mut caps = DesiredCapabilities::firefox();
+                if headless {
+                    caps.set_headless()?;
+                }
+                Ok(caps.into())
+            }
+            BrowserKind::Safari => {
+                let mut caps: Capabilities = DesiredCapabilities::safari().into();
+                // Keeps Web Inspector from attaching and pausing on the first page.
+                caps.insert("safari:automaticInspection".to_string(), json!(false));
+                caps.insert("safari:automaticProfiling".to_string(), json!(false));
+                Ok(caps)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BrowserKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BrowserKind::Chrome => "Chrome",
+            BrowserKind::Edge => "Edge",
+            BrowserKind::Firefox => "Firefox",
+            BrowserKind::Safari => "Safari",
+        })
+    }
+}
+
+impl FromStr for BrowserKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "chrome" | "chromium" => Ok(BrowserKind::Chrome),
+            "edge" | "msedge" | "microsoftedge" => Ok(BrowserKind::Edge),
+            "firefox" => Ok(BrowserKind::Firefox),
+            "safari" => Ok(BrowserKind::Safari),
+            other => Err(format!("unknown browser '{}' (chrome, edge, firefox or safari)", other)),
+        }
+    }
+}
+
+/// What one step of a `Transaction` does.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum TransactionAction {
+    Goto { url: String },
+    /// Waits for the element to be visible, then clicks it.
+    Click { selector: String },
+    /// Waits for the input to be visible, clears it and types `value`, or the value of the
+    /// environment variable `value_env` (for passwords).
+    Fill {
+        selector: String,
+        #[serde(default)]
+        value: String,
+        value_env: Option<String>,
+    },
+    WaitFor { selector: String },
+    /// The element's text (the whole page's without a selector) must contain `text`.
+    AssertText { selector: Option<String>, text: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TransactionStep {
+    /// Shown in results; defaults to a description of the action.
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub action: TransactionAction,
+}
+
+impl TransactionStep {
+    pub fn label(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match &self.action {
+            TransactionAction::Goto { url } => format!("goto {}", url),
+            TransactionAction::Click { selector } => format!("click {}", selector),
+            TransactionAction::Fill { selector, .. } => format!("fill {}", selector),
+            TransactionAction::WaitFor { selector } => format!("wait for {}", selector),
+            TransactionAction::AssertText { text, .. } => format!("assert text '{}'", text),
+        }
+    }
+}
+
+/// A scripted browser session, e.g. a login flow or checkout path, read from TOML:
+///
+/// ```text
+/// name = "shop login"
+/// step_timeout = "15s"
+///
+/// [[step]]
+/// action = "goto"
+/// url = "https://shop.example.com/login"
+///
+/// [[step]]
+/// action = "fill"
+/// selector = "#password"
+/// value_env = "SHOP_PASSWORD"
+///
+/// [[step]]
+/// name = "logged in"
+/// action = "assert_text"
+/// selector = ".greeting"
+/// text = "Welcome"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Transaction {
+    pub name: String,
+    /// How long each step may take, including waiting for its element.
+    #[serde(default = "default_step_timeout", deserialize_with = "interval")]
+    pub step_timeout: Duration,
+    #[serde(rename = "step")]
+    pub steps: Vec<TransactionStep>,
+}
+
+fn default_step_timeout() -> Duration {
+    Duration::from_secs(BrowserEmulator::DEFAULT_ELEMENT_WAIT_TIMEOUT_SECONDS)
+}
+
+fn interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_interval(&value).map_err(serde::de::Error::custom)
+}
+
+impl Transaction {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
 
 /// Emulates a web browser to interact with web pages, primarily for measuring load times.
 ///
 /// It uses Selenium WebDriver (via the `thirtyfour` crate) to control a browser instance.
 /// A running WebDriver server (e.g., chromedriver, geckodriver) is required.
+///
+/// Call `close` when done. An emulator that is dropped without it (a panicking or
+/// cancelled check) still quits its session in the background, so no headless browser is
+/// left running.
 pub struct BrowserEmulator {
-    driver: WebDriver,
+    // Only `None` once `close` or `drop` has taken it.
+    driver: Option<WebDriver>,
+    tracking: Option<(SessionRegistry, String)>,
 }
 
 impl BrowserEmulator {
@@ -25,19 +228,51 @@ impl BrowserEmulator {
     ///
     /// # Notes
     ///
-    /// Currently hardcoded to use Chrome. Headless mode arguments are specific to Chrome.
+    /// Uses Chrome; see `with_browser` for Edge and Safari.
     pub async fn new(webdriver_url: &str, headless: bool) -> Result<Self, WebDriverError> {
-        let mut caps = DesiredCapabilities::chrome();
-        if headless {
-            caps.add_chrome_arg("--headless")?;
-            caps.add_chrome_arg("--no-sandbox")?; // Often needed in containerized environments
-            caps.add_chrome_arg("--disable-dev-shm-usage")?; // Overcomes limited resource problems
-            // You might need more arguments depending on the environment and Chrome version
-            // e.g., "--disable-gpu", "--window-size=1920,1080"
+        Self::with_browser(webdriver_url, BrowserKind::Chrome, headless).await
+    }
+
+    /// Like `new`, but drives the given browser.
+    ///
+    /// # Arguments
+    ///
+    /// * `webdriver_url`: The URL of the matching WebDriver server.
+    /// * `browser`: The browser to start; see `BrowserKind::capabilities` for its quirks.
+    /// * `headless`: If `true`, runs without a window where the browser supports it.
+    pub async fn with_browser(webdriver_url: &str, browser: BrowserKind, headless: bool) -> Result<Self, WebDriverError> {
+        if headless && browser == BrowserKind::Safari {
+            eprintln!("Safari has no headless mode; opening a window");
+        }
+        let driver = WebDriver::new(webdriver_url, browser.capabilities(headless)?).await?;
+        if browser == BrowserKind::Chrome {
+            // Only Chrome's driver takes DevTools commands; elsewhere `page_errors` falls
+            // back to Resource Timing. A failure here costs the console errors, not the check.
+            let devtools = ChromeDevTools::new(driver.handle.clone());
+            let _ = devtools.execute_cdp_with_params("Page.addScriptToEvaluateOnNewDocument", json!({ "source": PAGE_ERRORS_HOOK })).await;
         }
+        Ok(Self {
+            driver: Some(driver),
+            tracking: None,
+        })
+    }
 
-        let driver = WebDriver::new(webdriver_url, caps).await?;
-        Ok(Self { driver })
+    /// Records the session in `registry`, so the orphan reaper can kill it should this
+    /// process die before the session is closed.
+    ///
+    /// # Arguments
+    ///
+    /// * `registry`: The session registry shared by all checks.
+    /// * `webdriver_url`: The URL of the WebDriver server the session was created on.
+    pub async fn track(&mut self, registry: SessionRegistry, webdriver_url: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let session_id = self.driver().session_id().await?.to_string();
+        registry.record(webdriver_url, &session_id)?;
+        self.tracking = Some((registry, session_id));
+        Ok(())
+    }
+
+    fn driver(&self) -> &WebDriver {
+        self.driver.as_ref().expect("driver is only taken when the emulator is closed")
     }
 
     // Default timeout for waiting for an element to become available
@@ -65,25 +300,30 @@ impl BrowserEmulator {
     ///
     /// Can return `WebDriverError::NoSuchElement` or `WebDriverError::Timeout` if `functional_criteria_selector`
     /// is provided and the element is not found or visible within `DEFAULT_ELEMENT_WAIT_TIMEOUT_SECONDS`.
+    ///
+    /// # Notes
+    ///
+    /// Console errors and failed requests of the page are collected while it loads; read
+    /// them with `page_errors` afterwards.
     pub async fn measure_load_time(
         &self,
         url: &str,
         functional_criteria_selector: Option<&str>,
     ) -> Result<Duration, WebDriverError> {
         let start_time = Instant::now();
-        self.driver.goto(url).await?;
+        self.driver().goto(url).await?;
 
         if let Some(selector) = functional_criteria_selector {
             let by = By::Css(selector);
             // Wait for the element to be present and visible
             // thirtyfour's find() method implicitly waits for the element to be present.
             // We can also add an explicit wait with a timeout.
-            let wait_timeout = Duration::from_secs(DEFAULT_ELEMENT_WAIT_TIMEOUT_SECONDS);
+            let wait_timeout = Duration::from_secs(Self::DEFAULT_ELEMENT_WAIT_TIMEOUT_SECONDS);
             let mut attempts = 0;
             let max_attempts = wait_timeout.as_secs() * 2; // Check twice per second
 
             loop {
-                match self.driver.query(by.clone()).first().await {
+                match self.driver().query(by.clone()).first().await {
                     Ok(element) => {
                         if element.is_displayed().await? {
                             break; // Element found and is visible
@@ -117,6 +357,199 @@ impl BrowserEmulator {
         Ok(duration)
     }
 
+    /// Reads the Resource Timing entries of the page currently loaded, e.g. right after
+    /// `measure_load_time`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The URL the page was loaded from, used as the waterfall's title.
+    /// * `functional_time`: The result of `measure_load_time`, marked in the waterfall.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `Waterfall`, or a `WebDriverError` if the script fails.
+    pub async fn resource_timings(&self, url: &str, functional_time: Option<Duration>) -> Result<Waterfall, WebDriverError> {
+        let entries = self
+            .driver()
+            .execute("return performance.getEntriesByType('resource').map(e => e.toJSON());", Vec::new())
+            .await?;
+        Ok(Waterfall::from_entries(
+            url,
+            functional_time.map(|d| d.as_secs_f64() * 1000.0),
+            entries.json(),
+        ))
+    }
+
+    /// Runs the steps of `transaction` in order, timing each. The first failing step fails
+    /// the check and the steps after it are marked skipped, as in `CompositeCheck`. The
+    /// overall latency is the sum of the step latencies.
+    ///
+    /// # Arguments
+    ///
+    /// * `transaction`: The steps to run, usually from `Transaction::load`.
+    ///
+    /// # Returns
+    ///
+    /// A `CheckResult` for the transaction's name, with one entry per step in `steps`.
+    pub async fn run_transaction(&self, transaction: &Transaction) -> CheckResult {
+        let clock = ClockGuard::start();
+        let mut steps = Vec::with_capacity(transaction.steps.len());
+        let mut failure = None;
+        for step in &transaction.steps {
+            if failure.is_some() {
+                steps.push(StepResult {
+                    name: step.label(),
+                    status: StepStatus::Skipped,
+                    latency: None,
+                    error: None,
+                });
+                continue;
+            }
+            let start = Instant::now();
+            let outcome = match tokio::time::timeout(transaction.step_timeout, self.run_step(&step.action, transaction.step_timeout)).await {
+                Ok(outcome) => outcome,
+                Err(_) => Err((FailureKind::BrowserTimeout, format!("timed out after {:?}", transaction.step_timeout))),
+            };
+            let latency = outcome.is_ok().then(|| start.elapsed());
+            let error = outcome.err().map(|(kind, error)| {
+                failure = Some(kind);
+                error
+            });
+            steps.push(StepResult {
+                name: step.label(),
+                status: if error.is_some() { StepStatus::Failed } else { StepStatus::Passed },
+                latency,
+                error,
+            });
+        }
+
+        let total: Duration = steps.iter().filter_map(|s| s.latency).sum();
+        let mut result = match (steps.iter().find(|s| s.status == StepStatus::Failed), failure) {
+            (Some(step), Some(kind)) => CheckResult::failure(
+                &transaction.name,
+                format!("step '{}' failed: {}", step.name, step.error.as_deref().unwrap_or("unknown error")),
+            )
+            .with_failure_kind(kind),
+            _ => CheckResult::success(&transaction.name, total),
+        };
+        result.steps = steps;
+        clock.check(&mut result);
+        result
+    }
+
+    async fn run_step(&self, action: &TransactionAction, wait: Duration) -> Result<(), (FailureKind, String)> {
+        let driver_error = |e: WebDriverError| {
+            let error = e.to_string();
+            (FailureKind::classify(&error), error)
+        };
+        match action {
+            TransactionAction::Goto { url } => self.driver().goto(url).await.map_err(driver_error),
+            TransactionAction::Click { selector } => self.visible(selector, wait).await?.click().await.map_err(driver_error),
+            TransactionAction::Fill { selector, value, value_env } => {
+                let value = match value_env {
+                    Some(name) => std::env::var(name).map_err(|_| (FailureKind::InfraError, format!("{} is not set", name)))?,
+                    None => value.clone(),
+                };
+                let input = self.visible(selector, wait).await?;
+                input.clear().await.map_err(driver_error)?;
+                input.send_keys(value).await.map_err(driver_error)
+            }
+            TransactionAction::WaitFor { selector } => self.visible(selector, wait).await.map(|_| ()),
+            TransactionAction::AssertText { selector, text } => {
+                let element = match selector {
+                    Some(selector) => self.visible(selector, wait).await?,
+                    None => self.driver().find(By::Tag("body")).await.map_err(driver_error)?,
+                };
+                let found = element.text().await.map_err(driver_error)?;
+                if found.contains(text.as_str()) {
+                    Ok(())
+                } else {
+                    Err((FailureKind::ContentMismatch, format!("'{}' not found in the text", text)))
+                }
+            }
+        }
+    }
+
+    // Polls for the first element matching `selector` until it is displayed.
+    async fn visible(&self, selector: &str, wait: Duration) -> Result<WebElement, (FailureKind, String)> {
+        let start = Instant::now();
+        loop {
+            if let Ok(element) = self.driver().query(By::Css(selector)).first().await
+                && element.is_displayed().await.unwrap_or(false)
+            {
+                return Ok(element);
+            }
+            if start.elapsed() > wait {
+                return Err((FailureKind::ContentMismatch, format!("no visible element matches '{}'", selector)));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Reads the Navigation Timing breakdown (DNS, connect, TTFB, DOMContentLoaded, load)
+    /// of the page currently loaded, e.g. right after `measure_load_time`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `NavigationTiming`, `None` if the browser has no entry for
+    /// the page, or a `WebDriverError` if the script fails.
+    pub async fn navigation_timing(&self) -> Result<Option<NavigationTiming>, WebDriverError> {
+        let entry = self.driver().execute(NAVIGATION_TIMING_SCRIPT, Vec::new()).await?;
+        Ok(NavigationTiming::from_entry(entry.json()))
+    }
+
+    /// Reads the console errors, exceptions and failed requests of the page currently
+    /// loaded, e.g. right after `measure_load_time`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the errors, oldest first, or a `WebDriverError` if the script
+    /// fails.
+    ///
+    /// # Notes
+    ///
+    /// Console errors and exceptions are only collected on Chrome; Edge and Safari report
+    /// failed requests only.
+    pub async fn page_errors(&self) -> Result<Vec<PageError>, WebDriverError> {
+        let errors = self.driver().execute(PAGE_ERRORS_SCRIPT, Vec::new()).await?;
+        Ok(PageError::from_script(errors.json()))
+    }
+
+    /// Loads `url` once and checks whether `selector` parses and currently matches a
+    /// visible element, without waiting for it to appear.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The page the functional selector is meant for.
+    /// * `selector`: The CSS selector to validate.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the `SelectorValidation`, or a `WebDriverError` if the page
+    /// can't be loaded.
+    pub async fn check_selector(&self, url: &str, selector: &str) -> Result<SelectorValidation, WebDriverError> {
+        self.driver().goto(url).await?;
+        let result = self
+            .driver()
+            .execute(SELECTOR_SCRIPT, vec![serde_json::Value::from(selector)])
+            .await?;
+        Ok(SelectorValidation::from_script(result.json()))
+    }
+
+    /// Checks that the session still answers and clears what the last check left behind
+    /// (its cookies and open page), so the session can run another check.
+    ///
+    /// # Notes
+    ///
+    /// The HTTP cache is kept, so a reused session loads repeat visits faster than a
+    /// fresh one would.
+    pub async fn reset(&self) -> Result<(), WebDriverError> {
+        // Cookies can only be deleted for the page that is open, so before leaving it. An
+        // error page has none, which is no reason to give up on the session.
+        let _ = self.driver().delete_all_cookies().await;
+        self.driver().goto("about:blank").await
+    }
+
     /// Closes the browser and quits the WebDriver session.
     ///
     /// This should be called to clean up resources when the emulator is no longer needed.
@@ -124,12 +557,211 @@ impl BrowserEmulator {
     /// # Returns
     ///
     /// A `Result` indicating success or a `WebDriverError` if quitting fails.
-    pub async fn close(&self) -> Result<(), WebDriverError> {
-        self.driver.quit().await?;
+    pub async fn close(mut self) -> Result<(), WebDriverError> {
+        let driver = self.driver.take().expect("driver is only taken when the emulator is closed");
+        let tracking = self.tracking.take();
+        driver.quit().await?;
+        forget(tracking);
         Ok(())
     }
 }
 
+impl Drop for BrowserEmulator {
+    fn drop(&mut self) {
+        let Some(driver) = self.driver.take() else {
+            return;
+        };
+        let tracking = self.tracking.take();
+        // Without a runtime we can't quit here; the session stays in the registry and the
+        // reaper kills it on the next start.
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            runtime.spawn(async move {
+                if driver.quit().await.is_ok() {
+                    forget(tracking);
+                }
+            });
+        }
+    }
+}
+
+fn forget(tracking: Option<(SessionRegistry, String)>) {
+    if let Some((registry, session_id)) = tracking
+        && let Err(e) = registry.forget(&session_id)
+    {
+        eprintln!("Error updating WebDriver session registry: {}", e);
+    }
+}
+
+/// Keeps up to `size` WebDriver sessions open and lends them to checks, instead of starting
+/// and quitting a browser for every check. Sessions are reset before each use; one that no
+/// longer answers is replaced with a new one.
+pub struct SessionPool {
+    webdriver_url: String,
+    browser: BrowserKind,
+    headless: bool,
+    size: usize,
+    registry: Option<SessionRegistry>,
+    idle: Mutex<Vec<BrowserEmulator>>,
+    // One permit per session that may be lent out at once.
+    permits: Arc<Semaphore>,
+}
+
+impl SessionPool {
+    /// Creates an empty pool; sessions are opened on first use or by `warm`.
+    ///
+    /// # Arguments
+    ///
+    /// * `webdriver_url`: The URL of the WebDriver server sessions are opened on.
+    /// * `browser`: The browser every session drives.
+    /// * `headless`: If `true`, runs without a window where the browser supports it.
+    /// * `size`: How many sessions may be open at once; at least one.
+    pub fn new(webdriver_url: &str, browser: BrowserKind, headless: bool, size: usize) -> Arc<Self> {
+        let size = size.max(1);
+        Arc::new(Self {
+            webdriver_url: webdriver_url.to_string(),
+            browser,
+            headless,
+            size,
+            registry: None,
+            idle: Mutex::new(Vec::new()),
+            permits: Arc::new(Semaphore::new(size)),
+        })
+    }
+
+    /// Like `new`, but records every session in `registry` for the orphan reaper.
+    pub fn with_registry(webdriver_url: &str, browser: BrowserKind, headless: bool, size: usize, registry: SessionRegistry) -> Arc<Self> {
+        let mut pool = Self::new(webdriver_url, browser, headless, size);
+        Arc::get_mut(&mut pool).expect("the pool was just created").registry = Some(registry);
+        pool
+    }
+
+    async fn open(&self) -> Result<BrowserEmulator, WebDriverError> {
+        let mut emulator = BrowserEmulator::with_browser(&self.webdriver_url, self.browser, self.headless).await?;
+        if let Some(registry) = &self.registry
+            && let Err(e) = emulator.track(registry.clone(), &self.webdriver_url).await
+        {
+            eprintln!("Error recording WebDriver session: {}", e);
+        }
+        Ok(emulator)
+    }
+
+    fn take_idle(&self) -> Option<BrowserEmulator> {
+        self.idle.lock().unwrap().pop()
+    }
+
+    /// Opens sessions until `size` are idle, so the first checks don't wait for a browser.
+    pub async fn warm(&self) -> Result<(), WebDriverError> {
+        while self.idle.lock().unwrap().len() < self.size {
+            let emulator = self.open().await?;
+            self.idle.lock().unwrap().push(emulator);
+        }
+        Ok(())
+    }
+
+    /// Lends out a session, waiting while all `size` are in use. Idle sessions that fail
+    /// their reset are dropped (which quits them) and a new one is opened instead.
+    pub async fn checkout(self: &Arc<Self>) -> Result<PooledSession, WebDriverError> {
+        let permit = self.permits.clone().acquire_owned().await.expect("the pool never closes its semaphore");
+        while let Some(emulator) = self.take_idle() {
+            match emulator.reset().await {
+                Ok(()) => return Ok(self.lend(emulator, permit)),
+                Err(e) => eprintln!("Replacing dead WebDriver session: {}", e),
+            }
+        }
+        let emulator = self.open().await?;
+        Ok(self.lend(emulator, permit))
+    }
+
+    fn lend(self: &Arc<Self>, emulator: BrowserEmulator, permit: OwnedSemaphorePermit) -> PooledSession {
+        PooledSession {
+            emulator: Some(emulator),
+            pool: self.clone(),
+            _permit: permit,
+        }
+    }
+
+    /// Resets every idle session and replaces those that fail. Returns how many were
+    /// replaced; meant to run on a timer, see `maintain`.
+    pub async fn health_check(&self) -> Result<usize, WebDriverError> {
+        let sessions = std::mem::take(&mut *self.idle.lock().unwrap());
+        let mut replaced = 0;
+        for emulator in sessions {
+            let emulator = match emulator.reset().await {
+                Ok(()) => emulator,
+                Err(e) => {
+                    eprintln!("Replacing dead WebDriver session: {}", e);
+                    replaced += 1;
+                    drop(emulator);
+                    self.open().await?
+                }
+            };
+            self.give_back(emulator);
+        }
+        Ok(replaced)
+    }
+
+    /// Runs `health_check` every `every` until the task is dropped.
+    pub async fn maintain(self: Arc<Self>, every: Duration) {
+        let mut timer = tokio::time::interval(every);
+        timer.tick().await;
+        loop {
+            timer.tick().await;
+            if let Err(e) = self.health_check().await {
+                eprintln!("Cannot replace WebDriver session: {}", e);
+            }
+        }
+    }
+
+    // Sessions beyond `size` (opened while a health check held the idle ones) are quit.
+    fn give_back(&self, emulator: BrowserEmulator) {
+        let mut idle = self.idle.lock().unwrap();
+        if idle.len() < self.size {
+            idle.push(emulator);
+        }
+    }
+
+    /// Quits the idle sessions. Sessions still lent out are quit when they are dropped.
+    pub async fn close(&self) {
+        let sessions = std::mem::take(&mut *self.idle.lock().unwrap());
+        for emulator in sessions {
+            if let Err(e) = emulator.close().await {
+                eprintln!("Error closing browser: {:?}", e);
+            }
+        }
+    }
+}
+
+/// A session lent out by a `SessionPool`; goes back to the pool when dropped.
+pub struct PooledSession {
+    // Only `None` once `discard` or `drop` has taken it.
+    emulator: Option<BrowserEmulator>,
+    pool: Arc<SessionPool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PooledSession {
+    /// Quits the session instead of returning it, e.g. after the browser misbehaved.
+    pub fn discard(mut self) {
+        self.emulator.take();
+    }
+}
+
+impl Deref for PooledSession {
+    type Target = BrowserEmulator;
+
+    fn deref(&self) -> &BrowserEmulator {
+        self.emulator.as_ref().expect("the session is only taken when it is returned")
+    }
+}
+
+impl Drop for PooledSession {
+    fn drop(&mut self) {
+        if let Some(emulator) = self.emulator.take() {
+            self.pool.give_back(emulator);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -141,6 +773,29 @@ mod tests {
     // To run chromedriver: `chromedriver --port=4444`
     const WEBDRIVER_URL: &str = "http://localhost:4444";
 
+    #[test]
+    fn test_browser_capability_profiles() {
+        let edge = BrowserKind::Edge.capabilities(true).unwrap();
+        assert_eq!(edge["browserName"], "MicrosoftEdge");
+        let args = edge["ms:edgeOptions"]["args"].as_array().unwrap();
+        assert!(args.contains(&json!("--headless=new")));
+        assert!(!BrowserKind::Edge.capabilities(false).unwrap()["ms:edgeOptions"]["args"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("--headless=new")));
+
+        let safari = BrowserKind::Safari.capabilities(true).unwrap();
+        assert_eq!(safari["browserName"], "safari");
+        assert!(!serde_json::Value::Object(safari).to_string().contains("headless"));
+
+        let firefox = BrowserKind::Firefox.capabilities(true).unwrap();
+        assert_eq!(firefox["browserName"], "firefox");
+        assert!(firefox["moz:firefoxOptions"]["args"].as_array().unwrap().contains(&json!("--headless")));
+
+        assert_eq!("msedge".parse::<BrowserKind>(), Ok(BrowserKind::Edge));
+        assert!("netscape".parse::<BrowserKind>().is_err());
+    }
+
     #[tokio::test]
     #[ignore] // Ignored because it requires an external WebDriver server
     async fn test_can_launch_browser_and_navigate() {
@@ -170,6 +825,76 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_transaction_steps_from_toml() {
+        let transaction: Transaction = toml::from_str(
+            r##"
+            name = "login"
+            step_timeout = "10s"
+
+            [[step]]
+            action = "goto"
+            url = "https://example.com/login"
+
+            [[step]]
+            name = "password"
+            action = "fill"
+            selector = "#password"
+            value_env = "LOGIN_PASSWORD"
+
+            [[step]]
+            action = "assert_text"
+            text = "Welcome"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(transaction.step_timeout, Duration::from_secs(10));
+        let labels: Vec<String> = transaction.steps.iter().map(TransactionStep::label).collect();
+        assert_eq!(labels, ["goto https://example.com/login", "password", "assert text 'Welcome'"]);
+        assert_eq!(
+            transaction.steps[1].action,
+            TransactionAction::Fill { selector: "#password".to_string(), value: String::new(), value_env: Some("LOGIN_PASSWORD".to_string()) }
+        );
+        assert!(toml::from_str::<Transaction>("name = \"x\"\n[[step]]\naction = \"hover\"").is_err());
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignored because it requires an external WebDriver server
+    async fn test_transaction_stops_at_the_first_failing_step() {
+        let transaction: Transaction = toml::from_str(
+            "name = \"example\"\nstep_timeout = \"5s\"\n\
+             [[step]]\naction = \"goto\"\nurl = \"https://www.example.com\"\n\
+             [[step]]\naction = \"assert_text\"\nselector = \"h1\"\ntext = \"Example Domain\"\n\
+             [[step]]\naction = \"click\"\nselector = \"#missing\"\n\
+             [[step]]\naction = \"wait_for\"\nselector = \"h1\"",
+        )
+        .unwrap();
+        let emulator = BrowserEmulator::new(WEBDRIVER_URL, true).await.unwrap();
+        let result = emulator.run_transaction(&transaction).await;
+        let statuses: Vec<StepStatus> = result.steps.iter().map(|s| s.status).collect();
+        assert_eq!(statuses, [StepStatus::Passed, StepStatus::Passed, StepStatus::Failed, StepStatus::Skipped]);
+        assert_eq!(result.failure_kind, Some(FailureKind::BrowserTimeout));
+        emulator.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignored because it requires an external WebDriver server
+    async fn test_pool_reuses_sessions() {
+        let pool = SessionPool::new(WEBDRIVER_URL, BrowserKind::Chrome, true, 1);
+        pool.warm().await.unwrap();
+        let first = pool.checkout().await.unwrap().driver().session_id().await.unwrap();
+        assert!(pool.checkout().await.unwrap().measure_load_time("https://www.example.com", Some("h1")).await.is_ok());
+        let session = pool.checkout().await.unwrap();
+        assert_eq!(session.driver().session_id().await.unwrap(), first);
+
+        // A session that was quit behind the pool's back is replaced by the health check.
+        session.driver().clone().quit().await.unwrap();
+        drop(session);
+        assert_eq!(pool.health_check().await.unwrap(), 1);
+        assert_ne!(pool.checkout().await.unwrap().driver().session_id().await.unwrap(), first);
+        pool.close().await;
+    }
+
     #[tokio::test]
     #[ignore] // Ignored because it requires an external WebDriver server
     async fn test_new_emulator_error_if_webdriver_not_running() {
@@ -0,0 +1,193 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// What a team may use per accounting period. `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quota {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_checks: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_browser_minutes: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckCost {
+    /// Cheap network probe (TCP, HTTP, DNS, ...).
+    Probe,
+    /// Runs a real browser; its run time is charged afterwards with `charge_browser_time`.
+    Browser,
+}
+
+/// Usage of one team in the current period.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Usage {
+    pub checks: u64,
+    pub browser_minutes: f64,
+    /// Checks refused because the quota was used up.
+    pub rejected: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetExceeded {
+    pub team: String,
+    pub reason: String,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "budget of team '{}' exhausted: {}", self.team, self.reason)
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// Counts check executions and browser minutes per team (workspace or API key) and refuses
+/// checks once a team's quota for the current period is used up, so one team's burst of
+/// browser checks can't starve everyone else's monitoring.
+pub struct BudgetLedger {
+    period: ChronoDuration,
+    default_quota: Quota,
+    quotas: HashMap<String, Quota>,
+    usage: HashMap<String, Usage>,
+    period_start: Option<DateTime<Utc>>,
+}
+
+impl BudgetLedger {
+    pub fn new(period: ChronoDuration, default_quota: Quota) -> Self {
+        Self {
+            period,
+            default_quota,
+            quotas: HashMap::new(),
+            usage: HashMap::new(),
+            period_start: None,
+        }
+    }
+
+    pub fn set_quota(&mut self, team: &str, quota: Quota) {
+        self.quotas.insert(team.to_string(), quota);
+    }
+
+    /// Books one check for `team`, or refuses it when the quota is used up.
+    pub fn reserve(&mut self, team: &str, cost: CheckCost, now: DateTime<Utc>) -> Result<(), BudgetExceeded> {
+        self.roll_period(now);
+        let quota = self.quotas.get(team).unwrap_or(&self.default_quota).clone();
+        let usage = self.usage.entry(team.to_string()).or_default();
+
+        let refusal = if quota.max_checks.is_some_and(|max| usage.checks >= max) {
+            Some(format!("{} checks this period", usage.checks))
+        } else if cost == CheckCost::Browser && quota.max_browser_minutes.is_some_and(|max| usage.browser_minutes >= max) {
+            Some(format!("{:.1} browser minutes this period", usage.browser_minutes))
+        } else {
+            None
+        };
+        if let Some(reason) = refusal {
+            usage.rejected += 1;
+            return Err(BudgetExceeded {
+                team: team.to_string(),
+                reason,
+            });
+        }
+        usage.checks += 1;
+        Ok(())
+    }
+
+    /// Charges the run time of a finished browser check.
+    pub fn charge_browser_time(&mut self, team: &str, elapsed: Duration, now: DateTime<Utc>) {
+        self.roll_period(now);
+        self.usage.entry(team.to_string()).or_default().browser_minutes += elapsed.as_secs_f64() / 60.0;
+    }
+
+    /// Usage of every team in the current period, sorted by team.
+    pub fn report(&self) -> Vec<(String, Usage)> {
+        let mut report: Vec<_> = self.usage.iter().map(|(team, usage)| (team.clone(), usage.clone())).collect();
+        report.sort_by(|a, b| a.0.cmp(&b.0));
+        report
+    }
+
+    fn roll_period(&mut self, now: DateTime<Utc>) {
+        match self.period_start {
+            Some(start) if now - start < self.period => {}
+            _ => {
+                self.period_start = Some(now);
+                self.usage.clear();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_browser_minutes_quota_blocks_only_browser_checks() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 9, 0, 0).unwrap();
+        let mut ledger = BudgetLedger::new(ChronoDuration::hours(1), Quota::default());
+        ledger.set_quota(
+            "web-team",
+            Quota {
+                max_checks: Some(100),
+                max_browser_minutes: Some(1.0),
+            },
+        );
+
+        ledger.reserve("web-team", CheckCost::Browser, now).unwrap();
+        ledger.charge_browser_time("web-team", Duration::from_secs(75), now);
+        let refused = ledger.reserve("web-team", CheckCost::Browser, now).unwrap_err();
+        assert!(refused.to_string().contains("browser minutes"));
+        ledger.reserve("web-team", CheckCost::Probe, now).unwrap();
+        // Teams without a quota of their own fall back to the (unlimited) default.
+        ledger.reserve("db-team", CheckCost::Browser, now).unwrap();
+
+        let report = ledger.report();
+        assert_eq!((report[1].0.as_str(), report[1].1.checks, report[1].1.rejected), ("web-team", 2, 1));
+
+        // A new period starts from zero.
+        ledger.reserve("web-team", CheckCost::Browser, now + ChronoDuration::hours(1)).unwrap();
+        let report = ledger.report();
+        assert_eq!((report.len(), report[0].1.checks), (1, 1));
+    }
+
+    #[test]
+    fn test_check_count_quota() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 9, 0, 0).unwrap();
+        let mut ledger = BudgetLedger::new(
+            ChronoDuration::days(1),
+            Quota {
+                max_checks: Some(2),
+                max_browser_minutes: None,
+            },
+        );
+        assert!(ledger.reserve("ops", CheckCost::Probe, now).is_ok());
+        assert!(ledger.reserve("ops", CheckCost::Probe, now).is_ok());
+        assert!(ledger.reserve("ops", CheckCost::Probe, now).is_err());
+        assert_eq!(ledger.report(), vec![("ops".to_string(), Usage { checks: 2, browser_minutes: 0.0, rejected: 1 })]);
+    }
+
+    #[tokio::test]
+    async fn test_monitor_refuses_checks_of_owners_over_budget() {
+        use crate::back_end::event_bus::EventBus;
+        use crate::back_end::monitor::Monitor;
+        use crate::back_end::target::MonitorTarget;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let mut ledger = BudgetLedger::new(ChronoDuration::days(1), Quota::default());
+        ledger.set_quota("ops", Quota { max_checks: Some(1), max_browser_minutes: None });
+        monitor.set_budget(ledger);
+        monitor.add_monitor_target(MonitorTarget { owner: Some("ops".to_string()), ..MonitorTarget::new(addr) }).unwrap();
+
+        assert!(monitor.run_check(addr).await.success);
+        let refused = monitor.run_check(addr).await;
+        assert!(refused.error.unwrap().starts_with("budget of team 'ops' exhausted"));
+        // The refusal says nothing about the target, which stays up.
+        assert_eq!(monitor.budget_report(), vec![("ops".to_string(), Usage { checks: 1, browser_minutes: 0.0, rejected: 1 })]);
+        assert_eq!(monitor.state(addr), Some(crate::back_end::state_tracker::TargetState::Up));
+    }
+}
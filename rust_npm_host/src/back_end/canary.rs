@@ -0,0 +1,149 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+
+use super::check_result::CheckResult;
+
+/// Anycast resolvers that are reachable whenever the internet is; given as addresses so
+/// a broken local resolver doesn't make them look down.
+pub const DEFAULT_CANARIES: [&str; 3] = ["1.1.1.1:443", "8.8.8.8:443", "9.9.9.9:443"];
+
+/// Appended to the errors of failures that happened while the monitor itself was offline;
+/// they don't count towards a target's outages.
+pub const OFFLINE_NOTE: &str = "(local connectivity lost)";
+
+/// How long one verdict on the canaries is reused, so a wave of failing targets probes
+/// them once rather than once per target.
+const VERDICT_TTL: Duration = Duration::from_secs(15);
+
+/// The monitor lost or regained its own way out to the internet.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConnectivityChange {
+    pub online: bool,
+    pub at: DateTime<Utc>,
+    pub canaries: Vec<String>,
+    /// On recovery: failed checks of external targets that were put down to the outage
+    /// instead of alerting.
+    pub held_back: usize,
+}
+
+impl fmt::Display for ConnectivityChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.online {
+            write!(f, "Local connectivity restored; {} failed checks of external targets were not alerted on", self.held_back)
+        } else {
+            write!(
+                f,
+                "Local connectivity LOST: none of the canaries ({}) is reachable; alerts for external targets are held back",
+                self.canaries.join(", ")
+            )
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CanaryState {
+    verdict: Option<(Instant, bool)>,
+    offline: bool,
+    held_back: usize,
+}
+
+/// Endpoints outside our network that tell a local uplink failure apart from many
+/// targets failing at once. A target counts as down only if at least one canary still
+/// accepts TCP connections.
+#[derive(Debug)]
+pub struct Canaries {
+    endpoints: Vec<String>,
+    timeout: Duration,
+    state: Mutex<CanaryState>,
+}
+
+impl Canaries {
+    /// `endpoints` are `host:port`; empty means `DEFAULT_CANARIES`.
+    pub fn new(endpoints: Vec<String>, timeout: Duration) -> Self {
+        let endpoints = if endpoints.is_empty() { DEFAULT_CANARIES.iter().map(|e| e.to_string()).collect() } else { endpoints };
+        Self { endpoints, timeout, state: Mutex::new(CanaryState::default()) }
+    }
+
+    /// Whether any canary accepts a connection.
+    async fn probe(&self) -> bool {
+        let attempts = self.endpoints.iter().map(|endpoint| tokio::time::timeout(self.timeout, TcpStream::connect(endpoint.as_str())));
+        futures::future::join_all(attempts).await.iter().any(|attempt| matches!(attempt, Ok(Ok(_))))
+    }
+
+    /// Weighs a check result of an external target. Returns whether its failure is down to
+    /// the monitor being offline, and the change to announce if connectivity flipped.
+    pub async fn judge(&self, result: &CheckResult) -> (bool, Option<ConnectivityChange>) {
+        let mut state = self.state.lock().await;
+        let online = if result.success {
+            // Reaching any target proves the way out works.
+            true
+        } else {
+            match state.verdict {
+                Some((at, online)) if at.elapsed() < VERDICT_TTL => online,
+                _ => {
+                    let online = self.probe().await;
+                    state.verdict = Some((Instant::now(), online));
+                    online
+                }
+            }
+        };
+        if !online {
+            state.held_back += 1;
+        }
+        if state.offline != online {
+            return (!online, None);
+        }
+        state.offline = !online;
+        let mut held_back = 0;
+        if online {
+            state.verdict = None;
+            held_back = std::mem::take(&mut state.held_back);
+        }
+        let change = ConnectivityChange { online, at: Utc::now(), canaries: self.endpoints.clone(), held_back };
+        (!online, Some(change))
+    }
+}
+
+/// Whether `ip` is reached through the uplink, as opposed to the local network.
+pub fn is_external(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let shared = ip.octets()[0] == 100 && (ip.octets()[1] & 0xc0) == 64;
+            !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || shared)
+        }
+        IpAddr::V6(ip) => !(ip.is_loopback() || ip.is_unspecified() || ip.is_unique_local() || ip.is_unicast_link_local()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_failures_are_held_back_while_canaries_are_down() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let up = Canaries::new(vec![listener.local_addr().unwrap().to_string()], Duration::from_secs(1));
+        assert_eq!(up.judge(&CheckResult::failure("203.0.113.7:443", "timed out")).await, (false, None));
+
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let down = Canaries::new(vec![closed.to_string()], Duration::from_millis(200));
+        let (local, change) = down.judge(&CheckResult::failure("203.0.113.7:443", "timed out")).await;
+        assert!(local && !change.unwrap().online);
+        // Alerted once; further failures are held back quietly.
+        assert_eq!(down.judge(&CheckResult::failure("198.51.100.2:80", "timed out")).await, (true, None));
+        let (local, change) = down.judge(&CheckResult::success("198.51.100.2:80", Duration::from_millis(9))).await;
+        let change = change.unwrap();
+        assert!(!local && change.online);
+        assert_eq!(change.held_back, 2);
+
+        assert!(is_external("203.0.113.7".parse().unwrap()));
+        assert!(!is_external("192.168.1.10".parse().unwrap()));
+        assert!(!is_external("fd00::1".parse().unwrap()));
+    }
+}
@@ -0,0 +1,233 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::event_bus::MonitorEvent;
+use super::monitor::Monitor;
+
+/// Whether this build can capture packets at all.
+pub const AVAILABLE: bool = cfg!(feature = "packet-capture");
+
+// Captures running at once; outages of many targets shouldn't turn into a capture storm.
+const MAX_CONCURRENT_CAPTURES: usize = 2;
+
+/// When and how much to capture. Captures stop at whichever limit is reached first.
+#[derive(Debug, Clone)]
+#[cfg_attr(not(feature = "packet-capture"), allow(dead_code))]
+pub struct CaptureConfig {
+    /// Where `.pcap` files are written.
+    pub dir: PathBuf,
+    /// Consecutive failures of a target before its traffic is captured.
+    pub after_failures: u32,
+    pub duration: Duration,
+    pub max_packets: usize,
+    /// Bytes kept of each packet; headers are usually all that's needed.
+    pub snaplen: i32,
+    /// Interface to capture on; `None` uses the default one.
+    pub interface: Option<String>,
+}
+
+impl CaptureConfig {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            after_failures: 3,
+            duration: Duration::from_secs(10),
+            max_packets: 1000,
+            snaplen: 256,
+            interface: None,
+        }
+    }
+}
+
+/// Decides when to capture: once per outage, when a target reaches the failure threshold.
+#[derive(Debug)]
+pub struct CaptureTrigger {
+    after_failures: u32,
+    failures: HashMap<String, u32>,
+}
+
+impl CaptureTrigger {
+    pub fn new(after_failures: u32) -> Self {
+        Self {
+            after_failures: after_failures.max(1),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Records one result; true exactly when this failure is the one to capture on.
+    pub fn record(&mut self, target: &str, success: bool) -> bool {
+        if success {
+            self.failures.remove(target);
+            return false;
+        }
+        let count = self.failures.entry(target.to_string()).or_default();
+        *count += 1;
+        *count == self.after_failures
+    }
+}
+
+/// Capture filter for traffic to and from `addr`.
+#[cfg_attr(not(feature = "packet-capture"), allow(dead_code))]
+pub fn bpf_filter(addr: SocketAddr) -> String {
+    format!("host {} and port {}", addr.ip(), addr.port())
+}
+
+/// `<dir>/<target>_<time>.pcap`, with the target made safe for file names.
+pub fn capture_path(dir: &Path, addr: SocketAddr, at: DateTime<Utc>) -> PathBuf {
+    dir.join(format!("{}_{}.pcap", file_safe(addr), at.format(CAPTURE_TIME_FORMAT)))
+}
+
+const CAPTURE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+fn file_safe(addr: SocketAddr) -> String {
+    addr.to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' })
+        .collect()
+}
+
+/// Captures of `addr` in `dir` started between `from` and `to`, oldest first, going by the
+/// names `capture_path` gives them.
+pub fn captures_between(dir: &Path, addr: SocketAddr, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<PathBuf> {
+    let prefix = format!("{}_", file_safe(addr));
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut captures: Vec<(DateTime<Utc>, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let stamp = name.strip_prefix(&prefix)?.strip_suffix(".pcap")?;
+            let at = chrono::NaiveDateTime::parse_from_str(stamp, CAPTURE_TIME_FORMAT).ok()?.and_utc();
+            (from <= at && at <= to).then(|| (at, entry.path()))
+        })
+        .collect();
+    captures.sort();
+    captures.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Captures traffic of `addr` into `path` until a limit of `config` is reached and returns
+/// the number of packets written. Blocks; needs permission to capture (root or
+/// CAP_NET_RAW on Linux).
+#[cfg(feature = "packet-capture")]
+pub fn capture(config: &CaptureConfig, addr: SocketAddr, path: &Path) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let device = match &config.interface {
+        Some(name) => pcap::Device::from(name.as_str()),
+        None => pcap::Device::lookup()?.ok_or("no capture device found")?,
+    };
+    let mut capture = pcap::Capture::from_device(device)?
+        .snaplen(config.snaplen)
+        // Wake up now and then so the time limit holds on a quiet link.
+        .timeout(250)
+        .immediate_mode(true)
+        .open()?;
+    capture.filter(&bpf_filter(addr), true)?;
+    let mut file = capture.savefile(path)?;
+    let deadline = std::time::Instant::now() + config.duration;
+    let mut packets = 0;
+    while packets < config.max_packets && std::time::Instant::now() < deadline {
+        match capture.next_packet() {
+            Ok(packet) => {
+                file.write(&packet);
+                packets += 1;
+            }
+            Err(pcap::Error::TimeoutExpired) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    file.flush()?;
+    Ok(packets)
+}
+
+#[cfg(not(feature = "packet-capture"))]
+pub fn capture(_config: &CaptureConfig, _addr: SocketAddr, _path: &Path) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    Err("built without the packet-capture feature".into())
+}
+
+/// Captures the traffic of targets that keep failing and announces each saved file with
+/// `MonitorEvent::CaptureSaved`, until the event bus closes.
+pub async fn watch(monitor: Arc<Monitor>, config: CaptureConfig) {
+    let config = Arc::new(config);
+    let slots = Arc::new(Semaphore::new(MAX_CONCURRENT_CAPTURES));
+    let mut trigger = CaptureTrigger::new(config.after_failures);
+    let mut events = monitor.bus().subscribe();
+    loop {
+        let result = match events.recv().await {
+            Ok(MonitorEvent::CheckCompleted(result)) => result,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(missed)) => {
+                eprintln!("Packet capture missed {} events", missed);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        // Only targets given by address can be filtered on; URLs and the like are skipped.
+        let Ok(addr) = result.target.parse::<SocketAddr>() else {
+            continue;
+        };
+        if !trigger.record(&result.target, result.success) {
+            continue;
+        }
+        let Ok(slot) = slots.clone().try_acquire_owned() else {
+            eprintln!("Not capturing {}: {} captures already running", addr, MAX_CONCURRENT_CAPTURES);
+            continue;
+        };
+        let (monitor, config) = (monitor.clone(), config.clone());
+        let correlation_id = result.correlation_id.clone();
+        tokio::spawn(async move {
+            let path = capture_path(&config.dir, addr, Utc::now());
+            let run = {
+                let (config, path) = (config.clone(), path.clone());
+                tokio::task::spawn_blocking(move || {
+                    std::fs::create_dir_all(&config.dir)?;
+                    capture(&config, addr, &path)
+                })
+            };
+            match run.await {
+                Ok(Ok(packets)) => monitor.bus().publish(MonitorEvent::CaptureSaved {
+                    target: addr,
+                    path,
+                    packets,
+                    correlation_id,
+                }),
+                Ok(Err(e)) => eprintln!("Packet capture of {} failed: {}", addr, e),
+                Err(e) => eprintln!("Packet capture of {} failed: {}", addr, e),
+            }
+            drop(slot);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_trigger_fires_once_per_outage() {
+        let mut trigger = CaptureTrigger::new(3);
+        let fired: Vec<bool> = [false, false, false, false, true, false, false, false]
+            .iter()
+            .map(|ok| trigger.record("db:5432", *ok))
+            .collect();
+        assert_eq!(fired, [false, false, true, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn test_filter_and_path() {
+        let addr: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        assert_eq!(bpf_filter(addr), "host 2001:db8::1 and port 443");
+        let at = Utc.with_ymd_and_hms(2026, 10, 17, 8, 30, 0).unwrap();
+        assert_eq!(
+            capture_path(Path::new("/tmp/captures"), "10.0.0.5:443".parse().unwrap(), at),
+            Path::new("/tmp/captures/10.0.0.5_443_20261017T083000Z.pcap")
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::clock::CLOCK_SKEW_METRIC;
+use super::diagnostics::Hint;
+use super::failure_kind::FailureKind;
+use super::metadata::Metadata;
+use super::page_errors::PageError;
+use super::result_payload::Artifact;
+use super::severity::Severity;
+
+/// The outcome of a single check run against a target.
+///
+/// This is the common record that the checks produce and that the analysis,
+/// storage and notification code consume.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    /// Identifier of the checked target (e.g. "192.168.1.1:80" or a URL).
+    pub target: String,
+    /// When the check completed.
+    pub timestamp: DateTime<Utc>,
+    /// Whether the target was considered up.
+    pub success: bool,
+    /// Measured latency, if the check got far enough to measure one.
+    pub latency: Option<Duration>,
+    /// Human readable error when the check failed.
+    pub error: Option<String>,
+    /// Additional named measurements, including derived metrics computed on ingest.
+    #[serde(default)]
+    pub metrics: BTreeMap<String, f64>,
+    /// Identifies the check run that produced this result. Alerts, notifications and log
+    /// lines carry the same ID so they can be traced back to the exact probe.
+    #[serde(default)]
+    pub correlation_id: String,
+    /// Address a host name resolved to for this check, so latency shifts can be matched
+    /// with DNS changes. `None` for targets given as IP addresses.
+    #[serde(default)]
+    pub resolved_ip: Option<IpAddr>,
+    /// Per-step outcomes of multi-step checks (composite checks, browser journeys), in the
+    /// order the steps ran. Empty for single-probe checks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub steps: Vec<StepResult>,
+    /// Remote agent (vantage point) that ran the check; `None` when it ran on this host.
+    #[serde(default)]
+    pub agent: Option<String>,
+    /// Why the check failed; `None` for successful checks.
+    #[serde(default)]
+    pub failure_kind: Option<FailureKind>,
+    /// What to check when the failure looks like a setup problem on our side.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<Hint>,
+    /// Console errors and failed requests of a page a browser check loaded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub page_errors: Vec<PageError>,
+    /// Large attachments, such as the HAR file of a browser check's page load.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artifacts: Vec<Artifact>,
+    /// The target's user-defined metadata, passed through unchanged.
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// Custom status a script hook gave the result, e.g. "degraded".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    /// Alert severity a script hook chose in place of the built-in one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    /// Not run because an earlier step failed.
+    Skipped,
+}
+
+/// Outcome of one step of a multi-step check.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StepResult {
+    pub name: String,
+    pub status: StepStatus,
+    pub latency: Option<Duration>,
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    pub fn success(target: &str, latency: Duration) -> Self {
+        Self {
+            target: target.to_string(),
+            timestamp: Utc::now(),
+            success: true,
+            latency: Some(latency),
+            error: None,
+            metrics: BTreeMap::new(),
+            correlation_id: new_correlation_id(),
+            resolved_ip: None,
+            steps: Vec::new(),
+            agent: None,
+            failure_kind: None,
+            hint: None,
+            page_errors: Vec::new(),
+            artifacts: Vec::new(),
+            metadata: Metadata::new(),
+            status: None,
+            severity: None,
+        }
+    }
+
+    /// A failed result; the failure kind is guessed from the message and can be overridden
+    /// with `with_failure_kind` by checks that know better.
+    pub fn failure(target: &str, error: impl Into<String>) -> Self {
+        let error = error.into();
+        Self {
+            target: target.to_string(),
+            timestamp: Utc::now(),
+            success: false,
+            latency: None,
+            failure_kind: Some(FailureKind::classify(&error)),
+            hint: Hint::diagnose(&error),
+            page_errors: Vec::new(),
+            artifacts: Vec::new(),
+            error: Some(error),
+            metrics: BTreeMap::new(),
+            correlation_id: new_correlation_id(),
+            resolved_ip: None,
+            steps: Vec::new(),
+            agent: None,
+            metadata: Metadata::new(),
+            status: None,
+            severity: None,
+        }
+    }
+
+    /// One result for `target` out of the results of its parts (services, units, mount
+    /// points, ...), each kept as a step: up only if every part is, as slow as the slowest.
+    pub fn from_steps(target: &str, parts: Vec<CheckResult>) -> Self {
+        let latency = parts.iter().filter_map(|part| part.latency).max();
+        let failed = parts.iter().find(|part| !part.success);
+        let mut result = match failed {
+            None => Self::success(target, latency.unwrap_or_default()),
+            Some(part) => Self {
+                latency,
+                failure_kind: part.failure_kind,
+                ..Self::failure(target, format!("{}: {}", part.target, part.error.as_deref().unwrap_or("failed")))
+            },
+        };
+        result.steps = parts
+            .into_iter()
+            .map(|part| StepResult {
+                status: if part.success { StepStatus::Passed } else { StepStatus::Failed },
+                name: part.target,
+                latency: part.latency,
+                error: part.error,
+            })
+            .collect();
+        result
+    }
+
+    /// Tags the result with the ID of the run it belongs to, for checks that produce
+    /// several results per run (one per unit, mount point, ...).
+    pub fn in_run(mut self, correlation_id: &str) -> Self {
+        self.correlation_id = correlation_id.to_string();
+        self
+    }
+
+    pub fn with_failure_kind(mut self, kind: FailureKind) -> Self {
+        self.failure_kind = Some(kind);
+        self
+    }
+
+    pub fn with_agent(mut self, agent: &str) -> Self {
+        self.agent = Some(agent.to_string());
+        self
+    }
+
+    /// Latency in milliseconds, handy for reports and statistics.
+    pub fn latency_ms(&self) -> Option<f64> {
+        self.latency.map(|d| d.as_secs_f64() * 1000.0)
+    }
+
+    /// False when the system clock jumped while the check ran, see `clock::ClockGuard`.
+    pub fn is_reliable(&self) -> bool {
+        !self.metrics.contains_key(CLOCK_SKEW_METRIC)
+    }
+}
+
+/// A fresh ID for one scheduled check run.
+pub fn new_correlation_id() -> String {
+    Uuid::new_v4().simple().to_string()
+}
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::time::Instant;
+
+use super::check_result::CheckResult;
+
+/// Metric set on results whose check saw the system clock jump, with the jump in
+/// milliseconds (negative when the clock went back).
+pub const CLOCK_SKEW_METRIC: &str = "clock_skew_ms";
+
+// Drift between the clocks below this is NTP slewing or scheduling noise, not a step.
+const SKEW_TOLERANCE: ChronoDuration = ChronoDuration::milliseconds(500);
+
+/// Reads the wall clock and the monotonic clock together when a check starts, to tell
+/// afterwards whether the wall clock was stepped (e.g. by NTP) while it ran. Latencies are
+/// always measured on the monotonic clock; a step only makes the result's timestamp and
+/// anything derived from wall-clock times unreliable.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockGuard {
+    wall: DateTime<Utc>,
+    mono: Instant,
+}
+
+impl ClockGuard {
+    pub fn start() -> Self {
+        Self {
+            wall: Utc::now(),
+            mono: Instant::now(),
+        }
+    }
+
+    /// How much further the wall clock moved than the monotonic clock between the start and
+    /// (`wall_now`, `mono_now`).
+    pub fn skew_between(&self, wall_now: DateTime<Utc>, mono_now: Instant) -> ChronoDuration {
+        let mono = ChronoDuration::from_std(mono_now.saturating_duration_since(self.mono)).unwrap_or(ChronoDuration::MAX);
+        (wall_now - self.wall) - mono
+    }
+
+    /// Flags `result` as unreliable if the wall clock jumped since the start.
+    pub fn check(&self, result: &mut CheckResult) {
+        let skew = self.skew_between(Utc::now(), Instant::now());
+        if skew.abs() > SKEW_TOLERANCE {
+            result.metrics.insert(CLOCK_SKEW_METRIC.to_string(), skew.num_milliseconds() as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_steps_are_flagged_but_slewing_is_not() {
+        let guard = ClockGuard::start();
+        let later = guard.mono + Duration::from_secs(2);
+        assert_eq!(guard.skew_between(guard.wall + ChronoDuration::seconds(2), later), ChronoDuration::zero());
+        assert_eq!(guard.skew_between(guard.wall - ChronoDuration::seconds(58), later), ChronoDuration::seconds(-60));
+
+        let mut result = CheckResult::success("web:443", Duration::from_millis(20));
+        guard.check(&mut result);
+        assert!(result.is_reliable());
+        let stepped = ClockGuard {
+            wall: guard.wall + ChronoDuration::hours(1),
+            ..guard
+        };
+        stepped.check(&mut result);
+        assert!(!result.is_reliable());
+        assert!(result.metrics[CLOCK_SKEW_METRIC] < -3_500_000.0);
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+use super::check_result::{CheckResult, StepResult, StepStatus};
+use super::clock::ClockGuard;
+use super::http_pool::HttpPool;
+
+/// What a single step of a composite check probes, by its `type`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepKind {
+    Tcp { address: SocketAddr },
+    /// GET request; any 2xx/3xx passes unless a specific status is expected.
+    Http {
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expect_status: Option<u16>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: StepKind,
+}
+
+/// A user journey made of several probes run in order, e.g. "login page, API health, DB
+/// port". The first failing step fails the check; the steps after it are marked skipped.
+#[derive(Debug, Clone)]
+pub struct CompositeCheck {
+    pub name: String,
+    pub steps: Vec<Step>,
+    /// Applies to each step on its own, not to the whole journey.
+    pub step_timeout: Duration,
+}
+
+impl CompositeCheck {
+    /// Runs every step and returns one result whose `steps` show where the journey broke.
+    /// The overall latency is the sum of the step latencies.
+    pub async fn run(&self, pool: &HttpPool) -> CheckResult {
+        let clock = ClockGuard::start();
+        let mut steps = Vec::with_capacity(self.steps.len());
+        let mut failed = false;
+        for step in &self.steps {
+            if failed {
+                steps.push(StepResult {
+                    name: step.name.clone(),
+                    status: StepStatus::Skipped,
+                    latency: None,
+                    error: None,
+                });
+                continue;
+            }
+            let outcome = match &step.kind {
+                StepKind::Tcp { address } => self.connect(*address).await,
+                StepKind::Http { url, expect_status } => request(pool, url, *expect_status, self.step_timeout).await,
+            };
+            failed = outcome.1.is_err();
+            steps.push(StepResult {
+                name: step.name.clone(),
+                status: if failed { StepStatus::Failed } else { StepStatus::Passed },
+                latency: outcome.0,
+                error: outcome.1.err(),
+            });
+        }
+
+        let total: Duration = steps.iter().filter_map(|s| s.latency).sum();
+        let mut result = match steps.iter().find(|s| s.status == StepStatus::Failed) {
+            None => CheckResult::success(&self.name, total),
+            Some(step) => CheckResult::failure(
+                &self.name,
+                format!("step '{}' failed: {}", step.name, step.error.as_deref().unwrap_or("unknown error")),
+            ),
+        };
+        result.steps = steps;
+        clock.check(&mut result);
+        result
+    }
+
+    async fn connect(&self, addr: SocketAddr) -> (Option<Duration>, Result<(), String>) {
+        let start = Instant::now();
+        match tokio::time::timeout(self.step_timeout, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => (Some(start.elapsed()), Ok(())),
+            Ok(Err(e)) => (None, Err(e.to_string())),
+            Err(_) => (None, Err(format!("timed out after {:?}", self.step_timeout))),
+        }
+    }
+}
+
+async fn request(
+    pool: &HttpPool,
+    url: &str,
+    expect_status: Option<u16>,
+    timeout: Duration,
+) -> (Option<Duration>, Result<(), String>) {
+    let _slot = match pool.acquire(url).await {
+        Ok(slot) => slot,
+        Err(e) => return (None, Err(e)),
+    };
+    let start = Instant::now();
+    let status = match pool.client().get(url).timeout(timeout).send().await {
+        Ok(response) => response.status(),
+        Err(e) => return (None, Err(e.to_string())),
+    };
+    let latency = Some(start.elapsed());
+    let passed = match expect_status {
+        Some(expected) => status.as_u16() == expected,
+        None => status.is_success() || status.is_redirection(),
+    };
+    if passed {
+        (latency, Ok(()))
+    } else {
+        (latency, Err(format!("HTTP {}", status)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn tcp(name: &str, addr: SocketAddr) -> Step {
+        Step {
+            name: name.to_string(),
+            kind: StepKind::Tcp { address: addr },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failing_step_is_recorded_and_later_steps_skipped() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        // Bind and drop to get a port nothing listens on.
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let check = CompositeCheck {
+            name: "checkout".to_string(),
+            steps: vec![tcp("frontend", open), tcp("payments", closed), tcp("db", open)],
+            step_timeout: Duration::from_secs(1),
+        };
+
+        let result = check.run(&HttpPool::default()).await;
+        assert!(!result.success);
+        let statuses: Vec<_> = result.steps.iter().map(|s| s.status).collect();
+        assert_eq!(statuses, vec![StepStatus::Passed, StepStatus::Failed, StepStatus::Skipped]);
+        assert_eq!(result.steps.iter().find(|s| s.status == StepStatus::Failed).unwrap().name, "payments");
+        assert!(result.error.unwrap().contains("step 'payments' failed"));
+    }
+
+    #[tokio::test]
+    async fn test_composite_targets_run_their_steps() {
+        use crate::back_end::event_bus::EventBus;
+        use crate::back_end::monitor::Monitor;
+        use crate::back_end::target::MonitorTarget;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        let target: MonitorTarget = toml::from_str(&format!(
+            r#"
+            address = "{open}"
+            check = "composite"
+            [spec]
+            kind = "composite"
+            steps = [{{ name = "frontend", type = "tcp", address = "{open}" }}]
+            "#
+        ))
+        .unwrap();
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        monitor.add_monitor_target(target).unwrap();
+
+        let result = monitor.run_check(open).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!((result.target, result.steps.len()), (open.to_string(), 1));
+    }
+}
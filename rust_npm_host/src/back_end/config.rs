@@ -0,0 +1,677 @@
+use chrono::Duration as ChronoDuration;
+use chrono_tz::Tz;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::alertmanager::AlertmanagerConfig;
+use super::budget::{BudgetLedger, Quota};
+use super::csv_import::parse_interval;
+use super::derived_metrics::DerivedMetrics;
+use super::encrypted_config::{self, KeySource};
+use super::logging::LogFormat;
+use super::metadata::DEFAULT_MAX_LABEL_VALUES;
+use super::monitor::DEFAULT_CONCURRENCY;
+use super::power::PowerMode;
+use super::socks_probe::SocksProxy;
+use super::state_tracker::RecoveryRules;
+use super::timezone::{TimeWindow, parse_timezone};
+use super::units::UnitPreferences;
+
+/// What `config print-default` prints: every setting with its default, explained. It must
+/// parse to `Config::default()`, which a test checks.
+pub const DEFAULT_CONFIG: &str = r#"# rust_npm settings. Command-line flags override them.
+# Intervals are written like 30s, 5m, 1h or 1d.
+
+[monitor]
+# Check targets periodically, each on its own interval if it has one and on this one
+# otherwise. Unset: check only when asked (`check`, `--gui`, ...).
+# interval = "60s"
+# How long a check may take before it counts as failed, for targets without their own.
+timeout = "1s"
+# Checks in flight at once.
+concurrency = 64
+# Save battery: "auto" while on battery, "on" always. Unset: off.
+# low_power = "auto"
+# How much longer intervals get in low-power mode.
+low_power_factor = 4
+# Start each target's checks at a fixed point within its interval, derived from its
+# address, instead of all at once. false: every target starts right away.
+spread = true
+# Recurring windows in which no checks run, in the [display] timezone, e.g.
+# ["Sun 02:00-04:00", "Mon-Fri 12:00-12:30"]. An end before the start runs past midnight.
+# maintenance = []
+# Name this instance's results are stored under, so `vantage` can tell its view of the
+# targets from that of other instances sharing the database. Unset: "local".
+# agent = "branch-oslo"
+
+[alerts]
+# When a target's outage and recovery are announced. `alerts dry-run` shows what other
+# values would have announced over the stored results.
+# Failed checks in a row before a target counts as down.
+down_after = 1
+# Successful checks in a row before a down target counts as up again.
+stable_checks = 1
+# Don't announce recoveries from outages shorter than this. Unset: announce them all.
+# min_downtime = "2m"
+# false: announce outages only.
+notify_on_recovery = true
+
+[alertmanager]
+# Forward outages and recoveries to Prometheus Alertmanager at this URL. Unset: don't.
+# url = "http://alertmanager:9093"
+# Link back to this monitor shown in Alertmanager's UI.
+# generator_url = "https://monitor.example.com"
+# Distinct values a metadata key may take before it is no longer sent as a label (it is
+# still sent as an annotation).
+max_metadata_values = 50
+# Added to every alert, so existing routes and silences match.
+# [alertmanager.labels]
+# team = "noc"
+
+[budget]
+# Checks each target owner may run per period, and minutes of browser checks; a check
+# over budget is skipped. Targets without an owner aren't limited. Unset: unlimited.
+period = "1d"
+# max_checks = 100000
+# max_browser_minutes = 600.0
+# Owners with a budget of their own.
+# [budget.teams.web-team]
+# max_browser_minutes = 1200.0
+
+[logging]
+# "text", or "json" for one object per line.
+format = "text"
+
+[storage]
+# postgres://..., sqlite://<path> or memory:. Unset: an SQLite file in the data directory.
+# database = "sqlite:///var/lib/rust_npm/results.db"
+# Store one summary per target and interval (check count, failures, p95 and max latency)
+# instead of every result, plus the results that took a target down or up. The GUI and
+# reports still see every result of this run. Unset: store every result.
+# sample_interval = "1m"
+
+[api]
+# Serve the HTTP API on this address. Unset: no API.
+# listen = "127.0.0.1:8080"
+# File with the API's access rules (`[api]` tokens and networks).
+# access = "/etc/rust_npm/api.toml"
+
+[webdriver]
+# Start a WebDriver server for browser checks on a free port, and restart it if it
+# crashes: "auto" for the first of chromedriver, msedgedriver, geckodriver or safaridriver
+# on PATH, a driver's name or its path. Unset: use one started separately on
+# http://localhost:4444.
+# driver = "auto"
+
+[plugins]
+# Directory with WebAssembly check plugins, each a `*.json` manifest next to its module;
+# targets use them with `{ kind = "plugin", name = "..." }` settings. Needs a build with
+# the wasm-plugins feature. Unset: no plugins.
+# dir = "/etc/rust_npm/plugins"
+
+[metrics]
+# Metrics computed from each result's latency_ms, success (1 or 0) and other metrics, in
+# order, so later ones can use earlier ones. They are stored with the result and added to
+# Parquet exports; give them a unit in [display.units].
+# [[metrics.derived]]
+# name = "tls_share"
+# expression = "tls_handshake_ms / latency_ms"
+
+[socks]
+# SOCKS5 proxies at other sites; targets with `proxy = "<name>"` are checked (TCP and
+# HTTP) through them. The password is read from the named environment variable.
+# [[socks.proxies]]
+# name = "branch-berlin"
+# address = "10.20.0.1:1080"
+# username = "monitor"
+# password_env = "BERLIN_SOCKS_PASSWORD"
+
+[display]
+# Durations: "auto" (ms below a second, s above), "ms" or "s".
+durations = "auto"
+# Sizes: "binary" (KiB, MiB), "decimal" (kB, MB) or "bytes".
+sizes = "binary"
+# Times in the GUI and reports, as an IANA zone. Unset: $TZ, or UTC.
+# timezone = "Europe/Berlin"
+# Units of metrics whose names don't say (names ending in _ms, _bytes, ... do), e.g.
+# derived metrics: "ms", "s", "bytes", "ratio", "percent" or "count".
+# [display.units]
+# tls_share = "ratio"
+"#;
+
+/// A problem with one setting, addressed by its path in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// Like `monitor.interval` or `target[3].timeout`; empty for the file as a whole.
+    pub path: String,
+    pub message: String,
+}
+
+impl ConfigError {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// Everything wrong with one file, one problem per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigErrors {
+    pub file: PathBuf,
+    pub errors: Vec<ConfigError>,
+}
+
+impl fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.file.display())?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigErrors {}
+
+/// Deserializes `value`, naming the setting that failed, e.g. `monitor.concurrency:
+/// invalid type: string "many", expected usize`.
+pub fn deserialize<T: DeserializeOwned>(value: JsonValue) -> Result<T, ConfigError> {
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        let path = e.path().to_string();
+        ConfigError::new(if path == "." { String::new() } else { path }, e.into_inner().to_string())
+    })
+}
+
+/// Reads a TOML or, by extension, JSON file into a value for `deserialize`. Encrypted
+/// files are decrypted with the key from `encrypted_config::PASSPHRASE_ENV`.
+pub fn read_value(path: &Path) -> Result<JsonValue, Box<dyn Error>> {
+    let content = encrypted_config::read_config(path, KeySource::from_env().as_ref())?;
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        Ok(serde_json::from_str(&content)?)
+    } else {
+        Ok(toml::from_str(&content)?)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitorSettings {
+    #[serde(with = "optional_interval", skip_serializing_if = "Option::is_none")]
+    pub interval: Option<Duration>,
+    #[serde(with = "interval")]
+    pub timeout: Duration,
+    pub concurrency: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub low_power: Option<PowerMode>,
+    pub low_power_factor: u32,
+    pub spread: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agent: Option<String>,
+}
+
+impl Default for MonitorSettings {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            timeout: Duration::from_secs(1),
+            concurrency: DEFAULT_CONCURRENCY,
+            low_power: None,
+            low_power_factor: 4,
+            spread: true,
+            maintenance: Vec::new(),
+            agent: None,
+        }
+    }
+}
+
+impl MonitorSettings {
+    /// The `maintenance` windows, read in `tz`.
+    pub fn maintenance_windows(&self, tz: Tz) -> Result<Vec<TimeWindow>, ConfigError> {
+        self.maintenance
+            .iter()
+            .enumerate()
+            .map(|(i, spec)| TimeWindow::parse(spec, tz).map_err(|e| ConfigError::new(format!("monitor.maintenance[{}]", i), e)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertSettings {
+    pub down_after: u32,
+    pub stable_checks: u32,
+    #[serde(with = "optional_interval", skip_serializing_if = "Option::is_none")]
+    pub min_downtime: Option<Duration>,
+    pub notify_on_recovery: bool,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        let rules = RecoveryRules::default();
+        Self {
+            down_after: rules.down_after,
+            stable_checks: rules.stable_checks,
+            min_downtime: None,
+            notify_on_recovery: rules.notify_on_recovery,
+        }
+    }
+}
+
+impl AlertSettings {
+    pub fn rules(&self) -> RecoveryRules {
+        RecoveryRules {
+            down_after: self.down_after,
+            stable_checks: self.stable_checks,
+            min_downtime: self.min_downtime.unwrap_or_default(),
+            notify_on_recovery: self.notify_on_recovery,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertmanagerSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generator_url: Option<String>,
+    pub max_metadata_values: usize,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+}
+
+impl Default for AlertmanagerSettings {
+    fn default() -> Self {
+        Self {
+            url: None,
+            generator_url: None,
+            max_metadata_values: DEFAULT_MAX_LABEL_VALUES,
+            labels: BTreeMap::new(),
+        }
+    }
+}
+
+impl AlertmanagerSettings {
+    /// Where to forward alerts to; `None` without a URL.
+    pub fn config(&self) -> Option<AlertmanagerConfig> {
+        Some(AlertmanagerConfig {
+            url: self.url.clone()?,
+            extra_labels: self.labels.clone(),
+            generator_url: self.generator_url.clone(),
+            timeout: Duration::from_secs(10),
+            max_metadata_values: self.max_metadata_values,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BudgetSettings {
+    #[serde(with = "interval")]
+    pub period: Duration,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_checks: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_browser_minutes: Option<f64>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub teams: BTreeMap<String, Quota>,
+}
+
+impl Default for BudgetSettings {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_secs(24 * 60 * 60),
+            max_checks: None,
+            max_browser_minutes: None,
+            teams: BTreeMap::new(),
+        }
+    }
+}
+
+impl BudgetSettings {
+    pub fn ledger(&self) -> BudgetLedger {
+        let default_quota = Quota {
+            max_checks: self.max_checks,
+            max_browser_minutes: self.max_browser_minutes,
+        };
+        let mut ledger = BudgetLedger::new(ChronoDuration::from_std(self.period).unwrap_or(ChronoDuration::MAX), default_quota);
+        for (team, quota) in &self.teams {
+            ledger.set_quota(team, quota.clone());
+        }
+        ledger
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingSettings {
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub database: Option<String>,
+    #[serde(with = "optional_interval", skip_serializing_if = "Option::is_none")]
+    pub sample_interval: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub listen: Option<SocketAddr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebDriverSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DerivedMetricSetting {
+    pub name: String,
+    /// A rhai expression, see `derived_metrics`.
+    pub expression: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsSettings {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub derived: Vec<DerivedMetricSetting>,
+}
+
+impl MetricsSettings {
+    /// The derived metrics in definition order; an error names the first broken one.
+    pub fn derived_metrics(&self) -> Result<DerivedMetrics, ConfigError> {
+        let mut metrics = DerivedMetrics::default();
+        for (i, metric) in self.derived.iter().enumerate() {
+            metrics
+                .add(&metric.name, &metric.expression)
+                .map_err(|e| ConfigError::new(format!("metrics.derived[{}]", i), e.to_string()))?;
+        }
+        Ok(metrics)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProxySetting {
+    pub name: String,
+    /// `host:port` of the proxy.
+    pub address: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocksSettings {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub proxies: Vec<ProxySetting>,
+}
+
+impl SocksSettings {
+    /// The proxies with their passwords read from the environment.
+    pub fn proxies(&self) -> Result<Vec<SocksProxy>, ConfigError> {
+        self.proxies
+            .iter()
+            .enumerate()
+            .map(|(i, setting)| {
+                let password = match &setting.password_env {
+                    Some(var) => Some(std::env::var(var).map_err(|_| {
+                        ConfigError::new(format!("socks.proxies[{}].password_env", i), format!("${} is not set", var))
+                    })?),
+                    None => None,
+                };
+                Ok(SocksProxy {
+                    username: setting.username.clone(),
+                    password,
+                    ..SocksProxy::new(&setting.name, &setting.address)
+                })
+            })
+            .collect()
+    }
+}
+
+/// The settings file (see `DEFAULT_CONFIG`). Every setting has a default, so an empty or
+/// missing file is fine; unknown settings are errors, as they are usually typos.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub monitor: MonitorSettings,
+    pub alerts: AlertSettings,
+    pub alertmanager: AlertmanagerSettings,
+    pub budget: BudgetSettings,
+    pub logging: LoggingSettings,
+    pub storage: StorageSettings,
+    pub api: ApiSettings,
+    pub webdriver: WebDriverSettings,
+    pub plugins: PluginSettings,
+    pub metrics: MetricsSettings,
+    pub socks: SocksSettings,
+    pub display: UnitPreferences,
+}
+
+impl Config {
+    /// `config.toml` in the user's config directory.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rust_npm").join("config.toml"))
+    }
+
+    /// Loads and validates `path`; a missing file gives the defaults unless `required`.
+    pub fn load(path: &Path, required: bool) -> Result<Self, ConfigErrors> {
+        let fail = |error: ConfigError| ConfigErrors {
+            file: path.to_path_buf(),
+            errors: vec![error],
+        };
+        let value = match read_value(path) {
+            Ok(value) => value,
+            Err(e) if !required && e.downcast_ref::<io::Error>().is_some_and(|e| e.kind() == io::ErrorKind::NotFound) => {
+                return Ok(Self::default());
+            }
+            Err(e) => return Err(fail(ConfigError::new("", e.to_string()))),
+        };
+        let config: Self = deserialize(value).map_err(fail)?;
+        let errors = config.validate();
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigErrors {
+                file: path.to_path_buf(),
+                errors,
+            })
+        }
+    }
+
+    /// What is wrong with settings that parsed, e.g. a timeout longer than the interval.
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
+        let monitor = &self.monitor;
+        if monitor.timeout.is_zero() {
+            errors.push(ConfigError::new("monitor.timeout", "must be at least 1s"));
+        }
+        if let Some(interval) = monitor.interval
+            && monitor.timeout > interval
+        {
+            errors.push(ConfigError::new(
+                "monitor.timeout",
+                format!("must not be longer than monitor.interval ({})", format_interval(interval)),
+            ));
+        }
+        if monitor.concurrency == 0 {
+            errors.push(ConfigError::new("monitor.concurrency", "must be at least 1"));
+        }
+        if monitor.low_power_factor == 0 {
+            errors.push(ConfigError::new("monitor.low_power_factor", "must be at least 1"));
+        }
+        if monitor.agent.as_deref().is_some_and(|agent| agent.trim().is_empty()) {
+            errors.push(ConfigError::new("monitor.agent", "must not be empty"));
+        }
+        if let Err(e) = monitor.maintenance_windows(self.display.timezone()) {
+            errors.push(e);
+        }
+        if self.alerts.down_after == 0 {
+            errors.push(ConfigError::new("alerts.down_after", "must be at least 1"));
+        }
+        if self.budget.period.is_zero() {
+            errors.push(ConfigError::new("budget.period", "must be at least 1s"));
+        }
+        if self.alerts.stable_checks == 0 {
+            errors.push(ConfigError::new("alerts.stable_checks", "must be at least 1"));
+        }
+        if let Err(e) = self.metrics.derived_metrics() {
+            errors.push(e);
+        }
+        for (i, proxy) in self.socks.proxies.iter().enumerate() {
+            if self.socks.proxies[..i].iter().any(|earlier| earlier.name == proxy.name) {
+                errors.push(ConfigError::new(format!("socks.proxies[{}].name", i), format!("'{}' is used twice", proxy.name)));
+            }
+        }
+        if let Some(name) = &self.display.timezone
+            && let Err(e) = parse_timezone(name)
+        {
+            errors.push(ConfigError::new("display.timezone", e));
+        }
+        if let Some(url) = &self.storage.database
+            && !["postgres://", "postgresql://", "sqlite://", "memory:"].iter().any(|scheme| url.starts_with(scheme))
+        {
+            errors.push(ConfigError::new("storage.database", "must start with postgres://, sqlite:// or memory:"));
+        }
+        errors
+    }
+}
+
+/// `d` the way intervals are written in settings: `500ms`, `1.5s`, `90s`, `5m`, `2h`, `1d`.
+pub fn format_interval(d: Duration) -> String {
+    let secs = d.as_secs();
+    match secs {
+        _ if d.subsec_nanos() != 0 && secs == 0 && d.subsec_nanos().is_multiple_of(1_000_000) => format!("{}ms", d.as_millis()),
+        _ if d.subsec_nanos() != 0 || secs == 0 => format!("{}s", d.as_secs_f64()),
+        _ if secs.is_multiple_of(86_400) => format!("{}d", secs / 86_400),
+        _ if secs.is_multiple_of(3600) => format!("{}h", secs / 3600),
+        _ if secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        _ => format!("{}s", secs),
+    }
+}
+
+mod interval {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(d: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_interval(*d))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        parse_interval(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+mod optional_interval {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some(d) => interval::serialize(d, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        interval::deserialize(deserializer).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_errors_name_the_setting_and_defaults_are_documented() {
+        let documented: Config = deserialize(toml::from_str(DEFAULT_CONFIG).unwrap()).unwrap();
+        assert_eq!(documented, Config::default());
+
+        let typo = deserialize::<Config>(json!({"monitor": {"intervall": "5m"}})).unwrap_err();
+        assert_eq!(typo.path, "monitor.intervall");
+        assert!(typo.message.starts_with("unknown field `intervall`"), "{}", typo);
+        let bad = deserialize::<Config>(json!({"monitor": {"interval": "5 parsecs"}})).unwrap_err();
+        assert_eq!(bad.to_string(), "monitor.interval: unknown interval unit 'parsecs'");
+
+        let config: Config = deserialize(json!({
+            "monitor": {"interval": "30s", "timeout": "1m", "concurrency": 0, "maintenance": ["Sun 02:00-04:00", "Sunday night"]},
+            "storage": {"database": "mysql://db"},
+            "metrics": {"derived": [{"name": "tls_share", "expression": "tls_handshake_ms /"}]},
+            "socks": {"proxies": [{"name": "berlin", "address": "10.20.0.1:1080"}, {"name": "berlin", "address": "10.20.0.2:1080"}]},
+        }))
+        .unwrap();
+        let errors: Vec<String> = config.validate().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            errors,
+            [
+                "monitor.timeout: must not be longer than monitor.interval (30s)",
+                "monitor.concurrency: must be at least 1",
+                "monitor.maintenance[1]: time range 'night' must look like '08:00-18:00'",
+                "metrics.derived[0]: Script is incomplete (line 1, position 19)",
+                "socks.proxies[1].name: 'berlin' is used twice",
+                "storage.database: must start with postgres://, sqlite:// or memory:",
+            ]
+        );
+        assert_eq!(format_interval(Duration::from_secs(7200)), "2h");
+        for d in [Duration::from_millis(500), Duration::from_millis(1500), Duration::from_secs(90)] {
+            assert_eq!(parse_interval(&format_interval(d)), Ok(d));
+        }
+        assert_eq!(format_interval(Duration::from_millis(1500)), "1.5s");
+        assert_eq!(parse_interval("1.1m"), Ok(Duration::from_secs(66)));
+    }
+
+    #[test]
+    fn test_encrypted_files_need_the_key() {
+        let dir = std::env::temp_dir().join(format!("rnpm-encrypted-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let source = KeySource::Passphrase("pw".to_string());
+        encrypted_config::write_encrypted(&path, "[monitor]\nconcurrency = 4\n", &source).unwrap();
+        let errors = Config::load(&path, false).unwrap_err();
+        assert!(errors.to_string().contains(encrypted_config::PASSPHRASE_ENV), "{}", errors);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::monitor::{CheckKind as MonitorCheck, Monitor};
+use super::address::{self, HostSpec};
+use super::target::MonitorTarget;
+
+/// Kind of check requested for an imported target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    Tcp,
+    Icmp,
+    Http,
+    Browser,
+}
+
+impl FromStr for CheckKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "tcp" | "port" => Ok(CheckKind::Tcp),
+            "icmp" | "ping" => Ok(CheckKind::Icmp),
+            "http" | "https" => Ok(CheckKind::Http),
+            "browser" | "web" => Ok(CheckKind::Browser),
+            other => Err(format!("unknown check type '{}'", other)),
+        }
+    }
+}
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CheckKind::Tcp => "tcp",
+            CheckKind::Icmp => "icmp",
+            CheckKind::Http => "http",
+            CheckKind::Browser => "browser",
+        };
+        // pad() so the preview table's column widths apply.
+        f.pad(name)
+    }
+}
+
+/// Which spreadsheet column holds which target field. Only the host column is required;
+/// a missing port column means every row must carry `host:port`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnMapping {
+    pub host: String,
+    pub port: Option<String>,
+    pub check_type: Option<String>,
+    pub interval: Option<String>,
+    pub tags: Option<String>,
+    /// Separator between several tags in one cell.
+    pub tag_separator: char,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            host: "hostname".to_string(),
+            port: Some("port".to_string()),
+            check_type: Some("check_type".to_string()),
+            interval: Some("interval".to_string()),
+            tags: Some("tags".to_string()),
+            tag_separator: ';',
+        }
+    }
+}
+
+impl ColumnMapping {
+    /// Parses overrides like `host=Device Name,port=Port,tags=Labels` on top of the
+    /// defaults. An empty column name (`interval=`) means the field isn't in the file.
+    pub fn parse(spec: &str) -> Result<Self, Box<dyn Error>> {
+        let mut mapping = Self::default();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (field, column) = pair
+                .split_once('=')
+                .ok_or_else(|| format!("expected field=column, got '{}'", pair))?;
+            let column = column.trim();
+            let optional = (!column.is_empty()).then(|| column.to_string());
+            match field.trim() {
+                "host" | "hostname" => {
+                    mapping.host = optional.ok_or("the host column can't be left out")?;
+                }
+                "port" => mapping.port = optional,
+                "check" | "check_type" => mapping.check_type = optional,
+                "interval" => mapping.interval = optional,
+                "tags" => mapping.tags = optional,
+                other => return Err(format!("unknown field '{}'", other).into()),
+            }
+        }
+        Ok(mapping)
+    }
+}
+
+/// One valid row of the import file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedTarget {
+    /// Line in the file, counting the header as line 1.
+    pub line: usize,
+    pub host: String,
+    pub port: u16,
+    pub check: CheckKind,
+    pub interval: Option<Duration>,
+    pub tags: Vec<String>,
+}
+
+/// Everything the import would do, for a dry run or as input to `apply`.
+#[derive(Debug, Clone, Default)]
+pub struct ImportPreview {
+    pub targets: Vec<ImportedTarget>,
+    /// Rows that can't be imported, with their line numbers.
+    pub errors: Vec<(usize, String)>,
+}
+
+impl fmt::Display for ImportPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<6} {:<40} {:<6} {:<8} {:<9} tags", "line", "host", "port", "check", "interval")?;
+        for t in &self.targets {
+            let interval = t.interval.map(|i| format!("{}s", i.as_secs())).unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "{:<6} {:<40} {:<6} {:<8} {:<9} {}",
+                t.line,
+                t.host,
+                t.port,
+                t.check,
+                interval,
+                t.tags.join(", ")
+            )?;
+        }
+        for (line, error) in &self.errors {
+            writeln!(f, "line {}: {}", line, error)?;
+        }
+        write!(f, "{} targets ready, {} rows with errors", self.targets.len(), self.errors.len())
+    }
+}
+
+/// Accepts plain seconds or a number with an ms/s/m/h/d suffix; fractions like `0.5s` too.
+pub fn parse_interval(value: &str) -> Result<Duration, String> {
+    let value = value.trim().to_lowercase();
+    let (number, unit) = match value.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((i, _)) => value.split_at(i),
+        None => (value.as_str(), "s"),
+    };
+    let number: f64 = number
+        .trim()
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite() && *n >= 0.0)
+        .ok_or_else(|| format!("'{}' is not an interval", value))?;
+    let seconds = match unit.trim() {
+        "ms" => number / 1000.0,
+        "s" | "sec" => number,
+        "m" | "min" => number * 60.0,
+        "h" => number * 3600.0,
+        "d" => number * 86_400.0,
+        other => return Err(format!("unknown interval unit '{}'", other)),
+    };
+    // Whole milliseconds, so `1.1m` is 66 s and not a hair more.
+    let millis = (seconds * 1000.0).round();
+    if millis < 1.0 {
+        return Err("interval must be at least one millisecond".to_string());
+    }
+    if millis >= u64::MAX as f64 {
+        return Err(format!("'{}' is too long", value));
+    }
+    Ok(Duration::from_millis(millis as u64))
+}
+
+/// Reads the whole file and validates every row without touching the monitor.
+pub fn preview<R: Read>(reader: R, mapping: &ColumnMapping) -> Result<ImportPreview, Box<dyn Error>> {
+    let mut rdr = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(reader);
+    let headers: HashMap<String, usize> = rdr
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(i, h)| (h.to_lowercase(), i))
+        .collect();
+    let defaults = ColumnMapping::default();
+    // Optional columns under their default name may simply be absent; a column the user
+    // mapped explicitly has to exist.
+    let column = |name: &Option<String>, default: &Option<String>| -> Result<Option<usize>, Box<dyn Error>> {
+        let Some(name) = name else { return Ok(None) };
+        match headers.get(&name.to_lowercase()) {
+            Some(i) => Ok(Some(*i)),
+            None if name == default.as_deref().unwrap_or_default() => Ok(None),
+            None => Err(format!("column '{}' not found in the header", name).into()),
+        }
+    };
+    let host_col = *headers
+        .get(&mapping.host.to_lowercase())
+        .ok_or_else(|| format!("column '{}' not found in the header", mapping.host))?;
+    let port_col = column(&mapping.port, &defaults.port)?;
+    let check_col = column(&mapping.check_type, &defaults.check_type)?;
+    let interval_col = column(&mapping.interval, &defaults.interval)?;
+    let tags_col = column(&mapping.tags, &defaults.tags)?;
+
+    let mut preview = ImportPreview::default();
+    for (i, record) in rdr.records().enumerate() {
+        let line = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                preview.errors.push((line, e.to_string()));
+                continue;
+            }
+        };
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        let cell = |col: Option<usize>| col.and_then(|c| record.get(c)).unwrap_or("");
+        match parse_row(line, cell(Some(host_col)), cell(port_col), cell(check_col), cell(interval_col), cell(tags_col), mapping.tag_separator) {
+            Ok(target) => preview.targets.push(target),
+            Err(e) => preview.errors.push((line, e)),
+        }
+    }
+    Ok(preview)
+}
+
+fn parse_row(line: usize, host: &str, port: &str, check: &str, interval: &str, tags: &str, separator: char) -> Result<ImportedTarget, String> {
+    let (host, port) = match (host.rsplit_once(':'), port.is_empty()) {
+        // "host:port" in the host cell, unless it's a bare IPv6 address.
+        (Some((h, p)), true) if !h.contains(':') => (h, p),
+        _ => (host, port),
+    };
+    if host.is_empty() {
+        return Err("host is empty".to_string());
+    }
+    let port = match port.parse::<u16>() {
+        Ok(0) | Err(_) => return Err(format!("'{}' is not a valid port", port)),
+        Ok(port) => port,
+    };
+    Ok(ImportedTarget {
+        line,
+        host: host.trim_matches(['[', ']']).to_string(),
+        port,
+        check: check.parse()?,
+        interval: (!interval.is_empty()).then(|| parse_interval(interval)).transpose()?,
+        tags: tags
+            .split(separator)
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .collect(),
+    })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    pub added: usize,
+    /// Rows that were valid but not imported, with the reason.
+    pub skipped: Vec<(usize, String)>,
+}
+
+impl ImportedTarget {
+    fn monitor_target(&self, addr: SocketAddr) -> MonitorTarget {
+        let check = match self.check {
+            CheckKind::Tcp => MonitorCheck::Tcp,
+            CheckKind::Icmp => MonitorCheck::Icmp,
+            CheckKind::Http => MonitorCheck::Http,
+            CheckKind::Browser => MonitorCheck::Browser,
+        };
+        let mut target = MonitorTarget::new(addr).with_check(check);
+        target.interval = self.interval;
+        target
+    }
+}
+
+/// Adds the previewed targets to the monitor with their check and interval; host names
+/// with every address they resolve to. The first tag becomes the target's group.
+pub async fn apply(monitor: &Monitor, preview: &ImportPreview) -> ImportSummary {
+    let mut summary = ImportSummary::default();
+    for target in &preview.targets {
+        let spec = HostSpec {
+            host: target.host.clone(),
+            port: target.port,
+        };
+        let added = match address::add_host(monitor, &spec, |addr| target.monitor_target(addr)).await {
+            Ok(added) => added,
+            Err(e) => {
+                summary.skipped.push((target.line, e.to_string()));
+                continue;
+            }
+        };
+        for addr in added {
+            if let Some(group) = target.tags.first() {
+                monitor.set_group(addr, Some(group.clone())).ok();
+            }
+        }
+        summary.added += 1;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::event_bus::EventBus;
+
+    const INVENTORY: &str = "Device Name,Port,Type,Every,Labels
+10.0.0.1,443,https,5m,web;prod
+10.0.0.2:5432,,,30,db
+,22,tcp,,
+10.0.0.3,99999,tcp,,
+10.0.0.4,22,telnet,,
+";
+
+    fn mapping() -> ColumnMapping {
+        ColumnMapping::parse("host=Device Name, port=Port, check=Type, interval=Every, tags=Labels").unwrap()
+    }
+
+    #[test]
+    fn test_preview_with_column_mapping() {
+        let preview = preview(INVENTORY.as_bytes(), &mapping()).unwrap();
+        assert_eq!(preview.targets.len(), 2);
+        assert_eq!(preview.targets[0].check, CheckKind::Http);
+        assert_eq!(preview.targets[0].interval, Some(Duration::from_secs(300)));
+        assert_eq!(preview.targets[0].tags, vec!["web", "prod"]);
+        assert_eq!(preview.targets[1].port, 5432);
+        let error_lines: Vec<usize> = preview.errors.iter().map(|(line, _)| *line).collect();
+        assert_eq!(error_lines, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn test_missing_column_is_reported() {
+        let err = preview(INVENTORY.as_bytes(), &ColumnMapping::default()).unwrap_err();
+        assert!(err.to_string().contains("hostname"));
+        assert!(ColumnMapping::parse("colour=Red").is_err());
+        let mapped = ColumnMapping::parse("host=Device Name,tags=Groups").unwrap();
+        assert!(preview(INVENTORY.as_bytes(), &mapped).unwrap_err().to_string().contains("Groups"));
+        // Default optional columns may be missing.
+        let minimal = preview("hostname\n10.0.0.9:80\n".as_bytes(), &ColumnMapping::default()).unwrap();
+        assert_eq!(minimal.targets[0].port, 80);
+    }
+
+    #[tokio::test]
+    async fn test_apply_adds_targets_with_check_and_group() {
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let preview = preview(INVENTORY.as_bytes(), &mapping()).unwrap();
+        let summary = apply(&monitor, &preview).await;
+        assert_eq!(summary.added, 2);
+        assert!(summary.skipped.is_empty());
+        let (web, db) = ("10.0.0.1:443".parse().unwrap(), "10.0.0.2:5432".parse().unwrap());
+        assert_eq!(monitor.targets(), vec![web, db]);
+        let web = monitor.monitor_target(web).unwrap();
+        assert_eq!((web.check, web.url().as_str()), (MonitorCheck::Http, "https://10.0.0.1:443/"));
+        assert_eq!(web.interval, Some(Duration::from_secs(300)));
+        assert_eq!(monitor.group(db).as_deref(), Some("db"));
+    }
+}
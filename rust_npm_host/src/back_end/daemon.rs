@@ -0,0 +1,171 @@
+use chrono::Duration as ChronoDuration;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::event_bus::MonitorEvent;
+use super::monitor::Monitor;
+use super::webdriver_reaper::{OrphanReaper, SessionRegistry};
+
+// How long shutdown waits for running checks and for the history to be written.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const WEBDRIVER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Holds the PID file of a running daemon and removes it when dropped.
+#[derive(Debug)]
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Writes our PID to `path`. Fails if the file names a process that is still running,
+    /// so two daemons don't monitor (and alert) twice; a stale file is replaced.
+    pub fn create(path: &Path) -> Result<Self, Box<dyn Error>> {
+        if let Some(pid) = fs::read_to_string(path).ok().and_then(|s| s.trim().parse::<u32>().ok())
+            && pid != std::process::id()
+            && is_running(pid)
+        {
+            return Err(format!("already running as PID {} (see {})", pid, path.display()).into());
+        }
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::File::create(path)?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(Self { path: path.to_path_buf() })
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::runtime_dir()
+            .or_else(dirs::data_dir)
+            .map(|dir| dir.join("rust_npm").join("rust_npm.pid"))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            eprintln!("Cannot remove PID file {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+// Without /proc there is no cheap portable check; trust the file.
+#[cfg(not(target_os = "linux"))]
+fn is_running(_pid: u32) -> bool {
+    true
+}
+
+/// Waits for SIGINT or SIGTERM (Ctrl+C elsewhere) and returns its name.
+pub async fn wait_for_signal() -> io::Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut interrupt = signal(SignalKind::interrupt())?;
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            _ = interrupt.recv() => Ok("SIGINT"),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok("Ctrl+C")
+    }
+}
+
+/// Shuts the monitor down in order: lets the scheduler finish the checks it is running,
+/// waits until `history` has written every result (it stops at `ShuttingDown`), then
+/// quits the idle browser sessions and closes the WebDriver sessions still left open.
+pub async fn shut_down(monitor: &Arc<Monitor>, scheduler: JoinHandle<()>, history: JoinHandle<()>, sessions: Option<SessionRegistry>) {
+    monitor.stop();
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, scheduler).await.is_err() {
+        eprintln!("Checks still running after {:?}; not waiting for them", SHUTDOWN_TIMEOUT);
+    }
+    monitor.bus().publish(MonitorEvent::ShuttingDown);
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, history).await.is_err() {
+        eprintln!("Results still being written after {:?}; some may be lost", SHUTDOWN_TIMEOUT);
+    }
+    if let Some(pool) = monitor.take_browser_pool() {
+        pool.close().await;
+    }
+    let Some(registry) = sessions else { return };
+    let closed = match OrphanReaper::new(registry, ChronoDuration::zero(), WEBDRIVER_TIMEOUT) {
+        Ok(reaper) => reaper.close_own().await,
+        Err(e) => Err(e),
+    };
+    match closed {
+        Ok(report) => {
+            for (session, e) in report.failed {
+                eprintln!("Cannot close WebDriver session {}: {}", session, e);
+            }
+        }
+        Err(e) => eprintln!("Cannot close WebDriver sessions: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file_refuses_a_running_daemon_and_cleans_up() {
+        let path = std::env::temp_dir().join(format!("rust_npm_{}.pid", uuid::Uuid::new_v4().simple()));
+        let pid_file = PidFile::create(&path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().trim(), std::process::id().to_string());
+        drop(pid_file);
+        assert!(!path.exists());
+
+        // PID 1 is always running; a file naming it belongs to a live daemon.
+        fs::write(&path, "1\n").unwrap();
+        if cfg!(target_os = "linux") {
+            assert!(PidFile::create(&path).is_err());
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_recorded_sessions() {
+        use crate::back_end::event_bus::EventBus;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // A WebDriver that reports the request line of every request it gets.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let webdriver_url = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, mut received) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let n = socket.read(&mut request).await.unwrap_or(0);
+                let line = String::from_utf8_lossy(&request[..n]).lines().next().unwrap_or_default().to_string();
+                requests.send(line).ok();
+                let body = r#"{"value":null}"#;
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let path = std::env::temp_dir().join(format!("rust_npm_sessions_{}.json", uuid::Uuid::new_v4().simple()));
+        let registry = SessionRegistry::new(&path);
+        registry.record(&webdriver_url, "s1").unwrap();
+        let monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_secs(1)));
+        shut_down(&monitor, tokio::spawn(async {}), tokio::spawn(async {}), Some(registry.clone())).await;
+
+        assert_eq!(received.recv().await.unwrap(), "DELETE /session/s1 HTTP/1.1");
+        assert!(registry.sessions().unwrap().is_empty());
+        fs::remove_file(&path).ok();
+    }
+}
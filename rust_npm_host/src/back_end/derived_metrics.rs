@@ -0,0 +1,138 @@
+use rhai::{AST, Dynamic, Engine, Scope};
+use std::collections::BTreeMap;
+use std::error::Error;
+
+use super::check_result::CheckResult;
+
+/// A named metric computed from other fields of a result, e.g.
+/// `tls_share = tls_handshake_ms / latency_ms`.
+///
+/// Expressions use rhai syntax and can reference `latency_ms`, `success` (1 or 0), every
+/// entry in the result's `metrics` map and any derived metric defined before this one.
+pub struct DerivedMetric {
+    pub name: String,
+    ast: AST,
+}
+
+/// The configured set of derived metrics, evaluated in definition order on ingest.
+pub struct DerivedMetrics {
+    engine: Engine,
+    metrics: Vec<DerivedMetric>,
+}
+
+impl Default for DerivedMetrics {
+    fn default() -> Self {
+        let mut engine = Engine::new();
+        // Expressions are tiny; anything bigger than this is a mistake.
+        engine.set_max_operations(10_000);
+        Self {
+            engine,
+            metrics: Vec::new(),
+        }
+    }
+}
+
+impl DerivedMetrics {
+    /// Adds a metric, rejecting expressions that don't parse or clash with built-in names.
+    pub fn add(&mut self, name: &str, expression: &str) -> Result<(), Box<dyn Error>> {
+        if !is_identifier(name) {
+            return Err(format!("metric name '{}' must be a plain identifier", name).into());
+        }
+        if name == "latency_ms" || name == "success" || self.metrics.iter().any(|m| m.name == name) {
+            return Err(format!("metric '{}' is already defined", name).into());
+        }
+        let ast = self.engine.compile_expression(expression)?;
+        self.metrics.push(DerivedMetric {
+            name: name.to_string(),
+            ast,
+        });
+        Ok(())
+    }
+
+    /// Computes every derived metric for the given input fields.
+    ///
+    /// A metric whose inputs are missing (e.g. no latency on a failed check) or whose value
+    /// is not a finite number is skipped rather than recorded as garbage.
+    pub fn evaluate(&self, fields: &BTreeMap<String, f64>) -> BTreeMap<String, f64> {
+        let mut scope = Scope::new();
+        for (name, value) in fields {
+            scope.push(name.clone(), *value);
+        }
+
+        let mut derived = BTreeMap::new();
+        for metric in &self.metrics {
+            let value = self
+                .engine
+                .eval_ast_with_scope::<Dynamic>(&mut scope, &metric.ast)
+                .ok()
+                .and_then(|v| v.as_float().ok().or_else(|| v.as_int().ok().map(|i| i as f64)))
+                .filter(|v| v.is_finite());
+            if let Some(value) = value {
+                scope.push(metric.name.clone(), value);
+                derived.insert(metric.name.clone(), value);
+            }
+        }
+        derived
+    }
+
+    /// Evaluates the metrics for a result and stores them alongside its native metrics.
+    pub fn apply(&self, result: &mut CheckResult) {
+        let derived = self.evaluate(&result_fields(result));
+        result.metrics.extend(derived);
+    }
+}
+
+/// The numeric fields of a result that expressions can refer to.
+pub fn result_fields(result: &CheckResult) -> BTreeMap<String, f64> {
+    let mut fields = result.metrics.clone();
+    fields.insert("success".to_string(), if result.success { 1.0 } else { 0.0 });
+    if let Some(ms) = result.latency_ms() {
+        fields.insert("latency_ms".to_string(), ms);
+    }
+    fields
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_ratio_and_chained_metrics() {
+        let mut metrics = DerivedMetrics::default();
+        metrics.add("tls_share", "tls_handshake_ms / latency_ms").unwrap();
+        metrics.add("tls_percent", "tls_share * 100").unwrap();
+
+        let mut result = CheckResult::success("https://example.com", Duration::from_millis(200));
+        result.metrics.insert("tls_handshake_ms".to_string(), 50.0);
+        metrics.apply(&mut result);
+
+        assert_eq!(result.metrics.get("tls_share"), Some(&0.25));
+        assert_eq!(result.metrics.get("tls_percent"), Some(&25.0));
+    }
+
+    #[test]
+    fn test_missing_inputs_skip_metric() {
+        let mut metrics = DerivedMetrics::default();
+        metrics.add("spread", "p95_latency - p50_latency").unwrap();
+        metrics.add("per_second", "1000.0 / latency_ms").unwrap();
+
+        let mut result = CheckResult::failure("db:5432", "refused");
+        metrics.apply(&mut result);
+        assert!(result.metrics.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_bad_definitions() {
+        let mut metrics = DerivedMetrics::default();
+        assert!(metrics.add("bad name", "1").is_err());
+        assert!(metrics.add("latency_ms", "1").is_err());
+        assert!(metrics.add("broken", "latency_ms /").is_err());
+    }
+}
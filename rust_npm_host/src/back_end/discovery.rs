@@ -0,0 +1,314 @@
+use futures::stream::{self, StreamExt};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use super::icmp::IcmpProbe;
+use super::monitor::CheckKind;
+use super::presets;
+use super::target::MonitorTarget;
+use super::target_rules::InventoryHost;
+
+/// Largest network `sweep` accepts (a /20), so a typo like /8 doesn't ping 16M addresses.
+pub const MAX_SWEEP_HOSTS: u32 = 4096;
+
+/// Ports tried when fingerprinting, chosen because they tell device types apart.
+pub const FINGERPRINT_PORTS: &[u16] = &[21, 22, 23, 80, 135, 445, 515, 631, 3389, 9100];
+
+const SWEEP_CONCURRENCY: usize = 64;
+const BANNER_TIMEOUT: Duration = Duration::from_millis(800);
+
+/// The IPv4 addresses of a CIDR block, without network and broadcast address.
+pub fn hosts_in(cidr: &str) -> Result<Vec<Ipv4Addr>, String> {
+    let (addr, prefix) = cidr.split_once('/').unwrap_or((cidr, "32"));
+    let addr: Ipv4Addr = addr.trim().parse().map_err(|_| format!("'{}' is not an IPv4 address", addr))?;
+    let prefix: u32 = match prefix.trim().parse() {
+        Ok(prefix) if prefix <= 32 => prefix,
+        _ => return Err(format!("'{}' is not a prefix length", prefix)),
+    };
+    let size = 1u64 << (32 - prefix);
+    if size > MAX_SWEEP_HOSTS as u64 {
+        return Err(format!("/{} has {} addresses, sweeps are limited to {}", prefix, size, MAX_SWEEP_HOSTS));
+    }
+    let network = u32::from(addr) & (u32::MAX.checked_shl(32 - prefix).unwrap_or(0));
+    let (first, last) = match size {
+        // /31 and /32 have no network or broadcast address.
+        1 | 2 => (network, network + size as u32 - 1),
+        _ => (network + 1, network + size as u32 - 2),
+    };
+    Ok((first..=last).map(Ipv4Addr::from).collect())
+}
+
+/// Device type suggested by the fingerprint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceType {
+    /// Linux or another Unix; they share the initial TTL of 64.
+    Linux,
+    Windows,
+    Printer,
+    /// Switch, router, firewall.
+    NetworkDevice,
+    Unknown,
+}
+
+impl fmt::Display for DeviceType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DeviceType::Linux => "linux",
+            DeviceType::Windows => "windows",
+            DeviceType::Printer => "printer",
+            DeviceType::NetworkDevice => "network_device",
+            DeviceType::Unknown => "unknown",
+        })
+    }
+}
+
+/// A guess, not a fact: the evidence is kept so operators can judge it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DeviceHint {
+    pub device: DeviceType,
+    pub reasons: Vec<String>,
+}
+
+// Banner words and what they point to.
+const BANNER_HINTS: &[(&str, DeviceType)] = &[
+    ("jetdirect", DeviceType::Printer),
+    ("laserjet", DeviceType::Printer),
+    ("printer", DeviceType::Printer),
+    ("cups", DeviceType::Printer),
+    ("microsoft", DeviceType::Windows),
+    ("windows", DeviceType::Windows),
+    ("cisco", DeviceType::NetworkDevice),
+    ("mikrotik", DeviceType::NetworkDevice),
+    ("routeros", DeviceType::NetworkDevice),
+    ("junos", DeviceType::NetworkDevice),
+    ("ubuntu", DeviceType::Linux),
+    ("debian", DeviceType::Linux),
+    ("openssh", DeviceType::Linux),
+];
+
+/// Weighs the reply TTL, open ports and service banners. Banners and device-specific
+/// ports count more than the TTL, which only narrows down the OS family. TCP window sizes
+/// would need raw sockets, so they are not used.
+pub fn fingerprint(ttl: Option<u8>, open_ports: &[u16], banners: &BTreeMap<u16, String>) -> DeviceHint {
+    let mut scores: HashMap<DeviceType, u32> = HashMap::new();
+    let mut reasons = Vec::new();
+    let mut vote = |device, points, reason: String| {
+        *scores.entry(device).or_insert(0) += points;
+        reasons.push(reason);
+    };
+
+    // Initial TTLs are 64 (Linux, macOS, most Unix), 128 (Windows) and 255 (network gear);
+    // the reply arrives with that minus the hops in between.
+    if let Some(ttl) = ttl {
+        let device = match ttl {
+            0..=64 => DeviceType::Linux,
+            65..=128 => DeviceType::Windows,
+            _ => DeviceType::NetworkDevice,
+        };
+        vote(device, 1, format!("TTL {}", ttl));
+    }
+    for port in open_ports {
+        let device = match port {
+            9100 | 515 | 631 => DeviceType::Printer,
+            135 | 445 | 3389 => DeviceType::Windows,
+            _ => continue,
+        };
+        vote(device, 2, format!("port {} open", port));
+    }
+    for (port, banner) in banners {
+        let lower = banner.to_lowercase();
+        if let Some((word, device)) = BANNER_HINTS.iter().find(|(word, _)| lower.contains(word)) {
+            vote(*device, 3, format!("'{}' in the banner on port {}", word, port));
+        }
+    }
+
+    // Ties go to the more specific type: a printer running Linux is still a printer.
+    let device = [DeviceType::Printer, DeviceType::NetworkDevice, DeviceType::Windows, DeviceType::Linux]
+        .into_iter()
+        .filter(|d| scores.contains_key(d))
+        .max_by_key(|d| scores[d])
+        .unwrap_or(DeviceType::Unknown);
+    DeviceHint { device, reasons }
+}
+
+/// A host that answered the sweep.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiscoveredHost {
+    pub address: IpAddr,
+    pub rtt: Duration,
+    pub ttl: Option<u8>,
+    pub open_ports: Vec<u16>,
+    /// First line the service sent (or the HTTP `Server` header), by port.
+    pub banners: BTreeMap<u16, String>,
+    /// Only set when fingerprinting was asked for.
+    pub hint: Option<DeviceHint>,
+}
+
+impl DiscoveredHost {
+    /// An inventory entry tagged `source=discovery` and, if known, `device=<type>`, ready
+    /// for the target rules.
+    pub fn inventory_host(&self) -> InventoryHost {
+        let mut tags = BTreeMap::from([("source".to_string(), "discovery".to_string())]);
+        if let Some(hint) = self.hint.as_ref().filter(|h| h.device != DeviceType::Unknown) {
+            tags.insert("device".to_string(), hint.device.to_string());
+        }
+        InventoryHost {
+            name: self.address.to_string(),
+            address: Some(self.address.to_string()),
+            tags,
+        }
+    }
+}
+
+impl DiscoveredHost {
+    /// What to monitor on the host: the preset for its device type if fingerprinting found
+    /// one (`linux`, `windows`), a ping otherwise. Tagged `source=discovery`.
+    pub fn targets(&self) -> Vec<MonitorTarget> {
+        let preset = match self.hint.as_ref().map(|hint| hint.device) {
+            Some(DeviceType::Linux) => presets::find("linux"),
+            Some(DeviceType::Windows) => presets::find("windows"),
+            _ => None,
+        };
+        let targets = match preset {
+            Some(preset) => preset.expand(self.address),
+            None => vec![MonitorTarget::new(SocketAddr::new(self.address, 0)).with_check(CheckKind::Icmp)],
+        };
+        targets.into_iter().map(|target| target.with_metadata("source", "discovery")).collect()
+    }
+}
+
+impl fmt::Display for DiscoveredHost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<16} {:>7.1} ms", self.address, self.rtt.as_secs_f64() * 1000.0)?;
+        if let Some(ttl) = self.ttl {
+            write!(f, "  ttl {:<3}", ttl)?;
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "  {:<14} ({})", hint.device.to_string(), hint.reasons.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Pings every address of `cidr` once and, with `fingerprint` set, probes the answering
+/// hosts' common ports for a device type hint. Needs permission to open ICMP sockets.
+pub async fn sweep(cidr: &str, fingerprint: bool) -> Result<Vec<DiscoveredHost>, Box<dyn Error + Send + Sync>> {
+    let hosts = hosts_in(cidr)?;
+    let probe = IcmpProbe::default();
+    // Fail early when ICMP isn't allowed at all rather than reporting an empty network.
+    probe.echo(IpAddr::V4(Ipv4Addr::LOCALHOST)).await?;
+
+    let mut found: Vec<DiscoveredHost> = stream::iter(hosts)
+        .map(|ip| {
+            let probe = &probe;
+            async move {
+                let reply = probe.echo(IpAddr::V4(ip)).await.ok().flatten()?;
+                let mut host = DiscoveredHost {
+                    address: IpAddr::V4(ip),
+                    rtt: reply.rtt,
+                    ttl: reply.ttl,
+                    open_ports: Vec::new(),
+                    banners: BTreeMap::new(),
+                    hint: None,
+                };
+                if fingerprint {
+                    probe_ports(&mut host).await;
+                    host.hint = Some(self::fingerprint(host.ttl, &host.open_ports, &host.banners));
+                }
+                Some(host)
+            }
+        })
+        .buffer_unordered(SWEEP_CONCURRENCY)
+        .filter_map(|host| async move { host })
+        .collect()
+        .await;
+    found.sort_by_key(|host| host.address);
+    Ok(found)
+}
+
+async fn probe_ports(host: &mut DiscoveredHost) {
+    let results = futures::future::join_all(
+        FINGERPRINT_PORTS.iter().map(|port| grab_banner(SocketAddr::new(host.address, *port))),
+    )
+    .await;
+    for (port, result) in FINGERPRINT_PORTS.iter().zip(results) {
+        if let Some(banner) = result {
+            host.open_ports.push(*port);
+            if !banner.is_empty() {
+                host.banners.insert(*port, banner);
+            }
+        }
+    }
+}
+
+// `None` when the port is closed; an empty banner when it is open but said nothing.
+async fn grab_banner(addr: SocketAddr) -> Option<String> {
+    let mut stream = tokio::time::timeout(BANNER_TIMEOUT, TcpStream::connect(addr)).await.ok()?.ok()?;
+    if addr.port() == 80 {
+        let _ = stream.write_all(b"HEAD / HTTP/1.0\r\n\r\n").await;
+    }
+    let mut buffer = [0; 512];
+    let read = match tokio::time::timeout(BANNER_TIMEOUT, stream.read(&mut buffer)).await {
+        Ok(Ok(read)) => read,
+        _ => 0,
+    };
+    let text = String::from_utf8_lossy(&buffer[..read]);
+    let line = match addr.port() {
+        80 => text.lines().find_map(|l| l.strip_prefix("Server:").or_else(|| l.strip_prefix("server:"))),
+        _ => text.lines().next(),
+    };
+    Some(line.unwrap_or_default().trim().chars().filter(|c| !c.is_control()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cidr_hosts() {
+        let hosts = hosts_in("192.168.1.77/30").unwrap();
+        assert_eq!(hosts, vec![Ipv4Addr::new(192, 168, 1, 77), Ipv4Addr::new(192, 168, 1, 78)]);
+        assert_eq!(hosts_in("10.0.0.0/24").unwrap().len(), 254);
+        assert_eq!(hosts_in("10.0.0.5").unwrap(), vec![Ipv4Addr::new(10, 0, 0, 5)]);
+        assert!(hosts_in("10.0.0.0/8").is_err());
+        assert!(hosts_in("10.0.0.0/33").is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_hints() {
+        let none = BTreeMap::new();
+        assert_eq!(fingerprint(Some(63), &[22], &none).device, DeviceType::Linux);
+        assert_eq!(fingerprint(Some(127), &[445, 3389], &none).device, DeviceType::Windows);
+        assert_eq!(fingerprint(None, &[], &none).device, DeviceType::Unknown);
+
+        // Embedded Linux TTL, but the ports and banner say printer.
+        let banners = BTreeMap::from([(80, "HP HTTP Server; HP LaserJet M404".to_string())]);
+        let hint = fingerprint(Some(64), &[80, 9100], &banners);
+        assert_eq!(hint.device, DeviceType::Printer);
+        assert_eq!(hint.reasons.len(), 3);
+
+        let banners = BTreeMap::from([(22, "SSH-2.0-Cisco-1.25".to_string())]);
+        assert_eq!(fingerprint(Some(254), &[22, 23], &banners).device, DeviceType::NetworkDevice);
+
+        let mut host = DiscoveredHost {
+            address: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 9)),
+            rtt: Duration::from_millis(1),
+            ttl: Some(64),
+            open_ports: vec![22],
+            banners: BTreeMap::new(),
+            hint: None,
+        };
+        let ping = host.targets();
+        assert_eq!((ping.len(), ping[0].check, ping[0].metadata["source"].as_str()), (1, CheckKind::Icmp, "discovery"));
+        host.hint = Some(fingerprint(host.ttl, &host.open_ports, &host.banners));
+        let linux: Vec<u16> = host.targets().iter().map(|t| t.address.port()).collect();
+        assert_eq!(linux, [0, 22]);
+    }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::check_result::{CheckResult, new_correlation_id};
+use super::ssh::{SshPool, SshTarget};
+use super::target::{CheckSpec, MonitorTarget};
+
+// Pseudo file systems that are always "full" or irrelevant for capacity planning.
+const IGNORED_FS_TYPES: [&str; 5] = ["tmpfs", "devtmpfs", "squashfs", "overlay", "iso9660"];
+
+/// Usage limits in percent; a mount point fails once either is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UsageThreshold {
+    pub max_used_percent: f64,
+    pub max_inodes_percent: f64,
+}
+
+impl Default for UsageThreshold {
+    fn default() -> Self {
+        Self {
+            max_used_percent: 90.0,
+            max_inodes_percent: 90.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DiskCheckConfig {
+    pub default_threshold: UsageThreshold,
+    /// Overrides keyed by mount point, e.g. "/var/lib/postgresql".
+    pub per_mount: HashMap<String, UsageThreshold>,
+    /// Mount points to skip entirely.
+    pub ignore_mounts: Vec<String>,
+}
+
+impl DiskCheckConfig {
+    fn threshold_for(&self, mount: &str) -> UsageThreshold {
+        self.per_mount.get(mount).copied().unwrap_or(self.default_threshold)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountUsage {
+    pub mount: String,
+    pub filesystem: String,
+    pub total: u64,
+    pub used: u64,
+    pub available: u64,
+}
+
+impl MountUsage {
+    pub fn used_percent(&self) -> f64 {
+        // Same formula as df: reserved blocks count as unavailable.
+        let usable = self.used + self.available;
+        if usable == 0 {
+            0.0
+        } else {
+            self.used as f64 * 100.0 / usable as f64
+        }
+    }
+}
+
+/// Parses POSIX `df -P` output (blocks or inodes, the columns are the same).
+pub fn parse_df(output: &str) -> Vec<MountUsage> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 6 {
+                return None;
+            }
+            Some(MountUsage {
+                filesystem: fields[0].to_string(),
+                total: fields[1].parse().ok()?,
+                used: fields[2].parse().ok()?,
+                available: fields[3].parse().ok()?,
+                // Mount points may contain spaces, so take everything after the capacity column.
+                mount: fields[5..].join(" "),
+            })
+        })
+        .collect()
+}
+
+/// Turns block and inode usage into one result per mount point.
+pub fn evaluate(host: &str, blocks: &[MountUsage], inodes: &[MountUsage], config: &DiskCheckConfig, latency: Duration) -> Vec<CheckResult> {
+    blocks
+        .iter()
+        .filter(|usage| !config.ignore_mounts.contains(&usage.mount))
+        .map(|usage| {
+            let target = format!("{}/disk{}", host, usage.mount);
+            let threshold = config.threshold_for(&usage.mount);
+            let used = usage.used_percent();
+            // Some file systems (btrfs, vfat) report no inodes at all.
+            let inode_used = inodes
+                .iter()
+                .find(|i| i.mount == usage.mount && i.total > 0)
+                .map(MountUsage::used_percent);
+
+            let mut problems = Vec::new();
+            if used >= threshold.max_used_percent {
+                problems.push(format!(
+                    "{:.1}% used (limit {:.0}%, {} MiB free)",
+                    used,
+                    threshold.max_used_percent,
+                    usage.available / 1024
+                ));
+            }
+            if let Some(inode_used) = inode_used.filter(|p| *p >= threshold.max_inodes_percent) {
+                problems.push(format!(
+                    "{:.1}% inodes used (limit {:.0}%)",
+                    inode_used, threshold.max_inodes_percent
+                ));
+            }
+
+            if problems.is_empty() {
+                CheckResult::success(&target, latency)
+            } else {
+                CheckResult {
+                    latency: Some(latency),
+                    ..CheckResult::failure(&target, format!("{}: {}", usage.mount, problems.join(", ")))
+                }
+            }
+        })
+        .collect()
+}
+
+/// Reads block and inode usage over SSH and checks every real file system on the host.
+/// All mount point results of one run share a correlation ID.
+pub async fn check_disks(pool: &SshPool, target: &SshTarget, config: &DiskCheckConfig, timeout: Duration) -> Vec<CheckResult> {
+    let run_id = new_correlation_id();
+    query_disks(pool, target, config, timeout)
+        .await
+        .into_iter()
+        .map(|result| result.in_run(&run_id))
+        .collect()
+}
+
+async fn query_disks(pool: &SshPool, target: &SshTarget, config: &DiskCheckConfig, timeout: Duration) -> Vec<CheckResult> {
+    let excludes: String = IGNORED_FS_TYPES.iter().map(|t| format!(" -x {}", t)).collect();
+    let start = Instant::now();
+    // Errors become strings right away, as the future has to stay `Send` across the second run.
+    let blocks = pool.run(target, &format!("df -P -k{}", excludes), timeout).await.map_err(|e| e.to_string());
+    let inodes = pool.run(target, &format!("df -P -i{}", excludes), timeout).await.map_err(|e| e.to_string());
+    let latency = start.elapsed();
+
+    match (blocks, inodes) {
+        (Ok(blocks), Ok(inodes)) => {
+            let blocks = parse_df(&blocks.stdout);
+            if blocks.is_empty() {
+                return vec![CheckResult::failure(&format!("{}/disk", target.host), "df returned no file systems")];
+            }
+            evaluate(&target.host, &blocks, &parse_df(&inodes.stdout), config, latency)
+        }
+        (Err(e), _) | (_, Err(e)) => vec![CheckResult::failure(&format!("{}/disk", target.host), e)],
+    }
+}
+
+/// Runs the disk check of a `CheckSpec::Disk` target as one result, with a step per
+/// mount point.
+pub async fn check_target(pool: &SshPool, target: &MonitorTarget, timeout: Duration) -> CheckResult {
+    let name = target.address.to_string();
+    let Some(CheckSpec::Disk { user, identity_file, limits }) = target.spec() else {
+        return CheckResult::failure(&name, "disk check without SSH settings");
+    };
+    let host = SshTarget::for_target(target, user, identity_file.clone());
+    CheckResult::from_steps(&name, check_disks(pool, &host, limits, timeout).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DF_BLOCKS: &str = "Filesystem     1024-blocks      Used Available Capacity Mounted on
+/dev/sda1         41152736  38000000   1039316      98% /
+/dev/sdb1        103081248  20000000  77821984      21% /var/lib/my data
+";
+
+    const DF_INODES: &str = "Filesystem      Inodes  IUsed   IFree IUse% Mounted on
+/dev/sda1      2621440 100000 2521440      4% /
+/dev/sdb1      6553600 6500000   53600     100% /var/lib/my data
+";
+
+    #[test]
+    fn test_parse_df_handles_spaces_in_mount() {
+        let usage = parse_df(DF_BLOCKS);
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[1].mount, "/var/lib/my data");
+        assert!(usage[0].used_percent() > 97.0);
+    }
+
+    #[test]
+    fn test_block_and_inode_thresholds() {
+        let results = evaluate(
+            "web01",
+            &parse_df(DF_BLOCKS),
+            &parse_df(DF_INODES),
+            &DiskCheckConfig::default(),
+            Duration::from_millis(5),
+        );
+        assert_eq!(results.len(), 2);
+        assert!(!results[0].success);
+        assert!(results[0].error.as_deref().unwrap().contains("% used"));
+        assert!(!results[1].success);
+        assert!(results[1].error.as_deref().unwrap().contains("inodes"));
+    }
+
+    #[test]
+    fn test_per_mount_override() {
+        let mut config = DiskCheckConfig::default();
+        config.per_mount.insert(
+            "/".to_string(),
+            UsageThreshold {
+                max_used_percent: 99.5,
+                max_inodes_percent: 90.0,
+            },
+        );
+        config.ignore_mounts.push("/var/lib/my data".to_string());
+        let results = evaluate("web01", &parse_df(DF_BLOCKS), &parse_df(DF_INODES), &config, Duration::ZERO);
+        assert_eq!(results.len(), 1);
+        assert!(results[0].success);
+    }
+}
@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Mutex;
+
+use super::check_result::new_correlation_id;
+use super::event_bus::{EventBus, MonitorEvent};
+use super::severity::Severity;
+
+/// A host name started resolving to a different set of addresses.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolutionChange {
+    pub host: String,
+    pub added: Vec<IpAddr>,
+    pub removed: Vec<IpAddr>,
+    pub current: Vec<IpAddr>,
+    pub at: DateTime<Utc>,
+    /// Correlation ID of the check that noticed the change.
+    pub correlation_id: String,
+}
+
+impl ResolutionChange {
+    /// DNS changes are expected during cutovers; they explain alerts rather than being one.
+    pub fn severity(&self) -> Severity {
+        Severity::Info
+    }
+}
+
+impl fmt::Display for ResolutionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |ips: &[IpAddr]| ips.iter().map(IpAddr::to_string).collect::<Vec<_>>().join(", ");
+        write!(f, "[{}] {} now resolves to {}", self.severity(), self.host, list(&self.current))?;
+        if !self.added.is_empty() {
+            write!(f, " (added {})", list(&self.added))?;
+        }
+        if !self.removed.is_empty() {
+            write!(f, " (removed {})", list(&self.removed))?;
+        }
+        Ok(())
+    }
+}
+
+/// Remembers what host names resolved to and reports when their addresses change.
+pub struct DnsWatcher {
+    bus: EventBus,
+    last: Mutex<HashMap<String, BTreeSet<IpAddr>>>,
+}
+
+impl DnsWatcher {
+    pub fn new(bus: EventBus) -> Self {
+        Self {
+            bus,
+            last: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Remembers the addresses of `host` and returns what changed since the last call.
+    /// The first resolution of a host is not a change.
+    pub fn observe(&self, host: &str, addrs: &BTreeSet<IpAddr>, correlation_id: &str) -> Option<ResolutionChange> {
+        let mut last = self.last.lock().unwrap();
+        let previous = last.insert(host.to_string(), addrs.clone())?;
+        if previous == *addrs {
+            return None;
+        }
+        Some(ResolutionChange {
+            host: host.to_string(),
+            added: addrs.difference(&previous).copied().collect(),
+            removed: previous.difference(addrs).copied().collect(),
+            current: addrs.iter().copied().collect(),
+            at: Utc::now(),
+            correlation_id: correlation_id.to_string(),
+        })
+    }
+
+    /// Resolves `host` and publishes a `ResolutionChanged` event if its addresses moved
+    /// since the last call.
+    pub async fn resolve(&self, host: &str, port: u16) -> io::Result<BTreeSet<SocketAddr>> {
+        let addrs: BTreeSet<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+        let ips: BTreeSet<IpAddr> = addrs.iter().map(|a| a.ip()).collect();
+        if !ips.is_empty()
+            && let Some(change) = self.observe(host, &ips, &new_correlation_id())
+        {
+            self.bus.publish(MonitorEvent::ResolutionChanged(change));
+        }
+        Ok(addrs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ips(list: &[&str]) -> BTreeSet<IpAddr> {
+        list.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_changes_are_reported_after_first_resolution() {
+        let watcher = DnsWatcher::new(EventBus::new());
+        assert!(watcher.observe("api.example", &ips(&["10.0.0.1", "10.0.0.2"]), "a").is_none());
+        assert!(watcher.observe("api.example", &ips(&["10.0.0.2", "10.0.0.1"]), "b").is_none());
+
+        let change = watcher.observe("api.example", &ips(&["10.0.0.2", "10.0.0.3"]), "c").unwrap();
+        assert_eq!(change.added, vec!["10.0.0.3".parse::<IpAddr>().unwrap()]);
+        assert_eq!(change.removed, vec!["10.0.0.1".parse::<IpAddr>().unwrap()]);
+        assert_eq!(change.correlation_id, "c");
+        assert!(change.to_string().contains("added 10.0.0.3"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_publishes_changes() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let watcher = DnsWatcher::new(bus);
+        watcher.observe("localhost", &ips(&["10.0.0.1"]), "a");
+        let addrs = watcher.resolve("localhost", 80).await.unwrap();
+        assert!(addrs.iter().all(|a| a.ip().is_loopback() && a.port() == 80));
+        match events.try_recv().unwrap() {
+            MonitorEvent::ResolutionChanged(change) => assert_eq!(change.removed, vec!["10.0.0.1".parse::<IpAddr>().unwrap()]),
+            other => panic!("unexpected event {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::Argon2;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// File layout: MAGIC | mode | [salt] | nonce | ciphertext (AES-256-GCM, tag included).
+const MAGIC: &[u8; 8] = b"RNPMENC1";
+const MODE_PASSPHRASE: u8 = 1;
+const MODE_KEYRING: u8 = 2;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+const KEY_LEN: usize = 32;
+
+/// Environment variable read by `KeySource::from_env` so the passphrase never has to be
+/// typed into a config file or command line.
+pub const PASSPHRASE_ENV: &str = "RUST_NPM_CONFIG_PASSPHRASE";
+
+/// Environment variable naming the OS keyring entry holding the key, as `service/account`
+/// or just `service` (account `config`). Read when no passphrase is set.
+pub const KEYRING_ENV: &str = "RUST_NPM_CONFIG_KEYRING";
+
+// Keyring account used when `KEYRING_ENV` names only the service.
+#[cfg(feature = "os-keyring")]
+const DEFAULT_KEYRING_ACCOUNT: &str = "config";
+
+/// Where the key for an encrypted config file comes from.
+#[derive(Clone)]
+pub enum KeySource {
+    /// Key derived from a passphrase with Argon2id; a fresh salt is stored in each file.
+    Passphrase(String),
+    /// Random key kept in the OS keyring (Keychain, Credential Manager, kernel keyring).
+    #[cfg(feature = "os-keyring")]
+    Keyring { service: String, account: String },
+}
+
+impl std::fmt::Debug for KeySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeySource::Passphrase(_) => f.write_str("Passphrase(***)"),
+            #[cfg(feature = "os-keyring")]
+            KeySource::Keyring { service, account } => write!(f, "Keyring({}/{})", service, account),
+        }
+    }
+}
+
+impl KeySource {
+    /// The passphrase in `PASSPHRASE_ENV`, or else the keyring entry in `KEYRING_ENV`.
+    pub fn from_env() -> Option<Self> {
+        let passphrase = std::env::var(PASSPHRASE_ENV).ok().filter(|p| !p.is_empty()).map(KeySource::Passphrase);
+        passphrase.or_else(|| Self::keyring(&std::env::var(KEYRING_ENV).ok()?))
+    }
+
+    /// The keyring entry named by `spec`, see `KEYRING_ENV`.
+    #[cfg(feature = "os-keyring")]
+    pub fn keyring(spec: &str) -> Option<Self> {
+        let (service, account) = spec.split_once('/').unwrap_or((spec, DEFAULT_KEYRING_ACCOUNT));
+        (!service.is_empty() && !account.is_empty()).then(|| KeySource::Keyring {
+            service: service.to_string(),
+            account: account.to_string(),
+        })
+    }
+
+    /// Without the `os-keyring` feature there is no keyring to read.
+    #[cfg(not(feature = "os-keyring"))]
+    pub fn keyring(spec: &str) -> Option<Self> {
+        if !spec.is_empty() {
+            eprintln!("{} ignored: built without the os-keyring feature", KEYRING_ENV);
+        }
+        None
+    }
+
+    fn mode(&self) -> u8 {
+        match self {
+            KeySource::Passphrase(_) => MODE_PASSPHRASE,
+            #[cfg(feature = "os-keyring")]
+            KeySource::Keyring { .. } => MODE_KEYRING,
+        }
+    }
+
+    /// Returns the key, creating it first when `create` is set and the source supports it.
+    #[cfg_attr(not(feature = "os-keyring"), allow(unused_variables))]
+    fn key(&self, salt: &[u8], create: bool) -> Result<[u8; KEY_LEN], Box<dyn Error>> {
+        let mut key = [0u8; KEY_LEN];
+        match self {
+            KeySource::Passphrase(passphrase) => {
+                Argon2::default()
+                    .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+                    .map_err(|e| format!("key derivation failed: {}", e))?;
+            }
+            #[cfg(feature = "os-keyring")]
+            KeySource::Keyring { service, account } => {
+                let entry = keyring::Entry::new(service, account)?;
+                let stored = match entry.get_password() {
+                    Ok(stored) => stored,
+                    Err(keyring::Error::NoEntry) if create => {
+                        OsRng.fill_bytes(&mut key);
+                        entry.set_password(&hex::encode(key))?;
+                        return Ok(key);
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                let bytes = hex::decode(stored.trim())?;
+                if bytes.len() != KEY_LEN {
+                    return Err(format!("keyring entry {}/{} is not a {}-byte key", service, account, KEY_LEN).into());
+                }
+                key.copy_from_slice(&bytes);
+            }
+        }
+        Ok(key)
+    }
+}
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub fn encrypt(plaintext: &[u8], source: &KeySource) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mode = source.mode();
+    let mut salt = [0u8; SALT_LEN];
+    if mode == MODE_PASSPHRASE {
+        OsRng.fill_bytes(&mut salt);
+    }
+    let key = source.key(&salt, true)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| "encryption failed")?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(MAGIC);
+    out.push(mode);
+    if mode == MODE_PASSPHRASE {
+        out.extend_from_slice(&salt);
+    }
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn decrypt(data: &[u8], source: &KeySource) -> Result<Vec<u8>, Box<dyn Error>> {
+    let rest = data.strip_prefix(MAGIC).ok_or("not an encrypted config file")?;
+    let (&mode, rest) = rest.split_first().ok_or("encrypted config file is truncated")?;
+    if mode != source.mode() {
+        let expected = if mode == MODE_KEYRING { "an OS keyring key" } else { "a passphrase" };
+        return Err(format!("config file was encrypted with {}", expected).into());
+    }
+    let (salt, rest) = if mode == MODE_PASSPHRASE {
+        rest.split_at_checked(SALT_LEN).ok_or("encrypted config file is truncated")?
+    } else {
+        (&[][..], rest)
+    };
+    let (nonce, ciphertext) = rest.split_at_checked(NONCE_LEN).ok_or("encrypted config file is truncated")?;
+
+    let key = source.key(salt, false)?;
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+    // GCM authenticates the data, so a wrong key and a tampered file look the same.
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| "cannot decrypt config file: wrong key or corrupted file".into())
+}
+
+/// Reads a config file, decrypting it in memory if it is encrypted. Plain text files are
+/// returned as they are, so encryption can be switched on without a migration step.
+pub fn read_config(path: &Path, source: Option<&KeySource>) -> Result<String, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    if !is_encrypted(&data) {
+        return Ok(String::from_utf8(data)?);
+    }
+    let source = source.ok_or_else(|| {
+        format!(
+            "{} is encrypted; set {} or {}",
+            path.display(),
+            PASSPHRASE_ENV,
+            KEYRING_ENV
+        )
+    })?;
+    Ok(String::from_utf8(decrypt(&data, source)?)?)
+}
+
+/// Encrypts `contents` and replaces the file at `path` with it.
+pub fn write_encrypted(path: &Path, contents: &str, source: &KeySource) -> Result<(), Box<dyn Error>> {
+    let data = encrypt(contents.as_bytes(), source)?;
+    // Write next to the target and rename, so a crash never leaves a half-written file.
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Encrypts the plain file at `path` in place; `false` if it already was encrypted.
+pub fn encrypt_file(path: &Path, source: &KeySource) -> Result<bool, Box<dyn Error>> {
+    let data = fs::read(path)?;
+    if is_encrypted(&data) {
+        return Ok(false);
+    }
+    write_encrypted(path, &String::from_utf8(data)?, source)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_passphrase_round_trip() {
+        let source = KeySource::Passphrase("correct horse".to_string());
+        let data = encrypt(b"targets: [db:5432]", &source).unwrap();
+        assert!(is_encrypted(&data));
+        assert_eq!(decrypt(&data, &source).unwrap(), b"targets: [db:5432]");
+    }
+
+    #[test]
+    fn test_wrong_passphrase_and_tampering_are_rejected() {
+        let source = KeySource::Passphrase("correct horse".to_string());
+        let mut data = encrypt(b"secret", &source).unwrap();
+        assert!(decrypt(&data, &KeySource::Passphrase("battery staple".to_string())).is_err());
+        *data.last_mut().unwrap() ^= 1;
+        assert!(decrypt(&data, &source).is_err());
+    }
+
+    #[cfg(feature = "os-keyring")]
+    #[test]
+    fn test_keyring_entry_is_named_by_the_setting() {
+        assert_eq!(format!("{:?}", KeySource::keyring("rust_npm").unwrap()), "Keyring(rust_npm/config)");
+        assert_eq!(format!("{:?}", KeySource::keyring("rust_npm/ops").unwrap()), "Keyring(rust_npm/ops)");
+        assert!(KeySource::keyring("rust_npm/").is_none());
+    }
+
+    #[test]
+    fn test_plain_files_are_read_unchanged() {
+        let dir = std::env::temp_dir().join(format!("rnpm-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, "plain = true").unwrap();
+        assert_eq!(read_config(&path, None).unwrap(), "plain = true");
+
+        let source = KeySource::Passphrase("pw".to_string());
+        assert!(encrypt_file(&path, &source).unwrap());
+        assert!(!encrypt_file(&path, &source).unwrap());
+        assert_eq!(read_config(&path, Some(&source)).unwrap(), "plain = true");
+        write_encrypted(&path, "plain = false", &source).unwrap();
+        assert!(read_config(&path, None).is_err());
+        assert_eq!(read_config(&path, Some(&source)).unwrap(), "plain = false");
+        fs::remove_dir_all(&dir).ok();
+    }
+}
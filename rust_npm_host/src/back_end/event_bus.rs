@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+
+use super::canary::ConnectivityChange;
+use super::check_result::CheckResult;
+use super::dns_watch::ResolutionChange;
+use super::ha::Role;
+use super::monitor::CheckKind;
+use super::notify::Notification;
+use super::pause::Pause;
+use super::service::ServiceChange;
+use super::staleness::StaleChange;
+use super::state_tracker::Transition;
+use super::target::MonitorTarget;
+
+// Subscribers that fall further behind than this start missing events (and are told so).
+const BUS_CAPACITY: usize = 1024;
+
+/// Something that happened in the monitoring core.
+#[derive(Debug, Clone)]
+pub enum MonitorEvent {
+    TargetAdded(SocketAddr),
+    TargetRemoved(SocketAddr),
+    /// `None` when the target was resumed.
+    TargetPaused { target: SocketAddr, pause: Option<Pause> },
+    TargetGrouped { target: SocketAddr, group: Option<String> },
+    CheckKindChanged { target: SocketAddr, kind: CheckKind },
+    /// A target's check settings (timeout, interval, retries, ...) were replaced.
+    TargetConfigured(MonitorTarget),
+    /// Someone acknowledged that the target is down; cleared again when it recovers.
+    AlertAcknowledged(SocketAddr),
+    CheckStarted(SocketAddr),
+    CheckCompleted(CheckResult),
+    Transition(Transition),
+    /// The rollup status of a service (a group of targets) changed.
+    ServiceChanged(ServiceChange),
+    /// A monitored host name now resolves to different addresses. Informational only.
+    ResolutionChanged(ResolutionChange),
+    /// This instance of an HA pair became active or went to standby.
+    HaRoleChanged { node: String, role: Role, at: DateTime<Utc> },
+    /// Traffic of a failing target was captured to `path`, as evidence for the outage the
+    /// check run `correlation_id` belongs to.
+    CaptureSaved { target: SocketAddr, path: PathBuf, packets: usize, correlation_id: String },
+    /// A target went without results for longer than expected, or has them again.
+    StaleChanged(StaleChange),
+    /// The monitor lost or regained its own internet connectivity, see `canary`.
+    ConnectivityChanged(ConnectivityChange),
+    /// Notifications were held back during an alert storm; this sums them up, see
+    /// `notify::SharedStormGuard`.
+    StormSummary(Notification),
+    /// Low-power mode was turned on (`true`) or off.
+    LowPowerChanged(bool),
+    /// The monitor stopped checking and is about to exit; listeners should finish up.
+    ShuttingDown,
+}
+
+/// Fan-out channel between the monitoring core and everything that wants to watch it
+/// (GUI, notifiers, storage). Cloning it gives another handle to the same bus.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<MonitorEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(BUS_CAPACITY);
+        Self { sender }
+    }
+
+    /// Sends an event to all current subscribers. Having none is not an error.
+    pub fn publish(&self, event: MonitorEvent) {
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MonitorEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::error::Error;
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use std::net::IpAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use super::annotations::{Annotation, AnnotationStore, availability};
+use super::check_result::CheckResult;
+use super::history::History;
+use super::timezone::format_local;
+
+// Traceroute gives up on a hop after 2 s; 30 silent hops then take a minute.
+const TRACEROUTE_TIMEOUT: Duration = Duration::from_secs(90);
+
+/// A run of consecutive failed checks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outage {
+    pub from: DateTime<Utc>,
+    /// First successful check afterwards; `None` if the target was still down at the end.
+    pub to: Option<DateTime<Utc>>,
+    pub failed_checks: usize,
+    pub first_error: String,
+    pub correlation_id: String,
+}
+
+/// The outages in `results`, which must be sorted oldest first.
+pub fn outages(results: &[CheckResult]) -> Vec<Outage> {
+    let mut outages: Vec<Outage> = Vec::new();
+    let mut open = false;
+    for result in results {
+        match (result.success, open) {
+            (false, false) => outages.push(Outage {
+                from: result.timestamp,
+                to: None,
+                failed_checks: 1,
+                first_error: result.error.clone().unwrap_or_default(),
+                correlation_id: result.correlation_id.clone(),
+            }),
+            (false, true) => outages.last_mut().expect("an open outage").failed_checks += 1,
+            (true, true) => outages.last_mut().expect("an open outage").to = Some(result.timestamp),
+            (true, false) => {}
+        }
+        open = !result.success;
+    }
+    outages
+}
+
+/// Everything known about one target's incident, packed into one zip for vendor tickets:
+/// the check results (JSON and CSV), annotations, packet captures and any other files
+/// added, a human-readable `summary.txt` and a `manifest.json` with SHA-256 hashes.
+#[derive(Debug, Clone)]
+pub struct EvidenceBundle {
+    target: String,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    generated_at: DateTime<Utc>,
+    results: Vec<CheckResult>,
+    annotations: Vec<Annotation>,
+    files: Vec<(String, Vec<u8>)>,
+    notes: Vec<String>,
+    tz: Tz,
+}
+
+impl EvidenceBundle {
+    pub fn new(target: &str, from: DateTime<Utc>, to: DateTime<Utc>, now: DateTime<Utc>) -> Self {
+        Self {
+            target: target.to_string(),
+            from,
+            to,
+            generated_at: now,
+            results: Vec::new(),
+            annotations: Vec::new(),
+            files: Vec::new(),
+            notes: Vec::new(),
+            tz: Tz::UTC,
+        }
+    }
+
+    /// Shows the times in `summary.txt` in `tz` instead of UTC; the data files keep UTC.
+    pub fn in_timezone(mut self, tz: Tz) -> Self {
+        self.tz = tz;
+        self
+    }
+
+    /// Keeps the results inside the window, oldest first, and their artifacts (e.g. the
+    /// HAR files of browser checks) as files under `artifacts/`.
+    pub fn add_results(&mut self, results: &[CheckResult]) {
+        let kept: Vec<&CheckResult> = results.iter().filter(|r| self.from <= r.timestamp && r.timestamp <= self.to).collect();
+        for result in &kept {
+            for artifact in &result.artifacts {
+                match artifact.bytes() {
+                    Ok(bytes) => self.add_file(&format!("artifacts/{}-{}", result.timestamp.format("%Y%m%dT%H%M%S"), artifact.name), bytes),
+                    Err(e) => self.note(format!("artifact {} of {}: {}", artifact.name, result.correlation_id, e)),
+                }
+            }
+        }
+        self.results.extend(kept.into_iter().cloned());
+        self.results.sort_by_key(|r| r.timestamp);
+    }
+
+    pub fn add_annotations<'a>(&mut self, annotations: impl IntoIterator<Item = &'a Annotation>) {
+        self.annotations.extend(annotations.into_iter().cloned());
+    }
+
+    /// Adds `bytes` as `name`; a name already taken gets a numbered suffix.
+    pub fn add_file(&mut self, name: &str, bytes: Vec<u8>) {
+        let mut unique = name.to_string();
+        let mut n = 1;
+        while self.files.iter().any(|(taken, _)| *taken == unique) {
+            n += 1;
+            unique = match name.rsplit_once('.') {
+                Some((stem, ext)) => format!("{}_{}.{}", stem, n, ext),
+                None => format!("{}_{}", name, n),
+            };
+        }
+        self.files.push((unique, bytes));
+    }
+
+    /// Adds the file at `path` under `folder/`.
+    pub fn add_path(&mut self, folder: &str, path: &Path) -> Result<(), Box<dyn Error>> {
+        let name = path.file_name().and_then(|n| n.to_str()).ok_or_else(|| format!("{} has no file name", path.display()))?;
+        self.add_file(&format!("{}/{}", folder, name), std::fs::read(path)?);
+        Ok(())
+    }
+
+    /// A remark for `summary.txt`, e.g. evidence that could not be collected.
+    pub fn note(&mut self, note: impl Into<String>) {
+        self.notes.push(note.into());
+    }
+
+    /// `evidence_<target>_<from>.zip`, safe for file names.
+    pub fn file_name(&self) -> String {
+        let target: String = self
+            .target
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '.' { c } else { '_' })
+            .collect();
+        format!("evidence_{}_{}.zip", target, self.from.format("%Y%m%dT%H%MZ"))
+    }
+
+    pub fn to_zip(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut files = vec![
+            ("results.json".to_string(), serde_json::to_vec_pretty(&self.results)?),
+            ("results.csv".to_string(), results_csv(&self.results)?),
+        ];
+        if !self.annotations.is_empty() {
+            files.push(("annotations.json".to_string(), serde_json::to_vec_pretty(&self.annotations)?));
+        }
+        files.extend(self.files.iter().cloned());
+        files.insert(0, ("summary.txt".to_string(), self.summary(&files).into_bytes()));
+
+        let manifest = json!({
+            "target": self.target,
+            "from": self.from,
+            "to": self.to,
+            "generated_at": self.generated_at,
+            "files": files.iter().map(|(name, bytes)| json!({
+                "name": name,
+                "bytes": bytes.len(),
+                "sha256": hex::encode(Sha256::digest(bytes)),
+            })).collect::<Vec<_>>(),
+        });
+        files.push(("manifest.json".to_string(), serde_json::to_vec_pretty(&manifest)?));
+
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        for (name, bytes) in &files {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(bytes)?;
+        }
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn summary(&self, files: &[(String, Vec<u8>)]) -> String {
+        let time = |at: DateTime<Utc>| format_local(at, self.tz);
+        let mut out = String::new();
+        let _ = writeln!(out, "Evidence for {}", self.target);
+        let _ = writeln!(out, "Window:    {} .. {}", time(self.from), time(self.to));
+        let _ = writeln!(out, "Generated: {}", time(self.generated_at));
+        let failed = self.results.iter().filter(|r| !r.success).count();
+        let _ = writeln!(out, "\nChecks: {}, failed: {}", self.results.len(), failed);
+        let counted: Vec<CheckResult> = self
+            .results
+            .iter()
+            .filter(|r| !self.annotations.iter().any(|a| a.exclude && a.covers(r)))
+            .cloned()
+            .collect();
+        if let Some(share) = availability(&counted) {
+            let excluded = self.results.len() - counted.len();
+            let _ = writeln!(out, "Availability: {:.3}% ({} checks in excluded spans left out)", share * 100.0, excluded);
+        }
+        let outages = outages(&self.results);
+        let _ = writeln!(out, "\nOutages ({}):", outages.len());
+        for outage in &outages {
+            let end = outage.to.map_or("still down".to_string(), time);
+            let _ = writeln!(
+                out,
+                "  {} .. {}, {} failed checks: {} [{}]",
+                time(outage.from),
+                end,
+                outage.failed_checks,
+                outage.first_error,
+                outage.correlation_id
+            );
+        }
+        if !self.annotations.is_empty() {
+            let _ = writeln!(out, "\nAnnotations:");
+            for annotation in &self.annotations {
+                let _ = writeln!(out, "  {}", annotation);
+            }
+        }
+        if !self.notes.is_empty() {
+            let _ = writeln!(out, "\nNotes:");
+            for note in &self.notes {
+                let _ = writeln!(out, "  {}", note);
+            }
+        }
+        let _ = writeln!(out, "\nFiles (hashes in manifest.json):");
+        for (name, bytes) in files {
+            let _ = writeln!(out, "  {} ({} bytes)", name, bytes.len());
+        }
+        out
+    }
+}
+
+/// A bundle of what `history` and `annotations` hold on `target` between `from` and `to`.
+pub fn collect(
+    history: &History,
+    annotations: Option<&AnnotationStore>,
+    target: &str,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> EvidenceBundle {
+    let mut bundle = EvidenceBundle::new(target, from, to, now);
+    bundle.add_results(&history.results(target, from));
+    if let Some(store) = annotations {
+        bundle.add_annotations(store.for_target(target, from, to));
+    }
+    bundle
+}
+
+fn results_csv(results: &[CheckResult]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["timestamp", "success", "latency_ms", "error", "failure_kind", "correlation_id", "resolved_ip", "agent"])?;
+    for r in results {
+        writer.write_record([
+            r.timestamp.to_rfc3339(),
+            r.success.to_string(),
+            r.latency_ms().map_or(String::new(), |ms| format!("{:.3}", ms)),
+            r.error.clone().unwrap_or_default(),
+            r.failure_kind.map_or(String::new(), |kind| kind.to_string()),
+            r.correlation_id.clone(),
+            r.resolved_ip.map_or(String::new(), |ip| ip.to_string()),
+            r.agent.clone().unwrap_or_default(),
+        ])?;
+    }
+    Ok(writer.into_inner().map_err(|e| e.to_string())?)
+}
+
+/// Runs the system's traceroute (`tracert` on Windows) to `ip` and returns its output.
+/// It shows today's path, which may differ from the path during the incident.
+pub async fn traceroute(ip: IpAddr) -> Result<String, String> {
+    let mut command = if cfg!(windows) {
+        let mut command = tokio::process::Command::new("tracert");
+        command.args(["-d", "-w", "2000"]);
+        command
+    } else {
+        let mut command = tokio::process::Command::new("traceroute");
+        command.args(["-n", "-w", "2", "-q", "1"]);
+        command
+    };
+    command.arg(ip.to_string()).kill_on_drop(true);
+    let output = tokio::time::timeout(TRACEROUTE_TIMEOUT, command.output())
+        .await
+        .map_err(|_| format!("traceroute did not finish within {:?}", TRACEROUTE_TIMEOUT))?
+        .map_err(|e| format!("cannot run traceroute: {}", e))?;
+    if !output.status.success() && output.stdout.is_empty() {
+        return Err(format!("traceroute failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, TimeZone};
+    use std::io::Read;
+
+    #[test]
+    fn test_bundle_holds_results_summary_and_hashes() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        let at = |minutes: i64, mut result: CheckResult| {
+            result.timestamp = start + ChronoDuration::minutes(minutes);
+            result
+        };
+        let results = [
+            at(-10, CheckResult::failure("db:5432", "before the window")),
+            at(0, CheckResult::success("db:5432", Duration::from_millis(4))),
+            at(1, CheckResult::failure("db:5432", "connection refused")),
+            at(2, CheckResult::failure("db:5432", "connection refused")),
+            at(3, CheckResult::success("db:5432", Duration::from_millis(5))),
+            at(4, CheckResult::failure("db:5432", "timed out after 1s")),
+        ];
+        let mut bundle = EvidenceBundle::new("db:5432", start, start + ChronoDuration::hours(1), start + ChronoDuration::hours(2));
+        bundle.add_results(&results);
+        bundle.add_file("traceroute.txt", b"1 10.0.0.1".to_vec());
+        bundle.add_file("traceroute.txt", b"1 10.0.0.1".to_vec());
+        bundle.note("no captures");
+
+        let found = outages(&bundle.results);
+        assert_eq!(found.len(), 2);
+        assert_eq!((found[0].failed_checks, found[0].to), (2, Some(start + ChronoDuration::minutes(3))));
+        assert_eq!(found[1].to, None);
+        assert_eq!(bundle.file_name(), "evidence_db_5432_20261017T0800Z.zip");
+
+        let mut zip = zip::ZipArchive::new(Cursor::new(bundle.to_zip().unwrap())).unwrap();
+        let mut names: Vec<&str> = zip.file_names().collect();
+        names.sort();
+        assert_eq!(names, ["manifest.json", "results.csv", "results.json", "summary.txt", "traceroute.txt", "traceroute_2.txt"]);
+        let mut summary = String::new();
+        zip.by_name("summary.txt").unwrap().read_to_string(&mut summary).unwrap();
+        assert!(summary.contains("Checks: 5, failed: 3"), "{}", summary);
+        assert!(summary.contains("Availability: 40.000% (0 checks in excluded spans left out)"), "{}", summary);
+        assert!(summary.contains("Outages (2):"));
+        let mut manifest = String::new();
+        zip.by_name("manifest.json").unwrap().read_to_string(&mut manifest).unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["files"].as_array().unwrap().len(), 5);
+    }
+}
@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::check_result::CheckResult;
+
+/// Why a check failed, so reports can break failures down by cause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureKind {
+    DnsFailure,
+    ConnectRefused,
+    ConnectTimeout,
+    TlsError,
+    Http5xx,
+    /// The target answered, but not with what the check expected (status, element, text).
+    ContentMismatch,
+    BrowserTimeout,
+    /// Our own side broke (SSH hop, proxy, WebDriver, plugin), not necessarily the target.
+    InfraError,
+    Other,
+}
+
+impl fmt::Display for FailureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FailureKind::DnsFailure => "DNS failure",
+            FailureKind::ConnectRefused => "connection refused",
+            FailureKind::ConnectTimeout => "connect timeout",
+            FailureKind::TlsError => "TLS error",
+            FailureKind::Http5xx => "HTTP 5xx",
+            FailureKind::ContentMismatch => "content mismatch",
+            FailureKind::BrowserTimeout => "browser timeout",
+            FailureKind::InfraError => "infrastructure error",
+            FailureKind::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FailureKind {
+    /// Best guess from an error message, for checks that don't set the kind themselves.
+    /// The order matters: "resolving x timed out" is a DNS problem, "TLS handshake timed
+    /// out" a TLS one.
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_ascii_lowercase();
+        let has = |needles: &[&str]| needles.iter().any(|n| error.contains(n));
+
+        if has(&["webdriver", "plugin '", "proxy ", "ssh", "tunnel", "winrm"]) {
+            FailureKind::InfraError
+        } else if has(&["resolv", "dns", "lookup address", "no addresses", "name or service not known"]) {
+            FailureKind::DnsFailure
+        } else if has(&["tls", "ssl", "certificate", "handshake"]) {
+            FailureKind::TlsError
+        } else if has(&["refused"]) {
+            FailureKind::ConnectRefused
+        } else if has(&["timed out", "timeout"]) {
+            FailureKind::ConnectTimeout
+        } else if error.contains("http 5") || error.contains("returned 5") {
+            FailureKind::Http5xx
+        } else if error.contains("http 4") || error.contains("returned 4") {
+            FailureKind::ContentMismatch
+        } else {
+            FailureKind::Other
+        }
+    }
+}
+
+/// Number of failed results per failure kind.
+pub fn breakdown(results: &[CheckResult]) -> BTreeMap<FailureKind, usize> {
+    let mut counts = BTreeMap::new();
+    for result in results.iter().filter(|r| !r.success) {
+        *counts.entry(result.failure_kind.unwrap_or(FailureKind::Other)).or_insert(0) += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_classify_common_errors() {
+        assert_eq!(FailureKind::classify("resolving api.example timed out"), FailureKind::DnsFailure);
+        assert_eq!(FailureKind::classify("Connection refused (os error 111)"), FailureKind::ConnectRefused);
+        assert_eq!(FailureKind::classify("timed out after 1s"), FailureKind::ConnectTimeout);
+        assert_eq!(FailureKind::classify("invalid peer certificate: Expired"), FailureKind::TlsError);
+        assert_eq!(FailureKind::classify("HTTP 503 Service Unavailable"), FailureKind::Http5xx);
+        assert_eq!(FailureKind::classify("proxy branch failed: refused"), FailureKind::InfraError);
+        assert_eq!(FailureKind::classify("disk 97% full"), FailureKind::Other);
+    }
+
+    #[test]
+    fn test_breakdown_counts_failures_only() {
+        let results = vec![
+            CheckResult::success("db:5432", Duration::from_millis(2)),
+            CheckResult::failure("db:5432", "refused"),
+            CheckResult::failure("db:5432", "refused"),
+            CheckResult::failure("web:443", "HTTP 502 Bad Gateway"),
+        ];
+        let counts = breakdown(&results);
+        assert_eq!(counts[&FailureKind::ConnectRefused], 2);
+        assert_eq!(counts[&FailureKind::Http5xx], 1);
+        assert_eq!(counts.len(), 2);
+    }
+}
@@ -0,0 +1,255 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::event_bus::MonitorEvent;
+use super::monitor::Monitor;
+
+// Creates the lease table on first use; one row per HA pair.
+const CREATE_LEASE_TABLE: &str = "CREATE TABLE IF NOT EXISTS monitor_lease (
+    name TEXT PRIMARY KEY,
+    holder TEXT NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+)";
+
+// Takes the lease if it is free, expired or already ours; returns the holder afterwards.
+const ACQUIRE_LEASE: &str = "INSERT INTO monitor_lease (name, holder, expires_at)
+VALUES ($1, $2, now() + make_interval(secs => $3))
+ON CONFLICT (name) DO UPDATE SET holder = EXCLUDED.holder, expires_at = EXCLUDED.expires_at
+WHERE monitor_lease.holder = EXCLUDED.holder OR monitor_lease.expires_at < now()
+RETURNING holder";
+
+// How often and how long a node waits for the other to release the lease file's lock.
+const LOCK_ATTEMPTS: usize = 20;
+const LOCK_RETRY: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Role {
+    Active,
+    Standby,
+}
+
+/// Where the two instances of a pair agree on who is active.
+#[derive(Debug, Clone)]
+pub enum LeaseBackend {
+    /// A row in the shared Postgres database; the database clock decides expiry.
+    Postgres { pool: sqlx::PgPool, name: String },
+    /// A small JSON file on storage both instances can reach (e.g. an NFS share). Meant for
+    /// setups without a database; the instances' clocks must be roughly in sync.
+    File(PathBuf),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileLease {
+    holder: String,
+    expires_at: DateTime<Utc>,
+}
+
+impl LeaseBackend {
+    /// A `postgres://` URL for the database backend, otherwise the path of the lease file.
+    pub async fn open(spec: &str) -> Result<Self, Box<dyn Error>> {
+        if spec.starts_with("postgres://") || spec.starts_with("postgresql://") {
+            let pool = sqlx::PgPool::connect(spec).await?;
+            return Ok(LeaseBackend::Postgres { pool, name: "monitor".to_string() });
+        }
+        Ok(LeaseBackend::File(PathBuf::from(spec)))
+    }
+
+    /// Takes or renews the lease for `node_id`. Returns whether this node holds it now.
+    pub async fn acquire(&self, node_id: &str, ttl: ChronoDuration, now: DateTime<Utc>) -> Result<bool, Box<dyn Error>> {
+        match self {
+            LeaseBackend::Postgres { pool, name } => {
+                sqlx::query(CREATE_LEASE_TABLE).execute(pool).await?;
+                let holder: Option<String> = sqlx::query_scalar(ACQUIRE_LEASE)
+                    .bind(name)
+                    .bind(node_id)
+                    .bind(ttl.num_milliseconds() as f64 / 1000.0)
+                    .fetch_optional(pool)
+                    .await?;
+                Ok(holder.as_deref() == Some(node_id))
+            }
+            LeaseBackend::File(path) => {
+                // Reading, deciding and writing happen under the lock, so two nodes racing
+                // for an expired lease can't both take it.
+                let _lock = LeaseLock::acquire(path, ttl).await?;
+                let current: Option<FileLease> = match fs::read_to_string(path) {
+                    Ok(content) => serde_json::from_str(&content).ok(),
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                    Err(e) => return Err(e.into()),
+                };
+                if current.is_some_and(|l| l.holder != node_id && l.expires_at > now) {
+                    return Ok(false);
+                }
+                let lease = FileLease {
+                    holder: node_id.to_string(),
+                    expires_at: now + ttl,
+                };
+                // Write-and-rename so a crash never leaves half a file.
+                let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+                fs::write(&tmp, serde_json::to_string(&lease)?)?;
+                fs::rename(&tmp, path)?;
+                Ok(true)
+            }
+        }
+    }
+}
+
+/// Lock file next to a lease file, created with `O_EXCL` (atomic on NFSv3 and later);
+/// removed when dropped.
+struct LeaseLock(PathBuf);
+
+impl LeaseLock {
+    /// Waits a moment while the other node holds the lock. A lock older than `ttl` was left
+    /// by a node that crashed while holding it and is broken.
+    async fn acquire(path: &Path, ttl: ChronoDuration) -> Result<Self, Box<dyn Error>> {
+        let lock = path.with_extension("lock");
+        for _ in 0..LOCK_ATTEMPTS {
+            match fs::OpenOptions::new().write(true).create_new(true).open(&lock) {
+                Ok(_) => return Ok(Self(lock)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    let age = fs::metadata(&lock).and_then(|m| m.modified()).ok().and_then(|t| t.elapsed().ok());
+                    if age.is_some_and(|age| age > ttl.to_std().unwrap_or_default()) {
+                        fs::remove_file(&lock).ok();
+                    } else {
+                        tokio::time::sleep(LOCK_RETRY).await;
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Err(format!("{} is still held by the other node", lock.display()).into())
+    }
+}
+
+impl Drop for LeaseLock {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.0) {
+            eprintln!("Cannot remove lease lock {}: {}", self.0.display(), e);
+        }
+    }
+}
+
+/// Decides whether this instance of an active/standby pair runs checks and sends alerts.
+///
+/// Both instances call `tick` every `heartbeat`. The one holding the lease is active and
+/// keeps renewing it; the standby takes over once the active's lease lapses. An active
+/// instance that can no longer reach the lease store steps down when its own lease
+/// expires, so the two never alert at the same time.
+pub struct HaCoordinator {
+    pub node_id: String,
+    backend: LeaseBackend,
+    lease_ttl: ChronoDuration,
+    role: Role,
+    held_until: Option<DateTime<Utc>>,
+}
+
+impl HaCoordinator {
+    pub fn new(node_id: &str, backend: LeaseBackend, lease_ttl: ChronoDuration) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            backend,
+            lease_ttl,
+            role: Role::Standby,
+            held_until: None,
+        }
+    }
+
+    /// How often to call `tick`: a few renewals fit into one lease.
+    pub fn heartbeat(&self) -> ChronoDuration {
+        self.lease_ttl / 3
+    }
+
+    /// Renews or contends for the lease. Returns the new role when it changed.
+    pub async fn tick(&mut self, now: DateTime<Utc>) -> Option<Role> {
+        let role = match self.backend.acquire(&self.node_id, self.lease_ttl, now).await {
+            Ok(true) => {
+                self.held_until = Some(now + self.lease_ttl);
+                Role::Active
+            }
+            Ok(false) => {
+                self.held_until = None;
+                Role::Standby
+            }
+            Err(e) => {
+                eprintln!("HA lease check failed: {}", e);
+                if self.held_until.is_some_and(|until| now < until) {
+                    self.role
+                } else {
+                    Role::Standby
+                }
+            }
+        };
+        let changed = role != self.role;
+        self.role = role;
+        changed.then_some(role)
+    }
+}
+
+/// Keeps `monitor` in step with the coordinator until the task is dropped: checks only run
+/// while this instance is active.
+pub async fn run(mut coordinator: HaCoordinator, monitor: Arc<Monitor>) {
+    monitor.set_standby(true);
+    let heartbeat = coordinator.heartbeat().to_std().unwrap_or(std::time::Duration::from_secs(5));
+    loop {
+        let now = Utc::now();
+        if let Some(role) = coordinator.tick(now).await {
+            monitor.set_standby(role == Role::Standby);
+            monitor.bus().publish(MonitorEvent::HaRoleChanged {
+                node: coordinator.node_id.clone(),
+                role,
+                at: now,
+            });
+        }
+        tokio::time::sleep(heartbeat).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn test_standby_takes_over_when_lease_lapses() {
+        let path = std::env::temp_dir().join(format!("rust_npm_lease_{}.json", uuid::Uuid::new_v4().simple()));
+        let ttl = ChronoDuration::seconds(30);
+        let mut a = HaCoordinator::new("a", LeaseBackend::File(path.clone()), ttl);
+        let mut b = HaCoordinator::new("b", LeaseBackend::File(path.clone()), ttl);
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+
+        assert_eq!(a.tick(now).await, Some(Role::Active));
+        assert_eq!(b.tick(now).await, None);
+        assert_eq!(b.role, Role::Standby);
+        // a keeps renewing, so b stays standby past the first lease's expiry.
+        assert_eq!(a.tick(now + ChronoDuration::seconds(20)).await, None);
+        assert_eq!(b.tick(now + ChronoDuration::seconds(40)).await, None);
+
+        // a stops heartbeating; b takes over after the lease lapses and a steps down.
+        assert_eq!(b.tick(now + ChronoDuration::seconds(51)).await, Some(Role::Active));
+        assert_eq!(a.tick(now + ChronoDuration::seconds(52)).await, Some(Role::Standby));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_only_one_node_takes_a_free_lease() {
+        let ttl = ChronoDuration::seconds(30);
+        for _ in 0..20 {
+            let path = std::env::temp_dir().join(format!("rust_npm_lease_{}.json", uuid::Uuid::new_v4().simple()));
+            let now = Utc::now();
+            let contenders = ["a", "b"].map(|node| {
+                let backend = LeaseBackend::File(path.clone());
+                tokio::spawn(async move { backend.acquire(node, ttl, now).await.unwrap() })
+            });
+            let [a, b] = contenders;
+            let (a, b) = (a.await.unwrap(), b.await.unwrap());
+            assert!(a != b, "both nodes got the lease: {}", a);
+            assert!(!path.with_extension("lock").exists());
+            fs::remove_file(&path).unwrap();
+        }
+    }
+}
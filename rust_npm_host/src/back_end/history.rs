@@ -0,0 +1,471 @@
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::annotations::{AnnotationStore, availability};
+use super::check_result::CheckResult;
+use super::diagnostics;
+use super::event_bus::MonitorEvent;
+use super::metadata::Metadata;
+use super::monitor::Monitor;
+use super::sampling::{IntervalAggregate, Sampler};
+use super::series::CompressedSeries;
+use super::state_tracker::RecoveryRules;
+use super::storage::{StatusStore, Storage};
+use super::trend::{self, Advisory, TrendConfig};
+
+/// Results kept in memory per target unless configured otherwise; about a week of
+/// one-minute checks.
+pub const DEFAULT_RETENTION: usize = 10_000;
+
+/// Newest results per target kept whole, for the GUI's detail view and recent reports.
+/// Older ones are compressed.
+const RECENT_RESULTS: usize = 120;
+
+/// Points per compressed block; a block is freed once all of its points have expired.
+const BLOCK_POINTS: usize = 1024;
+
+/// Timestamped check results per target, with the queries reports and the GUI need. Kept
+/// in memory; with a database attached (Postgres or SQLite), every result is also written
+/// to `status_log_table` and `load` brings older results back after a restart.
+///
+/// Beyond the newest `RECENT_RESULTS`, a successful result is kept as its timestamp (to
+/// the millisecond) and latency in a `CompressedSeries`, a few bytes each; its metrics,
+/// metadata and correlation ID are dropped. Failures and unreliable results are kept whole.
+pub struct History {
+    results: RwLock<HashMap<String, TargetHistory>>,
+    retention: usize,
+    store: Option<Storage>,
+    /// Reduces what goes to the database to one result per target and interval.
+    sampler: Option<Mutex<Sampler>>,
+}
+
+#[derive(Default)]
+struct TargetHistory {
+    blocks: VecDeque<Block>,
+    /// Leading points of the oldest block that are past the retention.
+    expired: usize,
+    /// Points in `blocks` that have not expired.
+    archived: usize,
+    recent: VecDeque<CheckResult>,
+}
+
+struct Block {
+    /// Latency in ms of each result; NaN where the whole result is in `detailed`.
+    points: CompressedSeries,
+    /// Results a timestamp and latency don't describe, with their index in `points`.
+    detailed: Vec<(usize, CheckResult)>,
+    newest: DateTime<Utc>,
+}
+
+impl TargetHistory {
+    fn len(&self) -> usize {
+        self.archived + self.recent.len()
+    }
+
+    fn push(&mut self, result: CheckResult, retention: usize) {
+        // Results normally arrive in order; keep the recent ones sorted when one doesn't.
+        let at = self.recent.partition_point(|r| r.timestamp <= result.timestamp);
+        self.recent.insert(at, result);
+        while self.recent.len() > RECENT_RESULTS {
+            let oldest = self.recent.pop_front().unwrap();
+            self.archive(oldest);
+        }
+        while self.len() > retention {
+            self.drop_oldest();
+        }
+    }
+
+    fn archive(&mut self, result: CheckResult) {
+        if self.blocks.back().is_none_or(|block| block.points.len() >= BLOCK_POINTS) {
+            if let Some(full) = self.blocks.back_mut() {
+                full.points.shrink_to_fit();
+            }
+            self.blocks.push_back(Block { points: CompressedSeries::new(), detailed: Vec::new(), newest: result.timestamp });
+        }
+        let block = self.blocks.back_mut().unwrap();
+        let millis = result.timestamp.timestamp_millis();
+        block.newest = block.newest.max(result.timestamp);
+        match result.latency_ms() {
+            Some(ms) if result.success && result.is_reliable() => block.points.push(millis, ms),
+            _ => {
+                block.detailed.push((block.points.len(), result));
+                block.points.push(millis, f64::NAN);
+            }
+        }
+        self.archived += 1;
+    }
+
+    fn drop_oldest(&mut self) {
+        if self.archived == 0 {
+            self.recent.pop_front();
+            return;
+        }
+        self.archived -= 1;
+        self.expired += 1;
+        if self.blocks.front().is_some_and(|block| block.points.len() == self.expired) {
+            self.blocks.pop_front();
+            self.expired = 0;
+        }
+    }
+
+    /// Results since `since`, oldest first.
+    fn iter<'a>(&'a self, target: &'a str, since: DateTime<Utc>) -> impl Iterator<Item = CheckResult> + 'a {
+        let archived = self.blocks.iter().enumerate().filter(move |(_, block)| block.newest >= since).flat_map(
+            move |(i, block)| {
+                let first = if i == 0 { self.expired } else { 0 };
+                let mut detailed = block.detailed.iter().peekable();
+                block.points.iter().enumerate().skip(first).map(move |(index, (millis, ms))| {
+                    while detailed.next_if(|(at, _)| *at < index).is_some() {}
+                    match detailed.next_if(|(at, _)| *at == index) {
+                        Some((_, result)) => result.clone(),
+                        None => compacted(target, millis, ms),
+                    }
+                })
+            },
+        );
+        archived.chain(self.recent.iter().cloned()).filter(move |r| r.timestamp >= since)
+    }
+
+    /// Failures, newest first.
+    fn failures(&self) -> impl Iterator<Item = &CheckResult> {
+        let archived = self.blocks.iter().enumerate().rev().flat_map(move |(i, block)| {
+            let first = if i == 0 { self.expired } else { 0 };
+            block.detailed.iter().rev().filter(move |(at, _)| *at >= first).map(|(_, result)| result)
+        });
+        self.recent.iter().rev().chain(archived).filter(|r| !r.success)
+    }
+}
+
+async fn store_all(store: &Storage, results: &[CheckResult]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for result in results {
+        store.insert_result(result).await?;
+    }
+    Ok(())
+}
+
+/// A successful result brought back from its timestamp and latency.
+fn compacted(target: &str, millis: i64, latency_ms: f64) -> CheckResult {
+    CheckResult {
+        target: target.to_string(),
+        timestamp: DateTime::from_timestamp_millis(millis).unwrap_or_default(),
+        success: true,
+        latency: Some(Duration::from_nanos((latency_ms * 1e6).round() as u64)),
+        error: None,
+        metrics: BTreeMap::new(),
+        correlation_id: String::new(),
+        resolved_ip: None,
+        steps: Vec::new(),
+        agent: None,
+        failure_kind: None,
+        hint: None,
+        page_errors: Vec::new(),
+        artifacts: Vec::new(),
+        metadata: Metadata::new(),
+        status: None,
+        severity: None,
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETENTION)
+    }
+}
+
+impl History {
+    pub fn new(retention: usize) -> Self {
+        Self {
+            results: RwLock::new(HashMap::new()),
+            retention: retention.max(1),
+            store: None,
+            sampler: None,
+        }
+    }
+
+    /// Also stores results in `store`.
+    pub fn with_database(mut self, store: Storage) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    /// The database results are written to, if there is one.
+    pub fn database(&self) -> Option<&Storage> {
+        self.store.as_ref()
+    }
+
+    /// Keeps `result` in memory, dropping the target's oldest result beyond the retention.
+    pub fn push(&self, result: CheckResult) {
+        let mut results = self.results.write().unwrap();
+        results.entry(result.target.clone()).or_default().push(result, self.retention);
+    }
+
+    /// Writes one aggregate per target and `interval` to the database instead of every
+    /// result, plus the results that changed a target's state under `rules`. Memory still
+    /// keeps every result.
+    pub fn with_sampling(mut self, interval: Duration, rules: RecoveryRules) -> Self {
+        self.sampler = Some(Mutex::new(Sampler::new(interval, rules)));
+        self
+    }
+
+    /// Keeps `result` and writes it to the database, if there is one.
+    pub async fn record(&self, result: CheckResult) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let stored = match &self.store {
+            Some(store) => store_all(store, &self.to_store(&result)).await,
+            None => Ok(()),
+        };
+        self.push(result);
+        stored
+    }
+
+    fn to_store(&self, result: &CheckResult) -> Vec<CheckResult> {
+        let Some(sampler) = &self.sampler else {
+            return vec![result.clone()];
+        };
+        let output = sampler.lock().unwrap().record(result);
+        let mut stored: Vec<CheckResult> = output.aggregate.iter().map(IntervalAggregate::to_result).collect();
+        if output.transition.is_some() {
+            stored.push(result.clone());
+        }
+        stored
+    }
+
+    /// Writes the aggregates of the intervals still open, on shutdown.
+    pub async fn flush_samples(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let (Some(store), Some(sampler)) = (&self.store, &self.sampler) else {
+            return Ok(());
+        };
+        let aggregates = sampler.lock().unwrap().flush(DateTime::<Utc>::MAX_UTC);
+        store_all(store, &aggregates.iter().map(IntervalAggregate::to_result).collect::<Vec<_>>()).await
+    }
+
+    /// Reads results newer than `since` back from the database into memory.
+    pub async fn load(&self, since: DateTime<Utc>) -> Result<usize, Box<dyn Error + Send + Sync>> {
+        let Some(store) = &self.store else { return Ok(0) };
+        let results = store.results_since(since).await?;
+        let count = results.len();
+        for result in results {
+            self.push(result);
+        }
+        Ok(count)
+    }
+
+    /// Results of `target` since `since`, oldest first.
+    pub fn results(&self, target: &str, since: DateTime<Utc>) -> Vec<CheckResult> {
+        self.results
+            .read()
+            .unwrap()
+            .get(target)
+            .map(|results| results.iter(target, since).collect())
+            .unwrap_or_default()
+    }
+
+    /// Every target's results since `since`, for reports across targets.
+    pub fn all_results(&self, since: DateTime<Utc>) -> Vec<CheckResult> {
+        let results = self.results.read().unwrap();
+        results.iter().flat_map(|(target, results)| results.iter(target, since)).collect()
+    }
+
+    /// Latency of every check of `target` since `since`, oldest first, for charts; `None`
+    /// for failed checks. Checks during which the system clock jumped are left out.
+    pub fn latency_series(&self, target: &str, since: DateTime<Utc>) -> Vec<(DateTime<Utc>, Option<f64>)> {
+        self.results
+            .read()
+            .unwrap()
+            .get(target)
+            .map(|results| {
+                results
+                    .iter(target, since)
+                    .filter(|r| r.is_reliable())
+                    .map(|r| (r.timestamp, if r.success { r.latency_ms() } else { None }))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Share of successful checks since `since`, from 0.0 to 1.0, leaving out checks in
+    /// spans `annotations` exclude; `None` without checks.
+    pub fn uptime(&self, target: &str, since: DateTime<Utc>, annotations: Option<&AnnotationStore>) -> Option<f64> {
+        let results = self.results(target, since);
+        match annotations {
+            Some(store) => availability(&store.without_excluded(&results)),
+            None => availability(&results),
+        }
+    }
+
+    /// Mean latency of the successful checks since `since`, leaving out checks during which
+    /// the system clock jumped.
+    pub fn average_latency(&self, target: &str, since: DateTime<Utc>) -> Option<Duration> {
+        let latencies: Vec<Duration> = self
+            .results(target, since)
+            .iter()
+            .filter(|r| r.success && r.is_reliable())
+            .filter_map(|r| r.latency)
+            .collect();
+        (!latencies.is_empty()).then(|| latencies.iter().sum::<Duration>() / latencies.len() as u32)
+    }
+
+    /// The `limit` most recent failures of `target`, newest first.
+    pub fn recent_failures(&self, target: &str, limit: usize) -> Vec<CheckResult> {
+        self.results
+            .read()
+            .unwrap()
+            .get(target)
+            .map(|results| results.failures().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Slowly degrading targets, judged on everything in memory outside the spans
+    /// `annotations` exclude.
+    pub fn trends(&self, config: &TrendConfig, annotations: Option<&AnnotationStore>) -> Vec<Advisory> {
+        let results = self.all_results(DateTime::<Utc>::MIN_UTC);
+        match annotations {
+            Some(store) => trend::analyze(&store.without_excluded(&results), config),
+            None => trend::analyze(&results, config),
+        }
+    }
+}
+
+/// Records every completed check of `monitor` until its event bus closes or the monitor
+/// shuts down. Results published before `ShuttingDown` are all written when it returns.
+pub async fn record_events(monitor: Arc<Monitor>, history: Arc<History>) {
+    let mut events = monitor.bus().subscribe();
+    loop {
+        match events.recv().await {
+            Ok(MonitorEvent::CheckCompleted(result)) => {
+                if let Err(e) = history.record(result).await {
+                    eprintln!("Cannot store check result: {}{}", e, diagnostics::explain(&e.to_string()));
+                }
+            }
+            Ok(MonitorEvent::ShuttingDown) => break,
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => eprintln!("History missed {} events", missed),
+            Err(RecvError::Closed) => break,
+        }
+    }
+    if let Err(e) = history.flush_samples().await {
+        eprintln!("Cannot store sampled results: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, TimeZone};
+
+    #[test]
+    fn test_uptime_latency_and_failures() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        let history = History::new(3);
+        let at = |minutes: i64, mut result: CheckResult| {
+            result.timestamp = start + ChronoDuration::minutes(minutes);
+            result
+        };
+        history.push(at(0, CheckResult::failure("db:5432", "refused")));
+        history.push(at(2, CheckResult::success("db:5432", Duration::from_millis(30))));
+        history.push(at(1, CheckResult::success("db:5432", Duration::from_millis(10))));
+        history.push(at(3, CheckResult::failure("db:5432", "timed out after 1s")));
+
+        // Retention of 3 dropped the oldest failure.
+        assert_eq!(history.results("db:5432", start).len(), 3);
+        assert_eq!(history.uptime("db:5432", start, None), Some(2.0 / 3.0));
+        assert_eq!(history.uptime("db:5432", start + ChronoDuration::minutes(3), None), Some(0.0));
+        assert_eq!(history.average_latency("db:5432", start), Some(Duration::from_millis(20)));
+        assert_eq!(
+            history.latency_series("db:5432", start + ChronoDuration::minutes(2)),
+            [(start + ChronoDuration::minutes(2), Some(30.0)), (start + ChronoDuration::minutes(3), None)]
+        );
+        let failures = history.recent_failures("db:5432", 5);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].error.as_deref(), Some("timed out after 1s"));
+        assert_eq!(history.uptime("web:443", start, None), None);
+
+        let mut maintenance = AnnotationStore::default();
+        let (from, to) = (start + ChronoDuration::minutes(3), start + ChronoDuration::minutes(4));
+        maintenance.add("db:5432", from, to, "ISP maintenance", true).unwrap();
+        assert_eq!(history.uptime("db:5432", start, Some(&maintenance)), Some(1.0));
+    }
+
+    #[test]
+    fn test_trends_leave_out_excluded_spans() {
+        let start = Utc::now() - ChronoDuration::days(21);
+        let history = History::new(1000);
+        for day in 0..21 {
+            for sample in 0..10 {
+                let mut result = CheckResult::success("db:5432", Duration::from_millis(40 + day * 3));
+                result.timestamp = start + ChronoDuration::days(day as i64) + ChronoDuration::minutes(sample);
+                history.push(result);
+            }
+        }
+        assert_eq!(history.trends(&TrendConfig::default(), None).len(), 1);
+
+        let mut maintenance = AnnotationStore::default();
+        maintenance.add("db:5432", start + ChronoDuration::days(7), Utc::now(), "migration", true).unwrap();
+        assert!(history.trends(&TrendConfig::default(), Some(&maintenance)).is_empty());
+    }
+
+    #[test]
+    fn test_older_results_are_compressed() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        let history = History::new(2500);
+        for i in 0..3000 {
+            let mut result = match i % 500 {
+                7 => CheckResult::failure("db:5432", format!("refused #{}", i)),
+                _ => CheckResult::success("db:5432", Duration::from_micros(12_000 + (i % 4) * 250)),
+            };
+            result.timestamp = start + ChronoDuration::seconds(i as i64);
+            history.push(result);
+        }
+
+        let results = history.results("db:5432", start);
+        assert_eq!(results.len(), 2500);
+        assert_eq!(results[0].timestamp, start + ChronoDuration::seconds(500));
+        assert!(results.windows(2).all(|pair| pair[0].timestamp < pair[1].timestamp));
+        assert_eq!(results[1].latency, Some(Duration::from_micros(12_250)));
+        assert_eq!(results[2499].correlation_id.len(), 32);
+        // Failures keep their details; the one at 7 s has expired.
+        let failures = history.recent_failures("db:5432", 10);
+        let errors: Vec<_> = failures.iter().filter_map(|r| r.error.as_deref()).collect();
+        assert_eq!(errors, ["refused #2507", "refused #2007", "refused #1507", "refused #1007", "refused #507"]);
+        assert_eq!(history.uptime("db:5432", start + ChronoDuration::seconds(1000), None), Some(1996.0 / 2000.0));
+        assert_eq!(history.latency_series("db:5432", start + ChronoDuration::seconds(2998)).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_results_survive_a_restart() {
+        let store = Storage::Memory(Default::default());
+        let history = History::default().with_database(store.clone());
+        history.record(CheckResult::success("db:5432", Duration::from_millis(7))).await.unwrap();
+        history.record(CheckResult::failure("db:5432", "refused")).await.unwrap();
+
+        let restarted = History::default().with_database(store);
+        assert_eq!(restarted.load(Utc::now() - ChronoDuration::hours(1)).await.unwrap(), 2);
+        assert_eq!(restarted.recent_failures("db:5432", 5).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_sampling_stores_aggregates_and_state_changes() {
+        let store = Storage::Memory(Default::default());
+        let history = History::default().with_database(store.clone()).with_sampling(Duration::from_secs(3600), RecoveryRules::default());
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        for (minutes, success) in [(0, true), (1, true), (2, false), (3, true)] {
+            let mut result = if success {
+                CheckResult::success("db:5432", Duration::from_millis(7))
+            } else {
+                CheckResult::failure("db:5432", "refused")
+            };
+            result.timestamp = start + ChronoDuration::minutes(minutes);
+            history.record(result).await.unwrap();
+        }
+        // Only the failure and the recovery, until the hour is over.
+        assert_eq!(store.results_since(start).await.unwrap().len(), 2);
+        history.flush_samples().await.unwrap();
+        let stored = store.results_since(start).await.unwrap();
+        let aggregates: Vec<_> = stored.iter().filter(|r| r.metrics.contains_key("sampled_checks")).collect();
+        let counts: f64 = aggregates.iter().map(|r| r.metrics["sampled_checks"]).sum();
+        assert_eq!((history.results("db:5432", start).len(), counts), (4, 4.0));
+    }
+}
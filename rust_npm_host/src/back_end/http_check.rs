@@ -0,0 +1,124 @@
+use std::time::{Duration, Instant};
+
+use super::check_result::CheckResult;
+use super::clock::ClockGuard;
+use super::http_pool::HttpPool;
+
+/// A plain HTTP(S) availability check: one GET, no browser. Much cheaper than a
+/// `BrowserEmulator` check when all that matters is that the site answers correctly.
+#[derive(Debug, Clone)]
+pub struct HttpCheck {
+    pub url: String,
+    /// Status that counts as up; any 2xx/3xx when unset.
+    pub expect_status: Option<u16>,
+    /// Text the response body must contain, e.g. a footer only a fully rendered page has.
+    pub body_contains: Option<String>,
+    pub timeout: Duration,
+}
+
+impl HttpCheck {
+    pub fn new(url: &str, timeout: Duration) -> Self {
+        Self {
+            url: url.to_string(),
+            expect_status: None,
+            body_contains: None,
+            timeout,
+        }
+    }
+
+    pub fn expect_status(mut self, status: u16) -> Self {
+        self.expect_status = Some(status);
+        self
+    }
+
+    pub fn body_contains(mut self, text: &str) -> Self {
+        self.body_contains = Some(text.to_string());
+        self
+    }
+
+    /// Runs the request. Latency is the total response time including the body; the
+    /// metrics add `status_code`, `ttfb_ms` (until the response headers arrived),
+    /// `total_ms` and `body_bytes`, also for failed checks that got a response. Waiting
+    /// for a free slot in `pool` doesn't count towards the latency.
+    pub async fn run(&self, pool: &HttpPool) -> CheckResult {
+        let _slot = match pool.acquire(&self.url).await {
+            Ok(slot) => slot,
+            Err(e) => return CheckResult::failure(&self.url, e),
+        };
+        let clock = ClockGuard::start();
+        let start = Instant::now();
+        let response = match pool.client().get(&self.url).timeout(self.timeout).send().await {
+            Ok(response) => response,
+            Err(e) => return CheckResult::failure(&self.url, e.to_string()),
+        };
+        let ttfb = start.elapsed();
+        let status = response.status();
+        // Only read the body when it is checked; otherwise the headers are enough and
+        // downloading a large page would just inflate the total.
+        let body = match &self.body_contains {
+            Some(_) => match response.text().await {
+                Ok(body) => Some(body),
+                Err(e) => return CheckResult::failure(&self.url, format!("reading the body failed: {}", e)),
+            },
+            None => None,
+        };
+        let total = start.elapsed();
+
+        let status_ok = match self.expect_status {
+            Some(expected) => status.as_u16() == expected,
+            None => status.is_success() || status.is_redirection(),
+        };
+        let missing = self
+            .body_contains
+            .as_ref()
+            .filter(|text| !body.as_deref().unwrap_or_default().contains(text.as_str()));
+        let mut result = match (status_ok, missing) {
+            (false, _) => CheckResult::failure(&self.url, format!("HTTP {}", status)),
+            (true, Some(text)) => CheckResult::failure(&self.url, format!("body does not contain '{}'", text)),
+            (true, None) => CheckResult::success(&self.url, total),
+        };
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        result.metrics.insert("status_code".to_string(), status.as_u16() as f64);
+        result.metrics.insert("ttfb_ms".to_string(), ms(ttfb));
+        result.metrics.insert("total_ms".to_string(), ms(total));
+        if let Some(body) = &body {
+            result.metrics.insert("body_bytes".to_string(), body.len() as f64);
+        }
+        clock.check(&mut result);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_status_and_body_are_checked() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = [0; 1024];
+                let _ = socket.read(&mut request).await;
+                let body = "<footer>Shop</footer>";
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body);
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        let pool = HttpPool::default();
+        let result = HttpCheck::new(&url, Duration::from_secs(2)).body_contains("Shop").run(&pool).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.metrics["status_code"], 200.0);
+        assert!(result.metrics["ttfb_ms"] <= result.metrics["total_ms"]);
+
+        let result = HttpCheck::new(&url, Duration::from_secs(2)).body_contains("Cart").run(&pool).await;
+        assert_eq!(result.error.as_deref(), Some("body does not contain 'Cart'"));
+        let result = HttpCheck::new(&url, Duration::from_secs(2)).expect_status(204).run(&pool).await;
+        assert_eq!(result.error.as_deref(), Some("HTTP 200 OK"));
+    }
+}
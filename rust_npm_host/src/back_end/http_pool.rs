@@ -0,0 +1,170 @@
+use reqwest::Client;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::collections::HashMap;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// Limits and caching of the shared HTTP client.
+#[derive(Debug, Clone)]
+pub struct HttpPoolConfig {
+    /// Requests to one host (host and port) in flight at once; more wait for a slot.
+    pub max_per_host: usize,
+    /// Idle keep-alive connections kept per host for reuse.
+    pub max_idle_per_host: usize,
+    pub idle_timeout: Duration,
+    /// How long resolved addresses are reused; `None` asks the system resolver every time.
+    pub dns_ttl: Option<Duration>,
+}
+
+impl Default for HttpPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_per_host: 6,
+            max_idle_per_host: 8,
+            idle_timeout: Duration::from_secs(90),
+            dns_ttl: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+/// One HTTP client shared by every HTTP-type check, so connections are reused instead of
+/// opened and torn down per check. Clones share the client, host limits and DNS cache.
+/// Timeouts are set per request, as checks have different ones.
+#[derive(Debug, Clone)]
+pub struct HttpPool {
+    client: Client,
+    config: HttpPoolConfig,
+    hosts: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl HttpPool {
+    pub fn new(config: HttpPoolConfig) -> Result<Self, Box<dyn Error>> {
+        let mut builder = Client::builder()
+            .pool_max_idle_per_host(config.max_idle_per_host)
+            .pool_idle_timeout(config.idle_timeout);
+        if let Some(ttl) = config.dns_ttl {
+            builder = builder.dns_resolver(Arc::new(CachingResolver::new(ttl)));
+        }
+        Ok(Self {
+            client: builder.build()?,
+            config,
+            hosts: Arc::default(),
+        })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Waits for a request slot for the host of `url`. Hold the permit until the response
+    /// has been read.
+    pub async fn acquire(&self, url: &str) -> Result<OwnedSemaphorePermit, String> {
+        let key = host_key(url)?;
+        let slots = self
+            .hosts
+            .lock()
+            .unwrap()
+            .entry(key)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.config.max_per_host.max(1))))
+            .clone();
+        slots.acquire_owned().await.map_err(|e| e.to_string())
+    }
+}
+
+impl Default for HttpPool {
+    fn default() -> Self {
+        Self::new(HttpPoolConfig::default()).expect("default HTTP client builds")
+    }
+}
+
+/// `host:port` of `url`, with the scheme's default port filled in.
+fn host_key(url: &str) -> Result<String, String> {
+    let url = reqwest::Url::parse(url).map_err(|e| format!("invalid URL '{}': {}", url, e))?;
+    let host = url.host_str().ok_or_else(|| format!("URL '{}' has no host", url))?;
+    Ok(format!("{}:{}", host, url.port_or_known_default().unwrap_or_default()))
+}
+
+// Resolved addresses by host name, with when they were looked up.
+type DnsCache = Arc<Mutex<HashMap<String, (Instant, Vec<SocketAddr>)>>>;
+
+/// Resolves through the system resolver and reuses the answer for `ttl`.
+#[derive(Debug)]
+struct CachingResolver {
+    ttl: Duration,
+    cache: DnsCache,
+}
+
+impl CachingResolver {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cache: Arc::default(),
+        }
+    }
+
+    fn cached(&self, host: &str, now: Instant) -> Option<Vec<SocketAddr>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(host)
+            .filter(|(at, _)| now.duration_since(*at) < self.ttl)
+            .map(|(_, addrs)| addrs.clone())
+    }
+}
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        let (ttl, cache) = (self.ttl, self.cache.clone());
+        let cached = self.cached(&host, Instant::now());
+        Box::pin(async move {
+            let addrs = match cached {
+                Some(addrs) => addrs,
+                None => {
+                    // The port is replaced by the request's own.
+                    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+                    if !addrs.is_empty() && !ttl.is_zero() {
+                        cache.lock().unwrap().insert(host, (Instant::now(), addrs.clone()));
+                    }
+                    addrs
+                }
+            };
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_requests_per_host_are_capped() {
+        let pool = HttpPool::new(HttpPoolConfig {
+            max_per_host: 2,
+            ..HttpPoolConfig::default()
+        })
+        .unwrap();
+        let first = pool.acquire("http://example.com/a").await.unwrap();
+        let _second = pool.acquire("http://example.com:80/b").await.unwrap();
+        let third = pool.acquire("http://example.com/c");
+        assert!(tokio::time::timeout(Duration::from_millis(50), third).await.is_err());
+        // Other hosts and ports have their own slots.
+        let _other = pool.acquire("https://example.com/").await.unwrap();
+        drop(first);
+        assert!(tokio::time::timeout(Duration::from_millis(50), pool.acquire("http://example.com/")).await.is_ok());
+        assert!(pool.acquire("not a url").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolver_reuses_answers_within_ttl() {
+        let resolver = CachingResolver::new(Duration::from_secs(60));
+        let addrs: Vec<SocketAddr> = resolver.resolve("localhost".parse().unwrap()).await.unwrap().collect();
+        assert!(!addrs.is_empty());
+        let now = Instant::now();
+        assert_eq!(resolver.cached("localhost", now), Some(addrs));
+        assert_eq!(resolver.cached("localhost", now + Duration::from_secs(61)), None);
+    }
+}
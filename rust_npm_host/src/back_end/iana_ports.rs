@@ -1,9 +1,16 @@
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::error::Error;
-use std::fs::File;
-
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, LazyLock, RwLock};
+use std::time::Duration;
 
+// A row of IANA's registry CSV; the other columns are ignored.
 #[derive(Debug, Deserialize)]
 struct PortRecord {
     #[serde(rename = "Service Name")]
@@ -14,63 +21,327 @@ struct PortRecord {
     transport_protocol: String,
     #[serde(rename = "Description")]
     description: String,
-    #[serde(rename = "Assignee")]
-    assignee: String,
-    #[serde(rename = "Contact")]
-    contact: String,
-    #[serde(rename = "Registration Date")]
-    registration_date: String,
-    #[serde(rename = "Modification Date")]
-    modification_date: String,
-    #[serde(rename = "Reference")]
-    reference: String,
-    #[serde(rename = "Service Code")]
-    service_code: String,
-    #[serde(rename = "Unauthorized Use Reported")]
-    unauthorized_use_reported: String,
-    #[serde(rename = "Assignment Notes")]
-    assignment_notes: String
 }
 
+/// A transport protocol of the registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+    Sctp,
+    Dccp,
+}
 
+impl FromStr for Protocol {
+    type Err = String;
 
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "tcp" => Ok(Protocol::Tcp),
+            "udp" => Ok(Protocol::Udp),
+            "sctp" => Ok(Protocol::Sctp),
+            "dccp" => Ok(Protocol::Dccp),
+            _ => Err(format!("unknown transport protocol '{}'", s)),
+        }
+    }
+}
 
-fn load_port_services(file_path: &str) -> Result<(HashMap<String, PortRecord>, HashMap<String, PortRecord>), Box<dyn Error>> {
-    let mut tcp_services: HashMap<String, PortRecord> = HashMap::new();
-    let mut udp_services: HashMap<String, PortRecord> = HashMap::new();
-    let mut range_services: HashMap<String, PortRecord> = HashMap::new();
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Protocol::Tcp => "tcp",
+            Protocol::Udp => "udp",
+            Protocol::Sctp => "sctp",
+            Protocol::Dccp => "dccp",
+        })
+    }
+}
 
+/// A named assignment of the registry: one port, or a range like `6000-6063` for x11.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceRecord {
+    pub name: String,
+    pub ports: RangeInclusive<u16>,
+    /// `None` for old range assignments that name no protocol; they cover all of them.
+    pub protocol: Option<Protocol>,
+    pub description: String,
+}
 
+impl ServiceRecord {
+    fn covers(&self, port: u16, protocol: Protocol) -> bool {
+        self.ports.contains(&port) && self.protocol.is_none_or(|p| p == protocol)
+    }
+}
 
-    let file = File::open(file_path)?;
-    let mut rdr = csv::Reader::from_reader(file);
+/// `443` or `6000-6063` as a range of ports; `None` if malformed or backwards.
+pub fn parse_ports(value: &str) -> Option<RangeInclusive<u16>> {
+    match value.split_once('-') {
+        Some((low, high)) => {
+            let (low, high) = (low.trim().parse().ok()?, high.trim().parse().ok()?);
+            (low <= high).then_some(low..=high)
+        }
+        None => value.trim().parse().ok().map(|port| port..=port),
+    }
+}
 
-    for result in rdr.deserialize() {
-        let record: PortRecord = result?;
+/// The named assignments of a registry CSV. Unassigned and reserved rows, which have no
+/// name, and rows with unknown protocols are left out.
+fn parse_records<R: Read>(reader: R) -> Result<Vec<ServiceRecord>, Box<dyn Error>> {
+    let mut records = Vec::new();
+    for row in csv::Reader::from_reader(reader).deserialize() {
+        let row: PortRecord = row?;
+        if row.service_name.is_empty() {
+            continue;
+        }
+        let Some(ports) = parse_ports(&row.port_number) else {
+            continue;
+        };
+        let protocol = match row.transport_protocol.trim() {
+            "" => None,
+            name => match name.parse() {
+                Ok(protocol) => Some(protocol),
+                Err(_) => continue,
+            },
+        };
+        records.push(ServiceRecord {
+            name: row.service_name,
+            ports,
+            protocol,
+            description: row.description,
+        });
+    }
+    Ok(records)
+}
+
+/// A TCP service from the registry, as offered in the add-target form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceSuggestion {
+    pub port: u16,
+    pub service: String,
+    pub description: String,
+}
 
+/// The registry snapshot built into the binary, so lookups work without any files.
+pub const BUNDLED_CSV: &str = include_str!("../local_table/service-names-port-numbers.csv");
 
-        if !record.port_number.is_empty() && !record.service_name.is_empty() {
-            
-            // I need to see if this conatins a - as a string
-            if record.port_number.contains("-") {
+// Parsed on first use only; most runs never look a port up.
+static BUNDLED: LazyLock<Arc<PortTables>> =
+    LazyLock::new(|| Arc::new(PortTables::parse(BUNDLED_CSV.as_bytes()).expect("the bundled port registry is valid")));
 
-                range_services.insert(record.port_number.clone(), record);
-                continue;
-            }
+/// Where IANA publishes the current registry.
+pub const IANA_CSV_URL: &str = "https://www.iana.org/assignments/service-names-port-numbers/service-names-port-numbers.csv";
 
-            if record.port_number.parse::<u16>().is_ok() {
+// A download with fewer named TCP services than this is an error page or truncated; the
+// registry has about six thousand.
+const MIN_TCP_SERVICES: usize = 1000;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
 
-                match record.transport_protocol.to_lowercase().as_str() {
-                    "tcp" => {
-                        tcp_services.insert(record.port_number.clone(), record);
-                    }
-                    "udp" => {
-                        udp_services.insert(record.port_number.clone(), record);
-                    }
-                    _ => {} // This is to ignore the unparseble protocols, and all the other ones.
+/// The registry's named assignments, indexed by port and by name.
+#[derive(Debug, Clone, Default)]
+pub struct PortTables {
+    records: Vec<ServiceRecord>,
+    // The first assignment of each single port; the registry lists the primary name first.
+    by_port: HashMap<(Protocol, u16), usize>,
+    ranges: Vec<usize>,
+    // Lower-case names.
+    by_name: HashMap<String, Vec<usize>>,
+}
+
+impl PortTables {
+    /// Parses a registry CSV and checks that it looks like the whole registry.
+    pub fn parse<R: Read>(reader: R) -> Result<Self, Box<dyn Error>> {
+        let tables = Self::from_records(parse_records(reader)?);
+        let tcp = tables.by_port.keys().filter(|(protocol, _)| *protocol == Protocol::Tcp).count();
+        if tcp < MIN_TCP_SERVICES {
+            return Err(format!("only {} TCP services, expected the full registry", tcp).into());
+        }
+        if tables.lookup_by_port(443, Protocol::Tcp).is_none_or(|s| s.name != "https") {
+            return Err("port 443/tcp is not https; not a service registry".into());
+        }
+        Ok(tables)
+    }
+
+    pub fn from_records(records: Vec<ServiceRecord>) -> Self {
+        let mut tables = Self::default();
+        for (i, record) in records.iter().enumerate() {
+            match (record.ports.start() == record.ports.end(), record.protocol) {
+                (true, Some(protocol)) => {
+                    tables.by_port.entry((protocol, *record.ports.start())).or_insert(i);
                 }
+                _ => tables.ranges.push(i),
             }
+            tables.by_name.entry(record.name.to_lowercase()).or_default().push(i);
+        }
+        tables.records = records;
+        tables
+    }
+
+    /// The service registered for `port`: its own assignment if it has one, otherwise the
+    /// range it falls in.
+    pub fn lookup_by_port(&self, port: u16, protocol: Protocol) -> Option<&ServiceRecord> {
+        match self.by_port.get(&(protocol, port)) {
+            Some(&i) => Some(&self.records[i]),
+            None => self.ranges.iter().map(|&i| &self.records[i]).find(|r| r.covers(port, protocol)),
+        }
+    }
+
+    /// Every assignment of the service `name`, ignoring case, e.g. its tcp and udp ports.
+    pub fn lookup_by_service_name(&self, name: &str) -> Vec<&ServiceRecord> {
+        self.by_name
+            .get(&name.to_lowercase())
+            .map(|indexes| indexes.iter().map(|&i| &self.records[i]).collect())
+            .unwrap_or_default()
+    }
+
+    /// The single-port TCP services, sorted by port, for autocompletion.
+    pub fn tcp_suggestions(&self) -> Vec<ServiceSuggestion> {
+        let mut suggestions: Vec<ServiceSuggestion> = self
+            .by_port
+            .iter()
+            .filter(|((protocol, _), _)| *protocol == Protocol::Tcp)
+            .map(|(&(_, port), &i)| ServiceSuggestion {
+                port,
+                service: self.records[i].name.clone(),
+                description: self.records[i].description.clone(),
+            })
+            .collect();
+        suggestions.sort_by_key(|s| s.port);
+        suggestions
+    }
+}
+
+/// The port registry in memory. Updates replace the tables in one step, so readers
+/// always see either the old or the new registry.
+#[derive(Debug, Default)]
+pub struct PortRegistry {
+    tables: RwLock<Arc<PortTables>>,
+}
+
+impl PortRegistry {
+    /// Loads `path` if given, otherwise the cached download if there is a usable one, and
+    /// the bundled snapshot if not. Only an unusable `path` is an error.
+    pub fn load(path: Option<&Path>, cache: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        if let Some(path) = path {
+            let tables = File::open(path).map_err(Into::into).and_then(PortTables::parse);
+            return tables.map(Self::from_tables).map_err(|e| format!("{}: {}", path.display(), e).into());
         }
+        if let Some(cache) = cache.filter(|path| path.exists()) {
+            match File::open(cache).map_err(Into::into).and_then(PortTables::parse) {
+                Ok(tables) => return Ok(Self::from_tables(tables)),
+                Err(e) => eprintln!("Ignoring cached port registry {}: {}", cache.display(), e),
+            }
+        }
+        Ok(Self::bundled())
+    }
+
+    /// The snapshot built into the binary.
+    pub fn bundled() -> Self {
+        Self {
+            tables: RwLock::new(BUNDLED.clone()),
+        }
+    }
+
+    pub fn from_tables(tables: PortTables) -> Self {
+        Self {
+            tables: RwLock::new(Arc::new(tables)),
+        }
+    }
+
+    /// `service-names-port-numbers.csv` in the user's cache directory.
+    pub fn default_cache_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("rust_npm").join("service-names-port-numbers.csv"))
+    }
+
+    /// The current tables; they stay valid when an update replaces them.
+    pub fn tables(&self) -> Arc<PortTables> {
+        self.tables.read().unwrap().clone()
+    }
+
+    /// The registered name of `port`, e.g. `https` for 443/tcp.
+    pub fn service_name(&self, port: u16, protocol: Protocol) -> Option<String> {
+        self.tables().lookup_by_port(port, protocol).map(|s| s.name.clone())
+    }
+
+    /// `port` with its likely service for display next to a target, e.g. `443/tcp https`;
+    /// `None` for unregistered ports.
+    pub fn label(&self, port: u16, protocol: Protocol) -> Option<String> {
+        self.service_name(port, protocol).map(|name| format!("{}/{} {}", port, protocol, name))
+    }
+
+    pub fn tcp_suggestions(&self) -> Vec<ServiceSuggestion> {
+        self.tables().tcp_suggestions()
+    }
+
+    pub fn replace(&self, tables: PortTables) {
+        *self.tables.write().unwrap() = Arc::new(tables);
+    }
+
+    /// Downloads the registry from `url`, validates it, writes it to `cache` and swaps it in.
+    /// On any error the current tables and cache stay as they are. Returns the number of
+    /// TCP services.
+    pub async fn update(&self, url: &str, cache: Option<&Path>) -> Result<usize, Box<dyn Error>> {
+        let client = reqwest::Client::builder().timeout(DOWNLOAD_TIMEOUT).build()?;
+        let body = client.get(url).send().await?.error_for_status()?.bytes().await?;
+        let tables = PortTables::parse(body.as_ref())?;
+        if let Some(cache) = cache {
+            if let Some(dir) = cache.parent().filter(|d| !d.as_os_str().is_empty()) {
+                fs::create_dir_all(dir)?;
+            }
+            // Write-and-rename so an interrupted download never leaves a truncated cache.
+            let tmp = cache.with_extension("tmp");
+            fs::write(&tmp, &body)?;
+            fs::rename(&tmp, cache)?;
+        }
+        let count = tables.by_port.keys().filter(|(protocol, _)| *protocol == Protocol::Tcp).count();
+        self.replace(tables);
+        Ok(count)
+    }
+}
+
+/// Updates `registry` from `url` every `interval`, starting after the first interval.
+pub async fn keep_updated(registry: Arc<PortRegistry>, url: String, cache: Option<PathBuf>, interval: Duration) {
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    loop {
+        ticks.tick().await;
+        if let Err(e) = registry.update(&url, cache.as_deref()).await {
+            eprintln!("Port registry not updated from {}: {}", url, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_is_validated_and_swapped() {
+        let registry = PortRegistry::load(None, None).unwrap();
+        assert_eq!(registry.service_name(443, Protocol::Tcp).as_deref(), Some("https"));
+        assert_eq!(registry.service_name(53, Protocol::Udp).as_deref(), Some("domain"));
+        assert_eq!(registry.label(443, Protocol::Tcp).as_deref(), Some("443/tcp https"));
+
+        let truncated: String = BUNDLED_CSV.lines().take(50).collect::<Vec<_>>().join("\n");
+        assert!(PortTables::parse(truncated.as_bytes()).is_err());
+        assert!(PortTables::parse("<html>Service Unavailable</html>".as_bytes()).is_err());
+        assert!(PortRegistry::load(Some(Path::new("/nonexistent/ports.csv")), None).is_err());
+
+        registry.replace(PortTables::default());
+        assert_eq!(registry.service_name(443, Protocol::Tcp), None);
+        assert!(registry.tcp_suggestions().is_empty());
+    }
+
+    #[test]
+    fn test_lookups_cover_ranges_and_all_protocols_of_a_name() {
+        let tables = PortRegistry::bundled().tables();
+        let x11 = tables.lookup_by_port(6010, Protocol::Tcp).unwrap();
+        assert_eq!((x11.name.as_str(), x11.ports.clone()), ("x11", 6000..=6063));
+        assert_eq!(tables.lookup_by_port(3323, Protocol::Udp).map(|s| s.name.as_str()), Some("active-net"));
+        assert_eq!(tables.lookup_by_port(21, Protocol::Tcp).map(|s| s.name.as_str()), Some("ftp"));
+
+        let ssh: Vec<_> = tables.lookup_by_service_name("SSH").iter().map(|s| (s.protocol, s.ports.clone())).collect();
+        assert!(ssh.contains(&(Some(Protocol::Tcp), 22..=22)));
+        assert!(ssh.contains(&(Some(Protocol::Udp), 22..=22)));
+        assert!(tables.lookup_by_service_name("no-such-service").is_empty());
+        assert_eq!(parse_ports("10-5"), None);
     }
-    Ok((tcp_services, udp_services))
 }
@@ -0,0 +1,158 @@
+use std::error::Error;
+use std::net::IpAddr;
+use std::time::Duration;
+use surge_ping::{Client, Config, ICMP, IcmpPacket, PingIdentifier, PingSequence};
+
+use super::check_result::CheckResult;
+
+// 56 bytes of payload, like the classic `ping`.
+const PAYLOAD: [u8; 56] = [0; 56];
+
+/// How one ICMP check pings its host.
+#[derive(Debug, Clone)]
+pub struct IcmpProbe {
+    /// Echo requests per check; packet loss is measured over these.
+    pub count: u16,
+    pub interval: Duration,
+    /// How long to wait for each reply.
+    pub timeout: Duration,
+}
+
+impl Default for IcmpProbe {
+    fn default() -> Self {
+        Self {
+            count: 4,
+            interval: Duration::from_millis(200),
+            timeout: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Round-trip times and loss of one series of echo requests.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PingStats {
+    pub sent: u16,
+    /// Round-trip time of every answered request.
+    pub rtts: Vec<Duration>,
+}
+
+impl PingStats {
+    pub fn received(&self) -> usize {
+        self.rtts.len()
+    }
+
+    /// Share of requests without a reply, from 0.0 to 1.0.
+    pub fn packet_loss(&self) -> f64 {
+        if self.sent == 0 {
+            return 1.0;
+        }
+        1.0 - self.received() as f64 / self.sent as f64
+    }
+
+    pub fn avg_rtt(&self) -> Option<Duration> {
+        (!self.rtts.is_empty()).then(|| self.rtts.iter().sum::<Duration>() / self.rtts.len() as u32)
+    }
+
+    /// The target is up when any request was answered. Latency is the average round trip;
+    /// min/avg/max and loss go into the metrics.
+    pub fn to_result(&self, target: &str) -> CheckResult {
+        let Some(avg) = self.avg_rtt() else {
+            return CheckResult::failure(target, format!("no reply to {} ICMP echo requests", self.sent));
+        };
+        let ms = |d: &Duration| d.as_secs_f64() * 1000.0;
+        let mut result = CheckResult::success(target, avg);
+        result.metrics.insert("rtt_min_ms".to_string(), self.rtts.iter().map(ms).fold(f64::INFINITY, f64::min));
+        result.metrics.insert("rtt_avg_ms".to_string(), ms(&avg));
+        result.metrics.insert("rtt_max_ms".to_string(), self.rtts.iter().map(ms).fold(0.0, f64::max));
+        result.metrics.insert("packet_loss".to_string(), self.packet_loss());
+        result
+    }
+}
+
+/// One answered echo request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoReply {
+    pub rtt: Duration,
+    /// TTL (hop limit) of the reply as it arrived; hints at the sender's OS.
+    pub ttl: Option<u8>,
+}
+
+impl IcmpProbe {
+    /// Sends a single echo request and returns the reply, or `None` when there was none
+    /// within the timeout. Used by the ping sweep, where one probe per address is enough.
+    pub async fn echo(&self, host: IpAddr) -> Result<Option<EchoReply>, Box<dyn Error + Send + Sync>> {
+        let client = Client::new(&config_for(host)).map_err(|e| format!("cannot open ICMP socket: {}", e))?;
+        let mut pinger = client.pinger(host, PingIdentifier(rand_ident())).await;
+        pinger.timeout(self.timeout);
+        Ok(pinger.ping(PingSequence(0), &PAYLOAD).await.ok().map(|(packet, rtt)| EchoReply {
+            rtt,
+            ttl: match packet {
+                IcmpPacket::V4(packet) => packet.get_ttl(),
+                IcmpPacket::V6(packet) => Some(packet.get_max_hop_limit()),
+            },
+        }))
+    }
+
+    /// Sends `count` echo requests to `host`. Fails only when no ICMP socket can be opened:
+    /// Linux allows unprivileged ping sockets for groups in `net.ipv4.ping_group_range`,
+    /// elsewhere this needs root or `CAP_NET_RAW`.
+    pub async fn ping(&self, host: IpAddr) -> Result<PingStats, Box<dyn Error + Send + Sync>> {
+        let client = Client::new(&config_for(host)).map_err(|e| format!("cannot open ICMP socket: {}", e))?;
+        let mut pinger = client.pinger(host, PingIdentifier(rand_ident())).await;
+        pinger.timeout(self.timeout);
+
+        let mut stats = PingStats::default();
+        for seq in 0..self.count {
+            if seq > 0 {
+                tokio::time::sleep(self.interval).await;
+            }
+            stats.sent += 1;
+            if let Ok((_, rtt)) = pinger.ping(PingSequence(seq), &PAYLOAD).await {
+                stats.rtts.push(rtt);
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn config_for(host: IpAddr) -> Config {
+    match host {
+        IpAddr::V4(_) => Config::default(),
+        IpAddr::V6(_) => Config::builder().kind(ICMP::V6).build(),
+    }
+}
+
+// Replies are matched by identifier; keep concurrent checks from claiming each other's.
+fn rand_ident() -> u16 {
+    uuid::Uuid::new_v4().as_u128() as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_become_check_results() {
+        let stats = PingStats {
+            sent: 4,
+            rtts: vec![Duration::from_millis(10), Duration::from_millis(20), Duration::from_millis(30)],
+        };
+        let result = stats.to_result("10.0.0.1");
+        assert!(result.success);
+        assert_eq!(result.latency, Some(Duration::from_millis(20)));
+        assert_eq!(result.metrics["packet_loss"], 0.25);
+        assert_eq!(result.metrics["rtt_min_ms"], 10.0);
+        assert_eq!(result.metrics["rtt_max_ms"], 30.0);
+
+        let lost = PingStats { sent: 4, rtts: Vec::new() };
+        assert_eq!(lost.packet_loss(), 1.0);
+        assert!(!lost.to_result("10.0.0.1").success);
+    }
+
+    #[tokio::test]
+    #[ignore] // Needs permission to open ICMP sockets
+    async fn test_ping_localhost() {
+        let stats = IcmpProbe::default().ping("127.0.0.1".parse().unwrap()).await.unwrap();
+        assert_eq!(stats.packet_loss(), 0.0);
+    }
+}
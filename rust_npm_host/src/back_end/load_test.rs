@@ -0,0 +1,219 @@
+use reqwest::Client;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// One synthetic load burst against a single URL.
+#[derive(Debug, Clone)]
+pub struct LoadTestConfig {
+    pub url: String,
+    pub concurrency: usize,
+    pub duration: Duration,
+    pub request_timeout: Duration,
+}
+
+/// Hard caps that a burst can never exceed, whatever the per-target config says.
+#[derive(Debug, Clone)]
+pub struct SafetyLimits {
+    /// Only hosts listed here may be load tested at all.
+    pub allowed_hosts: Vec<String>,
+    pub max_concurrency: usize,
+    pub max_duration: Duration,
+    pub max_total_requests: usize,
+    /// Stop early once this fraction of requests fails, so we don't pile onto a struggling service.
+    pub abort_error_rate: f64,
+    /// Minimum number of requests before the error-rate abort is considered.
+    pub abort_min_requests: usize,
+}
+
+impl Default for SafetyLimits {
+    fn default() -> Self {
+        Self {
+            allowed_hosts: Vec::new(),
+            max_concurrency: 50,
+            max_duration: Duration::from_secs(60),
+            max_total_requests: 10_000,
+            abort_error_rate: 0.5,
+            abort_min_requests: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LoadTestReport {
+    pub url: String,
+    pub requests: usize,
+    pub errors: usize,
+    pub elapsed: Duration,
+    pub throughput_rps: f64,
+    pub p50: Option<Duration>,
+    pub p90: Option<Duration>,
+    pub p99: Option<Duration>,
+    pub max: Option<Duration>,
+    /// Why the burst stopped before its planned duration, if it did.
+    pub aborted: Option<String>,
+}
+
+impl LoadTestReport {
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            0.0
+        } else {
+            self.errors as f64 / self.requests as f64
+        }
+    }
+}
+
+/// Rejects bursts that aim at a host outside the allowlist or exceed the safety caps.
+pub fn validate(config: &LoadTestConfig, limits: &SafetyLimits) -> Result<(), String> {
+    let url = reqwest::Url::parse(&config.url).map_err(|e| format!("invalid url '{}': {}", config.url, e))?;
+    let host = url.host_str().ok_or_else(|| format!("url '{}' has no host", config.url))?;
+    if !limits.allowed_hosts.iter().any(|allowed| allowed.eq_ignore_ascii_case(host)) {
+        return Err(format!("host '{}' is not on the load test allowlist", host));
+    }
+    if config.concurrency == 0 || config.concurrency > limits.max_concurrency {
+        return Err(format!(
+            "concurrency {} must be between 1 and {}",
+            config.concurrency, limits.max_concurrency
+        ));
+    }
+    if config.duration.is_zero() || config.duration > limits.max_duration {
+        return Err(format!(
+            "duration {:?} must be between 0s and {:?}",
+            config.duration, limits.max_duration
+        ));
+    }
+    Ok(())
+}
+
+/// Fires `concurrency` parallel request loops at the URL for the configured duration.
+pub async fn run_burst(client: &Client, config: &LoadTestConfig, limits: &SafetyLimits) -> Result<LoadTestReport, String> {
+    validate(config, limits)?;
+
+    let started = Instant::now();
+    let deadline = started + config.duration;
+    let issued = Arc::new(AtomicUsize::new(0));
+    let failed = Arc::new(AtomicUsize::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let abort_reason = Arc::new(std::sync::Mutex::new(None::<String>));
+
+    let mut workers = Vec::with_capacity(config.concurrency);
+    for _ in 0..config.concurrency {
+        let client = client.clone();
+        let url = config.url.clone();
+        let timeout = config.request_timeout;
+        let (issued, failed, stop, abort_reason) = (issued.clone(), failed.clone(), stop.clone(), abort_reason.clone());
+        let limits = limits.clone();
+
+        workers.push(tokio::spawn(async move {
+            let mut latencies = Vec::new();
+            while !stop.load(Ordering::Relaxed) && Instant::now() < deadline {
+                if issued.fetch_add(1, Ordering::Relaxed) >= limits.max_total_requests {
+                    stop.store(true, Ordering::Relaxed);
+                    *abort_reason.lock().expect("abort lock poisoned") =
+                        Some(format!("reached the cap of {} requests", limits.max_total_requests));
+                    break;
+                }
+                let request_start = Instant::now();
+                let ok = matches!(
+                    client.get(&url).timeout(timeout).send().await,
+                    Ok(response) if !response.status().is_server_error()
+                );
+                if ok {
+                    latencies.push(request_start.elapsed());
+                } else {
+                    let failures = failed.fetch_add(1, Ordering::Relaxed) + 1;
+                    let total = issued.load(Ordering::Relaxed);
+                    if total >= limits.abort_min_requests && failures as f64 / total as f64 >= limits.abort_error_rate {
+                        stop.store(true, Ordering::Relaxed);
+                        *abort_reason.lock().expect("abort lock poisoned") = Some(format!(
+                            "error rate {:.0}% exceeded the safety limit",
+                            failures as f64 * 100.0 / total as f64
+                        ));
+                    }
+                }
+            }
+            latencies
+        }));
+    }
+
+    let mut latencies = Vec::new();
+    for worker in workers {
+        latencies.extend(worker.await.map_err(|e| format!("load worker panicked: {}", e))?);
+    }
+    latencies.sort();
+
+    let elapsed = started.elapsed();
+    let errors = failed.load(Ordering::Relaxed);
+    let requests = latencies.len() + errors;
+    let aborted = abort_reason.lock().expect("abort lock poisoned").take();
+    Ok(LoadTestReport {
+        url: config.url.clone(),
+        requests,
+        errors,
+        elapsed,
+        throughput_rps: requests as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        p50: percentile(&latencies, 50.0),
+        p90: percentile(&latencies, 90.0),
+        p99: percentile(&latencies, 99.0),
+        max: latencies.last().copied(),
+        aborted,
+    })
+}
+
+/// Runs a burst every `every` and hands each report to `on_report` until it returns `false`.
+pub async fn run_schedule<F>(client: &Client, config: &LoadTestConfig, limits: &SafetyLimits, every: Duration, mut on_report: F)
+where
+    F: FnMut(Result<LoadTestReport, String>) -> bool,
+{
+    let mut ticker = tokio::time::interval(every);
+    loop {
+        ticker.tick().await;
+        if !on_report(run_burst(client, config, limits).await) {
+            break;
+        }
+    }
+}
+
+/// Nearest-rank percentile of an already sorted slice.
+pub fn percentile(sorted: &[Duration], pct: f64) -> Option<Duration> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(url: &str, concurrency: usize, secs: u64) -> LoadTestConfig {
+        LoadTestConfig {
+            url: url.to_string(),
+            concurrency,
+            duration: Duration::from_secs(secs),
+            request_timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_validate_enforces_allowlist_and_caps() {
+        let limits = SafetyLimits {
+            allowed_hosts: vec!["staging.example.com".to_string()],
+            ..SafetyLimits::default()
+        };
+        assert!(validate(&config("https://staging.example.com/health", 10, 30), &limits).is_ok());
+        assert!(validate(&config("https://www.example.com/", 10, 30), &limits).is_err());
+        assert!(validate(&config("https://staging.example.com/", 500, 30), &limits).is_err());
+        assert!(validate(&config("https://staging.example.com/", 10, 3600), &limits).is_err());
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&sorted, 50.0), Some(Duration::from_millis(50)));
+        assert_eq!(percentile(&sorted, 99.0), Some(Duration::from_millis(99)));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+}
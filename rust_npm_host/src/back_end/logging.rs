@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::canary::ConnectivityChange;
+use super::check_result::CheckResult;
+use super::event_bus::MonitorEvent;
+use super::iana_ports::PortRegistry;
+use super::monitor::Monitor;
+use super::staleness::StaleChange;
+
+/// How check results are written to stdout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One human-readable line per result.
+    #[default]
+    Text,
+    /// One JSON object per line, for log processors (Loki, Elasticsearch, Splunk, ...).
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format '{}', expected text or json", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogFormat::Text => "text",
+            LogFormat::Json => "json",
+        })
+    }
+}
+
+/// Installs the JSON event writer for `LogFormat::Json`; text output needs none. Events
+/// are flattened, so each line reads like
+/// `{"timestamp":"...","level":"INFO","message":"check completed","target":"10.0.0.5:443",...}`.
+pub fn init(format: LogFormat) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if format == LogFormat::Json {
+        tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_target(false)
+            .with_current_span(false)
+            .with_span_list(false)
+            .try_init()?;
+    }
+    Ok(())
+}
+
+/// Emits `result` as a structured event. `check` is how the target was checked, e.g. `tcp`;
+/// `service` what likely runs on its port, e.g. `443/tcp https`.
+pub fn log_result(result: &CheckResult, check: &str, service: Option<&str>) {
+    let outcome = if result.success { "up" } else { "down" };
+    let duration_ms = result.latency_ms();
+    let error = result.error.as_deref();
+    // The snake_case names of the stored results, e.g. `connect_refused`.
+    let failure_kind = result
+        .failure_kind
+        .and_then(|kind| serde_json::to_value(kind).ok())
+        .and_then(|kind| kind.as_str().map(String::from));
+    let resolved_ip = result.resolved_ip.map(|ip| ip.to_string());
+    let hint = result.hint.map(|hint| hint.code());
+    if result.success {
+        tracing::info!(
+            target = %result.target,
+            check,
+            service,
+            outcome,
+            duration_ms,
+            correlation_id = %result.correlation_id,
+            resolved_ip,
+            agent = result.agent.as_deref(),
+            "check completed"
+        );
+    } else {
+        tracing::warn!(
+            target = %result.target,
+            check,
+            service,
+            outcome,
+            duration_ms,
+            error,
+            failure_kind,
+            hint,
+            correlation_id = %result.correlation_id,
+            resolved_ip,
+            agent = result.agent.as_deref(),
+            "check completed"
+        );
+    }
+}
+
+/// Logs a target going stale as a warning, so "no data" shows up next to failures.
+pub fn log_stale(change: &StaleChange) {
+    let last_result = change.last_result.map(|at| at.to_rfc3339());
+    if change.stale {
+        tracing::warn!(target = %change.target, outcome = "stale", last_result, "no recent results");
+    } else {
+        tracing::info!(target = %change.target, last_result, "results are fresh again");
+    }
+}
+
+pub fn log_connectivity(change: &ConnectivityChange) {
+    if change.online {
+        tracing::info!(held_back = change.held_back, "local connectivity restored");
+    } else {
+        tracing::error!(canaries = %change.canaries.join(", "), "local connectivity lost");
+    }
+}
+
+/// Prints `result` as `text` or logs it as JSON, depending on `format`.
+pub fn report(format: LogFormat, result: &CheckResult, check: &str, service: Option<&str>, text: impl FnOnce() -> String) {
+    match format {
+        LogFormat::Text => println!("{}", text()),
+        LogFormat::Json => log_result(result, check, service),
+    }
+}
+
+/// How `monitor` checks the target of `result`; `other` for results of targets that aren't
+/// addresses (HTTP, composite and plugin checks).
+pub fn check_name(monitor: &Monitor, result: &CheckResult) -> String {
+    match result.target.parse() {
+        Ok(addr) => monitor.check_kind(addr).name().to_string(),
+        Err(_) => "other".to_string(),
+    }
+}
+
+/// The registered service on the port of `result`'s target, e.g. `443/tcp https`, for the
+/// way `monitor` checks it; `None` for ICMP, unregistered ports and non-address targets.
+pub fn service_label(monitor: &Monitor, ports: &PortRegistry, result: &CheckResult) -> Option<String> {
+    let addr: SocketAddr = result.target.parse().ok()?;
+    ports.label(addr.port(), monitor.check_kind(addr).protocol()?)
+}
+
+/// Logs every completed check of `monitor` until its event bus closes, for long-running
+/// modes without other output.
+pub async fn log_events(monitor: Arc<Monitor>, ports: Arc<PortRegistry>) {
+    let mut events = monitor.bus().subscribe();
+    loop {
+        match events.recv().await {
+            Ok(MonitorEvent::CheckCompleted(result)) => {
+                log_result(&result, &check_name(&monitor, &result), service_label(&monitor, &ports, &result).as_deref())
+            }
+            Ok(MonitorEvent::StaleChanged(change)) => log_stale(&change),
+            Ok(MonitorEvent::ConnectivityChanged(change)) => log_connectivity(&change),
+            Ok(MonitorEvent::ShuttingDown) => break,
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => tracing::warn!(missed, "results not logged"),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct Captured(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_results_are_logged_as_json_lines() {
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .json()
+            .flatten_event(true)
+            .with_target(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            log_result(&CheckResult::success("10.0.0.5:443", Duration::from_millis(12)), "tcp", Some("443/tcp https"));
+            log_result(&CheckResult::failure("10.0.0.6:22", "connection refused"), "tcp", None);
+        });
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["target"], "10.0.0.5:443");
+        assert_eq!((lines[0]["check"].as_str(), lines[0]["outcome"].as_str()), (Some("tcp"), Some("up")));
+        assert_eq!(lines[0]["duration_ms"], 12.0);
+        assert_eq!(lines[0]["service"], "443/tcp https");
+        assert!(lines[1].get("service").is_none());
+        assert_eq!((lines[1]["level"].as_str(), lines[1]["outcome"].as_str()), (Some("WARN"), Some("down")));
+        assert_eq!(lines[1]["error"], "connection refused");
+        assert_eq!(lines[1]["failure_kind"], "connect_refused");
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+    }
+}
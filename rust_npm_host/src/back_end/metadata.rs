@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// User-defined key/value pairs of a target, e.g. cost center, owner or CMDB ID. They are
+/// copied unchanged into the target's results, webhook payloads and alerts, so downstream
+/// systems can join on their own identifiers.
+pub type Metadata = BTreeMap<String, String>;
+
+/// Prefix of metadata keys used as labels, so they can't clash with built-in labels.
+pub const LABEL_PREFIX: &str = "meta_";
+
+/// Distinct values a key may have across all targets before it stops being used as a label.
+pub const DEFAULT_MAX_LABEL_VALUES: usize = 50;
+
+/// Parses `key=value` pairs separated by commas. An empty value (`owner=`) means the key is
+/// to be removed and is returned as `None`. Keys are limited to letters, digits and `_`, so
+/// they are valid label names.
+pub fn parse_entries(spec: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (key, value) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("metadata '{}' is not key=value", entry))?;
+            let key = key.trim();
+            let valid = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(format!("metadata key '{}' may only contain letters, digits and '_'", key));
+            }
+            let value = value.trim();
+            Ok((key.to_string(), (!value.is_empty()).then(|| value.to_string())))
+        })
+        .collect()
+}
+
+/// Turns metadata into labels for label-indexed sinks such as Alertmanager. Every distinct
+/// label value makes a new series there, so a key that takes more than `max_values` values
+/// (a per-target ID, say) is dropped from labels for good; it still reaches the payloads.
+#[derive(Debug)]
+pub struct LabelGuard {
+    max_values: usize,
+    seen: HashMap<String, HashSet<String>>,
+    dropped: HashSet<String>,
+}
+
+impl LabelGuard {
+    pub fn new(max_values: usize) -> Self {
+        Self {
+            max_values,
+            seen: HashMap::new(),
+            dropped: HashSet::new(),
+        }
+    }
+
+    /// The labels for `metadata`, with keys prefixed by `LABEL_PREFIX`.
+    pub fn labels(&mut self, metadata: &Metadata) -> BTreeMap<String, String> {
+        let mut labels = BTreeMap::new();
+        for (key, value) in metadata {
+            if self.dropped.contains(key) {
+                continue;
+            }
+            let values = self.seen.entry(key.clone()).or_default();
+            if !values.contains(value) && values.len() >= self.max_values {
+                eprintln!(
+                    "Metadata key '{}' has more than {} values; no longer used as a label",
+                    key, self.max_values
+                );
+                self.seen.remove(key);
+                self.dropped.insert(key.clone());
+                continue;
+            }
+            values.insert(value.clone());
+            labels.insert(format!("{}{}", LABEL_PREFIX, key), value.clone());
+        }
+        labels
+    }
+}
+
+impl Default for LabelGuard {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_LABEL_VALUES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_parse_and_validate() {
+        assert_eq!(
+            parse_entries("cost_center=4711, owner = ops@example.com,cmdb_id=").unwrap(),
+            vec![
+                ("cost_center".to_string(), Some("4711".to_string())),
+                ("owner".to_string(), Some("ops@example.com".to_string())),
+                ("cmdb_id".to_string(), None),
+            ]
+        );
+        assert!(parse_entries("owner").is_err());
+        assert!(parse_entries("owner-email=x").is_err());
+        assert!(parse_entries("1st=x").is_err());
+    }
+
+    #[test]
+    fn test_high_cardinality_keys_are_dropped_from_labels() {
+        let mut guard = LabelGuard::new(2);
+        let meta = |team: &str, id: &str| Metadata::from([("team".to_string(), team.to_string()), ("id".to_string(), id.to_string())]);
+        assert_eq!(guard.labels(&meta("web", "1")).len(), 2);
+        assert_eq!(guard.labels(&meta("db", "2")).len(), 2);
+        // A known value is always fine; a third one drops the key.
+        assert_eq!(guard.labels(&meta("web", "3")), BTreeMap::from([("meta_team".to_string(), "web".to_string())]));
+        assert_eq!(guard.labels(&meta("db", "1")).keys().collect::<Vec<_>>(), ["meta_team"]);
+    }
+}
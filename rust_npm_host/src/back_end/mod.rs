@@ -2,4 +2,82 @@ pub mod address;
 pub mod iana_ports;
 pub mod watcher;
 pub mod ping_test;
-pub mod browser_emulator;
\ No newline at end of file
+pub mod browser_emulator;
+pub mod check_result;
+pub mod severity;
+pub mod trend;
+pub mod scripting;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm_plugin;
+pub mod windows_check;
+pub mod ssh;
+pub mod systemd_check;
+pub mod disk_check;
+pub mod s3_check;
+pub mod rate_limit;
+pub mod load_test;
+pub mod state_tracker;
+pub mod timezone;
+pub mod derived_metrics;
+pub mod tunnel;
+pub mod socks_probe;
+pub mod encrypted_config;
+pub mod sampling;
+pub mod event_bus;
+pub mod monitor;
+pub mod csv_import;
+pub mod dns_watch;
+pub mod composite;
+pub mod auth;
+pub mod notify;
+pub mod vantage;
+pub mod failure_kind;
+pub mod alertmanager;
+pub mod budget;
+pub mod webdriver_reaper;
+pub mod ha;
+pub mod result_payload;
+pub mod schema;
+pub mod annotations;
+pub mod waterfall;
+pub mod selector_check;
+pub mod icmp;
+pub mod service;
+pub mod target;
+pub mod api_access;
+pub mod target_rules;
+pub mod http_check;
+pub mod slo_export;
+pub mod window_compare;
+pub mod history;
+pub mod discovery;
+pub mod storage;
+pub mod topology;
+#[cfg(feature = "parquet-export")]
+pub mod parquet_export;
+pub mod pause;
+pub mod webhook;
+pub mod capture;
+pub mod http_pool;
+pub mod clock;
+pub mod metadata;
+pub mod daemon;
+pub mod ownership;
+pub mod api_server;
+pub mod remote;
+pub mod uptimerobot;
+pub mod logging;
+pub mod evidence;
+pub mod power;
+pub mod config;
+pub mod staleness;
+pub mod presets;
+pub mod ticketing;
+pub mod diagnostics;
+pub mod navigation_timing;
+pub mod series;
+pub mod canary;
+pub mod page_errors;
+pub mod units;
+pub mod webdriver_process;
+pub mod alert_dry_run;
@@ -0,0 +1,1364 @@
+use chrono::{DateTime, Utc};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::sync::Semaphore;
+use tokio::task::{Id as TaskId, JoinError, JoinSet};
+
+use super::browser_emulator::SessionPool;
+use super::budget::{BudgetLedger, CheckCost, Quota, Usage};
+use super::canary::{self, Canaries};
+use super::check_result::{CheckResult, new_correlation_id};
+use super::clock::ClockGuard;
+use super::composite::CompositeCheck;
+use super::config::AlertSettings;
+use super::derived_metrics::DerivedMetrics;
+use super::disk_check;
+use super::event_bus::{EventBus, MonitorEvent};
+use super::failure_kind::FailureKind;
+use super::http_check::HttpCheck;
+use super::http_pool::HttpPool;
+use super::iana_ports::Protocol;
+use super::icmp::IcmpProbe;
+use super::pause::Pause;
+use super::power::LowPower;
+use super::rate_limit::{self, QuotaTracker};
+use super::s3_check;
+use super::scripting::{self, ScriptHook};
+use super::ping_test::{self, UdpOutcome, UdpProbe};
+use super::socks_probe::{SocksProbe, SocksProxy};
+use super::service::{Rollup, ServiceChange, ServiceStatus};
+use super::staleness::StalenessTracker;
+use super::systemd_check;
+use super::ssh::SshPool;
+use super::state_tracker::{RecoveryRules, StateTracker, TargetState};
+use super::target::{CheckSpec, DEFAULT_INTERVAL, MonitorTarget};
+use super::timezone::TimeWindow;
+use super::tunnel::{self, TunnelConfig};
+#[cfg(feature = "wasm-plugins")]
+use super::wasm_plugin::PluginRegistry;
+use super::windows_check;
+
+/// How a target is checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    /// Connect to the target's port.
+    #[default]
+    Tcp,
+    /// ICMP echo to the target's address, for hosts without an open TCP port. The port is
+    /// ignored.
+    Icmp,
+    /// A UDP request fitting the port (DNS on 53, NTP on 123); up only when the service
+    /// answers.
+    Udp,
+    /// A GET request, see `CheckSpec::Http`; `http://<address>/` (https on 443) by default.
+    Http,
+    /// Loads the page in a real browser from the pool set with `set_browser_pool`,
+    /// see `CheckSpec::Browser`. Only a timeout set on the target itself applies; the
+    /// monitor's default is far too short for a page load.
+    Browser,
+    /// Windows services and event log over WinRM, see `CheckSpec::Windows`.
+    Windows,
+    /// Systemd units over SSH, see `CheckSpec::Systemd`.
+    Systemd,
+    /// File system usage over SSH, see `CheckSpec::Disk`.
+    Disk,
+    /// An object in an S3-compatible bucket, see `CheckSpec::S3`.
+    S3,
+    /// An API's remaining rate limit, see `CheckSpec::ApiQuota`.
+    ApiQuota,
+    /// Several TCP and HTTP steps in order, see `CheckSpec::Composite`.
+    Composite,
+    /// A check type from a WebAssembly plugin loaded with `set_plugins`, see
+    /// `CheckSpec::Plugin`.
+    Plugin,
+}
+
+impl CheckKind {
+    pub const ALL: [CheckKind; 12] = [
+        CheckKind::Tcp,
+        CheckKind::Icmp,
+        CheckKind::Udp,
+        CheckKind::Http,
+        CheckKind::Browser,
+        CheckKind::Windows,
+        CheckKind::Systemd,
+        CheckKind::Disk,
+        CheckKind::S3,
+        CheckKind::ApiQuota,
+        CheckKind::Composite,
+        CheckKind::Plugin,
+    ];
+
+    /// The name used in files and logs: `tcp`, `icmp`, `udp`, `http`, `browser`, `windows`,
+    /// `systemd`, `disk`, `s3`, `api_quota`, `composite` or `plugin`.
+    pub fn name(self) -> &'static str {
+        match self {
+            CheckKind::Tcp => "tcp",
+            CheckKind::Icmp => "icmp",
+            CheckKind::Udp => "udp",
+            CheckKind::Http => "http",
+            CheckKind::Browser => "browser",
+            CheckKind::Windows => "windows",
+            CheckKind::Systemd => "systemd",
+            CheckKind::Disk => "disk",
+            CheckKind::S3 => "s3",
+            CheckKind::ApiQuota => "api_quota",
+            CheckKind::Composite => "composite",
+            CheckKind::Plugin => "plugin",
+        }
+    }
+
+    /// Whether the check can't run on the target's address alone but needs its
+    /// `CheckSpec`.
+    pub fn needs_spec(self) -> bool {
+        matches!(
+            self,
+            CheckKind::Windows
+                | CheckKind::Systemd
+                | CheckKind::Disk
+                | CheckKind::S3
+                | CheckKind::ApiQuota
+                | CheckKind::Composite
+                | CheckKind::Plugin
+        )
+    }
+
+    /// The transport the check uses on the target's port; `None` for ICMP, which has no
+    /// port, and for checks that don't go to the port.
+    pub fn protocol(self) -> Option<Protocol> {
+        match self {
+            CheckKind::Tcp | CheckKind::Http | CheckKind::Browser | CheckKind::Windows | CheckKind::Systemd | CheckKind::Disk | CheckKind::S3 | CheckKind::ApiQuota => {
+                Some(Protocol::Tcp)
+            }
+            CheckKind::Icmp | CheckKind::Composite | CheckKind::Plugin => None,
+            CheckKind::Udp => Some(Protocol::Udp),
+        }
+    }
+}
+
+impl fmt::Display for CheckKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CheckKind::Tcp => "TCP connect",
+            CheckKind::Icmp => "ICMP ping",
+            CheckKind::Udp => "UDP request",
+            CheckKind::Http => "HTTP request",
+            CheckKind::Browser => "browser page load",
+            CheckKind::Windows => "Windows services",
+            CheckKind::Systemd => "systemd units",
+            CheckKind::Disk => "disk usage",
+            CheckKind::S3 => "S3 bucket",
+            CheckKind::ApiQuota => "API quota",
+            CheckKind::Composite => "composite journey",
+            CheckKind::Plugin => "plugin check",
+        })
+    }
+}
+
+/// Checks `run_all` keeps in flight at once unless configured otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 64;
+
+// How often the scheduler looks for new targets when nothing is due sooner.
+const SCHEDULER_IDLE_POLL: Duration = Duration::from_secs(1);
+
+/// The monitoring core: owns the target list, runs checks and announces everything it
+/// does on the event bus. Front ends hold it in an `Arc` and only talk to it through these
+/// methods and the bus.
+pub struct Monitor {
+    bus: EventBus,
+    /// Defaults for targets without their own timeout or interval.
+    timeout: Duration,
+    default_interval: RwLock<Duration>,
+    targets: RwLock<Vec<SocketAddr>>,
+    paused: RwLock<HashMap<SocketAddr, Pause>>,
+    groups: RwLock<HashMap<SocketAddr, String>>,
+    configs: RwLock<HashMap<SocketAddr, MonitorTarget>>,
+    icmp: IcmpProbe,
+    /// Shared by all HTTP-type checks for connection reuse.
+    http: RwLock<HttpPool>,
+    acknowledged: Mutex<HashSet<SocketAddr>>,
+    tracker: Mutex<StateTracker>,
+    /// Last announced rollup per service, to detect changes.
+    rollups: Mutex<HashMap<String, Rollup>>,
+    standby: AtomicBool,
+    /// Set by `stop`; the scheduler returns once its running checks are done.
+    stopping: AtomicBool,
+    low_power: RwLock<Option<LowPower>>,
+    concurrency: AtomicUsize,
+    staleness: Mutex<StalenessTracker>,
+    /// Whether the scheduler spreads check starts over the interval, see `phase`.
+    spread: AtomicBool,
+    /// Recurring windows in which no scheduled checks run.
+    maintenance: RwLock<Vec<TimeWindow>>,
+    /// Vantage point results are tagged with; `None` for the local one.
+    agent: RwLock<Option<String>>,
+    /// When set, failures of external targets count only while a canary is reachable.
+    canaries: RwLock<Option<Arc<Canaries>>>,
+    /// Sessions browser checks borrow.
+    browser_pool: RwLock<Option<Arc<SessionPool>>>,
+    /// Rate-limit history of the API quota checks.
+    quotas: Mutex<QuotaTracker>,
+    /// Checks and browser minutes used per target owner.
+    budget: Mutex<BudgetLedger>,
+    /// Connections systemd and disk checks run their commands over.
+    ssh: RwLock<Option<Arc<SshPool>>>,
+    /// Proxies targets with a `proxy` are checked through.
+    socks: RwLock<Arc<SocksProbe>>,
+    /// Metrics computed on every result before the hooks run.
+    derived: RwLock<Arc<DerivedMetrics>>,
+    /// Scripts run on every result, see `scripting`.
+    hooks: RwLock<Arc<Vec<ScriptHook>>>,
+    #[cfg(feature = "wasm-plugins")]
+    plugins: RwLock<Arc<PluginRegistry>>,
+}
+
+impl Monitor {
+    pub fn new(bus: EventBus, timeout: Duration) -> Self {
+        Self {
+            bus,
+            timeout,
+            default_interval: RwLock::new(DEFAULT_INTERVAL),
+            targets: RwLock::new(Vec::new()),
+            paused: RwLock::new(HashMap::new()),
+            groups: RwLock::new(HashMap::new()),
+            configs: RwLock::new(HashMap::new()),
+            icmp: IcmpProbe::default(),
+            http: RwLock::new(HttpPool::default()),
+            acknowledged: Mutex::new(HashSet::new()),
+            tracker: Mutex::new(StateTracker::default()),
+            rollups: Mutex::new(HashMap::new()),
+            standby: AtomicBool::new(false),
+            stopping: AtomicBool::new(false),
+            low_power: RwLock::new(None),
+            concurrency: AtomicUsize::new(DEFAULT_CONCURRENCY),
+            staleness: Mutex::new(StalenessTracker::default()),
+            spread: AtomicBool::new(true),
+            maintenance: RwLock::new(Vec::new()),
+            agent: RwLock::new(None),
+            canaries: RwLock::new(None),
+            browser_pool: RwLock::new(None),
+            quotas: Mutex::new(QuotaTracker::default()),
+            budget: Mutex::new(BudgetLedger::new(chrono::Duration::days(1), Quota::default())),
+            ssh: RwLock::new(None),
+            socks: RwLock::new(Arc::new(SocksProbe::new(Vec::new()))),
+            derived: RwLock::new(Arc::new(DerivedMetrics::default())),
+            hooks: RwLock::new(Arc::new(Vec::new())),
+            #[cfg(feature = "wasm-plugins")]
+            plugins: RwLock::new(Arc::new(PluginRegistry::default())),
+        }
+    }
+
+    pub fn bus(&self) -> &EventBus {
+        &self.bus
+    }
+
+    pub fn targets(&self) -> Vec<SocketAddr> {
+        self.targets.read().unwrap().clone()
+    }
+
+    /// Adds a target together with its check settings.
+    pub fn add_monitor_target(&self, target: MonitorTarget) -> Result<(), Box<dyn Error>> {
+        let addr = target.address;
+        let mut targets = self.targets.write().unwrap();
+        if targets.contains(&addr) {
+            return Err(format!("{} is already monitored", addr).into());
+        }
+        targets.push(addr);
+        self.configs.write().unwrap().insert(addr, target.clone());
+        drop(targets);
+        self.apply_alert_settings(&target);
+        self.bus.publish(MonitorEvent::TargetAdded(addr));
+        if !target.is_plain() {
+            self.bus.publish(MonitorEvent::TargetConfigured(target));
+        }
+        Ok(())
+    }
+
+    /// Stops monitoring `addr` and forgets its settings and state.
+    pub fn remove_target(&self, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+        let mut targets = self.targets.write().unwrap();
+        let Some(index) = targets.iter().position(|a| *a == addr) else {
+            return Err(format!("{} is not monitored", addr).into());
+        };
+        targets.remove(index);
+        drop(targets);
+        self.paused.write().unwrap().remove(&addr);
+        self.configs.write().unwrap().remove(&addr);
+        self.acknowledged.lock().unwrap().remove(&addr);
+        self.tracker.lock().unwrap().forget(&addr.to_string());
+        let group = self.groups.write().unwrap().remove(&addr);
+        self.bus.publish(MonitorEvent::TargetRemoved(addr));
+        if let Some(group) = group {
+            self.update_rollup(&group, &new_correlation_id());
+        }
+        Ok(())
+    }
+
+    /// Replaces the check settings of a monitored target.
+    pub fn configure(&self, target: MonitorTarget) -> Result<(), Box<dyn Error>> {
+        self.ensure_known(target.address)?;
+        self.configs.write().unwrap().insert(target.address, target.clone());
+        self.apply_alert_settings(&target);
+        self.bus.publish(MonitorEvent::TargetConfigured(target));
+        Ok(())
+    }
+
+    fn apply_alert_settings(&self, target: &MonitorTarget) {
+        let rules = target.alerts.as_ref().map(AlertSettings::rules);
+        self.tracker.lock().unwrap().set_rules(&target.address.to_string(), rules);
+    }
+
+    pub fn monitor_target(&self, addr: SocketAddr) -> Option<MonitorTarget> {
+        self.configs.read().unwrap().get(&addr).cloned()
+    }
+
+    /// All targets with their settings, in the order they were added.
+    pub fn monitor_targets(&self) -> Vec<MonitorTarget> {
+        let configs = self.configs.read().unwrap();
+        self.targets()
+            .into_iter()
+            .map(|addr| configs.get(&addr).cloned().unwrap_or_else(|| MonitorTarget::new(addr)))
+            .collect()
+    }
+
+    /// Interval for targets that don't set their own.
+    pub fn set_default_interval(&self, interval: Duration) {
+        *self.default_interval.write().unwrap() = interval;
+    }
+
+    pub fn default_interval(&self) -> Duration {
+        *self.default_interval.read().unwrap()
+    }
+
+    fn ensure_known(&self, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+        if self.targets.read().unwrap().contains(&addr) {
+            Ok(())
+        } else {
+            Err(format!("{} is not monitored", addr).into())
+        }
+    }
+
+    /// Paused targets are skipped by `run_all` and the scheduler but can still be checked
+    /// explicitly. Pausing a paused target replaces its reason and expiry.
+    pub fn pause(&self, addr: SocketAddr, pause: Pause) -> Result<(), Box<dyn Error>> {
+        self.ensure_known(addr)?;
+        if pause.reason.trim().is_empty() {
+            return Err("a pause needs a reason".into());
+        }
+        self.paused.write().unwrap().insert(addr, pause.clone());
+        self.bus.publish(MonitorEvent::TargetPaused { target: addr, pause: Some(pause) });
+        Ok(())
+    }
+
+    pub fn resume(&self, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+        self.ensure_known(addr)?;
+        if self.paused.write().unwrap().remove(&addr).is_some() {
+            self.bus.publish(MonitorEvent::TargetPaused { target: addr, pause: None });
+        }
+        Ok(())
+    }
+
+    pub fn is_paused(&self, addr: SocketAddr) -> bool {
+        self.paused.read().unwrap().contains_key(&addr)
+    }
+
+    pub fn pause_of(&self, addr: SocketAddr) -> Option<Pause> {
+        self.paused.read().unwrap().get(&addr).cloned()
+    }
+
+    /// Paused targets with their pauses, longest paused first.
+    pub fn paused_targets(&self) -> Vec<(SocketAddr, Pause)> {
+        let mut paused: Vec<(SocketAddr, Pause)> =
+            self.paused.read().unwrap().iter().map(|(addr, pause)| (*addr, pause.clone())).collect();
+        paused.sort_by_key(|(addr, pause)| (pause.since, *addr));
+        paused
+    }
+
+    /// Resumes every target whose pause has run out and returns them.
+    pub fn resume_expired(&self) -> Vec<SocketAddr> {
+        let now = chrono::Utc::now();
+        let mut expired: Vec<SocketAddr> = self
+            .paused
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, pause)| pause.is_expired(now))
+            .map(|(addr, _)| *addr)
+            .collect();
+        expired.sort();
+        for addr in &expired {
+            self.resume(*addr).ok();
+        }
+        expired
+    }
+
+    pub fn set_group(&self, addr: SocketAddr, group: Option<String>) -> Result<(), Box<dyn Error>> {
+        self.ensure_known(addr)?;
+        let group = group.map(|g| g.trim().to_string()).filter(|g| !g.is_empty());
+        match &group {
+            Some(group) => self.groups.write().unwrap().insert(addr, group.clone()),
+            None => self.groups.write().unwrap().remove(&addr),
+        };
+        self.bus.publish(MonitorEvent::TargetGrouped { target: addr, group });
+        Ok(())
+    }
+
+    pub fn group(&self, addr: SocketAddr) -> Option<String> {
+        self.groups.read().unwrap().get(&addr).cloned()
+    }
+
+    /// Up/down state of `addr`; `None` until its first check.
+    pub fn state(&self, addr: SocketAddr) -> Option<TargetState> {
+        self.tracker.lock().unwrap().state(&addr.to_string())
+    }
+
+    /// Every group as a service with its rollup status, sorted by name.
+    pub fn services(&self) -> Vec<ServiceStatus> {
+        let mut members: BTreeMap<String, Vec<SocketAddr>> = BTreeMap::new();
+        for addr in self.targets() {
+            if let Some(group) = self.group(addr) {
+                members.entry(group).or_default().push(addr);
+            }
+        }
+        members
+            .into_iter()
+            .map(|(name, addrs)| ServiceStatus::new(&name, addrs.into_iter().map(|a| (a, self.state(a))).collect()))
+            .collect()
+    }
+
+    pub fn service(&self, name: &str) -> Option<ServiceStatus> {
+        self.services().into_iter().find(|s| s.name == name)
+    }
+
+    /// Checks all unpaused members of a service, like `run_all` does for everything.
+    pub async fn run_service(&self, name: &str) -> Vec<CheckResult> {
+        let members: Vec<SocketAddr> = self
+            .targets()
+            .into_iter()
+            .filter(|a| self.group(*a).as_deref() == Some(name) && !self.is_paused(*a))
+            .collect();
+        stream::iter(members)
+            .map(|addr| self.run_check(addr))
+            .buffer_unordered(self.concurrency())
+            .collect()
+            .await
+    }
+
+    // Publishes a `ServiceChanged` event when a check changed its service's rollup.
+    fn update_rollup(&self, service: &str, correlation_id: &str) {
+        let Some(status) = self.service(service) else { return };
+        let previous = self.rollups.lock().unwrap().insert(service.to_string(), status.rollup);
+        let from = previous.unwrap_or(Rollup::Unknown);
+        if from != status.rollup {
+            self.bus.publish(MonitorEvent::ServiceChanged(ServiceChange {
+                service: service.to_string(),
+                from,
+                to: status.rollup,
+                at: chrono::Utc::now(),
+                correlation_id: correlation_id.to_string(),
+            }));
+        }
+    }
+
+    pub fn set_check_kind(&self, addr: SocketAddr, kind: CheckKind) -> Result<(), Box<dyn Error>> {
+        self.ensure_known(addr)?;
+        let previous = self
+            .configs
+            .write()
+            .unwrap()
+            .get_mut(&addr)
+            .map(|config| std::mem::replace(&mut config.check, kind));
+        if previous != Some(kind) {
+            self.bus.publish(MonitorEvent::CheckKindChanged { target: addr, kind });
+        }
+        Ok(())
+    }
+
+    pub fn check_kind(&self, addr: SocketAddr) -> CheckKind {
+        self.monitor_target(addr).map(|t| t.check).unwrap_or_default()
+    }
+
+    /// Marks the current outage of `addr` as known, so it isn't raised again until the
+    /// target has recovered and failed anew.
+    pub fn acknowledge(&self, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+        self.ensure_known(addr)?;
+        if self.tracker.lock().unwrap().state(&addr.to_string()) != Some(TargetState::Down) {
+            return Err(format!("{} has no active alert", addr).into());
+        }
+        if self.acknowledged.lock().unwrap().insert(addr) {
+            self.bus.publish(MonitorEvent::AlertAcknowledged(addr));
+        }
+        Ok(())
+    }
+
+    pub fn is_acknowledged(&self, addr: SocketAddr) -> bool {
+        self.acknowledged.lock().unwrap().contains(&addr)
+    }
+
+    /// Checks `addr` the way its settings say (by default, whether it accepts TCP
+    /// connections), retrying failures up to `retries` times, and publishes the result,
+    /// plus the up/down transition it caused, if any.
+    pub async fn run_check(&self, addr: SocketAddr) -> CheckResult {
+        self.bus.publish(MonitorEvent::CheckStarted(addr));
+        let config = self.monitor_target(addr).unwrap_or_else(|| MonitorTarget::new(addr));
+        let timeout = config.timeout.unwrap_or(self.timeout);
+        // A refused check isn't recorded: it says nothing about the target.
+        let cost = if config.check == CheckKind::Browser { CheckCost::Browser } else { CheckCost::Probe };
+        if let Some(team) = &config.owner
+            && let Err(e) = self.budget.lock().unwrap().reserve(team, cost, Utc::now())
+        {
+            return CheckResult::failure(&addr.to_string(), e.to_string()).with_failure_kind(FailureKind::InfraError);
+        }
+        let clock = ClockGuard::start();
+        let started = Instant::now();
+        let mut attempts = 1;
+        let mut result = self.probe(&config, timeout).await;
+        while !result.success && attempts <= config.retries {
+            attempts += 1;
+            result = self.probe(&config, timeout).await;
+        }
+        if let (Some(team), CheckCost::Browser) = (&config.owner, cost) {
+            self.budget.lock().unwrap().charge_browser_time(team, started.elapsed(), Utc::now());
+        }
+        clock.check(&mut result);
+        result.metadata = config.metadata.clone();
+        // Proxied checks already name the proxy they saw the target from.
+        if result.agent.is_none() {
+            result.agent = self.agent.read().unwrap().clone();
+        }
+        // Which of a host name's addresses answered, for telling apart DNS problems.
+        if config.host.is_some() {
+            result.resolved_ip = Some(addr.ip());
+        }
+        if attempts > 1 {
+            result.metrics.insert("attempts".to_string(), attempts as f64);
+        }
+        self.derived.read().unwrap().apply(&mut result);
+        let hooks = self.hooks.read().unwrap().clone();
+        if !hooks.is_empty() {
+            scripting::apply_hooks(&hooks, &result).apply_to(&mut result);
+        }
+
+        // While the monitor itself is offline, failures of external targets say nothing
+        // about them: they are kept out of the up/down state and announced once instead.
+        let canaries = self.canaries.read().unwrap().clone();
+        let mut offline = false;
+        if let Some(canaries) = canaries
+            && canary::is_external(addr.ip())
+        {
+            let (local, change) = canaries.judge(&result).await;
+            if local {
+                offline = true;
+                result.failure_kind = Some(FailureKind::InfraError);
+                result.error = result.error.map(|e| format!("{} {}", e, canary::OFFLINE_NOTE));
+            }
+            if let Some(change) = change {
+                self.bus.publish(MonitorEvent::ConnectivityChanged(change));
+            }
+        }
+
+        self.record(addr, &result, offline);
+        result
+    }
+
+    /// Takes in a result checked elsewhere, e.g. by the remote monitor a GUI is connected
+    /// to, as if the check had run here.
+    pub fn accept(&self, result: &CheckResult) -> Result<(), Box<dyn Error>> {
+        let addr: SocketAddr = result.target.parse().map_err(|e| format!("{}: {}", result.target, e))?;
+        self.ensure_known(addr)?;
+        self.record(addr, result, false);
+        Ok(())
+    }
+
+    // Updates the up/down state, staleness and service rollup and publishes the result.
+    fn record(&self, addr: SocketAddr, result: &CheckResult, offline: bool) {
+        let transition = if offline { None } else { self.tracker.lock().unwrap().record(result) };
+        if transition.as_ref().is_some_and(|t| t.to == TargetState::Up) {
+            self.acknowledged.lock().unwrap().remove(&addr);
+        }
+        let fresh = self.staleness.lock().unwrap().record(addr, result.timestamp);
+        self.bus.publish(MonitorEvent::CheckCompleted(result.clone()));
+        if let Some(transition) = transition {
+            self.bus.publish(MonitorEvent::Transition(transition));
+        }
+        if let Some(change) = fresh {
+            self.bus.publish(MonitorEvent::StaleChanged(change));
+        }
+        if let Some(service) = self.group(addr) {
+            self.update_rollup(&service, &result.correlation_id);
+        }
+    }
+
+    /// Checks `addr` once with `check`, without retries, recording or announcing the
+    /// result; for trying a check out.
+    pub async fn probe_once(&self, addr: SocketAddr, check: CheckKind) -> CheckResult {
+        let config = self.monitor_target(addr).unwrap_or_else(|| MonitorTarget::new(addr)).with_check(check);
+        let timeout = config.timeout.unwrap_or(self.timeout);
+        let clock = ClockGuard::start();
+        let mut result = self.probe(&config, timeout).await;
+        clock.check(&mut result);
+        result
+    }
+
+    async fn probe(&self, config: &MonitorTarget, timeout: Duration) -> CheckResult {
+        let addr = config.address;
+        let target = addr.to_string();
+        if let (Some(tunnel), CheckKind::Tcp | CheckKind::Http) = (&config.tunnel, config.check) {
+            return self.probe_through(config, tunnel, timeout).await;
+        }
+        if let (Some(proxy), CheckKind::Tcp | CheckKind::Http) = (&config.proxy, config.check) {
+            return self.probe_via_proxy(config, proxy, timeout).await;
+        }
+        match config.check {
+            CheckKind::Tcp => {
+                let start = Instant::now();
+                match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+                    Ok(Ok(_)) => CheckResult::success(&target, start.elapsed()),
+                    Ok(Err(e)) => CheckResult::failure(&target, e.to_string()),
+                    Err(_) => CheckResult::failure(&target, format!("timed out after {:?}", timeout)),
+                }
+            }
+            CheckKind::Icmp => match self.icmp.ping(addr.ip()).await {
+                Ok(stats) => stats.to_result(&target),
+                Err(e) => CheckResult::failure(&target, e.to_string()),
+            },
+            CheckKind::Udp => match ping_test::probe_udp(addr, UdpProbe::for_port(addr.port()), timeout).await {
+                Ok(UdpOutcome::Responded { rtt }) => CheckResult::success(&target, rtt),
+                Ok(UdpOutcome::Refused) => CheckResult::failure(&target, "UDP port unreachable"),
+                Ok(UdpOutcome::NoResponse) => {
+                    CheckResult::failure(&target, format!("no UDP response within {:?} (open or filtered)", timeout))
+                }
+                Err(e) => CheckResult::failure(&target, e.to_string()),
+            },
+            CheckKind::Http => {
+                let mut check = HttpCheck::new(&config.url(), timeout);
+                if let Some(CheckSpec::Http { expect_status, body_contains, .. }) = config.spec() {
+                    check.expect_status = *expect_status;
+                    check.body_contains = body_contains.clone();
+                }
+                let mut result = check.run(&self.http_pool()).await;
+                result.target = target;
+                result
+            }
+            CheckKind::Browser => {
+                let Some(pool) = self.browser_pool.read().unwrap().clone() else {
+                    return CheckResult::failure(&target, "no WebDriver set up for browser checks").with_failure_kind(FailureKind::InfraError);
+                };
+                let (selector, fail_on_errors) = match config.spec() {
+                    Some(CheckSpec::Browser { selector, fail_on_errors, .. }) => (selector.as_deref(), *fail_on_errors),
+                    _ => (None, false),
+                };
+                let url = config.url();
+                let load = ping_test::measure_website_timing_pooled(&pool, &url, selector);
+                let page = match config.timeout {
+                    Some(limit) => match tokio::time::timeout(limit, load).await {
+                        Ok(page) => page,
+                        Err(_) => return CheckResult::failure(&target, format!("page not usable after {:?}", limit)),
+                    },
+                    None => load.await,
+                };
+                match page {
+                    Ok(page) => page.into_result(&target, fail_on_errors),
+                    Err(e) => CheckResult::failure(&target, e.to_string()),
+                }
+            }
+            CheckKind::Windows => windows_check::check_target(config, timeout).await,
+            CheckKind::S3 => s3_check::check_target(self.http_pool().client(), config, timeout).await,
+            CheckKind::ApiQuota => rate_limit::check_target(self.http_pool().client(), config, &self.quotas).await,
+            CheckKind::Systemd | CheckKind::Disk => {
+                let Some(pool) = self.ssh.read().unwrap().clone() else {
+                    return CheckResult::failure(&target, format!("no SSH set up for {} checks", config.check.name()))
+                        .with_failure_kind(FailureKind::InfraError);
+                };
+                match config.check {
+                    CheckKind::Systemd => systemd_check::check_target(&pool, config, timeout).await,
+                    _ => disk_check::check_target(&pool, config, timeout).await,
+                }
+            }
+            CheckKind::Composite => match config.spec() {
+                Some(CheckSpec::Composite { steps }) => {
+                    let check = CompositeCheck {
+                        name: target,
+                        steps: steps.clone(),
+                        step_timeout: timeout,
+                    };
+                    check.run(&self.http_pool()).await
+                }
+                _ => CheckResult::failure(&target, "composite check without steps in its spec"),
+            },
+            CheckKind::Plugin => match config.spec() {
+                Some(CheckSpec::Plugin { name, config }) => self.run_plugin(&target, name, config).await,
+                _ => CheckResult::failure(&target, "plugin check without a plugin name in its spec"),
+            },
+        }
+    }
+
+    /// TCP and HTTP checks of a target behind a bastion, over a fresh SSH forward.
+    async fn probe_through(&self, config: &MonitorTarget, tunnel: &TunnelConfig, timeout: Duration) -> CheckResult {
+        let target = config.address.to_string();
+        let Some(pool) = self.ssh.read().unwrap().clone() else {
+            return CheckResult::failure(&target, "no SSH set up for tunneled checks").with_failure_kind(FailureKind::InfraError);
+        };
+        let mut result = match config.check {
+            CheckKind::Http => tunnel::check_http(&pool, tunnel, &config.url(), timeout).await,
+            _ => {
+                let host = config.host.clone().unwrap_or_else(|| config.address.ip().to_string());
+                tunnel::check_tcp(&pool, tunnel, &host, config.address.port(), timeout).await
+            }
+        };
+        result.target = target;
+        result
+    }
+
+    /// TCP and HTTP checks of a target as seen from a SOCKS proxy's site. Failures while
+    /// the proxy itself keeps failing are the monitor's problem, not the target's.
+    async fn probe_via_proxy(&self, config: &MonitorTarget, name: &str, timeout: Duration) -> CheckResult {
+        let target = config.address.to_string();
+        let probe = self.socks.read().unwrap().clone();
+        let Some(proxy) = probe.proxies().iter().find(|proxy| proxy.name == name) else {
+            return CheckResult::failure(&target, format!("no SOCKS proxy named '{}' in [socks]", name)).with_failure_kind(FailureKind::InfraError);
+        };
+        let mut result = match config.check {
+            CheckKind::Http => probe.check_http(proxy, &config.url(), timeout).await,
+            _ => {
+                let host = config.host.clone().unwrap_or_else(|| config.address.ip().to_string());
+                probe.check_tcp(proxy, &host, config.address.port(), timeout).await
+            }
+        };
+        result.target = target;
+        if !result.success && !probe.is_healthy(name) {
+            result = result.with_failure_kind(FailureKind::InfraError);
+        }
+        // The proxy's site is where the target was seen from.
+        result.with_agent(name)
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    async fn run_plugin(&self, target: &str, name: &str, config: &serde_json::Value) -> CheckResult {
+        let plugins = self.plugins.read().unwrap().clone();
+        if plugins.get(name).is_none() {
+            return CheckResult::failure(target, format!("no plugin named '{}' is loaded", name)).with_failure_kind(FailureKind::InfraError);
+        }
+        // Plugins run on the CPU until their fuel runs out; keep them off the async workers.
+        let (owned_target, name, config) = (target.to_string(), name.to_string(), config.clone());
+        let run = tokio::task::spawn_blocking(move || plugins.get(&name).map(|plugin| plugin.run_check(&owned_target, &config)));
+        match run.await {
+            Ok(Some(result)) => result,
+            Ok(None) | Err(_) => CheckResult::failure(target, "plugin check did not complete").with_failure_kind(FailureKind::InfraError),
+        }
+    }
+
+    #[cfg(not(feature = "wasm-plugins"))]
+    async fn run_plugin(&self, target: &str, name: &str, _config: &serde_json::Value) -> CheckResult {
+        CheckResult::failure(target, format!("cannot run plugin '{}': built without the wasm-plugins feature", name))
+            .with_failure_kind(FailureKind::InfraError)
+    }
+
+    /// The plugins that `CheckKind::Plugin` targets run, see `wasm_plugin`.
+    #[cfg(feature = "wasm-plugins")]
+    pub fn set_plugins(&self, plugins: PluginRegistry) {
+        *self.plugins.write().unwrap() = Arc::new(plugins);
+    }
+
+    #[cfg(feature = "wasm-plugins")]
+    pub fn plugins(&self) -> Arc<PluginRegistry> {
+        self.plugins.read().unwrap().clone()
+    }
+
+    /// A standby instance of an HA pair (see `ha`) keeps its targets but runs no scheduled
+    /// checks, so it neither duplicates the active instance's alerts nor adds load.
+    pub fn set_standby(&self, standby: bool) {
+        self.standby.store(standby, Ordering::SeqCst);
+    }
+
+    pub fn is_standby(&self) -> bool {
+        self.standby.load(Ordering::SeqCst)
+    }
+
+    /// Asks `schedule` to start no further checks and return, for a graceful shutdown.
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopping(&self) -> bool {
+        self.stopping.load(Ordering::SeqCst)
+    }
+
+    /// Turns low-power mode on with `policy` or off with `None`, announcing changes. The
+    /// scheduler stretches intervals and batches checks while it is on (see `power`).
+    pub fn set_low_power(&self, policy: Option<LowPower>) {
+        let was = std::mem::replace(&mut *self.low_power.write().unwrap(), policy);
+        if was.is_some() != policy.is_some() {
+            self.bus.publish(MonitorEvent::LowPowerChanged(policy.is_some()));
+        }
+    }
+
+    pub fn low_power(&self) -> Option<LowPower> {
+        *self.low_power.read().unwrap()
+    }
+
+    /// Marks targets without a result for longer than their interval, timeout and `grace`
+    /// as stale, announcing changes. Paused targets, a standby instance and maintenance
+    /// windows expect none.
+    pub fn sweep_stale(&self, grace: Duration) {
+        let standby = self.is_standby();
+        let maintenance = self.in_maintenance(Utc::now());
+        let default_interval = self.default_interval();
+        let low_power = self.low_power();
+        let deadlines: Vec<(SocketAddr, Option<Duration>)> = self
+            .monitor_targets()
+            .into_iter()
+            .map(|target| {
+                let expected = (!standby && !maintenance && !self.is_paused(target.address)).then(|| {
+                    let interval = target.interval.unwrap_or(default_interval);
+                    let interval = low_power.map_or(interval, |p| p.interval(interval) + p.batch_window);
+                    interval + target.timeout.unwrap_or(self.timeout) + grace
+                });
+                (target.address, expected)
+            })
+            .collect();
+        let changes = self.staleness.lock().unwrap().sweep(chrono::Utc::now(), &deadlines);
+        for change in changes {
+            self.bus.publish(MonitorEvent::StaleChanged(change));
+        }
+    }
+
+    /// Whether `addr` is overdue for a result (see `sweep_stale`).
+    pub fn is_stale(&self, addr: SocketAddr) -> bool {
+        self.staleness.lock().unwrap().is_stale(addr)
+    }
+
+    /// Replaces the HTTP client pool; checks already running finish on the old one.
+    pub fn set_http_pool(&self, pool: HttpPool) {
+        *self.http.write().unwrap() = pool;
+    }
+
+    pub fn http_pool(&self) -> HttpPool {
+        self.http.read().unwrap().clone()
+    }
+
+    /// Limits how many checks `run_all` runs at the same time; at least one.
+    pub fn set_concurrency(&self, limit: usize) {
+        self.concurrency.store(limit.max(1), Ordering::SeqCst);
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.load(Ordering::SeqCst)
+    }
+
+    /// With `false`, the scheduler checks new targets right away and then every interval,
+    /// instead of at their slot within it.
+    pub fn set_spread(&self, spread: bool) {
+        self.spread.store(spread, Ordering::SeqCst);
+    }
+
+    pub fn spread(&self) -> bool {
+        self.spread.load(Ordering::SeqCst)
+    }
+
+    /// Recurring windows, e.g. a weekly patch night, in which the scheduler runs no checks
+    /// and nothing is reported stale.
+    pub fn set_maintenance_windows(&self, windows: Vec<TimeWindow>) {
+        *self.maintenance.write().unwrap() = windows;
+    }
+
+    /// Names the vantage point of this instance in its results; `None` leaves them local.
+    pub fn set_agent_name(&self, agent: Option<String>) {
+        *self.agent.write().unwrap() = agent;
+    }
+
+    pub fn in_maintenance(&self, at: DateTime<Utc>) -> bool {
+        self.maintenance.read().unwrap().iter().any(|window| window.contains(at))
+    }
+
+    /// When the next maintenance window opens after `after`.
+    pub fn next_maintenance(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.maintenance.read().unwrap().iter().filter_map(|window| window.next_start(after)).min()
+    }
+
+    /// When outages and recoveries of targets without `alerts` of their own are announced.
+    pub fn set_alert_rules(&self, rules: RecoveryRules) {
+        self.tracker.lock().unwrap().set_default_rules(rules);
+    }
+
+    /// Lends browser checks warm sessions from `pool`; with `None`, they fail as an
+    /// infrastructure error.
+    pub fn set_browser_pool(&self, pool: Option<Arc<SessionPool>>) {
+        *self.browser_pool.write().unwrap() = pool;
+    }
+
+    /// Detaches the browser pool, e.g. to close its sessions on shutdown.
+    pub fn take_browser_pool(&self) -> Option<Arc<SessionPool>> {
+        self.browser_pool.write().unwrap().take()
+    }
+
+    /// Runs the commands of systemd and disk checks over `pool`; with `None`, they fail as
+    /// an infrastructure error.
+    pub fn set_ssh_pool(&self, pool: Option<Arc<SshPool>>) {
+        *self.ssh.write().unwrap() = pool;
+    }
+
+    /// Quotas on the checks of each target owner, see `budget`. Targets without an owner
+    /// aren't limited.
+    pub fn set_budget(&self, ledger: BudgetLedger) {
+        *self.budget.lock().unwrap() = ledger;
+    }
+
+    /// Checks and browser minutes each owner used in the current period.
+    pub fn budget_report(&self) -> Vec<(String, Usage)> {
+        self.budget.lock().unwrap().report()
+    }
+
+    /// The proxies targets name in their `proxy` setting.
+    pub fn set_socks_proxies(&self, proxies: Vec<SocksProxy>) {
+        *self.socks.write().unwrap() = Arc::new(SocksProbe::new(proxies));
+    }
+
+    /// Adds `metrics` to every result, see `derived_metrics`.
+    pub fn set_derived_metrics(&self, metrics: DerivedMetrics) {
+        *self.derived.write().unwrap() = Arc::new(metrics);
+    }
+
+    /// Runs `hooks` on every result before it is recorded and published.
+    pub fn set_script_hooks(&self, hooks: Vec<ScriptHook>) {
+        *self.hooks.write().unwrap() = Arc::new(hooks);
+    }
+
+    /// Checks `canaries` before counting a failure of an external target; `None` turns this
+    /// off.
+    pub fn set_canaries(&self, canaries: Option<Canaries>) {
+        *self.canaries.write().unwrap() = canaries.map(Arc::new);
+    }
+
+    /// Checks all unpaused targets concurrently, at most `concurrency` at a time, so a few
+    /// slow targets don't hold up the rest. Results come back in completion order.
+    pub async fn run_all(&self) -> Vec<CheckResult> {
+        if self.is_standby() {
+            return Vec::new();
+        }
+        self.resume_expired();
+        let targets: Vec<SocketAddr> = self.targets().into_iter().filter(|a| !self.is_paused(*a)).collect();
+        stream::iter(targets)
+            .map(|addr| self.run_check(addr))
+            .buffer_unordered(self.concurrency())
+            .collect()
+            .await
+    }
+
+    /// Checks every target on its own interval until `stop` is called. Each check runs in
+    /// a task of its own, at most `concurrency` at a time, so a slow or timed-out target
+    /// never holds up the others' slots. A target is never checked twice at the same time:
+    /// when its previous check is still running at its slot it waits for the next slot
+    /// (or, without spread, for that check to finish). After `stop`, the checks already
+    /// running are awaited.
+    /// In low-power mode intervals are stretched, nearby due times are merged and browser
+    /// checks are suspended.
+    pub async fn schedule(self: Arc<Self>) {
+        let mut next_due: HashMap<SocketAddr, Instant> = HashMap::new();
+        let mut running: JoinSet<()> = JoinSet::new();
+        let mut in_flight: HashMap<TaskId, SocketAddr> = HashMap::new();
+        // Rebuilt when the limit changes; checks holding permits of the old one finish
+        // on it.
+        let mut limit = self.concurrency();
+        let mut permits = Arc::new(Semaphore::new(limit));
+        let mut was_low_power = false;
+        while !self.is_stopping() {
+            while let Some(done) = running.try_join_next_with_id() {
+                finished(&mut in_flight, done);
+            }
+            let now = Instant::now();
+            let targets = self.monitor_targets();
+            next_due.retain(|addr, _| targets.iter().any(|t| t.address == *addr));
+            let low_power = self.low_power();
+            if was_low_power && low_power.is_none() {
+                // Back on normal intervals, without waiting out the stretched ones.
+                next_due.clear();
+            }
+            was_low_power = low_power.is_some();
+            // In low-power mode, targets due soon run with this batch instead of on their own.
+            let horizon = now + low_power.map_or(Duration::ZERO, |p| p.batch_window);
+            let default_interval = self.default_interval();
+            let spread = self.spread();
+            let wall_clock = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+            let busy: HashSet<SocketAddr> = in_flight.values().copied().collect();
+            let mut due = Vec::new();
+            for target in &targets {
+                let interval = target.interval.unwrap_or(default_interval);
+                let interval = low_power.map_or(interval, |p| p.interval(interval));
+                // Half an interval ahead, so waking a little early or late can neither
+                // repeat nor skip a slot.
+                let next_slot = || interval / 2 + until_slot(target.address, interval, wall_clock + interval / 2);
+                match next_due.get(&target.address) {
+                    // A new target waits for its slot rather than joining everything else
+                    // that starts now.
+                    None if spread => {
+                        next_due.insert(target.address, now + until_slot(target.address, interval, wall_clock));
+                    }
+                    Some(at) if *at > horizon => {}
+                    // Still running at its slot. With spread, the check starts at its next
+                    // slot, so checks that overrun can't bunch up off their slots; without,
+                    // it runs as soon as the running one is done.
+                    _ if busy.contains(&target.address) => {
+                        if spread {
+                            next_due.insert(target.address, now + next_slot());
+                        }
+                    }
+                    _ => {
+                        next_due.insert(target.address, now + if spread { next_slot() } else { interval });
+                        due.push(target.address);
+                    }
+                }
+            }
+            self.resume_expired();
+            due.retain(|addr| !self.is_paused(*addr));
+            if self.in_maintenance(Utc::now()) {
+                // Slots inside the window are skipped, not made up afterwards.
+                due.clear();
+            }
+            if low_power.is_some() {
+                // Browsers are by far the heaviest checks; they wait for normal power.
+                due.retain(|addr| self.check_kind(*addr) != CheckKind::Browser);
+            }
+            if !self.is_standby() {
+                if self.concurrency() != limit {
+                    limit = self.concurrency();
+                    permits = Arc::new(Semaphore::new(limit));
+                }
+                for addr in due {
+                    let monitor = self.clone();
+                    let permits = permits.clone();
+                    let task = running.spawn(async move {
+                        let _permit = permits.acquire_owned().await;
+                        monitor.run_check(addr).await;
+                    });
+                    in_flight.insert(task.id(), addr);
+                }
+            }
+            let wake = next_due.values().copied().min().unwrap_or(now + SCHEDULER_IDLE_POLL);
+            let sleep = tokio::time::sleep_until(wake.min(Instant::now() + SCHEDULER_IDLE_POLL).into());
+            // A finished check may free a target that was due while it ran.
+            tokio::select! {
+                _ = sleep => {}
+                Some(done) = running.join_next_with_id() => finished(&mut in_flight, done),
+            }
+        }
+        while let Some(done) = running.join_next_with_id().await {
+            finished(&mut in_flight, done);
+        }
+    }
+}
+
+// Forgets a finished check task; a panicked one is reported, and its target is checked
+// again on its next slot.
+fn finished(in_flight: &mut HashMap<TaskId, SocketAddr>, done: Result<(TaskId, ()), JoinError>) {
+    let id = match done {
+        Ok((id, ())) => id,
+        Err(e) => {
+            eprintln!("Check task failed: {}", e);
+            e.id()
+        }
+    };
+    in_flight.remove(&id);
+}
+
+/// Where in each `interval` the checks of `target` start: an offset hashed from its
+/// address (FNV-1a, which unlike `DefaultHasher` is stable across builds), so slots survive
+/// restarts and many targets with the same interval are spread evenly over it.
+pub fn phase(target: SocketAddr, interval: Duration) -> Duration {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in target.to_string().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    let interval_ms = (interval.as_millis() as u64).max(1);
+    Duration::from_millis(hash % interval_ms)
+}
+
+/// How long from `now` (time since the Unix epoch) until the next slot of `target`; a full
+/// interval when `now` is the slot.
+fn until_slot(target: SocketAddr, interval: Duration, now: Duration) -> Duration {
+    let interval_ms = (interval.as_millis() as u64).max(1);
+    let phase_ms = phase(target, interval).as_millis() as u64;
+    let wait = (phase_ms + interval_ms - now.as_millis() as u64 % interval_ms) % interval_ms;
+    Duration::from_millis(if wait == 0 { interval_ms } else { wait })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_check_results_reach_subscribers() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let mut events = monitor.bus().subscribe();
+
+        monitor.add_monitor_target(MonitorTarget::new(addr)).unwrap();
+        assert!(monitor.add_monitor_target(MonitorTarget::new(addr)).is_err());
+        assert!(monitor.run_all().await[0].success);
+
+        assert!(matches!(events.recv().await.unwrap(), MonitorEvent::TargetAdded(a) if a == addr));
+        assert!(matches!(events.recv().await.unwrap(), MonitorEvent::CheckStarted(a) if a == addr));
+        match events.recv().await.unwrap() {
+            MonitorEvent::CheckCompleted(result) => assert_eq!(result.target, addr.to_string()),
+            other => panic!("unexpected event {:?}", other),
+        }
+
+        // Targets added by host name record which of its addresses was checked.
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let named = listener.local_addr().unwrap();
+        monitor.add_monitor_target(MonitorTarget { host: Some("localhost".to_string()), ..MonitorTarget::new(named) }).unwrap();
+        assert_eq!(monitor.run_check(named).await.resolved_ip, Some(named.ip()));
+        assert_eq!(monitor.run_check(addr).await.resolved_ip, None);
+
+        // Script hooks see every result before it is published.
+        let hook = ScriptHook::from_source("tag", r#"status = "checked"; severity = "low";"#).unwrap();
+        monitor.set_script_hooks(vec![hook]);
+        let result = monitor.run_check(addr).await;
+        assert_eq!((result.status.as_deref(), result.severity), (Some("checked"), Some(crate::back_end::severity::Severity::Low)));
+    }
+
+    #[tokio::test]
+    async fn test_run_all_checks_targets_concurrently() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let open = listener.local_addr().unwrap();
+        // Connections to this non-routable address hang until the timeout.
+        let blackholes = (1..=4).map(|port| SocketAddr::from(([10, 255, 255, 1], port)));
+        let monitor = Monitor::new(EventBus::new(), Duration::from_millis(300));
+        monitor.set_concurrency(8);
+        for addr in blackholes.chain([open]) {
+            monitor.add_monitor_target(MonitorTarget::new(addr)).unwrap();
+        }
+
+        let start = Instant::now();
+        let results = monitor.run_all().await;
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().any(|r| r.target == open.to_string() && r.success));
+        // Sequentially this would take four timeouts.
+        assert!(start.elapsed() < Duration::from_millis(900), "took {:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    async fn test_service_rollup_changes_are_announced() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let web = listener.local_addr().unwrap();
+        let db = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        for addr in [web, db] {
+            monitor.add_monitor_target(MonitorTarget::new(addr)).unwrap();
+            monitor.set_group(addr, Some("shop".to_string())).unwrap();
+        }
+        assert_eq!(monitor.service("shop").unwrap().rollup, Rollup::Unknown);
+        let mut events = monitor.bus().subscribe();
+
+        assert_eq!(monitor.run_service("shop").await.len(), 2);
+        let status = monitor.service("shop").unwrap();
+        assert_eq!(status.rollup, Rollup::Degraded);
+        assert_eq!(status.down().collect::<Vec<_>>(), vec![db]);
+
+        let mut last = None;
+        while let Ok(event) = events.try_recv() {
+            if let MonitorEvent::ServiceChanged(change) = event {
+                last = Some(change.to);
+            }
+        }
+        assert_eq!(last, Some(Rollup::Degraded));
+    }
+
+    #[tokio::test]
+    async fn test_paused_targets_are_skipped_and_alerts_acknowledged() {
+        // Bind and drop to get a port nothing listens on.
+        let addr = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        monitor.add_monitor_target(MonitorTarget::new(addr)).unwrap();
+
+        assert!(monitor.acknowledge(addr).is_err());
+        assert!(!monitor.run_all().await[0].success);
+        monitor.acknowledge(addr).unwrap();
+        assert!(monitor.is_acknowledged(addr));
+
+        let mut pause = Pause::new("decommissioning").unwrap();
+        monitor.pause(addr, pause.clone()).unwrap();
+        assert!(monitor.run_all().await.is_empty());
+        assert!(monitor.pause("127.0.0.1:1".parse().unwrap(), pause.clone()).is_err());
+        pause.reason.clear();
+        assert!(monitor.pause(addr, pause.clone()).is_err());
+
+        // Expired pauses end on the next run.
+        pause.reason = "maintenance".to_string();
+        monitor.pause(addr, pause.until(chrono::Utc::now())).unwrap();
+        assert_eq!(monitor.run_all().await.len(), 1);
+        assert!(monitor.pause_of(addr).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_http_and_browser_checks_are_dispatched_by_kind() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let _ = socket.read(&mut [0; 1024]).await;
+                socket.write_all(b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").await.unwrap();
+            }
+        });
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(2));
+        monitor.add_monitor_target(MonitorTarget::new(addr).with_check(CheckKind::Http)).unwrap();
+
+        let result = monitor.run_check(addr).await;
+        assert_eq!((result.target.as_str(), result.error.as_deref()), (addr.to_string().as_str(), Some("HTTP 503 Service Unavailable")));
+        let spec = CheckSpec::Http {
+            url: format!("http://{}/maintenance", addr),
+            expect_status: Some(503),
+            body_contains: None,
+        };
+        monitor.configure(MonitorTarget::new(addr).with_spec(spec)).unwrap();
+        assert!(monitor.run_check(addr).await.success);
+
+        // Without a WebDriver, browser checks fail as the monitor's problem, not the site's.
+        let result = monitor.probe_once(addr, CheckKind::Browser).await;
+        assert_eq!(result.failure_kind, Some(FailureKind::InfraError));
+
+        // Tunneled targets are never connected to directly, even though this one is reachable.
+        let bastion = crate::back_end::ssh::SshTarget::new("bastion.example.com", "monitor");
+        let tunnel = TunnelConfig {
+            bastion,
+            jump_hosts: Vec::new(),
+            ready_timeout: None,
+        };
+        monitor.configure(MonitorTarget { tunnel: Some(tunnel), ..MonitorTarget::new(addr) }).unwrap();
+        let result = monitor.probe_once(addr, CheckKind::Tcp).await;
+        assert_eq!(result.failure_kind, Some(FailureKind::InfraError));
+        monitor.configure(MonitorTarget { proxy: Some("branch".to_string()), ..MonitorTarget::new(addr) }).unwrap();
+        let result = monitor.probe_once(addr, CheckKind::Http).await;
+        assert_eq!(result.error.as_deref(), Some("no SOCKS proxy named 'branch' in [socks]"));
+    }
+
+    #[tokio::test]
+    async fn test_targets_use_their_own_settings_and_schedule() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fast = listener.local_addr().unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_secs(1)));
+        monitor.set_default_interval(Duration::from_secs(3600));
+        monitor.set_spread(false);
+        monitor
+            .add_monitor_target(MonitorTarget::new(fast).with_interval(Duration::from_millis(50)))
+            .unwrap();
+        monitor.add_monitor_target(MonitorTarget::new(closed).with_retries(2)).unwrap();
+
+        let result = monitor.run_check(closed).await;
+        assert!(!result.success);
+        assert_eq!(result.metrics["attempts"], 3.0);
+
+        let mut events = monitor.bus().subscribe();
+        let scheduler = tokio::spawn(monitor.clone().schedule());
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        monitor.stop();
+        tokio::time::timeout(Duration::from_secs(2), scheduler).await.unwrap().unwrap();
+        let mut checked = HashMap::new();
+        while let Ok(event) = events.try_recv() {
+            if let MonitorEvent::CheckStarted(addr) = event {
+                *checked.entry(addr).or_insert(0) += 1;
+            }
+        }
+        assert!(checked[&fast] >= 3, "{:?}", checked);
+        // `closed` runs once at startup, then waits for the hour-long default interval.
+        assert_eq!(checked[&closed], 1);
+    }
+
+    #[tokio::test]
+    async fn test_slow_target_does_not_hold_up_the_others() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let fast = listener.local_addr().unwrap();
+        // A UDP socket that never answers: each check waits for the full timeout.
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let slow = silent.local_addr().unwrap();
+        let monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_millis(800)));
+        monitor.set_spread(false);
+        monitor.add_monitor_target(MonitorTarget::new(slow).with_check(CheckKind::Udp).with_interval(Duration::from_millis(50))).unwrap();
+        monitor.add_monitor_target(MonitorTarget::new(fast).with_interval(Duration::from_millis(50))).unwrap();
+
+        let mut events = monitor.bus().subscribe();
+        let scheduler = tokio::spawn(monitor.clone().schedule());
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        monitor.stop();
+        // Stopping waits for the slow check that is still running.
+        tokio::time::timeout(Duration::from_secs(2), scheduler).await.unwrap().unwrap();
+        let (mut started, mut completed) = (HashMap::new(), HashMap::new());
+        while let Ok(event) = events.try_recv() {
+            match event {
+                MonitorEvent::CheckStarted(addr) => *started.entry(addr).or_insert(0) += 1,
+                MonitorEvent::CheckCompleted(result) => *completed.entry(result.target).or_insert(0) += 1,
+                _ => {}
+            }
+        }
+        assert!(started[&fast] >= 5, "{:?}", started);
+        // Never twice at the same time, however often it is due.
+        assert_eq!(started[&slow], 1);
+        assert_eq!(completed[&slow.to_string()], 1);
+    }
+
+    #[tokio::test]
+    async fn test_overrunning_check_waits_for_its_next_slot() {
+        let silent = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let slow = silent.local_addr().unwrap();
+        let interval = Duration::from_millis(200);
+        // Each check takes one and a half intervals.
+        let monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_millis(300)));
+        monitor.add_monitor_target(MonitorTarget::new(slow).with_check(CheckKind::Udp).with_interval(interval)).unwrap();
+
+        let mut events = monitor.bus().subscribe();
+        let scheduler = tokio::spawn(monitor.clone().schedule());
+        let mut offsets = Vec::new();
+        let deadline = Instant::now() + Duration::from_millis(1500);
+        while let Ok(Ok(event)) = tokio::time::timeout_at(deadline.into(), events.recv()).await {
+            if let MonitorEvent::CheckStarted(_) = event {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+                offsets.push(now.as_millis() as i64 % 200);
+            }
+        }
+        monitor.stop();
+        scheduler.await.unwrap();
+
+        // Every other slot is skipped, but each start is on one.
+        let slot = phase(slow, interval).as_millis() as i64;
+        assert!((2..=4).contains(&offsets.len()), "{:?}", offsets);
+        for offset in offsets {
+            let off_by = (offset - slot).rem_euclid(200);
+            assert!(off_by.min(200 - off_by) < 40, "started {}ms off its slot", off_by);
+        }
+    }
+
+    #[test]
+    fn test_check_starts_are_spread_over_the_interval() {
+        let interval = Duration::from_secs(60);
+        let targets: Vec<SocketAddr> = (1..=300).map(|i| SocketAddr::from(([10, 0, (i / 250) as u8, (i % 250) as u8], 443))).collect();
+        // 300 targets on a 60s interval: no second may get more than a small share of them.
+        let mut per_second = [0; 60];
+        for target in &targets {
+            per_second[phase(*target, interval).as_secs() as usize] += 1;
+        }
+        assert!(per_second.iter().all(|n| *n <= 15), "{:?}", per_second);
+        assert_eq!(phase(targets[0], interval), phase(targets[0], interval));
+
+        let target = targets[0];
+        let slot = Duration::from_secs(1_700_000_040) + phase(target, interval);
+        assert_eq!(until_slot(target, interval, slot - Duration::from_secs(5)), Duration::from_secs(5));
+        assert_eq!(until_slot(target, interval, slot), interval);
+        assert_eq!(until_slot(target, interval, slot + Duration::from_secs(1)), Duration::from_secs(59));
+    }
+}
@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+/// Returns the page's navigation entry, in ms since navigation start. Browsers without
+/// Navigation Timing Level 2 (older Safari) get the same fields from `performance.timing`.
+pub const NAVIGATION_TIMING_SCRIPT: &str = r#"
+const [entry] = performance.getEntriesByType('navigation');
+if (entry) return entry.toJSON();
+const t = performance.timing;
+if (!t || !t.navigationStart) return null;
+const fields = ['domainLookupStart', 'domainLookupEnd', 'connectStart', 'connectEnd', 'secureConnectionStart',
+    'requestStart', 'responseStart', 'responseEnd', 'domContentLoadedEventEnd', 'loadEventEnd'];
+const since = {};
+for (const field of fields) since[field] = t[field] ? t[field] - t.navigationStart : 0;
+return since;
+"#;
+
+/// Where the time of a page load went, from the browser's Navigation Timing entry. Phases
+/// are durations; `ttfb_ms`, `dom_content_loaded_ms` and `load_event_ms` are ms since
+/// navigation start.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct NavigationTiming {
+    pub dns_ms: f64,
+    /// TCP connect, including the TLS handshake.
+    pub connect_ms: f64,
+    /// `None` for plain HTTP and reused connections.
+    pub tls_ms: Option<f64>,
+    /// Until the first byte of the response.
+    pub ttfb_ms: f64,
+    /// From the first to the last byte of the response.
+    pub download_ms: f64,
+    pub dom_content_loaded_ms: f64,
+    /// `None` if the load event had not finished yet.
+    pub load_event_ms: Option<f64>,
+}
+
+impl NavigationTiming {
+    /// Reads the object `NAVIGATION_TIMING_SCRIPT` returns; `None` if the browser had no
+    /// entry (e.g. the page never loaded).
+    pub fn from_entry(entry: &JsonValue) -> Option<Self> {
+        let field = |name: &str| entry[name].as_f64().unwrap_or(0.0);
+        let span = |from: &str, to: &str| (field(to) - field(from)).max(0.0);
+        let response_start = entry["responseStart"].as_f64()?;
+        let secure_start = field("secureConnectionStart");
+        Some(Self {
+            dns_ms: span("domainLookupStart", "domainLookupEnd"),
+            connect_ms: span("connectStart", "connectEnd"),
+            tls_ms: (secure_start > 0.0).then(|| (field("connectEnd") - secure_start).max(0.0)),
+            ttfb_ms: response_start,
+            download_ms: span("responseStart", "responseEnd"),
+            dom_content_loaded_ms: field("domContentLoadedEventEnd"),
+            load_event_ms: Some(field("loadEventEnd")).filter(|ms| *ms > 0.0),
+        })
+    }
+
+    /// The phases as named metrics for `CheckResult::metrics`, e.g. `nav_ttfb_ms`.
+    pub fn metrics(&self) -> Vec<(&'static str, f64)> {
+        let mut metrics = vec![
+            ("nav_dns_ms", self.dns_ms),
+            ("nav_connect_ms", self.connect_ms),
+            ("nav_ttfb_ms", self.ttfb_ms),
+            ("nav_download_ms", self.download_ms),
+            ("nav_dom_content_loaded_ms", self.dom_content_loaded_ms),
+        ];
+        metrics.extend(self.tls_ms.map(|ms| ("nav_tls_ms", ms)));
+        metrics.extend(self.load_event_ms.map(|ms| ("nav_load_event_ms", ms)));
+        metrics
+    }
+}
+
+impl fmt::Display for NavigationTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DNS {:.0} ms, connect {:.0} ms", self.dns_ms, self.connect_ms)?;
+        if let Some(tls) = self.tls_ms {
+            write!(f, " (TLS {:.0} ms)", tls)?;
+        }
+        write!(f, ", TTFB {:.0} ms, DOMContentLoaded {:.0} ms", self.ttfb_ms, self.dom_content_loaded_ms)?;
+        match self.load_event_ms {
+            Some(load) => write!(f, ", load {:.0} ms", load),
+            None => write!(f, ", load not finished"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_breakdown_from_navigation_entry() {
+        let entry = json!({
+            "name": "https://example.com/", "startTime": 0.0,
+            "domainLookupStart": 2.0, "domainLookupEnd": 14.5,
+            "connectStart": 14.5, "secureConnectionStart": 30.0, "connectEnd": 52.0,
+            "requestStart": 52.5, "responseStart": 120.0, "responseEnd": 135.0,
+            "domContentLoadedEventEnd": 340.0, "loadEventEnd": 0.0
+        });
+        let timing = NavigationTiming::from_entry(&entry).unwrap();
+        assert_eq!((timing.dns_ms, timing.connect_ms, timing.tls_ms), (12.5, 37.5, Some(22.0)));
+        assert_eq!((timing.ttfb_ms, timing.download_ms, timing.load_event_ms), (120.0, 15.0, None));
+        assert_eq!(timing.to_string(), "DNS 12 ms, connect 38 ms (TLS 22 ms), TTFB 120 ms, DOMContentLoaded 340 ms, load not finished");
+        assert!(timing.metrics().iter().any(|(name, _)| *name == "nav_tls_ms"));
+        assert!(!timing.metrics().iter().any(|(name, _)| *name == "nav_load_event_ms"));
+
+        assert!(NavigationTiming::from_entry(&JsonValue::Null).is_none());
+    }
+}
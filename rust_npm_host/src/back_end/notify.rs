@@ -0,0 +1,320 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::csv_import::parse_interval;
+use super::event_bus::{EventBus, MonitorEvent};
+use super::severity::Severity;
+use super::state_tracker::{TargetState, Transition};
+
+// How many suppressed targets a storm summary names before it just counts the rest.
+const SUMMARY_MAX_TARGETS: usize = 10;
+
+/// A message for the people on call, independent of the channel that delivers it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub target: String,
+    pub severity: Severity,
+    pub message: String,
+    pub at: DateTime<Utc>,
+    /// Correlation ID of the check run behind this notification; empty for summaries.
+    pub correlation_id: String,
+}
+
+impl fmt::Display for Notification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.target, self.message)
+    }
+}
+
+impl From<&Transition> for Notification {
+    fn from(transition: &Transition) -> Self {
+        let (severity, message) = match (transition.to, transition.downtime) {
+            (TargetState::Down, _) => (transition.severity.unwrap_or(Severity::Critical), "is DOWN".to_string()),
+            (TargetState::Up, Some(downtime)) => (Severity::Info, format!("is UP again after {:?}", downtime)),
+            (TargetState::Up, None) => (Severity::Info, "is UP".to_string()),
+        };
+        Self {
+            target: transition.target.clone(),
+            severity,
+            message,
+            at: transition.at,
+            correlation_id: transition.correlation_id.clone(),
+        }
+    }
+}
+
+/// Global cap on outgoing notifications, e.g. 20 per 5 minutes.
+#[derive(Debug, Clone)]
+pub struct StormLimits {
+    pub max_notifications: usize,
+    pub window: ChronoDuration,
+}
+
+impl Default for StormLimits {
+    fn default() -> Self {
+        Self {
+            max_notifications: 20,
+            window: ChronoDuration::minutes(5),
+        }
+    }
+}
+
+/// Keeps a site-wide outage from flooding on-call phones. Notifications beyond the limit are
+/// held back and, once the window has room again, replaced by a single summary message.
+#[derive(Debug, Default)]
+pub struct StormGuard {
+    limits: StormLimits,
+    sent: VecDeque<DateTime<Utc>>,
+    suppressed: Vec<Notification>,
+}
+
+impl StormGuard {
+    pub fn new(limits: StormLimits) -> Self {
+        Self {
+            limits,
+            ..Self::default()
+        }
+    }
+
+    /// Offers one notification and returns what should be delivered now: nothing during a
+    /// storm, otherwise the notification itself, preceded by the summary of a storm that
+    /// just ended. A notification offered again while held back is only counted once.
+    pub fn admit(&mut self, notification: Notification, now: DateTime<Utc>) -> Vec<Notification> {
+        let mut out: Vec<Notification> = self.flush(now).into_iter().collect();
+        if self.has_room(now) {
+            self.sent.push_back(now);
+            out.push(notification);
+        } else if notification.correlation_id.is_empty() || !self.suppressed.contains(&notification) {
+            self.suppressed.push(notification);
+        }
+        out
+    }
+
+    /// Returns the storm summary once the window has room for it. Call this periodically so
+    /// the summary also goes out when no further notifications arrive.
+    pub fn flush(&mut self, now: DateTime<Utc>) -> Option<Notification> {
+        if self.suppressed.is_empty() || !self.has_room(now) {
+            return None;
+        }
+        self.sent.push_back(now);
+        let suppressed = std::mem::take(&mut self.suppressed);
+        Some(summarize(&suppressed, now))
+    }
+
+    fn has_room(&mut self, now: DateTime<Utc>) -> bool {
+        while self.sent.front().is_some_and(|at| now - *at >= self.limits.window) {
+            self.sent.pop_front();
+        }
+        self.sent.len() < self.limits.max_notifications.max(1)
+    }
+}
+
+/// A `StormGuard` shared by every channel (webhook, owner routing, tickets), so the cap
+/// holds for all their notifications together. Storm summaries are published as
+/// `MonitorEvent::StormSummary` for the channels to deliver.
+#[derive(Debug, Clone)]
+pub struct SharedStormGuard {
+    guard: Arc<Mutex<StormGuard>>,
+    bus: EventBus,
+}
+
+impl SharedStormGuard {
+    pub fn new(limits: StormLimits, bus: EventBus) -> Self {
+        Self {
+            guard: Arc::new(Mutex::new(StormGuard::new(limits))),
+            bus,
+        }
+    }
+
+    /// Whether `notification` may be sent now.
+    pub fn admit(&self, notification: &Notification) -> bool {
+        let now = Utc::now();
+        let mut guard = self.guard.lock().unwrap();
+        if let Some(summary) = guard.flush(now) {
+            self.bus.publish(MonitorEvent::StormSummary(summary));
+        }
+        !guard.admit(notification.clone(), now).is_empty()
+    }
+
+    /// Publishes the summary of a storm once the window has room for it, also when no
+    /// further notifications arrive; checks every `every` until the task is dropped.
+    pub async fn flush_every(self, every: Duration) {
+        let mut timer = tokio::time::interval(every);
+        loop {
+            timer.tick().await;
+            let summary = self.guard.lock().unwrap().flush(Utc::now());
+            if let Some(summary) = summary {
+                self.bus.publish(MonitorEvent::StormSummary(summary));
+            }
+        }
+    }
+}
+
+/// How a channel delivers notifications. Chat can take every flap as it happens; mail
+/// is better off with one summary every quarter hour.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Delivery {
+    #[default]
+    Instant,
+    /// Collected and sent as one message per interval.
+    Digest(ChronoDuration),
+}
+
+impl FromStr for Delivery {
+    type Err = String;
+
+    /// `instant`, or the digest interval like `15m`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "instant" | "0" => Ok(Delivery::Instant),
+            interval => {
+                let interval = parse_interval(interval)?;
+                ChronoDuration::from_std(interval).map(Delivery::Digest).map_err(|e| e.to_string())
+            }
+        }
+    }
+}
+
+/// Holds back what a digest channel would send, until its interval has passed since the
+/// first held item. Items are anything the channel sends, e.g. notifications or webhook
+/// state changes.
+#[derive(Debug)]
+pub struct DigestBuffer<T> {
+    delivery: Delivery,
+    pending: Vec<T>,
+    since: Option<DateTime<Utc>>,
+}
+
+impl<T> DigestBuffer<T> {
+    pub fn new(delivery: Delivery) -> Self {
+        Self {
+            delivery,
+            pending: Vec::new(),
+            since: None,
+        }
+    }
+
+    /// Offers one item; returns it right back for instant delivery, otherwise keeps it.
+    pub fn offer(&mut self, item: T, now: DateTime<Utc>) -> Option<T> {
+        if self.delivery == Delivery::Instant {
+            return Some(item);
+        }
+        self.since.get_or_insert(now);
+        self.pending.push(item);
+        None
+    }
+
+    /// The held items, oldest first, once the interval is up. Call this periodically.
+    pub fn due(&mut self, now: DateTime<Utc>) -> Option<Vec<T>> {
+        let Delivery::Digest(interval) = self.delivery else {
+            return None;
+        };
+        if self.since.is_some_and(|since| now - since >= interval) {
+            self.since = None;
+            return Some(std::mem::take(&mut self.pending));
+        }
+        None
+    }
+}
+
+fn summarize(suppressed: &[Notification], now: DateTime<Utc>) -> Notification {
+    let targets: BTreeSet<&str> = suppressed.iter().map(|n| n.target.as_str()).collect();
+    let mut names: Vec<&str> = targets.iter().copied().take(SUMMARY_MAX_TARGETS).collect();
+    let more = targets.len().saturating_sub(SUMMARY_MAX_TARGETS);
+    let more = (more > 0).then(|| format!("{} more", more));
+    names.extend(more.as_deref());
+    Notification {
+        target: format!("{} targets", targets.len()),
+        severity: suppressed.iter().map(|n| n.severity).max().unwrap_or(Severity::Info),
+        message: format!("{} notifications suppressed during alert storm: {}", suppressed.len(), names.join(", ")),
+        at: now,
+        correlation_id: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn down(target: &str, at: DateTime<Utc>) -> Notification {
+        Notification {
+            target: target.to_string(),
+            severity: Severity::Critical,
+            message: "is DOWN".to_string(),
+            at,
+            correlation_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_digest_holds_items_for_its_interval() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let mut instant = DigestBuffer::new("instant".parse().unwrap());
+        assert_eq!(instant.offer(1, start), Some(1));
+        assert_eq!(instant.due(start + ChronoDuration::hours(1)), None);
+
+        let mut digest = DigestBuffer::new("15m".parse().unwrap());
+        assert_eq!(digest.offer(1, start), None);
+        assert_eq!(digest.offer(2, start + ChronoDuration::minutes(10)), None);
+        assert_eq!(digest.due(start + ChronoDuration::minutes(14)), None);
+        assert_eq!(digest.due(start + ChronoDuration::minutes(15)), Some(vec![1, 2]));
+        // The next window starts with the next item, not on a fixed clock.
+        assert_eq!(digest.due(start + ChronoDuration::minutes(40)), None);
+        digest.offer(3, start + ChronoDuration::minutes(41));
+        assert_eq!(digest.due(start + ChronoDuration::minutes(55)), None);
+        assert_eq!(digest.due(start + ChronoDuration::minutes(56)), Some(vec![3]));
+        assert!("soon".parse::<Delivery>().is_err());
+    }
+
+    #[test]
+    fn test_storm_is_summarized_once_window_has_room() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let mut guard = StormGuard::new(StormLimits {
+            max_notifications: 2,
+            window: ChronoDuration::minutes(5),
+        });
+        assert_eq!(guard.admit(down("a", start), start).len(), 1);
+        assert_eq!(guard.admit(down("b", start), start).len(), 1);
+        for target in ["c", "d", "c"] {
+            assert!(guard.admit(down(target, start), start).is_empty());
+        }
+        assert_eq!(guard.suppressed.len(), 3);
+        assert!(guard.flush(start + ChronoDuration::minutes(1)).is_none());
+
+        let later = start + ChronoDuration::minutes(5);
+        let out = guard.admit(down("e", later), later);
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].target, "2 targets");
+        assert!(out[0].message.starts_with("3 notifications suppressed"));
+        assert_eq!(out[0].severity, Severity::Critical);
+        assert_eq!(out[1].target, "e");
+        assert!(guard.suppressed.is_empty());
+    }
+
+    #[test]
+    fn test_shared_guard_caps_all_channels_together() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        let storm = SharedStormGuard::new(
+            StormLimits {
+                max_notifications: 1,
+                window: ChronoDuration::minutes(5),
+            },
+            bus,
+        );
+        let webhook = storm.clone();
+        let mut ticket = down("b", Utc::now());
+        ticket.correlation_id = "run-1".to_string();
+        assert!(webhook.admit(&down("a", Utc::now())));
+        assert!(!storm.admit(&ticket));
+        // Tickets are offered again on every poll; that is still one held-back notification.
+        assert!(!storm.admit(&ticket));
+        assert_eq!(storm.guard.lock().unwrap().suppressed.len(), 1);
+        assert!(events.try_recv().is_err());
+    }
+}
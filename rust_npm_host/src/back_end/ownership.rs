@@ -0,0 +1,276 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::check_result::CheckResult;
+use super::csv_import::parse_interval;
+use super::event_bus::MonitorEvent;
+use super::monitor::Monitor;
+use super::notify::{Notification, SharedStormGuard};
+use super::state_tracker::TargetState;
+use super::webhook::{StateChange, WebhookKind, WebhookNotifier};
+
+// How often unacknowledged outages are looked at for due escalation steps.
+const ESCALATION_POLL: Duration = Duration::from_secs(10);
+
+/// Further channels to notify once a target has been down for `after` and nobody has
+/// acknowledged the alert.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct EscalationStep {
+    #[serde(deserialize_with = "interval")]
+    pub after: ChronoDuration,
+    pub channels: Vec<String>,
+}
+
+/// Where a user's or team's alerts go by default.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Owner {
+    /// Webhook URLs notified of every up/down change of the owner's targets.
+    #[serde(default)]
+    pub channels: Vec<String>,
+    #[serde(default)]
+    pub escalation: Vec<EscalationStep>,
+}
+
+/// The owners targets can name, read from TOML:
+///
+/// ```text
+/// [owner.dba]
+/// channels = ["https://hooks.slack.com/services/..."]
+///
+/// [[owner.dba.escalation]]
+/// after = "15m"
+/// channels = ["https://discord.com/api/webhooks/..."]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct OwnerDirectory {
+    #[serde(default, rename = "owner")]
+    pub owners: BTreeMap<String, Owner>,
+}
+
+impl OwnerDirectory {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// The channels of `owner` for an outage that has lasted `down_for`: its own plus those
+    /// of every escalation step reached. Empty for unknown owners.
+    pub fn channels(&self, owner: &str, down_for: ChronoDuration) -> BTreeSet<String> {
+        let Some(owner) = self.owners.get(owner) else {
+            return BTreeSet::new();
+        };
+        let escalated = owner.escalation.iter().filter(|step| down_for >= step.after);
+        owner.channels.iter().chain(escalated.flat_map(|step| &step.channels)).cloned().collect()
+    }
+}
+
+fn interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ChronoDuration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_interval(&value)
+        .and_then(|d| ChronoDuration::from_std(d).map_err(|e| e.to_string()))
+        .map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone)]
+struct Outage {
+    owner: String,
+    change: StateChange,
+    notified: BTreeSet<String>,
+    acknowledged: bool,
+}
+
+/// Decides which owner channels hear of which change. Everyone notified of an outage,
+/// escalation included, also hears of the recovery.
+#[derive(Debug, Default)]
+pub struct OwnerRouter {
+    directory: OwnerDirectory,
+    outages: HashMap<String, Outage>,
+}
+
+impl OwnerRouter {
+    pub fn new(directory: OwnerDirectory) -> Self {
+        Self {
+            directory,
+            outages: HashMap::new(),
+        }
+    }
+
+    /// The channels to send `change` to, for a target owned by `owner`.
+    pub fn route(&mut self, change: &StateChange, owner: &str) -> BTreeSet<String> {
+        match change.new_state {
+            TargetState::Down => {
+                let notified = self.directory.channels(owner, ChronoDuration::zero());
+                self.outages.insert(
+                    change.target.clone(),
+                    Outage {
+                        owner: owner.to_string(),
+                        change: change.clone(),
+                        notified: notified.clone(),
+                        acknowledged: false,
+                    },
+                );
+                notified
+            }
+            TargetState::Up => match self.outages.remove(&change.target) {
+                Some(outage) => outage.notified,
+                None => self.directory.channels(owner, ChronoDuration::zero()),
+            },
+        }
+    }
+
+    /// Stops escalating the outage of `target`.
+    pub fn acknowledge(&mut self, target: &str) {
+        if let Some(outage) = self.outages.get_mut(target) {
+            outage.acknowledged = true;
+        }
+    }
+
+    pub fn forget(&mut self, target: &str) {
+        self.outages.remove(target);
+    }
+
+    /// Escalation messages that are due by `now`, with the channel to send each to.
+    pub fn escalations(&mut self, now: DateTime<Utc>) -> Vec<(String, StateChange)> {
+        let mut due = Vec::new();
+        for outage in self.outages.values_mut().filter(|o| !o.acknowledged) {
+            let down_for = now - outage.change.at;
+            let reached = self.directory.channels(&outage.owner, down_for);
+            for channel in reached.difference(&outage.notified) {
+                let mut change = outage.change.clone();
+                change.message = format!("{} (escalated, down for {} min)", change.message, down_for.num_minutes());
+                due.push((channel.clone(), change));
+            }
+            outage.notified.extend(reached);
+        }
+        due
+    }
+}
+
+/// Sends the up/down changes of owned targets to their owners' channels, escalating
+/// unacknowledged outages, until the event bus closes.
+pub async fn route(monitor: Arc<Monitor>, directory: OwnerDirectory, storm: SharedStormGuard) {
+    let mut events = monitor.bus().subscribe();
+    let mut router = OwnerRouter::new(directory);
+    let mut notifiers: HashMap<String, WebhookNotifier> = HashMap::new();
+    // The result that caused a transition is published just before it.
+    let mut last: HashMap<String, CheckResult> = HashMap::new();
+    let mut poll = tokio::time::interval(ESCALATION_POLL);
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = poll.tick() => {
+                for (channel, change) in router.escalations(Utc::now()) {
+                    send(&mut notifiers, &channel, &change, &storm).await;
+                }
+                continue;
+            }
+        };
+        match event {
+            Ok(MonitorEvent::CheckCompleted(result)) => {
+                last.insert(result.target.clone(), result);
+            }
+            Ok(MonitorEvent::Transition(transition)) => {
+                let owner = transition
+                    .target
+                    .parse::<SocketAddr>()
+                    .ok()
+                    .and_then(|addr| monitor.monitor_target(addr))
+                    .and_then(|target| target.owner);
+                let Some(owner) = owner else { continue };
+                let result = last.get(&transition.target).filter(|r| r.correlation_id == transition.correlation_id);
+                let change = StateChange::new(&transition, result);
+                for channel in router.route(&change, &owner) {
+                    send(&mut notifiers, &channel, &change, &storm).await;
+                }
+            }
+            Ok(MonitorEvent::AlertAcknowledged(addr)) => router.acknowledge(&addr.to_string()),
+            Ok(MonitorEvent::TargetRemoved(addr)) => {
+                last.remove(&addr.to_string());
+                router.forget(&addr.to_string());
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => eprintln!("Owner routing missed {} events", missed),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+async fn send(notifiers: &mut HashMap<String, WebhookNotifier>, channel: &str, change: &StateChange, storm: &SharedStormGuard) {
+    if !storm.admit(&Notification::from(change)) {
+        return;
+    }
+    if !notifiers.contains_key(channel) {
+        match WebhookNotifier::new(channel, WebhookKind::detect(channel), Duration::from_secs(10)) {
+            Ok(notifier) => {
+                notifiers.insert(channel.to_string(), notifier);
+            }
+            Err(e) => {
+                eprintln!("Cannot set up webhook {}: {}", channel, e);
+                return;
+            }
+        }
+    }
+    if let Err(e) = notifiers[channel].send(change).await {
+        eprintln!("Webhook {} failed for {}: {}", channel, change.target, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::state_tracker::Transition;
+    use chrono::TimeZone;
+
+    const OWNERS: &str = r#"
+        [owner.dba]
+        channels = ["https://hooks.slack.com/services/dba"]
+
+        [[owner.dba.escalation]]
+        after = "15m"
+        channels = ["https://discord.com/api/webhooks/oncall"]
+    "#;
+
+    fn change(to: TargetState, at: DateTime<Utc>) -> StateChange {
+        let transition = Transition {
+            target: "10.0.0.6:5432".to_string(),
+            from: None,
+            to,
+            at,
+            downtime: None,
+            correlation_id: "c1".to_string(),
+            metadata: Default::default(),
+            severity: None,
+        };
+        StateChange::new(&transition, None)
+    }
+
+    #[test]
+    fn test_outages_escalate_until_acknowledged_and_recoveries_reach_everyone() {
+        let directory: OwnerDirectory = toml::from_str(OWNERS).unwrap();
+        assert!(directory.channels("web", ChronoDuration::hours(1)).is_empty());
+        let start = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        let mut router = OwnerRouter::new(directory.clone());
+
+        let first = router.route(&change(TargetState::Down, start), "dba");
+        assert_eq!(first.into_iter().collect::<Vec<_>>(), ["https://hooks.slack.com/services/dba"]);
+        assert!(router.escalations(start + ChronoDuration::minutes(14)).is_empty());
+        let escalated = router.escalations(start + ChronoDuration::minutes(15));
+        assert_eq!(escalated.len(), 1);
+        assert_eq!(escalated[0].0, "https://discord.com/api/webhooks/oncall");
+        assert!(escalated[0].1.message.contains("escalated, down for 15 min"));
+        assert!(router.escalations(start + ChronoDuration::minutes(30)).is_empty());
+        assert_eq!(router.route(&change(TargetState::Up, start + ChronoDuration::hours(1)), "dba").len(), 2);
+
+        let mut router = OwnerRouter::new(directory);
+        router.route(&change(TargetState::Down, start), "dba");
+        router.acknowledge("10.0.0.6:5432");
+        assert!(router.escalations(start + ChronoDuration::hours(1)).is_empty());
+        assert_eq!(router.route(&change(TargetState::Up, start + ChronoDuration::hours(1)), "dba").len(), 1);
+    }
+}
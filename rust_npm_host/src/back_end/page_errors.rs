@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+/// Errors kept per page load; a broken page can log thousands.
+pub const MAX_PAGE_ERRORS: usize = 100;
+
+/// Installed before navigation (on Chrome, through the DevTools protocol), so errors are
+/// caught from the first script on: `console.error` calls, uncaught exceptions and
+/// rejections, elements that failed to load, and fetch/XHR calls that failed or got a
+/// 4xx/5xx.
+pub const PAGE_ERRORS_HOOK: &str = r#"
+(() => {
+    if (window.__npmPageErrors) return;
+    const errors = window.__npmPageErrors = [];
+    const push = (kind, message, url, status) => {
+        if (errors.length < 100) errors.push({ kind, message: String(message).slice(0, 500), url: url || null, status: status || null });
+    };
+    const consoleError = console.error;
+    console.error = function (...args) {
+        push('console', args.map(String).join(' '));
+        return consoleError.apply(this, args);
+    };
+    window.addEventListener('error', (e) => {
+        const el = e.target;
+        if (el && el !== window && (el.src || el.href)) push('request', 'failed to load <' + el.tagName.toLowerCase() + '>', el.src || el.href);
+        else push('exception', e.message, e.filename);
+    }, true);
+    window.addEventListener('unhandledrejection', (e) => push('exception', 'unhandled rejection: ' + ((e.reason && e.reason.message) || e.reason)));
+    const fetch = window.fetch;
+    if (fetch) window.fetch = function (...args) {
+        const url = String((args[0] && args[0].url) || args[0]);
+        return fetch.apply(this, args).then(
+            (r) => { if (r.status >= 400) push('request', 'HTTP ' + r.status, r.url, r.status); return r; },
+            (e) => { push('request', String(e), url); throw e; });
+    };
+    const send = XMLHttpRequest.prototype.send;
+    XMLHttpRequest.prototype.send = function (...args) {
+        this.addEventListener('loadend', () => {
+            if (this.status >= 400 || this.status === 0) push('request', this.status ? 'HTTP ' + this.status : 'network error', this.responseURL, this.status);
+        });
+        return send.apply(this, args);
+    };
+})();
+"#;
+
+/// Returns what the hook collected, plus page and resource loads that got a 4xx/5xx
+/// according to Resource Timing. Without the hook (Edge, Safari) only the latter are found.
+pub const PAGE_ERRORS_SCRIPT: &str = r#"
+const errors = (window.__npmPageErrors || []).slice();
+const seen = new Set(errors.map((e) => e.url));
+const entries = performance.getEntriesByType('navigation').concat(performance.getEntriesByType('resource'));
+for (const entry of entries) {
+    if (entry.responseStatus >= 400 && !seen.has(entry.name)) {
+        errors.push({ kind: 'request', message: 'HTTP ' + entry.responseStatus, url: entry.name, status: entry.responseStatus });
+        seen.add(entry.name);
+    }
+}
+return errors;
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageErrorKind {
+    /// Logged with `console.error`.
+    Console,
+    /// Uncaught exception or unhandled promise rejection.
+    Exception,
+    /// A request of the page that failed or got a 4xx/5xx.
+    Request,
+}
+
+/// Something that went wrong on a page that otherwise loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PageError {
+    pub kind: PageErrorKind,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+}
+
+impl PageError {
+    /// Reads the array `PAGE_ERRORS_SCRIPT` returns; entries that don't fit are skipped.
+    pub fn from_script(value: &JsonValue) -> Vec<Self> {
+        let entries = value.as_array().map(Vec::as_slice).unwrap_or_default();
+        entries.iter().filter_map(|entry| serde_json::from_value(entry.clone()).ok()).take(MAX_PAGE_ERRORS).collect()
+    }
+
+    /// Counts as metrics for `CheckResult::metrics`: `page_console_errors` (console and
+    /// exceptions) and `page_failed_requests`.
+    pub fn metrics(errors: &[PageError]) -> [(&'static str, f64); 2] {
+        let requests = errors.iter().filter(|e| e.kind == PageErrorKind::Request).count();
+        [("page_console_errors", (errors.len() - requests) as f64), ("page_failed_requests", requests as f64)]
+    }
+}
+
+impl fmt::Display for PageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            PageErrorKind::Console => "console",
+            PageErrorKind::Exception => "exception",
+            PageErrorKind::Request => "request",
+        };
+        write!(f, "{}: {}", kind, self.message)?;
+        match &self.url {
+            Some(url) => write!(f, " ({})", url),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_errors_from_script() {
+        let value = json!([
+            { "kind": "console", "message": "Failed to init widget", "url": null, "status": null },
+            { "kind": "exception", "message": "TypeError: x is undefined", "url": "https://example.com/app.js", "status": null },
+            { "kind": "request", "message": "HTTP 404", "url": "https://example.com/logo.png", "status": 404 },
+            { "kind": "warning", "message": "not ours" }
+        ]);
+        let errors = PageError::from_script(&value);
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[2].status, Some(404));
+        assert_eq!(errors[1].to_string(), "exception: TypeError: x is undefined (https://example.com/app.js)");
+        assert_eq!(PageError::metrics(&errors), [("page_console_errors", 2.0), ("page_failed_requests", 1.0)]);
+        assert!(PageError::from_script(&JsonValue::Null).is_empty());
+    }
+}
@@ -0,0 +1,152 @@
+use arrow::array::{ArrayRef, BooleanArray, Float64Array, StringArray, TimestampMicrosecondArray};
+use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::check_result::CheckResult;
+use super::units::{Unit, UnitPreferences};
+
+/// File written into every partition directory.
+pub const PARTITION_FILE: &str = "results.parquet";
+
+/// What an export wrote.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportSummary {
+    pub rows: usize,
+    pub files: Vec<PathBuf>,
+}
+
+/// Columns of the exported files; metrics are a JSON object per row, which DuckDB and
+/// Spark read with their JSON functions. Columns with a unit say so in their `unit` field
+/// metadata; the units of the metrics are in the file's `metric_units` metadata.
+pub fn schema() -> Arc<Schema> {
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp", DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into())), false),
+        Field::new("target", DataType::Utf8, false),
+        Field::new("agent", DataType::Utf8, true),
+        Field::new("success", DataType::Boolean, false),
+        Field::new("latency_ms", DataType::Float64, true).with_metadata(HashMap::from([("unit".to_string(), Unit::Milliseconds.symbol().to_string())])),
+        Field::new("error", DataType::Utf8, true),
+        Field::new("failure_kind", DataType::Utf8, true),
+        Field::new("correlation_id", DataType::Utf8, false),
+        Field::new("metrics", DataType::Utf8, true),
+    ]))
+}
+
+/// Hive-style partition of a result, `date=2026-10-17/target=10.0.0.1_443`, so query
+/// engines can skip whole days and targets. Target names are reduced to characters that
+/// are safe in paths on every OS.
+pub fn partition(result: &CheckResult) -> PathBuf {
+    let target: String = result
+        .target
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || "-.".contains(c) { c } else { '_' })
+        .collect();
+    PathBuf::from(format!("date={}", result.timestamp.format("%Y-%m-%d"))).join(format!("target={}", target))
+}
+
+/// Writes `results` below `dir`, one Snappy-compressed file per day and target. A
+/// partition that already exists is replaced as a whole, so export whole days. `units`
+/// names the units of metrics whose names don't say.
+pub fn export(results: &[CheckResult], dir: &Path, units: &UnitPreferences) -> Result<ExportSummary, Box<dyn Error>> {
+    let mut partitions: BTreeMap<PathBuf, Vec<&CheckResult>> = BTreeMap::new();
+    for result in results {
+        partitions.entry(partition(result)).or_default().push(result);
+    }
+    let mut summary = ExportSummary::default();
+    for (partition, mut rows) in partitions {
+        rows.sort_by_key(|r| r.timestamp);
+        let batch = record_batch(&rows)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .set_key_value_metadata(Some(vec![KeyValue::new("metric_units".to_string(), metric_units(&rows, units)?)]))
+            .build();
+        let dir = dir.join(partition);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(PARTITION_FILE);
+        let mut writer = ArrowWriter::try_new(File::create(&path)?, batch.schema(), Some(properties))?;
+        writer.write(&batch)?;
+        writer.close()?;
+        summary.rows += rows.len();
+        summary.files.push(path);
+    }
+    Ok(summary)
+}
+
+/// `{"ttfb_ms": "ms", ...}` for the metrics of `rows`.
+fn metric_units(rows: &[&CheckResult], units: &UnitPreferences) -> Result<String, serde_json::Error> {
+    let symbols: BTreeMap<&str, &str> = rows.iter().flat_map(|r| r.metrics.keys()).map(|name| (name.as_str(), units.unit(name).symbol())).collect();
+    serde_json::to_string(&symbols)
+}
+
+fn record_batch(rows: &[&CheckResult]) -> Result<RecordBatch, Box<dyn Error>> {
+    let strings = |value: fn(&CheckResult) -> Option<String>| -> ArrayRef {
+        Arc::new(rows.iter().map(|r| value(r)).collect::<StringArray>())
+    };
+    let metrics = rows
+        .iter()
+        .map(|r| (!r.metrics.is_empty()).then(|| serde_json::to_string(&r.metrics)).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(
+            TimestampMicrosecondArray::from(rows.iter().map(|r| r.timestamp.timestamp_micros()).collect::<Vec<_>>())
+                .with_timezone("UTC"),
+        ),
+        strings(|r| Some(r.target.clone())),
+        strings(|r| r.agent.clone()),
+        Arc::new(rows.iter().map(|r| Some(r.success)).collect::<BooleanArray>()),
+        Arc::new(rows.iter().map(|r| r.latency_ms()).collect::<Float64Array>()),
+        strings(|r| r.error.clone()),
+        strings(|r| r.failure_kind.map(|k| k.to_string())),
+        strings(|r| Some(r.correlation_id.clone())),
+        Arc::new(metrics.into_iter().collect::<StringArray>()),
+    ];
+    Ok(RecordBatch::try_new(schema(), columns)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use std::time::Duration;
+
+    #[test]
+    fn test_export_partitions_by_date_and_target() {
+        let at = |day: u32, mut result: CheckResult| {
+            result.timestamp = Utc.with_ymd_and_hms(2026, 10, day, 12, 0, 0).unwrap();
+            result
+        };
+        let mut with_metrics = CheckResult::success("10.0.0.1:443", Duration::from_millis(12));
+        with_metrics.metrics.insert("status_code".to_string(), 200.0);
+        with_metrics.metrics.insert("ttfb_ms".to_string(), 8.0);
+        let results = vec![
+            at(16, with_metrics),
+            at(16, CheckResult::failure("10.0.0.1:443", "connection refused")),
+            at(17, CheckResult::success("10.0.0.1:443", Duration::from_millis(9))),
+            at(17, CheckResult::success("db:5432", Duration::from_millis(2))),
+        ];
+        assert_eq!(partition(&results[0]), PathBuf::from("date=2026-10-16/target=10.0.0.1_443"));
+
+        let dir = std::env::temp_dir().join(format!("rust_npm_parquet_{}", uuid::Uuid::new_v4()));
+        let summary = export(&results, &dir, &UnitPreferences::default()).unwrap();
+        assert_eq!(summary.rows, 4);
+        assert_eq!(summary.files.len(), 3);
+
+        let reader = SerializedFileReader::new(File::open(&summary.files[0]).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+        assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), schema().fields().len());
+        let metadata = reader.metadata().file_metadata().key_value_metadata().unwrap();
+        let metric_units = metadata.iter().find(|kv| kv.key == "metric_units").and_then(|kv| kv.value.as_deref());
+        assert_eq!(metric_units, Some(r#"{"status_code":"","ttfb_ms":"ms"}"#));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
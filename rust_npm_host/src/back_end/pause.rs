@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::csv_import::parse_interval;
+
+/// Why and until when a target is paused. A reason is required so nobody has to guess
+/// months later whether a paused check is still meant to be off.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pause {
+    pub reason: String,
+    pub since: DateTime<Utc>,
+    /// The target resumes on its own after this; `None` pauses until resumed by hand.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<DateTime<Utc>>,
+    /// Who paused it: a user, or the component that paused it automatically.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub by: Option<String>,
+}
+
+impl Pause {
+    /// A pause starting now; fails on a blank reason.
+    pub fn new(reason: &str) -> Result<Self, String> {
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err("a pause needs a reason".to_string());
+        }
+        Ok(Self {
+            reason: reason.to_string(),
+            since: Utc::now(),
+            until: None,
+            by: None,
+        })
+    }
+
+    pub fn until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn by(mut self, by: &str) -> Self {
+        self.by = Some(by.to_string());
+        self
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_some_and(|until| until <= now)
+    }
+}
+
+impl fmt::Display for Pause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "paused since {}: {}", self.since.format("%Y-%m-%d %H:%M"), self.reason)?;
+        if let Some(by) = &self.by {
+            write!(f, " (by {})", by)?;
+        }
+        match self.until {
+            Some(until) => write!(f, ", until {}", until.format("%Y-%m-%d %H:%M")),
+            None => write!(f, ", no expiry"),
+        }
+    }
+}
+
+/// When a pause given as `2h`, `3d` or an RFC 3339 time ends.
+pub fn parse_expiry(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(value.trim()) {
+        let at = at.with_timezone(&Utc);
+        return match at > now {
+            true => Ok(at),
+            false => Err(format!("{} is in the past", value)),
+        };
+    }
+    let duration = parse_interval(value).map_err(|e| format!("expiry: {}", e))?;
+    Ok(now + chrono::Duration::from_std(duration).map_err(|e| e.to_string())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_reasons_and_expiry() {
+        assert!(Pause::new("  ").is_err());
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        let until = parse_expiry("3d", now).unwrap();
+        assert_eq!(until, Utc.with_ymd_and_hms(2026, 10, 20, 8, 0, 0).unwrap());
+        assert_eq!(parse_expiry("2026-10-18T00:00:00Z", now).unwrap(), Utc.with_ymd_and_hms(2026, 10, 18, 0, 0, 0).unwrap());
+        assert!(parse_expiry("2026-10-01T00:00:00Z", now).is_err());
+
+        let mut pause = Pause::new("switch replacement").unwrap().until(until).by("alice");
+        pause.since = now;
+        assert!(!pause.is_expired(now));
+        assert!(pause.is_expired(until));
+        assert_eq!(
+            pause.to_string(),
+            "paused since 2026-10-17 08:00: switch replacement (by alice), until 2026-10-20 08:00"
+        );
+    }
+}
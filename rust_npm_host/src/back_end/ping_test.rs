@@ -1,11 +1,135 @@
-use std::time::Duration;
-use std::net::{SocketAddr, TcpStream};
-use thirtyfour::WebDriverError; // Added for error type
+use futures::stream::{self, StreamExt};
+use std::time::{Duration, Instant};
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+use thirtyfour::prelude::WebDriverError;
+use tokio::net::{TcpStream, UdpSocket};
 
-use super::browser_emulator::BrowserEmulator; // Import BrowserEmulator
+use super::browser_emulator::{BrowserEmulator, BrowserKind, SessionPool, Transaction}; // Import BrowserEmulator
+use super::check_result::CheckResult;
+use super::failure_kind::FailureKind;
+use super::navigation_timing::NavigationTiming;
+use super::page_errors::PageError;
+use super::selector_check::{self, SelectorValidation};
+use super::result_payload::Artifact;
+use super::waterfall::{HAR_ARTIFACT, Waterfall};
 
-pub fn is_port_open(addr: SocketAddr, timeout: Duration) -> bool {
-    TcpStream::connect_timeout(&addr, timeout).is_ok()
+/// A port that accepted a connection during `scan_ports`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OpenPort {
+    pub port: u16,
+    /// How long the TCP handshake took.
+    pub latency: Duration,
+}
+
+/// Tries a TCP connection to every port in `ports` on `host`, at most `concurrency` at a
+/// time, and returns the ones that accepted within `timeout`, lowest first. Filtered ports
+/// cost the full timeout, so a wide range wants a short one.
+pub async fn scan_ports(host: IpAddr, ports: RangeInclusive<u16>, timeout: Duration, concurrency: usize) -> Vec<OpenPort> {
+    let mut open: Vec<OpenPort> = stream::iter(ports)
+        .map(|port| async move {
+            let start = Instant::now();
+            match tokio::time::timeout(timeout, TcpStream::connect((host, port))).await {
+                Ok(Ok(_)) => Some(OpenPort { port, latency: start.elapsed() }),
+                _ => None,
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|open| async move { open })
+        .collect()
+        .await;
+    open.sort_by_key(|open| open.port);
+    open
+}
+
+/// What to send to a UDP port. UDP has no handshake, so only a request the service
+/// understands gets an answer back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdpProbe {
+    /// A query for the root NS records.
+    Dns,
+    /// An SNTP client request.
+    Ntp,
+    /// An empty datagram: services rarely answer, but a closed port still shows up as
+    /// refused through the ICMP port-unreachable reply.
+    Empty,
+}
+
+/// What came back from a UDP probe.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UdpOutcome {
+    Responded { rtt: Duration },
+    /// ICMP port unreachable: nothing listens.
+    Refused,
+    /// Nothing came back in time: the port is open but ignored the probe, or filtered.
+    NoResponse,
+}
+
+// Fixed query ID; replies are matched on it.
+const DNS_QUERY_ID: u16 = 0x4e50;
+
+impl UdpProbe {
+    /// The probe that fits the well-known service on `port`.
+    pub fn for_port(port: u16) -> Self {
+        match port {
+            53 | 5353 => UdpProbe::Dns,
+            123 => UdpProbe::Ntp,
+            _ => UdpProbe::Empty,
+        }
+    }
+
+    fn request(self) -> Vec<u8> {
+        match self {
+            UdpProbe::Dns => {
+                let mut query = Vec::with_capacity(17);
+                query.extend_from_slice(&DNS_QUERY_ID.to_be_bytes());
+                // Recursion desired, one question, no other records.
+                query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+                // Root name, type NS, class IN.
+                query.extend_from_slice(&[0, 0, 2, 0, 1]);
+                query
+            }
+            UdpProbe::Ntp => {
+                let mut request = vec![0u8; 48];
+                request[0] = 0x23; // LI 0, version 4, mode 3 (client)
+                request
+            }
+            UdpProbe::Empty => Vec::new(),
+        }
+    }
+
+    /// Whether `reply` is a proper answer to our request, not just any datagram.
+    fn accepts(self, reply: &[u8]) -> bool {
+        match self {
+            UdpProbe::Dns => reply.len() >= 12 && reply[..2] == DNS_QUERY_ID.to_be_bytes() && reply[2] & 0x80 != 0,
+            UdpProbe::Ntp => reply.len() >= 48 && reply[0] & 0x07 == 4,
+            UdpProbe::Empty => true,
+        }
+    }
+}
+
+/// Sends `probe` to `addr` and waits up to `timeout` for a matching reply.
+pub async fn probe_udp(addr: SocketAddr, probe: UdpProbe, timeout: Duration) -> std::io::Result<UdpOutcome> {
+    let bind: SocketAddr = if addr.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+    let socket = UdpSocket::bind(bind).await?;
+    // A connected socket reports ICMP port unreachable as ConnectionRefused on receive.
+    socket.connect(addr).await?;
+    let start = Instant::now();
+    socket.send(&probe.request()).await?;
+
+    let mut buf = [0u8; 1500];
+    let deadline = start + timeout;
+    loop {
+        match tokio::time::timeout_at(deadline.into(), socket.recv(&mut buf)).await {
+            Err(_) => return Ok(UdpOutcome::NoResponse),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => return Ok(UdpOutcome::Refused),
+            Ok(Err(e)) => return Err(e),
+            Ok(Ok(len)) if probe.accepts(&buf[..len]) => return Ok(UdpOutcome::Responded { rtt: start.elapsed() }),
+            // Stray or malformed datagram; keep waiting for the real answer.
+            Ok(Ok(_)) => {}
+        }
+    }
 }
 
 /// Measures the time it takes for a website to become "functional" by emulating a browser.
@@ -32,6 +156,10 @@ pub fn is_port_open(addr: SocketAddr, timeout: Duration) -> bool {
 /// * A running Selenium WebDriver server (chromedriver or geckodriver) accessible at `webdriver_url`.
 /// * The browser controlled by WebDriver (e.g., Chrome) must be installed on the system
 ///   where WebDriver is running.
+///
+/// `web-check --browser` uses `measure_website_timing` instead, which also reports where
+/// the time went; this is what the WebDriver integration tests below drive.
+#[cfg_attr(not(test), allow(dead_code))]
 pub async fn measure_website_functional_time(
     webdriver_url: &str,
     target_url: &str,
@@ -66,25 +194,242 @@ pub async fn measure_website_functional_time(
     }
 }
 
+/// What a browser check found on a page that loaded.
+#[derive(Debug, Clone)]
+pub struct PageLoad {
+    /// Until the page was usable.
+    pub duration: Duration,
+    /// Where the time went; `None` if the browser couldn't report it.
+    pub timing: Option<NavigationTiming>,
+    /// Console errors and failed requests during the load.
+    pub errors: Vec<PageError>,
+    /// The page's resources; `None` if the browser couldn't report them.
+    pub waterfall: Option<Waterfall>,
+}
+
+impl PageLoad {
+    /// The check result for `target`, with the timing and error counts as metrics and the
+    /// errors attached. With `fail_on_errors`, a page that loaded but logged errors or had
+    /// requests fail counts as broken: the check fails as a content mismatch.
+    pub fn into_result(self, target: &str, fail_on_errors: bool) -> CheckResult {
+        let mut result = CheckResult::success(target, self.duration);
+        let timing = self.timing.iter().flat_map(NavigationTiming::metrics);
+        for (name, value) in timing.chain(PageError::metrics(&self.errors)) {
+            result.metrics.insert(name.to_string(), value);
+        }
+        if fail_on_errors && let Some(first) = self.errors.first() {
+            result.success = false;
+            result.error = Some(format!("page loaded with {} errors, e.g. {}", self.errors.len(), first));
+            result.failure_kind = Some(FailureKind::ContentMismatch);
+        }
+        result.page_errors = self.errors;
+        if let Some(waterfall) = self.waterfall {
+            let started = result.timestamp - chrono::Duration::from_std(self.duration).unwrap_or_default();
+            match serde_json::to_vec(&waterfall.to_har(started)).map_err(Into::into).and_then(|har| Artifact::new(HAR_ARTIFACT, "application/json", &har)) {
+                Ok(artifact) => result.artifacts.push(artifact),
+                Err(e) => eprintln!("Cannot attach the HAR of {}: {}", target, e),
+            }
+        }
+        result
+    }
+}
+
+/// Like `measure_website_functional_time`, but also returns where the time of the page
+/// load went (DNS, connect, TTFB, DOMContentLoaded, load event) and the errors the page
+/// ran into while loading, so a page that loads but is broken can be told apart.
+pub async fn measure_website_timing(
+    webdriver_url: &str,
+    browser: BrowserKind,
+    target_url: &str,
+    functional_criteria_selector: Option<&str>,
+    headless: bool,
+) -> Result<PageLoad, Box<dyn std::error::Error>> {
+    let emulator = BrowserEmulator::with_browser(webdriver_url, browser, headless).await?;
+    let page = load_page(&emulator, target_url, functional_criteria_selector).await;
+    if let Err(e) = emulator.close().await {
+        eprintln!("Error closing browser: {:?}", e);
+    }
+    Ok(page?)
+}
+
+/// Like `measure_website_timing`, but borrows a warm session from `pool` instead of
+/// starting a browser, which makes checks of many sites on a schedule far cheaper. A
+/// session that failed the load is quit rather than lent out again.
+pub async fn measure_website_timing_pooled(
+    pool: &Arc<SessionPool>,
+    target_url: &str,
+    functional_criteria_selector: Option<&str>,
+) -> Result<PageLoad, Box<dyn std::error::Error>> {
+    let session = pool.checkout().await?;
+    match load_page(&session, target_url, functional_criteria_selector).await {
+        Ok(page) => Ok(page),
+        Err(e) => {
+            session.discard();
+            Err(e.into())
+        }
+    }
+}
+
+async fn load_page(emulator: &BrowserEmulator, target_url: &str, functional_criteria_selector: Option<&str>) -> Result<PageLoad, WebDriverError> {
+    let duration = emulator.measure_load_time(target_url, functional_criteria_selector).await?;
+    let timing = emulator.navigation_timing().await.unwrap_or_else(|e| {
+        eprintln!("Cannot read navigation timing: {:?}", e);
+        None
+    });
+    let errors = emulator.page_errors().await.unwrap_or_else(|e| {
+        eprintln!("Cannot read page errors: {:?}", e);
+        Vec::new()
+    });
+    let waterfall = emulator.resource_timings(target_url, Some(duration)).await.map_or_else(
+        |e| {
+            eprintln!("Cannot read resource timings: {:?}", e);
+            None
+        },
+        Some,
+    );
+    Ok(PageLoad {
+        duration,
+        timing,
+        errors,
+        waterfall,
+    })
+}
+
+/// Runs a scripted browser transaction (login flow, checkout path) in a new session. A
+/// WebDriver that can't be reached fails the check as an infrastructure error.
+pub async fn run_browser_transaction(
+    webdriver_url: &str,
+    browser: BrowserKind,
+    transaction: &Transaction,
+    headless: bool,
+) -> CheckResult {
+    let emulator = match BrowserEmulator::with_browser(webdriver_url, browser, headless).await {
+        Ok(emulator) => emulator,
+        Err(e) => {
+            return CheckResult::failure(&transaction.name, format!("cannot start a browser: {}", e)).with_failure_kind(FailureKind::InfraError);
+        }
+    };
+    let result = emulator.run_transaction(transaction).await;
+    if let Err(e) = emulator.close().await {
+        eprintln!("Error closing browser: {:?}", e);
+    }
+    result
+}
+
+/// Like `measure_website_functional_time`, but also returns the page's resource timing
+/// waterfall so slow third-party resources can be spotted. When the page never became
+/// functional the waterfall has no functional mark.
+pub async fn measure_website_waterfall(
+    webdriver_url: &str,
+    browser: BrowserKind,
+    target_url: &str,
+    functional_criteria_selector: Option<&str>,
+    headless: bool,
+) -> Result<Waterfall, Box<dyn std::error::Error>> {
+    let emulator = BrowserEmulator::with_browser(webdriver_url, browser, headless).await?;
+    let load_time = emulator
+        .measure_load_time(target_url, functional_criteria_selector)
+        .await;
+    // Resources loaded so far are still worth showing when the selector never appeared.
+    let waterfall = emulator.resource_timings(target_url, load_time.as_ref().ok().copied()).await;
+
+    if let Err(e) = emulator.close().await {
+        eprintln!("Error closing browser: {:?}", e);
+    }
+    Ok(waterfall?)
+}
+
+/// One-off check of a functional selector for `target_url`, meant to run when a web target
+/// is added or edited so a typo shows up right away instead of as a `NoSuchElement`
+/// failure on the next scheduled run. Obviously malformed selectors are rejected without
+/// starting a browser.
+pub async fn validate_functional_selector(
+    webdriver_url: &str,
+    browser: BrowserKind,
+    target_url: &str,
+    selector: &str,
+    headless: bool,
+) -> Result<SelectorValidation, Box<dyn std::error::Error>> {
+    if let Err(e) = selector_check::precheck(selector) {
+        return Ok(SelectorValidation::Invalid(e));
+    }
+    let emulator = BrowserEmulator::with_browser(webdriver_url, browser, headless).await?;
+    let validation = emulator.check_selector(target_url, selector).await;
+    if let Err(e) = emulator.close().await {
+        eprintln!("Error closing browser: {:?}", e);
+    }
+    Ok(validation?)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    // Basic test for is_port_open - requires a listening port (e.g., a simple netcat listener)
-    // `nc -l 8080`
     #[test]
-    #[ignore] // Requires a local listener
-    fn test_is_port_open_true() {
-        use std::net::{IpAddr, Ipv4Addr};
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080);
-        assert!(is_port_open(addr, Duration::from_secs(1)));
+    fn test_page_errors_make_a_broken_page_fail() {
+        let load = PageLoad {
+            duration: Duration::from_millis(850),
+            timing: None,
+            waterfall: Some(Waterfall::default()),
+            errors: PageError::from_script(&serde_json::json!([
+                { "kind": "request", "message": "HTTP 500", "url": "https://example.com/api/cart", "status": 500 }
+            ])),
+        };
+        let lenient = load.clone().into_result("https://example.com", false);
+        assert!(lenient.success);
+        assert_eq!(lenient.metrics["page_failed_requests"], 1.0);
+        let strict = load.into_result("https://example.com", true);
+        assert!(!strict.success && strict.failure_kind == Some(FailureKind::ContentMismatch));
+        assert_eq!(strict.page_errors.len(), 1);
+        assert_eq!(strict.artifacts[0].name, HAR_ARTIFACT);
+        assert_eq!(strict.latency, Some(Duration::from_millis(850)));
     }
 
-    #[test]
-    fn test_is_port_open_false() {
-        use std::net::{IpAddr, Ipv4Addr};
-        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 12345); // Assume port 12345 is not open
-        assert!(!is_port_open(addr, Duration::from_secs(1)));
+    #[tokio::test]
+    async fn test_udp_probe_matches_replies() {
+        let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = server.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut buf = [0u8; 512];
+            // Answer the DNS query with its header, QR bit set.
+            let (len, peer) = server.recv_from(&mut buf).await.unwrap();
+            assert_eq!(len, 17);
+            buf[2] |= 0x80;
+            server.send_to(&buf[..len], peer).await.unwrap();
+        });
+        let outcome = probe_udp(addr, UdpProbe::Dns, Duration::from_secs(1)).await.unwrap();
+        assert!(matches!(outcome, UdpOutcome::Responded { .. }));
+
+        // Nothing bound there any more: Linux reports the ICMP port unreachable.
+        let closed = UdpSocket::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        let outcome = probe_udp(closed, UdpProbe::Ntp, Duration::from_millis(500)).await.unwrap();
+        assert!(matches!(outcome, UdpOutcome::Refused | UdpOutcome::NoResponse));
+
+        assert_eq!(UdpProbe::for_port(53), UdpProbe::Dns);
+        assert!(UdpProbe::Ntp.accepts(&[0x24; 48]));
+        assert!(!UdpProbe::Ntp.accepts(&[0x23; 48]));
+    }
+
+    #[tokio::test]
+    async fn test_scan_finds_listening_ports() {
+        let listeners = [
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap(),
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap(),
+        ];
+        let mut ports: Vec<u16> = listeners.iter().map(|l| l.local_addr().unwrap().port()).collect();
+        ports.sort();
+        let open = scan_ports([127, 0, 0, 1].into(), ports[0]..=ports[1], Duration::from_millis(500), 16).await;
+        let found: Vec<u16> = open.iter().map(|open| open.port).collect();
+        assert_eq!(found.first(), Some(&ports[0]));
+        assert_eq!(found.last(), Some(&ports[1]));
+        assert!(found.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[tokio::test]
+    async fn test_scan_skips_closed_ports() {
+        // Nothing listens there once the listener is dropped.
+        let port = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        assert!(scan_ports([127, 0, 0, 1].into(), port..=port, Duration::from_secs(1), 1).await.is_empty());
     }
 
     // Integration test for measure_website_functional_time
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::monitor::Monitor;
+
+// How often `watch` looks at the power source.
+const POWER_POLL: Duration = Duration::from_secs(30);
+
+/// What the machine is running on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    /// No battery information, e.g. on Windows or in a VM.
+    Unknown,
+}
+
+/// How the monitor saves power while running on battery.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LowPower {
+    /// Intervals are multiplied by this...
+    pub interval_factor: u32,
+    /// ...and are at least this long.
+    pub min_interval: Duration,
+    /// Targets due within this much of each other are checked together, so the network
+    /// adapter wakes up once per batch instead of once per target.
+    pub batch_window: Duration,
+}
+
+impl Default for LowPower {
+    fn default() -> Self {
+        Self {
+            interval_factor: 4,
+            min_interval: Duration::from_secs(5 * 60),
+            batch_window: Duration::from_secs(30),
+        }
+    }
+}
+
+impl LowPower {
+    /// The interval a target with `interval` is checked at in low-power mode.
+    pub fn interval(&self, interval: Duration) -> Duration {
+        (interval * self.interval_factor).max(self.min_interval)
+    }
+}
+
+/// When low-power mode is on: `--low-power auto` follows the power source, `on` forces it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerMode {
+    Auto,
+    On,
+}
+
+impl FromStr for PowerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "auto" => Ok(PowerMode::Auto),
+            "on" => Ok(PowerMode::On),
+            _ => Err(format!("unknown power mode '{}', expected auto or on", s)),
+        }
+    }
+}
+
+impl fmt::Display for PowerMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PowerMode::Auto => "auto",
+            PowerMode::On => "on",
+        })
+    }
+}
+
+/// The current power source, from sysfs on Linux and `pmset` on macOS.
+pub fn detect() -> PowerSource {
+    if cfg!(target_os = "macos") {
+        return std::process::Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .map_or(PowerSource::Unknown, |output| parse_pmset(&String::from_utf8_lossy(&output.stdout)));
+    }
+    detect_sysfs(Path::new("/sys/class/power_supply"))
+}
+
+/// Reads a directory laid out like Linux's `/sys/class/power_supply`. Any online mains or
+/// USB supply means AC; otherwise a discharging battery means battery.
+fn detect_sysfs(dir: &Path) -> PowerSource {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return PowerSource::Unknown;
+    };
+    let read = |path: &Path, name: &str| fs::read_to_string(path.join(name)).map(|s| s.trim().to_string()).unwrap_or_default();
+    let mut source = PowerSource::Unknown;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        match read(&path, "type").as_str() {
+            "Mains" | "USB" if read(&path, "online") == "1" => return PowerSource::Ac,
+            "Battery" if read(&path, "status") == "Discharging" => source = PowerSource::Battery,
+            _ => {}
+        }
+    }
+    source
+}
+
+// `pmset -g batt` starts with e.g. `Now drawing from 'Battery Power'`.
+fn parse_pmset(output: &str) -> PowerSource {
+    if output.contains("'Battery Power'") {
+        PowerSource::Battery
+    } else if output.contains("'AC Power'") {
+        PowerSource::Ac
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+/// Puts `monitor` into low-power mode with `policy` while on battery and out of it on AC,
+/// until the monitor stops. An unknown source leaves the mode as it is.
+pub async fn watch(monitor: Arc<Monitor>, policy: LowPower) {
+    let mut poll = tokio::time::interval(POWER_POLL);
+    while !monitor.is_stopping() {
+        poll.tick().await;
+        match tokio::task::spawn_blocking(detect).await.unwrap_or(PowerSource::Unknown) {
+            PowerSource::Battery => monitor.set_low_power(Some(policy)),
+            PowerSource::Ac => monitor.set_low_power(None),
+            PowerSource::Unknown => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_source_is_read_from_sysfs() {
+        let dir = std::env::temp_dir().join(format!("rust_npm_power_{}", uuid::Uuid::new_v4().simple()));
+        let supply = |name: &str, files: &[(&str, &str)]| {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            for (file, value) in files {
+                fs::write(dir.join(name).join(file), format!("{}\n", value)).unwrap();
+            }
+        };
+        assert_eq!(detect_sysfs(&dir), PowerSource::Unknown);
+        supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+        supply("AC", &[("type", "Mains"), ("online", "0")]);
+        assert_eq!(detect_sysfs(&dir), PowerSource::Battery);
+        supply("AC", &[("online", "1")]);
+        assert_eq!(detect_sysfs(&dir), PowerSource::Ac);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(parse_pmset("Now drawing from 'Battery Power'\n -InternalBattery-0"), PowerSource::Battery);
+        let policy = LowPower::default();
+        assert_eq!(policy.interval(Duration::from_secs(30)), Duration::from_secs(300));
+        assert_eq!(policy.interval(Duration::from_secs(600)), Duration::from_secs(2400));
+    }
+}
@@ -0,0 +1,127 @@
+use std::net::{IpAddr, SocketAddr};
+
+use super::monitor::CheckKind;
+use super::target::MonitorTarget;
+
+/// Metadata key naming the preset a target was created from.
+pub const PRESET_METADATA_KEY: &str = "preset";
+
+/// One probe of a preset. `port` is ignored for ICMP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PresetCheck {
+    pub port: u16,
+    pub check: CheckKind,
+    /// What the probe covers, e.g. `imaps`.
+    pub service: &'static str,
+}
+
+/// A named set of probes for a common kind of server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub checks: &'static [PresetCheck],
+}
+
+const fn tcp(port: u16, service: &'static str) -> PresetCheck {
+    PresetCheck { port, check: CheckKind::Tcp, service }
+}
+
+const fn udp(port: u16, service: &'static str) -> PresetCheck {
+    PresetCheck { port, check: CheckKind::Udp, service }
+}
+
+const PING: PresetCheck = PresetCheck {
+    port: 0,
+    check: CheckKind::Icmp,
+    service: "ping",
+};
+
+/// The built-in presets, listed by `preset list`.
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "web",
+        description: "web server: HTTP and HTTPS",
+        checks: &[tcp(80, "http"), tcp(443, "https")],
+    },
+    Preset {
+        name: "mail",
+        description: "mail server: SMTP, SMTPS, submission and IMAPS",
+        checks: &[tcp(25, "smtp"), tcp(465, "submissions"), tcp(587, "submission"), tcp(993, "imaps")],
+    },
+    Preset {
+        name: "dns",
+        description: "DNS server: ping and a query over UDP",
+        checks: &[PING, udp(53, "domain")],
+    },
+    Preset {
+        name: "ntp",
+        description: "time server: an SNTP request",
+        checks: &[udp(123, "ntp")],
+    },
+    Preset {
+        name: "linux",
+        description: "Linux host: ping and SSH",
+        checks: &[PING, tcp(22, "ssh")],
+    },
+    Preset {
+        name: "windows",
+        description: "Windows host: ping, SMB and remote desktop",
+        checks: &[PING, tcp(445, "microsoft-ds"), tcp(3389, "ms-wbt-server")],
+    },
+    Preset {
+        name: "postgres",
+        description: "PostgreSQL server",
+        checks: &[tcp(5432, "postgresql")],
+    },
+    Preset {
+        name: "mysql",
+        description: "MySQL or MariaDB server",
+        checks: &[tcp(3306, "mysql")],
+    },
+];
+
+/// The preset called `name`, ignoring case.
+pub fn find(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name.eq_ignore_ascii_case(name.trim()))
+}
+
+impl Preset {
+    /// One target per probe of the preset on `host`, tagged with the preset's name.
+    pub fn expand(&self, host: IpAddr) -> Vec<MonitorTarget> {
+        self.checks
+            .iter()
+            .map(|probe| {
+                MonitorTarget::new(SocketAddr::new(host, probe.port))
+                    .with_check(probe.check)
+                    .with_metadata(PRESET_METADATA_KEY, self.name)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_expand_into_one_target_per_probe() {
+        let host: IpAddr = "10.0.0.7".parse().unwrap();
+        let targets = find("Mail").unwrap().expand(host);
+        let ports: Vec<u16> = targets.iter().map(|t| t.address.port()).collect();
+        assert_eq!(ports, [25, 465, 587, 993]);
+        assert!(targets.iter().all(|t| t.check == CheckKind::Tcp && t.metadata[PRESET_METADATA_KEY] == "mail"));
+
+        let dns = find("dns").unwrap().expand(host);
+        assert_eq!((dns[1].address.port(), dns[1].check), (53, CheckKind::Udp));
+        assert!(find("mainframe").is_none());
+
+        // Targets are keyed by address, so no preset may use a port twice, even over UDP and TCP.
+        for preset in PRESETS {
+            let targets = preset.expand(host);
+            for (i, target) in targets.iter().enumerate() {
+                assert!(!targets[..i].iter().any(|t| t.address == target.address), "{} repeats {}", preset.name, target.address);
+            }
+        }
+    }
+}
@@ -0,0 +1,303 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::Client;
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use super::check_result::CheckResult;
+use super::failure_kind::FailureKind;
+use super::severity::Severity;
+use super::target::{CheckSpec, MonitorTarget};
+
+// Reset values larger than this are treated as epoch seconds rather than "seconds from now".
+const EPOCH_THRESHOLD_SECS: i64 = 1_000_000_000;
+
+/// Rate-limit information advertised by an API in its response headers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitInfo {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset_at: Option<DateTime<Utc>>,
+    /// Set when the API told us to back off (`Retry-After`).
+    pub retry_after: Option<DateTime<Utc>>,
+}
+
+/// Reads the common `X-RateLimit-*`, IETF `RateLimit-*` and `Retry-After` headers.
+pub fn parse_headers(headers: &HeaderMap, now: DateTime<Utc>) -> RateLimitInfo {
+    let header = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()).map(str::trim))
+    };
+    // Some APIs send a list like "100, 100;w=60"; the first number is what counts.
+    let number = |value: &str| value.split([',', ';']).next().and_then(|v| v.trim().parse::<u64>().ok());
+
+    let reset_at = header(&["x-ratelimit-reset", "ratelimit-reset", "x-rate-limit-reset"])
+        .and_then(|v| v.parse::<i64>().ok())
+        .map(|secs| {
+            if secs > EPOCH_THRESHOLD_SECS {
+                DateTime::from_timestamp(secs, 0).unwrap_or(now)
+            } else {
+                now + ChronoDuration::seconds(secs)
+            }
+        });
+
+    let retry_after = header(&["retry-after"]).and_then(|v| match v.parse::<i64>() {
+        Ok(secs) => Some(now + ChronoDuration::seconds(secs)),
+        Err(_) => DateTime::parse_from_rfc2822(v).ok().map(|d| d.with_timezone(&Utc)),
+    });
+
+    RateLimitInfo {
+        limit: header(&["x-ratelimit-limit", "ratelimit-limit", "x-rate-limit-limit"]).and_then(number),
+        remaining: header(&["x-ratelimit-remaining", "ratelimit-remaining", "x-rate-limit-remaining"]).and_then(number),
+        reset_at,
+        retry_after,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QuotaAlert {
+    pub target: String,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for QuotaAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.severity, self.target, self.message)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Sample {
+    at: DateTime<Utc>,
+    remaining: u64,
+}
+
+/// Follows the remaining quota of each API over time and warns before it runs out.
+pub struct QuotaTracker {
+    /// Warn when less than this fraction of the limit is left (0.1 = 10%).
+    pub low_watermark: f64,
+    samples: HashMap<String, Vec<Sample>>,
+}
+
+impl Default for QuotaTracker {
+    fn default() -> Self {
+        Self {
+            low_watermark: 0.1,
+            samples: HashMap::new(),
+        }
+    }
+}
+
+impl QuotaTracker {
+    /// Records one observation and returns an alert if the quota is, or is about to be, exhausted.
+    pub fn observe(&mut self, target: &str, info: &RateLimitInfo, now: DateTime<Utc>) -> Option<QuotaAlert> {
+        let alert = |severity, message: String| {
+            Some(QuotaAlert {
+                target: target.to_string(),
+                severity,
+                message,
+            })
+        };
+
+        if let Some(retry_after) = info.retry_after.filter(|r| *r > now) {
+            return alert(
+                Severity::Critical,
+                format!("API is throttling us, retry after {}", retry_after.to_rfc3339()),
+            );
+        }
+
+        let remaining = info.remaining?;
+        let samples = self.samples.entry(target.to_string()).or_default();
+        // Remaining going up means the window was reset; older samples no longer describe the rate.
+        if samples.last().is_some_and(|last| remaining > last.remaining) {
+            samples.clear();
+        }
+        samples.push(Sample { at: now, remaining });
+        if samples.len() > 100 {
+            samples.remove(0);
+        }
+
+        if let Some(limit) = info.limit.filter(|l| *l > 0) {
+            let fraction = remaining as f64 / limit as f64;
+            if fraction <= self.low_watermark {
+                return alert(
+                    Severity::Warning,
+                    format!("only {} of {} requests left ({:.0}%)", remaining, limit, fraction * 100.0),
+                );
+            }
+        }
+
+        // Project when the quota runs out at the current consumption rate.
+        let first = samples.first()?;
+        let elapsed = (now - first.at).num_milliseconds() as f64 / 1000.0;
+        let used = first.remaining.saturating_sub(remaining) as f64;
+        if elapsed <= 0.0 || used <= 0.0 {
+            return None;
+        }
+        let per_second = used / elapsed;
+        let exhausted_at = now + ChronoDuration::milliseconds((remaining as f64 / per_second * 1000.0) as i64);
+        match info.reset_at {
+            Some(reset_at) if exhausted_at < reset_at => alert(
+                Severity::Low,
+                format!(
+                    "at {:.2} req/s the quota runs out at {}, before the reset at {}",
+                    per_second,
+                    exhausted_at.to_rfc3339(),
+                    reset_at.to_rfc3339()
+                ),
+            ),
+            _ => None,
+        }
+    }
+}
+
+/// Requests `url`, with `token` as bearer token, and feeds its rate-limit headers into
+/// the tracker. The remaining requests are kept as the `quota_remaining` metric.
+pub async fn check_api_quota(
+    client: &Client,
+    target: &str,
+    url: &str,
+    token: Option<&str>,
+    tracker: &Mutex<QuotaTracker>,
+) -> (CheckResult, Option<QuotaAlert>) {
+    let start = Instant::now();
+    let request = match token {
+        Some(token) => client.get(url).bearer_auth(token),
+        None => client.get(url),
+    };
+    match request.send().await {
+        Ok(response) => {
+            let latency = start.elapsed();
+            let info = parse_headers(response.headers(), Utc::now());
+            let status = response.status();
+            let mut result = if status.is_success() {
+                CheckResult::success(target, latency)
+            } else {
+                CheckResult {
+                    latency: Some(latency),
+                    ..CheckResult::failure(target, format!("HTTP {}", status))
+                }
+            };
+            if let Some(remaining) = info.remaining {
+                result.metrics.insert("quota_remaining".to_string(), remaining as f64);
+            }
+            let alert = tracker.lock().unwrap().observe(target, &info, Utc::now());
+            (result, alert)
+        }
+        Err(e) => (CheckResult::failure(target, e.to_string()), None),
+    }
+}
+
+/// Runs the quota check of a `CheckSpec::ApiQuota` target. Throttling fails the check;
+/// a quota running low gives the result a status and severity for its alert instead.
+pub async fn check_target(client: &Client, target: &MonitorTarget, tracker: &Mutex<QuotaTracker>) -> CheckResult {
+    let name = target.address.to_string();
+    let Some(CheckSpec::ApiQuota { url, token_env }) = target.spec() else {
+        return CheckResult::failure(&name, "API quota check without a URL");
+    };
+    let token = match token_env.as_deref().map(std::env::var).transpose() {
+        Ok(token) => token,
+        Err(_) => {
+            return CheckResult::failure(&name, format!("{} is not set", token_env.as_deref().unwrap_or_default()))
+                .with_failure_kind(FailureKind::InfraError);
+        }
+    };
+    let (result, alert) = check_api_quota(client, &name, url, token.as_deref(), tracker).await;
+    match alert {
+        Some(alert) if alert.severity == Severity::Critical => CheckResult {
+            latency: result.latency,
+            metrics: result.metrics,
+            ..CheckResult::failure(&name, alert.message)
+        },
+        Some(alert) => CheckResult {
+            status: Some(format!("quota: {}", alert.message)),
+            severity: Some(alert.severity),
+            ..result
+        },
+        None => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::header::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_parse_github_style_headers() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let info = parse_headers(
+            &headers(&[
+                ("x-ratelimit-limit", "5000"),
+                ("x-ratelimit-remaining", "4321"),
+                ("x-ratelimit-reset", "1792238400"),
+            ]),
+            now,
+        );
+        assert_eq!(info.limit, Some(5000));
+        assert_eq!(info.remaining, Some(4321));
+        assert_eq!(info.reset_at.unwrap().timestamp(), 1792238400);
+    }
+
+    #[test]
+    fn test_parse_retry_after_and_relative_reset() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let info = parse_headers(&headers(&[("retry-after", "120"), ("ratelimit-reset", "30")]), now);
+        assert_eq!(info.retry_after, Some(now + ChronoDuration::seconds(120)));
+        assert_eq!(info.reset_at, Some(now + ChronoDuration::seconds(30)));
+    }
+
+    #[test]
+    fn test_tracker_projects_exhaustion_before_reset() {
+        let now = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let reset_at = now + ChronoDuration::hours(1);
+        let mut tracker = QuotaTracker::default();
+        let info = |remaining| RateLimitInfo {
+            limit: Some(1000),
+            remaining: Some(remaining),
+            reset_at: Some(reset_at),
+            retry_after: None,
+        };
+        assert!(tracker.observe("api", &info(900), now).is_none());
+        // 400 requests in 10 minutes: the remaining 500 last ~12.5 minutes, well before the reset.
+        let alert = tracker.observe("api", &info(500), now + ChronoDuration::minutes(10)).unwrap();
+        assert_eq!(alert.severity, Severity::Low);
+
+        let alert = tracker.observe("api", &info(50), now + ChronoDuration::minutes(20)).unwrap();
+        assert_eq!(alert.severity, Severity::Warning);
+    }
+
+    #[tokio::test]
+    async fn test_low_quota_marks_the_result() {
+        let app = axum::Router::new().route(
+            "/rate_limit",
+            axum::routing::get(|| async { ([("x-ratelimit-limit", "1000"), ("x-ratelimit-remaining", "50")], "{}") }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let spec = CheckSpec::ApiQuota {
+            url: format!("http://{}/rate_limit", addr),
+            token_env: None,
+        };
+        let target = MonitorTarget::new(addr).with_spec(spec);
+        let result = check_target(&Client::new(), &target, &Mutex::new(QuotaTracker::default())).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.severity, Some(Severity::Warning));
+        assert_eq!(result.status.as_deref(), Some("quota: only 50 of 1000 requests left (5%)"));
+        assert_eq!(result.metrics["quota_remaining"], 50.0);
+    }
+}
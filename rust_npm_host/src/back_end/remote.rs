@@ -0,0 +1,227 @@
+use chrono::{DateTime, Duration as ChronoDuration, SecondsFormat, Utc};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::auth::Scope;
+use super::check_result::CheckResult;
+use super::event_bus::MonitorEvent;
+use super::monitor::Monitor;
+use super::target::MonitorTarget;
+
+// How far back results are fetched on connecting, so charts don't start out empty.
+const BACKFILL_MINUTES: i64 = 60;
+
+/// The API of another instance (see `api_server`), for a GUI that shows and manages that
+/// monitor instead of checking targets itself.
+pub struct RemoteApi {
+    base: String,
+    token: Option<String>,
+    client: Client,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhoAmI {
+    scope: Scope,
+}
+
+/// A target as `GET /targets` lists it.
+#[derive(Debug, Deserialize)]
+struct RemoteTarget {
+    #[serde(flatten)]
+    target: MonitorTarget,
+    group: Option<String>,
+}
+
+impl RemoteApi {
+    /// `base` is the API's URL, e.g. `https://monitor.example:8080`; `token` is sent as
+    /// bearer token with every request.
+    pub fn new(base: &str, token: Option<String>, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            base: base.trim_end_matches('/').to_string(),
+            token,
+            client: Client::builder().timeout(timeout).build()?,
+        })
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self.client.request(method, format!("{}{}", self.base, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// What the token may do on the remote monitor; an error if it isn't accepted at all.
+    pub async fn scope(&self) -> Result<Scope, Box<dyn Error>> {
+        let response = self.request(Method::GET, "/whoami").send().await?.error_for_status()?;
+        Ok(response.json::<WhoAmI>().await?.scope)
+    }
+
+    async fn targets(&self) -> Result<Vec<RemoteTarget>, Box<dyn Error>> {
+        Ok(self.request(Method::GET, "/targets").send().await?.error_for_status()?.json().await?)
+    }
+
+    async fn history(&self, addr: SocketAddr, since: DateTime<Utc>) -> Result<Vec<CheckResult>, Box<dyn Error>> {
+        let since = since.to_rfc3339_opts(SecondsFormat::Micros, true);
+        let response = self
+            .request(Method::GET, &format!("/targets/{}/history", addr))
+            .query(&[("since", since)])
+            .send()
+            .await?;
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    async fn add(&self, target: &MonitorTarget, group: Option<String>) -> Result<(), Box<dyn Error>> {
+        let mut body = serde_json::to_value(target)?;
+        body["group"] = serde_json::json!(group);
+        self.request(Method::POST, "/targets").json(&body).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    async fn remove(&self, addr: SocketAddr) -> Result<(), Box<dyn Error>> {
+        self.request(Method::DELETE, &format!("/targets/{}", addr)).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    // Brings `monitor` in line with the remote target list and feeds it the results
+    // checked since `seen`. `known` holds the targets the remote monitor has.
+    async fn sync(
+        &self,
+        monitor: &Monitor,
+        known: &mut HashSet<SocketAddr>,
+        seen: &mut HashMap<SocketAddr, DateTime<Utc>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let targets = self.targets().await?;
+        let listed: HashSet<SocketAddr> = targets.iter().map(|t| t.target.address).collect();
+        for gone in known.difference(&listed).copied().collect::<Vec<_>>() {
+            known.remove(&gone);
+            seen.remove(&gone);
+            let _ = monitor.remove_target(gone);
+        }
+        for RemoteTarget { target, group } in targets {
+            let addr = target.address;
+            if known.insert(addr) && !monitor.targets().contains(&addr) {
+                monitor.add_monitor_target(target)?;
+                monitor.set_group(addr, group)?;
+            }
+        }
+        let backfill = Utc::now() - ChronoDuration::minutes(BACKFILL_MINUTES);
+        for addr in known.iter().copied().collect::<Vec<_>>() {
+            let since = seen.get(&addr).copied().unwrap_or(backfill);
+            let results = self.history(addr, since).await?;
+            for result in results.iter().filter(|r| r.timestamp > since) {
+                monitor.accept(result)?;
+                seen.insert(addr, result.timestamp);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keeps `monitor` a copy of the remote monitor: targets and results are fetched every
+/// `every`, and targets added or removed here are added or removed there. Other changes,
+/// such as groups and pauses, stay local. `monitor` should be on standby, so it doesn't
+/// check the targets itself. Runs until the monitor's event bus closes.
+pub async fn mirror(remote: RemoteApi, monitor: Arc<Monitor>, every: Duration) {
+    let mut events = monitor.bus().subscribe();
+    let mut ticks = tokio::time::interval(every);
+    let mut known = HashSet::new();
+    let mut seen = HashMap::new();
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                if let Err(e) = remote.sync(&monitor, &mut known, &mut seen).await {
+                    eprintln!("Cannot sync with {}: {}", remote.base, e);
+                }
+            }
+            // The sync's own additions are known by the time their events arrive, and its
+            // removals are no longer.
+            event = events.recv() => match event {
+                Ok(MonitorEvent::TargetAdded(addr)) if !known.contains(&addr) => {
+                    let target = monitor.monitor_target(addr).unwrap_or_else(|| MonitorTarget::new(addr));
+                    match remote.add(&target, monitor.group(addr)).await {
+                        Ok(()) => {
+                            known.insert(addr);
+                        }
+                        Err(e) => eprintln!("Cannot add {} to {}: {}", addr, remote.base, e),
+                    }
+                }
+                Ok(MonitorEvent::TargetRemoved(addr)) if known.remove(&addr) => {
+                    seen.remove(&addr);
+                    if let Err(e) = remote.remove(addr).await {
+                        eprintln!("Cannot remove {} from {}: {}", addr, remote.base, e);
+                    }
+                }
+                Ok(_) | Err(RecvError::Lagged(_)) => {}
+                Err(RecvError::Closed) => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::api_access::{ApiConfig, TokenConfig};
+    use crate::back_end::api_server::{self, ApiState};
+    use crate::back_end::event_bus::EventBus;
+    use crate::back_end::history::History;
+    use sha2::{Digest, Sha256};
+
+    #[tokio::test]
+    async fn test_mirror_follows_the_remote_monitor() {
+        let config = ApiConfig {
+            tokens: vec![TokenConfig {
+                user: "wallboard".to_string(),
+                sha256: hex::encode(Sha256::digest(b"view")),
+                scope: Scope::Read,
+            }],
+            ..ApiConfig::default()
+        };
+        let remote_monitor = Arc::new(Monitor::new(EventBus::new(), Duration::from_secs(1)));
+        let db: SocketAddr = "10.0.0.5:5432".parse().unwrap();
+        remote_monitor.add_monitor_target(MonitorTarget::new(db)).unwrap();
+        let history = Arc::new(History::default());
+        history.push(CheckResult::success(&db.to_string(), Duration::from_millis(3)));
+        let state = Arc::new(ApiState::new(remote_monitor.clone(), history, config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, api_server::router(state).into_make_service_with_connect_info::<SocketAddr>()).await
+        });
+
+        let viewer = RemoteApi::new(&base, Some("view".to_string()), Duration::from_secs(5)).unwrap();
+        assert_eq!(viewer.scope().await.unwrap(), Scope::Read);
+        let remote = RemoteApi::new(&base, None, Duration::from_secs(5)).unwrap();
+        assert_eq!(remote.scope().await.unwrap(), Scope::Admin);
+
+        let local = Arc::new(Monitor::new(EventBus::new(), Duration::from_secs(1)));
+        local.set_standby(true);
+        let mut events = local.bus().subscribe();
+        tokio::spawn(mirror(remote, local.clone(), Duration::from_millis(50)));
+        loop {
+            if let Ok(MonitorEvent::CheckCompleted(result)) = events.recv().await {
+                assert_eq!(result.target, db.to_string());
+                break;
+            }
+        }
+        assert_eq!(local.targets(), [db]);
+
+        let web: SocketAddr = "10.0.0.6:443".parse().unwrap();
+        local.add_monitor_target(MonitorTarget::new(web)).unwrap();
+        local.remove_target(db).unwrap();
+        for _ in 0..100 {
+            if remote_monitor.targets() == [web] {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(remote_monitor.targets(), [web]);
+    }
+}
@@ -0,0 +1,205 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::net::IpAddr;
+
+use super::check_result::{CheckResult, StepResult};
+use super::failure_kind::FailureKind;
+use super::metadata::Metadata;
+
+/// Version written into every payload. Bump it when the layout changes incompatibly and
+/// teach `StoredPayload::from_json` to read the old one.
+pub const PAYLOAD_VERSION: u32 = 1;
+
+// Artifacts larger than this are gzipped; small ones stay readable in the database.
+const COMPRESS_ABOVE_BYTES: usize = 4096;
+
+/// Check-type specific part of the `object_data` JSONB column.
+///
+/// Field names are kept short because they are repeated in every row. Only artifacts are
+/// ever compressed, so everything else stays queryable with plain JSONB operators, e.g.
+/// `WHERE object_data->>'kind' = 'http' AND (object_data->>'status')::int >= 500`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckPayload {
+    Tcp {
+        #[serde(rename = "ip", default, skip_serializing_if = "Option::is_none")]
+        resolved_ip: Option<IpAddr>,
+    },
+    Http {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        status: Option<u16>,
+    },
+    Browser {
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        steps: Vec<StepResult>,
+        #[serde(rename = "art", default, skip_serializing_if = "Vec::is_empty")]
+        artifacts: Vec<Artifact>,
+    },
+    Composite {
+        steps: Vec<StepResult>,
+    },
+    /// Check types without a layout of their own; only their metrics are kept.
+    Other {
+        #[serde(rename = "m", default, skip_serializing_if = "BTreeMap::is_empty")]
+        metrics: BTreeMap<String, f64>,
+    },
+}
+
+impl CheckPayload {
+    /// Layout for results of the generic checks: browser artifacts, per-step data when
+    /// there is any, the resolved address for plain connects, otherwise just the metrics.
+    pub fn from_result(result: &CheckResult) -> Self {
+        if !result.artifacts.is_empty() {
+            CheckPayload::Browser {
+                steps: result.steps.clone(),
+                artifacts: result.artifacts.clone(),
+            }
+        } else if !result.steps.is_empty() {
+            CheckPayload::Composite {
+                steps: result.steps.clone(),
+            }
+        } else if result.metrics.is_empty() {
+            CheckPayload::Tcp {
+                resolved_ip: result.resolved_ip,
+            }
+        } else {
+            CheckPayload::Other {
+                metrics: result.metrics.clone(),
+            }
+        }
+    }
+}
+
+/// A large attachment of a result, such as a HAR file or a trace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub content_type: String,
+    /// `gzip` when `data` is base64 of gzipped bytes, absent when it is plain text.
+    #[serde(rename = "enc", default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    pub data: String,
+}
+
+impl Artifact {
+    /// Stores `bytes` as is when small and valid UTF-8, gzipped otherwise.
+    pub fn new(name: &str, content_type: &str, bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let (encoding, data) = match std::str::from_utf8(bytes) {
+            Ok(text) if bytes.len() <= COMPRESS_ABOVE_BYTES => (None, text.to_string()),
+            _ => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(bytes)?;
+                (Some("gzip".to_string()), BASE64.encode(encoder.finish()?))
+            }
+        };
+        Ok(Self {
+            name: name.to_string(),
+            content_type: content_type.to_string(),
+            encoding,
+            data,
+        })
+    }
+
+    pub fn bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        match self.encoding.as_deref() {
+            None => Ok(self.data.as_bytes().to_vec()),
+            Some("gzip") => {
+                let mut bytes = Vec::new();
+                GzDecoder::new(BASE64.decode(&self.data)?.as_slice()).read_to_end(&mut bytes)?;
+                Ok(bytes)
+            }
+            Some(other) => Err(format!("unknown artifact encoding '{}'", other).into()),
+        }
+    }
+}
+
+/// The full, versioned `object_data` document of one stored result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredPayload {
+    pub v: u32,
+    #[serde(flatten)]
+    pub payload: CheckPayload,
+    #[serde(rename = "ms", default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    #[serde(rename = "fail", default, skip_serializing_if = "Option::is_none")]
+    pub failure_kind: Option<FailureKind>,
+    #[serde(rename = "err", default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(rename = "cid", default, skip_serializing_if = "String::is_empty")]
+    pub correlation_id: String,
+    #[serde(rename = "meta", default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+}
+
+impl StoredPayload {
+    pub fn new(result: &CheckResult, payload: CheckPayload) -> Self {
+        Self {
+            v: PAYLOAD_VERSION,
+            payload,
+            latency_ms: result.latency_ms(),
+            failure_kind: result.failure_kind,
+            error: result.error.clone(),
+            correlation_id: result.correlation_id.clone(),
+            metadata: result.metadata.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<JsonValue, serde_json::Error> {
+        serde_json::to_value(self)
+    }
+
+    /// Reads a payload, refusing ones written by a newer version we can't interpret.
+    pub fn from_json(value: &JsonValue) -> Result<Self, Box<dyn Error>> {
+        match value.get("v").and_then(JsonValue::as_u64) {
+            Some(v) if v <= PAYLOAD_VERSION as u64 => Ok(serde_json::from_value(value.clone())?),
+            Some(v) => Err(format!("payload version {} is newer than supported {}", v, PAYLOAD_VERSION).into()),
+            None => Err("payload has no version".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_payload_round_trip_is_compact_and_versioned() {
+        let result = CheckResult::failure("web:443", "HTTP 502 Bad Gateway");
+        let stored = StoredPayload::new(&result, CheckPayload::Http { status: Some(502) });
+        let json = stored.to_json().unwrap();
+        assert_eq!(json["kind"], "http");
+        assert_eq!(json["v"], PAYLOAD_VERSION);
+        assert_eq!(json["fail"], "http5xx");
+        assert!(json.get("ms").is_none());
+        assert_eq!(StoredPayload::from_json(&json).unwrap(), stored);
+
+        let mut newer = json.clone();
+        newer["v"] = serde_json::json!(PAYLOAD_VERSION + 1);
+        assert!(StoredPayload::from_json(&newer).is_err());
+
+        let ok = CheckResult::success("db:5432", Duration::from_millis(4));
+        assert_eq!(CheckPayload::from_result(&ok), CheckPayload::Tcp { resolved_ip: None });
+    }
+
+    #[test]
+    fn test_large_artifacts_are_compressed() {
+        let har = "{\"log\":{\"entries\":[]}}".repeat(500);
+        let artifact = Artifact::new("page.har", "application/json", har.as_bytes()).unwrap();
+        assert_eq!(artifact.encoding.as_deref(), Some("gzip"));
+        assert!(artifact.data.len() < har.len() / 4);
+        assert_eq!(artifact.bytes().unwrap(), har.as_bytes());
+
+        let small = Artifact::new("note.txt", "text/plain", b"ok").unwrap();
+        assert_eq!((small.encoding, small.data.as_str()), (None, "ok"));
+    }
+}
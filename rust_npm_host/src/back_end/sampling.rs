@@ -0,0 +1,210 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::check_result::CheckResult;
+use super::load_test::percentile;
+use super::state_tracker::{RecoveryRules, StateTracker, Transition};
+
+/// Summary of all results of one target within one sampling interval.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct IntervalAggregate {
+    pub target: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub count: u64,
+    pub failures: u64,
+    pub p95_latency: Option<Duration>,
+    pub max_latency: Option<Duration>,
+    /// Most recent error seen in the interval, to give failures some context.
+    pub last_error: Option<String>,
+}
+
+impl IntervalAggregate {
+    /// The aggregate as one stored result at the interval's start: failed if any check
+    /// failed, with the p95 latency and the counts as metrics.
+    pub fn to_result(&self) -> CheckResult {
+        let mut result = if self.failures > 0 {
+            CheckResult::failure(&self.target, self.last_error.as_deref().unwrap_or("check failed"))
+        } else {
+            CheckResult::success(&self.target, Duration::ZERO)
+        };
+        result.timestamp = self.start;
+        result.latency = self.p95_latency;
+        result.metrics.insert("sampled_checks".to_string(), self.count as f64);
+        result.metrics.insert("sampled_failures".to_string(), self.failures as f64);
+        if let Some(max) = self.max_latency {
+            result.metrics.insert("max_latency_ms".to_string(), max.as_secs_f64() * 1000.0);
+        }
+        result
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    start: DateTime<Utc>,
+    count: u64,
+    failures: u64,
+    latencies: Vec<Duration>,
+    last_error: Option<String>,
+}
+
+impl Bucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            count: 0,
+            failures: 0,
+            latencies: Vec::new(),
+            last_error: None,
+        }
+    }
+
+    fn finish(mut self, target: &str, interval: ChronoDuration) -> IntervalAggregate {
+        self.latencies.sort();
+        IntervalAggregate {
+            target: target.to_string(),
+            start: self.start,
+            end: self.start + interval,
+            count: self.count,
+            failures: self.failures,
+            p95_latency: percentile(&self.latencies, 95.0),
+            max_latency: self.latencies.last().copied(),
+            last_error: self.last_error,
+        }
+    }
+}
+
+/// What the sampler wants stored or sent after seeing one result.
+#[derive(Debug, Default)]
+pub struct SamplerOutput {
+    /// Up/down change caused by this very result; never delayed by sampling.
+    pub transition: Option<Transition>,
+    /// Aggregate of the interval that just ended, if this result started a new one.
+    pub aggregate: Option<IntervalAggregate>,
+}
+
+/// Reduces high-frequency check results to one aggregate per target and interval.
+///
+/// Intervals are aligned to multiples of `interval` since the Unix epoch, so aggregates from
+/// different targets line up. Only the aggregates should be persisted; state changes are
+/// passed through as soon as they happen.
+pub struct Sampler {
+    interval: ChronoDuration,
+    tracker: StateTracker,
+    buckets: HashMap<String, Bucket>,
+}
+
+impl Sampler {
+    pub fn new(interval: Duration, rules: RecoveryRules) -> Self {
+        let interval = ChronoDuration::from_std(interval)
+            .unwrap_or(ChronoDuration::MAX)
+            .max(ChronoDuration::milliseconds(1));
+        Self {
+            interval,
+            tracker: StateTracker::new(rules),
+            buckets: HashMap::new(),
+        }
+    }
+
+    fn bucket_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let interval_ms = self.interval.num_milliseconds();
+        let ms = at.timestamp_millis();
+        DateTime::from_timestamp_millis(ms - ms.rem_euclid(interval_ms)).unwrap_or(at)
+    }
+
+    pub fn record(&mut self, result: &CheckResult) -> SamplerOutput {
+        let transition = self.tracker.record(result);
+        let start = self.bucket_start(result.timestamp);
+
+        let mut aggregate = None;
+        let bucket = match self.buckets.remove(&result.target) {
+            // Results arriving slightly out of order are counted in the current bucket.
+            Some(bucket) if start <= bucket.start => bucket,
+            Some(bucket) => {
+                aggregate = Some(bucket.finish(&result.target, self.interval));
+                Bucket::new(start)
+            }
+            None => Bucket::new(start),
+        };
+        let bucket = self.buckets.entry(result.target.clone()).or_insert(bucket);
+        bucket.count += 1;
+        if !result.success {
+            bucket.failures += 1;
+            bucket.last_error = result.error.clone();
+        }
+        if let Some(latency) = result.latency {
+            bucket.latencies.push(latency);
+        }
+
+        SamplerOutput { transition, aggregate }
+    }
+
+    /// Closes every interval that ended before `now`, e.g. for targets that stopped
+    /// reporting, or on shutdown with `now` far in the future.
+    pub fn flush(&mut self, now: DateTime<Utc>) -> Vec<IntervalAggregate> {
+        let interval = self.interval;
+        let expired: Vec<String> = self
+            .buckets
+            .iter()
+            .filter(|(_, bucket)| bucket.start + interval <= now)
+            .map(|(target, _)| target.clone())
+            .collect();
+        let mut aggregates: Vec<IntervalAggregate> = expired
+            .into_iter()
+            .filter_map(|target| {
+                let bucket = self.buckets.remove(&target)?;
+                Some(bucket.finish(&target, interval))
+            })
+            .collect();
+        aggregates.sort_by(|a, b| a.target.cmp(&b.target));
+        aggregates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::state_tracker::TargetState;
+
+    fn result(ms: i64, success: bool, latency_ms: u64) -> CheckResult {
+        let mut r = if success {
+            CheckResult::success("api:443", Duration::from_millis(latency_ms))
+        } else {
+            CheckResult::failure("api:443", "timeout")
+        };
+        r.timestamp = DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::milliseconds(ms);
+        r
+    }
+
+    #[test]
+    fn test_results_are_aggregated_per_interval() {
+        let mut sampler = Sampler::new(Duration::from_secs(10), RecoveryRules::default());
+        for i in 0..20 {
+            let output = sampler.record(&result(i * 500, true, 10 + i as u64));
+            assert!(output.aggregate.is_none());
+        }
+        let output = sampler.record(&result(10_000, true, 5));
+        let aggregate = output.aggregate.unwrap();
+        assert_eq!(aggregate.count, 20);
+        assert_eq!(aggregate.failures, 0);
+        assert_eq!(aggregate.p95_latency, Some(Duration::from_millis(28)));
+        assert_eq!(aggregate.max_latency, Some(Duration::from_millis(29)));
+        assert_eq!(aggregate.end, DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::seconds(10));
+    }
+
+    #[test]
+    fn test_transitions_are_not_delayed() {
+        let mut sampler = Sampler::new(Duration::from_secs(60), RecoveryRules::default());
+        sampler.record(&result(0, true, 5));
+        let output = sampler.record(&result(200, false, 0));
+        assert_eq!(output.transition.unwrap().to, TargetState::Down);
+        assert!(output.aggregate.is_none());
+
+        let aggregates = sampler.flush(DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::seconds(60));
+        assert_eq!(aggregates.len(), 1);
+        assert_eq!(aggregates[0].failures, 1);
+        assert_eq!(aggregates[0].last_error.as_deref(), Some("timeout"));
+    }
+}
@@ -0,0 +1,121 @@
+use sqlx::PgPool;
+use std::error::Error;
+use std::fmt;
+
+/// Database schema migrations, applied in order. The schema version is the number of
+/// migrations applied; never edit a released entry, only append new ones.
+const MIGRATIONS: &[&str] = &[
+    // 1: status log written by `storage::postgres`.
+    "CREATE TABLE IF NOT EXISTS status_log_table (
+        id SERIAL PRIMARY KEY,
+        event_time TIMESTAMPTZ NOT NULL,
+        agent_name TEXT NOT NULL,
+        status_ok BOOLEAN NOT NULL,
+        object_data JSONB
+    )",
+    // 2: reports filter by time and by payload kind.
+    "CREATE INDEX IF NOT EXISTS status_log_event_time_idx ON status_log_table (event_time);
+     CREATE INDEX IF NOT EXISTS status_log_kind_idx ON status_log_table ((object_data->>'kind'))",
+    // 3: result history per target, written by `history`.
+    "ALTER TABLE status_log_table ADD COLUMN IF NOT EXISTS target TEXT;
+     CREATE INDEX IF NOT EXISTS status_log_target_idx ON status_log_table (target, event_time)",
+];
+
+/// Schema version this build writes and understands.
+pub const SCHEMA_VERSION: i32 = MIGRATIONS.len() as i32;
+
+const CREATE_VERSION_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_version (version INTEGER NOT NULL)";
+
+/// How a database relates to this build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    Current,
+    /// Older schema; `migrate` will upgrade it.
+    NeedsMigration { from: i32 },
+    /// Written by a newer build. Rolling back the binary is fine only once the newer
+    /// instances are gone, and we must not touch the schema.
+    Newer { found: i32 },
+}
+
+impl fmt::Display for Compatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Compatibility::Current => write!(f, "schema v{} (current)", SCHEMA_VERSION),
+            Compatibility::NeedsMigration { from } => {
+                write!(f, "schema v{} will be migrated to v{}", from, SCHEMA_VERSION)
+            }
+            Compatibility::Newer { found } => {
+                write!(f, "schema v{} is newer than this build (v{})", found, SCHEMA_VERSION)
+            }
+        }
+    }
+}
+
+pub fn compatibility(found: i32) -> Compatibility {
+    match found {
+        v if v == SCHEMA_VERSION => Compatibility::Current,
+        v if v < SCHEMA_VERSION => Compatibility::NeedsMigration { from: v },
+        v => Compatibility::Newer { found: v },
+    }
+}
+
+/// Version of the schema in `pool`, without changing anything; 0 for a database that
+/// predates versioning or is empty.
+pub async fn current_version(pool: &PgPool) -> Result<i32, Box<dyn Error>> {
+    let versioned: bool = sqlx::query_scalar("SELECT to_regclass('schema_version') IS NOT NULL")
+        .fetch_one(pool)
+        .await?;
+    if !versioned {
+        return Ok(0);
+    }
+    let version: Option<i32> = sqlx::query_scalar("SELECT max(version) FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Brings the schema up to `SCHEMA_VERSION`. Each migration runs in its own transaction
+/// together with the version bump, and an advisory lock keeps two instances starting at
+/// the same time from migrating twice. Refuses to run against a newer schema.
+pub async fn migrate(pool: &PgPool) -> Result<Compatibility, Box<dyn Error>> {
+    let found = current_version(pool).await?;
+    sqlx::query(CREATE_VERSION_TABLE).execute(pool).await?;
+    let compat = compatibility(found);
+    if let Compatibility::Newer { .. } = compat {
+        return Err(compat.to_string().into());
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(found.max(0) as usize) {
+        let version = index as i32 + 1;
+        let mut tx = pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock(727001)").execute(&mut *tx).await?;
+        let applied: Option<i32> = sqlx::query_scalar("SELECT max(version) FROM schema_version")
+            .fetch_one(&mut *tx)
+            .await?;
+        if applied.unwrap_or(0) >= version {
+            // Another instance got here first.
+            continue;
+        }
+        sqlx::raw_sql(migration).execute(&mut *tx).await?;
+        sqlx::query("INSERT INTO schema_version (version) VALUES ($1)")
+            .bind(version)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+    }
+    Ok(compat)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compatibility_of_versions() {
+        assert_eq!(compatibility(SCHEMA_VERSION), Compatibility::Current);
+        assert_eq!(compatibility(0), Compatibility::NeedsMigration { from: 0 });
+        let newer = compatibility(SCHEMA_VERSION + 1);
+        assert_eq!(newer, Compatibility::Newer { found: SCHEMA_VERSION + 1 });
+        assert!(newer.to_string().contains("newer than this build"));
+    }
+}
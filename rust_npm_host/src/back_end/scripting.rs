@@ -0,0 +1,217 @@
+use rhai::{AST, Dynamic, Engine, Map, Scope};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use super::check_result::CheckResult;
+use super::severity::Severity;
+
+// Keeps a runaway user script (e.g. an endless loop) from stalling the monitor.
+const MAX_SCRIPT_OPERATIONS: u64 = 100_000;
+
+/// What a user script decided about a single check result.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ScriptOutcome {
+    /// Custom status label, e.g. "degraded". `None` keeps the built-in up/down status.
+    pub status: Option<String>,
+    /// Replacement alert severity, if the script set one.
+    pub severity: Option<Severity>,
+    /// Synthetic metrics derived by the script.
+    pub metrics: BTreeMap<String, f64>,
+}
+
+impl ScriptOutcome {
+    /// Puts the decisions into `result`; its own metrics win over synthetic ones.
+    pub fn apply_to(self, result: &mut CheckResult) {
+        for (name, value) in self.metrics {
+            result.metrics.entry(name).or_insert(value);
+        }
+        result.status = self.status;
+        result.severity = self.severity;
+    }
+}
+
+/// A compiled rhai script that runs on every `CheckResult`.
+///
+/// The script sees a read-only `result` map (`target`, `success`, `latency_ms`, `error`,
+/// `timestamp`) and may assign the variables `status`, `severity` and `metrics`:
+///
+/// ```text
+/// if result.success && result.latency_ms > 500.0 {
+///     status = "degraded";
+///     severity = "warning";
+/// }
+/// // Failed checks have no latency.
+/// if result.latency_ms != () {
+///     metrics.latency_s = result.latency_ms / 1000.0;
+/// }
+/// ```
+pub struct ScriptHook {
+    name: String,
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptHook {
+    pub fn from_source(name: &str, source: &str) -> Result<Self, Box<dyn Error>> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+        let ast = engine.compile(source)?;
+        Ok(Self {
+            name: name.to_string(),
+            engine,
+            ast,
+        })
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let source = fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.display().to_string());
+        Self::from_source(&name, &source)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the script against one result and collects whatever it assigned.
+    pub fn run(&self, result: &CheckResult) -> Result<ScriptOutcome, Box<dyn Error>> {
+        let mut scope = Scope::new();
+        scope.push_constant("result", result_to_map(result));
+        scope.push("status", String::new());
+        scope.push("severity", String::new());
+        scope.push("metrics", Map::new());
+
+        self.engine.run_ast_with_scope(&mut scope, &self.ast)?;
+
+        let mut outcome = ScriptOutcome::default();
+        if let Some(status) = scope.get_value::<String>("status").filter(|s| !s.is_empty()) {
+            outcome.status = Some(status);
+        }
+        if let Some(severity) = scope.get_value::<String>("severity").filter(|s| !s.is_empty()) {
+            outcome.severity = Some(severity.parse::<Severity>()?);
+        }
+        if let Some(metrics) = scope.get_value::<Map>("metrics") {
+            for (key, value) in metrics {
+                let number = value
+                    .as_float()
+                    .or_else(|_| value.as_int().map(|i| i as f64))
+                    .map_err(|type_name| {
+                        format!("metric '{}' must be a number, got {}", key, type_name)
+                    })?;
+                outcome.metrics.insert(key.to_string(), number);
+            }
+        }
+        Ok(outcome)
+    }
+}
+
+fn result_to_map(result: &CheckResult) -> Map {
+    let mut map = Map::new();
+    map.insert("target".into(), Dynamic::from(result.target.clone()));
+    map.insert("success".into(), Dynamic::from(result.success));
+    map.insert("timestamp".into(), Dynamic::from(result.timestamp.to_rfc3339()));
+    map.insert(
+        "latency_ms".into(),
+        result.latency_ms().map_or(Dynamic::UNIT, Dynamic::from),
+    );
+    map.insert(
+        "error".into(),
+        result.error.clone().map_or(Dynamic::UNIT, Dynamic::from),
+    );
+    map
+}
+
+/// Loads every `*.rhai` file in a directory, sorted by file name so the run order is predictable.
+pub fn load_hooks(dir: &Path) -> Result<Vec<ScriptHook>, Box<dyn Error>> {
+    let mut paths: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+    paths.iter().map(|p| ScriptHook::from_file(p)).collect()
+}
+
+/// Runs all hooks in order; later hooks override the status/severity of earlier ones and
+/// metrics are merged. A failing script is reported and skipped so one bad hook doesn't
+/// hide results.
+pub fn apply_hooks(hooks: &[ScriptHook], result: &CheckResult) -> ScriptOutcome {
+    let mut combined = ScriptOutcome::default();
+    for hook in hooks {
+        match hook.run(result) {
+            Ok(outcome) => {
+                if outcome.status.is_some() {
+                    combined.status = outcome.status;
+                }
+                if outcome.severity.is_some() {
+                    combined.severity = outcome.severity;
+                }
+                combined.metrics.extend(outcome.metrics);
+            }
+            Err(e) => {
+                eprintln!(
+                    "[{}] Script hook '{}' failed for {}: {}",
+                    result.correlation_id,
+                    hook.name(),
+                    result.target,
+                    e
+                );
+            }
+        }
+    }
+    combined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_script_sets_status_severity_and_metrics() {
+        let hook = ScriptHook::from_source(
+            "slow",
+            r#"
+            if result.success && result.latency_ms > 500.0 {
+                status = "degraded";
+                severity = "warning";
+            }
+            if result.latency_ms != () {
+                metrics.latency_s = result.latency_ms / 1000.0;
+            }
+            metrics.checks = 1;
+            "#,
+        )
+        .unwrap();
+
+        let outcome = hook
+            .run(&CheckResult::success("10.0.0.1:443", Duration::from_millis(800)))
+            .unwrap();
+        assert_eq!(outcome.status.as_deref(), Some("degraded"));
+        assert_eq!(outcome.severity, Some(Severity::Warning));
+        assert_eq!(outcome.metrics.get("latency_s"), Some(&0.8));
+        assert_eq!(outcome.metrics.get("checks"), Some(&1.0));
+
+        let mut failed = CheckResult::failure("10.0.0.1:443", "refused");
+        hook.run(&failed).unwrap().apply_to(&mut failed);
+        assert_eq!((failed.status, failed.metrics.get("latency_s")), (None, None));
+        assert_eq!(failed.metrics.get("checks"), Some(&1.0));
+    }
+
+    #[test]
+    fn test_script_without_assignments_changes_nothing() {
+        let hook = ScriptHook::from_source("noop", "let x = 1;").unwrap();
+        let outcome = hook.run(&CheckResult::failure("10.0.0.1:22", "refused")).unwrap();
+        assert_eq!(outcome, ScriptOutcome::default());
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let hook = ScriptHook::from_source("loop", "loop { }").unwrap();
+        assert!(hook.run(&CheckResult::failure("10.0.0.1:22", "refused")).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+use serde_json::Value as JsonValue;
+use std::fmt;
+
+/// Asks the page itself, so the browser's CSS parser decides what is valid. Returns
+/// `{error}` for a selector it can't parse, otherwise match and visibility counts.
+pub(crate) const SELECTOR_SCRIPT: &str = "
+const selector = arguments[0];
+let elements;
+try { elements = Array.from(document.querySelectorAll(selector)); }
+catch (e) { return { error: e.message }; }
+const visible = elements.filter(e => e.offsetWidth > 0 || e.offsetHeight > 0 || e.getClientRects().length > 0);
+return { matches: elements.length, visible: visible.length };";
+
+/// Outcome of a one-off selector validation against the live page.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectorValidation {
+    /// At least one element matches and is visible: the check will succeed today.
+    Ok { matches: u64 },
+    /// Elements match but none is visible, so the check would wait until it times out.
+    Hidden { matches: u64 },
+    NoMatch,
+    Invalid(String),
+}
+
+impl SelectorValidation {
+    /// Interprets the result of `SELECTOR_SCRIPT`.
+    pub fn from_script(value: &JsonValue) -> Self {
+        if let Some(error) = value["error"].as_str() {
+            return SelectorValidation::Invalid(error.to_string());
+        }
+        match (value["matches"].as_u64().unwrap_or(0), value["visible"].as_u64().unwrap_or(0)) {
+            (0, _) => SelectorValidation::NoMatch,
+            (matches, 0) => SelectorValidation::Hidden { matches },
+            (matches, _) => SelectorValidation::Ok { matches },
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        matches!(self, SelectorValidation::Ok { .. })
+    }
+}
+
+impl fmt::Display for SelectorValidation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectorValidation::Ok { matches: 1 } => write!(f, "selector matches 1 visible element"),
+            SelectorValidation::Ok { matches } => write!(f, "selector matches {} elements", matches),
+            SelectorValidation::Hidden { matches } => {
+                write!(f, "selector matches {} element(s), but none is visible; the check would time out", matches)
+            }
+            SelectorValidation::NoMatch => write!(f, "selector matches nothing on the page right now"),
+            SelectorValidation::Invalid(e) => write!(f, "selector is not valid CSS: {}", e),
+        }
+    }
+}
+
+/// Catches obvious typos without starting a browser: unbalanced brackets, parentheses or
+/// quotes and dangling combinators. Anything that passes still goes through the browser.
+pub fn precheck(selector: &str) -> Result<(), String> {
+    let selector = selector.trim();
+    if selector.is_empty() {
+        return Err("selector is empty".to_string());
+    }
+    let mut stack = Vec::new();
+    let mut quote = None;
+    let mut chars = selector.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '[' | '(') => stack.push(c),
+            (None, ']') if stack.pop() != Some('[') => return Err("unexpected ']'".to_string()),
+            (None, ')') if stack.pop() != Some('(') => return Err("unexpected ')'".to_string()),
+            _ => {}
+        }
+    }
+    if let Some(q) = quote {
+        return Err(format!("unclosed {} quote", q));
+    }
+    if let Some(open) = stack.pop() {
+        return Err(format!("unclosed '{}'", open));
+    }
+    if selector.ends_with(['>', '+', '~', ',']) {
+        return Err("selector ends with a combinator".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precheck_and_script_results() {
+        assert!(precheck("#main > div.item[data-id='a]b']").is_ok());
+        assert!(precheck("a:not(.b)").is_ok());
+        assert_eq!(precheck("div[data-x"), Err("unclosed '['".to_string()));
+        assert_eq!(precheck("div)"), Err("unexpected ')'".to_string()));
+        assert!(precheck("ul >").is_err());
+        assert!(precheck("  ").is_err());
+
+        let check = |value| SelectorValidation::from_script(&value);
+        assert_eq!(check(serde_json::json!({"matches": 2, "visible": 1})), SelectorValidation::Ok { matches: 2 });
+        assert_eq!(check(serde_json::json!({"matches": 1, "visible": 0})), SelectorValidation::Hidden { matches: 1 });
+        assert_eq!(check(serde_json::json!({"matches": 0, "visible": 0})), SelectorValidation::NoMatch);
+        assert!(matches!(check(serde_json::json!({"error": "'h1!' is not a valid selector"})), SelectorValidation::Invalid(_)));
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::fmt;
+use std::net::SocketAddr;
+
+use super::notify::Notification;
+use super::severity::Severity;
+use super::state_tracker::TargetState;
+
+/// Combined state of all checks of a service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Rollup {
+    Up,
+    /// Some checks are down, others up: the service is impaired but not gone.
+    Degraded,
+    Down,
+    /// None of the checks has a result yet.
+    Unknown,
+}
+
+impl fmt::Display for Rollup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rollup::Up => "UP",
+            Rollup::Degraded => "DEGRADED",
+            Rollup::Down => "DOWN",
+            Rollup::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+/// Down only when every checked member is down; unchecked members don't count.
+pub fn rollup(states: impl IntoIterator<Item = Option<TargetState>>) -> Rollup {
+    let (mut up, mut down) = (0, 0);
+    for state in states.into_iter().flatten() {
+        match state {
+            TargetState::Up => up += 1,
+            TargetState::Down => down += 1,
+        }
+    }
+    match (up, down) {
+        (0, 0) => Rollup::Unknown,
+        (_, 0) => Rollup::Up,
+        (0, _) => Rollup::Down,
+        _ => Rollup::Degraded,
+    }
+}
+
+/// A service: the checks (ICMP, HTTP, database port, ...) filed under one group name, with
+/// their combined status.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ServiceStatus {
+    pub name: String,
+    pub rollup: Rollup,
+    pub members: Vec<(SocketAddr, Option<TargetState>)>,
+}
+
+impl ServiceStatus {
+    pub fn new(name: &str, members: Vec<(SocketAddr, Option<TargetState>)>) -> Self {
+        Self {
+            name: name.to_string(),
+            rollup: rollup(members.iter().map(|(_, state)| *state)),
+            members,
+        }
+    }
+
+    pub fn down(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.members.iter().filter(|(_, s)| *s == Some(TargetState::Down)).map(|(addr, _)| *addr)
+    }
+}
+
+impl fmt::Display for ServiceStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} ({} checks", self.name, self.rollup, self.members.len())?;
+        let down: Vec<String> = self.down().map(|a| a.to_string()).collect();
+        if !down.is_empty() {
+            write!(f, ", down: {}", down.join(", "))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A change of a service's rollup status, announced like a target transition.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceChange {
+    pub service: String,
+    pub from: Rollup,
+    pub to: Rollup,
+    pub at: DateTime<Utc>,
+    /// Correlation ID of the check run that caused the change.
+    pub correlation_id: String,
+}
+
+impl From<&ServiceChange> for Notification {
+    fn from(change: &ServiceChange) -> Self {
+        let severity = match change.to {
+            Rollup::Down => Severity::Critical,
+            Rollup::Degraded => Severity::Warning,
+            Rollup::Up | Rollup::Unknown => Severity::Info,
+        };
+        Self {
+            target: format!("service {}", change.service),
+            severity,
+            message: format!("is {} (was {})", change.to, change.from),
+            at: change.at,
+            correlation_id: change.correlation_id.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rollup_of_member_states() {
+        use TargetState::{Down, Up};
+        assert_eq!(rollup([None, None]), Rollup::Unknown);
+        assert_eq!(rollup([Some(Up), None]), Rollup::Up);
+        assert_eq!(rollup([Some(Up), Some(Down)]), Rollup::Degraded);
+        assert_eq!(rollup([Some(Down), None, Some(Down)]), Rollup::Down);
+
+        let db: SocketAddr = "10.0.0.2:5432".parse().unwrap();
+        let status = ServiceStatus::new("shop", vec![("10.0.0.1:443".parse().unwrap(), Some(Up)), (db, Some(Down))]);
+        assert_eq!(status.to_string(), "shop: DEGRADED (2 checks, down: 10.0.0.2:5432)");
+    }
+}
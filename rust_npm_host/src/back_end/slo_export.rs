@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::Duration;
+
+use super::check_result::CheckResult;
+use super::load_test::percentile;
+
+/// Objectives and how latency thresholds are derived when none is configured.
+#[derive(Debug, Clone)]
+pub struct SloConfig {
+    /// Share of successful checks, e.g. 0.999.
+    pub availability_target: f64,
+    /// Share of checks that must be faster than the latency threshold.
+    pub latency_target: f64,
+    /// Percentile of the historical latency that becomes the threshold...
+    pub baseline_percentile: f64,
+    /// ...with this much headroom (1.2 = 20% slower still counts as good).
+    pub baseline_margin: f64,
+    pub window_days: u32,
+    /// Latency thresholds set by hand, in milliseconds, by target. These win over the
+    /// baseline.
+    pub latency_thresholds_ms: HashMap<String, f64>,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            availability_target: 0.999,
+            latency_target: 0.99,
+            baseline_percentile: 99.0,
+            baseline_margin: 1.2,
+            window_days: 28,
+            latency_thresholds_ms: HashMap::new(),
+        }
+    }
+}
+
+/// What the history says about one target, and the latency threshold chosen for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetSli {
+    pub target: String,
+    pub checks: usize,
+    pub availability: f64,
+    pub baseline_ms: Option<f64>,
+    pub latency_threshold_ms: Option<f64>,
+}
+
+/// Per-target SLIs from historical results, in target order.
+pub fn target_slis(results: &[CheckResult], config: &SloConfig) -> Vec<TargetSli> {
+    let mut by_target: BTreeMap<&str, Vec<&CheckResult>> = BTreeMap::new();
+    for result in results {
+        by_target.entry(&result.target).or_default().push(result);
+    }
+    by_target
+        .into_iter()
+        .map(|(target, results)| {
+            let mut latencies: Vec<Duration> = results.iter().filter(|r| r.success).filter_map(|r| r.latency).collect();
+            latencies.sort();
+            let baseline_ms = percentile(&latencies, config.baseline_percentile).map(|d| d.as_secs_f64() * 1000.0);
+            // Round derived thresholds up to 10 ms so they don't read as false precision.
+            let derived = baseline_ms.map(|ms| (ms * config.baseline_margin / 10.0).ceil() * 10.0);
+            TargetSli {
+                target: target.to_string(),
+                checks: results.len(),
+                availability: results.iter().filter(|r| r.success).count() as f64 / results.len() as f64,
+                baseline_ms,
+                latency_threshold_ms: config.latency_thresholds_ms.get(target).copied().or(derived),
+            }
+        })
+        .collect()
+}
+
+/// OpenSLO v1 documents (one availability and, where a threshold is known, one latency
+/// SLO per target), ready for `oslo validate` or conversion to sloth/Prometheus rules.
+/// `services` maps targets to the service (group) they belong to.
+pub fn to_openslo(slis: &[TargetSli], services: &HashMap<String, String>, config: &SloConfig) -> String {
+    let mut yaml = String::new();
+    for sli in slis {
+        let service = services.get(&sli.target).map_or("rust-npm", String::as_str);
+        let name = slug(&sli.target);
+        slo_document(&mut yaml, &format!("{}-availability", name), sli, service, config, None);
+        if let Some(threshold) = sli.latency_threshold_ms {
+            slo_document(&mut yaml, &format!("{}-latency", name), sli, service, config, Some(threshold));
+        }
+    }
+    yaml
+}
+
+fn slo_document(yaml: &mut String, name: &str, sli: &TargetSli, service: &str, config: &SloConfig, latency_ms: Option<f64>) {
+    let (what, query, objective) = match latency_ms {
+        None => ("availability", "success", config.availability_target),
+        Some(_) => ("latency", "latency_ms", config.latency_target),
+    };
+    // Writing to a String can't fail.
+    let _ = write!(
+        yaml,
+        "---\napiVersion: openslo/v1\nkind: SLO\nmetadata:\n  name: {name}\n  displayName: {display}\n  annotations:\n    \
+         rust-npm/target: {target}\n    rust-npm/observed-availability: {observed}\n",
+        name = name,
+        display = quote(&format!("{} {}", sli.target, what)),
+        target = quote(&sli.target),
+        observed = quote(&format!("{:.5}", sli.availability)),
+    );
+    if let Some(baseline) = sli.baseline_ms {
+        let _ = writeln!(yaml, "    rust-npm/baseline-ms: {}", quote(&format!("{:.1}", baseline)));
+    }
+    let _ = write!(
+        yaml,
+        "spec:\n  service: {service}\n  budgetingMethod: Occurrences\n  timeWindow:\n    - duration: {days}d\n      \
+         isRolling: true\n  indicator:\n    metadata:\n      name: {name}-sli\n    spec:\n",
+        service = quote(service),
+        days = config.window_days,
+        name = name,
+    );
+    match latency_ms {
+        None => {
+            let _ = write!(
+                yaml,
+                "      ratioMetric:\n        counter: true\n        good:\n{good}        total:\n{total}",
+                good = metric_source(10, &sli.target, query, Some("true")),
+                total = metric_source(10, &sli.target, query, None),
+            );
+        }
+        Some(_) => {
+            let _ = write!(yaml, "      thresholdMetric:\n{}", metric_source(8, &sli.target, query, None));
+        }
+    }
+    let _ = write!(yaml, "  objectives:\n    - displayName: {}\n", quote(what));
+    if let Some(threshold) = latency_ms {
+        let _ = write!(yaml, "      op: lte\n      value: {}\n", threshold);
+    }
+    let _ = writeln!(yaml, "      target: {}", objective);
+}
+
+fn metric_source(indent: usize, target: &str, field: &str, equals: Option<&str>) -> String {
+    let mut lines = vec![
+        "metricSource:".to_string(),
+        "  type: rust-npm".to_string(),
+        "  spec:".to_string(),
+        format!("    target: {}", quote(target)),
+        format!("    field: {}", field),
+    ];
+    if let Some(value) = equals {
+        lines.push(format!("    equals: {}", value));
+    }
+    lines.iter().map(|line| format!("{:indent$}{}\n", "", line, indent = indent)).collect()
+}
+
+// Double-quoted YAML scalar; covers the colons in `host:port` and anything a group name
+// might contain.
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// OpenSLO names must be lowercase DNS labels.
+fn slug(target: &str) -> String {
+    let slug: String = target
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    slug.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thresholds_from_baseline_and_config() {
+        let mut results: Vec<CheckResult> = (1..=100)
+            .map(|ms| CheckResult::success("10.0.0.1:443", Duration::from_millis(ms)))
+            .collect();
+        results.push(CheckResult::failure("10.0.0.1:443", "connection refused"));
+        results.push(CheckResult::failure("10.0.0.2:22", "connection refused"));
+        let mut config = SloConfig::default();
+        config.latency_thresholds_ms.insert("10.0.0.9:53".to_string(), 50.0);
+
+        let slis = target_slis(&results, &config);
+        assert_eq!(slis[0].baseline_ms, Some(99.0));
+        // 99 ms * 1.2, rounded up to 10 ms.
+        assert_eq!(slis[0].latency_threshold_ms, Some(120.0));
+        assert_eq!(slis[1].latency_threshold_ms, None);
+        assert_eq!(slis[1].availability, 0.0);
+
+        let services = HashMap::from([("10.0.0.1:443".to_string(), "shop".to_string())]);
+        let yaml = to_openslo(&slis, &services, &config);
+        assert_eq!(yaml.matches("kind: SLO").count(), 3);
+        assert!(yaml.contains("  name: 10-0-0-1-443-latency\n"));
+        assert!(yaml.contains("  service: \"shop\"\n"));
+        assert!(yaml.contains("      op: lte\n      value: 120\n      target: 0.99\n"));
+        assert!(yaml.contains("      thresholdMetric:\n        metricSource:\n"));
+    }
+}
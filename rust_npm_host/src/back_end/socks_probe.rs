@@ -0,0 +1,255 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio_socks::Error as SocksError;
+use tokio_socks::tcp::Socks5Stream;
+
+use super::check_result::CheckResult;
+
+/// A SOCKS5 proxy at a remote site, used as an extra vantage point for checks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocksProxy {
+    /// Short site name, appended to result targets ("db:5432@branch-berlin").
+    pub name: String,
+    /// `host:port` of the proxy.
+    pub address: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl SocksProxy {
+    pub fn new(name: &str, address: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            address: address.to_string(),
+            username: None,
+            password: None,
+        }
+    }
+
+    fn url(&self) -> String {
+        // socks5h: let the proxy resolve names, so site-local DNS works.
+        match (&self.username, &self.password) {
+            (Some(user), Some(pass)) => format!("socks5h://{}:{}@{}", user, pass, self.address),
+            _ => format!("socks5h://{}", self.address),
+        }
+    }
+
+    async fn connect(&self, host: &str, port: u16) -> Result<Socks5Stream<tokio::net::TcpStream>, SocksError> {
+        match (&self.username, &self.password) {
+            (Some(user), Some(pass)) => {
+                Socks5Stream::connect_with_password(self.address.as_str(), (host, port), user, pass).await
+            }
+            _ => Socks5Stream::connect(self.address.as_str(), (host, port)).await,
+        }
+    }
+}
+
+/// How well a proxy itself is doing, independent of the targets checked through it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProxyHealth {
+    pub consecutive_failures: u32,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Whether a failed check says something about the target or about the proxy.
+fn is_target_error(error: &SocksError) -> bool {
+    matches!(
+        error,
+        SocksError::ConnectionRefused
+            | SocksError::HostUnreachable
+            | SocksError::NetworkUnreachable
+            | SocksError::TtlExpired
+            | SocksError::ConnectionNotAllowedByRuleset
+    )
+}
+
+/// Runs checks through a set of SOCKS5 proxies and keeps track of which ones are usable.
+///
+/// A proxy that failed `unhealthy_after` times in a row counts as unhealthy until a check
+/// through it succeeds again; the monitor then reports failures through it as its own
+/// problem, so a dead branch site doesn't show every target there as down.
+pub struct SocksProbe {
+    proxies: Vec<SocksProxy>,
+    pub unhealthy_after: u32,
+    health: Mutex<HashMap<String, ProxyHealth>>,
+}
+
+impl SocksProbe {
+    pub fn new(proxies: Vec<SocksProxy>) -> Self {
+        Self {
+            proxies,
+            unhealthy_after: 3,
+            health: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn proxies(&self) -> &[SocksProxy] {
+        &self.proxies
+    }
+
+    pub fn health(&self, proxy: &str) -> ProxyHealth {
+        self.health.lock().unwrap().get(proxy).cloned().unwrap_or_default()
+    }
+
+    pub fn is_healthy(&self, proxy: &str) -> bool {
+        self.health(proxy).consecutive_failures < self.unhealthy_after
+    }
+
+    fn record(&self, proxy: &str, error: Option<String>) {
+        let mut health = self.health.lock().unwrap();
+        let entry = health.entry(proxy.to_string()).or_default();
+        match error {
+            None => {
+                entry.consecutive_failures = 0;
+                entry.last_success = Some(Utc::now());
+            }
+            Some(error) => {
+                entry.consecutive_failures += 1;
+                entry.last_error = Some(error);
+            }
+        }
+    }
+
+    /// Checks that `host:port` accepts TCP connections as seen from `proxy`.
+    pub async fn check_tcp(&self, proxy: &SocksProxy, host: &str, port: u16, timeout: Duration) -> CheckResult {
+        let target = format!("{}:{}@{}", host, port, proxy.name);
+        let start = Instant::now();
+        match tokio::time::timeout(timeout, proxy.connect(host, port)).await {
+            Ok(Ok(_stream)) => {
+                self.record(&proxy.name, None);
+                CheckResult::success(&target, start.elapsed())
+            }
+            Ok(Err(e)) if is_target_error(&e) => {
+                // The proxy answered properly, it just couldn't reach the target.
+                self.record(&proxy.name, None);
+                CheckResult::failure(&target, e.to_string())
+            }
+            Ok(Err(e)) => {
+                self.record(&proxy.name, Some(e.to_string()));
+                CheckResult::failure(&target, format!("proxy {} failed: {}", proxy.name, e))
+            }
+            Err(_) => {
+                // Can't tell a hanging proxy from a hanging target; count it against neither.
+                CheckResult::failure(&target, format!("timed out after {:?}", timeout))
+            }
+        }
+    }
+
+    /// Requests `url` through `proxy`. Only a received response counts towards proxy
+    /// health; reqwest doesn't tell proxy and target connection errors apart.
+    pub async fn check_http(&self, proxy: &SocksProxy, url: &str, timeout: Duration) -> CheckResult {
+        let target = format!("{}@{}", url, proxy.name);
+        match self.request(proxy, url, timeout).await {
+            Ok((status, latency)) => {
+                self.record(&proxy.name, None);
+                if status.is_success() || status.is_redirection() {
+                    CheckResult::success(&target, latency)
+                } else {
+                    CheckResult {
+                        latency: Some(latency),
+                        ..CheckResult::failure(&target, format!("HTTP {}", status))
+                    }
+                }
+            }
+            Err(e) => CheckResult::failure(&target, e.to_string()),
+        }
+    }
+
+    async fn request(&self, proxy: &SocksProxy, url: &str, timeout: Duration) -> Result<(reqwest::StatusCode, Duration), Box<dyn Error>> {
+        let client = reqwest::Client::builder()
+            .proxy(reqwest::Proxy::all(proxy.url())?)
+            .timeout(timeout)
+            .build()?;
+        let start = Instant::now();
+        let response = client.get(url).send().await?;
+        Ok((response.status(), start.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::event_bus::EventBus;
+    use crate::back_end::monitor::Monitor;
+    use crate::back_end::target::MonitorTarget;
+    use std::net::SocketAddr;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // Minimal SOCKS5 server that accepts one CONNECT and answers with `reply`.
+    async fn fake_proxy(reply: u8) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut greeting = [0u8; 2];
+            socket.read_exact(&mut greeting).await.unwrap();
+            let mut methods = vec![0u8; greeting[1] as usize];
+            socket.read_exact(&mut methods).await.unwrap();
+            socket.write_all(&[5, 0]).await.unwrap();
+
+            let mut header = [0u8; 5];
+            socket.read_exact(&mut header).await.unwrap();
+            // Domain name request: length byte, name, port.
+            let mut rest = vec![0u8; header[4] as usize + 2];
+            socket.read_exact(&mut rest).await.unwrap();
+            socket.write_all(&[5, reply, 0, 1, 127, 0, 0, 1, 0, 80]).await.unwrap();
+        });
+        address
+    }
+
+    #[tokio::test]
+    async fn test_connect_through_proxy() {
+        let proxy = SocksProxy::new("branch", &fake_proxy(0).await);
+        let probe = SocksProbe::new(vec![proxy.clone()]);
+        let result = probe.check_tcp(&proxy, "db.local", 5432, Duration::from_secs(2)).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.target, "db.local:5432@branch");
+        assert!(probe.health("branch").last_success.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_proxied_results_name_the_proxy_as_agent() {
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(2));
+        monitor.set_agent_name(Some("hq".to_string()));
+        monitor.set_socks_proxies(vec![SocksProxy::new("branch", &fake_proxy(0).await)]);
+        let proxied: SocketAddr = "10.0.0.5:5432".parse().unwrap();
+        monitor
+            .add_monitor_target(MonitorTarget {
+                proxy: Some("branch".to_string()),
+                ..MonitorTarget::new(proxied)
+            })
+            .unwrap();
+        // Bind and drop to get a port nothing listens on.
+        let direct = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap();
+        monitor.add_monitor_target(MonitorTarget::new(direct)).unwrap();
+        assert_eq!(monitor.run_check(proxied).await.agent.as_deref(), Some("branch"));
+        assert_eq!(monitor.run_check(direct).await.agent.as_deref(), Some("hq"));
+    }
+
+    #[tokio::test]
+    async fn test_refused_target_keeps_proxy_healthy() {
+        let proxy = SocksProxy::new("branch", &fake_proxy(5).await);
+        let probe = SocksProbe::new(vec![proxy.clone()]);
+        let result = probe.check_tcp(&proxy, "db.local", 5432, Duration::from_secs(2)).await;
+        assert!(!result.success);
+        assert_eq!(probe.health("branch").consecutive_failures, 0);
+    }
+
+    #[tokio::test]
+    async fn test_dead_proxy_turns_unhealthy() {
+        // Bind and drop to get a port nothing listens on.
+        let address = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().to_string();
+        let proxy = SocksProxy::new("gone", &address);
+        let mut probe = SocksProbe::new(vec![proxy.clone()]);
+        probe.unhealthy_after = 1;
+        assert!(probe.is_healthy("gone"));
+        let result = probe.check_tcp(&proxy, "db.local", 5432, Duration::from_secs(2)).await;
+        assert!(result.error.unwrap().starts_with("proxy gone failed"));
+        assert!(!probe.is_healthy("gone"));
+    }
+}
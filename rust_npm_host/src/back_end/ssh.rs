@@ -0,0 +1,240 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::process::{Child, Command};
+
+use super::target::MonitorTarget;
+
+/// A host reachable over SSH with key authentication.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SshTarget {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub user: String,
+    /// Private key to use; `None` falls back to the agent / ~/.ssh defaults.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub identity_file: Option<PathBuf>,
+}
+
+fn default_port() -> u16 {
+    22
+}
+
+impl SshTarget {
+    pub fn new(host: &str, user: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: 22,
+            user: user.to_string(),
+            identity_file: None,
+        }
+    }
+
+    /// The host of a monitored target, reached on the target's port.
+    pub fn for_target(target: &MonitorTarget, user: &str, identity_file: Option<PathBuf>) -> Self {
+        let host = target.host.clone().unwrap_or_else(|| target.address.ip().to_string());
+        Self {
+            port: target.address.port(),
+            identity_file,
+            ..Self::new(&host, user)
+        }
+    }
+
+    fn destination(&self) -> String {
+        format!("{}@{}", self.user, self.host)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SshOutput {
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Shares one SSH connection per host between all checks.
+///
+/// This drives the system `ssh` client with OpenSSH connection multiplexing
+/// (`ControlMaster`), so the first command to a host opens a master connection and
+/// later commands reuse it instead of doing a full handshake each time. Password
+/// prompts are disabled; only key based auth is used.
+pub struct SshPool {
+    control_dir: PathBuf,
+    connect_timeout: Duration,
+    /// How long an idle master connection is kept open by ssh itself.
+    keep_alive: Duration,
+    known_masters: Mutex<HashSet<SshTarget>>,
+}
+
+impl SshPool {
+    pub fn new(control_dir: PathBuf, connect_timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        std::fs::create_dir_all(&control_dir)?;
+        Ok(Self {
+            control_dir,
+            connect_timeout,
+            keep_alive: Duration::from_secs(300),
+            known_masters: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// `rust_npm-ssh` in the runtime directory (or the temp directory), short enough for
+    /// the control sockets' path limit.
+    pub fn default_control_dir() -> PathBuf {
+        dirs::runtime_dir().unwrap_or_else(std::env::temp_dir).join("rust_npm-ssh")
+    }
+
+    fn base_command(&self, target: &SshTarget) -> Command {
+        let mut cmd = self.unshared_command(target);
+        cmd.arg("-o")
+            .arg("ControlMaster=auto")
+            .arg("-o")
+            .arg(format!("ControlPath={}/%C", self.control_dir.display()))
+            .arg("-o")
+            .arg(format!("ControlPersist={}", self.keep_alive.as_secs()));
+        cmd
+    }
+
+    // Same options without multiplexing, for processes whose lifetime we manage ourselves.
+    fn unshared_command(&self, target: &SshTarget) -> Command {
+        let mut cmd = Command::new("ssh");
+        cmd.arg("-p")
+            .arg(target.port.to_string())
+            .arg("-o")
+            .arg("BatchMode=yes")
+            .arg("-o")
+            .arg("StrictHostKeyChecking=accept-new")
+            .arg("-o")
+            .arg(format!("ConnectTimeout={}", self.connect_timeout.as_secs().max(1)));
+        if let Some(identity) = &target.identity_file {
+            cmd.arg("-i").arg(identity).arg("-o").arg("IdentitiesOnly=yes");
+        }
+        cmd.stdin(Stdio::null()).kill_on_drop(true);
+        cmd
+    }
+
+    /// Runs a command on the remote host and returns its output. A non-zero exit code is
+    /// not treated as an error here; callers decide what it means.
+    pub async fn run(&self, target: &SshTarget, remote_command: &str, timeout: Duration) -> Result<SshOutput, Box<dyn Error>> {
+        let mut cmd = self.base_command(target);
+        cmd.arg(target.destination()).arg("--").arg(remote_command);
+
+        let output = tokio::time::timeout(timeout, cmd.output())
+            .await
+            .map_err(|_| format!("ssh command to {} timed out after {:?}", target.host, timeout))??;
+
+        // ssh itself exits with 255 when the connection could not be established.
+        if output.status.code() == Some(255) {
+            return Err(format!(
+                "ssh connection to {} failed: {}",
+                target.host,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+
+        self.known_masters
+            .lock()
+            .expect("ssh pool lock poisoned")
+            .insert(target.clone());
+
+        Ok(SshOutput {
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    /// Opens a local port forward to `remote_host:remote_port` as seen from `bastion`,
+    /// optionally hopping through `jump_hosts` (`user@host[:port]`, passed to `ssh -J`).
+    ///
+    /// The tunnel runs in its own ssh process that is killed when the returned
+    /// `SshTunnel` is closed or dropped.
+    pub async fn open_tunnel(
+        &self,
+        bastion: &SshTarget,
+        jump_hosts: &[String],
+        remote_host: &str,
+        remote_port: u16,
+        timeout: Duration,
+    ) -> Result<SshTunnel, Box<dyn Error>> {
+        // Let the OS pick a free port; the tiny window before ssh binds it is acceptable here.
+        let local_port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let local_addr = SocketAddr::from(([127, 0, 0, 1], local_port));
+
+        let mut cmd = self.unshared_command(bastion);
+        cmd.arg("-o")
+            .arg("ControlPath=none")
+            .arg("-o")
+            .arg("ExitOnForwardFailure=yes")
+            .arg("-N")
+            .arg("-L")
+            .arg(format!("127.0.0.1:{}:{}:{}", local_port, remote_host, remote_port));
+        if !jump_hosts.is_empty() {
+            cmd.arg("-J").arg(jump_hosts.join(","));
+        }
+        cmd.arg(bastion.destination()).stdout(Stdio::null()).stderr(Stdio::piped());
+        let mut child = cmd.spawn()?;
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(status) = child.try_wait()? {
+                let mut stderr = String::new();
+                if let Some(mut pipe) = child.stderr.take() {
+                    pipe.read_to_string(&mut stderr).await.ok();
+                }
+                return Err(format!("ssh tunnel via {} exited ({}): {}", bastion.host, status, stderr.trim()).into());
+            }
+            if tokio::net::TcpStream::connect(local_addr).await.is_ok() {
+                return Ok(SshTunnel { child, local_addr });
+            }
+            if Instant::now() >= deadline {
+                child.kill().await.ok();
+                return Err(format!("ssh tunnel via {} not ready after {:?}", bastion.host, timeout).into());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Tears down every master connection opened through this pool.
+    pub async fn close_all(&self) {
+        let targets: Vec<SshTarget> = self
+            .known_masters
+            .lock()
+            .expect("ssh pool lock poisoned")
+            .drain()
+            .collect();
+        for target in targets {
+            let mut cmd = self.base_command(&target);
+            cmd.arg("-O").arg("exit").arg(target.destination());
+            cmd.stdout(Stdio::null()).stderr(Stdio::null());
+            if let Err(e) = cmd.status().await {
+                eprintln!("Error closing ssh master for {}: {}", target.host, e);
+            }
+        }
+    }
+}
+
+/// A running `ssh -L` port forward. Connect to `local_addr()` to reach the remote service.
+pub struct SshTunnel {
+    child: Child,
+    local_addr: SocketAddr,
+}
+
+impl SshTunnel {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub async fn close(mut self) {
+        if let Err(e) = self.child.kill().await {
+            eprintln!("Error closing ssh tunnel on {}: {}", self.local_addr, e);
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::monitor::Monitor;
+
+/// How late a result may be, on top of the interval and timeout, before its target is stale.
+pub const DEFAULT_GRACE: Duration = Duration::from_secs(60);
+
+// How often `watch` looks for overdue targets.
+const STALENESS_POLL: Duration = Duration::from_secs(5);
+
+/// A target stopped or started producing results again.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StaleChange {
+    pub target: SocketAddr,
+    /// `false` when the target has a result again (or stopped being expected to have one).
+    pub stale: bool,
+    /// `None` if the target never had one.
+    pub last_result: Option<DateTime<Utc>>,
+    pub at: DateTime<Utc>,
+}
+
+impl fmt::Display for StaleChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let last = self.last_result.map_or("never".to_string(), |at| at.format("%H:%M:%S").to_string());
+        if self.stale {
+            write!(f, "{} is STALE: no result since {}", self.target, last)
+        } else {
+            write!(f, "{} has fresh results again", self.target)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    last_result: Option<DateTime<Utc>>,
+    /// When the clock for the next result started: the last result, or when the target
+    /// was first seen or last expected to have none (paused, standby).
+    since: DateTime<Utc>,
+    stale: bool,
+}
+
+/// Tells "no data" apart from "all fine": a target whose last result is older than its
+/// deadline is stale, whether the check is wedged, the scheduler stalled or the instance
+/// lost its network.
+#[derive(Debug, Default)]
+pub struct StalenessTracker {
+    entries: HashMap<SocketAddr, Entry>,
+}
+
+impl StalenessTracker {
+    /// Notes a result of `target`; returns the change if it was stale.
+    pub fn record(&mut self, target: SocketAddr, at: DateTime<Utc>) -> Option<StaleChange> {
+        let entry = self.entries.entry(target).or_insert(Entry {
+            last_result: None,
+            since: at,
+            stale: false,
+        });
+        entry.last_result = Some(at);
+        entry.since = entry.since.max(at);
+        let recovered = std::mem::replace(&mut entry.stale, false);
+        recovered.then_some(StaleChange {
+            target,
+            stale: false,
+            last_result: entry.last_result,
+            at,
+        })
+    }
+
+    /// Marks targets whose result is overdue as stale. `deadlines` lists every monitored
+    /// target with how long a result may take, or `None` if none is expected right now;
+    /// targets not listed are forgotten.
+    pub fn sweep(&mut self, now: DateTime<Utc>, deadlines: &[(SocketAddr, Option<Duration>)]) -> Vec<StaleChange> {
+        self.entries.retain(|addr, _| deadlines.iter().any(|(target, _)| target == addr));
+        let mut changes = Vec::new();
+        for (target, deadline) in deadlines {
+            let entry = self.entries.entry(*target).or_insert(Entry {
+                last_result: None,
+                since: now,
+                stale: false,
+            });
+            let stale = match deadline {
+                Some(deadline) => entry.stale || now - entry.since > chrono::Duration::from_std(*deadline).unwrap_or(chrono::Duration::MAX),
+                None => {
+                    entry.since = now;
+                    false
+                }
+            };
+            if stale != entry.stale {
+                entry.stale = stale;
+                changes.push(StaleChange {
+                    target: *target,
+                    stale,
+                    last_result: entry.last_result,
+                    at: now,
+                });
+            }
+        }
+        changes
+    }
+
+    pub fn is_stale(&self, target: SocketAddr) -> bool {
+        self.entries.get(&target).is_some_and(|entry| entry.stale)
+    }
+}
+
+/// Sweeps `monitor` for stale targets every few seconds until it stops. Runs apart from the
+/// scheduler, so a scheduler that hangs is noticed too.
+pub async fn watch(monitor: Arc<Monitor>, grace: Duration) {
+    let mut poll = tokio::time::interval(STALENESS_POLL);
+    while !monitor.is_stopping() {
+        poll.tick().await;
+        monitor.sweep_stale(grace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_targets_go_stale_without_results_and_recover_with_one() {
+        let a: SocketAddr = "10.0.0.1:80".parse().unwrap();
+        let b: SocketAddr = "10.0.0.2:80".parse().unwrap();
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let secs = |s| t0 + chrono::Duration::seconds(s);
+        let deadline = Some(Duration::from_secs(120));
+        let mut tracker = StalenessTracker::default();
+
+        assert!(tracker.sweep(t0, &[(a, deadline), (b, None)]).is_empty());
+        assert!(tracker.record(a, secs(60)).is_none());
+        assert!(tracker.sweep(secs(150), &[(a, deadline), (b, None)]).is_empty());
+
+        // `a` last reported at 60s, `b` is paused and so never stale.
+        let changes = tracker.sweep(secs(200), &[(a, deadline), (b, None)]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!((changes[0].target, changes[0].stale, changes[0].last_result), (a, true, Some(secs(60))));
+        assert!(tracker.is_stale(a) && !tracker.is_stale(b));
+        assert!(tracker.sweep(secs(300), &[(a, deadline), (b, None)]).is_empty());
+
+        // Once resumed, `b` gets a full deadline from then on.
+        assert!(tracker.sweep(secs(400), &[(a, deadline), (b, deadline)]).is_empty());
+        assert!(tracker.sweep(secs(521), &[(a, deadline), (b, deadline)])[0].stale);
+
+        let recovered = tracker.record(a, secs(530)).unwrap();
+        assert!(!recovered.stale);
+        assert!(!tracker.is_stale(a));
+        assert!(tracker.sweep(secs(540), &[(b, deadline)]).is_empty());
+        assert!(!tracker.is_stale(a));
+    }
+}
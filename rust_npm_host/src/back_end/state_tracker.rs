@@ -0,0 +1,259 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::check_result::CheckResult;
+use super::metadata::Metadata;
+use super::severity::Severity;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TargetState {
+    Up,
+    Down,
+}
+
+/// An announced up/down change of a target.
+#[derive(Debug, Clone, Serialize)]
+pub struct Transition {
+    pub target: String,
+    pub from: Option<TargetState>,
+    pub to: TargetState,
+    pub at: DateTime<Utc>,
+    /// For recoveries: how long the target was down.
+    pub downtime: Option<Duration>,
+    /// Correlation ID of the check run that caused this transition.
+    pub correlation_id: String,
+    /// The target's metadata, from the result that caused this transition.
+    #[serde(skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// Severity a script hook chose for the result that caused this transition.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub severity: Option<Severity>,
+}
+
+/// Controls when an outage and its recovery are announced, to keep very short blips out of
+/// the channels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecoveryRules {
+    /// Number of consecutive failed checks before the target counts as down.
+    pub down_after: u32,
+    /// Send a notification when the target comes back at all.
+    pub notify_on_recovery: bool,
+    /// Recoveries from outages shorter than this are not announced.
+    pub min_downtime: Duration,
+    /// Number of consecutive successful checks before the target counts as up again.
+    pub stable_checks: u32,
+}
+
+impl Default for RecoveryRules {
+    fn default() -> Self {
+        Self {
+            down_after: 1,
+            notify_on_recovery: true,
+            min_downtime: Duration::ZERO,
+            stable_checks: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Tracked {
+    /// `None` until the first result, or the first `down_after` failures, decide it.
+    state: Option<TargetState>,
+    /// First failure of the current outage, or of the failures counting towards one.
+    down_since: Option<DateTime<Utc>>,
+    up_streak: u32,
+    down_streak: u32,
+}
+
+/// Follows the up/down state of every target and decides which changes are worth announcing.
+#[derive(Debug, Default)]
+pub struct StateTracker {
+    default_rules: RecoveryRules,
+    per_target: HashMap<String, RecoveryRules>,
+    states: HashMap<String, Tracked>,
+}
+
+impl StateTracker {
+    pub fn new(default_rules: RecoveryRules) -> Self {
+        Self {
+            default_rules,
+            ..Self::default()
+        }
+    }
+
+    /// Rules for targets without their own.
+    pub fn set_default_rules(&mut self, rules: RecoveryRules) {
+        self.default_rules = rules;
+    }
+
+    /// Rules for `target` alone; `None` puts it back on the default rules.
+    pub fn set_rules(&mut self, target: &str, rules: Option<RecoveryRules>) {
+        match rules {
+            Some(rules) => self.per_target.insert(target.to_string(), rules),
+            None => self.per_target.remove(target),
+        };
+    }
+
+    /// Drops everything known about a target that is no longer monitored.
+    pub fn forget(&mut self, target: &str) {
+        self.states.remove(target);
+        self.per_target.remove(target);
+    }
+
+    pub fn state(&self, target: &str) -> Option<TargetState> {
+        self.states.get(target).and_then(|t| t.state)
+    }
+
+    /// Feeds one result in and returns the transition to announce, if any.
+    ///
+    /// A target counts as `Down` after `down_after` failed checks in a row, and stays
+    /// `Down` until it has passed `stable_checks` successful checks in a row. Once it is up
+    /// again the recovery is only returned when the outage, counted from its first failed
+    /// check, lasted at least `min_downtime`.
+    pub fn record(&mut self, result: &CheckResult) -> Option<Transition> {
+        let rules = self.per_target.get(&result.target).unwrap_or(&self.default_rules).clone();
+        let tracked = self.states.entry(result.target.clone()).or_insert(Tracked {
+            state: None,
+            down_since: None,
+            up_streak: 0,
+            down_streak: 0,
+        });
+        let transition = |from: Option<TargetState>, to: TargetState, downtime: Option<Duration>| Transition {
+            target: result.target.clone(),
+            from,
+            to,
+            at: result.timestamp,
+            downtime,
+            correlation_id: result.correlation_id.clone(),
+            metadata: result.metadata.clone(),
+            severity: result.severity,
+        };
+
+        if !result.success {
+            tracked.up_streak = 0;
+            if tracked.state == Some(TargetState::Down) {
+                return None;
+            }
+            if tracked.down_streak == 0 {
+                tracked.down_since = Some(result.timestamp);
+            }
+            tracked.down_streak += 1;
+            if tracked.down_streak < rules.down_after.max(1) {
+                return None;
+            }
+            // A target that is already down when we start watching it is worth knowing
+            // about too, hence `from: None`.
+            let from = tracked.state.replace(TargetState::Down);
+            tracked.down_streak = 0;
+            return Some(transition(from, TargetState::Down, None));
+        }
+
+        match tracked.state {
+            None | Some(TargetState::Up) => {
+                tracked.state = Some(TargetState::Up);
+                tracked.down_streak = 0;
+                tracked.down_since = None;
+                None
+            }
+            Some(TargetState::Down) => {
+                tracked.up_streak += 1;
+                if tracked.up_streak < rules.stable_checks.max(1) {
+                    return None;
+                }
+                tracked.state = Some(TargetState::Up);
+                tracked.up_streak = 0;
+                let downtime = tracked
+                    .down_since
+                    .take()
+                    .and_then(|since| (result.timestamp - since).to_std().ok())
+                    .unwrap_or_default();
+                if !rules.notify_on_recovery || downtime < rules.min_downtime {
+                    return None;
+                }
+                Some(transition(Some(TargetState::Down), TargetState::Up, Some(downtime)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+
+    fn result(seconds: i64, success: bool) -> CheckResult {
+        let mut r = if success {
+            CheckResult::success("db:5432", Duration::from_millis(3))
+        } else {
+            CheckResult::failure("db:5432", "refused")
+        };
+        r.timestamp = DateTime::<Utc>::UNIX_EPOCH + ChronoDuration::seconds(seconds);
+        r
+    }
+
+    #[test]
+    fn test_down_and_recovery_are_announced() {
+        let mut tracker = StateTracker::default();
+        assert!(tracker.record(&result(0, true)).is_none());
+        assert_eq!(tracker.record(&result(10, false)).unwrap().to, TargetState::Down);
+        assert!(tracker.record(&result(20, false)).is_none());
+        let recovery_result = result(30, true);
+        let recovery = tracker.record(&recovery_result).unwrap();
+        assert_eq!(recovery.to, TargetState::Up);
+        assert_eq!(recovery.correlation_id, recovery_result.correlation_id);
+        assert_eq!(recovery.downtime, Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_short_blip_recovery_is_suppressed() {
+        let mut tracker = StateTracker::new(RecoveryRules {
+            min_downtime: Duration::from_secs(60),
+            ..RecoveryRules::default()
+        });
+        tracker.record(&result(0, true));
+        assert!(tracker.record(&result(10, false)).is_some());
+        assert!(tracker.record(&result(20, true)).is_none());
+        assert_eq!(tracker.state("db:5432"), Some(TargetState::Up));
+    }
+
+    #[test]
+    fn test_recovery_requires_stable_checks() {
+        let mut tracker = StateTracker::default();
+        tracker.set_rules(
+            "db:5432",
+            Some(RecoveryRules {
+                stable_checks: 3,
+                ..RecoveryRules::default()
+            }),
+        );
+        tracker.record(&result(0, false));
+        assert!(tracker.record(&result(10, true)).is_none());
+        assert!(tracker.record(&result(20, true)).is_none());
+        // Flapping resets the streak.
+        assert!(tracker.record(&result(30, false)).is_none());
+        assert!(tracker.record(&result(40, true)).is_none());
+        assert!(tracker.record(&result(50, true)).is_none());
+        let recovery = tracker.record(&result(60, true)).unwrap();
+        assert_eq!(recovery.downtime, Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_outage_requires_consecutive_failures() {
+        let mut tracker = StateTracker::new(RecoveryRules {
+            down_after: 3,
+            ..RecoveryRules::default()
+        });
+        tracker.record(&result(0, true));
+        assert!(tracker.record(&result(10, false)).is_none());
+        assert!(tracker.record(&result(20, true)).is_none());
+        assert!(tracker.record(&result(30, false)).is_none());
+        assert!(tracker.record(&result(40, false)).is_none());
+        let down = tracker.record(&result(50, false)).unwrap();
+        assert_eq!((down.from, down.to), (Some(TargetState::Up), TargetState::Down));
+        // The outage counts from its first failure.
+        assert_eq!(tracker.record(&result(60, true)).unwrap().downtime, Some(Duration::from_secs(30)));
+    }
+}
@@ -0,0 +1,97 @@
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+use super::{StatusRow, StatusStore, StoreError};
+use crate::back_end::check_result::CheckResult;
+
+/// Rows kept in process memory only, for tests and runs that shouldn't leave anything
+/// behind. Clones share the same rows, like clones of a database pool.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    rows: Arc<RwLock<Vec<StatusRow>>>,
+}
+
+impl MemoryStore {
+    // Rows matching `keep`, newest first, at most `limit`.
+    fn newest(&self, limit: i64, keep: impl Fn(&StatusRow) -> bool) -> Vec<StatusRow> {
+        let mut rows: Vec<StatusRow> = self.rows.read().unwrap().iter().filter(|r| keep(r)).cloned().collect();
+        rows.sort_by_key(|r| std::cmp::Reverse(r.event_time));
+        rows.truncate(limit.max(0) as usize);
+        rows
+    }
+
+    // Results of the rows matching `keep`, oldest first.
+    fn results(&self, keep: impl Fn(&StatusRow) -> bool) -> Vec<CheckResult> {
+        let rows = self.rows.read().unwrap();
+        let mut matching: Vec<&StatusRow> = rows.iter().filter(|r| keep(r)).collect();
+        matching.sort_by_key(|r| r.event_time);
+        matching.into_iter().filter_map(StatusRow::to_result).collect()
+    }
+}
+
+impl StatusStore for MemoryStore {
+    async fn insert_result(&self, result: &CheckResult) -> Result<i32, StoreError> {
+        let mut status = StatusRow::from_result(result)?;
+        let mut rows = self.rows.write().unwrap();
+        status.id = rows.len() as i32 + 1;
+        rows.push(status);
+        Ok(rows.len() as i32)
+    }
+
+    async fn results_since(&self, since: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        Ok(self.results(|r| r.target.is_some() && r.event_time >= since))
+    }
+
+    async fn history_range(&self, target: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        Ok(self.results(|r| r.target.as_deref() == Some(target) && from <= r.event_time && r.event_time < to))
+    }
+
+    async fn recent_for_target(&self, target: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(self.newest(limit, |r| r.target.as_deref() == Some(target)))
+    }
+
+    async fn recent_for_agent(&self, agent: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(self.newest(limit, |r| r.agent_name == agent))
+    }
+
+    async fn latest_status(&self) -> Result<Vec<StatusRow>, StoreError> {
+        let mut latest: BTreeMap<(String, String), StatusRow> = BTreeMap::new();
+        for row in self.rows.read().unwrap().iter() {
+            let Some(target) = &row.target else { continue };
+            let key = (row.agent_name.clone(), target.clone());
+            if latest.get(&key).is_none_or(|newest| newest.event_time <= row.event_time) {
+                latest.insert(key, row.clone());
+            }
+        }
+        Ok(latest.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_range_and_latest() {
+        let store = MemoryStore::default();
+        let minute = |m: u32| Utc.with_ymd_and_hms(2026, 10, 17, 8, m, 0).unwrap();
+        for (m, ok) in [(0, true), (1, false), (2, true)] {
+            let mut result = match ok {
+                true => CheckResult::success("web:443", Duration::from_millis(10)),
+                false => CheckResult::failure("web:443", "connection refused"),
+            };
+            result.timestamp = minute(m);
+            store.insert_result(&result).await.unwrap();
+        }
+        let range = store.history_range("web:443", minute(1), minute(2)).await.unwrap();
+        assert_eq!(range.len(), 1);
+        assert!(!range[0].success);
+        let latest = store.latest_for_target("web:443").await.unwrap().unwrap();
+        assert_eq!(latest.timestamp, minute(2));
+        assert!(store.latest_for_target("db:5432").await.unwrap().is_none());
+        assert_eq!(store.latest_status().await.unwrap().len(), 1);
+    }
+}
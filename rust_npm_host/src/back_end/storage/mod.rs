@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::FromRow;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::check_result::CheckResult;
+use super::result_payload::{CheckPayload, StoredPayload};
+
+pub mod memory;
+pub mod postgres;
+pub mod sqlite;
+
+use memory::MemoryStore;
+use postgres::PostgresStore;
+use sqlite::SqliteStore;
+
+/// Errors of every backend; `Send` so stores can be used from spawned tasks.
+pub type StoreError = Box<dyn Error + Send + Sync>;
+
+/// Agent name for results of this instance that don't carry one.
+pub const LOCAL_AGENT: &str = "local";
+
+/// One row of `status_log_table`, the same in every backend.
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct StatusRow {
+    pub id: i32,
+    pub event_time: DateTime<Utc>,
+    pub agent_name: String,
+    pub status_ok: bool,
+    pub object_data: Option<JsonValue>,
+    /// Checked target; empty on rows written before results were stored per target.
+    pub target: Option<String>,
+}
+
+impl StatusRow {
+    /// A row for `result`; `id` is assigned by the database on insert.
+    pub fn from_result(result: &CheckResult) -> Result<Self, serde_json::Error> {
+        let mut status = Self {
+            id: 0,
+            event_time: result.timestamp,
+            agent_name: result.agent.clone().unwrap_or_else(|| LOCAL_AGENT.to_string()),
+            status_ok: result.success,
+            object_data: None,
+            target: Some(result.target.clone()),
+        };
+        status.set_payload(&StoredPayload::new(result, CheckPayload::from_result(result)))?;
+        Ok(status)
+    }
+
+    /// The versioned result payload in `object_data`, if the row has one.
+    pub fn payload(&self) -> Option<Result<StoredPayload, Box<dyn std::error::Error>>> {
+        self.object_data.as_ref().map(StoredPayload::from_json)
+    }
+
+    pub fn set_payload(&mut self, payload: &StoredPayload) -> Result<(), serde_json::Error> {
+        self.object_data = Some(payload.to_json()?);
+        Ok(())
+    }
+
+    /// Rebuilds the check result the row was stored from, including the steps and
+    /// artifacts of the payload. `None` for rows without a target.
+    pub fn to_result(&self) -> Option<CheckResult> {
+        let target = self.target.as_deref()?;
+        let payload = self.payload().and_then(Result::ok);
+        let error = payload.as_ref().and_then(|p| p.error.clone());
+        let mut result = match (self.status_ok, payload.as_ref().and_then(|p| p.latency_ms)) {
+            (true, latency) => {
+                CheckResult::success(target, Duration::from_secs_f64(latency.unwrap_or_default() / 1000.0))
+            }
+            (false, _) => CheckResult::failure(target, error.unwrap_or_else(|| "unknown error".to_string())),
+        };
+        result.timestamp = self.event_time;
+        if self.agent_name != LOCAL_AGENT {
+            result = result.with_agent(&self.agent_name);
+        }
+        if let Some(payload) = payload {
+            result.failure_kind = payload.failure_kind.or(result.failure_kind);
+            result.correlation_id = payload.correlation_id;
+            result.metadata = payload.metadata;
+            match payload.payload {
+                CheckPayload::Other { metrics } => result.metrics = metrics,
+                CheckPayload::Tcp { resolved_ip } => result.resolved_ip = resolved_ip,
+                CheckPayload::Composite { steps } => result.steps = steps,
+                CheckPayload::Browser { steps, artifacts } => {
+                    result.steps = steps;
+                    result.artifacts = artifacts;
+                }
+                CheckPayload::Http { .. } => {}
+            }
+        }
+        Some(result)
+    }
+}
+
+/// Where check results are kept. Every backend stores the same rows and answers the same
+/// queries, so the history and reports don't care which one is configured, and tests can
+/// use `MemoryStore`.
+pub trait StatusStore: Sync {
+    /// Stores a result and returns its row ID.
+    fn insert_result(&self, result: &CheckResult) -> impl Future<Output = Result<i32, StoreError>> + Send;
+
+    /// All results since `since`, oldest first.
+    fn results_since(&self, since: DateTime<Utc>) -> impl Future<Output = Result<Vec<CheckResult>, StoreError>> + Send;
+
+    /// Results of one target from `from` up to (not including) `to`, oldest first.
+    fn history_range(
+        &self,
+        target: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Vec<CheckResult>, StoreError>> + Send;
+
+    /// The newest result of one target, from any agent.
+    fn latest_for_target(&self, target: &str) -> impl Future<Output = Result<Option<CheckResult>, StoreError>> + Send {
+        async move {
+            let rows = self.recent_for_target(target, 1).await?;
+            Ok(rows.first().and_then(StatusRow::to_result))
+        }
+    }
+
+    /// The `limit` newest rows of one target, newest first.
+    fn recent_for_target(&self, target: &str, limit: i64) -> impl Future<Output = Result<Vec<StatusRow>, StoreError>> + Send;
+
+    /// The `limit` newest rows written by one agent, newest first.
+    fn recent_for_agent(&self, agent: &str, limit: i64) -> impl Future<Output = Result<Vec<StatusRow>, StoreError>> + Send;
+
+    /// The latest row of every (agent, target) pair: the current status as each agent
+    /// sees it.
+    fn latest_status(&self) -> impl Future<Output = Result<Vec<StatusRow>, StoreError>> + Send;
+}
+
+/// The configured backend: Postgres for shared installations, SQLite for a local file
+/// that needs no setup, memory for tests and throwaway runs.
+#[derive(Debug, Clone)]
+pub enum Storage {
+    Postgres(PostgresStore),
+    Sqlite(SqliteStore),
+    Memory(MemoryStore),
+}
+
+impl Storage {
+    /// Picks the backend by URL: `postgres://` or `postgresql://` for Postgres, `memory:`
+    /// for memory, and `sqlite://<path>` or a plain file path for SQLite.
+    pub async fn connect(url: &str) -> Result<Self, StoreError> {
+        if url.starts_with("postgres://") || url.starts_with("postgresql://") {
+            return Ok(Storage::Postgres(PostgresStore::connect(url).await?));
+        }
+        if url == "memory:" {
+            return Ok(Storage::Memory(MemoryStore::default()));
+        }
+        let path = url.strip_prefix("sqlite://").or_else(|| url.strip_prefix("sqlite:")).unwrap_or(url);
+        Ok(Storage::Sqlite(SqliteStore::open(path).await?))
+    }
+
+    /// The SQLite file used when no database is configured.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rust_npm").join("results.db"))
+    }
+}
+
+impl StatusStore for Storage {
+    async fn insert_result(&self, result: &CheckResult) -> Result<i32, StoreError> {
+        match self {
+            Storage::Postgres(store) => store.insert_result(result).await,
+            Storage::Sqlite(store) => store.insert_result(result).await,
+            Storage::Memory(store) => store.insert_result(result).await,
+        }
+    }
+
+    async fn results_since(&self, since: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        match self {
+            Storage::Postgres(store) => store.results_since(since).await,
+            Storage::Sqlite(store) => store.results_since(since).await,
+            Storage::Memory(store) => store.results_since(since).await,
+        }
+    }
+
+    async fn history_range(&self, target: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        match self {
+            Storage::Postgres(store) => store.history_range(target, from, to).await,
+            Storage::Sqlite(store) => store.history_range(target, from, to).await,
+            Storage::Memory(store) => store.history_range(target, from, to).await,
+        }
+    }
+
+    async fn recent_for_target(&self, target: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        match self {
+            Storage::Postgres(store) => store.recent_for_target(target, limit).await,
+            Storage::Sqlite(store) => store.recent_for_target(target, limit).await,
+            Storage::Memory(store) => store.recent_for_target(target, limit).await,
+        }
+    }
+
+    async fn recent_for_agent(&self, agent: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        match self {
+            Storage::Postgres(store) => store.recent_for_agent(agent, limit).await,
+            Storage::Sqlite(store) => store.recent_for_agent(agent, limit).await,
+            Storage::Memory(store) => store.recent_for_agent(agent, limit).await,
+        }
+    }
+
+    async fn latest_status(&self) -> Result<Vec<StatusRow>, StoreError> {
+        match self {
+            Storage::Postgres(store) => store.latest_status().await,
+            Storage::Sqlite(store) => store.latest_status().await,
+            Storage::Memory(store) => store.latest_status().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::check_result::{StepResult, StepStatus};
+
+    #[test]
+    fn test_results_round_trip_through_rows() {
+        let mut original = CheckResult::success("web:443", Duration::from_millis(42));
+        original.metrics.insert("status_code".to_string(), 200.0);
+        original.metadata.insert("cost_center".to_string(), "4711".to_string());
+        let row = StatusRow::from_result(&original).unwrap();
+        assert_eq!(row.agent_name, "local");
+        let restored = row.to_result().unwrap();
+        assert_eq!(restored.latency, Some(Duration::from_millis(42)));
+        assert_eq!(restored.metrics["status_code"], 200.0);
+        assert_eq!(restored.correlation_id, original.correlation_id);
+        assert_eq!(restored.metadata, original.metadata);
+        assert_eq!(restored.agent, None);
+
+        let failed = CheckResult::failure("db:5432", "connection refused").with_agent("branch-oslo");
+        let restored = StatusRow::from_result(&failed).unwrap().to_result().unwrap();
+        assert_eq!(restored.error.as_deref(), Some("connection refused"));
+        assert_eq!(restored.agent.as_deref(), Some("branch-oslo"));
+
+        let mut composite = CheckResult::failure("checkout", "step 2 failed");
+        composite.steps = vec![
+            StepResult {
+                name: "login".to_string(),
+                status: StepStatus::Passed,
+                latency: Some(Duration::from_millis(120)),
+                error: None,
+            },
+            StepResult {
+                name: "pay".to_string(),
+                status: StepStatus::Failed,
+                latency: None,
+                error: Some("timeout".to_string()),
+            },
+        ];
+        let restored = StatusRow::from_result(&composite).unwrap().to_result().unwrap();
+        assert_eq!(restored.steps, composite.steps);
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+use chrono::{DateTime, Utc}; // For the timestamp field in postgres
+use sqlx::PgPool;
+use sqlx::postgres::PgPoolOptions;
+
+use super::{StatusRow, StatusStore, StoreError};
+use crate::back_end::check_result::CheckResult;
+use crate::back_end::schema;
+
+/// Connections kept open by one instance; checks write one row each, so a handful is plenty.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+
+const ACQUIRE_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn insert_status_entry(pool: &PgPool, status_entry: &StatusRow) -> Result<i32, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO status_log_table (event_time, agent_name, status_ok, object_data, target)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id
+        "#,
+    )
+    .bind(status_entry.event_time)
+    .bind(&status_entry.agent_name)
+    .bind(status_entry.status_ok)
+    .bind(&status_entry.object_data) // This will be serialized to JSON/JSONB by sqlx
+    .bind(&status_entry.target)
+    .fetch_one(pool) // Fetches the single row returned by RETURNING id
+    .await
+}
+
+const COLUMNS: &str = "id, event_time, agent_name, status_ok, object_data, target";
+
+/// Check results in Postgres (`status_log_table`), behind a connection pool.
+#[derive(Debug, Clone)]
+pub struct PostgresStore {
+    pool: PgPool,
+}
+
+impl PostgresStore {
+    /// Opens a pool to `url` and brings the schema up to date, refusing databases written
+    /// by a newer build.
+    pub async fn connect(url: &str) -> Result<Self, StoreError> {
+        let pool = PgPoolOptions::new()
+            .max_connections(DEFAULT_MAX_CONNECTIONS)
+            .acquire_timeout(ACQUIRE_TIMEOUT)
+            .connect(url)
+            .await?;
+        schema::migrate(&pool).await.map_err(|e| e.to_string())?;
+        Ok(Self { pool })
+    }
+}
+
+impl StatusStore for PostgresStore {
+    async fn insert_result(&self, result: &CheckResult) -> Result<i32, StoreError> {
+        let status = StatusRow::from_result(result)?;
+        Ok(insert_status_entry(&self.pool, &status).await?)
+    }
+
+    async fn results_since(&self, since: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        let rows: Vec<StatusRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE target IS NOT NULL AND event_time >= $1 ORDER BY event_time",
+            COLUMNS
+        ))
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().filter_map(StatusRow::to_result).collect())
+    }
+
+    async fn history_range(&self, target: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        let rows: Vec<StatusRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE target = $1 AND event_time >= $2 AND event_time < $3 ORDER BY event_time",
+            COLUMNS
+        ))
+        .bind(target)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().filter_map(StatusRow::to_result).collect())
+    }
+
+    async fn recent_for_target(&self, target: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE target = $1 ORDER BY event_time DESC LIMIT $2",
+            COLUMNS
+        ))
+        .bind(target)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn recent_for_agent(&self, agent: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE agent_name = $1 ORDER BY event_time DESC LIMIT $2",
+            COLUMNS
+        ))
+        .bind(agent)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn latest_status(&self) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT DISTINCT ON (agent_name, target) {} FROM status_log_table
+             WHERE target IS NOT NULL ORDER BY agent_name, target, event_time DESC",
+            COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore] // Needs a database in DATABASE_URL
+    async fn test_insert_and_query() {
+        let store = PostgresStore::connect(&std::env::var("DATABASE_URL").unwrap()).await.unwrap();
+        let result = CheckResult::success("storage-test:1", Duration::from_millis(5));
+        store.insert_result(&result).await.unwrap();
+        let rows = store.recent_for_target("storage-test:1", 1).await.unwrap();
+        assert_eq!(rows[0].to_result().unwrap().correlation_id, result.correlation_id);
+        assert!(store.latest_status().await.unwrap().iter().any(|r| r.target.as_deref() == Some("storage-test:1")));
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions};
+use std::path::Path;
+use std::time::Duration;
+
+use super::{StatusRow, StatusStore, StoreError};
+use crate::back_end::check_result::CheckResult;
+
+/// SQLite allows one writer at a time; a few connections let reports read meanwhile.
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 4;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Schema migrations, applied in order and counted in `PRAGMA user_version`. Same rules
+/// as for Postgres: never edit a released entry, only append.
+const MIGRATIONS: &[&str] = &[
+    // 1: the status log of `storage::postgres`, with JSON as text. Times are RFC 3339 in
+    // UTC, which sorts correctly as text.
+    "CREATE TABLE IF NOT EXISTS status_log_table (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        event_time TEXT NOT NULL,
+        agent_name TEXT NOT NULL,
+        status_ok INTEGER NOT NULL,
+        object_data TEXT,
+        target TEXT
+    );
+    CREATE INDEX IF NOT EXISTS status_log_event_time_idx ON status_log_table (event_time);
+    CREATE INDEX IF NOT EXISTS status_log_target_idx ON status_log_table (target, event_time);
+    CREATE INDEX IF NOT EXISTS status_log_agent_idx ON status_log_table (agent_name, event_time)",
+];
+
+const COLUMNS: &str = "id, event_time, agent_name, status_ok, object_data, target";
+
+/// Check results in a local SQLite file; nothing to set up.
+#[derive(Debug, Clone)]
+pub struct SqliteStore {
+    pool: SqlitePool,
+}
+
+impl SqliteStore {
+    /// Opens `path`, creating the file and its directory if needed, and brings the schema
+    /// up to date.
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)?;
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(DEFAULT_MAX_CONNECTIONS)
+            .connect_with(options)
+            .await?;
+        migrate(&pool).await?;
+        Ok(Self { pool })
+    }
+}
+
+/// Brings the schema up to the latest migration; refuses files written by a newer build.
+async fn migrate(pool: &SqlitePool) -> Result<(), StoreError> {
+    let found: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+    if found > MIGRATIONS.len() as i64 {
+        return Err(format!("schema v{} is newer than this build (v{})", found, MIGRATIONS.len()).into());
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(found as usize) {
+        let mut tx = pool.begin().await?;
+        sqlx::raw_sql(migration).execute(&mut *tx).await?;
+        // PRAGMA takes no bound parameters; the version is our own number.
+        sqlx::raw_sql(&format!("PRAGMA user_version = {}", index + 1)).execute(&mut *tx).await?;
+        tx.commit().await?;
+    }
+    Ok(())
+}
+
+impl StatusStore for SqliteStore {
+    async fn insert_result(&self, result: &CheckResult) -> Result<i32, StoreError> {
+        let status = StatusRow::from_result(result)?;
+        Ok(sqlx::query_scalar(
+            "INSERT INTO status_log_table (event_time, agent_name, status_ok, object_data, target)
+             VALUES (?, ?, ?, ?, ?) RETURNING id",
+        )
+        .bind(status.event_time)
+        .bind(&status.agent_name)
+        .bind(status.status_ok)
+        .bind(&status.object_data)
+        .bind(&status.target)
+        .fetch_one(&self.pool)
+        .await?)
+    }
+
+    async fn results_since(&self, since: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        let rows: Vec<StatusRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE target IS NOT NULL AND event_time >= ? ORDER BY event_time",
+            COLUMNS
+        ))
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().filter_map(StatusRow::to_result).collect())
+    }
+
+    async fn history_range(&self, target: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<CheckResult>, StoreError> {
+        let rows: Vec<StatusRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE target = ? AND event_time >= ? AND event_time < ? ORDER BY event_time",
+            COLUMNS
+        ))
+        .bind(target)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.iter().filter_map(StatusRow::to_result).collect())
+    }
+
+    async fn recent_for_target(&self, target: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE target = ? ORDER BY event_time DESC LIMIT ?",
+            COLUMNS
+        ))
+        .bind(target)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn recent_for_agent(&self, agent: &str, limit: i64) -> Result<Vec<StatusRow>, StoreError> {
+        Ok(sqlx::query_as(&format!(
+            "SELECT {} FROM status_log_table WHERE agent_name = ? ORDER BY event_time DESC LIMIT ?",
+            COLUMNS
+        ))
+        .bind(agent)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?)
+    }
+
+    async fn latest_status(&self) -> Result<Vec<StatusRow>, StoreError> {
+        // SQLite has no DISTINCT ON; rank the rows of each pair instead.
+        Ok(sqlx::query_as(&format!(
+            "SELECT {columns} FROM (
+                 SELECT {columns}, ROW_NUMBER() OVER (PARTITION BY agent_name, target ORDER BY event_time DESC) AS rank
+                 FROM status_log_table WHERE target IS NOT NULL
+             ) WHERE rank = 1 ORDER BY agent_name, target",
+            columns = COLUMNS
+        ))
+        .fetch_all(&self.pool)
+        .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+
+    #[tokio::test]
+    async fn test_insert_and_query() {
+        let path = std::env::temp_dir().join(format!("rust_npm_test_{}.db", uuid::Uuid::new_v4()));
+        let store = SqliteStore::open(&path).await.unwrap();
+        let now = Utc::now();
+        let at = |minutes: i64, mut result: CheckResult| {
+            result.timestamp = now - ChronoDuration::minutes(minutes);
+            result
+        };
+        store.insert_result(&at(3, CheckResult::success("web:443", Duration::from_millis(40)))).await.unwrap();
+        let latest = at(1, CheckResult::failure("web:443", "connection refused"));
+        store.insert_result(&latest).await.unwrap();
+        store.insert_result(&at(2, CheckResult::success("db:5432", Duration::from_millis(3)).with_agent("oslo"))).await.unwrap();
+
+        let since = store.results_since(now - ChronoDuration::minutes(10)).await.unwrap();
+        assert_eq!(since.iter().map(|r| r.target.as_str()).collect::<Vec<_>>(), ["web:443", "db:5432", "web:443"]);
+        assert_eq!(since[0].latency, Some(Duration::from_millis(40)));
+
+        let rows = store.recent_for_target("web:443", 1).await.unwrap();
+        assert_eq!(rows[0].to_result().unwrap().correlation_id, latest.correlation_id);
+        assert_eq!(store.recent_for_agent("oslo", 10).await.unwrap().len(), 1);
+
+        let status = store.latest_status().await.unwrap();
+        assert_eq!(status.len(), 2);
+        assert!(status.iter().any(|r| r.target.as_deref() == Some("web:443") && !r.status_ok));
+
+        // Reopening finds the schema current and the data still there.
+        drop(store);
+        let store = SqliteStore::open(&path).await.unwrap();
+        assert_eq!(store.recent_for_target("web:443", 10).await.unwrap().len(), 2);
+        let _ = std::fs::remove_file(&path);
+    }
+}
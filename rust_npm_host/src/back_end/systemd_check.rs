@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use super::check_result::{CheckResult, new_correlation_id};
+use super::ssh::{SshPool, SshTarget};
+use super::target::{CheckSpec, MonitorTarget};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnitState {
+    Active,
+    /// The unit crashed and systemd is about to restart it.
+    Restarting,
+    Failed,
+    Inactive,
+    /// Still starting or stopping.
+    Transitioning,
+    NotFound,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitStatus {
+    pub unit: String,
+    pub active_state: String,
+    pub sub_state: String,
+    pub load_state: String,
+    pub restarts: u32,
+}
+
+impl UnitStatus {
+    pub fn state(&self) -> UnitState {
+        if self.load_state == "not-found" {
+            return UnitState::NotFound;
+        }
+        match (self.active_state.as_str(), self.sub_state.as_str()) {
+            ("active", _) => UnitState::Active,
+            ("activating", "auto-restart") => UnitState::Restarting,
+            ("failed", _) => UnitState::Failed,
+            ("inactive", _) => UnitState::Inactive,
+            _ => UnitState::Transitioning,
+        }
+    }
+}
+
+/// Parses `systemctl show --property=Id,LoadState,ActiveState,SubState,NRestarts` output.
+/// systemd prints one block of `Key=Value` lines per unit, separated by blank lines.
+pub fn parse_systemctl_show(output: &str) -> Vec<UnitStatus> {
+    output
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let mut status = UnitStatus {
+                unit: String::new(),
+                active_state: String::new(),
+                sub_state: String::new(),
+                load_state: String::new(),
+                restarts: 0,
+            };
+            for line in block.lines() {
+                let Some((key, value)) = line.split_once('=') else { continue };
+                let value = value.trim().to_string();
+                match key.trim() {
+                    "Id" => status.unit = value,
+                    "ActiveState" => status.active_state = value,
+                    "SubState" => status.sub_state = value,
+                    "LoadState" => status.load_state = value,
+                    "NRestarts" => status.restarts = value.parse().unwrap_or(0),
+                    _ => {}
+                }
+            }
+            status
+        })
+        .collect()
+}
+
+/// Checks that every unit in `units` is active on the host. Produces one result per unit
+/// so each can be alerted on separately; all units are queried over one SSH command and
+/// share its correlation ID.
+pub async fn check_units(pool: &SshPool, target: &SshTarget, units: &[String], timeout: Duration) -> Vec<CheckResult> {
+    let run_id = new_correlation_id();
+    query_units(pool, target, units, timeout)
+        .await
+        .into_iter()
+        .map(|result| result.in_run(&run_id))
+        .collect()
+}
+
+async fn query_units(pool: &SshPool, target: &SshTarget, units: &[String], timeout: Duration) -> Vec<CheckResult> {
+    let start = Instant::now();
+    let quoted: Vec<String> = units.iter().map(|u| shell_quote(u)).collect();
+    let command = format!(
+        "systemctl show --no-pager --property=Id,LoadState,ActiveState,SubState,NRestarts {}",
+        quoted.join(" ")
+    );
+
+    let output = match pool.run(target, &command, timeout).await {
+        Ok(output) if output.exit_code == Some(0) => output,
+        Ok(output) => {
+            let error = format!("systemctl exited with {:?}: {}", output.exit_code, output.stderr.trim());
+            return units.iter().map(|u| CheckResult::failure(&unit_target(target, u), error.clone())).collect();
+        }
+        Err(e) => {
+            return units.iter().map(|u| CheckResult::failure(&unit_target(target, u), e.to_string())).collect();
+        }
+    };
+    let elapsed = start.elapsed();
+
+    // systemctl answers in the order the units were requested.
+    let statuses = parse_systemctl_show(&output.stdout);
+    units
+        .iter()
+        .enumerate()
+        .map(|(i, unit)| {
+            let name = unit_target(target, unit);
+            let Some(status) = statuses.get(i) else {
+                return CheckResult::failure(&name, "unit missing from systemctl output");
+            };
+            match status.state() {
+                UnitState::Active => CheckResult::success(&name, elapsed),
+                state => CheckResult {
+                    latency: Some(elapsed),
+                    ..CheckResult::failure(
+                        &name,
+                        format!(
+                            "{} is {:?} ({}/{}, {} restarts)",
+                            unit, state, status.active_state, status.sub_state, status.restarts
+                        ),
+                    )
+                },
+            }
+        })
+        .collect()
+}
+
+/// Runs the unit checks of a `CheckSpec::Systemd` target as one result, with a step per
+/// unit.
+pub async fn check_target(pool: &SshPool, target: &MonitorTarget, timeout: Duration) -> CheckResult {
+    let name = target.address.to_string();
+    let Some(CheckSpec::Systemd { user, identity_file, units }) = target.spec() else {
+        return CheckResult::failure(&name, "systemd check without SSH settings");
+    };
+    if units.is_empty() {
+        return CheckResult::failure(&name, "systemd check without units to check");
+    }
+    let host = SshTarget::for_target(target, user, identity_file.clone());
+    CheckResult::from_steps(&name, check_units(pool, &host, units, timeout).await)
+}
+
+fn unit_target(target: &SshTarget, unit: &str) -> String {
+    format!("{}/systemd/{}", target.host, unit)
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "Id=nginx.service\nLoadState=loaded\nActiveState=active\nSubState=running\nNRestarts=0\n\n\
+Id=worker.service\nLoadState=loaded\nActiveState=activating\nSubState=auto-restart\nNRestarts=17\n\n\
+Id=backup.service\nLoadState=loaded\nActiveState=failed\nSubState=failed\nNRestarts=0\n\n\
+Id=typo.service\nLoadState=not-found\nActiveState=inactive\nSubState=dead\nNRestarts=0\n";
+
+    #[test]
+    fn test_parse_and_classify_units() {
+        let units = parse_systemctl_show(OUTPUT);
+        assert_eq!(units.len(), 4);
+        assert_eq!(units[0].state(), UnitState::Active);
+        assert_eq!(units[1].state(), UnitState::Restarting);
+        assert_eq!(units[1].restarts, 17);
+        assert_eq!(units[2].state(), UnitState::Failed);
+        assert_eq!(units[3].state(), UnitState::NotFound);
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("a'b"), "'a'\\''b'");
+    }
+}
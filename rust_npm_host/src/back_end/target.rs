@@ -0,0 +1,285 @@
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::composite::Step;
+use super::config::AlertSettings;
+use super::disk_check::DiskCheckConfig;
+use super::metadata::Metadata;
+use super::monitor::CheckKind;
+use super::tunnel::TunnelConfig;
+
+/// Settings of the check kinds that need more than the target's address. Only used when
+/// it matches the target's `check`; without one, the kind's defaults apply.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CheckSpec {
+    Http {
+        url: String,
+        /// Status that counts as up; any 2xx/3xx when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expect_status: Option<u16>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        body_contains: Option<String>,
+    },
+    Browser {
+        url: String,
+        /// Element that marks the page as usable; the load event when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        selector: Option<String>,
+        /// Whether console errors and failed requests during the load fail the check.
+        #[serde(default)]
+        fail_on_errors: bool,
+    },
+    /// A Windows service's state and the host's event log over WinRM (see `windows_check`),
+    /// on the target's port: 5986 for HTTPS, usually 5985 otherwise.
+    Windows {
+        user: String,
+        /// Environment variable holding the user's password.
+        password_env: String,
+        /// Services that must be running, by short name (e.g. "Spooler").
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        services: Vec<String>,
+        /// Event log (e.g. "System") that must have no errors in the last `lookback_minutes`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        event_log: Option<String>,
+        #[serde(default = "default_lookback_minutes")]
+        lookback_minutes: u32,
+    },
+    /// Systemd units that must be active, read over SSH (see `systemd_check`) on the
+    /// target's port.
+    Systemd {
+        user: String,
+        /// Private key; the agent or ~/.ssh defaults when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity_file: Option<PathBuf>,
+        units: Vec<String>,
+    },
+    /// Usage of every real file system, read with `df` over SSH (see `disk_check`) on the
+    /// target's port.
+    Disk {
+        user: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        identity_file: Option<PathBuf>,
+        /// 90% of blocks and inodes everywhere by default.
+        #[serde(default)]
+        limits: DiskCheckConfig,
+    },
+    /// A known object in an S3-compatible bucket (see `s3_check`), with keys from the
+    /// environment.
+    S3 {
+        /// e.g. "https://s3.eu-west-1.amazonaws.com"; the target's host and port when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        endpoint: Option<String>,
+        region: String,
+        bucket: String,
+        object_key: String,
+        access_key_env: String,
+        secret_key_env: String,
+        /// Also write and delete a temporary object.
+        #[serde(default)]
+        write_round_trip: bool,
+    },
+    /// An API's rate-limit headers (see `rate_limit`), warning before its quota runs out.
+    ApiQuota {
+        url: String,
+        /// Environment variable holding a bearer token for the API.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token_env: Option<String>,
+    },
+    /// A user journey of TCP and HTTP steps, see `composite`.
+    Composite { steps: Vec<Step> },
+    /// A check from a WebAssembly plugin (see `wasm_plugin`), by the name in its manifest.
+    Plugin {
+        name: String,
+        /// Passed to the plugin as is; checked against its manifest's schema.
+        #[serde(default)]
+        config: serde_json::Value,
+    },
+}
+
+impl CheckSpec {
+    pub fn kind(&self) -> CheckKind {
+        match self {
+            CheckSpec::Http { .. } => CheckKind::Http,
+            CheckSpec::Browser { .. } => CheckKind::Browser,
+            CheckSpec::Windows { .. } => CheckKind::Windows,
+            CheckSpec::Systemd { .. } => CheckKind::Systemd,
+            CheckSpec::Disk { .. } => CheckKind::Disk,
+            CheckSpec::S3 { .. } => CheckKind::S3,
+            CheckSpec::ApiQuota { .. } => CheckKind::ApiQuota,
+            CheckSpec::Composite { .. } => CheckKind::Composite,
+            CheckSpec::Plugin { .. } => CheckKind::Plugin,
+        }
+    }
+}
+
+fn default_lookback_minutes() -> u32 {
+    15
+}
+
+/// How often targets without an interval of their own are checked.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// One target with its own check settings. Unset timeout and interval fall back to the
+/// monitor's defaults, so changing those still affects every target that wasn't tuned.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorTarget {
+    pub address: SocketAddr,
+    /// The host name the target was added by, if not an IP address; `address::re_resolve`
+    /// follows its DNS records.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default)]
+    pub check: CheckKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spec: Option<CheckSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "millis")]
+    pub timeout: Option<Duration>,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "millis")]
+    pub interval: Option<Duration>,
+    /// Extra attempts after a failed check before the failure is reported, to ride out
+    /// a single dropped packet.
+    #[serde(default)]
+    pub retries: u32,
+    /// User or team responsible for the target, see `ownership::OwnerDirectory`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Copied into every result of the target, see `metadata::Metadata`.
+    #[serde(default, skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    /// SSH forward that TCP and HTTP checks go through, for targets only reachable from
+    /// behind a bastion.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tunnel: Option<TunnelConfig>,
+    /// `[socks]` proxy that TCP and HTTP checks go through, to see the target from its site.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    /// When outages and recoveries of this target are announced, instead of `[alerts]`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertSettings>,
+}
+
+impl MonitorTarget {
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            host: None,
+            check: CheckKind::default(),
+            spec: None,
+            timeout: None,
+            interval: None,
+            retries: 0,
+            owner: None,
+            metadata: Metadata::new(),
+            tunnel: None,
+            proxy: None,
+            alerts: None,
+        }
+    }
+
+    pub fn with_check(mut self, check: CheckKind) -> Self {
+        self.check = check;
+        self
+    }
+
+    /// Sets the check settings and the kind they belong to.
+    pub fn with_spec(mut self, spec: CheckSpec) -> Self {
+        self.check = spec.kind();
+        self.spec = Some(spec);
+        self
+    }
+
+    /// The settings for the target's check, if they are for its current kind.
+    pub fn spec(&self) -> Option<&CheckSpec> {
+        self.spec.as_ref().filter(|spec| spec.kind() == self.check)
+    }
+
+    /// The URL HTTP, browser and API quota checks request: the spec's, or else the target's host name
+    /// (or address), over https on port 443 and http otherwise.
+    pub fn url(&self) -> String {
+        match self.spec() {
+            Some(CheckSpec::Http { url, .. } | CheckSpec::Browser { url, .. } | CheckSpec::ApiQuota { url, .. }) => url.clone(),
+            Some(
+                CheckSpec::Windows { .. }
+                | CheckSpec::Systemd { .. }
+                | CheckSpec::Disk { .. }
+                | CheckSpec::S3 { .. }
+                | CheckSpec::Composite { .. }
+                | CheckSpec::Plugin { .. },
+            )
+            | None => {
+                let scheme = if self.address.port() == 443 { "https" } else { "http" };
+                match &self.host {
+                    Some(host) => format!("{}://{}:{}/", scheme, host, self.address.port()),
+                    None => format!("{}://{}/", scheme, self.address),
+                }
+            }
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    pub fn with_owner(mut self, owner: &str) -> Self {
+        self.owner = Some(owner.to_string());
+        self
+    }
+
+    pub fn with_metadata(mut self, key: &str, value: &str) -> Self {
+        self.metadata.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Whether every setting is the default, i.e. nothing needs to be stored for it.
+    pub fn is_plain(&self) -> bool {
+        *self == Self::new(self.address)
+    }
+}
+
+// Durations as whole milliseconds, which reads better in hand-edited files than serde's
+// `{secs, nanos}`.
+pub(crate) mod millis {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(d) => serializer.serialize_u64(d.as_millis() as u64),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_serialize_as_millis() {
+        let target = MonitorTarget::new("10.0.0.1:53".parse().unwrap())
+            .with_check(CheckKind::Udp)
+            .with_interval(Duration::from_secs(15))
+            .with_retries(2)
+            .with_metadata("cmdb_id", "CI0042");
+        let json = serde_json::to_value(&target).unwrap();
+        assert_eq!(json["interval"], 15000);
+        assert!(json.get("timeout").is_none());
+        assert_eq!(json["metadata"]["cmdb_id"], "CI0042");
+        assert_eq!(serde_json::from_value::<MonitorTarget>(json).unwrap(), target);
+        assert!(MonitorTarget::new(target.address).is_plain());
+        assert!(!target.is_plain());
+    }
+}
@@ -0,0 +1,328 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::csv_import::parse_interval;
+use super::monitor::{CheckKind, Monitor};
+use super::target::MonitorTarget;
+
+/// One host of the inventory, as exported by the CMDB or discovery.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InventoryHost {
+    pub name: String,
+    /// Address to check; the name is resolved when this is missing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl InventoryHost {
+    fn check_host(&self) -> &str {
+        self.address.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// The inventory file: `[[hosts]]` entries in TOML, or the same as JSON.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Inventory {
+    #[serde(default)]
+    pub hosts: Vec<InventoryHost>,
+}
+
+impl Inventory {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        load_file(path)
+    }
+}
+
+fn load_file<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let parsed = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&content).map_err(|e| e.to_string())
+    };
+    Ok(parsed.map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Condition {
+    Equals(String, String),
+    NotEquals(String, String),
+    Has(String),
+    Missing(String),
+}
+
+/// Which hosts a rule applies to: tag conditions that must all hold, separated by commas,
+/// e.g. `role=web, env!=staging, !decommissioned`. A bare `key` requires the tag to exist.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selector(Vec<Condition>);
+
+impl Selector {
+    pub fn matches(&self, host: &InventoryHost) -> bool {
+        self.0.iter().all(|condition| match condition {
+            Condition::Equals(key, value) => host.tags.get(key) == Some(value),
+            Condition::NotEquals(key, value) => host.tags.get(key) != Some(value),
+            Condition::Has(key) => host.tags.contains_key(key),
+            Condition::Missing(key) => !host.tags.contains_key(key),
+        })
+    }
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut conditions = Vec::new();
+        for part in s.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let condition = if let Some((key, value)) = part.split_once("!=") {
+                Condition::NotEquals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some((key, value)) = part.split_once('=') {
+                Condition::Equals(key.trim().to_string(), value.trim().to_string())
+            } else if let Some(key) = part.strip_prefix('!') {
+                Condition::Missing(key.trim().to_string())
+            } else {
+                Condition::Has(part.to_string())
+            };
+            let key = match &condition {
+                Condition::Equals(key, _) | Condition::NotEquals(key, _) | Condition::Has(key) | Condition::Missing(key) => key,
+            };
+            if key.is_empty() || key.contains(char::is_whitespace) {
+                return Err(format!("'{}' is not a tag condition", part));
+            }
+            conditions.push(condition);
+        }
+        Ok(Selector(conditions))
+    }
+}
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn deserialize_interval<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Duration>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_interval(&s))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+/// "For every inventory host matching `match`, check `port` with `check`."
+#[derive(Debug, Clone, Deserialize)]
+pub struct TargetRule {
+    pub name: String,
+    #[serde(rename = "match")]
+    pub selector: Selector,
+    pub port: u16,
+    #[serde(default)]
+    pub check: CheckKind,
+    /// `30s`, `5m`, ...; the monitor's default when unset.
+    #[serde(default, deserialize_with = "deserialize_interval")]
+    pub interval: Option<Duration>,
+    #[serde(default)]
+    pub retries: u32,
+    /// Group (service) of the generated targets; the rule's name when unset.
+    #[serde(default)]
+    pub group: Option<String>,
+}
+
+impl TargetRule {
+    pub fn group(&self) -> &str {
+        self.group.as_deref().unwrap_or(&self.name)
+    }
+
+    fn monitor_target(&self, address: SocketAddr) -> MonitorTarget {
+        let target = MonitorTarget::new(address).with_check(self.check).with_retries(self.retries);
+        match self.interval {
+            Some(interval) => target.with_interval(interval),
+            None => target,
+        }
+    }
+}
+
+/// The rules file: `[[rules]]` entries.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RuleSet {
+    #[serde(default)]
+    pub rules: Vec<TargetRule>,
+}
+
+impl RuleSet {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        load_file(path)
+    }
+
+    /// Every (rule, host) pair the inventory currently calls for.
+    pub fn matches<'a>(&'a self, inventory: &'a Inventory) -> Vec<(&'a TargetRule, &'a InventoryHost)> {
+        self.rules
+            .iter()
+            .flat_map(|rule| inventory.hosts.iter().filter(|host| rule.selector.matches(host)).map(move |host| (rule, host)))
+            .collect()
+    }
+}
+
+/// What one evaluation changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncSummary {
+    pub added: Vec<SocketAddr>,
+    pub removed: Vec<SocketAddr>,
+    /// Hosts that could not be turned into a target, with the reason.
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for SyncSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} targets added, {} removed", self.added.len(), self.removed.len())?;
+        for error in &self.errors {
+            write!(f, "\n  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the targets generated by a rule set in line with the inventory. Only targets it
+/// created (or found under the rule's group on startup) are ever removed, so targets added
+/// by hand are left alone.
+#[derive(Debug, Default)]
+pub struct TargetGenerator {
+    // (rule name, inventory host name) -> generated target
+    owned: HashMap<(String, String), SocketAddr>,
+}
+
+impl TargetGenerator {
+    /// Adds targets for new matches and removes those whose host no longer matches. A host
+    /// that fails to resolve keeps its existing target, so a DNS hiccup causes no churn.
+    pub async fn sync(&mut self, monitor: &Monitor, rules: &RuleSet, inventory: &Inventory) -> SyncSummary {
+        let mut summary = SyncSummary::default();
+        let mut wanted = HashMap::new();
+        for (rule, host) in rules.matches(inventory) {
+            let key = (rule.name.clone(), host.name.clone());
+            if let Some(addr) = self.owned.get(&key) {
+                wanted.insert(key, *addr);
+                continue;
+            }
+            let addr = match tokio::net::lookup_host((host.check_host(), rule.port)).await.map(|mut a| a.next()) {
+                Ok(Some(addr)) => addr,
+                Ok(None) => {
+                    summary.errors.push(format!("{}: {} has no addresses", rule.name, host.name));
+                    continue;
+                }
+                Err(e) => {
+                    summary.errors.push(format!("{}: cannot resolve {}: {}", rule.name, host.name, e));
+                    continue;
+                }
+            };
+            if monitor.targets().contains(&addr) {
+                // Ours from a previous run if it sits in the rule's group; otherwise someone
+                // added it by hand and it stays theirs.
+                if monitor.group(addr).as_deref() == Some(rule.group()) {
+                    wanted.insert(key, addr);
+                }
+                continue;
+            }
+            match monitor.add_monitor_target(rule.monitor_target(addr)) {
+                Ok(()) => {
+                    monitor.set_group(addr, Some(rule.group().to_string())).ok();
+                    summary.added.push(addr);
+                    wanted.insert(key, addr);
+                }
+                Err(e) => summary.errors.push(format!("{}: {}", rule.name, e)),
+            }
+        }
+        for (key, addr) in &self.owned {
+            // Two rules may generate the same address; keep it while either still wants it.
+            if !wanted.contains_key(key) && !wanted.values().any(|a| a == addr) && monitor.remove_target(*addr).is_ok() {
+                summary.removed.push(*addr);
+            }
+        }
+        self.owned = wanted;
+        summary
+    }
+}
+
+/// Re-reads the rules and inventory every `interval` and applies them, until the task is
+/// dropped. A file that fails to load skips that round instead of removing everything.
+pub async fn maintain(monitor: Arc<Monitor>, rules_path: PathBuf, inventory_path: PathBuf, interval: Duration) {
+    let mut generator = TargetGenerator::default();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        // As a String: `Box<dyn Error>` isn't `Send` and this lives across the sync.
+        let loaded = RuleSet::load(&rules_path)
+            .and_then(|rules| Ok((rules, Inventory::load(&inventory_path)?)))
+            .map_err(|e| e.to_string());
+        match loaded {
+            Ok((rules, inventory)) => {
+                let summary = generator.sync(&monitor, &rules, &inventory).await;
+                if summary != SyncSummary::default() {
+                    println!("Target rules: {}", summary);
+                }
+            }
+            Err(e) => eprintln!("Target rules not applied: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::event_bus::EventBus;
+
+    #[test]
+    fn test_selector_conditions() {
+        let host: InventoryHost = toml::from_str("name = \"web1\"\ntags = { role = \"web\", env = \"prod\" }").unwrap();
+        let matches = |s: &str| s.parse::<Selector>().unwrap().matches(&host);
+        assert!(matches("role=web"));
+        assert!(matches("role = web, env!=staging, !decommissioned"));
+        assert!(matches("env"));
+        assert!(!matches("role=web,env=staging"));
+        assert!(!matches("rack"));
+        assert!("role=web, bad key".parse::<Selector>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_targets_follow_the_inventory() {
+        let rules: RuleSet = toml::from_str(
+            "[[rules]]\nname = \"web-https\"\nmatch = \"role=web\"\nport = 443\ninterval = \"30s\"\n",
+        )
+        .unwrap();
+        let host = |name: &str, address: &str, role: &str| InventoryHost {
+            name: name.to_string(),
+            address: Some(address.to_string()),
+            tags: BTreeMap::from([("role".to_string(), role.to_string())]),
+        };
+        let mut inventory = Inventory {
+            hosts: vec![host("web1", "10.0.0.1", "web"), host("db1", "10.0.0.9", "db")],
+        };
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let manual: SocketAddr = "10.0.0.2:443".parse().unwrap();
+        monitor.add_monitor_target(MonitorTarget::new(manual)).unwrap();
+        let mut generator = TargetGenerator::default();
+
+        let summary = generator.sync(&monitor, &rules, &inventory).await;
+        let web1: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        assert_eq!(summary.added, vec![web1]);
+        assert_eq!(monitor.group(web1).as_deref(), Some("web-https"));
+        assert_eq!(monitor.monitor_target(web1).unwrap().interval, Some(Duration::from_secs(30)));
+
+        // web2 has the address of the hand-made target: it is left alone either way.
+        inventory.hosts = vec![host("web2", "10.0.0.2", "web")];
+        let summary = generator.sync(&monitor, &rules, &inventory).await;
+        assert_eq!(summary.removed, vec![web1]);
+        assert!(summary.added.is_empty());
+        inventory.hosts.clear();
+        generator.sync(&monitor, &rules, &inventory).await;
+        assert_eq!(monitor.targets(), vec![manual]);
+    }
+}
@@ -0,0 +1,479 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Map, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::check_result::CheckResult;
+use super::csv_import::parse_interval;
+use super::event_bus::MonitorEvent;
+use super::monitor::Monitor;
+use super::notify::{Notification, SharedStormGuard};
+use super::severity::Severity;
+use super::state_tracker::TargetState;
+use super::webhook::StateChange;
+
+/// Metadata key giving the severity of a target's outages; targets without it are critical.
+pub const SEVERITY_METADATA_KEY: &str = "severity";
+
+// How often open incidents are looked at for tickets that are due.
+const TICKET_POLL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TicketSystem {
+    Jira,
+    ServiceNow,
+}
+
+/// Where and when to open tickets for outages, read from TOML:
+///
+/// ```text
+/// system = "jira"
+/// url = "https://example.atlassian.net"
+/// user = "noc-bot@example.com"
+/// token_env = "JIRA_TOKEN"
+/// project = "OPS"
+/// open_after = "5m"
+/// min_severity = "warning"
+///
+/// [fields]
+/// team = "customfield_10010"
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct TicketConfig {
+    pub system: TicketSystem,
+    /// Base URL of the Jira site or ServiceNow instance.
+    pub url: String,
+    pub user: String,
+    /// Environment variable holding the API token (Jira) or password (ServiceNow).
+    pub token_env: String,
+    /// Jira project key.
+    #[serde(default)]
+    pub project: String,
+    #[serde(default = "default_issue_type")]
+    pub issue_type: String,
+    /// Jira transition that resolves an issue.
+    #[serde(default = "default_resolve_transition")]
+    pub resolve_transition: String,
+    /// ServiceNow close code of resolved incidents.
+    #[serde(default = "default_close_code")]
+    pub close_code: String,
+    /// How long a target has to be down before a ticket is opened.
+    #[serde(default = "default_open_after", deserialize_with = "interval")]
+    pub open_after: ChronoDuration,
+    /// Outages of targets below this severity never get a ticket.
+    #[serde(default = "default_min_severity")]
+    pub min_severity: Severity,
+    /// Target metadata key -> ticket field the value is copied into.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+}
+
+fn default_issue_type() -> String {
+    "Incident".to_string()
+}
+
+fn default_resolve_transition() -> String {
+    "Done".to_string()
+}
+
+fn default_close_code() -> String {
+    "Solved (Permanently)".to_string()
+}
+
+fn default_open_after() -> ChronoDuration {
+    ChronoDuration::minutes(5)
+}
+
+fn default_min_severity() -> Severity {
+    Severity::Warning
+}
+
+fn interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<ChronoDuration, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_interval(&value)
+        .and_then(|d| ChronoDuration::from_std(d).map_err(|e| e.to_string()))
+        .map_err(serde::de::Error::custom)
+}
+
+impl TicketConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// The request body that opens a ticket for the outage `change`.
+    pub fn open_body(&self, change: &StateChange, severity: Severity) -> JsonValue {
+        let summary = format!("{} is DOWN", change.target);
+        let mut description = format!("{}\nDown since {}\nSeverity: {}", change.message, change.at.to_rfc3339(), severity);
+        if !change.correlation_id.is_empty() {
+            description.push_str(&format!("\nCorrelation ID: {}", change.correlation_id));
+        }
+        for (key, value) in &change.metadata {
+            description.push_str(&format!("\n{}: {}", key, value));
+        }
+        let mut fields = Map::new();
+        for (key, field) in &self.fields {
+            if let Some(value) = change.metadata.get(key) {
+                fields.insert(field.clone(), json!(value));
+            }
+        }
+        match self.system {
+            TicketSystem::Jira => {
+                fields.insert("project".to_string(), json!({ "key": self.project }));
+                fields.insert("issuetype".to_string(), json!({ "name": self.issue_type }));
+                fields.insert("summary".to_string(), json!(summary));
+                fields.insert("description".to_string(), json!(description));
+                fields.insert("labels".to_string(), json!(["rust_npm"]));
+                json!({ "fields": fields })
+            }
+            TicketSystem::ServiceNow => {
+                // 1 is the most urgent in ServiceNow.
+                let urgency = match severity {
+                    Severity::Critical => "1",
+                    Severity::Warning => "2",
+                    Severity::Low | Severity::Info => "3",
+                };
+                fields.insert("short_description".to_string(), json!(summary));
+                fields.insert("description".to_string(), json!(description));
+                fields.insert("urgency".to_string(), json!(urgency));
+                fields.insert("impact".to_string(), json!(urgency));
+                fields.insert("correlation_id".to_string(), json!(change.correlation_id));
+                JsonValue::Object(fields)
+            }
+        }
+    }
+}
+
+/// A change to a ticket that is already open.
+#[derive(Debug, Clone)]
+pub enum TicketAction {
+    Comment { ticket: String, text: String },
+    Resolve { ticket: String, text: String },
+}
+
+#[derive(Debug, Clone)]
+struct Incident {
+    change: StateChange,
+    severity: Severity,
+    ticket: Option<String>,
+}
+
+/// Decides when outages get a ticket, which ticket later changes go to and when it is
+/// resolved. Outages that recover within `open_after` never get one.
+#[derive(Debug)]
+pub struct TicketTracker {
+    open_after: ChronoDuration,
+    min_severity: Severity,
+    incidents: HashMap<String, Incident>,
+}
+
+impl TicketTracker {
+    pub fn new(open_after: ChronoDuration, min_severity: Severity) -> Self {
+        Self {
+            open_after,
+            min_severity,
+            incidents: HashMap::new(),
+        }
+    }
+
+    /// Notes an up/down change; returns the resolution if the outage had a ticket.
+    pub fn transition(&mut self, change: StateChange) -> Option<TicketAction> {
+        match change.new_state {
+            TargetState::Down => {
+                let severity = change
+                    .metadata
+                    .get(SEVERITY_METADATA_KEY)
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(Severity::Critical);
+                if severity >= self.min_severity {
+                    self.incidents.insert(change.target.clone(), Incident { change, severity, ticket: None });
+                }
+                None
+            }
+            TargetState::Up => {
+                let ticket = self.incidents.remove(&change.target)?.ticket?;
+                let text = match change.downtime_secs {
+                    Some(secs) => format!("{} is UP again after {:.0}s", change.target, secs),
+                    None => format!("{} is UP again", change.target),
+                };
+                Some(TicketAction::Resolve { ticket, text })
+            }
+        }
+    }
+
+    /// Outages to open a ticket for: those that have lasted `open_after` and have none yet.
+    pub fn due(&self, now: DateTime<Utc>) -> Vec<(StateChange, Severity)> {
+        self.incidents
+            .values()
+            .filter(|incident| incident.ticket.is_none() && now - incident.change.at >= self.open_after)
+            .map(|incident| (incident.change.clone(), incident.severity))
+            .collect()
+    }
+
+    /// Records the ticket opened for the outage of `target`.
+    pub fn opened(&mut self, target: &str, ticket: String) {
+        if let Some(incident) = self.incidents.get_mut(target) {
+            incident.ticket = Some(ticket);
+        }
+    }
+
+    /// A comment on the ticket of `target`'s outage, if it has one.
+    pub fn update(&self, target: &str, text: impl Into<String>) -> Option<TicketAction> {
+        let ticket = self.incidents.get(target)?.ticket.clone()?;
+        Some(TicketAction::Comment { ticket, text: text.into() })
+    }
+
+    /// Drops the outage of a target that is no longer monitored; its ticket stays open.
+    pub fn forget(&mut self, target: &str) -> Option<TicketAction> {
+        let incident = self.incidents.remove(target)?;
+        let text = format!("{} was removed from monitoring while down", target);
+        incident.ticket.map(|ticket| TicketAction::Comment { ticket, text })
+    }
+}
+
+/// Talks to the REST API of the configured ticket system. Tickets are identified by their
+/// Jira issue key or ServiceNow `sys_id`.
+pub struct TicketClient {
+    config: TicketConfig,
+    client: Client,
+    token: String,
+}
+
+impl TicketClient {
+    pub fn new(config: TicketConfig) -> Result<Self, Box<dyn Error>> {
+        let token = std::env::var(&config.token_env).map_err(|_| format!("{} is not set", config.token_env))?;
+        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        Ok(Self { config, client, token })
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        format!("{}{}", self.config.url.trim_end_matches('/'), path)
+    }
+
+    async fn request(&self, method: reqwest::Method, path: &str, body: Option<&JsonValue>) -> Result<JsonValue, Box<dyn Error>> {
+        let mut request = self
+            .client
+            .request(method, self.endpoint(path))
+            .basic_auth(&self.config.user, Some(&self.token))
+            .header("Accept", "application/json");
+        if let Some(body) = body {
+            request = request.json(body);
+        }
+        let response = request.send().await?.error_for_status()?;
+        // Jira answers some calls with 204 and no body.
+        let text = response.text().await?;
+        Ok(if text.is_empty() { JsonValue::Null } else { serde_json::from_str(&text)? })
+    }
+
+    /// Opens a ticket and returns its ID.
+    pub async fn open(&self, change: &StateChange, severity: Severity) -> Result<String, Box<dyn Error>> {
+        let body = self.config.open_body(change, severity);
+        let (path, pointer) = match self.config.system {
+            TicketSystem::Jira => ("/rest/api/2/issue", "/key"),
+            TicketSystem::ServiceNow => ("/api/now/table/incident", "/result/sys_id"),
+        };
+        let response = self.request(reqwest::Method::POST, path, Some(&body)).await?;
+        let ticket = response.pointer(pointer).and_then(JsonValue::as_str).ok_or("no ticket ID in the response")?;
+        Ok(ticket.to_string())
+    }
+
+    pub async fn comment(&self, ticket: &str, text: &str) -> Result<(), Box<dyn Error>> {
+        match self.config.system {
+            TicketSystem::Jira => {
+                let path = format!("/rest/api/2/issue/{}/comment", ticket);
+                self.request(reqwest::Method::POST, &path, Some(&json!({ "body": text }))).await?;
+            }
+            TicketSystem::ServiceNow => {
+                let path = format!("/api/now/table/incident/{}", ticket);
+                self.request(reqwest::Method::PATCH, &path, Some(&json!({ "work_notes": text }))).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn resolve(&self, ticket: &str, text: &str) -> Result<(), Box<dyn Error>> {
+        match self.config.system {
+            TicketSystem::Jira => {
+                self.comment(ticket, text).await?;
+                // Transitions are configured per workflow, so look the ID up by name.
+                let path = format!("/rest/api/2/issue/{}/transitions", ticket);
+                let transitions = self.request(reqwest::Method::GET, &path, None).await?;
+                let id = transitions["transitions"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .find(|t| t["name"].as_str().is_some_and(|name| name.eq_ignore_ascii_case(&self.config.resolve_transition)))
+                    .and_then(|t| t["id"].as_str())
+                    .ok_or_else(|| format!("{} has no transition '{}'", ticket, self.config.resolve_transition))?
+                    .to_string();
+                self.request(reqwest::Method::POST, &path, Some(&json!({ "transition": { "id": id } }))).await?;
+            }
+            TicketSystem::ServiceNow => {
+                let path = format!("/api/now/table/incident/{}", ticket);
+                // State 6 is "Resolved".
+                let body = json!({ "state": "6", "close_code": self.config.close_code, "close_notes": text });
+                self.request(reqwest::Method::PATCH, &path, Some(&body)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Opens, updates and resolves tickets for the outages of `monitor` until the bus closes.
+/// Open tickets are only tracked in memory; after a restart they have to be closed by hand.
+/// During an alert storm, `storm` holds new tickets back until it has room again.
+pub async fn run(monitor: Arc<Monitor>, client: TicketClient, storm: SharedStormGuard) {
+    let mut events = monitor.bus().subscribe();
+    let mut tracker = TicketTracker::new(client.config.open_after, client.config.min_severity);
+    // The result that caused a transition is published just before it.
+    let mut last: HashMap<String, CheckResult> = HashMap::new();
+    let mut poll = tokio::time::interval(TICKET_POLL);
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = poll.tick() => {
+                for (change, severity) in tracker.due(Utc::now()) {
+                    if !storm.admit(&Notification::from(&change)) {
+                        continue;
+                    }
+                    match client.open(&change, severity).await {
+                        Ok(ticket) => {
+                            println!("Opened ticket {} for {}", ticket, change.target);
+                            tracker.opened(&change.target, ticket);
+                        }
+                        // Retried on the next poll.
+                        Err(e) => eprintln!("Cannot open a ticket for {}: {}", change.target, e),
+                    }
+                }
+                continue;
+            }
+        };
+        let action = match event {
+            Ok(MonitorEvent::CheckCompleted(result)) => {
+                last.insert(result.target.clone(), result);
+                None
+            }
+            Ok(MonitorEvent::Transition(transition)) => {
+                let result = last.get(&transition.target).filter(|r| r.correlation_id == transition.correlation_id);
+                tracker.transition(StateChange::new(&transition, result))
+            }
+            Ok(MonitorEvent::AlertAcknowledged(addr)) => tracker.update(&addr.to_string(), "Alert acknowledged"),
+            Ok(MonitorEvent::StaleChanged(change)) => tracker.update(&change.target.to_string(), change.to_string()),
+            Ok(MonitorEvent::TargetPaused { target, pause }) => {
+                let text = if pause.is_some() { "Checks paused" } else { "Checks resumed" };
+                tracker.update(&target.to_string(), text)
+            }
+            Ok(MonitorEvent::TargetRemoved(addr)) => {
+                last.remove(&addr.to_string());
+                tracker.forget(&addr.to_string())
+            }
+            Ok(_) => None,
+            Err(RecvError::Lagged(missed)) => {
+                eprintln!("Ticketing missed {} events", missed);
+                None
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let result = match &action {
+            Some(TicketAction::Comment { ticket, text }) => client.comment(ticket, text).await,
+            Some(TicketAction::Resolve { ticket, text }) => client.resolve(ticket, text).await,
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            eprintln!("Ticket update failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn change(target: &str, state: TargetState, at: DateTime<Utc>, metadata: &[(&str, &str)]) -> StateChange {
+        StateChange {
+            target: target.to_string(),
+            old_state: None,
+            new_state: state,
+            at,
+            latency_ms: None,
+            downtime_secs: (state == TargetState::Up).then_some(600.0),
+            correlation_id: String::new(),
+            message: format!("{} changed", target),
+            severity: if state == TargetState::Down { Severity::Critical } else { Severity::Info },
+            metadata: metadata.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn test_tickets_open_after_the_delay_and_resolve_on_recovery() {
+        let t0 = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mins = |m| t0 + ChronoDuration::minutes(m);
+        let mut tracker = TicketTracker::new(ChronoDuration::minutes(5), Severity::Warning);
+
+        assert!(tracker.transition(change("10.0.0.1:80", TargetState::Down, t0, &[])).is_none());
+        // Low severity outages never get a ticket.
+        tracker.transition(change("10.0.0.2:80", TargetState::Down, t0, &[("severity", "low")]));
+        // A blip shorter than the delay is forgotten without one.
+        tracker.transition(change("10.0.0.3:80", TargetState::Down, mins(1), &[]));
+        assert!(tracker.transition(change("10.0.0.3:80", TargetState::Up, mins(2), &[])).is_none());
+
+        assert!(tracker.due(mins(4)).is_empty());
+        let due = tracker.due(mins(5));
+        assert!(matches!(&due[..], [(change, Severity::Critical)] if change.target == "10.0.0.1:80"));
+        assert!(tracker.update("10.0.0.1:80", "Alert acknowledged").is_none());
+
+        tracker.opened("10.0.0.1:80", "OPS-7".to_string());
+        assert!(tracker.due(mins(6)).is_empty());
+        assert!(matches!(
+            tracker.update("10.0.0.1:80", "Alert acknowledged"),
+            Some(TicketAction::Comment { ticket, .. }) if ticket == "OPS-7"
+        ));
+        assert!(matches!(
+            tracker.transition(change("10.0.0.1:80", TargetState::Up, mins(10), &[])),
+            Some(TicketAction::Resolve { ticket, .. }) if ticket == "OPS-7"
+        ));
+        assert!(tracker.update("10.0.0.1:80", "late").is_none());
+    }
+
+    #[test]
+    fn test_open_body_maps_metadata_to_ticket_fields() {
+        let mut config: TicketConfig = toml::from_str(
+            r#"
+            system = "jira"
+            url = "https://example.atlassian.net"
+            user = "bot"
+            token_env = "JIRA_TOKEN"
+            project = "OPS"
+            open_after = "2m"
+
+            [fields]
+            team = "customfield_10010"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.open_after, ChronoDuration::minutes(2));
+        let mut down = change("10.0.0.1:80", TargetState::Down, Utc::now(), &[("team", "dba"), ("site", "ams")]);
+        down.correlation_id = "c0ffee42".to_string();
+
+        let body = config.open_body(&down, Severity::Critical);
+        assert_eq!(body["fields"]["project"]["key"], "OPS");
+        assert_eq!(body["fields"]["customfield_10010"], "dba");
+        assert_eq!(body["fields"]["summary"], "10.0.0.1:80 is DOWN");
+        let description = body["fields"]["description"].as_str().unwrap();
+        assert!(description.contains("site: ams"));
+        assert!(description.contains("Correlation ID: c0ffee42"));
+
+        config.system = TicketSystem::ServiceNow;
+        let body = config.open_body(&down, Severity::Warning);
+        assert_eq!((&body["urgency"], &body["customfield_10010"]), (&json!("2"), &json!("dba")));
+        assert_eq!(body["correlation_id"], "c0ffee42");
+    }
+}
@@ -0,0 +1,152 @@
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::env;
+
+/// Timezone used when none is configured: `$TZ` if it names a valid zone, otherwise UTC.
+pub fn default_timezone() -> Tz {
+    env::var("TZ")
+        .ok()
+        .and_then(|name| name.trim_start_matches(':').parse::<Tz>().ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// Parses an IANA zone name such as "Europe/Berlin".
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>()
+        .map_err(|_| format!("unknown timezone '{}', expected an IANA name like 'America/New_York'", name))
+}
+
+/// Formats a stored UTC timestamp in the operator's timezone, with the zone abbreviation
+/// so screenshots and reports are never ambiguous.
+pub fn format_local(timestamp: DateTime<Utc>, tz: Tz) -> String {
+    timestamp.with_timezone(&tz).format("%Y-%m-%d %H:%M:%S %Z").to_string()
+}
+
+/// A recurring local-time window, e.g. "Sun 02:00-04:00 Europe/London" for maintenance or
+/// "Mon-Fri 08:00-18:00 America/Chicago" for business hours.
+///
+/// The window is evaluated in its own timezone, so it follows daylight saving changes.
+/// `end` before `start` means the window runs past midnight into the next day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWindow {
+    pub days: Vec<Weekday>,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub tz: Tz,
+}
+
+impl TimeWindow {
+    /// Parses "Mon-Fri 08:00-18:00" or "Sat,Sun 22:00-02:00" in the given zone.
+    pub fn parse(spec: &str, tz: Tz) -> Result<Self, String> {
+        let (days_part, times_part) = spec
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| format!("window '{}' must look like 'Mon-Fri 08:00-18:00'", spec))?;
+        let (start, end) = times_part
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| format!("time range '{}' must look like '08:00-18:00'", times_part))?;
+        let parse_time = |t: &str| {
+            NaiveTime::parse_from_str(t.trim(), "%H:%M").map_err(|e| format!("invalid time '{}': {}", t, e))
+        };
+
+        Ok(Self {
+            days: parse_days(days_part)?,
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+            tz,
+        })
+    }
+
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        let local = at.with_timezone(&self.tz);
+        let time = local.time();
+        if self.start <= self.end {
+            self.days.contains(&local.weekday()) && time >= self.start && time < self.end
+        } else {
+            // Overnight: the late part belongs to today, the early part to yesterday's window.
+            (self.days.contains(&local.weekday()) && time >= self.start)
+                || (self.days.contains(&local.weekday().pred()) && time < self.end)
+        }
+    }
+
+    /// The next time the window opens strictly after `after`.
+    pub fn next_start(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local_date = after.with_timezone(&self.tz).date_naive();
+        (0..=7).find_map(|offset| {
+            let date = local_date + ChronoDuration::days(offset);
+            if !self.days.contains(&date.weekday()) {
+                return None;
+            }
+            // `earliest` picks the first instant when a DST change makes the time ambiguous;
+            // times skipped by a DST jump have no instant and are passed over.
+            let start = self.tz.from_local_datetime(&date.and_time(self.start)).earliest()?;
+            let start = start.with_timezone(&Utc);
+            (start > after).then_some(start)
+        })
+    }
+}
+
+fn parse_days(spec: &str) -> Result<Vec<Weekday>, String> {
+    let mut days = Vec::new();
+    for part in spec.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (mut day, to) = (parse_day(from)?, parse_day(to)?);
+                days.push(day);
+                while day != to {
+                    day = day.succ();
+                    days.push(day);
+                }
+            }
+            None => days.push(parse_day(part)?),
+        }
+    }
+    Ok(days)
+}
+
+fn parse_day(name: &str) -> Result<Weekday, String> {
+    name.trim().parse::<Weekday>().map_err(|_| format!("invalid weekday '{}'", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_business_hours_follow_local_time() {
+        let tz = parse_timezone("America/Chicago").unwrap();
+        let window = TimeWindow::parse("Mon-Fri 08:00-18:00", tz).unwrap();
+        // Friday 2026-10-16 14:00 UTC is 09:00 CDT.
+        assert!(window.contains(Utc.with_ymd_and_hms(2026, 10, 16, 14, 0, 0).unwrap()));
+        // Friday 12:00 UTC is 07:00 CDT, before opening.
+        assert!(!window.contains(Utc.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap()));
+        // Saturday.
+        assert!(!window.contains(Utc.with_ymd_and_hms(2026, 10, 17, 15, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_overnight_window() {
+        let window = TimeWindow::parse("Sat 22:00-02:00", Tz::UTC).unwrap();
+        assert!(window.contains(Utc.with_ymd_and_hms(2026, 10, 17, 23, 0, 0).unwrap()));
+        assert!(window.contains(Utc.with_ymd_and_hms(2026, 10, 18, 1, 0, 0).unwrap()));
+        assert!(!window.contains(Utc.with_ymd_and_hms(2026, 10, 18, 3, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_next_start_across_dst_change() {
+        let tz = parse_timezone("Europe/London").unwrap();
+        let window = TimeWindow::parse("Sun 02:00-04:00", tz).unwrap();
+        // Clocks go back on 2026-10-25; 02:00 local is then 02:00 GMT.
+        let next = window.next_start(Utc.with_ymd_and_hms(2026, 10, 20, 0, 0, 0).unwrap()).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2026, 10, 25, 2, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_format_local_includes_zone() {
+        let tz = parse_timezone("Asia/Tokyo").unwrap();
+        let formatted = format_local(Utc.with_ymd_and_hms(2026, 10, 17, 0, 0, 0).unwrap(), tz);
+        assert_eq!(formatted, "2026-10-17 09:00:00 JST");
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+}
@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::{self, Write};
+use std::net::SocketAddr;
+use std::path::Path;
+
+use super::monitor::Monitor;
+use super::service::{self, Rollup};
+use super::waterfall::escape_html;
+
+const NODE_WIDTH: usize = 180;
+const NODE_HEIGHT: usize = 36;
+const GAP_X: usize = 30;
+const GAP_Y: usize = 60;
+
+/// A node of the topology: a monitored target or a service (group).
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeId {
+    Target(SocketAddr),
+    Service(String),
+}
+
+impl NodeId {
+    /// `host:port` names a target, anything else a service.
+    pub fn parse(name: &str) -> Self {
+        match name.trim().parse() {
+            Ok(addr) => NodeId::Target(addr),
+            Err(_) => NodeId::Service(name.trim().to_string()),
+        }
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeId::Target(addr) => write!(f, "{}", addr),
+            NodeId::Service(name) => f.write_str(name),
+        }
+    }
+}
+
+/// `from` needs `to`: when `to` is down, `from` is in its blast radius.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct Dependency {
+    pub from: String,
+    pub to: String,
+}
+
+/// Dependencies beyond group membership, e.g. a shop service needing the database of
+/// another group. Read from TOML `[[dependency]]` tables.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DependencyConfig {
+    #[serde(default, rename = "dependency")]
+    pub dependencies: Vec<Dependency>,
+}
+
+impl DependencyConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// (dependent, dependency) pairs.
+    pub fn edges(&self) -> impl Iterator<Item = (NodeId, NodeId)> + '_ {
+        self.dependencies.iter().map(|d| (NodeId::parse(&d.from), NodeId::parse(&d.to)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TopologyNode {
+    pub id: NodeId,
+    pub status: Rollup,
+    /// Not down itself, but depends on something that is.
+    pub affected: bool,
+    /// 0 without dependencies, otherwise one more than the deepest dependency.
+    pub layer: usize,
+}
+
+/// Targets and services with what depends on what, and the live status of each.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Topology {
+    pub nodes: Vec<TopologyNode>,
+    /// (dependent, dependency) pairs.
+    pub edges: Vec<(NodeId, NodeId)>,
+}
+
+impl Topology {
+    /// Nodes named only by an edge are added with an unknown status.
+    pub fn new(statuses: Vec<(NodeId, Rollup)>, edges: Vec<(NodeId, NodeId)>) -> Self {
+        let mut statuses: BTreeMap<NodeId, Rollup> = statuses.into_iter().collect();
+        let mut edges: Vec<(NodeId, NodeId)> = edges.into_iter().filter(|(from, to)| from != to).collect();
+        edges.sort();
+        edges.dedup();
+        for (from, to) in &edges {
+            for node in [from, to] {
+                statuses.entry(node.clone()).or_insert(Rollup::Unknown);
+            }
+        }
+        let layers = layers(&statuses, &edges);
+        let mut topology = Self {
+            nodes: statuses
+                .into_iter()
+                .map(|(id, status)| TopologyNode { layer: layers[&id], id, status, affected: false })
+                .collect(),
+            edges,
+        };
+        let affected: BTreeSet<NodeId> = topology
+            .nodes
+            .iter()
+            .filter(|n| n.status == Rollup::Down)
+            .flat_map(|n| topology.blast_radius(&n.id))
+            .collect();
+        for node in &mut topology.nodes {
+            node.affected = node.status != Rollup::Down && affected.contains(&node.id);
+        }
+        topology
+    }
+
+    /// Every target and service of `monitor`; services depend on their members.
+    pub fn from_monitor(monitor: &Monitor, config: &DependencyConfig) -> Self {
+        let services = monitor.services();
+        let mut statuses: Vec<(NodeId, Rollup)> = monitor
+            .targets()
+            .into_iter()
+            .map(|addr| (NodeId::Target(addr), service::rollup([monitor.state(addr)])))
+            .collect();
+        statuses.extend(services.iter().map(|s| (NodeId::Service(s.name.clone()), s.rollup)));
+        let mut edges: Vec<(NodeId, NodeId)> = services
+            .iter()
+            .flat_map(|s| s.members.iter().map(|(addr, _)| (NodeId::Service(s.name.clone()), NodeId::Target(*addr))))
+            .collect();
+        edges.extend(config.edges());
+        Self::new(statuses, edges)
+    }
+
+    /// Everything that depends on `node`, directly or through others.
+    pub fn blast_radius(&self, node: &NodeId) -> BTreeSet<NodeId> {
+        let mut reached = BTreeSet::new();
+        let mut queue = vec![node.clone()];
+        while let Some(current) = queue.pop() {
+            for (from, _) in self.edges.iter().filter(|(_, to)| *to == current) {
+                if from != node && reached.insert(from.clone()) {
+                    queue.push(from.clone());
+                }
+            }
+        }
+        reached
+    }
+
+    /// Nodes by layer, dependents first: the order the GUI and the SVG draw them in.
+    pub fn rows(&self) -> Vec<Vec<&TopologyNode>> {
+        let depth = self.nodes.iter().map(|n| n.layer).max().unwrap_or(0);
+        (0..=depth)
+            .rev()
+            .map(|layer| self.nodes.iter().filter(|n| n.layer == layer).collect::<Vec<_>>())
+            .filter(|row| !row.is_empty())
+            .collect()
+    }
+
+    /// A self-contained SVG for the web UI: one box per node, colored by status, with
+    /// lines down to its dependencies. Affected nodes get a dashed red border.
+    pub fn to_svg(&self) -> String {
+        let rows = self.rows();
+        let mut positions: BTreeMap<&NodeId, (usize, usize)> = BTreeMap::new();
+        for (y, row) in rows.iter().enumerate() {
+            for (x, node) in row.iter().enumerate() {
+                positions.insert(&node.id, (x * (NODE_WIDTH + GAP_X) + GAP_X, y * (NODE_HEIGHT + GAP_Y) + GAP_Y / 2));
+            }
+        }
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0) * (NODE_WIDTH + GAP_X) + GAP_X;
+        let height = rows.len() * (NODE_HEIGHT + GAP_Y);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"topology\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" \
+             font-family=\"sans-serif\" font-size=\"12\">\n",
+            w = width,
+            h = height
+        );
+        // Writing to a String can't fail.
+        for (from, to) in &self.edges {
+            let ((fx, fy), (tx, ty)) = (positions[from], positions[to]);
+            let _ = writeln!(
+                svg,
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#888\"/>",
+                fx + NODE_WIDTH / 2,
+                fy + NODE_HEIGHT,
+                tx + NODE_WIDTH / 2,
+                ty
+            );
+        }
+        for node in &self.nodes {
+            let (x, y) = positions[&node.id];
+            let border = if node.affected { " stroke=\"#c62828\" stroke-width=\"2\" stroke-dasharray=\"4 3\"" } else { "" };
+            let _ = writeln!(
+                svg,
+                "<g class=\"{class}\"><rect x=\"{x}\" y=\"{y}\" width=\"{w}\" height=\"{h}\" rx=\"4\" fill=\"{fill}\"{border}/>\
+                 <text x=\"{tx}\" y=\"{ty}\" text-anchor=\"middle\" fill=\"#fff\">{label}</text></g>",
+                class = if node.affected { "affected" } else { "node" },
+                x = x,
+                y = y,
+                w = NODE_WIDTH,
+                h = NODE_HEIGHT,
+                fill = status_color(node.status),
+                border = border,
+                tx = x + NODE_WIDTH / 2,
+                ty = y + NODE_HEIGHT / 2 + 4,
+                label = escape_html(&node.id.to_string()),
+            );
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+fn status_color(status: Rollup) -> &'static str {
+    match status {
+        Rollup::Up => "#2e7d32",
+        Rollup::Degraded => "#ef6c00",
+        Rollup::Down => "#c62828",
+        Rollup::Unknown => "#757575",
+    }
+}
+
+// Longest path to a node without dependencies. A dependency cycle would make that
+// infinite, so stop after as many rounds as there are nodes.
+fn layers(nodes: &BTreeMap<NodeId, Rollup>, edges: &[(NodeId, NodeId)]) -> BTreeMap<NodeId, usize> {
+    let mut layers: BTreeMap<NodeId, usize> = nodes.keys().map(|n| (n.clone(), 0)).collect();
+    for _ in 0..nodes.len() {
+        let mut changed = false;
+        for (from, to) in edges {
+            let layer = layers[to] + 1;
+            if layers[from] < layer {
+                layers.insert(from.clone(), layer);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node<'a>(topology: &'a Topology, id: &NodeId) -> &'a TopologyNode {
+        topology.nodes.iter().find(|n| n.id == *id).unwrap()
+    }
+
+    #[test]
+    fn test_layers_and_blast_radius() {
+        let db = NodeId::parse("10.0.0.5:5432");
+        let web = NodeId::parse("10.0.0.1:443");
+        let (shop, checkout) = (NodeId::parse("shop"), NodeId::parse("checkout"));
+        let config: DependencyConfig = toml::from_str(
+            "[[dependency]]\nfrom = \"shop\"\nto = \"10.0.0.5:5432\"\n\n[[dependency]]\nfrom = \"checkout\"\nto = \"shop\"\n",
+        )
+        .unwrap();
+        let mut edges = vec![(shop.clone(), web.clone())];
+        edges.extend(config.edges());
+        let topology = Topology::new(vec![(db.clone(), Rollup::Down), (web.clone(), Rollup::Up), (shop.clone(), Rollup::Degraded)], edges);
+
+        assert_eq!(node(&topology, &db).layer, 0);
+        assert_eq!(node(&topology, &shop).layer, 1);
+        assert_eq!(node(&topology, &checkout).layer, 2);
+        // Only named by a dependency, so nothing is known about it.
+        assert_eq!(node(&topology, &checkout).status, Rollup::Unknown);
+
+        assert_eq!(topology.blast_radius(&db), BTreeSet::from([shop.clone(), checkout.clone()]));
+        assert!(node(&topology, &checkout).affected);
+        assert!(!node(&topology, &web).affected);
+        assert!(!node(&topology, &db).affected);
+        assert_eq!(topology.rows()[0].len(), 1);
+
+        let svg = topology.to_svg();
+        assert_eq!(svg.matches("<line").count(), 3);
+        assert_eq!(svg.matches("class=\"affected\"").count(), 2);
+    }
+
+    #[test]
+    fn test_cycles_terminate() {
+        let (a, b) = (NodeId::parse("a"), NodeId::parse("b"));
+        let topology = Topology::new(vec![(a.clone(), Rollup::Down)], vec![(a.clone(), b.clone()), (b.clone(), a.clone())]);
+        assert_eq!(topology.blast_radius(&a), BTreeSet::from([b.clone()]));
+        assert!(node(&topology, &b).affected);
+    }
+}
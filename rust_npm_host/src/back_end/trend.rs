@@ -0,0 +1,306 @@
+use chrono::Duration as ChronoDuration;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::check_result::CheckResult;
+use super::severity::Severity;
+
+/// Tuning knobs for the long-term trend analysis.
+#[derive(Debug, Clone)]
+pub struct TrendConfig {
+    /// Width of one aggregation bucket (one day by default).
+    pub bucket: ChronoDuration,
+    /// Minimum number of usable buckets before a trend is reported at all.
+    pub min_buckets: usize,
+    /// Buckets with fewer results than this are skipped as too noisy.
+    pub min_samples_per_bucket: usize,
+    /// Relative latency growth over the analysed span that triggers an advisory (0.5 = +50%).
+    pub latency_increase_ratio: f64,
+    /// Absolute growth of the failure rate over the span that triggers an advisory (0.05 = +5 points).
+    pub failure_rate_increase: f64,
+    /// Minimum goodness of fit (R²) so that only steady trends, not single spikes, are flagged.
+    pub min_fit: f64,
+}
+
+impl Default for TrendConfig {
+    fn default() -> Self {
+        Self {
+            bucket: ChronoDuration::days(1),
+            min_buckets: 7,
+            min_samples_per_bucket: 5,
+            latency_increase_ratio: 0.5,
+            failure_rate_increase: 0.05,
+            min_fit: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum AdvisoryKind {
+    LatencyDegrading,
+    FailuresIncreasing,
+}
+
+/// A low-severity hint that a target is slowly getting worse and may fail in the future.
+#[derive(Debug, Clone, Serialize)]
+pub struct Advisory {
+    pub target: String,
+    pub kind: AdvisoryKind,
+    pub severity: Severity,
+    /// Value of the fitted trend line at the start and end of the analysed span.
+    pub start_value: f64,
+    pub end_value: f64,
+    pub span_days: f64,
+}
+
+impl fmt::Display for Advisory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            AdvisoryKind::LatencyDegrading => write!(
+                f,
+                "[{}] {}: latency trending up over {:.0} days ({:.1} ms -> {:.1} ms)",
+                self.severity, self.target, self.span_days, self.start_value, self.end_value
+            ),
+            AdvisoryKind::FailuresIncreasing => write!(
+                f,
+                "[{}] {}: intermittent failures trending up over {:.0} days ({:.1}% -> {:.1}%)",
+                self.severity,
+                self.target,
+                self.span_days,
+                self.start_value * 100.0,
+                self.end_value * 100.0
+            ),
+        }
+    }
+}
+
+struct Bucket {
+    latencies_ms: Vec<f64>,
+    total: usize,
+    failures: usize,
+}
+
+/// Looks for steadily degrading latency or a rising failure rate per target.
+///
+/// Results may be in any order and may cover many targets. Returns one advisory per
+/// target and trend kind that crosses the configured thresholds.
+pub fn analyze(results: &[CheckResult], config: &TrendConfig) -> Vec<Advisory> {
+    let mut per_target: BTreeMap<&str, Vec<&CheckResult>> = BTreeMap::new();
+    for result in results {
+        per_target.entry(result.target.as_str()).or_default().push(result);
+    }
+
+    let mut advisories = Vec::new();
+    for (target, target_results) in per_target {
+        advisories.extend(analyze_target(target, &target_results, config));
+    }
+    advisories
+}
+
+fn analyze_target(target: &str, results: &[&CheckResult], config: &TrendConfig) -> Vec<Advisory> {
+    let mut advisories = Vec::new();
+    let bucket_secs = config.bucket.num_seconds().max(1);
+    let Some(origin) = results.iter().map(|r| r.timestamp).min() else {
+        return advisories;
+    };
+
+    let mut buckets: BTreeMap<i64, Bucket> = BTreeMap::new();
+    for result in results {
+        let index = (result.timestamp - origin).num_seconds() / bucket_secs;
+        let bucket = buckets.entry(index).or_insert_with(|| Bucket {
+            latencies_ms: Vec::new(),
+            total: 0,
+            failures: 0,
+        });
+        bucket.total += 1;
+        if result.success {
+            if let Some(ms) = result.latency_ms() {
+                bucket.latencies_ms.push(ms);
+            }
+        } else {
+            bucket.failures += 1;
+        }
+    }
+
+    let usable: Vec<(f64, &Bucket)> = buckets
+        .iter()
+        .filter(|(_, b)| b.total >= config.min_samples_per_bucket)
+        .map(|(index, b)| (*index as f64, b))
+        .collect();
+    if usable.len() < config.min_buckets {
+        return advisories;
+    }
+
+    let days_per_bucket = bucket_secs as f64 / 86_400.0;
+
+    let latency_points: Vec<(f64, f64)> = usable
+        .iter()
+        .filter_map(|(x, b)| median(&b.latencies_ms).map(|m| (*x, m)))
+        .collect();
+    if latency_points.len() >= config.min_buckets
+        && let Some(fit) = linear_fit(&latency_points)
+        && fit.slope > 0.0
+        && fit.r_squared >= config.min_fit
+        && fit.start > 0.0
+        && (fit.end - fit.start) / fit.start >= config.latency_increase_ratio
+    {
+        advisories.push(Advisory {
+            target: target.to_string(),
+            kind: AdvisoryKind::LatencyDegrading,
+            severity: Severity::Low,
+            start_value: fit.start,
+            end_value: fit.end,
+            span_days: fit.span * days_per_bucket,
+        });
+    }
+
+    let failure_points: Vec<(f64, f64)> = usable
+        .iter()
+        .map(|(x, b)| (*x, b.failures as f64 / b.total as f64))
+        .collect();
+    if let Some(fit) = linear_fit(&failure_points)
+        && fit.slope > 0.0
+        && fit.r_squared >= config.min_fit
+        && fit.end - fit.start.max(0.0) >= config.failure_rate_increase
+    {
+        advisories.push(Advisory {
+            target: target.to_string(),
+            kind: AdvisoryKind::FailuresIncreasing,
+            severity: Severity::Low,
+            start_value: fit.start.max(0.0),
+            end_value: fit.end.min(1.0),
+            span_days: fit.span * days_per_bucket,
+        });
+    }
+
+    advisories
+}
+
+pub fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        Some((sorted[mid - 1] + sorted[mid]) / 2.0)
+    } else {
+        Some(sorted[mid])
+    }
+}
+
+struct Fit {
+    slope: f64,
+    r_squared: f64,
+    start: f64,
+    end: f64,
+    span: f64,
+}
+
+// Ordinary least squares over (x, y) points.
+fn linear_fit(points: &[(f64, f64)]) -> Option<Fit> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let ss_xx: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    let ss_xy: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let ss_yy: f64 = points.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if ss_xx == 0.0 {
+        return None;
+    }
+
+    let slope = ss_xy / ss_xx;
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = if ss_yy == 0.0 { 0.0 } else { (ss_xy * ss_xy) / (ss_xx * ss_yy) };
+    let first_x = points.first().map(|(x, _)| *x).unwrap_or_default();
+    let last_x = points.last().map(|(x, _)| *x).unwrap_or_default();
+
+    Some(Fit {
+        slope,
+        r_squared,
+        start: intercept + slope * first_x,
+        end: intercept + slope * last_x,
+        span: last_x - first_x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    fn result_at(day: i64, sample: i64, success: bool, latency_ms: u64) -> CheckResult {
+        CheckResult {
+            target: "10.0.0.5:443".to_string(),
+            timestamp: Utc::now() - ChronoDuration::days(30) + ChronoDuration::days(day) + ChronoDuration::minutes(sample),
+            success,
+            latency: success.then(|| Duration::from_millis(latency_ms)),
+            error: None,
+            metrics: Default::default(),
+            correlation_id: String::new(),
+            resolved_ip: None,
+            steps: Vec::new(),
+            agent: None,
+            failure_kind: None,
+            hint: None,
+            page_errors: Vec::new(),
+            artifacts: Vec::new(),
+            metadata: Default::default(),
+            status: None,
+            severity: None,
+        }
+    }
+
+    #[test]
+    fn test_flags_steadily_rising_latency() {
+        let mut results = Vec::new();
+        for day in 0..21 {
+            for sample in 0..10 {
+                results.push(result_at(day, sample, true, 40 + day as u64 * 3));
+            }
+        }
+        let advisories = analyze(&results, &TrendConfig::default());
+        assert_eq!(advisories.len(), 1);
+        assert_eq!(advisories[0].kind, AdvisoryKind::LatencyDegrading);
+        assert_eq!(advisories[0].severity, Severity::Low);
+    }
+
+    #[test]
+    fn test_flat_latency_is_not_flagged() {
+        let mut results = Vec::new();
+        for day in 0..21 {
+            for sample in 0..10 {
+                results.push(result_at(day, sample, true, 40 + (sample as u64 % 3)));
+            }
+        }
+        assert!(analyze(&results, &TrendConfig::default()).is_empty());
+    }
+
+    #[test]
+    fn test_flags_rising_failure_rate() {
+        let mut results = Vec::new();
+        for day in 0..14 {
+            for sample in 0..20 {
+                // One extra failure out of twenty per day.
+                let success = sample >= day;
+                results.push(result_at(day, sample, success, 40));
+            }
+        }
+        let advisories = analyze(&results, &TrendConfig::default());
+        assert!(advisories.iter().any(|a| a.kind == AdvisoryKind::FailuresIncreasing));
+    }
+
+    #[test]
+    fn test_not_enough_history() {
+        let results: Vec<CheckResult> = (0..3)
+            .flat_map(|day| (0..10).map(move |s| result_at(day, s, true, 40 + day as u64 * 50)))
+            .collect();
+        assert!(analyze(&results, &TrendConfig::default()).is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+use reqwest::Url;
+use reqwest::header::{HOST, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+
+use super::check_result::CheckResult;
+use super::ssh::{SshPool, SshTarget, SshTunnel};
+use super::target::millis;
+
+// sshd accepts the local connection before it knows whether the remote side is reachable
+// and closes it right away when it isn't, so a quick EOF means "refused".
+const REFUSED_GRACE: Duration = Duration::from_millis(300);
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to reach a target that is only visible from inside another network segment, set
+/// as a target's `tunnel` table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunnelConfig {
+    /// Host that opens the forwarded connection to the target.
+    pub bastion: SshTarget,
+    /// Optional hops in front of the bastion, `user@host[:port]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub jump_hosts: Vec<String>,
+    /// How long to wait for the forward to come up before failing the check, in
+    /// milliseconds; ten seconds if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "millis")]
+    pub ready_timeout: Option<Duration>,
+}
+
+impl TunnelConfig {
+    async fn open(&self, pool: &SshPool, host: &str, port: u16) -> Result<SshTunnel, Box<dyn Error>> {
+        let ready_timeout = self.ready_timeout.unwrap_or(DEFAULT_READY_TIMEOUT);
+        pool.open_tunnel(&self.bastion, &self.jump_hosts, host, port, ready_timeout).await
+    }
+
+    fn describe(&self, host: &str, port: u16) -> String {
+        format!("{}:{} via {}", host, port, self.bastion.host)
+    }
+}
+
+/// Opens a tunnel, checks that `host:port` accepts TCP connections from the bastion, and
+/// tears the tunnel down again. Latency covers the connection through the tunnel only.
+pub async fn check_tcp(pool: &SshPool, config: &TunnelConfig, host: &str, port: u16, timeout: Duration) -> CheckResult {
+    let target = config.describe(host, port);
+    let tunnel = match config.open(pool, host, port).await {
+        Ok(tunnel) => tunnel,
+        Err(e) => return CheckResult::failure(&target, e.to_string()),
+    };
+
+    let start = Instant::now();
+    let result = match tokio::time::timeout(timeout, TcpStream::connect(tunnel.local_addr())).await {
+        Ok(Ok(mut stream)) => {
+            let latency = start.elapsed();
+            let mut buf = [0u8; 1];
+            match tokio::time::timeout(REFUSED_GRACE, stream.read(&mut buf)).await {
+                Ok(Ok(0)) | Ok(Err(_)) => CheckResult::failure(&target, "connection refused by remote host"),
+                // Either the service sent a banner or it is waiting for us to talk first.
+                Ok(Ok(_)) | Err(_) => CheckResult::success(&target, latency),
+            }
+        }
+        Ok(Err(e)) => CheckResult::failure(&target, e.to_string()),
+        Err(_) => CheckResult::failure(&target, format!("timed out after {:?}", timeout)),
+    };
+    tunnel.close().await;
+    result
+}
+
+/// Requests `url` through a tunnel to its host. The request keeps its original `Host`
+/// header and TLS server name, so virtual hosts and certificates behave as they would
+/// from inside the segment.
+pub async fn check_http(pool: &SshPool, config: &TunnelConfig, url: &str, timeout: Duration) -> CheckResult {
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(e) => return CheckResult::failure(url, e.to_string()),
+    };
+    let (Some(host), Some(port)) = (parsed.host_str(), parsed.port_or_known_default()) else {
+        return CheckResult::failure(url, "URL has no host");
+    };
+
+    let tunnel = match config.open(pool, host, port).await {
+        Ok(tunnel) => tunnel,
+        Err(e) => return CheckResult::failure(url, e.to_string()),
+    };
+    let result = request_through(&tunnel, &parsed, host, timeout)
+        .await
+        .unwrap_or_else(|e| CheckResult::failure(url, e.to_string()));
+    tunnel.close().await;
+    result
+}
+
+async fn request_through(tunnel: &SshTunnel, url: &Url, host: &str, timeout: Duration) -> Result<CheckResult, Box<dyn Error>> {
+    // reqwest prefers an explicit URL port over the resolve override, so drop it and keep
+    // the original authority in the Host header instead.
+    let mut local_url = url.clone();
+    local_url.set_port(None).map_err(|_| "URL cannot carry a port")?;
+    let authority = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+
+    let client = reqwest::Client::builder()
+        .resolve(host, tunnel.local_addr())
+        .timeout(timeout)
+        .build()?;
+    let start = Instant::now();
+    let response = client
+        .get(local_url)
+        .header(HOST, HeaderValue::from_str(&authority)?)
+        .send()
+        .await?;
+    let latency = start.elapsed();
+    let status = response.status();
+    Ok(if status.is_success() || status.is_redirection() {
+        CheckResult::success(url.as_str(), latency)
+    } else {
+        CheckResult {
+            latency: Some(latency),
+            ..CheckResult::failure(url.as_str(), format!("HTTP {}", status))
+        }
+    })
+}
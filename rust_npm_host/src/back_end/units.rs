@@ -0,0 +1,210 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use super::timezone::{default_timezone, format_local, parse_timezone};
+
+/// What a metric's values measure. Stored values are always in the base unit the name
+/// says (`_ms` in milliseconds, `_bytes` in bytes); units only change how they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Unit {
+    #[serde(rename = "ms")]
+    Milliseconds,
+    #[serde(rename = "s")]
+    Seconds,
+    #[serde(rename = "bytes")]
+    Bytes,
+    /// 0.0 to 1.0, shown as a percentage.
+    #[serde(rename = "ratio")]
+    Ratio,
+    #[serde(rename = "percent")]
+    Percent,
+    #[serde(rename = "count")]
+    Count,
+}
+
+impl Unit {
+    /// Guesses the unit from the metric name: `_ms`, `_secs`, `_bytes`, `_ratio`/`_share`,
+    /// `_percent`/`_pct` suffixes and `packet_loss`; anything else is a plain number.
+    pub fn of_metric(name: &str) -> Self {
+        let has = |suffixes: &[&str]| suffixes.iter().any(|suffix| name.ends_with(suffix));
+        if has(&["_ms"]) {
+            Unit::Milliseconds
+        } else if has(&["_secs", "_seconds"]) {
+            Unit::Seconds
+        } else if has(&["_bytes"]) {
+            Unit::Bytes
+        } else if name == "packet_loss" || has(&["_ratio", "_share"]) {
+            Unit::Ratio
+        } else if has(&["_percent", "_pct"]) {
+            Unit::Percent
+        } else {
+            Unit::Count
+        }
+    }
+
+    /// The base unit's symbol for column names and export metadata, e.g. `ms`; empty for
+    /// plain numbers.
+    #[cfg_attr(not(feature = "parquet-export"), allow(dead_code))]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Milliseconds => "ms",
+            Unit::Seconds => "s",
+            Unit::Bytes => "B",
+            Unit::Ratio => "ratio",
+            Unit::Percent => "%",
+            Unit::Count => "",
+        }
+    }
+}
+
+/// How durations are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DurationStyle {
+    /// Milliseconds below a second, seconds above.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "ms")]
+    Milliseconds,
+    #[serde(rename = "s")]
+    Seconds,
+}
+
+/// How sizes are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeStyle {
+    /// KiB, MiB, GiB (powers of 1024).
+    #[default]
+    Binary,
+    /// kB, MB, GB (powers of 1000).
+    Decimal,
+    Bytes,
+}
+
+/// One user's display preferences, `[display]` in the settings file. Every latency and
+/// metric the GUI shows goes through `format`, and every time through `time` or
+/// `timestamp`, so all views agree.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnitPreferences {
+    pub durations: DurationStyle,
+    pub sizes: SizeStyle,
+    /// Units of metrics whose names don't tell, e.g. derived metrics.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub units: BTreeMap<String, Unit>,
+    /// IANA zone times are shown in, e.g. "Europe/Berlin"; `$TZ` or UTC when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl UnitPreferences {
+    /// The configured unit of `metric`, or the one its name suggests.
+    pub fn unit(&self, metric: &str) -> Unit {
+        self.units.get(metric).copied().unwrap_or_else(|| Unit::of_metric(metric))
+    }
+
+    /// `value`, given in `unit`, the way this user wants it shown, e.g. `1.25 s` or `3.4 MiB`.
+    pub fn format(&self, value: f64, unit: Unit) -> String {
+        match unit {
+            Unit::Milliseconds => self.duration(value),
+            Unit::Seconds => self.duration(value * 1000.0),
+            Unit::Bytes => self.size(value),
+            Unit::Ratio => format!("{:.1} %", value * 100.0),
+            Unit::Percent => format!("{:.1} %", value),
+            Unit::Count if value.fract() == 0.0 => format!("{}", value),
+            Unit::Count => format!("{:.2}", value),
+        }
+    }
+
+    /// The zone times are shown in. An unknown name counts as unset; `config check`
+    /// reports it.
+    pub fn timezone(&self) -> Tz {
+        self.timezone.as_deref().and_then(|name| parse_timezone(name).ok()).unwrap_or_else(default_timezone)
+    }
+
+    /// The time of day of `at`, e.g. `14:05:09`.
+    pub fn time(&self, at: DateTime<Utc>) -> String {
+        at.with_timezone(&self.timezone()).format("%H:%M:%S").to_string()
+    }
+
+    /// Date and time of `at` with the zone, e.g. `2026-10-17 09:00:00 JST`.
+    pub fn timestamp(&self, at: DateTime<Utc>) -> String {
+        format_local(at, self.timezone())
+    }
+
+    /// A latency in ms.
+    pub fn latency(&self, ms: f64) -> String {
+        self.duration(ms)
+    }
+
+    /// A metric's value, in the metric's unit.
+    pub fn metric(&self, name: &str, value: f64) -> String {
+        self.format(value, self.unit(name))
+    }
+
+    fn duration(&self, ms: f64) -> String {
+        let seconds = match self.durations {
+            DurationStyle::Auto => ms.abs() >= 1000.0,
+            DurationStyle::Milliseconds => false,
+            DurationStyle::Seconds => true,
+        };
+        if seconds {
+            format!("{:.2} s", ms / 1000.0)
+        } else if ms.abs() < 10.0 && ms.fract() != 0.0 {
+            format!("{:.1} ms", ms)
+        } else {
+            format!("{:.0} ms", ms)
+        }
+    }
+
+    fn size(&self, bytes: f64) -> String {
+        let (base, prefixes) = match self.sizes {
+            SizeStyle::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+            SizeStyle::Decimal => (1000.0, ["B", "kB", "MB", "GB", "TB"]),
+            SizeStyle::Bytes => return format!("{:.0} B", bytes),
+        };
+        let mut value = bytes;
+        let mut prefix = 0;
+        while value.abs() >= base && prefix < prefixes.len() - 1 {
+            value /= base;
+            prefix += 1;
+        }
+        match prefix {
+            0 => format!("{:.0} B", value),
+            _ => format!("{:.1} {}", value, prefixes[prefix]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_follow_units_and_preferences() {
+        let auto = UnitPreferences::default();
+        assert_eq!(auto.metric("ttfb_ms", 84.0), "84 ms");
+        assert_eq!(auto.metric("nav_load_event_ms", 1250.0), "1.25 s");
+        assert_eq!(auto.metric("rtt_avg_ms", 0.42), "0.4 ms");
+        assert_eq!(auto.metric("body_bytes", 3_565_158.0), "3.4 MiB");
+        assert_eq!(auto.metric("packet_loss", 0.25), "25.0 %");
+        assert_eq!(auto.metric("status_code", 200.0), "200");
+
+        let preferences: UnitPreferences = toml::from_str(
+            r#"
+            durations = "ms"
+            sizes = "decimal"
+            [units]
+            tls_time = "s"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(preferences.latency(1250.0), "1250 ms");
+        assert_eq!(preferences.metric("tls_time", 0.3), "300 ms");
+        assert_eq!(preferences.metric("body_bytes", 3_565_158.0), "3.6 MB");
+        assert_eq!(preferences.unit("tls_time").symbol(), "s");
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::Deserialize;
+use serde_json::{Value as JsonValue, json};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+
+use super::annotations::AnnotationStore;
+use super::history::History;
+use super::monitor::{CheckKind, Monitor};
+use super::state_tracker::TargetState;
+
+// UptimeRobot never returns more than 50 monitors per page.
+const MAX_PAGE: usize = 50;
+const DEFAULT_RESPONSE_TIMES: usize = 24;
+
+// UptimeRobot's monitor `type`, port `sub_type` and `status` codes.
+const TYPE_HTTP: u8 = 1;
+const TYPE_PING: u8 = 3;
+const TYPE_PORT: u8 = 4;
+const SUB_TYPE_CUSTOM_PORT: u8 = 99;
+const STATUS_PAUSED: u8 = 0;
+const STATUS_NOT_CHECKED: u8 = 1;
+const STATUS_UP: u8 = 2;
+const STATUS_DOWN: u8 = 9;
+
+/// Parameters of `getMonitors`, posted as a form like UptimeRobot's v2 API expects.
+/// Unsupported ones (logs, alert contacts, ...) are ignored.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GetMonitors {
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Monitor IDs separated by `-`; all monitors if not given.
+    #[serde(default)]
+    pub monitors: Option<String>,
+    #[serde(default)]
+    pub response_times: Option<u8>,
+    #[serde(default)]
+    pub response_times_limit: Option<usize>,
+    /// Days separated by `-`, e.g. `1-7-30`.
+    #[serde(default)]
+    pub custom_uptime_ratios: Option<String>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// The numeric ID a target gets in this API. Derived from the address, so it stays the
+/// same across restarts and on every instance.
+pub fn monitor_id(addr: SocketAddr) -> u32 {
+    let digest = Sha256::digest(addr.to_string().as_bytes());
+    u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) & 0x7fff_ffff
+}
+
+/// An error body in UptimeRobot's shape.
+pub fn fail(kind: &str, message: &str) -> JsonValue {
+    json!({"stat": "fail", "error": {"type": kind, "message": message}})
+}
+
+/// The `getMonitors` response for `params`.
+pub fn get_monitors(
+    monitor: &Monitor,
+    history: &History,
+    annotations: Option<&AnnotationStore>,
+    params: &GetMonitors,
+    now: DateTime<Utc>,
+) -> JsonValue {
+    let wanted: Option<Vec<u32>> = params
+        .monitors
+        .as_deref()
+        .map(|ids| ids.split('-').filter_map(|id| id.trim().parse().ok()).collect());
+    let ratio_days: Vec<i64> = params
+        .custom_uptime_ratios
+        .as_deref()
+        .map(|days| days.split('-').filter_map(|d| d.trim().parse().ok()).collect())
+        .unwrap_or_default();
+    let targets: Vec<_> = monitor
+        .monitor_targets()
+        .into_iter()
+        .filter(|t| wanted.as_ref().is_none_or(|ids| ids.contains(&monitor_id(t.address))))
+        .collect();
+    let offset = params.offset.unwrap_or(0);
+    let limit = params.limit.unwrap_or(MAX_PAGE).clamp(1, MAX_PAGE);
+
+    let monitors: Vec<JsonValue> = targets
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|target| {
+            let addr = target.address;
+            let name = addr.to_string();
+            let status = match (monitor.is_paused(addr), monitor.state(addr)) {
+                (true, _) => STATUS_PAUSED,
+                (false, None) => STATUS_NOT_CHECKED,
+                (false, Some(TargetState::Up)) => STATUS_UP,
+                (false, Some(TargetState::Down)) => STATUS_DOWN,
+            };
+            let (kind, sub_type, port, url) = match target.check {
+                CheckKind::Icmp => (TYPE_PING, json!(""), json!(""), addr.ip().to_string()),
+                // UptimeRobot has no custom checks; a port monitor is the closest.
+                CheckKind::Tcp | CheckKind::Udp | CheckKind::Windows | CheckKind::Systemd | CheckKind::Disk | CheckKind::Composite | CheckKind::Plugin => (TYPE_PORT, json!(SUB_TYPE_CUSTOM_PORT), json!(addr.port()), addr.ip().to_string()),
+                CheckKind::Http | CheckKind::Browser | CheckKind::S3 | CheckKind::ApiQuota => (TYPE_HTTP, json!(""), json!(""), target.url()),
+            };
+            let mut entry = json!({
+                "id": monitor_id(addr),
+                "friendly_name": target.metadata.get("name").cloned().unwrap_or_else(|| name.clone()),
+                "url": url,
+                "type": kind,
+                "sub_type": sub_type,
+                "keyword_type": "",
+                "keyword_value": "",
+                "http_username": "",
+                "http_password": "",
+                "port": port,
+                "interval": target.interval.unwrap_or(monitor.default_interval()).as_secs(),
+                "status": status,
+                "create_datetime": 0,
+            });
+            if !ratio_days.is_empty() {
+                // Without checks in the period there was no downtime either.
+                let ratios: Vec<String> = ratio_days
+                    .iter()
+                    .map(|days| history.uptime(&name, now - ChronoDuration::days(*days), annotations).unwrap_or(1.0))
+                    .map(|uptime| format!("{:.3}", uptime * 100.0))
+                    .collect();
+                entry["custom_uptime_ratio"] = json!(ratios.join("-"));
+            }
+            if params.response_times == Some(1) {
+                let limit = params.response_times_limit.unwrap_or(DEFAULT_RESPONSE_TIMES);
+                let times: Vec<(i64, f64)> = history
+                    .latency_series(&name, DateTime::<Utc>::MIN_UTC)
+                    .into_iter()
+                    .rev()
+                    .filter_map(|(at, ms)| Some((at.timestamp(), ms?)))
+                    .take(limit)
+                    .collect();
+                let average = times.iter().map(|(_, ms)| ms).sum::<f64>() / times.len().max(1) as f64;
+                entry["response_times"] = times
+                    .iter()
+                    .map(|(at, ms)| json!({"datetime": at, "value": ms.round() as u64}))
+                    .collect();
+                entry["average_response_time"] = json!(format!("{:.3}", average));
+            }
+            entry
+        })
+        .collect();
+
+    json!({
+        "stat": "ok",
+        "pagination": {"offset": offset, "limit": limit, "total": targets.len()},
+        "monitors": monitors,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::back_end::check_result::CheckResult;
+    use crate::back_end::event_bus::EventBus;
+    use crate::back_end::target::MonitorTarget;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_monitors_have_uptimerobot_shape() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let up = listener.local_addr().unwrap();
+        let ping: SocketAddr = "10.0.0.6:0".parse().unwrap();
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        monitor.add_monitor_target(MonitorTarget::new(up).with_metadata("name", "Shop")).unwrap();
+        monitor.add_monitor_target(MonitorTarget::new(ping).with_check(CheckKind::Icmp)).unwrap();
+        let result = monitor.run_check(up).await;
+        let history = History::default();
+        history.push(result);
+        history.push(CheckResult::failure(&up.to_string(), "refused"));
+
+        let params = GetMonitors {
+            response_times: Some(1),
+            custom_uptime_ratios: Some("1-7".to_string()),
+            ..GetMonitors::default()
+        };
+        let body = get_monitors(&monitor, &history, None, &params, Utc::now());
+        assert_eq!(body["stat"], "ok");
+        assert_eq!(body["pagination"]["total"], 2);
+        let shop = &body["monitors"][0];
+        assert_eq!((shop["friendly_name"].as_str(), shop["type"].as_u64(), shop["status"].as_u64()), (Some("Shop"), Some(4), Some(2)));
+        assert_eq!(shop["port"], up.port());
+        assert_eq!(shop["custom_uptime_ratio"], "50.000-50.000");
+        assert_eq!(shop["response_times"].as_array().unwrap().len(), 1);
+        assert_eq!(body["monitors"][1]["status"], 1);
+
+        let params = GetMonitors {
+            monitors: Some(monitor_id(ping).to_string()),
+            ..GetMonitors::default()
+        };
+        let body = get_monitors(&monitor, &history, None, &params, Utc::now());
+        assert_eq!(body["monitors"].as_array().unwrap().len(), 1);
+        assert_eq!(body["monitors"][0]["id"], monitor_id(ping));
+        assert!(body["monitors"][0].get("response_times").is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use super::check_result::CheckResult;
+use super::trend::median;
+
+/// Name used in reports for results that were produced on this host rather than by an agent.
+pub const CENTRAL: &str = "central";
+
+/// Thresholds for calling out vantage points that see a target differently.
+#[derive(Debug, Clone)]
+pub struct VantageConfig {
+    /// A target counts as fine centrally at or above this availability (0.99 = 99%).
+    pub healthy_availability: f64,
+    /// An agent below this availability is flagged when the target is fine centrally.
+    pub degraded_availability: f64,
+    /// The agent whose view counts as central, e.g. this instance's `[monitor] agent`;
+    /// results without an agent otherwise.
+    pub central: Option<String>,
+}
+
+impl Default for VantageConfig {
+    fn default() -> Self {
+        Self {
+            healthy_availability: 0.99,
+            degraded_availability: 0.95,
+            central: None,
+        }
+    }
+}
+
+/// How one target looked from one vantage point.
+#[derive(Debug, Clone, Serialize)]
+pub struct VantageStats {
+    pub agent: String,
+    pub checks: usize,
+    pub availability: f64,
+    pub median_latency_ms: Option<f64>,
+    /// Fine centrally, but not from here.
+    pub flagged: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetComparison {
+    pub target: String,
+    /// Span during which every vantage point reported; only results inside it are compared,
+    /// so an agent that joined late isn't judged on a different slice of time.
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub vantages: Vec<VantageStats>,
+}
+
+impl TargetComparison {
+    pub fn has_flags(&self) -> bool {
+        self.vantages.iter().any(|v| v.flagged)
+    }
+}
+
+/// Availability and latency per target and vantage point over a period.
+#[derive(Debug, Clone, Serialize)]
+pub struct VantageReport {
+    pub targets: Vec<TargetComparison>,
+    /// Zone the text report shows times in; JSON keeps UTC.
+    #[serde(skip)]
+    pub tz: Tz,
+}
+
+impl VantageReport {
+    /// Shows times in `tz` instead of UTC.
+    pub fn in_timezone(mut self, tz: Tz) -> Self {
+        self.tz = tz;
+        self
+    }
+}
+
+impl fmt::Display for VantageReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for target in &self.targets {
+            writeln!(
+                f,
+                "{} ({} - {})",
+                target.target,
+                target.from.with_timezone(&self.tz).format("%Y-%m-%d %H:%M"),
+                target.to.with_timezone(&self.tz).format("%Y-%m-%d %H:%M")
+            )?;
+            for v in &target.vantages {
+                let latency = v
+                    .median_latency_ms
+                    .map(|ms| format!("{:.1} ms", ms))
+                    .unwrap_or_else(|| "-".to_string());
+                writeln!(
+                    f,
+                    "  {} {:<16} {:>7.2}% {:>10} ({} checks)",
+                    if v.flagged { "!" } else { " " },
+                    v.agent,
+                    v.availability * 100.0,
+                    latency,
+                    v.checks
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Compares the results of targets watched from several vantage points between `from` and
+/// `to`. Targets seen by a single vantage point only are left out.
+pub fn compare(results: &[CheckResult], from: DateTime<Utc>, to: DateTime<Utc>, config: &VantageConfig) -> VantageReport {
+    let mut per_target: BTreeMap<&str, BTreeMap<&str, Vec<&CheckResult>>> = BTreeMap::new();
+    for result in results.iter().filter(|r| r.timestamp >= from && r.timestamp <= to) {
+        let agent = result.agent.as_deref().unwrap_or(CENTRAL);
+        per_target
+            .entry(result.target.as_str())
+            .or_default()
+            .entry(agent)
+            .or_default()
+            .push(result);
+    }
+
+    let targets = per_target
+        .into_iter()
+        .filter(|(_, agents)| agents.len() > 1)
+        .filter_map(|(target, agents)| compare_target(target, &agents, config))
+        .collect();
+    VantageReport { targets, tz: Tz::UTC }
+}
+
+fn compare_target(target: &str, agents: &BTreeMap<&str, Vec<&CheckResult>>, config: &VantageConfig) -> Option<TargetComparison> {
+    let from = agents.values().filter_map(|r| r.iter().map(|r| r.timestamp).min()).max()?;
+    let to = agents.values().filter_map(|r| r.iter().map(|r| r.timestamp).max()).min()?;
+    if from > to {
+        // The agents never reported at the same time, so there is nothing fair to compare.
+        return None;
+    }
+
+    let mut vantages: Vec<VantageStats> = agents
+        .iter()
+        .filter_map(|(agent, results)| {
+            let in_span: Vec<_> = results.iter().filter(|r| r.timestamp >= from && r.timestamp <= to).collect();
+            if in_span.is_empty() {
+                return None;
+            }
+            let latencies: Vec<f64> = in_span.iter().filter(|r| r.success).filter_map(|r| r.latency_ms()).collect();
+            Some(VantageStats {
+                agent: agent.to_string(),
+                checks: in_span.len(),
+                availability: in_span.iter().filter(|r| r.success).count() as f64 / in_span.len() as f64,
+                median_latency_ms: median(&latencies),
+                flagged: false,
+            })
+        })
+        .collect();
+
+    let central = config.central.as_deref().unwrap_or(CENTRAL);
+    let central_ok = vantages
+        .iter()
+        .find(|v| v.agent == central)
+        .is_some_and(|v| v.availability >= config.healthy_availability);
+    if central_ok {
+        for v in vantages.iter_mut().filter(|v| v.agent != central) {
+            v.flagged = v.availability < config.degraded_availability;
+        }
+    }
+    Some(TargetComparison {
+        target: target.to_string(),
+        from,
+        to,
+        vantages,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration as ChronoDuration, TimeZone};
+    use std::time::Duration;
+
+    fn result(minute: i64, agent: Option<&str>, success: bool) -> CheckResult {
+        let mut r = if success {
+            CheckResult::success("erp:443", Duration::from_millis(20))
+        } else {
+            CheckResult::failure("erp:443", "timed out")
+        };
+        r.timestamp = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap() + ChronoDuration::minutes(minute);
+        r.agent = agent.map(str::to_string);
+        r
+    }
+
+    #[test]
+    fn test_branch_outage_is_flagged_while_central_is_fine() {
+        let mut results = Vec::new();
+        for minute in 0..60 {
+            results.push(result(minute, None, true));
+            results.push(result(minute, Some("branch-lyon"), minute % 2 == 0));
+            // Only joins half way through; its earlier results must not be compared.
+            if minute >= 30 {
+                results.push(result(minute, Some("branch-oslo"), true));
+            }
+        }
+        // A late failure of the central check outside the period is ignored.
+        results.push(result(120, None, false));
+
+        let from = results[0].timestamp;
+        let report = compare(&results, from, from + ChronoDuration::minutes(60), &VantageConfig::default());
+        let erp = &report.targets[0];
+        assert_eq!(erp.from, from + ChronoDuration::minutes(30));
+        let stats: BTreeMap<_, _> = erp.vantages.iter().map(|v| (v.agent.as_str(), v)).collect();
+        assert_eq!(stats["central"].checks, 30);
+        assert!(stats["branch-lyon"].flagged);
+        assert!(!stats["branch-oslo"].flagged);
+        assert!(report.to_string().contains("! branch-lyon"));
+
+        // An instance with an agent name of its own judges the others by its own view.
+        let named = VantageConfig {
+            central: Some("branch-lyon".to_string()),
+            ..VantageConfig::default()
+        };
+        let report = compare(&results, from, from + ChronoDuration::minutes(60), &named);
+        assert!(report.targets[0].vantages.iter().all(|v| !v.flagged));
+    }
+}
@@ -0,0 +1,303 @@
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use super::check_result::CheckResult;
+use super::diagnostics::Hint;
+use super::failure_kind::FailureKind;
+
+// Per-invocation sandbox limits. A plugin that needs more than this is doing too much
+// work inside a single check.
+const PLUGIN_FUEL: u64 = 50_000_000;
+const PLUGIN_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Manifest shipped next to a plugin's `.wasm` file, e.g. `redis_ping.json`:
+///
+/// ```text
+/// {
+///   "name": "redis_ping",
+///   "version": "0.1.0",
+///   "module": "redis_ping.wasm",
+///   "config_schema": {
+///     "required": ["host"],
+///     "properties": { "host": { "type": "string" }, "port": { "type": "integer" } }
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub version: String,
+    /// Path of the module, relative to the manifest.
+    pub module: PathBuf,
+    #[serde(default)]
+    pub description: String,
+    /// Subset of JSON Schema: `required` and per-property `type` are enforced.
+    #[serde(default)]
+    pub config_schema: JsonValue,
+}
+
+// What the plugin's `check` export writes back as JSON.
+#[derive(Debug, Deserialize)]
+struct PluginOutput {
+    success: bool,
+    latency_ms: Option<f64>,
+    error: Option<String>,
+}
+
+/// A custom check type loaded from a WebAssembly module.
+///
+/// Plugins get no host imports at all, so they cannot touch the file system or network
+/// of the monitor. The ABI is deliberately small; the module must export:
+///
+/// * `memory`
+/// * `alloc(len: i32) -> i32` returning a buffer for the JSON encoded target config
+/// * `check(ptr: i32, len: i32) -> i64` returning `(out_ptr << 32) | out_len` of a JSON
+///   object `{"success": bool, "latency_ms": number?, "error": string?}`
+pub struct WasmPlugin {
+    manifest: PluginManifest,
+    engine: Engine,
+    module: Module,
+}
+
+impl WasmPlugin {
+    pub fn load(manifest_path: &Path) -> Result<Self, Box<dyn Error>> {
+        let manifest: PluginManifest = serde_json::from_str(&fs::read_to_string(manifest_path)?)?;
+        let base_dir = manifest_path.parent().unwrap_or_else(|| Path::new("."));
+        let bytes = fs::read(base_dir.join(&manifest.module))?;
+        Self::from_bytes(manifest, &bytes)
+    }
+
+    pub fn from_bytes(manifest: PluginManifest, wasm: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::new(&engine, wasm)?;
+        Ok(Self {
+            manifest,
+            engine,
+            module,
+        })
+    }
+
+    pub fn manifest(&self) -> &PluginManifest {
+        &self.manifest
+    }
+
+    /// Checks a target's plugin config against the manifest's schema.
+    pub fn validate_config(&self, config: &JsonValue) -> Result<(), String> {
+        validate_against_schema(&self.manifest.config_schema, config)
+    }
+
+    /// Runs the plugin once for `target`. This is CPU bound, so async callers should wrap
+    /// it in `tokio::task::spawn_blocking`.
+    pub fn run_check(&self, target: &str, config: &JsonValue) -> CheckResult {
+        if let Err(e) = self.validate_config(config) {
+            return CheckResult::failure(target, format!("invalid config for plugin '{}': {}", self.manifest.name, e));
+        }
+        match self.invoke(config) {
+            Ok(output) => CheckResult {
+                success: output.success,
+                latency: output
+                    .latency_ms
+                    .filter(|ms| ms.is_finite() && *ms >= 0.0)
+                    .map(|ms| Duration::from_secs_f64(ms / 1000.0)),
+                failure_kind: (!output.success).then(|| FailureKind::classify(output.error.as_deref().unwrap_or_default())),
+                hint: output.error.as_deref().and_then(Hint::diagnose),
+                error: output.error,
+                ..CheckResult::success(target, Duration::ZERO)
+            },
+            Err(e) => CheckResult::failure(target, format!("plugin '{}' failed: {}", self.manifest.name, e)),
+        }
+    }
+
+    fn invoke(&self, config: &JsonValue) -> Result<PluginOutput, Box<dyn Error>> {
+        let limits = StoreLimitsBuilder::new().memory_size(PLUGIN_MEMORY_BYTES).build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(PLUGIN_FUEL)?;
+
+        // A fresh instance per check means no state leaks between runs.
+        let instance = Instance::new(&mut store, &self.module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or("plugin does not export 'memory'")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let check = instance.get_typed_func::<(i32, i32), i64>(&mut store, "check")?;
+
+        let input = serde_json::to_vec(config)?;
+        let input_len = i32::try_from(input.len())?;
+        let input_ptr = alloc.call(&mut store, input_len)?;
+        memory.write(&mut store, input_ptr as u32 as usize, &input)?;
+
+        let packed = check.call(&mut store, (input_ptr, input_len))? as u64;
+        let out_ptr = (packed >> 32) as usize;
+        let out_len = (packed & 0xffff_ffff) as usize;
+        let mut output = vec![0u8; out_len];
+        memory.read(&store, out_ptr, &mut output)?;
+        Ok(serde_json::from_slice(&output)?)
+    }
+}
+
+fn validate_against_schema(schema: &JsonValue, config: &JsonValue) -> Result<(), String> {
+    if schema.is_null() {
+        return Ok(());
+    }
+    let object = config.as_object().ok_or("config must be a JSON object")?;
+
+    if let Some(required) = schema.get("required").and_then(JsonValue::as_array) {
+        for key in required.iter().filter_map(JsonValue::as_str) {
+            if !object.contains_key(key) {
+                return Err(format!("missing required field '{}'", key));
+            }
+        }
+    }
+
+    if let Some(properties) = schema.get("properties").and_then(JsonValue::as_object) {
+        for (key, spec) in properties {
+            let (Some(value), Some(expected)) = (object.get(key), spec.get("type").and_then(JsonValue::as_str)) else {
+                continue;
+            };
+            let matches = match expected {
+                "string" => value.is_string(),
+                "number" => value.is_number(),
+                "integer" => value.is_i64() || value.is_u64(),
+                "boolean" => value.is_boolean(),
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                _ => true,
+            };
+            if !matches {
+                return Err(format!("field '{}' must be of type {}", key, expected));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// All plugins found in a directory, keyed by the check type name from their manifest.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: HashMap<String, WasmPlugin>,
+}
+
+impl PluginRegistry {
+    /// Loads every `*.json` manifest in `dir`. Broken plugins are reported and skipped.
+    pub fn load_dir(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut registry = Self::default();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match WasmPlugin::load(&path) {
+                Ok(plugin) => {
+                    println!(
+                        "Loaded check plugin '{}' v{}",
+                        plugin.manifest.name, plugin.manifest.version
+                    );
+                    registry.plugins.insert(plugin.manifest.name.clone(), plugin);
+                }
+                Err(e) => eprintln!("Skipping plugin {}: {}", path.display(), e),
+            }
+        }
+        Ok(registry)
+    }
+
+    pub fn get(&self, check_type: &str) -> Option<&WasmPlugin> {
+        self.plugins.get(check_type)
+    }
+
+    /// The check type names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.plugins.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Minimal plugin that ignores its input and always reports success in 12.5 ms.
+    const ALWAYS_UP_WAT: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "{\"success\":true,\"latency_ms\":12.5}")
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "check") (param i32 i32) (result i64) (i64.const 34)))
+    "#;
+
+    const SPIN_WAT: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+          (func (export "check") (param i32 i32) (result i64) (loop (br 0)) (i64.const 0)))
+    "#;
+
+    fn manifest() -> PluginManifest {
+        serde_json::from_value(json!({
+            "name": "always_up",
+            "version": "0.1.0",
+            "module": "always_up.wasm",
+            "config_schema": {
+                "required": ["host"],
+                "properties": { "host": { "type": "string" } }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_plugin_reports_result() {
+        let plugin = WasmPlugin::from_bytes(manifest(), ALWAYS_UP_WAT.as_bytes()).unwrap();
+        let result = plugin.run_check("db01", &json!({ "host": "db01" }));
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.latency, Some(Duration::from_micros(12_500)));
+    }
+
+    #[test]
+    fn test_config_is_validated() {
+        let plugin = WasmPlugin::from_bytes(manifest(), ALWAYS_UP_WAT.as_bytes()).unwrap();
+        assert!(!plugin.run_check("db01", &json!({})).success);
+        assert!(!plugin.run_check("db01", &json!({ "host": 5 })).success);
+    }
+
+    #[tokio::test]
+    async fn test_monitor_runs_plugin_targets() {
+        use crate::back_end::event_bus::EventBus;
+        use crate::back_end::monitor::Monitor;
+        use crate::back_end::target::{CheckSpec, MonitorTarget};
+
+        let monitor = Monitor::new(EventBus::new(), Duration::from_secs(1));
+        let addr = "10.0.0.7:6379".parse().unwrap();
+        let spec = CheckSpec::Plugin {
+            name: "always_up".to_string(),
+            config: json!({ "host": "cache" }),
+        };
+        monitor.add_monitor_target(MonitorTarget::new(addr).with_spec(spec)).unwrap();
+        assert!(!monitor.run_check(addr).await.success);
+
+        let mut registry = PluginRegistry::default();
+        registry.plugins.insert("always_up".to_string(), WasmPlugin::from_bytes(manifest(), ALWAYS_UP_WAT.as_bytes()).unwrap());
+        monitor.set_plugins(registry);
+        assert_eq!(monitor.plugins().names(), ["always_up"]);
+        let result = monitor.run_check(addr).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.target, addr.to_string());
+    }
+
+    #[test]
+    fn test_runaway_plugin_runs_out_of_fuel() {
+        let plugin = WasmPlugin::from_bytes(manifest(), SPIN_WAT.as_bytes()).unwrap();
+        let result = plugin.run_check("db01", &json!({ "host": "db01" }));
+        assert!(!result.success);
+    }
+}
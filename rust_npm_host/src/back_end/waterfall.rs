@@ -0,0 +1,271 @@
+use chrono::{DateTime, Duration as ChronoDuration, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value as JsonValue, json};
+use std::fmt;
+
+use super::check_result::CheckResult;
+
+/// Width of the bar area in the text rendering, in characters.
+pub const TEXT_WIDTH: usize = 50;
+
+/// Name of the HAR artifact browser checks attach to their results.
+pub const HAR_ARTIFACT: &str = "page.har";
+
+/// One entry of the page's Resource Timing buffer (`performance.getEntriesByType('resource')`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceTiming {
+    pub name: String,
+    /// `script`, `img`, `css`, `fetch`, ... as reported by the browser.
+    pub initiator: String,
+    /// Milliseconds since navigation start.
+    pub start_ms: f64,
+    pub duration_ms: f64,
+    /// Bytes over the wire; 0 for cached and (without Timing-Allow-Origin) cross-origin entries.
+    pub transfer_bytes: u64,
+}
+
+impl ResourceTiming {
+    pub fn end_ms(&self) -> f64 {
+        self.start_ms + self.duration_ms
+    }
+
+    /// Host part of the resource URL, to tell first- from third-party requests.
+    pub fn host(&self) -> &str {
+        let rest = self.name.split_once("://").map_or(self.name.as_str(), |(_, rest)| rest);
+        rest.split(['/', '?', '#']).next().unwrap_or(rest)
+    }
+}
+
+/// Resource timings of one browser check run, ordered by start time.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Waterfall {
+    pub url: String,
+    /// When the check considered the page functional, in ms since navigation start.
+    pub functional_ms: Option<f64>,
+    pub entries: Vec<ResourceTiming>,
+}
+
+impl Waterfall {
+    /// Builds a waterfall from the JSON array the browser returns for the resource entries.
+    /// Entries missing the timing fields are skipped.
+    pub fn from_entries(url: &str, functional_ms: Option<f64>, entries: &JsonValue) -> Self {
+        let mut entries: Vec<ResourceTiming> = entries
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|entry| {
+                Some(ResourceTiming {
+                    name: entry["name"].as_str()?.to_string(),
+                    initiator: entry["initiatorType"].as_str().unwrap_or("other").to_string(),
+                    start_ms: entry["startTime"].as_f64()?,
+                    duration_ms: entry["duration"].as_f64()?,
+                    transfer_bytes: entry["transferSize"].as_u64().unwrap_or(0),
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| a.start_ms.total_cmp(&b.start_ms));
+        Self {
+            url: url.to_string(),
+            functional_ms,
+            entries,
+        }
+    }
+
+    /// End of the last resource or the functional time, whichever is later.
+    pub fn span_ms(&self) -> f64 {
+        self.entries
+            .iter()
+            .map(ResourceTiming::end_ms)
+            .chain(self.functional_ms)
+            .fold(0.0, f64::max)
+    }
+
+    /// The `count` longest resources that finished before the page became functional, i.e.
+    /// the ones that could have delayed it.
+    pub fn slowest_blocking(&self, count: usize) -> Vec<&ResourceTiming> {
+        let cutoff = self.functional_ms.unwrap_or(f64::INFINITY);
+        let mut blocking: Vec<_> = self.entries.iter().filter(|e| e.end_ms() <= cutoff).collect();
+        blocking.sort_by(|a, b| b.duration_ms.total_cmp(&a.duration_ms));
+        blocking.truncate(count);
+        blocking
+    }
+
+    /// One line per resource with a bar positioned on a shared time axis; `|` marks the
+    /// functional time.
+    pub fn text_rows(&self, width: usize) -> Vec<String> {
+        let span = self.span_ms().max(1.0);
+        let column = |ms: f64| ((ms / span) * width as f64).round() as usize;
+        let marker = self.functional_ms.map(column);
+        self.entries
+            .iter()
+            .map(|entry| {
+                let (start, end) = (column(entry.start_ms), column(entry.end_ms()).min(width));
+                let bar: String = (0..width)
+                    .map(|i| match i {
+                        _ if (start..end.max(start + 1)).contains(&i) => '#',
+                        _ if Some(i) == marker => '|',
+                        _ => ' ',
+                    })
+                    .collect();
+                format!("{} {:>7.0} ms  {:<8} {}", bar, entry.duration_ms, entry.initiator, short_name(&entry.name))
+            })
+            .collect()
+    }
+
+    /// A self-contained HTML table for the web UI; bars are plain positioned divs.
+    pub fn to_html(&self) -> String {
+        let span = self.span_ms().max(1.0);
+        let mut html = format!(
+            "<table class=\"waterfall\"><caption>{}</caption>\n<tr><th>Resource</th><th>Type</th><th>ms</th><th></th></tr>\n",
+            escape_html(&self.url)
+        );
+        for entry in &self.entries {
+            html.push_str(&format!(
+                "<tr><td title=\"{name}\">{short}</td><td>{initiator}</td><td>{ms:.0}</td>\
+                 <td class=\"bar\"><div style=\"margin-left:{left:.2}%;width:{width:.2}%\"></div></td></tr>\n",
+                name = escape_html(&entry.name),
+                short = escape_html(short_name(&entry.name)),
+                initiator = escape_html(&entry.initiator),
+                ms = entry.duration_ms,
+                left = entry.start_ms / span * 100.0,
+                width = (entry.duration_ms / span * 100.0).max(0.2),
+            ));
+        }
+        html.push_str("</table>");
+        html
+    }
+
+    /// The waterfall as a HAR 1.2 log, for tools like the browsers' network panels.
+    /// Resource Timing has no headers or status codes, so those are left empty; `started`
+    /// is the navigation start.
+    pub fn to_har(&self, started: DateTime<Utc>) -> JsonValue {
+        let at = |ms: f64| (started + ChronoDuration::microseconds((ms * 1000.0) as i64)).to_rfc3339_opts(SecondsFormat::Millis, true);
+        let entries: Vec<JsonValue> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "pageref": "page_1",
+                    "startedDateTime": at(entry.start_ms),
+                    "time": entry.duration_ms,
+                    "request": {
+                        "method": "GET", "url": entry.name, "httpVersion": "", "cookies": [], "headers": [],
+                        "queryString": [], "headersSize": -1, "bodySize": -1,
+                    },
+                    "response": {
+                        "status": 0, "statusText": "", "httpVersion": "", "cookies": [], "headers": [],
+                        "content": { "size": entry.transfer_bytes, "mimeType": "" },
+                        "redirectURL": "", "headersSize": -1, "bodySize": entry.transfer_bytes,
+                    },
+                    "cache": {},
+                    "timings": { "send": 0, "wait": entry.duration_ms, "receive": 0 },
+                    "_initiator": entry.initiator,
+                    "_startMs": entry.start_ms,
+                })
+            })
+            .collect();
+        json!({
+            "log": {
+                "version": "1.2",
+                "creator": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                "pages": [{
+                    "id": "page_1",
+                    "title": self.url,
+                    "startedDateTime": at(0.0),
+                    "pageTimings": { "onLoad": self.functional_ms.unwrap_or(-1.0) },
+                }],
+                "entries": entries,
+            }
+        })
+    }
+
+    /// The waterfall of a browser check, from the HAR artifact it attached.
+    pub fn of_result(result: &CheckResult) -> Option<Self> {
+        let artifact = result.artifacts.iter().find(|a| a.name == HAR_ARTIFACT)?;
+        let har = serde_json::from_slice(&artifact.bytes().ok()?).ok()?;
+        Self::from_har(&har)
+    }
+
+    /// Reads back a log written by `to_har`; `None` if it has no page.
+    pub fn from_har(har: &JsonValue) -> Option<Self> {
+        let page = &har["log"]["pages"][0];
+        let entries = har["log"]["entries"]
+            .as_array()?
+            .iter()
+            .filter_map(|entry| {
+                Some(ResourceTiming {
+                    name: entry["request"]["url"].as_str()?.to_string(),
+                    initiator: entry["_initiator"].as_str().unwrap_or("other").to_string(),
+                    start_ms: entry["_startMs"].as_f64()?,
+                    duration_ms: entry["time"].as_f64()?,
+                    transfer_bytes: entry["response"]["bodySize"].as_u64().unwrap_or(0),
+                })
+            })
+            .collect();
+        Some(Self {
+            url: page["title"].as_str()?.to_string(),
+            functional_ms: page["pageTimings"]["onLoad"].as_f64().filter(|ms| *ms >= 0.0),
+            entries,
+        })
+    }
+}
+
+impl fmt::Display for Waterfall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} resources over {:.0} ms", self.url, self.entries.len(), self.span_ms())?;
+        if let Some(ms) = self.functional_ms {
+            write!(f, ", functional after {:.0} ms", ms)?;
+        }
+        for row in self.text_rows(TEXT_WIDTH) {
+            write!(f, "\n{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+// Long URLs would push the bars off screen; keep host and file name.
+fn short_name(name: &str) -> &str {
+    let rest = name.split_once("://").map_or(name, |(_, rest)| rest);
+    let rest = rest.split(['?', '#']).next().unwrap_or(rest);
+    if rest.len() <= 60 {
+        return rest;
+    }
+    let start = rest.char_indices().map(|(i, _)| i).find(|&i| rest.len() - i <= 60).unwrap_or(0);
+    &rest[start..]
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_waterfall_from_resource_entries() {
+        let entries = serde_json::json!([
+            {"name": "https://cdn.tracker.example/t.js?id=1", "initiatorType": "script", "startTime": 120.0, "duration": 900.0, "transferSize": 40000},
+            {"name": "https://shop.example/app.css", "initiatorType": "link", "startTime": 20.0, "duration": 80.0},
+            {"name": "https://shop.example/late.png", "initiatorType": "img", "startTime": 1500.0, "duration": 500.0},
+            {"initiatorType": "script", "startTime": 1.0, "duration": 1.0}
+        ]);
+        let waterfall = Waterfall::from_entries("https://shop.example/", Some(1100.0), &entries);
+        assert_eq!(waterfall.entries.len(), 3);
+        assert_eq!(waterfall.entries[0].host(), "shop.example");
+        assert_eq!(waterfall.span_ms(), 2000.0);
+
+        let blocking = waterfall.slowest_blocking(1);
+        assert_eq!(blocking[0].host(), "cdn.tracker.example");
+
+        let rows = waterfall.text_rows(20);
+        assert!(rows[1].starts_with(" ######### |"));
+        assert!(rows[1].ends_with("cdn.tracker.example/t.js"));
+        assert!(waterfall.to_html().contains("margin-left:6.00%;width:45.00%"));
+
+        let har = waterfall.to_har(Utc::now());
+        assert_eq!(har["log"]["entries"][1]["request"]["url"], "https://cdn.tracker.example/t.js?id=1");
+        assert_eq!(Waterfall::from_har(&har), Some(waterfall));
+    }
+}
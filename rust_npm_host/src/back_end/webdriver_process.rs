@@ -0,0 +1,198 @@
+use std::error::Error;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+use super::browser_emulator::BrowserKind;
+
+/// Driver binaries `find_driver("auto")` looks for on PATH, in this order, and the browser
+/// each drives.
+pub const DRIVERS: [(&str, BrowserKind); 4] = [
+    ("chromedriver", BrowserKind::Chrome),
+    ("msedgedriver", BrowserKind::Edge),
+    ("geckodriver", BrowserKind::Firefox),
+    ("safaridriver", BrowserKind::Safari),
+];
+
+/// The driver binary for `spec`: `auto` for the first of `DRIVERS` on PATH (or `browser`'s
+/// driver, if given), a bare name like `geckodriver` to look up on PATH, or a path.
+pub fn find_driver(spec: &str, browser: Option<BrowserKind>) -> Result<PathBuf, String> {
+    let spec = spec.trim();
+    if spec.eq_ignore_ascii_case("auto") {
+        let names: Vec<&str> = DRIVERS.iter().filter(|(_, b)| browser.is_none_or(|browser| *b == browser)).map(|(name, _)| *name).collect();
+        return names
+            .iter()
+            .find_map(|name| on_path(name))
+            .ok_or_else(|| format!("no WebDriver found on PATH (looked for {})", names.join(", ")));
+    }
+    let path = Path::new(spec);
+    if path.components().count() == 1 {
+        return on_path(spec).ok_or_else(|| format!("{} not found on PATH", spec));
+    }
+    if path.is_file() {
+        Ok(path.to_path_buf())
+    } else {
+        Err(format!("{} does not exist", path.display()))
+    }
+}
+
+fn on_path(name: &str) -> Option<PathBuf> {
+    let file = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(&std::env::var_os("PATH")?).map(|dir| dir.join(&file)).find(|path| path.is_file())
+}
+
+/// The browser the driver at `binary` drives, by its file name; Chrome if it's not one of
+/// `DRIVERS`.
+pub fn driver_browser(binary: &Path) -> BrowserKind {
+    let stem = binary.file_stem().and_then(|s| s.to_str()).unwrap_or_default().to_ascii_lowercase();
+    DRIVERS.iter().find(|(name, _)| stem.starts_with(name)).map_or(BrowserKind::Chrome, |(_, browser)| *browser)
+}
+
+// chromedriver and msedgedriver only take `--port=N`; geckodriver and safaridriver only
+// `--port N`.
+fn port_args(binary: &Path, port: u16) -> Vec<String> {
+    match driver_browser(binary) {
+        BrowserKind::Firefox | BrowserKind::Safari => vec!["--port".to_string(), port.to_string()],
+        BrowserKind::Chrome | BrowserKind::Edge => vec![format!("--port={}", port)],
+    }
+}
+
+/// A WebDriver server this process started and looks after, so browser checks work
+/// without one started by hand. It listens on a free local port, keeps that port across
+/// restarts, and is killed when dropped.
+pub struct ManagedDriver {
+    binary: PathBuf,
+    addr: SocketAddr,
+    startup_timeout: Duration,
+    child: Mutex<Child>,
+    restarts: AtomicU32,
+    stopped: AtomicBool,
+}
+
+impl ManagedDriver {
+    /// Starts `binary` and waits up to `startup_timeout` for it to accept connections.
+    pub async fn start(binary: PathBuf, startup_timeout: Duration) -> Result<Arc<Self>, Box<dyn Error>> {
+        // Let the OS pick a free port; the tiny window before the driver binds it is acceptable here.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let child = spawn(&binary, addr, startup_timeout).await?;
+        Ok(Arc::new(Self {
+            binary,
+            addr,
+            startup_timeout,
+            child: Mutex::new(child),
+            restarts: AtomicU32::new(0),
+            stopped: AtomicBool::new(false),
+        }))
+    }
+
+    /// Where to open sessions, e.g. `http://127.0.0.1:41237`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn browser(&self) -> BrowserKind {
+        driver_browser(&self.binary)
+    }
+
+    /// How often the driver had to be restarted.
+    pub fn restarts(&self) -> u32 {
+        self.restarts.load(Ordering::Relaxed)
+    }
+
+    /// Restarts the driver if it has exited; returns whether it had. A failed restart is
+    /// an error and is tried again on the next call.
+    pub async fn ensure_running(&self) -> Result<bool, Box<dyn Error>> {
+        let mut child = self.child.lock().await;
+        if self.stopped.load(Ordering::Relaxed) || child.try_wait()?.is_none() {
+            return Ok(false);
+        }
+        *child = spawn(&self.binary, self.addr, self.startup_timeout).await?;
+        self.restarts.fetch_add(1, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    /// Checks on the driver every `every` and restarts it when it crashed, until `stop`.
+    /// Sessions open on a crashed driver are gone; pools replace them on their next check.
+    pub async fn supervise(self: Arc<Self>, every: Duration) {
+        let mut ticker = tokio::time::interval(every);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        while !self.stopped.load(Ordering::Relaxed) {
+            ticker.tick().await;
+            match self.ensure_running().await {
+                Ok(true) => eprintln!("{} exited; restarted it on {} (restart {})", self.binary.display(), self.addr, self.restarts()),
+                Ok(false) => {}
+                Err(e) => eprintln!("Cannot restart {}: {}", self.binary.display(), e),
+            }
+        }
+    }
+
+    /// Kills the driver for good.
+    pub async fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        if let Err(e) = self.child.lock().await.kill().await {
+            eprintln!("Error stopping {}: {}", self.binary.display(), e);
+        }
+    }
+}
+
+async fn spawn(binary: &Path, addr: SocketAddr, timeout: Duration) -> Result<Child, Box<dyn Error>> {
+    let mut child = Command::new(binary)
+        .args(port_args(binary, addr.port()))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("cannot start {}: {}", binary.display(), e))?;
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Err(format!("{} exited ({})", binary.display(), status).into());
+        }
+        if tokio::net::TcpStream::connect(addr).await.is_ok() {
+            return Ok(child);
+        }
+        if Instant::now() >= deadline {
+            child.kill().await.ok();
+            return Err(format!("{} not ready after {:?}", binary.display(), timeout).into());
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drivers_are_told_apart_by_name() {
+        assert_eq!(driver_browser(Path::new("/usr/local/bin/geckodriver")), BrowserKind::Firefox);
+        assert_eq!(driver_browser(Path::new("/opt/edge/msedgedriver.exe")), BrowserKind::Edge);
+        assert_eq!(driver_browser(Path::new("/opt/chromedriver-linux64/chromedriver")), BrowserKind::Chrome);
+        assert_eq!(port_args(Path::new("chromedriver"), 9515), ["--port=9515"]);
+        assert_eq!(port_args(Path::new("geckodriver"), 4444), ["--port", "4444"]);
+        assert!(find_driver("/nonexistent/dir/chromedriver", None).is_err());
+        assert!(find_driver("no-such-driver-xyz", None).is_err());
+    }
+
+    #[tokio::test]
+    #[ignore] // Ignored because it requires chromedriver or geckodriver on PATH
+    async fn test_crashed_driver_is_restarted() {
+        let binary = find_driver("auto", None).unwrap();
+        let driver = ManagedDriver::start(binary, Duration::from_secs(10)).await.unwrap();
+        assert!(!driver.ensure_running().await.unwrap());
+
+        driver.child.lock().await.kill().await.unwrap();
+        assert!(driver.ensure_running().await.unwrap());
+        assert_eq!(driver.restarts(), 1);
+        assert!(tokio::net::TcpStream::connect(driver.addr).await.is_ok());
+        driver.stop().await;
+    }
+}
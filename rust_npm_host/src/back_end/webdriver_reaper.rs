@@ -0,0 +1,254 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A WebDriver session one of our checks opened.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub webdriver_url: String,
+    pub session_id: String,
+    pub started_at: DateTime<Utc>,
+    /// Process that opened the session; sessions of other (crashed) processes are orphans.
+    pub pid: u32,
+}
+
+/// Sessions opened by browser checks and not closed yet, kept in a small JSON file so they
+/// can still be found after a crash.
+#[derive(Debug, Clone)]
+pub struct SessionRegistry {
+    path: PathBuf,
+    // Serializes read-modify-write cycles of the file within this process.
+    lock: Arc<Mutex<()>>,
+}
+
+impl SessionRegistry {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// `sessions.json` in the user's data directory, shared by all processes of a user.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("rust_npm").join("sessions.json"))
+    }
+
+    pub fn sessions(&self) -> Result<Vec<SessionRecord>, Box<dyn Error>> {
+        let _guard = self.lock.lock().unwrap();
+        self.load()
+    }
+
+    pub fn record(&self, webdriver_url: &str, session_id: &str) -> Result<(), Box<dyn Error>> {
+        let _guard = self.lock.lock().unwrap();
+        let mut sessions = self.load()?;
+        sessions.push(SessionRecord {
+            webdriver_url: webdriver_url.to_string(),
+            session_id: session_id.to_string(),
+            started_at: Utc::now(),
+            pid: std::process::id(),
+        });
+        self.save(&sessions)
+    }
+
+    pub fn forget(&self, session_id: &str) -> Result<(), Box<dyn Error>> {
+        let _guard = self.lock.lock().unwrap();
+        let mut sessions = self.load()?;
+        sessions.retain(|s| s.session_id != session_id);
+        self.save(&sessions)
+    }
+
+    fn load(&self) -> Result<Vec<SessionRecord>, Box<dyn Error>> {
+        match fs::read_to_string(&self.path) {
+            Ok(content) if !content.trim().is_empty() => Ok(serde_json::from_str(&content)?),
+            Ok(_) => Ok(Vec::new()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, sessions: &[SessionRecord]) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(sessions)?)?;
+        Ok(())
+    }
+}
+
+/// What a reaper pass did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReapReport {
+    pub killed: Vec<String>,
+    /// Sessions that could not be deleted, with the reason. They stay registered.
+    pub failed: Vec<(String, String)>,
+}
+
+/// Kills stale WebDriver sessions: ones left behind by crashed or killed processes, ones
+/// older than `max_age`, and (on Selenium Grid) sessions nobody registered at all.
+pub struct OrphanReaper {
+    registry: SessionRegistry,
+    max_age: ChronoDuration,
+    client: reqwest::Client,
+}
+
+impl OrphanReaper {
+    pub fn new(registry: SessionRegistry, max_age: ChronoDuration, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            registry,
+            max_age,
+            client: reqwest::Client::builder().timeout(timeout).build()?,
+        })
+    }
+
+    /// Deletes registered sessions that belong to another process or are too old.
+    pub async fn reap_registered(&self, now: DateTime<Utc>) -> Result<ReapReport, Box<dyn Error>> {
+        let own_pid = std::process::id();
+        self.delete_registered(|session| is_orphan(session, own_pid, self.max_age, now)).await
+    }
+
+    /// Deletes every registered session of this process, for a clean shutdown.
+    pub async fn close_own(&self) -> Result<ReapReport, Box<dyn Error>> {
+        let own_pid = std::process::id();
+        self.delete_registered(|session| session.pid == own_pid).await
+    }
+
+    async fn delete_registered(&self, select: impl Fn(&SessionRecord) -> bool) -> Result<ReapReport, Box<dyn Error>> {
+        let mut report = ReapReport::default();
+        let sessions = self.registry.sessions()?;
+        for session in sessions {
+            if !select(&session) {
+                continue;
+            }
+            match self.delete(&session.webdriver_url, &session.session_id).await {
+                Ok(()) => {
+                    self.registry.forget(&session.session_id)?;
+                    report.killed.push(session.session_id);
+                }
+                Err(e) => report.failed.push((session.session_id, e.to_string())),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Asks a Selenium Grid for its running sessions and deletes those older than
+    /// `max_age`. Plain chromedriver/geckodriver can't list sessions, so there only the
+    /// registry helps.
+    pub async fn reap_grid(&self, grid_url: &str, now: DateTime<Utc>) -> Result<ReapReport, Box<dyn Error>> {
+        let url = format!("{}/status", grid_url.trim_end_matches('/'));
+        let status: JsonValue = self.client.get(&url).send().await?.error_for_status()?.json().await?;
+        let mut report = ReapReport::default();
+        for (session_id, started_at) in grid_sessions(&status) {
+            if now - started_at < self.max_age {
+                continue;
+            }
+            match self.delete(grid_url, &session_id).await {
+                Ok(()) => {
+                    let _ = self.registry.forget(&session_id);
+                    report.killed.push(session_id);
+                }
+                Err(e) => report.failed.push((session_id, e.to_string())),
+            }
+        }
+        Ok(report)
+    }
+
+    /// Reaps the registry and the sessions on `webdriver_url` (if it is a Selenium Grid)
+    /// right away and then every `every`, logging what it killed or could not kill.
+    pub async fn watch(self, webdriver_url: String, every: Duration) {
+        let mut timer = tokio::time::interval(every);
+        loop {
+            timer.tick().await;
+            let now = Utc::now();
+            log_report(self.reap_registered(now).await);
+            log_report(self.reap_grid(&webdriver_url, now).await);
+        }
+    }
+
+    async fn delete(&self, webdriver_url: &str, session_id: &str) -> Result<(), Box<dyn Error>> {
+        let url = format!("{}/session/{}", webdriver_url.trim_end_matches('/'), session_id);
+        let response = self.client.delete(&url).send().await?;
+        // 404: the session is already gone, which is what we wanted.
+        if response.status().is_success() || response.status() == reqwest::StatusCode::NOT_FOUND {
+            Ok(())
+        } else {
+            Err(format!("HTTP {}", response.status()).into())
+        }
+    }
+}
+
+fn log_report(report: Result<ReapReport, Box<dyn Error>>) {
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => return eprintln!("Cannot reap WebDriver sessions: {}", e),
+    };
+    if !report.killed.is_empty() {
+        eprintln!("Killed {} orphaned WebDriver sessions", report.killed.len());
+    }
+    for (session, e) in report.failed {
+        eprintln!("Cannot kill WebDriver session {}: {}", session, e);
+    }
+}
+
+fn is_orphan(session: &SessionRecord, own_pid: u32, max_age: ChronoDuration, now: DateTime<Utc>) -> bool {
+    session.pid != own_pid || now - session.started_at >= max_age
+}
+
+// Selenium Grid 4 `/status`: value.nodes[].slots[].session.{sessionId, start}
+fn grid_sessions(status: &JsonValue) -> Vec<(String, DateTime<Utc>)> {
+    let nodes = status["value"]["nodes"].as_array().cloned().unwrap_or_default();
+    nodes
+        .iter()
+        .flat_map(|node| node["slots"].as_array().cloned().unwrap_or_default())
+        .filter_map(|slot| {
+            let session = slot.get("session").filter(|s| !s.is_null())?;
+            let id = session["sessionId"].as_str()?.to_string();
+            let start = DateTime::parse_from_rfc3339(session["start"].as_str()?).ok()?;
+            Some((id, start.with_timezone(&Utc)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_registry_round_trip_and_orphan_rules() {
+        let path = std::env::temp_dir().join(format!("rust_npm_sessions_{}.json", uuid::Uuid::new_v4().simple()));
+        let registry = SessionRegistry::new(&path);
+        registry.record("http://localhost:4444", "a1").unwrap();
+        registry.record("http://localhost:4444", "b2").unwrap();
+        registry.forget("a1").unwrap();
+        let sessions = registry.sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        fs::remove_file(&path).unwrap();
+
+        let session = &sessions[0];
+        let own = std::process::id();
+        let max_age = ChronoDuration::minutes(10);
+        assert!(!is_orphan(session, own, max_age, session.started_at));
+        assert!(is_orphan(session, own, max_age, session.started_at + max_age));
+        assert!(is_orphan(session, own.wrapping_add(1), max_age, session.started_at));
+    }
+
+    #[test]
+    fn test_grid_status_sessions_are_parsed() {
+        let status = serde_json::json!({
+            "value": {"nodes": [{"slots": [
+                {"session": {"sessionId": "abc", "start": "2026-10-17T08:00:00Z"}},
+                {"session": null}
+            ]}]}
+        });
+        assert_eq!(
+            grid_sessions(&status),
+            vec![("abc".to_string(), Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap())]
+        );
+    }
+}
@@ -0,0 +1,388 @@
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::{Value as JsonValue, json};
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use super::canary::ConnectivityChange;
+use super::check_result::CheckResult;
+use super::event_bus::MonitorEvent;
+use super::metadata::Metadata;
+use super::monitor::Monitor;
+use super::notify::{Delivery, DigestBuffer, Notification, SharedStormGuard};
+use super::severity::Severity;
+use super::staleness::StaleChange;
+use super::state_tracker::{TargetState, Transition};
+use super::storage::StatusRow;
+
+// Sidebar colors of Slack attachments and Discord embeds.
+const COLOR_DOWN: u32 = 0xd93025;
+const COLOR_UP: u32 = 0x1e8e3e;
+// Changes listed one by one in a chat digest; Discord caps messages at 2000 characters.
+const DIGEST_MAX_LINES: usize = 20;
+// How often a digest webhook checks whether its interval is up.
+const DIGEST_POLL: Duration = Duration::from_secs(10);
+
+/// The JSON shape a webhook expects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookKind {
+    /// Slack incoming webhook: `text` plus a colored attachment.
+    Slack,
+    /// Discord webhook: `content` plus a colored embed.
+    Discord,
+    /// Any other endpoint: the `StateChange` as is.
+    Generic,
+}
+
+impl WebhookKind {
+    /// Guesses the kind from the well-known Slack and Discord webhook hosts.
+    pub fn detect(url: &str) -> Self {
+        if url.contains("hooks.slack.com") {
+            WebhookKind::Slack
+        } else if url.contains("discord.com/api/webhooks") || url.contains("discordapp.com/api/webhooks") {
+            WebhookKind::Discord
+        } else {
+            WebhookKind::Generic
+        }
+    }
+}
+
+impl FromStr for WebhookKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "slack" => Ok(WebhookKind::Slack),
+            "discord" => Ok(WebhookKind::Discord),
+            "generic" | "json" => Ok(WebhookKind::Generic),
+            _ => Err(format!("unknown webhook kind '{}', expected slack, discord or generic", s)),
+        }
+    }
+}
+
+/// An up/down change as delivered to webhooks. `status` is the result as it is stored in
+/// the database, so receivers can parse both with the same code.
+#[derive(Debug, Clone, Serialize)]
+pub struct StateChange {
+    pub target: String,
+    pub old_state: Option<TargetState>,
+    pub new_state: TargetState,
+    pub at: DateTime<Utc>,
+    pub latency_ms: Option<f64>,
+    /// For recoveries: how long the target was down, in seconds.
+    pub downtime_secs: Option<f64>,
+    pub correlation_id: String,
+    pub message: String,
+    pub severity: Severity,
+    /// The target's user-defined metadata.
+    #[serde(skip_serializing_if = "Metadata::is_empty")]
+    pub metadata: Metadata,
+    pub status: Option<StatusRow>,
+}
+
+impl StateChange {
+    /// `result` is the check that caused the transition, if it is still known.
+    pub fn new(transition: &Transition, result: Option<&CheckResult>) -> Self {
+        let notification = Notification::from(transition);
+        Self {
+            target: transition.target.clone(),
+            old_state: transition.from,
+            new_state: transition.to,
+            at: transition.at,
+            latency_ms: result.and_then(CheckResult::latency_ms),
+            downtime_secs: transition.downtime.map(|d| d.as_secs_f64()),
+            correlation_id: transition.correlation_id.clone(),
+            message: notification.to_string(),
+            severity: notification.severity,
+            metadata: transition.metadata.clone(),
+            status: result.and_then(|r| StatusRow::from_result(r).ok()),
+        }
+    }
+
+    /// The request body for a webhook of `kind`.
+    pub fn body(&self, kind: WebhookKind) -> JsonValue {
+        let color = match self.new_state {
+            TargetState::Down => COLOR_DOWN,
+            TargetState::Up => COLOR_UP,
+        };
+        let state = |s: Option<TargetState>| s.map_or("unknown".to_string(), |s| format!("{:?}", s).to_uppercase());
+        let latency = self.latency_ms.map_or("-".to_string(), |ms| format!("{:.0} ms", ms));
+        let mut fields = vec![
+            ("Target".to_string(), self.target.clone()),
+            ("State".to_string(), format!("{} -> {}", state(self.old_state), state(Some(self.new_state)))),
+            ("Latency".to_string(), latency),
+        ];
+        fields.extend(self.metadata.iter().map(|(key, value)| (key.clone(), value.clone())));
+        match kind {
+            WebhookKind::Slack => json!({
+                "text": self.message,
+                "attachments": [{
+                    "color": format!("#{:06x}", color),
+                    "fields": fields.iter().map(|(title, value)| json!({"title": title, "value": value, "short": true})).collect::<Vec<_>>(),
+                    "ts": self.at.timestamp(),
+                }],
+            }),
+            WebhookKind::Discord => json!({
+                "content": self.message,
+                "embeds": [{
+                    "color": color,
+                    "fields": fields.iter().map(|(name, value)| json!({"name": name, "value": value, "inline": true})).collect::<Vec<_>>(),
+                    "timestamp": self.at.to_rfc3339(),
+                }],
+            }),
+            WebhookKind::Generic => serde_json::to_value(self).unwrap_or(JsonValue::Null),
+        }
+    }
+}
+
+/// The request body for a digest of several state changes, oldest first.
+pub fn digest_body(changes: &[StateChange], kind: WebhookKind) -> JsonValue {
+    let mut lines: Vec<String> = changes
+        .iter()
+        .take(DIGEST_MAX_LINES)
+        .map(|c| format!("{} {}", c.at.format("%H:%M:%S"), c.message))
+        .collect();
+    if changes.len() > DIGEST_MAX_LINES {
+        lines.push(format!("... and {} more", changes.len() - DIGEST_MAX_LINES));
+    }
+    let text = format!("{} state changes:\n{}", changes.len(), lines.join("\n"));
+    match kind {
+        WebhookKind::Slack => json!({ "text": text }),
+        WebhookKind::Discord => json!({ "content": text }),
+        WebhookKind::Generic => json!({ "digest": true, "changes": changes }),
+    }
+}
+
+/// The request body announcing that a target went stale or has results again.
+pub fn stale_body(change: &StaleChange, kind: WebhookKind) -> JsonValue {
+    match kind {
+        WebhookKind::Slack => json!({ "text": change.to_string() }),
+        WebhookKind::Discord => json!({ "content": change.to_string() }),
+        WebhookKind::Generic => json!({ "stale": change, "message": change.to_string() }),
+    }
+}
+
+/// The request body announcing that the monitor lost or regained its own connectivity.
+/// Sent once in place of a down alert for every external target.
+pub fn connectivity_body(change: &ConnectivityChange, kind: WebhookKind) -> JsonValue {
+    match kind {
+        WebhookKind::Slack => json!({ "text": change.to_string() }),
+        WebhookKind::Discord => json!({ "content": change.to_string() }),
+        WebhookKind::Generic => json!({ "connectivity": change, "message": change.to_string() }),
+    }
+}
+
+impl From<&StateChange> for Notification {
+    fn from(change: &StateChange) -> Self {
+        Self {
+            target: change.target.clone(),
+            severity: change.severity,
+            message: change.message.clone(),
+            at: change.at,
+            correlation_id: change.correlation_id.clone(),
+        }
+    }
+}
+
+/// The request body of an alert storm summary, see `notify::SharedStormGuard`.
+pub fn summary_body(summary: &Notification, kind: WebhookKind) -> JsonValue {
+    match kind {
+        WebhookKind::Slack => json!({ "text": summary.to_string() }),
+        WebhookKind::Discord => json!({ "content": summary.to_string() }),
+        WebhookKind::Generic => json!({ "target": summary.target, "message": summary.to_string() }),
+    }
+}
+
+/// POSTs state changes to one webhook, each right away or batched into digests.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    url: String,
+    kind: WebhookKind,
+    client: Client,
+    delivery: Delivery,
+    /// Also report targets going stale; always right away, never in digests.
+    stale_alerts: bool,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str, kind: WebhookKind, timeout: Duration) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            url: url.to_string(),
+            kind,
+            client: Client::builder().timeout(timeout).build()?,
+            delivery: Delivery::Instant,
+            stale_alerts: false,
+        })
+    }
+
+    pub fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = delivery;
+        self
+    }
+
+    pub fn with_stale_alerts(mut self, stale_alerts: bool) -> Self {
+        self.stale_alerts = stale_alerts;
+        self
+    }
+
+    pub async fn send(&self, change: &StateChange) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.post(&change.body(self.kind)).await
+    }
+
+    pub async fn send_digest(&self, changes: &[StateChange]) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.post(&digest_body(changes, self.kind)).await
+    }
+
+    async fn post(&self, body: &JsonValue) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.client.post(&self.url).json(body).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Sends every up/down transition of `monitor` to `notifier`, until the event bus closes.
+/// Failed deliveries are reported, not retried.
+pub async fn deliver(monitor: Arc<Monitor>, notifier: WebhookNotifier, storm: SharedStormGuard) {
+    let mut events = monitor.bus().subscribe();
+    // The result that caused a transition is published just before it.
+    let mut last: HashMap<String, CheckResult> = HashMap::new();
+    let mut digest = DigestBuffer::new(notifier.delivery);
+    let mut poll = tokio::time::interval(DIGEST_POLL);
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = poll.tick() => {
+                if let Some(changes) = digest.due(Utc::now())
+                    && let Err(e) = notifier.send_digest(&changes).await
+                {
+                    eprintln!("Webhook {} failed for a digest of {} changes: {}", notifier.url, changes.len(), e);
+                }
+                continue;
+            }
+        };
+        match event {
+            Ok(MonitorEvent::CheckCompleted(result)) => {
+                last.insert(result.target.clone(), result);
+            }
+            Ok(MonitorEvent::Transition(transition)) => {
+                if !storm.admit(&Notification::from(&transition)) {
+                    continue;
+                }
+                let result = last.get(&transition.target).filter(|r| r.correlation_id == transition.correlation_id);
+                let Some(change) = digest.offer(StateChange::new(&transition, result), Utc::now()) else {
+                    continue;
+                };
+                if let Err(e) = notifier.send(&change).await {
+                    eprintln!("Webhook {} failed for {}: {}", notifier.url, change.target, e);
+                }
+            }
+            Ok(MonitorEvent::TargetRemoved(addr)) => {
+                last.remove(&addr.to_string());
+            }
+            Ok(MonitorEvent::StaleChanged(change)) if notifier.stale_alerts => {
+                let notification = Notification {
+                    target: change.target.to_string(),
+                    severity: if change.stale { Severity::Warning } else { Severity::Info },
+                    message: change.to_string(),
+                    at: change.at,
+                    correlation_id: String::new(),
+                };
+                if !storm.admit(&notification) {
+                    continue;
+                }
+                if let Err(e) = notifier.post(&stale_body(&change, notifier.kind)).await {
+                    eprintln!("Webhook {} failed for {}: {}", notifier.url, change.target, e);
+                }
+            }
+            Ok(MonitorEvent::ConnectivityChanged(change)) => {
+                if let Err(e) = notifier.post(&connectivity_body(&change, notifier.kind)).await {
+                    eprintln!("Webhook {} failed for the connectivity change: {}", notifier.url, e);
+                }
+            }
+            Ok(MonitorEvent::StormSummary(summary)) => {
+                if let Err(e) = notifier.post(&summary_body(&summary, notifier.kind)).await {
+                    eprintln!("Webhook {} failed for the storm summary: {}", notifier.url, e);
+                }
+            }
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => eprintln!("Webhook {} missed {} events", notifier.url, missed),
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_bodies_carry_target_states_and_latency() {
+        let at = Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap();
+        let mut result = CheckResult::success("db:5432", Duration::from_millis(42));
+        result.timestamp = at;
+        result.metadata.insert("cmdb_id".to_string(), "CI0042".to_string());
+        let transition = Transition {
+            target: "db:5432".to_string(),
+            from: Some(TargetState::Down),
+            to: TargetState::Up,
+            at,
+            downtime: Some(Duration::from_secs(300)),
+            correlation_id: result.correlation_id.clone(),
+            metadata: result.metadata.clone(),
+            severity: None,
+        };
+        let change = StateChange::new(&transition, Some(&result));
+
+        let generic = change.body(WebhookKind::Generic);
+        assert_eq!(generic["target"], "db:5432");
+        assert_eq!((generic["old_state"].as_str(), generic["new_state"].as_str()), (Some("Down"), Some("Up")));
+        assert_eq!(generic["latency_ms"], 42.0);
+        assert_eq!(generic["status"]["object_data"]["ms"], 42.0);
+        assert_eq!(generic["metadata"]["cmdb_id"], "CI0042");
+        assert_eq!(generic["status"]["object_data"]["meta"]["cmdb_id"], "CI0042");
+
+        let slack = change.body(WebhookKind::Slack);
+        assert_eq!(slack["attachments"][0]["fields"][1]["value"], "DOWN -> UP");
+        assert_eq!(slack["attachments"][0]["fields"][2]["value"], "42 ms");
+        assert_eq!(slack["attachments"][0]["fields"][3]["title"], "cmdb_id");
+        let discord = change.body(WebhookKind::Discord);
+        assert_eq!(discord["embeds"][0]["color"], COLOR_UP);
+        assert!(discord["content"].as_str().unwrap().contains("db:5432"));
+    }
+
+    #[test]
+    fn test_digest_lists_changes() {
+        let transition = |to| Transition {
+            target: "db:5432".to_string(),
+            from: None,
+            to,
+            at: Utc.with_ymd_and_hms(2026, 10, 17, 12, 0, 0).unwrap(),
+            downtime: None,
+            correlation_id: String::new(),
+            metadata: Default::default(),
+            severity: None,
+        };
+        let changes: Vec<StateChange> =
+            (0..25).map(|i| StateChange::new(&transition(if i % 2 == 0 { TargetState::Down } else { TargetState::Up }), None)).collect();
+        let slack = digest_body(&changes, WebhookKind::Slack);
+        let text = slack["text"].as_str().unwrap();
+        assert!(text.starts_with("25 state changes:\n12:00:00 [critical] db:5432: is DOWN"));
+        assert!(text.ends_with("... and 5 more"));
+        let generic = digest_body(&changes[..2], WebhookKind::Generic);
+        assert_eq!(generic["changes"][1]["new_state"], "Up");
+    }
+
+    #[test]
+    fn test_kind_detection() {
+        assert_eq!(WebhookKind::detect("https://hooks.slack.com/services/T0/B0/x"), WebhookKind::Slack);
+        assert_eq!(WebhookKind::detect("https://discord.com/api/webhooks/1/abc"), WebhookKind::Discord);
+        assert_eq!(WebhookKind::detect("https://ops.example.com/hook"), WebhookKind::Generic);
+        assert_eq!("Slack".parse(), Ok(WebhookKind::Slack));
+        assert!("teams".parse::<WebhookKind>().is_err());
+    }
+}
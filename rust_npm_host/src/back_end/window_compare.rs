@@ -0,0 +1,188 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use super::check_result::CheckResult;
+use super::failure_kind::{self, FailureKind};
+use super::load_test::percentile;
+
+/// A half-open time range, written as two RFC 3339 times separated by `/` (the ISO 8601
+/// interval notation), e.g. `2026-10-01T00:00:00Z/2026-10-08T00:00:00Z`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct TimeWindow {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+impl TimeWindow {
+    pub fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from <= at && at < self.to
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s.split_once('/').ok_or_else(|| format!("'{}' is not <from>/<to>", s))?;
+        let time = |value: &str| {
+            DateTime::parse_from_rfc3339(value.trim())
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| format!("invalid time '{}': {}", value, e))
+        };
+        let window = TimeWindow { from: time(from)?, to: time(to)? };
+        if window.from >= window.to {
+            return Err(format!("window '{}' ends before it starts", s));
+        }
+        Ok(window)
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.from.to_rfc3339(), self.to.to_rfc3339())
+    }
+}
+
+/// How one target did within one window.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowStats {
+    pub window: TimeWindow,
+    pub checks: usize,
+    /// `None` without any check in the window.
+    pub availability: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub failures: BTreeMap<FailureKind, usize>,
+}
+
+impl WindowStats {
+    pub fn new(target: &str, window: TimeWindow, results: &[CheckResult]) -> Self {
+        let in_window: Vec<CheckResult> = results
+            .iter()
+            .filter(|r| r.target == target && window.contains(r.timestamp))
+            .cloned()
+            .collect();
+        let mut latencies: Vec<Duration> = in_window.iter().filter(|r| r.success).filter_map(|r| r.latency).collect();
+        latencies.sort();
+        let ms = |pct| percentile(&latencies, pct).map(|d| d.as_secs_f64() * 1000.0);
+        Self {
+            window,
+            checks: in_window.len(),
+            availability: (!in_window.is_empty())
+                .then(|| in_window.iter().filter(|r| r.success).count() as f64 / in_window.len() as f64),
+            p50_ms: ms(50.0),
+            p90_ms: ms(90.0),
+            p99_ms: ms(99.0),
+            failures: failure_kind::breakdown(&in_window),
+        }
+    }
+}
+
+/// The `compare-windows` report: one target, before and after, e.g. the week before and
+/// the week after a network change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowComparison {
+    pub target: String,
+    pub before: WindowStats,
+    pub after: WindowStats,
+}
+
+impl WindowComparison {
+    pub fn new(target: &str, before: TimeWindow, after: TimeWindow, results: &[CheckResult]) -> Self {
+        Self {
+            target: target.to_string(),
+            before: WindowStats::new(target, before, results),
+            after: WindowStats::new(target, after, results),
+        }
+    }
+}
+
+fn cell(value: Option<f64>, scale: f64, unit: &str) -> String {
+    value.map_or("-".to_string(), |v| format!("{:.2}{}", v * scale, unit))
+}
+
+// "after minus before", signed; empty when either side has no data.
+fn change(before: Option<f64>, after: Option<f64>, scale: f64, unit: &str) -> String {
+    match (before, after) {
+        (Some(b), Some(a)) => format!("{:+.2}{}", (a - b) * scale, unit),
+        _ => String::new(),
+    }
+}
+
+impl fmt::Display for WindowComparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (b, a) = (&self.before, &self.after);
+        writeln!(f, "{}", self.target)?;
+        writeln!(f, "  before: {}", b.window)?;
+        writeln!(f, "  after:  {}", a.window)?;
+        writeln!(f, "{:<22} {:>12} {:>12} {:>12}", "", "before", "after", "change")?;
+        writeln!(f, "{:<22} {:>12} {:>12} {:>12}", "checks", b.checks, a.checks, format!("{:+}", a.checks as i64 - b.checks as i64))?;
+        let rows = [
+            ("availability", b.availability, a.availability, 100.0, "%"),
+            ("latency p50", b.p50_ms, a.p50_ms, 1.0, " ms"),
+            ("latency p90", b.p90_ms, a.p90_ms, 1.0, " ms"),
+            ("latency p99", b.p99_ms, a.p99_ms, 1.0, " ms"),
+        ];
+        for (name, before, after, scale, unit) in rows {
+            writeln!(
+                f,
+                "{:<22} {:>12} {:>12} {:>12}",
+                name,
+                cell(before, scale, unit),
+                cell(after, scale, unit),
+                change(before, after, scale, unit)
+            )?;
+        }
+        let mut kinds: Vec<FailureKind> = b.failures.keys().chain(a.failures.keys()).copied().collect();
+        kinds.sort();
+        kinds.dedup();
+        for kind in kinds {
+            let (before, after) = (b.failures.get(&kind).copied().unwrap_or(0), a.failures.get(&kind).copied().unwrap_or(0));
+            writeln!(f, "{:<22} {:>12} {:>12} {:>12}", kind.to_string(), before, after, format!("{:+}", after as i64 - before as i64))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_windows_are_compared() {
+        let day = |d: u32| Utc.with_ymd_and_hms(2026, 10, d, 12, 0, 0).unwrap();
+        let at = |d, mut result: CheckResult| {
+            result.timestamp = day(d);
+            result
+        };
+        let results = vec![
+            at(2, CheckResult::success("gw:443", Duration::from_millis(10))),
+            at(3, CheckResult::success("gw:443", Duration::from_millis(20))),
+            at(3, CheckResult::success("other:22", Duration::from_millis(900))),
+            at(9, CheckResult::success("gw:443", Duration::from_millis(40))),
+            at(10, CheckResult::failure("gw:443", "timed out after 1s")),
+        ];
+        let before: TimeWindow = "2026-10-01T00:00:00Z/2026-10-08T00:00:00Z".parse().unwrap();
+        let after: TimeWindow = "2026-10-08T00:00:00Z/2026-10-15T00:00:00Z".parse().unwrap();
+        assert!("2026-10-08T00:00:00Z/2026-10-01T00:00:00Z".parse::<TimeWindow>().is_err());
+
+        let report = WindowComparison::new("gw:443", before, after, &results);
+        assert_eq!(report.before.checks, 2);
+        assert_eq!(report.before.availability, Some(1.0));
+        assert_eq!(report.before.p90_ms, Some(20.0));
+        assert_eq!(report.after.availability, Some(0.5));
+        assert_eq!(report.after.failures[&FailureKind::ConnectTimeout], 1);
+
+        let table = report.to_string();
+        assert!(table.contains("availability                100.00%       50.00%      -50.00%"));
+        assert!(table.contains("connect timeout                   0            1           +1"));
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["after"]["failures"]["connect_timeout"], 1);
+    }
+}
@@ -0,0 +1,380 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use reqwest::Client;
+use std::error::Error;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::check_result::CheckResult;
+use super::failure_kind::FailureKind;
+use super::target::{CheckSpec, MonitorTarget};
+
+const WMI_CIMV2: &str = "http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2";
+const ACTION_GET: &str = "http://schemas.xmlsoap.org/ws/2004/09/transfer/Get";
+const ACTION_ENUMERATE: &str = "http://schemas.xmlsoap.org/ws/2004/09/enumeration/Enumerate";
+const WQL_DIALECT: &str = "http://schemas.microsoft.com/wbem/wsman/1/WQL";
+
+/// Connection details for the WinRM (WS-Management) endpoint of a Windows host.
+///
+/// Uses HTTP basic auth, so the host needs `winrm set winrm/config/service/auth @{Basic="true"}`
+/// and should be reached over HTTPS (port 5986) unless it sits on a trusted segment.
+#[derive(Debug, Clone)]
+pub struct WinRmEndpoint {
+    pub host: String,
+    pub port: u16,
+    pub use_https: bool,
+    pub username: String,
+    pub password: String,
+    pub timeout: Duration,
+}
+
+impl WinRmEndpoint {
+    fn url(&self) -> String {
+        let scheme = if self.use_https { "https" } else { "http" };
+        format!("{}://{}:{}/wsman", scheme, self.host, self.port)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceStatus {
+    pub name: String,
+    /// WMI state, e.g. "Running", "Stopped", "Start Pending".
+    pub state: String,
+    /// e.g. "Auto", "Manual", "Disabled".
+    pub start_mode: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventLogEntry {
+    pub time_generated: String,
+    pub source: String,
+    pub event_code: String,
+    pub message: String,
+}
+
+pub struct WinRmClient {
+    endpoint: WinRmEndpoint,
+    http: Client,
+}
+
+impl WinRmClient {
+    pub fn new(endpoint: WinRmEndpoint) -> Result<Self, Box<dyn Error>> {
+        let http = Client::builder().timeout(endpoint.timeout).build()?;
+        Ok(Self { endpoint, http })
+    }
+
+    /// Reads `Win32_Service` for one service by its short name (e.g. "Spooler").
+    pub async fn service_status(&self, service_name: &str) -> Result<ServiceStatus, Box<dyn Error>> {
+        let selector = format!(
+            "<w:SelectorSet><w:Selector Name=\"Name\">{}</w:Selector></w:SelectorSet>",
+            xml_escape(service_name)
+        );
+        let envelope = self.envelope(
+            &format!("{}/Win32_Service", WMI_CIMV2),
+            ACTION_GET,
+            &selector,
+            "",
+        );
+        let response = self.send(envelope).await?;
+
+        Ok(ServiceStatus {
+            name: service_name.to_string(),
+            state: first_element(&response, "State").ok_or("response has no State element")?,
+            start_mode: first_element(&response, "StartMode").unwrap_or_default(),
+        })
+    }
+
+    /// Lists error/critical entries of an event log (e.g. "System") newer than `since`.
+    pub async fn critical_events(
+        &self,
+        log_file: &str,
+        since: DateTime<Utc>,
+        max_entries: u32,
+    ) -> Result<Vec<EventLogEntry>, Box<dyn Error>> {
+        // EventType 1 is "Error"; critical events are reported with the same type by WMI.
+        let query = format!(
+            "SELECT * FROM Win32_NTLogEvent WHERE Logfile = '{}' AND EventType = 1 AND TimeGenerated >= '{}'",
+            log_file.replace('\'', ""),
+            since.format("%Y%m%d%H%M%S.000000-000")
+        );
+        let body = format!(
+            "<n:Enumerate xmlns:n=\"http://schemas.xmlsoap.org/ws/2004/09/enumeration\">\
+             <w:OptimizeEnumeration/><w:MaxElements>{}</w:MaxElements>\
+             <w:Filter Dialect=\"{}\">{}</w:Filter></n:Enumerate>",
+            max_entries,
+            WQL_DIALECT,
+            xml_escape(&query)
+        );
+        let envelope = self.envelope(&format!("{}/*", WMI_CIMV2), ACTION_ENUMERATE, "", &body);
+        let response = self.send(envelope).await?;
+
+        Ok(elements(&response, "Win32_NTLogEvent")
+            .into_iter()
+            .map(|item| EventLogEntry {
+                time_generated: first_element(&item, "Datetime")
+                    .or_else(|| first_element(&item, "TimeGenerated"))
+                    .unwrap_or_default(),
+                source: first_element(&item, "SourceName").unwrap_or_default(),
+                event_code: first_element(&item, "EventCode").unwrap_or_default(),
+                message: first_element(&item, "Message").unwrap_or_default().trim().to_string(),
+            })
+            .collect())
+    }
+
+    fn envelope(&self, resource_uri: &str, action: &str, extra_headers: &str, body: &str) -> String {
+        format!(
+            "<s:Envelope xmlns:s=\"http://www.w3.org/2003/05/soap-envelope\" \
+             xmlns:a=\"http://schemas.xmlsoap.org/ws/2004/08/addressing\" \
+             xmlns:w=\"http://schemas.dmtf.org/wbem/wsman/1/wsman.xsd\">\
+             <s:Header>\
+             <a:To>{to}</a:To>\
+             <w:ResourceURI s:mustUnderstand=\"true\">{resource}</w:ResourceURI>\
+             <a:ReplyTo><a:Address s:mustUnderstand=\"true\">http://schemas.xmlsoap.org/ws/2004/08/addressing/role/anonymous</a:Address></a:ReplyTo>\
+             <a:Action s:mustUnderstand=\"true\">{action}</a:Action>\
+             <w:MaxEnvelopeSize s:mustUnderstand=\"true\">512000</w:MaxEnvelopeSize>\
+             <a:MessageID>uuid:{id}</a:MessageID>\
+             <w:OperationTimeout>PT{secs}S</w:OperationTimeout>\
+             {extra}\
+             </s:Header><s:Body>{body}</s:Body></s:Envelope>",
+            to = self.endpoint.url(),
+            resource = resource_uri,
+            action = action,
+            id = Uuid::new_v4(),
+            secs = self.endpoint.timeout.as_secs().max(1),
+            extra = extra_headers,
+            body = body,
+        )
+    }
+
+    async fn send(&self, envelope: String) -> Result<String, Box<dyn Error>> {
+        let response = self
+            .http
+            .post(self.endpoint.url())
+            .basic_auth(&self.endpoint.username, Some(&self.endpoint.password))
+            .header("Content-Type", "application/soap+xml;charset=UTF-8")
+            .body(envelope)
+            .send()
+            .await?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            let reason = first_element(&text, "Text").unwrap_or_else(|| status.to_string());
+            return Err(format!("WinRM request failed ({}): {}", status, reason).into());
+        }
+        Ok(text)
+    }
+}
+
+/// Checks that a Windows service is in the expected state (normally "Running").
+pub async fn check_service(client: &WinRmClient, service_name: &str, expected_state: &str) -> CheckResult {
+    let target = format!("{}/service/{}", client.endpoint.host, service_name);
+    let start = Instant::now();
+    match client.service_status(service_name).await {
+        Ok(status) if status.state.eq_ignore_ascii_case(expected_state) => {
+            CheckResult::success(&target, start.elapsed())
+        }
+        Ok(status) => CheckResult {
+            latency: Some(start.elapsed()),
+            ..CheckResult::failure(
+                &target,
+                format!("{} is {} (expected {}, start mode {})", service_name, status.state, expected_state, status.start_mode),
+            )
+        },
+        Err(e) => CheckResult::failure(&target, e.to_string()),
+    }
+}
+
+/// Fails when the event log has error/critical entries within the lookback window.
+pub async fn check_event_log(client: &WinRmClient, log_file: &str, lookback: ChronoDuration) -> CheckResult {
+    let target = format!("{}/eventlog/{}", client.endpoint.host, log_file);
+    let start = Instant::now();
+    match client.critical_events(log_file, Utc::now() - lookback, 20).await {
+        Ok(entries) if entries.is_empty() => CheckResult::success(&target, start.elapsed()),
+        Ok(entries) => {
+            let summary: Vec<String> = entries
+                .iter()
+                .take(5)
+                .map(|e| format!("{} {} ({})", e.source, e.event_code, e.time_generated))
+                .collect();
+            CheckResult {
+                latency: Some(start.elapsed()),
+                ..CheckResult::failure(
+                    &target,
+                    format!("{} error event(s) in {}: {}", entries.len(), log_file, summary.join("; ")),
+                )
+            }
+        }
+        Err(e) => CheckResult::failure(&target, e.to_string()),
+    }
+}
+
+/// Runs the service and event log checks of a `CheckSpec::Windows` target as one result,
+/// with a step per service and log.
+pub async fn check_target(target: &MonitorTarget, timeout: Duration) -> CheckResult {
+    let name = target.address.to_string();
+    let Some(CheckSpec::Windows { user, password_env, services, event_log, lookback_minutes }) = target.spec() else {
+        return CheckResult::failure(&name, "Windows check without WinRM settings");
+    };
+    if services.is_empty() && event_log.is_none() {
+        return CheckResult::failure(&name, "Windows check without services or an event log to check");
+    }
+    let Ok(password) = std::env::var(password_env) else {
+        return CheckResult::failure(&name, format!("{} is not set", password_env)).with_failure_kind(FailureKind::InfraError);
+    };
+    let endpoint = WinRmEndpoint {
+        host: target.host.clone().unwrap_or_else(|| target.address.ip().to_string()),
+        port: target.address.port(),
+        use_https: target.address.port() == 5986,
+        username: user.clone(),
+        password,
+        timeout,
+    };
+    let client = match WinRmClient::new(endpoint) {
+        Ok(client) => client,
+        Err(e) => return CheckResult::failure(&name, e.to_string()).with_failure_kind(FailureKind::InfraError),
+    };
+    let mut parts = Vec::new();
+    for service in services {
+        parts.push(check_service(&client, service, "Running").await);
+    }
+    if let Some(log) = event_log {
+        parts.push(check_event_log(&client, log, ChronoDuration::minutes(i64::from(*lookback_minutes))).await);
+    }
+    CheckResult::from_steps(&name, parts)
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+// WinRM responses use varying namespace prefixes (p:, cim:, ...), so elements are matched
+// on their local name only. This is enough for the flat WMI objects we read.
+fn elements(xml: &str, local_name: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(open_start) = find_open_tag(rest, local_name) {
+        let after_open = &rest[open_start..];
+        let Some(open_end) = after_open.find('>') else { break };
+        let open_tag = &after_open[..open_end];
+        let content_start = open_start + open_end + 1;
+        if open_tag.ends_with('/') {
+            found.push(String::new());
+            rest = &rest[content_start..];
+            continue;
+        }
+        let tag_name = open_tag[1..].split_whitespace().next().unwrap_or_default();
+        let close = format!("</{}>", tag_name);
+        let Some(close_pos) = rest[content_start..].find(&close) else { break };
+        found.push(xml_unescape(&rest[content_start..content_start + close_pos]));
+        rest = &rest[content_start + close_pos + close.len()..];
+    }
+    found
+}
+
+fn first_element(xml: &str, local_name: &str) -> Option<String> {
+    elements(xml, local_name).into_iter().next().map(|content| strip_tags(&content))
+}
+
+fn find_open_tag(xml: &str, local_name: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(pos) = xml[offset..].find('<') {
+        let start = offset + pos;
+        let name: String = xml[start + 1..]
+            .chars()
+            .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+            .collect();
+        let local = name.rsplit(':').next().unwrap_or_default();
+        if local == local_name && !name.is_empty() {
+            return Some(start);
+        }
+        offset = start + 1;
+    }
+    None
+}
+
+fn strip_tags(content: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in content.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE_RESPONSE: &str = r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope"><s:Body>
+        <p:Win32_Service xmlns:p="http://schemas.microsoft.com/wbem/wsman/1/wmi/root/cimv2/Win32_Service">
+        <p:Name>Spooler</p:Name><p:StartMode>Auto</p:StartMode><p:State>Running</p:State>
+        </p:Win32_Service></s:Body></s:Envelope>"#;
+
+    const EVENTS_RESPONSE: &str = r#"<s:Envelope><s:Body><n:EnumerateResponse><w:Items>
+        <p:Win32_NTLogEvent><p:EventCode>7031</p:EventCode><p:Message>The Print Spooler service terminated &amp; restarted.</p:Message>
+        <p:SourceName>Service Control Manager</p:SourceName>
+        <p:TimeGenerated><cim:Datetime>2026-10-17T08:00:00Z</cim:Datetime></p:TimeGenerated></p:Win32_NTLogEvent>
+        <p:Win32_NTLogEvent><p:EventCode>41</p:EventCode><p:Message>Kernel-Power</p:Message>
+        <p:SourceName>Microsoft-Windows-Kernel-Power</p:SourceName>
+        <p:TimeGenerated><cim:Datetime>2026-10-17T09:00:00Z</cim:Datetime></p:TimeGenerated></p:Win32_NTLogEvent>
+        </w:Items></n:EnumerateResponse></s:Body></s:Envelope>"#;
+
+    #[test]
+    fn test_parses_service_state_regardless_of_prefix() {
+        assert_eq!(first_element(SERVICE_RESPONSE, "State").as_deref(), Some("Running"));
+        assert_eq!(first_element(SERVICE_RESPONSE, "StartMode").as_deref(), Some("Auto"));
+        assert_eq!(first_element(SERVICE_RESPONSE, "Missing"), None);
+    }
+
+    #[test]
+    fn test_parses_event_log_items() {
+        let items = elements(EVENTS_RESPONSE, "Win32_NTLogEvent");
+        assert_eq!(items.len(), 2);
+        assert_eq!(first_element(&items[0], "EventCode").as_deref(), Some("7031"));
+        assert_eq!(
+            first_element(&items[0], "Message").as_deref(),
+            Some("The Print Spooler service terminated & restarted.")
+        );
+        assert_eq!(first_element(&items[1], "Datetime").as_deref(), Some("2026-10-17T09:00:00Z"));
+    }
+
+    #[tokio::test]
+    async fn test_target_check_has_a_step_per_service() {
+        let app = axum::Router::new().route("/wsman", axum::routing::post(|| async { SERVICE_RESPONSE }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        // SAFETY: no other test reads or writes this variable.
+        unsafe { std::env::set_var("RUST_NPM_TEST_WINRM_PASSWORD", "secret") };
+        let spec = CheckSpec::Windows {
+            user: "monitor".to_string(),
+            password_env: "RUST_NPM_TEST_WINRM_PASSWORD".to_string(),
+            services: vec!["Spooler".to_string(), "W32Time".to_string()],
+            event_log: None,
+            lookback_minutes: 15,
+        };
+        let result = check_target(&MonitorTarget::new(addr).with_spec(spec), Duration::from_secs(5)).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.target, addr.to_string());
+        assert_eq!(result.steps.len(), 2);
+        assert_eq!(result.steps[0].name, "127.0.0.1/service/Spooler");
+    }
+}
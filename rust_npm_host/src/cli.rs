@@ -0,0 +1,1351 @@
+use clap::{Args, Parser, Subcommand};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::back_end::address::{self, AddressBook, HostSpec};
+use crate::back_end::alert_dry_run::DryRun;
+use crate::back_end::browser_emulator::{BrowserKind, Transaction};
+use crate::back_end::check_result::CheckResult;
+use crate::back_end::config::{Config, DEFAULT_CONFIG};
+use crate::back_end::csv_import::parse_interval;
+use crate::back_end::diagnostics;
+use crate::back_end::encrypted_config::{self, KEYRING_ENV, KeySource, PASSPHRASE_ENV};
+use crate::back_end::event_bus::MonitorEvent;
+use crate::back_end::http_check::HttpCheck;
+use crate::back_end::history::History;
+use crate::back_end::http_pool::HttpPool;
+use crate::back_end::iana_ports::{self, PortRegistry, Protocol};
+use crate::back_end::load_test::{self, LoadTestConfig, LoadTestReport, SafetyLimits};
+use crate::back_end::logging::{self, LogFormat};
+use crate::back_end::monitor::{CheckKind, Monitor};
+use crate::back_end::notify::Delivery;
+use crate::back_end::ping_test;
+use crate::back_end::power::PowerMode;
+use crate::back_end::presets::{self, PRESETS};
+use crate::back_end::slo_export::{self, SloConfig};
+use crate::back_end::state_tracker::RecoveryRules;
+use crate::back_end::storage::{StatusStore, StoreError, Storage};
+use crate::back_end::target::{CheckSpec, MonitorTarget};
+use crate::back_end::units::UnitPreferences;
+use crate::back_end::vantage::{self, VantageConfig};
+use crate::back_end::webdriver_process::{self, ManagedDriver};
+use crate::back_end::webhook::WebhookKind;
+use crate::back_end::window_compare::TimeWindow;
+use crate::shell;
+
+// Browser checks go through a local WebDriver unless told otherwise.
+const DEFAULT_WEBDRIVER_URL: &str = "http://localhost:4444";
+// How long a driver started with `--start-webdriver` may take to listen.
+const DRIVER_STARTUP_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Subcommands for scripting the monitor. Without one, the `--flag` options of `Options`
+/// pick what to do, e.g. `--gui` or `--daemon`.
+#[derive(Debug, Parser)]
+#[command(name = "rust_npm", version, about = "Network and port monitor", arg_required_else_help = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub options: Options,
+    /// Address book to use instead of the default one.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub targets: Option<String>,
+    /// Where results are stored: `postgres://...`, `sqlite://<path>` or `memory:`.
+    #[arg(long, global = true, value_name = "URL")]
+    pub database: Option<String>,
+    /// Don't store results at all.
+    #[arg(long, global = true)]
+    pub no_database: bool,
+    /// How results are printed: `text`, or `json` with one object per line. Defaults to
+    /// the settings file's, which defaults to `text`.
+    #[arg(long, global = true)]
+    pub log_format: Option<LogFormat>,
+    /// Settings file to use instead of the default one.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+}
+
+/// What to do when no subcommand is given, and the settings of the monitor's background
+/// tasks. The settings that also exist in the settings file override it.
+#[derive(Debug, Args)]
+pub struct Options {
+    /// Open the monitor window.
+    #[arg(long, help_heading = "Modes")]
+    pub gui: bool,
+    /// With `--gui`: hide the controls that change targets, for wallboards.
+    #[arg(long, requires = "gui", help_heading = "Modes")]
+    pub read_only: bool,
+    /// With `--gui`: API token deciding what the window may change; read-scoped tokens of
+    /// `--api-config` (or of the `--remote` monitor) make it read-only.
+    #[arg(long, value_name = "TOKEN", requires = "gui", help_heading = "Modes")]
+    pub gui_token: Option<String>,
+    /// With `--gui`: show the monitor at this URL through its API instead of checking here.
+    #[arg(long, value_name = "URL", requires = "gui", help_heading = "Modes")]
+    pub remote: Option<String>,
+    /// Keep checking in the foreground until SIGINT or SIGTERM, as systemd or launchd expect.
+    #[arg(long, conflicts_with = "gui", help_heading = "Modes")]
+    pub daemon: bool,
+    /// With `--daemon`: where to write the process ID; the data directory if not given.
+    #[arg(long, value_name = "FILE", requires = "daemon", help_heading = "Modes")]
+    pub pid_file: Option<PathBuf>,
+    /// Serve the HTTP API on this address while the monitor runs, e.g. with `--daemon`.
+    #[arg(long, value_name = "ADDR:PORT", help_heading = "Modes")]
+    pub api: Option<SocketAddr>,
+    /// Who may use the API and the `--gui-token`s; `[api] access` if not given, otherwise only
+    /// this machine.
+    #[arg(long, value_name = "FILE", help_heading = "Modes")]
+    pub api_config: Option<PathBuf>,
+
+    /// Check targets in the background every this many seconds, each on its own interval if
+    /// it has one.
+    #[arg(long, value_name = "SECS", help_heading = "Checking")]
+    pub interval: Option<u64>,
+    /// Checks in flight at once; `[monitor] concurrency` if not given.
+    #[arg(long, value_name = "N", help_heading = "Checking")]
+    pub concurrency: Option<usize>,
+    /// Start the checks of every target right away instead of at its slot in the interval.
+    #[arg(long, help_heading = "Checking")]
+    pub no_spread: bool,
+    /// Save battery: `on`, or `auto` to do so only while on battery. Stretches intervals,
+    /// batches checks and suspends browser checks.
+    #[arg(long, value_name = "MODE", help_heading = "Checking")]
+    pub low_power: Option<PowerMode>,
+    /// How much `--low-power` stretches intervals; `[monitor] low_power_factor` if not given.
+    #[arg(long, value_name = "N", help_heading = "Checking")]
+    pub low_power_factor: Option<u32>,
+    /// Start a WebDriver server for browser checks, and restart it if it crashes: `auto`, a
+    /// driver's name or its path. Otherwise they go through one on port 4444.
+    #[arg(long, value_name = "DRIVER", help_heading = "Checking")]
+    pub start_webdriver: Option<String>,
+    /// Warm browser sessions scheduled browser checks share.
+    #[arg(long, value_name = "N", default_value_t = 2, help_heading = "Checking")]
+    pub browser_sessions: usize,
+    /// Parallel requests to one host across all HTTP checks.
+    #[arg(long, value_name = "N", help_heading = "Checking")]
+    pub http_max_per_host: Option<usize>,
+    /// How long resolved addresses are reused by HTTP checks; 0 turns the cache off.
+    #[arg(long, value_name = "SECS", help_heading = "Checking")]
+    pub dns_cache_secs: Option<u64>,
+    /// Endpoints checked before counting a failure of an external target, comma separated,
+    /// or `default`; when none is reachable the outage is announced once as a loss of local
+    /// connectivity.
+    #[arg(long, value_name = "HOST:PORT,...", value_delimiter = ',', help_heading = "Checking")]
+    pub canaries: Option<Vec<String>>,
+    /// Run the `*.rhai` files in this directory on every result, in file name order.
+    #[arg(long, value_name = "DIR", help_heading = "Checking")]
+    pub scripts: Option<PathBuf>,
+    /// Results kept in memory per target; older ones are compressed.
+    #[arg(long, value_name = "COUNT", help_heading = "Checking")]
+    pub history_retention: Option<usize>,
+    /// Mark a target STALE when its next result is this much later than its interval and
+    /// timeout allow; 60 if not given.
+    #[arg(long, value_name = "SECS", help_heading = "Checking")]
+    pub stale_grace: Option<u64>,
+    /// How often the host names of targets are resolved again.
+    #[arg(long, value_name = "SECS", default_value_t = 300, help_heading = "Checking")]
+    pub resolve_interval: u64,
+    /// Pair this instance with another one using the same lease, a file or `postgres://...`:
+    /// only the one holding it runs checks.
+    #[arg(long, value_name = "LEASE", help_heading = "Checking")]
+    pub ha: Option<String>,
+    /// With `--ha`: this instance's name; host name and process ID if not given.
+    #[arg(long, value_name = "ID", requires = "ha", help_heading = "Checking")]
+    pub ha_node: Option<String>,
+    /// With `--ha`: seconds until a lease that isn't renewed lapses.
+    #[arg(long, value_name = "SECS", default_value_t = 30, requires = "ha", help_heading = "Checking")]
+    pub ha_ttl: i64,
+    /// Generate targets from the tags of `--inventory` and keep them in line with it.
+    #[arg(long, value_name = "FILE", requires = "inventory", help_heading = "Checking")]
+    pub rules: Option<PathBuf>,
+    /// With `--rules`: the inventory of hosts and their tags, e.g. from `--discover`.
+    #[arg(long, value_name = "FILE", requires = "rules", help_heading = "Checking")]
+    pub inventory: Option<PathBuf>,
+    /// With `--rules`: seconds between re-reading the rules and the inventory.
+    #[arg(long, value_name = "SECS", default_value_t = 60, requires = "rules", help_heading = "Checking")]
+    pub rules_interval: u64,
+    /// Dependencies between targets and services for the topology.
+    #[arg(long, value_name = "FILE", help_heading = "Checking")]
+    pub dependencies: Option<PathBuf>,
+    /// Capture a few seconds of a failing target's traffic into this directory; the file is
+    /// announced with the outage.
+    #[arg(long, value_name = "DIR", help_heading = "Checking")]
+    pub capture_dir: Option<PathBuf>,
+    /// With `--capture-dir`: failures in a row before capturing.
+    #[arg(long, value_name = "N", requires = "capture_dir", help_heading = "Checking")]
+    pub capture_after: Option<u32>,
+    /// With `--capture-dir`: seconds to capture.
+    #[arg(long, value_name = "SECS", requires = "capture_dir", help_heading = "Checking")]
+    pub capture_secs: Option<u64>,
+    /// With `--capture-dir`: network interface to capture on.
+    #[arg(long, value_name = "NAME", requires = "capture_dir", help_heading = "Checking")]
+    pub capture_interface: Option<String>,
+
+    /// Post every up/down change to this URL as JSON.
+    #[arg(long, value_name = "URL", help_heading = "Alerts")]
+    pub webhook: Option<String>,
+    /// With `--webhook`: slack, discord or generic; guessed from the URL if not given.
+    #[arg(long, value_name = "KIND", requires = "webhook", help_heading = "Alerts")]
+    pub webhook_kind: Option<WebhookKind>,
+    /// With `--webhook`: batch the changes into one message per interval, e.g. `15m`.
+    #[arg(long, value_name = "INTERVAL", requires = "webhook", help_heading = "Alerts")]
+    pub webhook_digest: Option<Delivery>,
+    /// With `--webhook`: also post when a target goes stale.
+    #[arg(long, requires = "webhook", help_heading = "Alerts")]
+    pub stale_alert: bool,
+    /// Send the changes of targets with an owner to that owner's channels and escalation chain.
+    #[arg(long, value_name = "FILE", help_heading = "Alerts")]
+    pub owners: Option<PathBuf>,
+    /// Open Jira or ServiceNow tickets for long outages and resolve them on recovery.
+    #[arg(long, value_name = "FILE", help_heading = "Alerts")]
+    pub tickets: Option<PathBuf>,
+    /// Notifications the webhook, owner and ticket channels may send together per 5
+    /// minutes; the rest are summed up afterwards.
+    #[arg(long, value_name = "N", default_value_t = 20, help_heading = "Alerts")]
+    pub storm_limit: usize,
+
+    /// Check a website once without a browser; exits non-zero unless it answered as expected.
+    #[arg(long, value_name = "URL", help_heading = "One-off commands")]
+    pub http: Option<String>,
+    /// With `--http`: status code to expect.
+    #[arg(long, value_name = "CODE", requires = "http", help_heading = "One-off commands")]
+    pub expect_status: Option<u16>,
+    /// With `--http`: text the page body must contain.
+    #[arg(long, value_name = "TEXT", requires = "http", help_heading = "One-off commands")]
+    pub expect_body: Option<String>,
+    /// Check everything once and list each service with its rollup status.
+    #[arg(long, help_heading = "One-off commands")]
+    pub services: bool,
+    /// Check one service once; exits non-zero unless it is fully up.
+    #[arg(long, value_name = "NAME", help_heading = "One-off commands")]
+    pub service: Option<String>,
+    /// Check every target once and draw the dependency graph as SVG.
+    #[arg(long, value_name = "FILE", help_heading = "One-off commands")]
+    pub topology: Option<PathBuf>,
+    /// Uptime, average latency and recent failures of a target from the stored history.
+    #[arg(long, value_name = "TARGET", help_heading = "One-off commands")]
+    pub history: Option<String>,
+    /// Targets whose latency or failure rate has been creeping up; exits non-zero when there
+    /// are any.
+    #[arg(long, help_heading = "One-off commands")]
+    pub trends: bool,
+    /// The newest stored result of every target, per agent.
+    #[arg(long, help_heading = "One-off commands")]
+    pub latest_status: bool,
+    /// Pack a target's results, annotations, captures and attached files into one zip.
+    #[arg(long, value_name = "TARGET", help_heading = "One-off commands")]
+    pub evidence: Option<String>,
+    /// With `--evidence`: where to write the zip.
+    #[arg(long, value_name = "FILE", requires = "evidence", help_heading = "One-off commands")]
+    pub out: Option<PathBuf>,
+    /// With `--evidence`: directory of the `--capture-dir` captures to include.
+    #[arg(long, value_name = "DIR", requires = "evidence", help_heading = "One-off commands")]
+    pub captures: Option<PathBuf>,
+    /// With `--evidence`: files to add, comma separated (HAR, screenshots, logs, ...).
+    #[arg(long, value_name = "FILE,...", value_delimiter = ',', requires = "evidence", help_heading = "One-off commands")]
+    pub attach: Vec<PathBuf>,
+    /// With `--evidence`: add a traceroute to the target, taken now.
+    #[arg(long, requires = "evidence", help_heading = "One-off commands")]
+    pub traceroute: bool,
+    /// Start of the `--evidence` window (`24h` back or RFC 3339) or of the `--annotate` span
+    /// (RFC 3339).
+    #[arg(long, value_name = "TIME", help_heading = "One-off commands")]
+    pub from: Option<String>,
+    /// End of the `--evidence` window or the `--annotate` span, RFC 3339.
+    #[arg(long, value_name = "TIME", help_heading = "One-off commands")]
+    pub to: Option<String>,
+    /// Contrast a target's availability, latency and failure causes between two time ranges.
+    #[arg(long, value_name = "TARGET", requires_all = ["before", "after", "results"], help_heading = "One-off commands")]
+    pub compare_windows: Option<String>,
+    /// With `--compare-windows`: the first range, `<from>/<to>`.
+    #[arg(long, value_name = "FROM/TO", requires = "compare_windows", help_heading = "One-off commands")]
+    pub before: Option<TimeWindow>,
+    /// With `--compare-windows`: the second range, `<from>/<to>`.
+    #[arg(long, value_name = "FROM/TO", requires = "compare_windows", help_heading = "One-off commands")]
+    pub after: Option<TimeWindow>,
+    /// With `--compare-windows`: check results, one JSON object per line.
+    #[arg(long, value_name = "FILE", requires = "compare_windows", help_heading = "One-off commands")]
+    pub results: Option<PathBuf>,
+    /// Print `--compare-windows` and `--trends` reports as JSON.
+    #[arg(long, help_heading = "One-off commands")]
+    pub json: bool,
+    /// Export the stored results as Parquet files into this directory.
+    #[arg(long, value_name = "DIR", help_heading = "One-off commands")]
+    pub export_parquet: Option<PathBuf>,
+    /// With `--export-parquet`: only the last this many whole days.
+    #[arg(long, value_name = "DAYS", requires = "export_parquet", help_heading = "One-off commands")]
+    pub export_days: Option<i64>,
+    /// Report whether this build can read the address book and the database schema.
+    #[arg(long, help_heading = "One-off commands")]
+    pub check_compat: bool,
+
+    /// Import targets from a CSV file and check them once.
+    #[arg(long, value_name = "FILE", help_heading = "Targets")]
+    pub import: Option<PathBuf>,
+    /// With `--import`: which columns hold what, e.g. `host=Name,port=Port`.
+    #[arg(long, value_name = "MAPPING", requires = "import", help_heading = "Targets")]
+    pub columns: Option<String>,
+    /// With `--import`: only print what would be imported.
+    #[arg(long, requires = "import", help_heading = "Targets")]
+    pub dry_run: bool,
+    /// Ping sweep of a network, e.g. `10.0.0.0/24`.
+    #[arg(long, value_name = "CIDR", help_heading = "Targets")]
+    pub discover: Option<String>,
+    /// With `--discover`: guess each host's device type.
+    #[arg(long, requires = "discover", help_heading = "Targets")]
+    pub fingerprint: bool,
+    /// With `--discover`: write the hosts as an inventory for `--rules`.
+    #[arg(long, value_name = "FILE", requires = "discover", help_heading = "Targets")]
+    pub inventory_out: Option<PathBuf>,
+    /// With `--discover`: add the hosts' open ports to the address book.
+    #[arg(long, requires = "discover", help_heading = "Targets")]
+    pub add_targets: bool,
+    /// Pause a target in the address book.
+    #[arg(long, value_name = "TARGET", requires = "reason", help_heading = "Targets")]
+    pub pause: Option<String>,
+    /// With `--pause`: why, shown wherever the target is.
+    #[arg(long, value_name = "TEXT", requires = "pause", help_heading = "Targets")]
+    pub reason: Option<String>,
+    /// With `--pause`: when the pause ends, e.g. `3d` or an RFC 3339 time.
+    #[arg(long, value_name = "TIME", requires = "pause", help_heading = "Targets")]
+    pub until: Option<String>,
+    /// Resume a paused target.
+    #[arg(long, value_name = "TARGET", help_heading = "Targets")]
+    pub resume: Option<String>,
+    /// List the paused targets, longest paused first.
+    #[arg(long, help_heading = "Targets")]
+    pub paused: bool,
+    /// Show a target's metadata, or change it with `--set key=value,...`; `key=` removes a key.
+    #[arg(long, value_name = "TARGET", conflicts_with = "owner", help_heading = "Targets")]
+    pub meta: Option<String>,
+    /// Show who owns a target, or change it with `--set <owner>`; an empty owner removes it.
+    #[arg(long, value_name = "TARGET", help_heading = "Targets")]
+    pub owner: Option<String>,
+    /// The new value for `--meta` or `--owner`.
+    #[arg(long, value_name = "VALUE", help_heading = "Targets")]
+    pub set: Option<String>,
+    /// Add an operator note on a span (`--from`, `--to`) of a target's history.
+    #[arg(long, value_name = "TARGET", requires_all = ["from", "to"], help_heading = "Targets")]
+    pub annotate: Option<String>,
+    /// With `--annotate`: the note.
+    #[arg(long, value_name = "TEXT", requires = "annotate", help_heading = "Targets")]
+    pub label: Option<String>,
+    /// With `--annotate`: count the span in the SLA instead of excluding it.
+    #[arg(long, requires = "annotate", help_heading = "Targets")]
+    pub keep_in_sla: bool,
+    /// Remove the annotation with this number.
+    #[arg(long, value_name = "ID", help_heading = "Targets")]
+    pub unannotate: Option<u64>,
+    /// List the annotations, of one target if given.
+    #[arg(long, value_name = "TARGET", num_args = 0..=1, help_heading = "Targets")]
+    pub annotations: Option<Option<String>>,
+    /// Where annotations are kept; the data directory if not given.
+    #[arg(long, value_name = "FILE", help_heading = "Targets")]
+    pub annotations_file: Option<PathBuf>,
+
+    /// Download the IANA port registry now and use it instead of the built-in snapshot.
+    #[arg(long, help_heading = "Port registry")]
+    pub update_ports: bool,
+    /// Keep the downloaded port registry current while the monitor runs, e.g. `7d`.
+    #[arg(long, value_name = "INTERVAL", value_parser = parse_interval, conflicts_with = "ports_file", help_heading = "Port registry")]
+    pub ports_update_interval: Option<Duration>,
+    /// Where `--update-ports` downloads the registry from; IANA if not given.
+    #[arg(long, value_name = "URL", help_heading = "Port registry")]
+    pub ports_url: Option<String>,
+    /// Use this copy of the IANA registry (CSV) instead of the built-in one.
+    #[arg(long, value_name = "FILE", help_heading = "Port registry")]
+    pub ports_file: Option<PathBuf>,
+    /// Look a port (`6010`, `53/udp`) or a service name up in the registry.
+    #[arg(long, value_name = "PORT|NAME", help_heading = "Port registry")]
+    pub lookup_port: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Adds a target to the address book; a host name with every address it resolves to.
+    Add {
+        /// `host:port`, `ip:port` or `[ipv6]:port`.
+        addr: String,
+        /// How to check it: tcp, icmp, udp, http, browser, windows, systemd, disk, s3,
+        /// api_quota, composite or plugin.
+        #[arg(long, default_value = "tcp", value_parser = parse_check_kind)]
+        check: CheckKind,
+        /// The page http and browser checks request; `http(s)://<addr>/` if not given.
+        #[arg(long)]
+        url: Option<String>,
+        /// The check's settings as JSON, e.g. `{"kind":"plugin","name":"redis"}`; sets the
+        /// check to the settings' kind.
+        #[arg(long, value_parser = parse_check_spec, conflicts_with = "url")]
+        spec: Option<CheckSpec>,
+        /// Seconds between checks; the monitor's default if not given.
+        #[arg(long)]
+        interval: Option<u64>,
+        /// Service (group) the target belongs to.
+        #[arg(long)]
+        group: Option<String>,
+        /// User or team whose channels get the target's alerts (see `--owners`).
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// Removes a target from the address book; a host name with all of its addresses.
+    Remove { addr: String },
+    /// Lists the targets in the address book.
+    List,
+    /// Checks the given targets, or all of them, once; exits non-zero if any is down.
+    Check { addrs: Vec<String> },
+    /// Checks every target periodically and prints the results until interrupted.
+    Run {
+        /// Seconds between checks of targets without an interval of their own.
+        #[arg(long, default_value_t = 60)]
+        interval: u64,
+        /// Checks in flight at once.
+        #[arg(long)]
+        concurrency: Option<usize>,
+    },
+    /// Runs a scripted browser transaction (a TOML file of goto, click, fill, wait_for and
+    /// assert_text steps) once and times each step; exits non-zero if a step fails.
+    Transaction {
+        file: PathBuf,
+        #[arg(long, default_value = DEFAULT_WEBDRIVER_URL)]
+        webdriver: String,
+        /// Start a WebDriver server for this run instead of using `--webdriver`: `auto` for
+        /// the browser's driver on PATH, a driver's name or its path.
+        #[arg(long, value_name = "DRIVER")]
+        start_webdriver: Option<String>,
+        /// chrome, edge, firefox or safari.
+        #[arg(long, default_value = "chrome")]
+        browser: BrowserKind,
+        /// Show the browser window.
+        #[arg(long)]
+        headed: bool,
+    },
+    /// Checks a website once; exits non-zero unless it answered as expected.
+    WebCheck {
+        url: String,
+        /// Status code to expect; any 2xx or 3xx if not given.
+        #[arg(long)]
+        expect_status: Option<u16>,
+        /// Text the page body must contain.
+        #[arg(long)]
+        expect_body: Option<String>,
+        /// Load the page in a browser through WebDriver instead, and time until it is usable.
+        #[arg(long)]
+        browser: bool,
+        /// With `--browser`: CSS selector of the element that marks the page as usable.
+        #[arg(long, requires = "browser")]
+        selector: Option<String>,
+        /// WebDriver server for `--browser`.
+        #[arg(long, default_value = DEFAULT_WEBDRIVER_URL, requires = "browser")]
+        webdriver: String,
+        /// With `--browser`: start a WebDriver server for this run instead of using
+        /// `--webdriver`: `auto` for the first driver on PATH, a driver's name or its path.
+        #[arg(long, value_name = "DRIVER", requires = "browser")]
+        start_webdriver: Option<String>,
+        /// With `--browser`: fail if the page logged console errors or had requests fail.
+        #[arg(long, requires = "browser")]
+        fail_on_page_errors: bool,
+    },
+    /// Scans a host for open TCP ports and names the services registered for them.
+    Scan {
+        /// Host name or IP address.
+        host: String,
+        /// Ports to try: one, like `22`, or a range, like `1-1024`.
+        #[arg(long, default_value = "1-1024", value_parser = parse_port_range)]
+        ports: RangeInclusive<u16>,
+        /// Milliseconds to wait for each connection.
+        #[arg(long, default_value_t = 500)]
+        timeout_ms: u64,
+        /// Connections in flight at once.
+        #[arg(long, default_value_t = 256)]
+        concurrency: usize,
+    },
+    /// Fires parallel requests at a URL for a while and prints the throughput and latency
+    /// percentiles; exits non-zero if any request failed or the burst was cut short.
+    LoadTest {
+        url: String,
+        /// Hosts that may be load tested, comma separated; the URL's host must be one of
+        /// them, so a typo doesn't load someone else's service.
+        #[arg(long, value_delimiter = ',', required = true)]
+        allow: Vec<String>,
+        /// Request loops running at once, at most 50.
+        #[arg(long, default_value_t = 10)]
+        concurrency: usize,
+        /// Seconds to keep firing, at most 60.
+        #[arg(long, default_value_t = 10)]
+        duration: u64,
+        /// Repeats the burst every this many seconds until one fails.
+        #[arg(long)]
+        every: Option<u64>,
+    },
+    /// Interactive troubleshooting: resolve names, try checks, look at the scheduler, follow
+    /// the event bus and query recent results.
+    Shell {
+        /// Also check targets in the background every this many seconds, as `run` does.
+        #[arg(long)]
+        interval: Option<u64>,
+    },
+    /// Lists the check presets or adds the checks of one for a host.
+    Preset {
+        #[command(subcommand)]
+        action: PresetAction,
+    },
+    /// Shows or checks the settings file.
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Lists the check plugins loaded from `[plugins] dir` and the config fields they need.
+    Plugins,
+    /// Encrypts the settings file and the address book in place, or the given files, with
+    /// the passphrase in RUST_NPM_CONFIG_PASSPHRASE or the OS keyring entry named in
+    /// RUST_NPM_CONFIG_KEYRING. Files already encrypted are left as they are.
+    Encrypt { files: Vec<PathBuf> },
+    /// Tries out changes of the `[alerts]` rules on stored results.
+    Alerts {
+        #[command(subcommand)]
+        action: AlertsAction,
+    },
+    /// Compares the stored results of targets checked both here and by agents, and flags
+    /// the agents that saw a target failing while it was fine from here.
+    Vantage {
+        /// Only these targets (`host:port`); all if none given.
+        targets: Vec<String>,
+        /// Days of stored results to compare.
+        #[arg(long, default_value_t = 1)]
+        days: i64,
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Writes OpenSLO documents for the targets' availability and latency, with latency
+    /// thresholds derived from their stored results, for `oslo` or sloth.
+    SloExport {
+        /// Only these targets (`host:port`); all if none given.
+        targets: Vec<String>,
+        /// Days of stored results to derive the thresholds from, and the SLOs' window.
+        #[arg(long, default_value_t = 28)]
+        days: u32,
+        /// Share of checks that must succeed.
+        #[arg(long, default_value_t = 0.999)]
+        availability: f64,
+        /// Share of checks that must be faster than the latency threshold.
+        #[arg(long, default_value_t = 0.99)]
+        latency: f64,
+        /// File to write to instead of stdout.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PresetAction {
+    /// Lists the presets and the checks each one adds.
+    List,
+    /// Adds a target for every check of a preset, e.g. `preset apply mail mx.example.com`.
+    Apply {
+        preset: String,
+        /// Host name or IP address.
+        host: String,
+        /// Service (group) the new targets belong to.
+        #[arg(long)]
+        group: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Prints every setting with its default and what it does.
+    PrintDefault,
+    /// Validates a settings file (the `--config` one or the default) and prints the
+    /// settings in effect; exits non-zero listing every problem otherwise.
+    Check { file: Option<PathBuf> },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AlertsAction {
+    /// Replays the stored results of the last days through the current rules and the given
+    /// changes of them, and lists the alerts that would have fired differently. Nothing is
+    /// sent.
+    DryRun {
+        /// Only these targets (`host:port`); all if none given.
+        targets: Vec<String>,
+        /// Days of stored results to replay.
+        #[arg(long, default_value_t = 7)]
+        days: i64,
+        /// Failed checks in a row before a target counts as down.
+        #[arg(long)]
+        down_after: Option<u32>,
+        /// Successful checks in a row before a down target counts as up again.
+        #[arg(long)]
+        stable_checks: Option<u32>,
+        /// Don't announce recoveries from outages shorter than this, e.g. `2m`.
+        #[arg(long, value_parser = parse_interval)]
+        min_downtime: Option<Duration>,
+        /// Don't announce recoveries at all.
+        #[arg(long)]
+        no_recovery: bool,
+    },
+}
+
+pub fn parse_check_kind(value: &str) -> Result<CheckKind, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "tcp" => Ok(CheckKind::Tcp),
+        "icmp" | "ping" => Ok(CheckKind::Icmp),
+        "udp" => Ok(CheckKind::Udp),
+        "http" | "https" => Ok(CheckKind::Http),
+        "browser" => Ok(CheckKind::Browser),
+        "windows" | "winrm" => Ok(CheckKind::Windows),
+        "systemd" => Ok(CheckKind::Systemd),
+        "disk" => Ok(CheckKind::Disk),
+        "s3" => Ok(CheckKind::S3),
+        "api_quota" | "quota" => Ok(CheckKind::ApiQuota),
+        "composite" | "journey" => Ok(CheckKind::Composite),
+        "plugin" => Ok(CheckKind::Plugin),
+        _ => Err(format!(
+            "unknown check '{}', expected tcp, icmp, udp, http, browser, windows, systemd, disk, s3, api_quota, composite or plugin",
+            value
+        )),
+    }
+}
+
+fn parse_check_spec(value: &str) -> Result<CheckSpec, String> {
+    serde_json::from_str(value).map_err(|e| format!("invalid check settings: {}", e))
+}
+
+fn parse_port_range(value: &str) -> Result<RangeInclusive<u16>, String> {
+    iana_ports::parse_ports(value).ok_or_else(|| format!("'{}' is not a port or a range like 1-1024", value))
+}
+
+/// Resolves `host:port` to the first address of the host.
+fn host_spec(input: &str) -> Result<HostSpec, String> {
+    let (spec, warning) = HostSpec::parse(input, 0)?;
+    if warning.is_some() {
+        return Err(format!("'{}' needs a port, e.g. {}:443", input, spec.host));
+    }
+    Ok(spec)
+}
+
+/// The first address of `input`, for one-off checks; targets are added with all of their
+/// addresses by `address::add_host`.
+pub async fn resolve(input: &str) -> Result<SocketAddr, String> {
+    let spec = host_spec(input)?;
+    tokio::net::lookup_host((spec.host.as_str(), spec.port))
+        .await
+        .map_err(|e| format!("cannot resolve {}: {}", spec, e))?
+        .next()
+        .ok_or_else(|| format!("{} has no addresses", spec))
+}
+
+/// One line per result, as printed by `check` and `run`.
+fn describe(result: &CheckResult, service: Option<&str>) -> String {
+    let target = match service {
+        Some(service) => format!("{} ({})", result.target, service),
+        None => result.target.clone(),
+    };
+    match (&result.error, result.latency_ms()) {
+        (None, Some(ms)) => format!("{} UP {:.0} ms", target, ms),
+        (None, None) => format!("{} UP", target),
+        (Some(e), _) => match result.hint {
+            Some(hint) => format!("{} DOWN: {}\n  {}", target, e, hint),
+            None => format!("{} DOWN: {}", target, e),
+        },
+    }
+}
+
+/// The WebDriver URL to use: `webdriver`, or that of a driver started from `start` (see
+/// `--start-webdriver`), which is killed when the returned handle is dropped.
+async fn webdriver_for(
+    webdriver: String,
+    start: Option<&str>,
+    browser: Option<BrowserKind>,
+) -> Result<(String, Option<Arc<ManagedDriver>>), Box<dyn std::error::Error>> {
+    let Some(spec) = start else {
+        return Ok((webdriver, None));
+    };
+    let driver = ManagedDriver::start(webdriver_process::find_driver(spec, browser)?, DRIVER_STARTUP_TIMEOUT).await?;
+    Ok((driver.url(), Some(driver)))
+}
+
+/// Runs `command` and returns whether it succeeded. `book` is where target changes are
+/// saved; check results are printed in `format`, with the service `ports` names for their port.
+pub async fn run(
+    command: Command,
+    monitor: Arc<Monitor>,
+    history: Arc<History>,
+    book: Option<&Path>,
+    format: LogFormat,
+    ports: Arc<PortRegistry>,
+) -> bool {
+    match command {
+        Command::Add {
+            addr,
+            check,
+            url,
+            spec,
+            interval,
+            group,
+            owner,
+        } => {
+            let settings = |resolved| {
+                let mut target = MonitorTarget::new(resolved).with_check(check);
+                target.spec = url.clone().map(|url| match check {
+                    CheckKind::Browser => CheckSpec::Browser {
+                        url,
+                        selector: None,
+                        fail_on_errors: false,
+                    },
+                    _ => CheckSpec::Http {
+                        url,
+                        expect_status: None,
+                        body_contains: None,
+                    },
+                });
+                if let Some(spec) = spec.clone() {
+                    target = target.with_spec(spec);
+                }
+                target.interval = interval.map(Duration::from_secs);
+                match &owner {
+                    Some(owner) => target.with_owner(owner),
+                    None => target,
+                }
+            };
+            let changed = match host_spec(&addr) {
+                Ok(spec) => address::add_host(&monitor, &spec, settings).await.and_then(|added| {
+                    added.into_iter().try_for_each(|resolved| {
+                        println!("Added {}", resolved);
+                        monitor.set_group(resolved, group.clone())
+                    })
+                }),
+                Err(e) => Err(e.into()),
+            };
+            crate::save_address_book(changed, &monitor, book);
+            true
+        }
+        Command::Remove { addr } => {
+            let named: Vec<SocketAddr> = match host_spec(&addr) {
+                Ok(spec) => monitor
+                    .monitor_targets()
+                    .into_iter()
+                    .filter(|t| t.host.as_deref() == Some(spec.host.as_str()) && t.address.port() == spec.port)
+                    .map(|t| t.address)
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            let changed = if named.is_empty() {
+                match resolve(&addr).await {
+                    Ok(resolved) => monitor.remove_target(resolved),
+                    Err(e) => Err(e.into()),
+                }
+            } else {
+                named.into_iter().try_for_each(|resolved| monitor.remove_target(resolved))
+            };
+            crate::save_address_book(changed, &monitor, book);
+            true
+        }
+        Command::List => {
+            for target in monitor.monitor_targets() {
+                let interval = target.interval.map_or("default".to_string(), |d| format!("{}s", d.as_secs()));
+                let mut line = format!("{:<24} {:<12} {:<8}", target.address, target.check.to_string(), interval);
+                if let Some(group) = monitor.group(target.address) {
+                    line = format!("{} {}", line, group);
+                }
+                if let Some(owner) = &target.owner {
+                    line = format!("{} owner={}", line, owner);
+                }
+                if let Some(pause) = monitor.pause_of(target.address) {
+                    line = format!("{} ({})", line, pause);
+                }
+                println!("{}", line.trim_end());
+            }
+            true
+        }
+        Command::Check { addrs } => {
+            let results = if addrs.is_empty() {
+                monitor.run_all().await
+            } else {
+                let mut results = Vec::new();
+                for addr in &addrs {
+                    match resolve(addr).await {
+                        Ok(resolved) => results.push(monitor.run_check(resolved).await),
+                        Err(e) => results.push(CheckResult::failure(addr, e)),
+                    }
+                }
+                results
+            };
+            for result in &results {
+                let service = logging::service_label(&monitor, &ports, result);
+                logging::report(format, result, &logging::check_name(&monitor, result), service.as_deref(), || {
+                    describe(result, service.as_deref())
+                });
+            }
+            results.iter().all(|r| r.success)
+        }
+        Command::Run { interval, concurrency } => {
+            monitor.set_default_interval(Duration::from_secs(interval));
+            if let Some(limit) = concurrency {
+                monitor.set_concurrency(limit);
+            }
+            let mut events = monitor.bus().subscribe();
+            tokio::spawn(monitor.clone().schedule());
+            loop {
+                tokio::select! {
+                    event = events.recv() => match event {
+                        Ok(MonitorEvent::CheckCompleted(result)) => {
+                            let check = logging::check_name(&monitor, &result);
+                            let service = logging::service_label(&monitor, &ports, &result);
+                            logging::report(format, &result, &check, service.as_deref(), || {
+                                format!("{} {}", result.timestamp.to_rfc3339(), describe(&result, service.as_deref()))
+                            });
+                        }
+                        Ok(MonitorEvent::Transition(transition)) => println!("{} {:?} -> {:?}", transition.target, transition.from, transition.to),
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(missed)) => eprintln!("Missed {} events", missed),
+                        Err(RecvError::Closed) => return true,
+                    },
+                    _ = tokio::signal::ctrl_c() => return true,
+                }
+            }
+        }
+        Command::WebCheck {
+            url,
+            expect_status,
+            expect_body,
+            browser,
+            selector,
+            webdriver,
+            start_webdriver,
+            fail_on_page_errors,
+        } => {
+            if browser {
+                let (webdriver, driver) = match webdriver_for(webdriver, start_webdriver.as_deref(), None).await {
+                    Ok(started) => started,
+                    Err(e) => {
+                        eprintln!("Cannot start the WebDriver: {}", e);
+                        return false;
+                    }
+                };
+                let browser = driver.as_ref().map_or(BrowserKind::Chrome, |driver| driver.browser());
+                return match ping_test::measure_website_timing(&webdriver, browser, &url, selector.as_deref(), true).await {
+                    Ok(load) => {
+                        println!("{}: usable after {} ms", url, load.duration.as_millis());
+                        if let Some(timing) = &load.timing {
+                            println!("  {}", timing);
+                        }
+                        for error in &load.errors {
+                            println!("  {}", error);
+                        }
+                        let result = load.into_result(&url, fail_on_page_errors);
+                        if let Some(error) = &result.error {
+                            eprintln!("{} failed: {}", url, error);
+                        }
+                        result.success
+                    }
+                    Err(e) => {
+                        eprintln!("{} failed: {}{}", url, e, diagnostics::explain(&e.to_string()));
+                        false
+                    }
+                };
+            }
+            web_check(&monitor.http_pool(), &url, expect_status, expect_body.as_deref()).await
+        }
+        Command::Transaction {
+            file,
+            webdriver,
+            start_webdriver,
+            browser,
+            headed,
+        } => {
+            let transaction = match Transaction::load(&file) {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    eprintln!("Cannot read {}: {}", file.display(), e);
+                    return false;
+                }
+            };
+            let (webdriver, _driver) = match webdriver_for(webdriver, start_webdriver.as_deref(), Some(browser)).await {
+                Ok(started) => started,
+                Err(e) => {
+                    eprintln!("Cannot start the WebDriver: {}", e);
+                    return false;
+                }
+            };
+            let result = ping_test::run_browser_transaction(&webdriver, browser, &transaction, !headed).await;
+            for step in &result.steps {
+                let latency = step.latency.map_or("-".to_string(), |d| format!("{} ms", d.as_millis()));
+                println!("  {:<8} {:<40} {:>8}  {}", format!("{:?}", step.status), step.name, latency, step.error.as_deref().unwrap_or_default());
+            }
+            println!("{}", describe(&result, None));
+            result.success
+        }
+        Command::Scan {
+            host,
+            ports: range,
+            timeout_ms,
+            concurrency,
+        } => scan(&host, range, Duration::from_millis(timeout_ms), concurrency, &ports).await,
+        Command::LoadTest {
+            url,
+            allow,
+            concurrency,
+            duration,
+            every,
+        } => {
+            let config = LoadTestConfig {
+                url,
+                concurrency,
+                duration: Duration::from_secs(duration),
+                request_timeout: Duration::from_secs(5),
+            };
+            let limits = SafetyLimits {
+                allowed_hosts: allow,
+                ..SafetyLimits::default()
+            };
+            let client = HttpPool::default().client().clone();
+            match every {
+                Some(every) => {
+                    let mut passed = true;
+                    load_test::run_schedule(&client, &config, &limits, Duration::from_secs(every), |report| {
+                        passed = print_load_report(report);
+                        passed
+                    })
+                    .await;
+                    passed
+                }
+                None => print_load_report(load_test::run_burst(&client, &config, &limits).await),
+            }
+        }
+        Command::Preset { action: PresetAction::List } => {
+            for preset in PRESETS {
+                let checks: Vec<String> = preset
+                    .checks
+                    .iter()
+                    .map(|probe| match probe.check {
+                        CheckKind::Icmp => probe.service.to_string(),
+                        check => format!("{}/{} {}", probe.port, check.name(), probe.service),
+                    })
+                    .collect();
+                println!("{:<10} {}: {}", preset.name, preset.description, checks.join(", "));
+            }
+            true
+        }
+        Command::Preset {
+            action: PresetAction::Apply { preset, host, group },
+        } => {
+            let Some(preset) = presets::find(&preset) else {
+                let names: Vec<&str> = PRESETS.iter().map(|p| p.name).collect();
+                eprintln!("Unknown preset '{}', expected one of {}", preset, names.join(", "));
+                return false;
+            };
+            let ip = match tokio::net::lookup_host((host.as_str(), 0)).await.map(|mut addrs| addrs.next()) {
+                Ok(Some(addr)) => addr.ip(),
+                Ok(None) => {
+                    eprintln!("{} has no addresses", host);
+                    return false;
+                }
+                Err(e) => {
+                    eprintln!("Cannot resolve {}: {}", host, e);
+                    return false;
+                }
+            };
+            let mut added = 0;
+            for target in preset.expand(ip) {
+                let addr = target.address;
+                match monitor.add_monitor_target(target).and_then(|()| monitor.set_group(addr, group.clone())) {
+                    Ok(()) => {
+                        println!("Added {} ({})", addr, monitor.check_kind(addr).name());
+                        added += 1;
+                    }
+                    Err(e) => println!("Skipped: {}", e),
+                }
+            }
+            if added > 0 {
+                crate::save_address_book(Ok(()), &monitor, book);
+            }
+            true
+        }
+        Command::Shell { interval } => shell::run(monitor, history, ports, interval).await,
+        // `main` runs these before loading anything, with the `--config` file.
+        Command::Config { action } => config(&action, None),
+        Command::Encrypt { files } => encrypt(&files, None, book),
+        Command::Plugins => plugins(&monitor),
+        // main runs it with the database and settings; without them there's nothing to replay.
+        Command::Alerts { action } => alerts(&action, None, &RecoveryRules::default(), &UnitPreferences::default()).await,
+        Command::Vantage { targets, days, json } => vantage(&targets, days, json, None, None, &UnitPreferences::default()).await,
+        Command::SloExport { targets, days, availability, latency, output } => {
+            slo_export(&targets, &slo_config(days, availability, latency), output.as_deref(), None, book).await
+        }
+    }
+}
+
+/// Runs a `config` subcommand; `path` is the `--config` file, if given.
+pub fn config(action: &ConfigAction, path: Option<&Path>) -> bool {
+    match action {
+        ConfigAction::PrintDefault => {
+            print!("{}", DEFAULT_CONFIG);
+            true
+        }
+        ConfigAction::Check { file } => {
+            let Some(path) = file.as_deref().or(path).map(Path::to_path_buf).or_else(Config::default_path) else {
+                eprintln!("No settings file location; pass one");
+                return false;
+            };
+            let effective = Config::load(&path, true).map_err(|e| e.to_string()).and_then(|config| {
+                toml::to_string(&config).map_err(|e| e.to_string())
+            });
+            match effective {
+                Ok(settings) => {
+                    println!("# {} is valid; settings in effect:\n{}", path.display(), settings);
+                    true
+                }
+                Err(e) => {
+                    eprintln!("{}", e);
+                    false
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wasm-plugins")]
+fn plugins(monitor: &Monitor) -> bool {
+    let plugins = monitor.plugins();
+    let names = plugins.names();
+    if names.is_empty() {
+        println!("No plugins loaded; put their manifests and modules in the [plugins] dir");
+    }
+    for manifest in names.into_iter().filter_map(|name| plugins.get(name)).map(|plugin| plugin.manifest()) {
+        println!("{} v{} ({})", manifest.name, manifest.version, manifest.module.display());
+        if !manifest.description.is_empty() {
+            println!("  {}", manifest.description);
+        }
+        if let Some(required) = manifest.config_schema.get("required").and_then(|r| r.as_array()) {
+            let fields: Vec<&str> = required.iter().filter_map(|f| f.as_str()).collect();
+            println!("  requires {}", fields.join(", "));
+        }
+    }
+    true
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+fn plugins(_monitor: &Monitor) -> bool {
+    eprintln!("This build has no plugin support. Rebuild with --features wasm-plugins");
+    false
+}
+
+/// Runs the `encrypt` subcommand: `files`, or else the `config` and `book` files that exist.
+pub fn encrypt(files: &[PathBuf], config: Option<&Path>, book: Option<&Path>) -> bool {
+    let Some(source) = KeySource::from_env() else {
+        eprintln!("No key; set {} or {}", PASSPHRASE_ENV, KEYRING_ENV);
+        return false;
+    };
+    let files = if files.is_empty() {
+        let config = config.map(Path::to_path_buf).or_else(Config::default_path);
+        let book = book.map(Path::to_path_buf).or_else(AddressBook::default_path);
+        config.into_iter().chain(book).filter(|path| path.exists()).collect()
+    } else {
+        files.to_vec()
+    };
+    if files.is_empty() {
+        eprintln!("No settings file or address book to encrypt");
+        return false;
+    }
+    let mut ok = true;
+    for path in &files {
+        match encrypted_config::encrypt_file(path, &source) {
+            Ok(true) => println!("Encrypted {}", path.display()),
+            Ok(false) => println!("{} is already encrypted", path.display()),
+            Err(e) => {
+                eprintln!("Cannot encrypt {}: {}", path.display(), e);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// Results stored since `since`, oldest first; only those of `targets` unless it is empty.
+async fn stored_results(store: &Storage, targets: &[String], since: chrono::DateTime<chrono::Utc>) -> Result<Vec<CheckResult>, StoreError> {
+    if targets.is_empty() {
+        return store.results_since(since).await;
+    }
+    let to = chrono::Utc::now();
+    let mut results = Vec::new();
+    for target in targets {
+        results.extend(store.history_range(target, since, to).await?);
+    }
+    results.sort_by_key(|r| r.timestamp);
+    Ok(results)
+}
+
+/// Runs an `alerts` subcommand on the results in `store`, with `current` as the rules in
+/// effect.
+pub async fn alerts(action: &AlertsAction, store: Option<&Storage>, current: &RecoveryRules, units: &UnitPreferences) -> bool {
+    let AlertsAction::DryRun {
+        targets,
+        days,
+        down_after,
+        stable_checks,
+        min_downtime,
+        no_recovery,
+    } = action;
+    let Some(store) = store else {
+        eprintln!("alerts dry-run needs a database");
+        return false;
+    };
+    let proposed = RecoveryRules {
+        down_after: down_after.unwrap_or(current.down_after),
+        stable_checks: stable_checks.unwrap_or(current.stable_checks),
+        min_downtime: min_downtime.unwrap_or(current.min_downtime),
+        notify_on_recovery: current.notify_on_recovery && !no_recovery,
+    };
+    if proposed.down_after == 0 || proposed.stable_checks == 0 {
+        eprintln!("--down-after and --stable-checks must be at least 1");
+        return false;
+    }
+    let results = match stored_results(store, targets, chrono::Utc::now() - chrono::Duration::days(*days)).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Cannot read results: {}", e);
+            return false;
+        }
+    };
+    print!("{}", DryRun::new(&results, current, &proposed).in_timezone(units.timezone()));
+    true
+}
+
+/// Runs the `vantage` subcommand on the results in `store`, judging the agents against
+/// `central`, this instance's agent name.
+pub async fn vantage(targets: &[String], days: i64, json: bool, store: Option<&Storage>, central: Option<&str>, units: &UnitPreferences) -> bool {
+    let Some(store) = store else {
+        eprintln!("vantage needs a database");
+        return false;
+    };
+    let to = chrono::Utc::now();
+    let results = match stored_results(store, targets, to - chrono::Duration::days(days)).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Cannot read results: {}", e);
+            return false;
+        }
+    };
+    let report = vantage::compare(&results, to - chrono::Duration::days(days), to, &VantageConfig {
+        central: central.map(str::to_string),
+        ..VantageConfig::default()
+    });
+    if json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Cannot write the report: {}", e);
+                return false;
+            }
+        }
+    } else if report.targets.is_empty() {
+        println!("No target was checked from more than one vantage point");
+    } else {
+        let flagged = report.targets.iter().any(|t| t.has_flags());
+        print!("{}", report.in_timezone(units.timezone()));
+        if flagged {
+            println!("! fails from that vantage point while the target is fine elsewhere");
+        }
+    }
+    true
+}
+
+pub fn slo_config(days: u32, availability: f64, latency: f64) -> SloConfig {
+    SloConfig {
+        availability_target: availability,
+        latency_target: latency,
+        window_days: days,
+        ..SloConfig::default()
+    }
+}
+
+/// Runs the `slo-export` subcommand on the results in `store`, with the services (groups)
+/// of the targets from the address book at `book`.
+pub async fn slo_export(targets: &[String], config: &SloConfig, output: Option<&Path>, store: Option<&Storage>, book: Option<&Path>) -> bool {
+    let Some(store) = store else {
+        eprintln!("slo-export needs a database");
+        return false;
+    };
+    let since = chrono::Utc::now() - chrono::Duration::days(config.window_days.into());
+    let results = match stored_results(store, targets, since).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Cannot read results: {}", e);
+            return false;
+        }
+    };
+    let services: HashMap<String, String> = match book.map(AddressBook::load).transpose() {
+        Ok(book) => book
+            .into_iter()
+            .flat_map(|book| book.targets)
+            .filter_map(|entry| Some((entry.address.to_string(), entry.group?)))
+            .collect(),
+        Err(e) => {
+            eprintln!("Address book not loaded, exporting without services: {}", e);
+            HashMap::new()
+        }
+    };
+    let yaml = slo_export::to_openslo(&slo_export::target_slis(&results, config), &services, config);
+    match output {
+        Some(path) => match std::fs::write(path, yaml) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Cannot write {}: {}", path.display(), e);
+                false
+            }
+        },
+        None => {
+            print!("{}", yaml);
+            true
+        }
+    }
+}
+
+/// Prints one burst's report; false if it failed, had errors or was aborted.
+fn print_load_report(report: Result<LoadTestReport, String>) -> bool {
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Load test not run: {}", e);
+            return false;
+        }
+    };
+    let ms = |d: Option<Duration>| d.map_or("-".to_string(), |d| format!("{} ms", d.as_millis()));
+    println!(
+        "{}: {} requests in {:.1}s ({:.1}/s), {} failed ({:.1}%)",
+        report.url,
+        report.requests,
+        report.elapsed.as_secs_f64(),
+        report.throughput_rps,
+        report.errors,
+        report.error_rate() * 100.0
+    );
+    println!("  p50 {}  p90 {}  p99 {}  max {}", ms(report.p50), ms(report.p90), ms(report.p99), ms(report.max));
+    if let Some(reason) = &report.aborted {
+        println!("  stopped early: {}", reason);
+    }
+    report.errors == 0 && report.aborted.is_none()
+}
+
+/// Scans `host` and prints its open ports with their registered services; false if the
+/// host doesn't resolve.
+async fn scan(host: &str, range: RangeInclusive<u16>, timeout: Duration, concurrency: usize, ports: &PortRegistry) -> bool {
+    let ip = match tokio::net::lookup_host((host, 0)).await.map(|mut addrs| addrs.next()) {
+        Ok(Some(addr)) => addr.ip(),
+        Ok(None) => {
+            eprintln!("{} has no addresses", host);
+            return false;
+        }
+        Err(e) => {
+            eprintln!("Cannot resolve {}: {}", host, e);
+            return false;
+        }
+    };
+    let total = range.len();
+    println!("Scanning {} ports of {} ({})", total, host, ip);
+    let open = ping_test::scan_ports(ip, range, timeout, concurrency).await;
+    for open in &open {
+        let service = ports.service_name(open.port, Protocol::Tcp).unwrap_or_else(|| "unknown".to_string());
+        println!("{:>5}/tcp  {:<20} {:.1} ms", open.port, service, open.latency.as_secs_f64() * 1000.0);
+    }
+    println!("{} of {} ports open", open.len(), total);
+    true
+}
+
+/// Checks `url` once over plain HTTP and prints the timings; false unless it answered
+/// as expected.
+pub async fn web_check(pool: &HttpPool, url: &str, expect_status: Option<u16>, expect_body: Option<&str>) -> bool {
+    let mut check = HttpCheck::new(url, Duration::from_secs(10));
+    if let Some(status) = expect_status {
+        check = check.expect_status(status);
+    }
+    if let Some(text) = expect_body {
+        check = check.body_contains(text);
+    }
+    let result = check.run(pool).await;
+    let metric = |name: &str| result.metrics.get(name).map_or("-".to_string(), |v| format!("{:.0}", v));
+    println!(
+        "{}: status {}, TTFB {} ms, total {} ms",
+        url,
+        metric("status_code"),
+        metric("ttfb_ms"),
+        metric("total_ms")
+    );
+    if let Some(e) = &result.error {
+        eprintln!("{} failed: {}", url, e);
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    fn parse(line: &str) -> Cli {
+        Cli::try_parse_from(line.split_whitespace()).unwrap()
+    }
+
+    #[test]
+    fn test_subcommands_and_mode_flags_parse() {
+        let cli = parse("rust_npm add db:5432 --check udp --interval 30 --targets t.toml");
+        assert_eq!(cli.targets.as_deref(), Some("t.toml"));
+        match cli.command {
+            Some(Command::Add { addr, check, interval, group, owner, .. }) => {
+                assert_eq!((addr.as_str(), check, interval, group), ("db:5432", CheckKind::Udp, Some(30), None));
+                assert_eq!(owner, None);
+            }
+            other => panic!("parsed as {:?}", other),
+        }
+        let cli = Cli::parse_from(["rust_npm", "add", "cache:6379", "--spec", r#"{"kind":"plugin","name":"redis"}"#]);
+        assert!(matches!(cli.command, Some(Command::Add { spec: Some(CheckSpec::Plugin { .. }), .. })));
+        assert!(matches!(parse("rust_npm web-check https://example.com").command, Some(Command::WebCheck { browser: false, .. })));
+        assert!(matches!(parse("rust_npm run").command, Some(Command::Run { interval: 60, .. })));
+        assert!(matches!(
+            parse("rust_npm config check --config a.toml"),
+            Cli { command: Some(Command::Config { action: ConfigAction::Check { file: None } }), config: Some(_), .. }
+        ));
+        assert!(matches!(parse("rust_npm shell --interval 30").command, Some(Command::Shell { interval: Some(30) })));
+        assert!(matches!(parse("rust_npm scan db --ports 20-25").command, Some(Command::Scan { ports, .. }) if ports == (20..=25)));
+        assert!(matches!(
+            parse("rust_npm preset apply web example.com --group shop").command,
+            Some(Command::Preset { action: PresetAction::Apply { group: Some(_), .. } })
+        ));
+        assert!(matches!(
+            parse("rust_npm load-test http://staging/ --allow staging,qa --every 300").command,
+            Some(Command::LoadTest { allow, every: Some(300), concurrency: 10, .. }) if allow == ["staging", "qa"]
+        ));
+        assert!(matches!(
+            parse("rust_npm vantage erp:443 --days 7 --json").command,
+            Some(Command::Vantage { targets, days: 7, json: true }) if targets == ["erp:443"]
+        ));
+        assert!(matches!(
+            parse("rust_npm slo-export --availability 0.99 -o slos.yaml").command,
+            Some(Command::SloExport { days: 28, latency: 0.99, output: Some(_), .. })
+        ));
+
+        let cli = parse("rust_npm --gui --read-only --interval 30 --canaries 10.0.0.1:53,default --database memory:");
+        assert!(cli.command.is_none());
+        assert!(cli.options.gui && cli.options.read_only);
+        assert_eq!(cli.options.interval, Some(30));
+        assert_eq!(cli.options.canaries, Some(vec!["10.0.0.1:53".to_string(), "default".to_string()]));
+        assert_eq!(cli.database.as_deref(), Some("memory:"));
+        let cli = parse("rust_npm --service checkout --lookup-port 53/udp --annotations");
+        assert_eq!(cli.options.service.as_deref(), Some("checkout"));
+        assert_eq!(cli.options.lookup_port.as_deref(), Some("53/udp"));
+        assert_eq!(cli.options.annotations, Some(None));
+        assert_eq!(cli.options.browser_sessions, 2);
+        // Flags that don't exist, or miss the flag they belong to, are usage errors now.
+        assert!(Cli::try_parse_from(["rust_npm", "--gui", "--no-such-flag"]).is_err());
+        assert!(Cli::try_parse_from(["rust_npm", "--pause", "db:5432"]).is_err());
+        assert!(Cli::try_parse_from(["rust_npm", "--expect-status", "200"]).is_err());
+        Cli::command().debug_assert();
+    }
+}
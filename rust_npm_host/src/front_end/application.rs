@@ -0,0 +1,1669 @@
+use iced::futures::stream::{self, Stream};
+use iced::widget::{Column, Row, Space, button, canvas, column, container, pick_list, row, scrollable, text, text_input};
+use iced::keyboard::{self, Key, key::Named};
+use iced::{Element, Length, Size, Subscription, Task, event, window};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+
+use super::command_palette::{self, Command, PaletteTarget};
+use super::desktop_notify;
+use super::latency_chart::{LatencyChart, TimeRange};
+use super::target_form::{self, TargetForm};
+use super::virtual_list::VirtualList;
+use crate::back_end::address::{self, HostSpec};
+use crate::back_end::auth::Scope;
+use crate::back_end::browser_emulator::BrowserKind;
+use crate::back_end::check_result::CheckResult;
+use crate::back_end::csv_import::{self, ColumnMapping, ImportPreview, ImportSummary};
+use crate::back_end::diagnostics;
+use crate::back_end::event_bus::MonitorEvent;
+use crate::back_end::ha::Role;
+use crate::back_end::history::History;
+use crate::back_end::iana_ports::{PortRegistry, ServiceSuggestion};
+use crate::back_end::monitor::{CheckKind, Monitor};
+use crate::back_end::pause::{self, Pause};
+use crate::back_end::ping_test;
+use crate::back_end::selector_check::{self, SelectorValidation};
+use crate::back_end::service::Rollup;
+use crate::back_end::state_tracker::TargetState;
+use crate::back_end::target::MonitorTarget;
+use crate::back_end::topology::{DependencyConfig, NodeId, Topology};
+use crate::back_end::units::UnitPreferences;
+use crate::back_end::waterfall::{self, Waterfall};
+
+const MAX_LOG_LINES: usize = 50;
+const MAX_SUGGESTIONS: usize = 8;
+// Results kept per target for the pop-out's list of recent checks.
+const MAX_HISTORY: usize = 60;
+const CHART_WIDTH: usize = 40;
+const LATENCY_CHART_HEIGHT: f32 = 180.0;
+const MAX_PALETTE_ENTRIES: usize = 12;
+// Events handled per update at most; a burst of results becomes one redraw, not thousands.
+const MAX_EVENT_BATCH: usize = 512;
+// Every target row has this height so the list can be virtualized.
+const ROW_HEIGHT: f32 = 32.0;
+// Fixed columns of the target table, shared by the header and the rows.
+const DOT_WIDTH: f32 = 20.0;
+const TIME_WIDTH: f32 = 80.0;
+const LATENCY_WIDTH: f32 = 80.0;
+const KEY_HELP: &str = "Ctrl+K commands | Up/Down select | Enter check | p pause | a acknowledge | o pop out";
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    HostChanged(String),
+    PortChanged(String),
+    PickService(u16),
+    CheckKindPicked(CheckKind),
+    IntervalChanged(String),
+    /// Loads a target's settings into the form.
+    EditTarget(SocketAddr),
+    CancelEdit,
+    /// Deletes a target; the first press only asks for confirmation.
+    RemoveTarget(SocketAddr),
+    ServicesLoaded(Result<Arc<Vec<ServiceSuggestion>>, String>),
+    AddTarget,
+    RunCheck(SocketAddr),
+    RunAll,
+    PopOut(SocketAddr),
+    /// Time range of a target's latency chart.
+    ChartRange(SocketAddr, TimeRange),
+    WindowClosed(window::Id),
+    /// A back-end call finished; `Err` carries a message for the error banner.
+    Done(Result<(), String>),
+    /// Events that arrived since the last update, oldest first.
+    Events(Vec<MonitorEvent>),
+    /// The GUI fell behind the event bus and missed this many events.
+    Lagged(u64),
+    DismissError,
+    Key(KeyAction),
+    PaletteQuery(String),
+    PaletteSubmit,
+    RunCommand(Command),
+    ToggleImport,
+    ImportPathChanged(String),
+    ImportColumnsChanged(String),
+    PreviewImport,
+    ImportPreviewed(Result<Arc<ImportPreview>, String>),
+    ApplyImport,
+    ImportApplied(ImportSummary),
+    ToggleWaterfall,
+    WaterfallUrlChanged(String),
+    WaterfallSelectorChanged(String),
+    WaterfallBrowserPicked(BrowserKind),
+    RunWaterfall,
+    WaterfallLoaded(Result<Arc<Waterfall>, String>),
+    ValidateSelector,
+    SelectorValidated(Result<SelectorValidation, String>),
+    ToggleTopology,
+    TargetsScrolled(scrollable::Viewport),
+    PauseReasonChanged(String),
+    PauseExpiryChanged(String),
+    ConfirmPause,
+    CancelPause,
+    ToggleNotify(SocketAddr),
+}
+
+impl Message {
+    /// Whether the message changes targets, and so needs admin rights. Running checks,
+    /// charts and pop-outs are open to viewers.
+    fn changes_targets(&self) -> bool {
+        match self {
+            Message::EditTarget(_)
+            | Message::RemoveTarget(_)
+            | Message::AddTarget
+            | Message::ToggleImport
+            | Message::PreviewImport
+            | Message::ApplyImport
+            | Message::ConfirmPause => true,
+            Message::RunCommand(command) => command.changes_targets(),
+            _ => false,
+        }
+    }
+}
+
+/// Keyboard shortcuts; letter keys only count when no text field has focus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyAction {
+    TogglePalette,
+    Escape,
+    Up,
+    Down,
+    Enter,
+    Check,
+    Pause,
+    Acknowledge,
+    PopOut,
+}
+
+/// State of the CSV import panel: file, column mapping and the last dry-run result.
+#[derive(Default)]
+struct ImportPanel {
+    path: String,
+    columns: String,
+    preview: Option<Arc<ImportPreview>>,
+    busy: bool,
+}
+
+/// State of the waterfall panel: page to load, optional functional selector and the
+/// resource timings of the most recent run.
+#[derive(Default)]
+struct WaterfallPanel {
+    url: String,
+    selector: String,
+    browser: BrowserKind,
+    waterfall: Option<Arc<Waterfall>>,
+    busy: bool,
+    /// Obvious syntax problems, shown while typing.
+    selector_error: Option<String>,
+    /// Result of the last validation against the live page.
+    selector_check: Option<SelectorValidation>,
+    validating: bool,
+}
+
+/// Asks for the reason and optional expiry before a target is paused.
+struct PausePanel {
+    target: SocketAddr,
+    reason: String,
+    /// `2h`, `3d` or an RFC 3339 time; empty pauses until resumed by hand.
+    expiry: String,
+    error: Option<String>,
+}
+
+impl PausePanel {
+    fn pause(&self) -> Result<Pause, String> {
+        let pause = Pause::new(&self.reason)?;
+        match self.expiry.trim() {
+            "" => Ok(pause),
+            expiry => Ok(pause.until(pause::parse_expiry(expiry, chrono::Utc::now())?)),
+        }
+    }
+}
+
+fn pause_reason_id() -> text_input::Id {
+    text_input::Id::new("pause-reason")
+}
+
+struct Palette {
+    query: String,
+    selected: usize,
+}
+
+fn palette_input_id() -> text_input::Id {
+    text_input::Id::new("command-palette")
+}
+
+fn target_list_id() -> scrollable::Id {
+    scrollable::Id::new("target-list")
+}
+
+struct TargetRow {
+    addr: SocketAddr,
+    checking: bool,
+    last: Option<CheckResult>,
+    /// Most recent results, newest last.
+    history: VecDeque<CheckResult>,
+    group: Option<String>,
+    pause: Option<Pause>,
+    /// Down and not acknowledged yet.
+    alerting: bool,
+    /// Overdue for a result (see `staleness`).
+    stale: bool,
+    check: CheckKind,
+    /// Show a desktop notification when this target goes down or recovers.
+    notify: bool,
+}
+
+impl TargetRow {
+    fn new(addr: SocketAddr) -> Self {
+        Self {
+            addr,
+            checking: false,
+            last: None,
+            history: VecDeque::new(),
+            group: None,
+            pause: None,
+            alerting: false,
+            stale: false,
+            check: CheckKind::Tcp,
+            notify: true,
+        }
+    }
+
+    fn status(&self, units: &UnitPreferences) -> String {
+        if self.checking {
+            return "checking...".to_string();
+        }
+        match &self.last {
+            None if self.stale => "STALE: never checked".to_string(),
+            Some(r) if self.stale => format!("STALE: no result since {}", units.time(r.timestamp)),
+            None => "not checked yet".to_string(),
+            Some(r) if r.success => format!("up, {}", units.latency(r.latency_ms().unwrap_or_default())),
+            Some(r) => format!("down: {}", r.error.as_deref().unwrap_or("unknown error")),
+        }
+    }
+
+    /// Status column of the table; the latency has its own.
+    fn state(&self, units: &UnitPreferences) -> String {
+        match &self.last {
+            _ if self.checking => "checking...".to_string(),
+            None if self.stale => "STALE: never checked".to_string(),
+            Some(r) if self.stale => format!("STALE: no result since {}", units.time(r.timestamp)),
+            None => "not checked yet".to_string(),
+            Some(r) if r.success => "up".to_string(),
+            Some(r) => format!("down: {}", r.error.as_deref().unwrap_or("unknown error")),
+        }
+    }
+
+    /// Green when up, red when down, grey when unknown, stale or paused.
+    fn dot(&self) -> Element<'static, Message> {
+        let dot = text("\u{25CF}").width(Length::Fixed(DOT_WIDTH));
+        match &self.last {
+            _ if self.pause.is_some() || self.stale => dot.style(text::secondary).into(),
+            Some(r) if r.success => dot.style(text::success).into(),
+            Some(_) => dot.style(text::danger).into(),
+            None => dot.style(text::secondary).into(),
+        }
+    }
+}
+
+/// GUI client of the monitoring core. Every back-end call is run on the tokio runtime the
+/// monitor lives on; the window only learns about results through the event bus.
+pub struct App {
+    monitor: Arc<Monitor>,
+    runtime: Handle,
+    main_window: window::Id,
+    /// Pop-out detail windows and the target each one shows.
+    popouts: BTreeMap<window::Id, SocketAddr>,
+    /// Where the latency charts get their data from.
+    history: Arc<History>,
+    chart_ranges: HashMap<SocketAddr, TimeRange>,
+    rows: Vec<TargetRow>,
+    /// Position of each target in `rows`, so events don't scan the whole list.
+    index: HashMap<SocketAddr, usize>,
+    /// Only the rows scrolled into view are built.
+    list: VirtualList,
+    form: TargetForm,
+    /// IANA services for the port suggestions; `None` while still loading or if loading failed.
+    services: Option<Arc<Vec<ServiceSuggestion>>>,
+    pending: usize,
+    error: Option<String>,
+    log: VecDeque<String>,
+    palette: Option<Palette>,
+    /// Target the keyboard shortcuts act on.
+    selected: Option<SocketAddr>,
+    /// Only targets of this group are listed.
+    group_filter: Option<String>,
+    /// Rollup status of every service (group), as last announced.
+    rollups: BTreeMap<String, Rollup>,
+    import: Option<ImportPanel>,
+    waterfall: Option<WaterfallPanel>,
+    /// Dependencies between targets and services beyond group membership.
+    dependencies: Arc<DependencyConfig>,
+    /// Names the likely service on each target's port.
+    ports: Arc<PortRegistry>,
+    show_topology: bool,
+    pausing: Option<PausePanel>,
+    /// Target whose delete button was pressed once and now asks for confirmation.
+    confirm_remove: Option<SocketAddr>,
+    /// What whoever sits at this window may do; `Read` hides the controls that change
+    /// targets, e.g. on a wallboard.
+    scope: Scope,
+    /// How latencies and metrics are shown, from the user's settings.
+    units: UnitPreferences,
+    /// WebDriver server the waterfall panel's browser checks go through.
+    webdriver_url: String,
+}
+
+/// Settings of the window from the settings file and flags.
+pub struct GuiSettings {
+    pub units: UnitPreferences,
+    /// WebDriver server for browser checks started from the window.
+    pub webdriver_url: String,
+}
+
+/// Opens the main window and blocks until it is closed. Must be called from a thread
+/// that can block, with `runtime` pointing at the runtime that drives `monitor`.
+pub fn run_gui(
+    monitor: Arc<Monitor>,
+    history: Arc<History>,
+    ports: Arc<PortRegistry>,
+    runtime: Handle,
+    dependencies: DependencyConfig,
+    scope: Scope,
+    settings: GuiSettings,
+) -> iced::Result {
+    // A daemon rather than an application so targets can be popped out into extra windows.
+    iced::daemon(App::title, App::update, App::view)
+        .subscription(App::subscription)
+        .run_with(move || App::new(monitor, history, ports, runtime, Arc::new(dependencies), scope, settings))
+}
+
+impl App {
+    fn new(
+        monitor: Arc<Monitor>,
+        history: Arc<History>,
+        ports: Arc<PortRegistry>,
+        runtime: Handle,
+        dependencies: Arc<DependencyConfig>,
+        scope: Scope,
+        settings: GuiSettings,
+    ) -> (Self, Task<Message>) {
+        let rows: Vec<TargetRow> = monitor
+            .targets()
+            .into_iter()
+            .map(|addr| TargetRow {
+                group: monitor.group(addr),
+                pause: monitor.pause_of(addr),
+                check: monitor.check_kind(addr),
+                ..TargetRow::new(addr)
+            })
+            .collect();
+        let index = rows.iter().enumerate().map(|(i, r)| (r.addr, i)).collect();
+        let rollups = monitor.services().into_iter().map(|s| (s.name, s.rollup)).collect();
+        let (main_window, open_main) = window::open(window::Settings::default());
+        let app = Self {
+            monitor,
+            runtime,
+            main_window,
+            popouts: BTreeMap::new(),
+            history,
+            chart_ranges: HashMap::new(),
+            rows,
+            index,
+            list: VirtualList::new(ROW_HEIGHT),
+            form: TargetForm::default(),
+            services: None,
+            pending: 0,
+            error: None,
+            log: VecDeque::new(),
+            palette: None,
+            selected: None,
+            group_filter: None,
+            rollups,
+            import: None,
+            waterfall: None,
+            dependencies,
+            ports: ports.clone(),
+            show_topology: false,
+            pausing: None,
+            confirm_remove: None,
+            scope,
+            units: settings.units,
+            webdriver_url: settings.webdriver_url,
+        };
+        // The registry has thousands of rows; collect them off the UI thread.
+        let load = app.runtime.spawn_blocking(move || Ok(Arc::new(ports.tcp_suggestions())));
+        let load = Task::perform(load, |joined| {
+            Message::ServicesLoaded(joined.unwrap_or_else(|e| Err(e.to_string())))
+        });
+        (app, Task::batch([open_main.discard(), load]))
+    }
+
+    /// Runs `work` against the monitor on the back-end runtime and reports back with `Done`.
+    fn call<F, Fut>(&mut self, work: F) -> Task<Message>
+    where
+        F: FnOnce(Arc<Monitor>) -> Fut,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.pending += 1;
+        let handle = self.runtime.spawn(work(self.monitor.clone()));
+        Task::perform(handle, |joined| {
+            Message::Done(joined.unwrap_or_else(|e| Err(format!("back-end task failed: {}", e))))
+        })
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        // The controls are hidden, but keyboard shortcuts and the palette still get here.
+        if self.scope < Scope::Admin && message.changes_targets() {
+            self.error = Some("Not permitted: this window is read-only".to_string());
+            return Task::none();
+        }
+        match message {
+            Message::HostChanged(host) => {
+                self.form.set_host(host);
+                Task::none()
+            }
+            Message::PortChanged(port) => {
+                self.form.set_port(port);
+                Task::none()
+            }
+            Message::PickService(port) => {
+                self.form.set_port(port.to_string());
+                Task::none()
+            }
+            Message::CheckKindPicked(kind) => {
+                self.form.check = kind;
+                Task::none()
+            }
+            Message::IntervalChanged(interval) => {
+                self.form.set_interval(interval);
+                Task::none()
+            }
+            Message::EditTarget(addr) => {
+                let target = self.monitor.monitor_target(addr).unwrap_or_else(|| MonitorTarget::new(addr));
+                self.form.edit(&target);
+                Task::none()
+            }
+            Message::CancelEdit => {
+                self.form.clear();
+                Task::none()
+            }
+            Message::RemoveTarget(addr) => {
+                if self.confirm_remove != Some(addr) {
+                    self.confirm_remove = Some(addr);
+                    return Task::none();
+                }
+                self.confirm_remove = None;
+                if self.form.editing == Some(addr) {
+                    self.form.clear();
+                }
+                self.call(move |monitor| async move { monitor.remove_target(addr).map_err(|e| e.to_string()) })
+            }
+            Message::ServicesLoaded(Ok(services)) => {
+                self.services = Some(services);
+                Task::none()
+            }
+            Message::ServicesLoaded(Err(e)) => {
+                self.push_log(format!("port suggestions unavailable: {}", e));
+                Task::none()
+            }
+            Message::AddTarget => {
+                let Some(form) = self.form.validate() else {
+                    return Task::none();
+                };
+                let editing = self.form.editing;
+                self.form.clear();
+                self.call(move |monitor| async move {
+                    let spec = HostSpec {
+                        host: form.host.clone(),
+                        port: form.port,
+                    };
+                    let Some(old) = editing else {
+                        // A new host name brings all of its addresses.
+                        let settings = |addr| MonitorTarget {
+                            check: form.check,
+                            interval: form.interval,
+                            ..MonitorTarget::new(addr)
+                        };
+                        let added = address::add_host(&monitor, &spec, settings).await.map_err(|e| e.to_string())?;
+                        for addr in added {
+                            monitor.run_check(addr).await;
+                        }
+                        return Ok(());
+                    };
+                    let addr = tokio::net::lookup_host((form.host.as_str(), form.port))
+                        .await
+                        .map_err(|e| format!("cannot resolve {}: {}", form.host, e))?
+                        .next()
+                        .ok_or_else(|| format!("{} has no addresses", form.host))?;
+                    let previous = monitor.monitor_target(old).unwrap_or_else(|| MonitorTarget::new(old));
+                    let target = MonitorTarget {
+                        address: addr,
+                        host: (!spec.is_ip()).then(|| spec.host.clone()),
+                        check: form.check,
+                        interval: form.interval,
+                        ..previous.clone()
+                    };
+                    // Same address: only the settings change, history and state stay.
+                    if previous.address == addr {
+                        monitor.configure(target)
+                    } else {
+                        let group = monitor.group(previous.address);
+                        monitor
+                            .add_monitor_target(target)
+                            .and_then(|()| monitor.set_group(addr, group))
+                            .and_then(|()| monitor.remove_target(previous.address))
+                    }
+                    .map_err(|e| e.to_string())?;
+                    monitor.run_check(addr).await;
+                    Ok(())
+                })
+            }
+            Message::RunCheck(addr) => self.call(move |monitor| async move {
+                monitor.run_check(addr).await;
+                Ok(())
+            }),
+            Message::RunAll => self.call(|monitor| async move {
+                monitor.run_all().await;
+                Ok(())
+            }),
+            Message::PopOut(addr) => {
+                if let Some((&id, _)) = self.popouts.iter().find(|(_, a)| **a == addr) {
+                    return window::gain_focus(id);
+                }
+                let (id, open) = window::open(window::Settings {
+                    size: Size::new(640.0, 560.0),
+                    ..window::Settings::default()
+                });
+                self.popouts.insert(id, addr);
+                open.discard()
+            }
+            Message::ChartRange(addr, range) => {
+                self.chart_ranges.insert(addr, range);
+                Task::none()
+            }
+            Message::WindowClosed(id) => {
+                if id == self.main_window {
+                    // Pop-outs are only views of the main window's data.
+                    return iced::exit();
+                }
+                if let Some(addr) = self.popouts.remove(&id) {
+                    self.chart_ranges.remove(&addr);
+                }
+                Task::none()
+            }
+            Message::Done(result) => {
+                self.pending = self.pending.saturating_sub(1);
+                if let Err(e) = result {
+                    self.error = Some(e);
+                }
+                Task::none()
+            }
+            Message::Events(events) => {
+                for event in events {
+                    self.apply(event);
+                }
+                Task::none()
+            }
+            Message::Lagged(missed) => {
+                self.push_log(format!("missed {} events, list may be stale", missed));
+                Task::none()
+            }
+            Message::DismissError => {
+                self.error = None;
+                Task::none()
+            }
+            Message::Key(action) => self.on_key(action),
+            Message::PaletteQuery(query) => {
+                if let Some(palette) = &mut self.palette {
+                    palette.query = query;
+                    palette.selected = 0;
+                }
+                Task::none()
+            }
+            Message::PaletteSubmit => {
+                let Some(palette) = &self.palette else {
+                    return Task::none();
+                };
+                match self.palette_commands().into_iter().nth(palette.selected) {
+                    Some(command) => self.run_command(command),
+                    None => Task::none(),
+                }
+            }
+            Message::RunCommand(command) => self.run_command(command),
+            Message::PauseReasonChanged(reason) => {
+                if let Some(panel) = &mut self.pausing {
+                    panel.reason = reason;
+                    panel.error = None;
+                }
+                Task::none()
+            }
+            Message::PauseExpiryChanged(expiry) => {
+                if let Some(panel) = &mut self.pausing {
+                    panel.expiry = expiry;
+                    panel.error = None;
+                }
+                Task::none()
+            }
+            Message::ConfirmPause => {
+                let Some(panel) = &mut self.pausing else {
+                    return Task::none();
+                };
+                let pause = match panel.pause() {
+                    Ok(pause) => pause,
+                    Err(e) => {
+                        panel.error = Some(e);
+                        return Task::none();
+                    }
+                };
+                let addr = panel.target;
+                self.pausing = None;
+                self.call(move |monitor| async move { monitor.pause(addr, pause).map_err(|e| e.to_string()) })
+            }
+            Message::CancelPause => {
+                self.pausing = None;
+                Task::none()
+            }
+            Message::ToggleNotify(addr) => {
+                if let Some(row) = self.row_mut(addr) {
+                    row.notify = !row.notify;
+                }
+                Task::none()
+            }
+            Message::ToggleImport => {
+                self.import = match self.import.take() {
+                    Some(_) => None,
+                    None => Some(ImportPanel::default()),
+                };
+                Task::none()
+            }
+            Message::ImportPathChanged(path) => {
+                if let Some(import) = &mut self.import {
+                    import.path = path;
+                    import.preview = None;
+                }
+                Task::none()
+            }
+            Message::ImportColumnsChanged(columns) => {
+                if let Some(import) = &mut self.import {
+                    import.columns = columns;
+                    import.preview = None;
+                }
+                Task::none()
+            }
+            Message::PreviewImport => {
+                let Some(import) = &mut self.import else {
+                    return Task::none();
+                };
+                import.busy = true;
+                let (path, columns) = (import.path.trim().to_string(), import.columns.clone());
+                let load = self.runtime.spawn_blocking(move || {
+                    let mapping = ColumnMapping::parse(&columns).map_err(|e| format!("column mapping: {}", e))?;
+                    let file = std::fs::File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+                    csv_import::preview(file, &mapping).map(Arc::new).map_err(|e| e.to_string())
+                });
+                Task::perform(load, |joined| {
+                    Message::ImportPreviewed(joined.unwrap_or_else(|e| Err(e.to_string())))
+                })
+            }
+            Message::ImportPreviewed(result) => {
+                if let Some(import) = &mut self.import {
+                    import.busy = false;
+                    match result {
+                        Ok(preview) => import.preview = Some(preview),
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+                Task::none()
+            }
+            Message::ApplyImport => {
+                let Some(preview) = self.import.as_mut().and_then(|import| {
+                    import.busy = true;
+                    import.preview.clone()
+                }) else {
+                    return Task::none();
+                };
+                let monitor = self.monitor.clone();
+                let apply = self.runtime.spawn(async move { csv_import::apply(&monitor, &preview).await });
+                Task::perform(apply, |joined| Message::ImportApplied(joined.unwrap_or_default()))
+            }
+            Message::ImportApplied(summary) => {
+                self.import = None;
+                self.push_log(format!("imported {} targets", summary.added));
+                for (line, reason) in summary.skipped {
+                    self.push_log(format!("import skipped line {}: {}", line, reason));
+                }
+                Task::none()
+            }
+            Message::ToggleWaterfall => {
+                self.waterfall = match self.waterfall.take() {
+                    Some(_) => None,
+                    None => Some(WaterfallPanel::default()),
+                };
+                Task::none()
+            }
+            Message::WaterfallUrlChanged(url) => {
+                if let Some(panel) = &mut self.waterfall {
+                    panel.url = url;
+                    panel.selector_check = None;
+                }
+                Task::none()
+            }
+            Message::WaterfallSelectorChanged(selector) => {
+                if let Some(panel) = &mut self.waterfall {
+                    panel.selector_error = (!selector.trim().is_empty())
+                        .then(|| selector_check::precheck(&selector).err())
+                        .flatten();
+                    panel.selector = selector;
+                    panel.selector_check = None;
+                }
+                Task::none()
+            }
+            Message::WaterfallBrowserPicked(browser) => {
+                if let Some(panel) = &mut self.waterfall {
+                    panel.browser = browser;
+                }
+                Task::none()
+            }
+            Message::RunWaterfall => {
+                if self.monitor.low_power().is_some() {
+                    self.error = Some("browser checks are suspended in low-power mode".to_string());
+                    return Task::none();
+                }
+                let Some(panel) = self.waterfall.as_mut().filter(|p| !p.busy && !p.url.trim().is_empty()) else {
+                    return Task::none();
+                };
+                panel.busy = true;
+                let (url, browser) = (panel.url.trim().to_string(), panel.browser);
+                let selector = Some(panel.selector.trim().to_string()).filter(|s| !s.is_empty());
+                let webdriver_url = self.webdriver_url.clone();
+                let run = self.runtime.spawn(async move {
+                    ping_test::measure_website_waterfall(&webdriver_url, browser, &url, selector.as_deref(), true)
+                        .await
+                        .map(Arc::new)
+                        .map_err(|e| e.to_string())
+                });
+                Task::perform(run, |joined| {
+                    Message::WaterfallLoaded(joined.unwrap_or_else(|e| Err(e.to_string())))
+                })
+            }
+            Message::ValidateSelector => {
+                let Some(panel) = self
+                    .waterfall
+                    .as_mut()
+                    .filter(|p| !p.validating && !p.url.trim().is_empty() && !p.selector.trim().is_empty())
+                else {
+                    return Task::none();
+                };
+                panel.validating = true;
+                let (url, selector) = (panel.url.trim().to_string(), panel.selector.trim().to_string());
+                let browser = panel.browser;
+                let webdriver_url = self.webdriver_url.clone();
+                let check = self.runtime.spawn(async move {
+                    ping_test::validate_functional_selector(&webdriver_url, browser, &url, &selector, true)
+                        .await
+                        .map_err(|e| e.to_string())
+                });
+                Task::perform(check, |joined| {
+                    Message::SelectorValidated(joined.unwrap_or_else(|e| Err(e.to_string())))
+                })
+            }
+            Message::SelectorValidated(result) => {
+                if let Some(panel) = &mut self.waterfall {
+                    panel.validating = false;
+                    match result {
+                        Ok(validation) => panel.selector_check = Some(validation),
+                        Err(e) => self.error = Some(format!("cannot validate selector: {}{}", e, diagnostics::explain(&e))),
+                    }
+                }
+                Task::none()
+            }
+            Message::WaterfallLoaded(result) => {
+                if let Some(panel) = &mut self.waterfall {
+                    panel.busy = false;
+                    match result {
+                        Ok(waterfall) => panel.waterfall = Some(waterfall),
+                        Err(e) => self.error = Some(format!("{}{}", e, diagnostics::explain(&e))),
+                    }
+                }
+                Task::none()
+            }
+            Message::ToggleTopology => {
+                self.show_topology = !self.show_topology;
+                Task::none()
+            }
+            Message::TargetsScrolled(viewport) => {
+                self.list.offset = viewport.absolute_offset().y;
+                self.list.height = viewport.bounds().height;
+                Task::none()
+            }
+        }
+    }
+
+    fn on_key(&mut self, action: KeyAction) -> Task<Message> {
+        let selected = self.selected;
+        let on_selected = |command: fn(SocketAddr) -> Command| {
+            selected.map_or(Task::none(), |addr| Task::done(Message::RunCommand(command(addr))))
+        };
+        match action {
+            KeyAction::TogglePalette => {
+                if self.palette.take().is_some() {
+                    return Task::none();
+                }
+                self.palette = Some(Palette {
+                    query: String::new(),
+                    selected: 0,
+                });
+                text_input::focus(palette_input_id())
+            }
+            KeyAction::Escape => {
+                if self.palette.take().is_none()
+                    && self.pausing.take().is_none()
+                    && self.confirm_remove.take().is_none()
+                    && self.group_filter.take().is_none()
+                {
+                    self.selected = None;
+                }
+                Task::none()
+            }
+            KeyAction::Up | KeyAction::Down => {
+                let step = if action == KeyAction::Up { -1 } else { 1 };
+                if self.palette.is_some() {
+                    let count = self.palette_commands().len().min(MAX_PALETTE_ENTRIES);
+                    if let Some(palette) = &mut self.palette {
+                        palette.selected = move_index(Some(palette.selected), step, count).unwrap_or(0);
+                    }
+                } else {
+                    let visible: Vec<SocketAddr> = self.visible_rows().map(|r| r.addr).collect();
+                    let current = self.selected.and_then(|addr| visible.iter().position(|a| *a == addr));
+                    self.selected = move_index(current, step, visible.len()).map(|i| visible[i]);
+                    return self.reveal_selected();
+                }
+                Task::none()
+            }
+            KeyAction::Enter | KeyAction::Check => on_selected(Command::Check),
+            KeyAction::Pause => {
+                let paused = selected.and_then(|addr| self.row(addr)).is_some_and(|r| r.pause.is_some());
+                on_selected(if paused { Command::Resume } else { Command::Pause })
+            }
+            KeyAction::Acknowledge => on_selected(Command::Acknowledge),
+            KeyAction::PopOut => on_selected(Command::PopOut),
+        }
+    }
+
+    fn run_command(&mut self, command: Command) -> Task<Message> {
+        self.palette = None;
+        match command {
+            Command::CheckAll => self.update(Message::RunAll),
+            Command::ShowAllTargets => {
+                self.group_filter = None;
+                Task::none()
+            }
+            Command::GoToTarget(addr) => {
+                if self.group_filter.is_some() && !self.visible_rows().any(|r| r.addr == addr) {
+                    self.group_filter = None;
+                }
+                self.selected = Some(addr);
+                self.reveal_selected()
+            }
+            Command::GoToGroup(group) => {
+                self.selected = self.rows.iter().find(|r| r.group.as_ref() == Some(&group)).map(|r| r.addr);
+                self.group_filter = Some(group);
+                Task::none()
+            }
+            Command::Check(addr) => self.update(Message::RunCheck(addr)),
+            Command::PopOut(addr) => self.update(Message::PopOut(addr)),
+            Command::Pause(addr) => {
+                self.pausing = Some(PausePanel {
+                    target: addr,
+                    reason: String::new(),
+                    expiry: String::new(),
+                    error: None,
+                });
+                text_input::focus(pause_reason_id())
+            }
+            Command::Resume(addr) => self.call(move |monitor| async move {
+                monitor.resume(addr).map_err(|e| e.to_string())
+            }),
+            Command::Acknowledge(addr) => self.call(move |monitor| async move {
+                monitor.acknowledge(addr).map_err(|e| e.to_string())
+            }),
+            Command::SetGroup(addr, group) => self.call(move |monitor| async move {
+                monitor.set_group(addr, Some(group)).map_err(|e| e.to_string())
+            }),
+            Command::SetCheckKind(addr, kind) => self.call(move |monitor| async move {
+                monitor.set_check_kind(addr, kind).map_err(|e| e.to_string())
+            }),
+        }
+    }
+
+    fn row(&self, addr: SocketAddr) -> Option<&TargetRow> {
+        self.index.get(&addr).map(|&i| &self.rows[i])
+    }
+
+    fn row_mut(&mut self, addr: SocketAddr) -> Option<&mut TargetRow> {
+        self.index.get(&addr).map(|&i| &mut self.rows[i])
+    }
+
+    /// Row of a result's target; results name targets as text.
+    fn result_row_mut(&mut self, target: &str) -> Option<&mut TargetRow> {
+        self.row_mut(target.parse().ok()?)
+    }
+
+    /// Scrolls the target list so the selected target is in view.
+    fn reveal_selected(&self) -> Task<Message> {
+        let Some(position) = self.selected.and_then(|addr| self.visible_rows().position(|r| r.addr == addr)) else {
+            return Task::none();
+        };
+        match self.list.reveal(position) {
+            Some(y) => scrollable::scroll_to(target_list_id(), scrollable::AbsoluteOffset { x: 0.0, y }),
+            None => Task::none(),
+        }
+    }
+
+    fn visible_rows(&self) -> impl Iterator<Item = &TargetRow> {
+        self.rows
+            .iter()
+            .filter(|r| self.group_filter.is_none() || r.group == self.group_filter)
+    }
+
+    /// The topology as the window currently knows it, built from the rows and rollups so
+    /// it follows the event bus like everything else.
+    fn topology(&self) -> Topology {
+        let mut statuses: Vec<(NodeId, Rollup)> = self
+            .rows
+            .iter()
+            .map(|r| {
+                let status = match &r.last {
+                    None => Rollup::Unknown,
+                    Some(result) if result.success => Rollup::Up,
+                    Some(_) => Rollup::Down,
+                };
+                (NodeId::Target(r.addr), status)
+            })
+            .collect();
+        statuses.extend(self.rollups.iter().map(|(name, rollup)| (NodeId::Service(name.clone()), *rollup)));
+        let mut edges: Vec<(NodeId, NodeId)> = self
+            .rows
+            .iter()
+            .filter_map(|r| Some((NodeId::Service(r.group.clone()?), NodeId::Target(r.addr))))
+            .collect();
+        edges.extend(self.dependencies.edges());
+        Topology::new(statuses, edges)
+    }
+
+    fn palette_commands(&self) -> Vec<Command> {
+        let Some(palette) = &self.palette else {
+            return Vec::new();
+        };
+        let targets: Vec<PaletteTarget> = self
+            .rows
+            .iter()
+            .map(|r| PaletteTarget {
+                addr: r.addr,
+                group: r.group.clone(),
+                paused: r.pause.is_some(),
+                alerting: r.alerting,
+                check: r.check,
+            })
+            .collect();
+        let mut commands = command_palette::commands(&targets, self.selected, &palette.query);
+        if self.scope < Scope::Admin {
+            commands.retain(|command| !command.changes_targets());
+        }
+        commands
+    }
+
+    fn apply(&mut self, event: MonitorEvent) {
+        match event {
+            MonitorEvent::TargetAdded(addr) => {
+                if !self.index.contains_key(&addr) {
+                    self.index.insert(addr, self.rows.len());
+                    self.rows.push(TargetRow::new(addr));
+                }
+            }
+            MonitorEvent::TargetRemoved(addr) => {
+                if let Some(removed) = self.index.remove(&addr) {
+                    self.rows.remove(removed);
+                    for (i, row) in self.rows.iter().enumerate().skip(removed) {
+                        self.index.insert(row.addr, i);
+                    }
+                }
+            }
+            MonitorEvent::TargetPaused { target, pause } => {
+                if let Some(row) = self.row_mut(target) {
+                    row.pause = pause;
+                }
+            }
+            MonitorEvent::TargetGrouped { target, group } => {
+                if let Some(row) = self.row_mut(target) {
+                    row.group = group;
+                }
+            }
+            MonitorEvent::CheckKindChanged { target, kind } => {
+                if let Some(row) = self.row_mut(target) {
+                    row.check = kind;
+                }
+            }
+            MonitorEvent::TargetConfigured(target) => {
+                if let Some(row) = self.row_mut(target.address) {
+                    row.check = target.check;
+                }
+            }
+            MonitorEvent::AlertAcknowledged(addr) => {
+                if let Some(row) = self.row_mut(addr) {
+                    row.alerting = false;
+                }
+                self.push_log(format!("alert on {} acknowledged", addr));
+            }
+            MonitorEvent::CheckStarted(addr) => {
+                if let Some(row) = self.row_mut(addr) {
+                    row.checking = true;
+                }
+            }
+            MonitorEvent::CheckCompleted(result) => {
+                if let Some(row) = self.result_row_mut(&result.target) {
+                    row.checking = false;
+                    row.history.push_back(result.clone());
+                    if row.history.len() > MAX_HISTORY {
+                        row.history.pop_front();
+                    }
+                    row.last = Some(result);
+                }
+            }
+            MonitorEvent::Transition(transition) => {
+                let mut notify = false;
+                if let Some(row) = self.result_row_mut(&transition.target) {
+                    row.alerting = transition.to == TargetState::Down;
+                    notify = row.notify;
+                }
+                if notify && desktop_notify::AVAILABLE {
+                    let transition = transition.clone();
+                    self.runtime.spawn_blocking(move || {
+                        if let Err(e) = desktop_notify::show(&transition) {
+                            eprintln!("Cannot show desktop notification: {}", e);
+                        }
+                    });
+                }
+                let state = match transition.to {
+                    TargetState::Up => "UP",
+                    TargetState::Down => "DOWN",
+                };
+                self.push_log(format!(
+                    "{} {} is {}",
+                    self.units.time(transition.at),
+                    transition.target,
+                    state
+                ));
+            }
+            MonitorEvent::ServiceChanged(change) => {
+                self.rollups.insert(change.service.clone(), change.to);
+                self.push_log(format!(
+                    "{} service {} is {} (was {})",
+                    self.units.time(change.at),
+                    change.service,
+                    change.to,
+                    change.from
+                ));
+            }
+            MonitorEvent::ResolutionChanged(change) => {
+                self.push_log(format!("{} {}", self.units.time(change.at), change));
+            }
+            MonitorEvent::HaRoleChanged { node, role, at } => {
+                let role = match role {
+                    Role::Active => "active",
+                    Role::Standby => "standby",
+                };
+                self.push_log(format!("{} node {} is now {}", self.units.time(at), node, role));
+            }
+            MonitorEvent::CaptureSaved { target, path, packets, correlation_id } => {
+                self.push_log(format!(
+                    "{} captured {} packets of {} to {} [{}]",
+                    self.units.time(chrono::Utc::now()),
+                    packets,
+                    target,
+                    path.display(),
+                    correlation_id
+                ));
+            }
+            MonitorEvent::StaleChanged(change) => {
+                if let Some(row) = self.row_mut(change.target) {
+                    row.stale = change.stale;
+                }
+                self.push_log(format!("{} {}", self.units.time(change.at), change));
+            }
+            MonitorEvent::ConnectivityChanged(change) => {
+                self.push_log(format!("{} {}", self.units.time(change.at), change));
+            }
+            MonitorEvent::StormSummary(summary) => {
+                self.push_log(format!("{} {}", self.units.time(summary.at), summary));
+            }
+            MonitorEvent::LowPowerChanged(on) => {
+                let mode = if on { "on: longer intervals, no browser checks" } else { "off" };
+                self.push_log(format!("{} low-power mode {}", self.units.time(chrono::Utc::now()), mode));
+            }
+            MonitorEvent::ShuttingDown => {
+                self.push_log(format!("{} monitor shutting down", self.units.time(chrono::Utc::now())));
+            }
+        }
+    }
+
+    fn push_log(&mut self, line: String) {
+        self.log.push_front(line);
+        self.log.truncate(MAX_LOG_LINES);
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        // iced keeps the stream with this id alive across calls, so subscribing again
+        // here is cheap; the extra receivers are dropped unused.
+        Subscription::batch([
+            Subscription::run_with_id("monitor-events", events(self.monitor.clone())),
+            window::close_events().map(Message::WindowClosed),
+            event::listen_with(key_action),
+        ])
+    }
+
+    fn title(&self, window: window::Id) -> String {
+        match self.popouts.get(&window) {
+            Some(addr) => format!("{} - Rust NPM", addr),
+            None if self.scope < Scope::Admin => "Rust NPM (read-only)".to_string(),
+            None => "Rust NPM".to_string(),
+        }
+    }
+
+    fn view(&self, window: window::Id) -> Element<'_, Message> {
+        match self.popouts.get(&window) {
+            Some(addr) => self.detail_view(*addr),
+            None => self.main_view(),
+        }
+    }
+
+    /// Status, latency chart and recent checks of one target, shown in its pop-out window.
+    fn detail_view(&self, addr: SocketAddr) -> Element<'_, Message> {
+        let Some(row) = self.row(addr) else {
+            return container(text(format!("{} is no longer monitored", addr))).padding(20).into();
+        };
+
+        let slowest = row
+            .history
+            .iter()
+            .filter_map(CheckResult::latency_ms)
+            .fold(0.0_f64, f64::max)
+            .max(1.0);
+        let range = self.chart_ranges.get(&addr).copied().unwrap_or_default();
+        let now = chrono::Utc::now();
+        let series = self.history.latency_series(&addr.to_string(), now - range.duration());
+        let latency_chart = LatencyChart::new(&series, range, now).with_units(self.units.clone());
+        let ranges = Row::with_children(TimeRange::ALL.iter().map(|&choice| {
+            button(text(choice.to_string()))
+                .style(if choice == range { button::primary } else { button::secondary })
+                .on_press(Message::ChartRange(addr, choice))
+                .into()
+        }))
+        .spacing(5);
+        let latency_chart: Element<'_, Message> = if latency_chart.is_empty() {
+            container(text(format!("No checks in the {}", range.to_string().to_lowercase())).style(text::secondary))
+                .height(Length::Fixed(LATENCY_CHART_HEIGHT))
+                .center_y(Length::Fixed(LATENCY_CHART_HEIGHT))
+                .into()
+        } else {
+            canvas(latency_chart).width(Length::Fill).height(Length::Fixed(LATENCY_CHART_HEIGHT)).into()
+        };
+
+        // Recent results as text bars, newest on top.
+        let chart = Column::with_children(row.history.iter().rev().map(|result| {
+            let time = self.units.time(result.timestamp);
+            let line = match result.latency_ms() {
+                Some(ms) if result.success => {
+                    let width = ((ms / slowest) * CHART_WIDTH as f64).ceil() as usize;
+                    format!("{}  {:<width$}  {}", time, "#".repeat(width.max(1)), self.units.latency(ms), width = CHART_WIDTH)
+                }
+                _ => format!("{}  DOWN {}", time, result.error.as_deref().unwrap_or_default()),
+            };
+            let line = text(line).size(12).font(iced::Font::MONOSPACE);
+            if result.success { line.into() } else { line.style(text::danger).into() }
+        }));
+
+        let title = match self.service(row) {
+            Some(service) => format!("{} ({})", addr, service),
+            None => addr.to_string(),
+        };
+        let content = column![
+            text(title).size(24),
+            row![
+                text(row.status(&self.units)).width(Length::Fill),
+                button("Check").on_press_maybe((!row.checking).then_some(Message::RunCheck(addr))),
+            ]
+            .spacing(10),
+        ]
+        // Setup problems on our side, e.g. no WebDriver, come with what to check.
+        .push_maybe(row.last.as_ref().and_then(|r| r.hint).map(|hint| text(hint.to_string()).style(text::secondary)))
+        .push_maybe(row.last.as_ref().filter(|r| !r.metrics.is_empty()).map(|r| {
+            let metrics: Vec<String> = r.metrics.iter().map(|(name, value)| format!("{} {}", name, self.units.metric(name, *value))).collect();
+            text(metrics.join(", ")).size(12).style(text::secondary)
+        }))
+        .push(ranges)
+        .push(latency_chart)
+        .push(scrollable(chart).height(Length::Fill))
+        .spacing(15);
+        container(content).padding(20).into()
+    }
+
+    fn main_view(&self) -> Element<'_, Message> {
+        let busy = self.pending > 0;
+
+        let host_field = column![
+            text_input("IP address or host name", &self.form.host)
+                .on_input(Message::HostChanged)
+                .on_submit(Message::AddTarget),
+        ]
+        .push_maybe(self.form.host_error.as_deref().map(inline_error))
+        .width(Length::FillPortion(3));
+
+        let mut port_field = column![
+            text_input("Port or service", &self.form.port)
+                .on_input(Message::PortChanged)
+                .on_submit(Message::AddTarget),
+        ]
+        .push_maybe(self.form.port_error.as_deref().map(inline_error))
+        .width(Length::FillPortion(2));
+        if let Some(services) = &self.services {
+            let already_picked = target_form::validate_port(&self.form.port).is_ok()
+                && services.iter().any(|s| s.port.to_string() == self.form.port.trim());
+            if !already_picked {
+                port_field = port_field.extend(target_form::suggest(services, &self.form.port, MAX_SUGGESTIONS).into_iter().map(|s| {
+                    button(text(format!("{}/tcp {} - {}", s.port, s.service, s.description)).size(12))
+                        .style(button::text)
+                        .on_press(Message::PickService(s.port))
+                        .into()
+                }));
+            }
+        }
+
+        let interval_field = column![
+            text_input("Interval, e.g. 30s", &self.form.interval)
+                .on_input(Message::IntervalChanged)
+                .on_submit(Message::AddTarget),
+        ]
+        .push_maybe(self.form.interval_error.as_deref().map(inline_error))
+        .width(Length::FillPortion(1));
+
+        let admin = self.scope == Scope::Admin;
+        let tools = row![]
+            .push_maybe(admin.then(|| button("Import CSV").on_press(Message::ToggleImport)))
+            .push(button("Waterfall").on_press(Message::ToggleWaterfall))
+            .push(button("Topology").on_press(Message::ToggleTopology))
+            .push(
+                button(if busy { "Checking..." } else { "Check all" })
+                    .on_press_maybe((!busy && !self.rows.is_empty()).then_some(Message::RunAll)),
+            )
+            .spacing(10);
+        // Viewers get the tools but not the form.
+        let add: Element<'_, Message> = if admin {
+            row![
+                host_field,
+                port_field,
+                // Checks that need settings are added with `add --spec` or the API.
+                pick_list(
+                    CheckKind::ALL.into_iter().filter(|kind| !kind.needs_spec()).collect::<Vec<_>>(),
+                    Some(self.form.check),
+                    Message::CheckKindPicked
+                ),
+                interval_field,
+                button(if self.form.editing.is_some() { "Save" } else { "Add" }).on_press(Message::AddTarget),
+            ]
+            .push_maybe(
+                self.form
+                    .editing
+                    .map(|_| button("Cancel").style(button::secondary).on_press(Message::CancelEdit)),
+            )
+            .push(tools)
+            .spacing(10)
+            .into()
+        } else {
+            tools.into()
+        };
+
+        let mut content = column![].spacing(15);
+        if let Some(palette) = &self.palette {
+            content = content.push(self.palette_view(palette));
+        }
+        let heading = match &self.group_filter {
+            Some(group) => format!(
+                "Service {}: {} (Esc shows all targets)",
+                group,
+                self.rollups.get(group).copied().unwrap_or(Rollup::Unknown)
+            ),
+            None => "Monitored targets".to_string(),
+        };
+        content = content.push(text(heading).size(24)).push(add).push(text(KEY_HELP).size(12));
+        if self.group_filter.is_none() && !self.rollups.is_empty() {
+            let services = Column::with_children(self.rollups.iter().map(|(name, rollup)| {
+                let line = text(format!("{}: {}", name, rollup));
+                match rollup {
+                    Rollup::Down | Rollup::Degraded => line.style(text::danger).into(),
+                    _ => line.into(),
+                }
+            }));
+            content = content.push(services);
+        }
+        if let Some(panel) = &self.pausing {
+            content = content.push(pause_view(panel));
+        }
+        if let Some(import) = &self.import {
+            content = content.push(import_view(import));
+        }
+        if let Some(panel) = &self.waterfall {
+            content = content.push(waterfall_view(panel, &self.units));
+        }
+        if self.show_topology {
+            content = content.push(topology_view(self.topology()));
+        }
+
+        if let Some(error) = &self.error {
+            content = content.push(
+                row![text(error), button("Dismiss").on_press(Message::DismissError)]
+                    .spacing(10),
+            );
+        }
+
+        let visible: Vec<&TargetRow> = self.visible_rows().collect();
+        let window = self.list.window(visible.len());
+        let (above, below) = self.list.padding(&window, visible.len());
+        let header = row![
+            Space::with_width(DOT_WIDTH),
+            text("Target").width(Length::FillPortion(2)),
+            text("Last check").width(Length::Fixed(TIME_WIDTH)),
+            text("Latency").width(Length::Fixed(LATENCY_WIDTH)),
+            text("Service").width(Length::FillPortion(1)),
+            text("Status").width(Length::FillPortion(3)),
+        ]
+        .spacing(10);
+        let targets = column![Space::with_height(above)]
+            .extend(visible[window].iter().map(|row| self.target_row(row)))
+            .push(Space::with_height(below));
+
+        let log = Column::with_children(self.log.iter().map(|line| text(line.clone()).into()));
+
+        content = content
+            .push(header)
+            .push(
+                scrollable(targets)
+                    .id(target_list_id())
+                    .on_scroll(Message::TargetsScrolled)
+                    .height(Length::FillPortion(3)),
+            )
+            .push(text("Events").size(18))
+            .push(scrollable(log).height(Length::FillPortion(1)));
+
+        container(content).padding(20).into()
+    }
+}
+
+impl App {
+    /// The registered service on the row's port, e.g. `443/tcp https`; none for ping checks.
+    fn service(&self, row: &TargetRow) -> Option<String> {
+        self.ports.label(row.addr.port(), row.check.protocol()?)
+    }
+
+    /// One line of the target list, always `ROW_HEIGHT` high.
+    fn target_row(&self, row: &TargetRow) -> Element<'_, Message> {
+        let mut name = row.addr.to_string();
+        if self.selected == Some(row.addr) {
+            name = format!("> {}", name);
+        }
+        if let Some(group) = &row.group {
+            name = format!("{} [{}]", name, group);
+        }
+        match row.check {
+            CheckKind::Tcp => {}
+            CheckKind::Icmp => name = format!("{} (ping)", name),
+            CheckKind::Udp => name = format!("{} (udp)", name),
+            CheckKind::Http => name = format!("{} (http)", name),
+            CheckKind::Browser => name = format!("{} (browser)", name),
+            CheckKind::Windows => name = format!("{} (windows)", name),
+            CheckKind::Systemd => name = format!("{} (systemd)", name),
+            CheckKind::Disk => name = format!("{} (disk)", name),
+            CheckKind::S3 => name = format!("{} (s3)", name),
+            CheckKind::ApiQuota => name = format!("{} (quota)", name),
+            CheckKind::Composite => name = format!("{} (journey)", name),
+            CheckKind::Plugin => name = format!("{} (plugin)", name),
+        }
+        let mut status = row.state(&self.units);
+        if let Some(pause) = &row.pause {
+            status = format!("{} ({})", status, pause);
+        }
+        if row.last.as_ref().is_some_and(|r| !r.is_reliable()) {
+            status = format!("{} (clock jumped during check, timing unreliable)", status);
+        }
+        let status = text(status).width(Length::FillPortion(3));
+        let checked = row.last.as_ref().map_or("-".to_string(), |r| self.units.time(r.timestamp));
+        let latency = match row.last.as_ref().and_then(CheckResult::latency_ms) {
+            Some(ms) => self.units.latency(ms),
+            None => "-".to_string(),
+        };
+        container(
+            row![
+                row.dot(),
+                text(name).width(Length::FillPortion(2)),
+                text(checked).width(Length::Fixed(TIME_WIDTH)),
+                text(latency).width(Length::Fixed(LATENCY_WIDTH)),
+                text(self.service(row).unwrap_or_else(|| "-".to_string())).width(Length::FillPortion(1)),
+                if row.alerting { status.style(text::danger) } else { status },
+            ]
+            .push_maybe(desktop_notify::AVAILABLE.then(|| {
+                button(if row.notify { "Notify: on" } else { "Notify: off" })
+                    .style(if row.notify { button::secondary } else { button::text })
+                    .on_press(Message::ToggleNotify(row.addr))
+            }))
+            .push(button("Check").on_press_maybe((!row.checking).then_some(Message::RunCheck(row.addr))))
+            .push(button("Pop out").on_press(Message::PopOut(row.addr)))
+            .push_maybe((self.scope == Scope::Admin).then(|| {
+                row![
+                    button("Edit").style(button::secondary).on_press(Message::EditTarget(row.addr)),
+                    button(if self.confirm_remove == Some(row.addr) { "Really delete?" } else { "Delete" })
+                        .style(button::danger)
+                        .on_press(Message::RemoveTarget(row.addr)),
+                ]
+                .spacing(10)
+            }))
+            .spacing(10)
+            .align_y(iced::alignment::Vertical::Center),
+        )
+        .height(Length::Fixed(ROW_HEIGHT))
+        .into()
+    }
+
+    fn palette_view(&self, palette: &Palette) -> Element<'_, Message> {
+        let entries = Column::with_children(
+            self.palette_commands()
+                .into_iter()
+                .take(MAX_PALETTE_ENTRIES)
+                .enumerate()
+                .map(|(i, command)| {
+                    button(text(command.label()))
+                        .width(Length::Fill)
+                        .style(if i == palette.selected { button::primary } else { button::text })
+                        .on_press(Message::RunCommand(command))
+                        .into()
+                }),
+        );
+        container(
+            column![
+                text_input("Type a command, target or group...", &palette.query)
+                    .id(palette_input_id())
+                    .on_input(Message::PaletteQuery)
+                    .on_submit(Message::PaletteSubmit),
+                entries,
+            ]
+            .spacing(5),
+        )
+        .padding(10)
+        .style(container::rounded_box)
+        .into()
+    }
+}
+
+fn pause_view(panel: &PausePanel) -> Element<'_, Message> {
+    let content = column![
+        text(format!("Pause {}", panel.target)),
+        row![
+            text_input("Reason (required)", &panel.reason)
+                .id(pause_reason_id())
+                .on_input(Message::PauseReasonChanged)
+                .on_submit(Message::ConfirmPause)
+                .width(Length::FillPortion(3)),
+            text_input("Expires in/at, e.g. 3d (optional)", &panel.expiry)
+                .on_input(Message::PauseExpiryChanged)
+                .on_submit(Message::ConfirmPause)
+                .width(Length::FillPortion(2)),
+            button("Pause").on_press(Message::ConfirmPause),
+            button("Cancel").style(button::secondary).on_press(Message::CancelPause),
+        ]
+        .spacing(10),
+    ]
+    .spacing(5)
+    .push_maybe(panel.error.as_deref().map(inline_error));
+    container(content).padding(10).style(container::rounded_box).into()
+}
+
+fn import_view(import: &ImportPanel) -> Element<'_, Message> {
+    let ready = import.preview.as_ref().is_some_and(|p| !p.targets.is_empty());
+    let mut panel = column![
+        row![
+            text_input("/path/to/inventory.csv", &import.path)
+                .on_input(Message::ImportPathChanged)
+                .on_submit(Message::PreviewImport),
+            button("Preview").on_press_maybe((!import.busy).then_some(Message::PreviewImport)),
+            button("Import").on_press_maybe((ready && !import.busy).then_some(Message::ApplyImport)),
+        ]
+        .spacing(10),
+        text_input("Column mapping, e.g. host=Device Name,port=Port,tags=Labels", &import.columns)
+            .on_input(Message::ImportColumnsChanged),
+    ]
+    .spacing(5);
+    if let Some(preview) = &import.preview {
+        panel = panel.push(
+            scrollable(text(preview.to_string()).size(12).font(iced::Font::MONOSPACE)).height(Length::Fixed(160.0)),
+        );
+    }
+    container(panel).padding(10).style(container::rounded_box).into()
+}
+
+/// Resource timings of the last browser run as text bars on a shared time axis, with the
+/// slowest resources that finished before the page became functional listed first.
+fn waterfall_view<'a>(panel: &'a WaterfallPanel, units: &UnitPreferences) -> Element<'a, Message> {
+    let mut content = column![
+        row![
+            text_input("https://www.example.com", &panel.url)
+                .on_input(Message::WaterfallUrlChanged)
+                .on_submit(Message::RunWaterfall),
+            text_input("Functional selector (optional)", &panel.selector)
+                .on_input(Message::WaterfallSelectorChanged)
+                .on_submit(Message::ValidateSelector),
+            pick_list(BrowserKind::ALL, Some(panel.browser), Message::WaterfallBrowserPicked),
+            button(if panel.validating { "Checking..." } else { "Check selector" })
+                .on_press_maybe(
+                    (!panel.validating && !panel.selector.trim().is_empty()).then_some(Message::ValidateSelector),
+                ),
+            button(if panel.busy { "Loading..." } else { "Run" })
+                .on_press_maybe((!panel.busy).then_some(Message::RunWaterfall)),
+        ]
+        .spacing(10),
+    ]
+    .spacing(5);
+    if let Some(error) = &panel.selector_error {
+        content = content.push(inline_error(error));
+    } else if let Some(check) = &panel.selector_check {
+        let line = text(check.to_string());
+        content = content.push(if check.is_ok() { line } else { line.style(text::danger) });
+    }
+    if let Some(waterfall) = &panel.waterfall {
+        let slowest = waterfall
+            .slowest_blocking(3)
+            .iter()
+            .map(|e| format!("{} ({})", e.host(), units.latency(e.duration_ms)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let summary = match waterfall.functional_ms {
+            Some(ms) => format!(
+                "{} resources, functional after {}. Slowest before that: {}",
+                waterfall.entries.len(),
+                units.latency(ms),
+                slowest
+            ),
+            None => format!("{} resources, page never became functional", waterfall.entries.len()),
+        };
+        let rows = waterfall.text_rows(waterfall::TEXT_WIDTH).join("\n");
+        content = content.push(text(summary)).push(
+            scrollable(text(rows).size(12).font(iced::Font::MONOSPACE)).height(Length::Fixed(240.0)),
+        );
+    }
+    container(content).padding(10).style(container::rounded_box).into()
+}
+
+/// The dependency graph, dependents on top: one line of nodes per layer, colored by
+/// status. Nodes that depend on something down are marked as impacted, and every down
+/// node lists its blast radius. Targets pop out, services filter the list.
+fn topology_view(topology: Topology) -> Element<'static, Message> {
+    if topology.nodes.is_empty() {
+        return container(text("No targets yet")).padding(10).style(container::rounded_box).into();
+    }
+    let mut content = column![].spacing(8);
+    for layer in topology.rows() {
+        content = content.push(row(layer.into_iter().map(|node| {
+            let mut label = format!("{} {}", node.id, node.status);
+            if node.affected {
+                label = format!("{} (impacted)", label);
+            }
+            let style = match node.status {
+                Rollup::Down | Rollup::Degraded => button::danger,
+                _ if node.affected => button::danger,
+                Rollup::Up => button::success,
+                Rollup::Unknown => button::secondary,
+            };
+            let press = match &node.id {
+                NodeId::Target(addr) => Message::PopOut(*addr),
+                NodeId::Service(name) => Message::RunCommand(Command::GoToGroup(name.clone())),
+            };
+            button(text(label).size(12)).style(style).on_press(press).into()
+        })).spacing(10));
+    }
+    for node in topology.nodes.iter().filter(|n| n.status == Rollup::Down) {
+        let radius = topology.blast_radius(&node.id);
+        if !radius.is_empty() {
+            let names: Vec<String> = radius.iter().map(NodeId::to_string).collect();
+            content = content.push(text(format!("{} is down, impacting {}", node.id, names.join(", "))).style(text::danger));
+        }
+    }
+    container(scrollable(content).height(Length::Fixed(240.0))).padding(10).style(container::rounded_box).into()
+}
+
+/// Moves `current` by `step` within `0..len`, wrapping around; starts at the first or last
+/// entry when nothing is selected yet.
+fn move_index(current: Option<usize>, step: isize, len: usize) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    Some(match current {
+        Some(i) => (i as isize + step).rem_euclid(len as isize) as usize,
+        None if step < 0 => len - 1,
+        None => 0,
+    })
+}
+
+fn key_action(event: iced::Event, status: event::Status, _window: window::Id) -> Option<Message> {
+    let iced::Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) = event else {
+        return None;
+    };
+    // Text fields swallow letters and Enter; Escape, arrows and Ctrl+K work everywhere.
+    let free = status == event::Status::Ignored && !modifiers.command() && !modifiers.alt();
+    let action = match key.as_ref() {
+        Key::Character("k") if modifiers.command() => KeyAction::TogglePalette,
+        Key::Named(Named::Escape) => KeyAction::Escape,
+        Key::Named(Named::ArrowUp) => KeyAction::Up,
+        Key::Named(Named::ArrowDown) => KeyAction::Down,
+        Key::Named(Named::Enter) if free => KeyAction::Enter,
+        Key::Character("c") if free => KeyAction::Check,
+        Key::Character("p") if free => KeyAction::Pause,
+        Key::Character("a") if free => KeyAction::Acknowledge,
+        Key::Character("o") if free => KeyAction::PopOut,
+        _ => return None,
+    };
+    Some(Message::Key(action))
+}
+
+fn inline_error(message: &str) -> Element<'_, Message> {
+    text(message).size(12).style(text::danger).into()
+}
+
+/// Monitor events in batches: waits for one, then takes whatever else is already queued.
+fn events(monitor: Arc<Monitor>) -> impl Stream<Item = Message> {
+    // A lag found while draining is reported after the batch.
+    stream::unfold((monitor.bus().subscribe(), None), |(mut receiver, lagged)| async move {
+        if let Some(missed) = lagged {
+            return Some((Message::Lagged(missed), (receiver, None)));
+        }
+        let first = match receiver.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => return Some((Message::Lagged(missed), (receiver, None))),
+            Err(RecvError::Closed) => return None,
+        };
+        let mut batch = vec![first];
+        let mut lagged = None;
+        while batch.len() < MAX_EVENT_BATCH {
+            match receiver.try_recv() {
+                Ok(event) => batch.push(event),
+                Err(TryRecvError::Lagged(missed)) => {
+                    lagged = Some(missed);
+                    break;
+                }
+                Err(_) => break,
+            }
+        }
+        Some((Message::Events(batch), (receiver, lagged)))
+    })
+}
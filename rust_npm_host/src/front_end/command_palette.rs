@@ -0,0 +1,168 @@
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+
+use crate::back_end::monitor::CheckKind;
+
+/// Something the user can do from the command palette.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    CheckAll,
+    ShowAllTargets,
+    GoToTarget(SocketAddr),
+    GoToGroup(String),
+    Check(SocketAddr),
+    Pause(SocketAddr),
+    Resume(SocketAddr),
+    Acknowledge(SocketAddr),
+    PopOut(SocketAddr),
+    SetGroup(SocketAddr, String),
+    SetCheckKind(SocketAddr, CheckKind),
+}
+
+impl Command {
+    /// Whether the command changes a target, and so needs admin rights.
+    pub fn changes_targets(&self) -> bool {
+        matches!(
+            self,
+            Command::Pause(_) | Command::Resume(_) | Command::Acknowledge(_) | Command::SetGroup(..) | Command::SetCheckKind(..)
+        )
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            Command::CheckAll => "Check all targets".to_string(),
+            Command::ShowAllTargets => "Show all targets".to_string(),
+            Command::GoToTarget(addr) => format!("Go to {}", addr),
+            Command::GoToGroup(group) => format!("Go to group {}", group),
+            Command::Check(addr) => format!("Check {} now", addr),
+            Command::Pause(addr) => format!("Pause {}", addr),
+            Command::Resume(addr) => format!("Resume {}", addr),
+            Command::Acknowledge(addr) => format!("Acknowledge alert on {}", addr),
+            Command::PopOut(addr) => format!("Pop out {}", addr),
+            Command::SetGroup(addr, group) => format!("Move {} to group {}", addr, group),
+            Command::SetCheckKind(addr, kind) => format!("Check {} by {}", addr, kind),
+        }
+    }
+}
+
+/// What the palette needs to know about a target to offer commands for it.
+#[derive(Debug, Clone)]
+pub struct PaletteTarget {
+    pub addr: SocketAddr,
+    pub group: Option<String>,
+    pub paused: bool,
+    /// Down and not acknowledged yet.
+    pub alerting: bool,
+    pub check: CheckKind,
+}
+
+/// Every word of the query has to appear in the label, in any order, ignoring case.
+pub fn matches(label: &str, query: &str) -> bool {
+    let label = label.to_lowercase();
+    query.to_lowercase().split_whitespace().all(|word| label.contains(word))
+}
+
+/// Lists the commands matching `query`. Commands for the selected target come first, and
+/// typing "group <name>" offers to move the selected target into that group.
+pub fn commands(targets: &[PaletteTarget], selected: Option<SocketAddr>, query: &str) -> Vec<Command> {
+    let mut all = Vec::new();
+
+    if let Some(target) = selected.and_then(|addr| targets.iter().find(|t| t.addr == addr)) {
+        all.extend(target_commands(target));
+        if let Some(group) = query.trim().strip_prefix("group ").map(str::trim).filter(|g| !g.is_empty())
+            && target.group.as_deref() != Some(group)
+        {
+            all.push(Command::SetGroup(target.addr, group.to_string()));
+        }
+    }
+    all.push(Command::CheckAll);
+    all.push(Command::ShowAllTargets);
+    let groups: BTreeSet<&str> = targets.iter().filter_map(|t| t.group.as_deref()).collect();
+    all.extend(groups.into_iter().map(|g| Command::GoToGroup(g.to_string())));
+    for target in targets {
+        all.push(Command::GoToTarget(target.addr));
+        if Some(target.addr) != selected {
+            all.extend(target_commands(target));
+        }
+    }
+
+    all.retain(|command| matches(&command.label(), query));
+    all
+}
+
+fn target_commands(target: &PaletteTarget) -> Vec<Command> {
+    let mut commands = vec![Command::Check(target.addr)];
+    if target.alerting {
+        commands.push(Command::Acknowledge(target.addr));
+    }
+    commands.push(if target.paused {
+        Command::Resume(target.addr)
+    } else {
+        Command::Pause(target.addr)
+    });
+    commands.push(Command::PopOut(target.addr));
+    for kind in CheckKind::ALL {
+        // Checks that need settings can't be switched to without them.
+        if kind != target.check && !kind.needs_spec() {
+            commands.push(Command::SetCheckKind(target.addr, kind));
+        }
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets() -> Vec<PaletteTarget> {
+        vec![
+            PaletteTarget {
+                addr: "10.0.0.1:443".parse().unwrap(),
+                group: Some("web".to_string()),
+                paused: false,
+                alerting: true,
+                check: CheckKind::Tcp,
+            },
+            PaletteTarget {
+                addr: "10.0.0.2:5432".parse().unwrap(),
+                group: Some("db".to_string()),
+                paused: true,
+                alerting: false,
+                check: CheckKind::Icmp,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_viewers_keep_only_commands_that_change_nothing() {
+        let addr: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        let viewer: Vec<Command> = commands(&targets(), Some(addr), "").into_iter().filter(|c| !c.changes_targets()).collect();
+        assert!(viewer.contains(&Command::Check(addr)) && viewer.contains(&Command::PopOut(addr)));
+        assert!(!viewer.iter().any(|c| matches!(c, Command::Acknowledge(_) | Command::Pause(_) | Command::SetCheckKind(..))));
+    }
+
+    #[test]
+    fn test_query_words_match_in_any_order() {
+        assert!(matches("Acknowledge alert on 10.0.0.1:443", "10.0.0.1 ack"));
+        assert!(!matches("Pause 10.0.0.1:443", "resume"));
+    }
+
+    #[test]
+    fn test_commands_reflect_target_state() {
+        let found = commands(&targets(), None, "5432");
+        assert!(found.contains(&Command::Resume("10.0.0.2:5432".parse().unwrap())));
+        assert!(!found.iter().any(|c| matches!(c, Command::Acknowledge(_))));
+        assert!(found.contains(&Command::SetCheckKind("10.0.0.2:5432".parse().unwrap(), CheckKind::Tcp)));
+        assert_eq!(commands(&targets(), None, "go group"), vec![
+            Command::GoToGroup("db".to_string()),
+            Command::GoToGroup("web".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn test_group_query_offers_move_for_selected_target() {
+        let selected: SocketAddr = "10.0.0.1:443".parse().unwrap();
+        let found = commands(&targets(), Some(selected), "group edge");
+        assert_eq!(found, vec![Command::SetGroup(selected, "edge".to_string())]);
+    }
+}
@@ -0,0 +1,69 @@
+use crate::back_end::notify::Notification;
+use crate::back_end::state_tracker::{TargetState, Transition};
+
+/// Whether this build can show desktop notifications at all.
+pub const AVAILABLE: bool = cfg!(feature = "desktop-notifications");
+
+#[cfg(feature = "desktop-notifications")]
+const APP_NAME: &str = "Rust NPM";
+
+/// Summary line and body of the desktop notification for `transition`.
+#[cfg_attr(not(feature = "desktop-notifications"), allow(dead_code))]
+pub fn content(transition: &Transition) -> (String, String) {
+    let state = match transition.to {
+        TargetState::Down => "down",
+        TargetState::Up => "back up",
+    };
+    (
+        format!("{} is {}", transition.target, state),
+        Notification::from(transition).to_string(),
+    )
+}
+
+/// Shows a native notification for `transition`. Blocks while talking to the desktop's
+/// notification service, so call it off the UI thread.
+#[cfg(feature = "desktop-notifications")]
+pub fn show(transition: &Transition) -> Result<(), String> {
+    let (summary, body) = content(transition);
+    let mut notification = notify_rust::Notification::new();
+    notification.appname(APP_NAME).summary(&summary).body(&body);
+    // Urgency is a freedesktop notion; other platforms have no equivalent.
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.urgency(match transition.to {
+        TargetState::Down => notify_rust::Urgency::Critical,
+        TargetState::Up => notify_rust::Urgency::Normal,
+    });
+    notification.show().map(drop).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "desktop-notifications"))]
+pub fn show(_transition: &Transition) -> Result<(), String> {
+    Err("built without the desktop-notifications feature".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::time::Duration;
+
+    #[test]
+    fn test_content_names_target_and_state() {
+        let mut transition = Transition {
+            target: "db:5432".to_string(),
+            from: Some(TargetState::Up),
+            to: TargetState::Down,
+            at: Utc::now(),
+            downtime: None,
+            correlation_id: String::new(),
+            metadata: Default::default(),
+            severity: None,
+        };
+        assert_eq!(content(&transition).0, "db:5432 is down");
+        transition.to = TargetState::Up;
+        transition.downtime = Some(Duration::from_secs(90));
+        let (summary, body) = content(&transition);
+        assert_eq!(summary, "db:5432 is back up");
+        assert!(body.contains("UP again after"));
+    }
+}
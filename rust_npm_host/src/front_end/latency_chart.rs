@@ -0,0 +1,189 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use iced::widget::canvas::{self, Frame, Geometry, Path, Stroke, Text};
+use iced::{Color, Point, Rectangle, Renderer, Size, Theme, mouse};
+use std::fmt;
+
+use crate::back_end::units::UnitPreferences;
+
+/// Slices the time range is split into; a week of one-minute checks would otherwise be
+/// thousands of points for a few hundred pixels.
+pub const BUCKETS: usize = 240;
+
+// Height of the failure marks along the bottom edge, and the space kept free above them.
+const FAILURE_MARK_HEIGHT: f32 = 4.0;
+const LABEL_SIZE: f32 = 12.0;
+
+/// How far back the chart goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeRange {
+    #[default]
+    Hour,
+    Day,
+    Week,
+}
+
+impl TimeRange {
+    pub const ALL: [TimeRange; 3] = [TimeRange::Hour, TimeRange::Day, TimeRange::Week];
+
+    pub fn duration(self) -> ChronoDuration {
+        match self {
+            TimeRange::Hour => ChronoDuration::hours(1),
+            TimeRange::Day => ChronoDuration::days(1),
+            TimeRange::Week => ChronoDuration::weeks(1),
+        }
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimeRange::Hour => "Last hour",
+            TimeRange::Day => "Last day",
+            TimeRange::Week => "Last week",
+        })
+    }
+}
+
+/// The checks that fell into one slice of the range.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Bucket {
+    /// Mean latency of the successful checks; `None` if there were none.
+    pub mean_ms: Option<f64>,
+    pub failures: usize,
+}
+
+/// Splits `since..until` into `count` equal slices and sums up the `(time, latency)`
+/// samples in each, as returned by `History::latency_series`.
+pub fn bucketize(series: &[(DateTime<Utc>, Option<f64>)], since: DateTime<Utc>, until: DateTime<Utc>, count: usize) -> Vec<Bucket> {
+    let span = (until - since).num_milliseconds().max(1) as f64;
+    let count = count.max(1);
+    let mut sums = vec![(0.0, 0usize, 0usize); count];
+    for (at, latency) in series {
+        if *at < since || *at > until {
+            continue;
+        }
+        let slot = (((*at - since).num_milliseconds() as f64 / span) * count as f64) as usize;
+        let (sum, successes, failures) = &mut sums[slot.min(count - 1)];
+        match latency {
+            Some(ms) => {
+                *sum += ms;
+                *successes += 1;
+            }
+            None => *failures += 1,
+        }
+    }
+    sums.into_iter()
+        .map(|(sum, successes, failures)| Bucket {
+            mean_ms: (successes > 0).then(|| sum / successes as f64),
+            failures,
+        })
+        .collect()
+}
+
+/// Latency over time as a line, with failed checks marked in red along the bottom. Gaps
+/// in the line are slices without a successful check.
+#[derive(Debug, Clone)]
+pub struct LatencyChart {
+    buckets: Vec<Bucket>,
+    units: UnitPreferences,
+}
+
+impl LatencyChart {
+    pub fn new(series: &[(DateTime<Utc>, Option<f64>)], range: TimeRange, now: DateTime<Utc>) -> Self {
+        Self {
+            buckets: bucketize(series, now - range.duration(), now, BUCKETS),
+            units: UnitPreferences::default(),
+        }
+    }
+
+    /// Labels the scale the way `units` says.
+    pub fn with_units(mut self, units: UnitPreferences) -> Self {
+        self.units = units;
+        self
+    }
+
+    /// Whether there is anything to draw.
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(|b| b.mean_ms.is_none() && b.failures == 0)
+    }
+}
+
+impl<Message> canvas::Program<Message> for LatencyChart {
+    type State = ();
+
+    fn draw(&self, _state: &(), renderer: &Renderer, theme: &Theme, bounds: Rectangle, _cursor: mouse::Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let palette = theme.extended_palette();
+        let slowest = self.buckets.iter().filter_map(|b| b.mean_ms).fold(0.0_f64, f64::max).max(1.0);
+        let step = bounds.width / self.buckets.len() as f32;
+        let top = LABEL_SIZE + 4.0;
+        let plot_height = (bounds.height - top - FAILURE_MARK_HEIGHT - 2.0).max(1.0);
+        let y = |ms: f64| top + plot_height * (1.0 - (ms / slowest) as f32);
+
+        frame.stroke(
+            &Path::line(Point::new(0.0, top + plot_height), Point::new(bounds.width, top + plot_height)),
+            Stroke::default().with_color(palette.background.strong.color).with_width(1.0),
+        );
+        let line = Path::new(|builder| {
+            let mut drawing = false;
+            for (i, bucket) in self.buckets.iter().enumerate() {
+                let x = (i as f32 + 0.5) * step;
+                match bucket.mean_ms {
+                    Some(ms) if drawing => builder.line_to(Point::new(x, y(ms))),
+                    Some(ms) => builder.move_to(Point::new(x, y(ms))),
+                    None => {}
+                }
+                drawing = bucket.mean_ms.is_some();
+            }
+        });
+        frame.stroke(&line, Stroke::default().with_color(palette.primary.base.color).with_width(2.0));
+
+        for (i, _) in self.buckets.iter().enumerate().filter(|(_, b)| b.failures > 0) {
+            frame.fill_rectangle(
+                Point::new(i as f32 * step, bounds.height - FAILURE_MARK_HEIGHT),
+                Size::new(step.max(1.0), FAILURE_MARK_HEIGHT),
+                palette.danger.base.color,
+            );
+        }
+
+        frame.fill_text(Text {
+            content: self.units.latency(slowest),
+            position: Point::ORIGIN,
+            color: Color { a: 0.7, ..palette.background.base.text },
+            size: LABEL_SIZE.into(),
+            ..Text::default()
+        });
+        vec![frame.into_geometry()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_samples_are_averaged_per_bucket() {
+        let since = Utc.with_ymd_and_hms(2026, 10, 17, 8, 0, 0).unwrap();
+        let at = |minutes: i64| since + ChronoDuration::minutes(minutes);
+        let series = [
+            (at(-5), Some(999.0)),
+            (at(1), Some(10.0)),
+            (at(14), Some(30.0)),
+            (at(20), None),
+            (at(59), Some(5.0)),
+            (at(60), Some(7.0)),
+        ];
+        let buckets = bucketize(&series, since, at(60), 4);
+        assert_eq!(
+            buckets,
+            [
+                Bucket { mean_ms: Some(20.0), failures: 0 },
+                Bucket { mean_ms: None, failures: 1 },
+                Bucket::default(),
+                Bucket { mean_ms: Some(6.0), failures: 0 },
+            ]
+        );
+        assert!(LatencyChart::new(&[], TimeRange::Day, at(60)).is_empty());
+    }
+}
@@ -1,3 +1,6 @@
 pub mod application;
-pub mod test;
-pub mod Todo_Parts;
\ No newline at end of file
+pub mod command_palette;
+pub mod desktop_notify;
+pub mod latency_chart;
+pub mod target_form;
+pub mod virtual_list;
\ No newline at end of file
@@ -0,0 +1,224 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use crate::back_end::csv_import::parse_interval;
+use crate::back_end::iana_ports::ServiceSuggestion;
+use crate::back_end::monitor::CheckKind;
+use crate::back_end::target::MonitorTarget;
+
+const MAX_HOSTNAME_LEN: usize = 253;
+const MAX_LABEL_LEN: usize = 63;
+
+/// Contents of the target form together with the inline error for each field. The same
+/// form adds new targets and edits existing ones.
+#[derive(Debug, Default)]
+pub struct TargetForm {
+    pub host: String,
+    pub port: String,
+    pub check: CheckKind,
+    /// Check interval like `30s` or `5m`; empty uses the monitor's default.
+    pub interval: String,
+    pub host_error: Option<String>,
+    pub port_error: Option<String>,
+    pub interval_error: Option<String>,
+    /// Target being edited; `None` while adding.
+    pub editing: Option<SocketAddr>,
+}
+
+/// What a valid form describes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormTarget {
+    pub host: String,
+    pub port: u16,
+    pub check: CheckKind,
+    pub interval: Option<Duration>,
+}
+
+impl TargetForm {
+    pub fn set_host(&mut self, host: String) {
+        self.host_error = (!host.trim().is_empty()).then(|| validate_host(&host).err()).flatten();
+        self.host = host;
+    }
+
+    pub fn set_port(&mut self, port: String) {
+        self.port_error = (!port.trim().is_empty()).then(|| validate_port(&port).err()).flatten();
+        self.port = port;
+    }
+
+    pub fn set_interval(&mut self, interval: String) {
+        self.interval_error = validate_interval(&interval).err();
+        self.interval = interval;
+    }
+
+    /// Fills the form with the settings of `target` for editing.
+    pub fn edit(&mut self, target: &MonitorTarget) {
+        *self = Self {
+            host: target.host.clone().unwrap_or_else(|| target.address.ip().to_string()),
+            port: target.address.port().to_string(),
+            check: target.check,
+            interval: target.interval.map(|i| format!("{}s", i.as_secs())).unwrap_or_default(),
+            editing: Some(target.address),
+            ..Self::default()
+        };
+    }
+
+    /// Validates every field, recording the errors, and returns the target if all are fine.
+    pub fn validate(&mut self) -> Option<FormTarget> {
+        let host = validate_host(&self.host);
+        let port = validate_port(&self.port);
+        let interval = validate_interval(&self.interval);
+        self.host_error = host.as_ref().err().cloned();
+        self.port_error = port.as_ref().err().cloned();
+        self.interval_error = interval.as_ref().err().cloned();
+        Some(FormTarget {
+            host: host.ok()?,
+            port: port.ok()?,
+            check: self.check,
+            interval: interval.ok()?,
+        })
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Accepts IPv4/IPv6 literals (IPv6 optionally in brackets) and RFC 1123 host names.
+pub fn validate_host(input: &str) -> Result<String, String> {
+    let host = input.trim();
+    if host.is_empty() {
+        return Err("enter an IP address or host name".to_string());
+    }
+    let unbracketed = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')).unwrap_or(host);
+    if unbracketed.parse::<IpAddr>().is_ok() {
+        return Ok(unbracketed.to_string());
+    }
+    if host.contains(':') {
+        return Err("enter the port in the port field".to_string());
+    }
+    // Something like 300.1.1.1 is a mistyped address, not a host name.
+    if host.split('.').all(|part| part.chars().all(|c| c.is_ascii_digit())) {
+        return Err(format!("'{}' is not a valid IP address", host));
+    }
+
+    let name = host.strip_suffix('.').unwrap_or(host);
+    if name.len() > MAX_HOSTNAME_LEN {
+        return Err(format!("host name is longer than {} characters", MAX_HOSTNAME_LEN));
+    }
+    for label in name.split('.') {
+        if label.is_empty() || label.len() > MAX_LABEL_LEN {
+            return Err(format!("'{}' has an empty or too long part", host));
+        }
+        if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("'{}' contains characters not allowed in host names", host));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("'{}' has a part starting or ending with '-'", host));
+        }
+    }
+    Ok(name.to_string())
+}
+
+pub fn validate_port(input: &str) -> Result<u16, String> {
+    let port = input.trim();
+    if port.is_empty() {
+        return Err("enter a port".to_string());
+    }
+    match port.parse::<u32>() {
+        Ok(0) => Err("port 0 can't be monitored".to_string()),
+        Ok(p) if p <= u16::MAX as u32 => Ok(p as u16),
+        Ok(_) => Err(format!("port must be between 1 and {}", u16::MAX)),
+        Err(_) => Err(format!("'{}' is not a port number", port)),
+    }
+}
+
+/// Empty means the monitor's default interval.
+pub fn validate_interval(input: &str) -> Result<Option<Duration>, String> {
+    match input.trim() {
+        "" => Ok(None),
+        interval => parse_interval(interval).map(Some),
+    }
+}
+
+/// Suggests services for what was typed into the port field: ports starting with the digits
+/// typed so far, or services whose name matches the text (prefix matches first).
+pub fn suggest<'a>(services: &'a [ServiceSuggestion], query: &str, limit: usize) -> Vec<&'a ServiceSuggestion> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    if query.chars().all(|c| c.is_ascii_digit()) {
+        return services
+            .iter()
+            .filter(|s| s.port.to_string().starts_with(&query))
+            .take(limit)
+            .collect();
+    }
+    let mut matches: Vec<&ServiceSuggestion> = services
+        .iter()
+        .filter(|s| s.service.to_lowercase().contains(&query))
+        .collect();
+    matches.sort_by_key(|s| (!s.service.to_lowercase().starts_with(&query), s.port));
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_validation() {
+        assert_eq!(validate_host(" 10.0.0.1 ").unwrap(), "10.0.0.1");
+        assert_eq!(validate_host("[::1]").unwrap(), "::1");
+        assert_eq!(validate_host("db-01.internal.").unwrap(), "db-01.internal");
+        assert!(validate_host("300.1.1.1").is_err());
+        assert!(validate_host("10.0.0.1:80").is_err());
+        assert!(validate_host("-bad.example").is_err());
+        assert!(validate_host("under_score.example").is_err());
+    }
+
+    #[test]
+    fn test_port_validation() {
+        assert_eq!(validate_port("443"), Ok(443));
+        assert!(validate_port("0").is_err());
+        assert!(validate_port("70000").is_err());
+        assert!(validate_port("https").is_err());
+    }
+
+    #[test]
+    fn test_editing_round_trips_target_settings() {
+        let target = MonitorTarget::new("10.0.0.5:53".parse().unwrap())
+            .with_check(CheckKind::Udp)
+            .with_interval(Duration::from_secs(300));
+        let mut form = TargetForm::default();
+        form.edit(&target);
+        assert_eq!(form.editing, Some(target.address));
+        assert_eq!(
+            form.validate(),
+            Some(FormTarget {
+                host: "10.0.0.5".to_string(),
+                port: 53,
+                check: CheckKind::Udp,
+                interval: Some(Duration::from_secs(300)),
+            })
+        );
+        form.set_interval("often".to_string());
+        assert!(form.interval_error.is_some());
+        assert!(form.validate().is_none());
+    }
+
+    #[test]
+    fn test_suggestions_by_port_and_name() {
+        let service = |port, name: &str| ServiceSuggestion {
+            port,
+            service: name.to_string(),
+            description: String::new(),
+        };
+        let services = vec![service(22, "ssh"), service(80, "http"), service(443, "https"), service(8080, "http-alt")];
+        let by_port: Vec<u16> = suggest(&services, "8", 10).iter().map(|s| s.port).collect();
+        assert_eq!(by_port, vec![80, 8080]);
+        let by_name: Vec<u16> = suggest(&services, "HTTP", 2).iter().map(|s| s.port).collect();
+        assert_eq!(by_name, vec![80, 443]);
+    }
+}
@@ -1,3 +0,0 @@
-pub fn write_host() -> (){
-    println!("Hello World!")
-}
\ No newline at end of file
@@ -0,0 +1,69 @@
+use std::ops::Range;
+
+/// Rows built above and below the visible ones, so fast scrolling doesn't show gaps.
+pub const OVERSCAN: usize = 5;
+
+/// Vertical scroll state of a list whose rows all have the same height. Only the rows in
+/// `window` get widgets; spacers stand in for the rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VirtualList {
+    pub row_height: f32,
+    /// Scrolled distance from the top.
+    pub offset: f32,
+    /// Height of the viewport; a guess until the first scroll event reports it.
+    pub height: f32,
+}
+
+impl VirtualList {
+    pub fn new(row_height: f32) -> Self {
+        Self {
+            row_height,
+            offset: 0.0,
+            height: 800.0,
+        }
+    }
+
+    /// Indices of the rows to build for a list of `len` rows.
+    pub fn window(&self, len: usize) -> Range<usize> {
+        let first = ((self.offset.max(0.0) / self.row_height) as usize).saturating_sub(OVERSCAN).min(len);
+        let count = (self.height / self.row_height).ceil() as usize + 2 * OVERSCAN;
+        first..(first + count).min(len)
+    }
+
+    /// Heights of the spacers above and below `window`.
+    pub fn padding(&self, window: &Range<usize>, len: usize) -> (f32, f32) {
+        (window.start as f32 * self.row_height, (len - window.end) as f32 * self.row_height)
+    }
+
+    /// Offset that brings row `index` into view, centered; `None` if it is already
+    /// fully visible.
+    pub fn reveal(&self, index: usize) -> Option<f32> {
+        let (top, bottom) = (index as f32 * self.row_height, (index + 1) as f32 * self.row_height);
+        if top >= self.offset && bottom <= self.offset + self.height {
+            return None;
+        }
+        Some((top - (self.height - self.row_height) / 2.0).max(0.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_and_reveal() {
+        let mut list = VirtualList::new(30.0);
+        list.height = 300.0;
+        assert_eq!(list.window(5000), 0..20);
+        assert_eq!(list.window(3), 0..3);
+
+        list.offset = 3000.0;
+        let window = list.window(5000);
+        assert_eq!(window, 95..115);
+        assert_eq!(list.padding(&window, 5000), (2850.0, 4885.0 * 30.0));
+
+        assert_eq!(list.reveal(102), None);
+        assert_eq!(list.reveal(200), Some(200.0 * 30.0 - 135.0));
+        assert_eq!(list.reveal(0), Some(0.0));
+    }
+}
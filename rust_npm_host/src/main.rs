@@ -1,116 +1,1383 @@
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
-use std::thread;
 mod back_end;
+mod cli;
 mod front_end;
-// Need to import the function if we're calling it directly here
-// use back_end::ping_test::measure_website_functional_time;
-
-// Example function demonstrating how to call the website check
-// In a real scenario, parameters would come from GUI input or config files.
-async fn perform_website_check(
-    webdriver_url: &str,
-    target_url: &str,
-    selector: Option<&str>,
-    headless: bool,
+mod shell;
+use back_end::storage::StatusStore;
+use clap::Parser;
+
+/// What `--lookup-port` looks up.
+#[derive(Debug, PartialEq)]
+enum PortQuery {
+    Port(u16, back_end::iana_ports::Protocol),
+    Name(String),
+}
+
+/// `6010`, `53/udp` or a service name like `ssh`.
+fn port_query(query: &str) -> Result<PortQuery, String> {
+    let (port, protocol) = query.split_once('/').unwrap_or((query, "tcp"));
+    match port.parse::<u16>() {
+        Ok(port) => Ok(PortQuery::Port(port, protocol.parse()?)),
+        Err(_) => Ok(PortQuery::Name(query.to_string())),
+    }
+}
+
+/// Saves the address book right away after `--pause`, `--resume`, `--meta`, `--owner`,
+/// `--import` or a subcommand like `add`, as the process exits before the background saver would; exits non-zero if the
+/// change or the save failed.
+fn save_address_book(
+    changed: Result<(), Box<dyn std::error::Error>>,
+    monitor: &back_end::monitor::Monitor,
+    path: Option<&std::path::Path>,
 ) {
-    println!(
-        "Performing website check for {} (headless: {})",
-        target_url, headless
-    );
-    match back_end::ping_test::measure_website_functional_time(
-        webdriver_url,
-        target_url,
-        selector,
-        headless,
-    )
-    .await
+    let saved = changed.and_then(|()| match path {
+        Some(path) => back_end::address::AddressBook::from_monitor(monitor).save(path),
+        None => Err("no address book location".into()),
+    });
+    if let Err(e) = saved {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// What the GUI lets its user do: everything, unless `--read-only` is given or the
+/// `--gui-token` is a read-scoped token of the `--api-config` (or `[api] access`) file.
+fn gui_scope(options: &cli::Options, config: &back_end::config::Config) -> Result<back_end::auth::Scope, Box<dyn std::error::Error>> {
+    use back_end::auth::Scope;
+
+    if options.read_only {
+        return Ok(Scope::Read);
+    }
+    let Some(token) = &options.gui_token else {
+        return Ok(Scope::Admin);
+    };
+    let path = options
+        .api_config
+        .clone()
+        .or(config.api.access.clone())
+        .ok_or("--gui-token needs --api-config")?;
+    let identity = back_end::api_access::ApiConfig::load(&path)?
+        .authenticator()
+        .verify(token)
+        .ok_or("unknown --gui-token")?;
+    Ok(identity.scope)
+}
+
+/// Mirrors the monitor at `url` into `monitor`, which stops checking targets itself, and
+/// returns what the `--gui-token` may do there.
+async fn connect_remote(
+    options: &cli::Options,
+    url: &str,
+    monitor: &Arc<back_end::monitor::Monitor>,
+) -> Result<back_end::auth::Scope, Box<dyn std::error::Error>> {
+    use back_end::remote::{self, RemoteApi};
+
+    let api = RemoteApi::new(url, options.gui_token.clone(), Duration::from_secs(10))?;
+    let scope = api.scope().await.map_err(|e| format!("{}: {}", url, e))?;
+    monitor.set_standby(true);
+    tokio::spawn(remote::mirror(api, monitor.clone(), Duration::from_secs(5)));
+    if options.read_only {
+        return Ok(back_end::auth::Scope::Read);
+    }
+    Ok(scope)
+}
+
+/// `--import <file.csv> [--columns host=Name,port=Port,...] [--dry-run]`: prints what would be
+/// imported and, unless it's a dry run, adds the targets to `monitor` and saves them to the
+/// address book at `book`. Returns false on errors.
+async fn import_targets(options: &cli::Options, path: &Path, monitor: &back_end::monitor::Monitor, book: Option<&Path>) -> bool {
+    use back_end::csv_import::{self, ColumnMapping};
+
+    let mapping = match options.columns.as_deref().map(ColumnMapping::parse) {
+        Some(Ok(mapping)) => mapping,
+        Some(Err(e)) => {
+            eprintln!("Invalid --columns: {}", e);
+            return false;
+        }
+        None => ColumnMapping::default(),
+    };
+    let preview = match std::fs::File::open(path)
+        .map_err(|e| e.into())
+        .and_then(|file| csv_import::preview(file, &mapping))
     {
-        Ok(duration) => {
-            println!(
-                "Website {} functional in {:?}{}",
-                target_url,
-                duration,
-                selector.map_or("".to_string(), |s| format!(" (waiting for '{}')", s))
-            );
+        Ok(preview) => preview,
+        Err(e) => {
+            eprintln!("Cannot import {}: {}", path.display(), e);
+            return false;
+        }
+    };
+    println!("{}", preview);
+    if options.dry_run {
+        return true;
+    }
+
+    let summary = csv_import::apply(monitor, &preview).await;
+    println!("Imported {} targets.", summary.added);
+    for (line, reason) in &summary.skipped {
+        println!("Skipped line {}: {}", line, reason);
+    }
+    if summary.added > 0 {
+        save_address_book(Ok(()), monitor, book);
+    }
+    true
+}
+
+/// `--check-compat [--targets <file>] [--database <url>]`: reports whether this build can
+/// read the address book and the database schema, without changing either. Returns false
+/// if it can't.
+async fn check_compat(book: Option<&Path>, database: Option<&str>) -> bool {
+    use back_end::address::{ADDRESS_BOOK_VERSION, AddressBook};
+    use back_end::schema::{self, Compatibility};
+
+    let mut ok = true;
+    if let Some(path) = book {
+        match AddressBook::file_version(path).and_then(|version| Ok((version, AddressBook::load(path)?))) {
+            Ok((None, _)) => println!("Address book {}: not present", path.display()),
+            Ok((Some(version), _)) if version < ADDRESS_BOOK_VERSION => println!(
+                "Address book {}: format v{}, upgraded to v{} on next save",
+                path.display(),
+                version,
+                ADDRESS_BOOK_VERSION
+            ),
+            Ok((Some(version), _)) => println!("Address book {}: format v{} (current)", path.display(), version),
+            Err(e) => {
+                println!("Address book: {}", e);
+                ok = false;
+            }
+        }
+    }
+
+    if let Some(url) = database {
+        let compat = match sqlx::PgPool::connect(url).await {
+            Ok(pool) => schema::current_version(&pool).await.map(schema::compatibility),
+            Err(e) => Err(e.into()),
+        };
+        match compat {
+            Ok(compat) => {
+                println!("Database: {}", compat);
+                ok &= !matches!(compat, Compatibility::Newer { .. });
+            }
+            Err(e) => {
+                println!("Database: {}", e);
+                ok = false;
+            }
+        }
+    }
+    ok
+}
+
+/// `--annotate <target> --from <time> --to <time> --label <text> [--keep-in-sla]`,
+/// `--unannotate <id>` and `--annotations [<target>]`: manage operator notes on spans of a
+/// target's history. Times are RFC 3339. Returns false on errors.
+fn annotate(options: &cli::Options) -> bool {
+    use back_end::annotations::AnnotationStore;
+
+    let Some(path) = options.annotations_file.clone().or_else(AnnotationStore::default_path) else {
+        eprintln!("No location for annotations; pass --annotations-file <file>");
+        return false;
+    };
+    let mut store = match AnnotationStore::load(&path) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", path.display(), e);
+            return false;
+        }
+    };
+    let time = |name: &str, value: Option<&str>| {
+        let value = value.ok_or_else(|| format!("{} is required", name))?;
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .map_err(|e| format!("invalid {} '{}': {}", name, value, e))
+    };
+
+    if let Some(target) = &options.annotate {
+        let label = options.label.clone().unwrap_or_default();
+        let added = time("--from", options.from.as_deref())
+            .and_then(|from| Ok((from, time("--to", options.to.as_deref())?)))
+            .and_then(|(from, to)| store.add(target, from, to, &label, !options.keep_in_sla));
+        match added {
+            Ok(id) => println!("Added annotation #{}", id),
+            Err(e) => {
+                eprintln!("Cannot annotate {}: {}", target, e);
+                return false;
+            }
+        }
+    } else if let Some(id) = options.unannotate {
+        if !store.remove(id) {
+            eprintln!("No annotation #{}", id);
+            return false;
+        }
+        println!("Removed annotation #{}", id);
+    } else {
+        let target = options.annotations.clone().flatten();
+        for annotation in store.annotations().iter().filter(|a| target.as_ref().is_none_or(|t| &a.target == t)) {
+            println!("{}", annotation);
+        }
+        return true;
+    }
+
+    if let Err(e) = store.save(&path) {
+        eprintln!("Cannot save {}: {}", path.display(), e);
+        return false;
+    }
+    true
+}
+
+/// The annotations in `--annotations-file`, or the default file; `None` if unreadable.
+fn load_annotations(options: &cli::Options) -> Option<back_end::annotations::AnnotationStore> {
+    use back_end::annotations::AnnotationStore;
+
+    options
+        .annotations_file
+        .clone()
+        .or_else(AnnotationStore::default_path)
+        .and_then(|path| AnnotationStore::load(&path).ok())
+}
+
+/// Writes the `--evidence` bundle of `target`. Returns false on errors.
+async fn write_evidence(options: &cli::Options, target: &str, history: &back_end::history::History, tz: chrono_tz::Tz) -> bool {
+    let now = chrono::Utc::now();
+    let window = back_end::api_server::parse_since(options.from.as_deref(), now)
+        .map_err(|e| format!("invalid --from: {}", e))
+        .and_then(|from| match &options.to {
+            Some(to) => chrono::DateTime::parse_from_rfc3339(to)
+                .map(|to| (from, to.with_timezone(&chrono::Utc)))
+                .map_err(|e| format!("invalid --to '{}': {}", to, e)),
+            None => Ok((from, now)),
+        });
+    let (from, to) = match window {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!("{}", e);
+            return false;
+        }
+    };
+    let annotations = load_annotations(options);
+    let mut bundle = back_end::evidence::collect(history, annotations.as_ref(), target, from, to, now).in_timezone(tz);
+
+    let addr = target.parse::<std::net::SocketAddr>().ok();
+    if let (Some(dir), Some(addr)) = (&options.captures, addr) {
+        for path in back_end::capture::captures_between(dir, addr, from, to) {
+            if let Err(e) = bundle.add_path("captures", &path) {
+                bundle.note(format!("capture {} not included: {}", path.display(), e));
+            }
+        }
+    }
+    for path in &options.attach {
+        if let Err(e) = bundle.add_path("attachments", path) {
+            eprintln!("Cannot attach {}: {}", path.display(), e);
+            return false;
+        }
+    }
+    if options.traceroute {
+        match addr.map(|addr| addr.ip()) {
+            Some(ip) => match back_end::evidence::traceroute(ip).await {
+                Ok(output) => {
+                    bundle.add_file("traceroute.txt", output.into_bytes());
+                    bundle.note(format!("traceroute.txt was taken at {}, not during the outages", now.to_rfc3339()));
+                }
+                Err(e) => bundle.note(format!("no traceroute: {}", e)),
+            },
+            None => bundle.note(format!("no traceroute: {} is not an address", target)),
+        }
+    }
+
+    let out = options.out.clone().unwrap_or_else(|| bundle.file_name().into());
+    match bundle.to_zip().and_then(|zip| Ok(std::fs::write(&out, zip)?)) {
+        Ok(()) => {
+            println!("Evidence for {} written to {}", target, out.display());
+            true
+        }
+        Err(e) => {
+            eprintln!("Cannot write {}: {}", out.display(), e);
+            false
+        }
+    }
+}
+
+/// Reads check results stored as one JSON object per line.
+fn load_results(path: &Path) -> Result<Vec<back_end::check_result::CheckResult>, Box<dyn std::error::Error>> {
+    let mut results = Vec::new();
+    for (i, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+        if !line.trim().is_empty() {
+            results.push(serde_json::from_str(line).map_err(|e| format!("{} line {}: {}", path.display(), i + 1, e))?);
+        }
+    }
+    Ok(results)
+}
+
+/// `--compare-windows <target> --before <from>/<to> --after <from>/<to> --results <file>
+/// [--json]`: contrasts a target's availability, latency percentiles and failure causes
+/// between two time ranges. Returns false on errors.
+fn compare_windows(options: &cli::Options) -> bool {
+    use back_end::window_compare::WindowComparison;
+
+    let (Some(target), Some(before), Some(after), Some(path)) = (&options.compare_windows, options.before, options.after, &options.results) else {
+        eprintln!("Usage: --compare-windows <target> --before <from>/<to> --after <from>/<to> --results <file>");
+        return false;
+    };
+    let results = match load_results(path) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Cannot read {}: {}", path.display(), e);
+            return false;
+        }
+    };
+    // Checks in excluded maintenance spans count in neither window.
+    let results = match load_annotations(options) {
+        Some(store) => store.without_excluded(&results),
+        None => results,
+    };
+    let report = WindowComparison::new(target, before, after, &results);
+    if options.json {
+        match serde_json::to_string_pretty(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Cannot write the report: {}", e);
+                return false;
+            }
+        }
+    } else {
+        print!("{}", report);
+    }
+    true
+}
+
+/// The IANA port registry: `--ports-file <csv>` if given, otherwise the `--update-ports`
+/// download if there is one, otherwise the snapshot built into the binary.
+fn load_port_registry(options: &cli::Options) -> Result<back_end::iana_ports::PortRegistry, Box<dyn std::error::Error>> {
+    use back_end::iana_ports::PortRegistry;
+
+    PortRegistry::load(options.ports_file.as_deref(), PortRegistry::default_cache_path().as_deref())
+}
+
+/// `--discover <cidr> [--fingerprint] [--inventory-out <file>] [--add-targets]`: ping sweep
+/// of a network, optionally with a device type guess per host, written as an inventory for
+/// `--rules` or added to the address book (`--targets` or the default) when asked. Returns
+/// false on errors.
+async fn discover(options: &cli::Options, cidr: &str, book: Option<&Path>) -> bool {
+    use back_end::target_rules::Inventory;
+
+    let hosts = match back_end::discovery::sweep(cidr, options.fingerprint).await {
+        Ok(hosts) => hosts,
+        Err(e) => {
+            eprintln!("Cannot sweep {}: {}", cidr, e);
+            return false;
+        }
+    };
+    let ports = match load_port_registry(options) {
+        Ok(registry) => registry.tables(),
+        Err(e) => {
+            eprintln!("Cannot load the port registry: {}", e);
+            return false;
+        }
+    };
+    for host in &hosts {
+        println!("{}", host);
+        for port in &host.open_ports {
+            let service = ports.lookup_by_port(*port, back_end::iana_ports::Protocol::Tcp);
+            println!("    {:>5}/tcp  {}", port, service.map_or("unknown", |s| s.name.as_str()));
+        }
+    }
+    println!("{} hosts answered", hosts.len());
+
+    if let Some(path) = &options.inventory_out {
+        let inventory = Inventory {
+            hosts: hosts.iter().map(|host| host.inventory_host()).collect(),
+        };
+        let written = toml::to_string_pretty(&inventory)
+            .map_err(|e| e.to_string())
+            .and_then(|content| std::fs::write(path, content).map_err(|e| e.to_string()));
+        if let Err(e) = written {
+            eprintln!("Cannot write {}: {}", path.display(), e);
+            return false;
+        }
+    }
+    if options.add_targets {
+        use back_end::address::AddressBook;
+
+        let Some(path) = book else {
+            eprintln!("No address book location; pass --targets <file>");
+            return false;
+        };
+        let monitor = back_end::monitor::Monitor::new(back_end::event_bus::EventBus::new(), Duration::from_secs(1));
+        match AddressBook::load(path) {
+            Ok(book) => {
+                book.apply_to(&monitor);
+            }
+            Err(e) => {
+                eprintln!("Cannot read {}: {}", path.display(), e);
+                return false;
+            }
+        }
+        let mut added = 0;
+        for target in hosts.iter().flat_map(|host| host.targets()) {
+            match monitor.add_monitor_target(target) {
+                Ok(()) => added += 1,
+                Err(e) => println!("Skipped: {}", e),
+            }
+        }
+        save_address_book(Ok(()), &monitor, Some(path));
+        println!("Added {} targets to {}", added, path.display());
+    }
+    true
+}
+
+/// Writes the stored results of the last `--export-days` whole days, or all of them, as
+/// Parquet files partitioned by date and target, with the `[metrics]` derived metrics.
+#[cfg(feature = "parquet-export")]
+async fn export_parquet(options: &cli::Options, store: Option<&back_end::storage::Storage>, dir: &Path, config: &back_end::config::Config) -> bool {
+    let Some(store) = store else {
+        eprintln!("--export-parquet needs a database");
+        return false;
+    };
+    let since = match options.export_days {
+        Some(days) => (chrono::Utc::now() - chrono::Duration::days(days)).date_naive().and_time(chrono::NaiveTime::MIN).and_utc(),
+        None => chrono::DateTime::UNIX_EPOCH,
+    };
+    let mut results = match store.results_since(since).await {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("Cannot read results: {}", e);
+            return false;
         }
+    };
+    // Results stored before a metric was defined get it too.
+    let derived = config.metrics.derived_metrics().unwrap_or_default();
+    for result in &mut results {
+        derived.apply(result);
+    }
+    match back_end::parquet_export::export(&results, dir, &config.display) {
+        Ok(summary) => {
+            println!("Exported {} results into {} files under {}", summary.rows, summary.files.len(), dir.display());
+            true
+        }
+        Err(e) => {
+            eprintln!("Export failed: {}", e);
+            false
+        }
+    }
+}
+
+#[cfg(not(feature = "parquet-export"))]
+async fn export_parquet(_options: &cli::Options, _store: Option<&back_end::storage::Storage>, _dir: &Path, _config: &back_end::config::Config) -> bool {
+    eprintln!("This build has no Parquet export; rebuild with --features parquet-export");
+    false
+}
+
+/// Loads the check plugins in `[plugins] dir` for the monitor's `plugin` targets.
+#[cfg(feature = "wasm-plugins")]
+fn load_plugins(monitor: &back_end::monitor::Monitor, dir: &std::path::Path) {
+    match back_end::wasm_plugin::PluginRegistry::load_dir(dir) {
+        Ok(plugins) => monitor.set_plugins(plugins),
         Err(e) => {
-            eprintln!("Error checking website {}: {:?}", target_url, e);
+            eprintln!("Cannot load plugins from {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(not(feature = "wasm-plugins"))]
+fn load_plugins(_monitor: &back_end::monitor::Monitor, dir: &std::path::Path) {
+    eprintln!("This build has no plugin support; the plugins in {} are not loaded. Rebuild with --features wasm-plugins", dir.display());
+}
+
+/// What checks need besides the targets: concurrency, power mode, the WebDriver and its
+/// browser sessions, SSH and the HTTP client. Set up before any subcommand or mode runs.
+struct Resources {
+    webdriver_url: String,
+    driver: Option<Arc<back_end::webdriver_process::ManagedDriver>>,
+    sessions: Option<back_end::webdriver_reaper::SessionRegistry>,
+    ssh: Option<Arc<back_end::ssh::SshPool>>,
+}
+
+impl Resources {
+    /// Closes the SSH connections and stops the WebDriver this process started.
+    async fn close(self) {
+        if let Some(ssh) = self.ssh {
+            ssh.close_all().await;
+        }
+        if let Some(driver) = self.driver {
+            driver.stop().await;
         }
     }
 }
 
+async fn set_up_resources(options: &cli::Options, config: &back_end::config::Config, monitor: &Arc<back_end::monitor::Monitor>) -> Resources {
+    // `--concurrency <n>` caps parallel checks; `--interval <secs>` checks targets
+    // periodically in the background, each on its own interval if it has one and every
+    // `<secs>` otherwise.
+    monitor.set_concurrency(options.concurrency.unwrap_or(config.monitor.concurrency));
+    // `--low-power <auto|on> [--low-power-factor <n>]` saves battery on laptops: intervals
+    // are stretched (4x, at least 5 minutes), checks due close together run as one batch
+    // and browser checks are suspended. `auto` only does so while running on battery.
+    if let Some(mode) = options.low_power.or(config.monitor.low_power) {
+        use back_end::power::{LowPower, PowerMode};
+
+        let policy = LowPower {
+            interval_factor: options.low_power_factor.unwrap_or(config.monitor.low_power_factor),
+            ..LowPower::default()
+        };
+        match mode {
+            PowerMode::On => monitor.set_low_power(Some(policy)),
+            PowerMode::Auto => {
+                tokio::spawn(back_end::power::watch(monitor.clone(), policy));
+            }
+        }
+    }
+    // `--start-webdriver <auto|name|path>` (or `[webdriver] driver`) starts a WebDriver
+    // server for browser checks on a free port and restarts it if it crashes; otherwise
+    // they go through one on port 4444.
+    let mut webdriver_url = "http://localhost:4444".to_string();
+    let mut browser = back_end::browser_emulator::BrowserKind::Chrome;
+    let mut driver = None;
+    if let Some(spec) = options.start_webdriver.clone().or(config.webdriver.driver.clone()) {
+        use back_end::webdriver_process::{self, ManagedDriver};
+        let started = match webdriver_process::find_driver(&spec, None) {
+            Ok(binary) => ManagedDriver::start(binary, Duration::from_secs(15)).await,
+            Err(e) => Err(e.into()),
+        };
+        match started {
+            Ok(started) => {
+                webdriver_url = started.url();
+                browser = started.browser();
+                tokio::spawn(started.clone().supervise(Duration::from_secs(5)));
+                driver = Some(started);
+            }
+            Err(e) => {
+                eprintln!("Cannot start the WebDriver: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    // `--browser-sessions <n>` (2 by default): how many warm browser sessions scheduled
+    // browser checks share; idle ones are checked and replaced every minute.
+    let browser_sessions = options.browser_sessions;
+    // Every session is recorded, so ones left behind by a crashed run (or leaked for more
+    // than six hours) are killed at startup and every ten minutes.
+    let sessions = back_end::webdriver_reaper::SessionRegistry::default_path().map(back_end::webdriver_reaper::SessionRegistry::new);
+    let browser_pool = match &sessions {
+        Some(registry) => {
+            use back_end::webdriver_reaper::OrphanReaper;
+            match OrphanReaper::new(registry.clone(), chrono::Duration::hours(6), Duration::from_secs(5)) {
+                Ok(reaper) => {
+                    tokio::spawn(reaper.watch(webdriver_url.clone(), Duration::from_secs(600)));
+                }
+                Err(e) => eprintln!("Cannot reap WebDriver sessions: {}", e),
+            }
+            back_end::browser_emulator::SessionPool::with_registry(&webdriver_url, browser, true, browser_sessions, registry.clone())
+        }
+        None => back_end::browser_emulator::SessionPool::new(&webdriver_url, browser, true, browser_sessions),
+    };
+    tokio::spawn(browser_pool.clone().maintain(Duration::from_secs(60)));
+    monitor.set_browser_pool(Some(browser_pool.clone()));
+    if monitor.monitor_targets().iter().any(|t| t.check == back_end::monitor::CheckKind::Browser) {
+        let pool = browser_pool.clone();
+        tokio::spawn(async move {
+            if let Err(e) = pool.warm().await {
+                eprintln!("Cannot open browser sessions: {}", e);
+            }
+        });
+    }
+    // Systemd and disk checks share one SSH connection per host.
+    let ssh = match back_end::ssh::SshPool::new(back_end::ssh::SshPool::default_control_dir(), Duration::from_secs(5)) {
+        Ok(pool) => Some(Arc::new(pool)),
+        Err(e) => {
+            eprintln!("SSH checks disabled: {}", e);
+            None
+        }
+    };
+    monitor.set_ssh_pool(ssh.clone());
+    // `--http-max-per-host <n>` caps parallel requests to one host across all HTTP checks;
+    // `--dns-cache-secs <n>` sets how long resolved addresses are reused, 0 to turn it off.
+    if options.http_max_per_host.is_some() || options.dns_cache_secs.is_some() {
+        let mut config = back_end::http_pool::HttpPoolConfig::default();
+        if let Some(n) = options.http_max_per_host {
+            config.max_per_host = n;
+        }
+        if let Some(secs) = options.dns_cache_secs {
+            config.dns_ttl = (secs > 0).then(|| Duration::from_secs(secs));
+        }
+        match back_end::http_pool::HttpPool::new(config) {
+            Ok(pool) => monitor.set_http_pool(pool),
+            Err(e) => {
+                eprintln!("Cannot set up the HTTP client: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    Resources {
+        webdriver_url,
+        driver,
+        sessions,
+        ssh,
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    //I am trying the gui interface rn
-    // front_end::application::run_gui(); // This would also need to be async or spawn tasks
+    // Subcommands (`add`, `list`, `check`, `run`, `web-check`, ...) and flags are validated
+    // up front so mistakes are reported before anything connects; subcommands run once the
+    // targets are loaded.
+    let cli::Cli {
+        command,
+        options,
+        targets,
+        database,
+        no_database,
+        log_format,
+        config: config_path,
+    } = cli::Cli::parse();
+    // `--config <file>` reads settings from there instead of `config.toml` in the config
+    // directory (`config print-default` shows them all); flags override settings.
+    if let Some(cli::Command::Config { action }) = &command {
+        std::process::exit(if cli::config(action, config_path.as_deref()) { 0 } else { 1 });
+    }
+    if let Some(cli::Command::Encrypt { files }) = &command {
+        let book = targets.as_deref().map(Path::new);
+        std::process::exit(if cli::encrypt(files, config_path.as_deref(), book) { 0 } else { 1 });
+    }
+    // `--targets <file>` overrides the default address book location.
+    let book_path = targets.map(std::path::PathBuf::from).or_else(back_end::address::AddressBook::default_path);
+    let config = match config_path.clone().or_else(back_end::config::Config::default_path) {
+        Some(path) => match back_end::config::Config::load(&path, config_path.is_some()) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Invalid settings in {}", e);
+                std::process::exit(1);
+            }
+        },
+        None => back_end::config::Config::default(),
+    };
+    // `--log-format json` prints check results as JSON lines for log processors.
+    let log_format = log_format.unwrap_or(config.logging.format);
+    if let Err(e) = back_end::logging::init(log_format) {
+        eprintln!("Cannot set up logging: {}", e);
+    }
+    // `--update-ports [--ports-url <url>]` downloads the IANA port registry now and caches it,
+    // replacing the snapshot built into the binary on later runs.
+    if options.update_ports {
+        use back_end::iana_ports::{IANA_CSV_URL, PortRegistry, PortTables};
 
-    // Example usage of the website check.
-    // In a real app, these values would come from user input, config, etc.
-    // And this call would likely be triggered by a UI event.
-    perform_website_check(
-        "http://localhost:4444",      // WebDriver URL
-        "https://www.example.com",    // Target website
-        Some("h1"),                   // Optional: CSS selector for "functional"
-        true,                         // Run headless
-    )
-    .await;
+        let url = options.ports_url.clone().unwrap_or_else(|| IANA_CSV_URL.to_string());
+        match PortRegistry::from_tables(PortTables::default()).update(&url, PortRegistry::default_cache_path().as_deref()).await {
+            Ok(count) => println!("Port registry updated: {} TCP services", count),
+            Err(e) => {
+                eprintln!("Cannot update the port registry from {}: {}", url, e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    // `--lookup-port <port>[/udp] | <name>` looks a port or service name up in the registry,
+    // e.g. `--lookup-port 6010` (x11, a range) or `--lookup-port ssh`.
+    if let Some(query) = &options.lookup_port {
+        let tables = match load_port_registry(&options) {
+            Ok(registry) => registry.tables(),
+            Err(e) => {
+                eprintln!("Cannot load the port registry: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let records = match port_query(query) {
+            Ok(PortQuery::Port(port, protocol)) => tables.lookup_by_port(port, protocol).into_iter().collect(),
+            Ok(PortQuery::Name(name)) => tables.lookup_by_service_name(&name),
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        };
+        if records.is_empty() {
+            eprintln!("No registered service for {}", query);
+            std::process::exit(1);
+        }
+        for record in records {
+            let ports = match (record.ports.start(), record.ports.end()) {
+                (low, high) if low == high => low.to_string(),
+                (low, high) => format!("{}-{}", low, high),
+            };
+            let protocol = record.protocol.map_or("any".to_string(), |p| p.to_string());
+            println!("{} {}/{}: {}", record.name, ports, protocol, record.description);
+        }
+        return;
+    }
+    if options.check_compat {
+        std::process::exit(if check_compat(book_path.as_deref(), database.as_deref()).await { 0 } else { 1 });
+    }
+    if options.annotate.is_some() || options.unannotate.is_some() || options.annotations.is_some() {
+        std::process::exit(if annotate(&options) { 0 } else { 1 });
+    }
+    if options.compare_windows.is_some() {
+        std::process::exit(if compare_windows(&options) { 0 } else { 1 });
+    }
+    if let Some(cidr) = &options.discover {
+        std::process::exit(if discover(&options, cidr, book_path.as_deref()).await { 0 } else { 1 });
+    }
+    // `--database <url>` stores results in Postgres (`postgres://...`), an SQLite file
+    // (`sqlite://<path>`) or memory (`memory:`); without it they go to a local SQLite
+    // file, unless `--no-database`.
+    let database = database.or(config.storage.database.clone()).or_else(|| {
+        let path = back_end::storage::Storage::default_path()?;
+        (!no_database).then(|| format!("sqlite://{}", path.display()))
+    });
+    let mut store = None;
+    if let Some(url) = database {
+        match back_end::storage::Storage::connect(&url).await {
+            Ok(connected) => store = Some(connected),
+            Err(e) => {
+                eprintln!("Database not usable: {}{}", e, back_end::diagnostics::explain(&e.to_string()));
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(cli::Command::Alerts { action }) = &command {
+        std::process::exit(if cli::alerts(action, store.as_ref(), &config.alerts.rules(), &config.display).await { 0 } else { 1 });
+    }
+    if let Some(cli::Command::Vantage { targets, days, json }) = &command {
+        std::process::exit(if cli::vantage(targets, *days, *json, store.as_ref(), config.monitor.agent.as_deref(), &config.display).await { 0 } else { 1 });
+    }
+    if let Some(cli::Command::SloExport { targets, days, availability, latency, output }) = &command {
+        let config = cli::slo_config(*days, *availability, *latency);
+        let exported = cli::slo_export(targets, &config, output.as_deref(), store.as_ref(), book_path.as_deref()).await;
+        std::process::exit(if exported { 0 } else { 1 });
+    }
+    // `--export-parquet <dir> [--export-days <n>]` copies stored results out for analytics.
+    if let Some(dir) = &options.export_parquet {
+        std::process::exit(if export_parquet(&options, store.as_ref(), dir, &config).await { 0 } else { 1 });
+    }
+    let monitor = Arc::new(back_end::monitor::Monitor::new(
+        back_end::event_bus::EventBus::new(),
+        config.monitor.timeout,
+    ));
+    // `--no-spread` starts the checks of every target right away instead of at its slot
+    // within the interval.
+    monitor.set_spread(config.monitor.spread && !options.no_spread);
+    monitor.set_alert_rules(config.alerts.rules());
+    // `--canaries <host:port,...|default>` checks those endpoints before counting a failure
+    // of an external target; when none is reachable, the outage is announced once as a
+    // loss of local connectivity instead of per target.
+    if let Some(list) = &options.canaries {
+        let endpoints = list.iter().map(|e| e.trim()).filter(|e| !e.is_empty() && *e != "default").map(String::from).collect();
+        monitor.set_canaries(Some(back_end::canary::Canaries::new(endpoints, Duration::from_secs(2))));
+    }
+    // `--scripts <dir>` runs the `*.rhai` files there on every result, in file name order,
+    // for custom statuses, severities and metrics (see `scripting`).
+    if let Some(dir) = &options.scripts {
+        match back_end::scripting::load_hooks(dir) {
+            Ok(hooks) => monitor.set_script_hooks(hooks),
+            Err(e) => {
+                eprintln!("Cannot load scripts from {}: {}", dir.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(dir) = &config.plugins.dir {
+        load_plugins(&monitor, dir);
+    }
+    // The settings were validated on loading, so the definitions compile and the windows
+    // parse.
+    monitor.set_derived_metrics(config.metrics.derived_metrics().unwrap_or_default());
+    monitor.set_maintenance_windows(config.monitor.maintenance_windows(config.display.timezone()).unwrap_or_default());
+    monitor.set_budget(config.budget.ledger());
+    monitor.set_agent_name(config.monitor.agent.clone());
+    match config.socks.proxies() {
+        Ok(proxies) => monitor.set_socks_proxies(proxies),
+        Err(e) => {
+            eprintln!("Invalid settings: {}", e);
+            std::process::exit(1);
+        }
+    }
 
-    perform_website_check(
-        "http://localhost:4444",
-        "https://www.example.com",
-        None, // No specific element, just page load
-        false, // Run with a visible browser (if not overridden by WebDriver default)
-    )
-    .await;
+    // `--latest-status`: the newest stored result of every target, per agent.
+    if options.latest_status {
+        let Some(store) = &store else {
+            eprintln!("--latest-status needs a database");
+            std::process::exit(1);
+        };
+        match store.latest_status().await {
+            Ok(rows) => {
+                for row in rows {
+                    println!(
+                        "{:<16} {:<30} {:<5} {}",
+                        row.agent_name,
+                        row.target.unwrap_or_default(),
+                        if row.status_ok { "UP" } else { "DOWN" },
+                        row.event_time.to_rfc3339()
+                    );
+                }
+                return;
+            }
+            Err(e) => {
+                eprintln!("Cannot read the status: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // If you still want to run the GUI, it needs to be compatible with the async main.
-    // For example, if run_gui() itself can be async:
-    // front_end::application::run_gui().await;
-    // Or if it's synchronous but needs to run within the tokio runtime:
-    // tokio::task::spawn_blocking(front_end::application::run_gui).await.unwrap();
-    // For now, I'll keep the original call commented out as its integration
-    // with async is beyond the current scope. The perform_website_check calls above
-    // demonstrate the core functionality.
+    // Every check result goes into the history and the database, and the last week is
+    // read back on startup. `--history-retention <count>` sets the results kept in memory
+    // per target (default 10000); older ones are compressed, so 86400 (a day of per-second
+    // checks) is fine for thousands of targets.
+    let mut history = back_end::history::History::new(options.history_retention.unwrap_or(back_end::history::DEFAULT_RETENTION));
+    if let Some(store) = store {
+        history = history.with_database(store);
+        if let Some(interval) = config.storage.sample_interval {
+            history = history.with_sampling(interval, config.alerts.rules());
+        }
+        if let Err(e) = history.load(chrono::Utc::now() - chrono::Duration::days(7)).await {
+            eprintln!("History not loaded: {}", e);
+        }
+    }
+    let history = Arc::new(history);
+    let recorder = tokio::spawn(back_end::history::record_events(monitor.clone(), history.clone()));
 
-    println!("GUI part would run here. For now, example checks are complete.");
+    // `--capture-dir <dir> [--capture-after <failures>] [--capture-secs <n>]
+    // [--capture-interface <name>]` captures a few seconds of a target's traffic once it
+    // has failed that many times in a row; the file is announced with the outage.
+    if let Some(dir) = &options.capture_dir {
+        if back_end::capture::AVAILABLE {
+            let mut config = back_end::capture::CaptureConfig::new(dir);
+            if let Some(n) = options.capture_after {
+                config.after_failures = n;
+            }
+            if let Some(secs) = options.capture_secs {
+                config.duration = Duration::from_secs(secs);
+            }
+            config.interface = options.capture_interface.clone();
+            tokio::spawn(back_end::capture::watch(monitor.clone(), config));
+        } else {
+            eprintln!("--capture-dir ignored: built without the packet-capture feature");
+        }
+    }
 
+    // `--storm-limit <n>` (20 by default): how many notifications the webhook, owner and
+    // ticket channels may send together per 5 minutes; the rest are summed up afterwards.
+    let storm = back_end::notify::SharedStormGuard::new(
+        back_end::notify::StormLimits {
+            max_notifications: options.storm_limit,
+            ..Default::default()
+        },
+        monitor.bus().clone(),
+    );
+    tokio::spawn(storm.clone().flush_every(Duration::from_secs(30)));
+    // `--webhook <url> [--webhook-kind slack|discord|generic] [--webhook-digest <15m>]` posts
+    // every up/down change as JSON; the kind is guessed from the URL unless given. With a
+    // digest interval, changes are batched into one message per interval instead.
+    // `--stale-alert` also posts when a target goes stale (see `--stale-grace`).
+    if let Some(url) = &options.webhook {
+        let kind = options.webhook_kind.unwrap_or_else(|| back_end::webhook::WebhookKind::detect(url));
+        let delivery = options.webhook_digest.unwrap_or(back_end::notify::Delivery::Instant);
+        match back_end::webhook::WebhookNotifier::new(url, kind, Duration::from_secs(10)) {
+            Ok(notifier) => {
+                let notifier = notifier.with_delivery(delivery).with_stale_alerts(options.stale_alert);
+                tokio::spawn(back_end::webhook::deliver(monitor.clone(), notifier, storm.clone()));
+            }
+            Err(e) => {
+                eprintln!("Cannot set up webhook {}: {}", url, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // front_end::application::run_gui();
+    if let Some(config) = config.alertmanager.config() {
+        let url = config.url.clone();
+        match back_end::alertmanager::AlertmanagerNotifier::new(config) {
+            Ok(notifier) => {
+                tokio::spawn(back_end::alertmanager::deliver(monitor.clone(), notifier));
+            }
+            Err(e) => {
+                eprintln!("Cannot set up Alertmanager {}: {}", url, e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    /*
-    // let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8)), 53);
-    let timeout: Duration = Duration::from_secs(1);
+    // `--owners <file>` sends the up/down changes of every target with an owner to that
+    // owner's channels, and on to its escalation chain while nobody acknowledges the outage.
+    if let Some(path) = &options.owners {
+        match back_end::ownership::OwnerDirectory::load(path) {
+            Ok(directory) => {
+                tokio::spawn(back_end::ownership::route(monitor.clone(), directory, storm.clone()));
+            }
+            Err(e) => {
+                eprintln!("Cannot read owners from {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    // Here I am going to add a funciton that loads all the ip addresses that I need.
+    // `--tickets <file>` opens Jira or ServiceNow tickets for long outages and resolves them
+    // on recovery, see `ticketing::TicketConfig`.
+    if let Some(path) = &options.tickets {
+        match back_end::ticketing::TicketConfig::load(path).and_then(back_end::ticketing::TicketClient::new) {
+            Ok(client) => {
+                tokio::spawn(back_end::ticketing::run(monitor.clone(), client, storm.clone()));
+            }
+            Err(e) => {
+                eprintln!("Cannot set up ticketing from {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
 
-    let mut addresses: Vec<SocketAddr> = Vec::new();
-    back_end::address::load_addresses(&mut addresses);
+    // A GUI on a `--remote` monitor shows that monitor's targets, not the local book's.
+    let remote = options.remote.clone();
+    if let Some(path) = book_path.as_ref().filter(|_| remote.is_none()) {
+        match back_end::address::AddressBook::load(path) {
+            Ok(book) => {
+                for (addr, reason) in book.apply_to(&monitor) {
+                    eprintln!("Skipped {} from {}: {}", addr, path.display(), reason);
+                }
+                tokio::spawn(back_end::address::persist_changes(monitor.clone(), path.clone()));
+            }
+            // Don't persist over a file we couldn't read; the user may be mid-edit.
+            Err(e) => eprintln!("Address book not loaded: {}", e),
+        }
+    }
 
-    loop {
-        for address_entry in &addresses {
-            let print_addr: String = address_entry.to_string();
-            println!("{}", back_end::ping_test::is_port_open(*address_entry, timeout));
-            
+    // `--pause <target> --reason <text> [--until <3d|RFC 3339 time>]` and `--resume <target>`
+    // change a target in the address book; `--paused` lists paused targets, longest paused
+    // first, so none get forgotten.
+    if let Some(target) = &options.pause {
+        let pause = options
+            .reason
+            .as_deref()
+            .ok_or_else(|| "--pause needs --reason <text>".to_string())
+            .and_then(back_end::pause::Pause::new)
+            .map(|pause| match std::env::var("USER") {
+                Ok(user) => pause.by(&user),
+                Err(_) => pause,
+            })
+            .and_then(|pause| match &options.until {
+                Some(until) => Ok(pause.until(back_end::pause::parse_expiry(until, chrono::Utc::now())?)),
+                None => Ok(pause),
+            });
+        let changed = pause.map_err(Into::into).and_then(|pause| {
+            let addr: std::net::SocketAddr = target.parse().map_err(|e| format!("{}: {}", target, e))?;
+            monitor.pause(addr, pause)
+        });
+        save_address_book(changed, &monitor, book_path.as_deref());
+        return;
+    }
+    if let Some(target) = &options.resume {
+        let changed = target
+            .parse()
+            .map_err(|e| format!("{}: {}", target, e).into())
+            .and_then(|addr| monitor.resume(addr));
+        save_address_book(changed, &monitor, book_path.as_deref());
+        return;
+    }
+    // `--meta <target> [--set key=value,...]` shows or changes a target's metadata (cost
+    // center, owner, CMDB ID, ...), which is passed through to its results, webhooks and
+    // alerts; `key=` removes a key.
+    if let Some(target) = &options.meta {
+        let addr = match target.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr,
+            Err(e) => {
+                eprintln!("Error: {}: {}", target, e);
+                std::process::exit(1);
+            }
+        };
+        let Some(spec) = &options.set else {
+            for (key, value) in monitor.monitor_target(addr).map(|t| t.metadata).unwrap_or_default() {
+                println!("{}={}", key, value);
+            }
+            return;
+        };
+        let changed = back_end::metadata::parse_entries(spec).map_err(Into::into).and_then(|entries| {
+            let mut config = monitor
+                .monitor_target(addr)
+                .ok_or_else(|| format!("{} is not monitored", addr))?;
+            for (key, value) in entries {
+                match value {
+                    Some(value) => config.metadata.insert(key, value),
+                    None => config.metadata.remove(&key),
+                };
+            }
+            monitor.configure(config)
+        });
+        save_address_book(changed, &monitor, book_path.as_deref());
+        return;
+    }
+    // `--owner <target> [--set <owner>]` shows or changes who owns a target; an empty owner
+    // removes it.
+    if let Some(target) = &options.owner {
+        let addr = match target.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr,
+            Err(e) => {
+                eprintln!("Error: {}: {}", target, e);
+                std::process::exit(1);
+            }
+        };
+        let Some(owner) = &options.set else {
+            if let Some(owner) = monitor.monitor_target(addr).and_then(|t| t.owner) {
+                println!("{}", owner);
+            }
+            return;
+        };
+        let changed = monitor
+            .monitor_target(addr)
+            .ok_or_else(|| format!("{} is not monitored", addr).into())
+            .and_then(|mut config| {
+                config.owner = Some(owner.trim().to_string()).filter(|o| !o.is_empty());
+                monitor.configure(config)
+            });
+        save_address_book(changed, &monitor, book_path.as_deref());
+        return;
+    }
+    if options.paused {
+        for (addr, pause) in monitor.paused_targets() {
+            println!("{} {}", addr, pause);
+        }
+        return;
+    }
+
+    // `--ports-file <csv>` uses that copy of the IANA port registry instead of the built-in
+    // one. It names the likely service next to each target, e.g. `443/tcp https`.
+    let ports = match load_port_registry(&options) {
+        Ok(registry) => Arc::new(registry),
+        Err(e) => {
+            eprintln!("Cannot load the port registry: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let resources = set_up_resources(&options, &config, &monitor).await;
+    if let Some(command) = command {
+        let ok = cli::run(command, monitor.clone(), history.clone(), book_path.as_deref(), log_format, ports.clone()).await;
+        resources.close().await;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+    let Resources {
+        webdriver_url,
+        driver,
+        sessions,
+        ssh,
+    } = resources;
+
+    // `--ha <lease file|postgres://...> [--ha-node <id>] [--ha-ttl <secs>]` pairs this instance
+    // with another one using the same lease: only the one holding it runs checks, the other
+    // takes over when the lease (30s by default) lapses.
+    if let Some(spec) = &options.ha {
+        use back_end::ha::{self, HaCoordinator, LeaseBackend};
+
+        let node = options.ha_node.clone().unwrap_or_else(|| {
+            format!("{}-{}", std::env::var("HOSTNAME").unwrap_or_else(|_| "node".to_string()), std::process::id())
+        });
+        let ttl = chrono::Duration::seconds(options.ha_ttl);
+        match LeaseBackend::open(spec).await {
+            Ok(backend) => {
+                tokio::spawn(ha::run(HaCoordinator::new(&node, backend, ttl), monitor.clone()));
+            }
+            Err(e) => {
+                eprintln!("Cannot open the HA lease {}: {}", spec, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    let interval = options.interval.map(Duration::from_secs);
+    let scheduler = interval.or(config.monitor.interval).map(|interval| {
+        monitor.set_default_interval(interval);
+        tokio::spawn(monitor.clone().schedule())
+    });
+    // `--stale-grace <secs>` (60 by default): a target whose next result is that much later
+    // than its interval and timeout allow is marked STALE, e.g. when the scheduler hangs.
+    let stale_grace = options.stale_grace.map_or(back_end::staleness::DEFAULT_GRACE, Duration::from_secs);
+    // `--resolve-interval <secs>` (300 by default): how often the host names of targets are
+    // resolved again, adding their new addresses and removing the ones they left.
+    let resolve_interval = Duration::from_secs(options.resolve_interval);
+    if scheduler.is_some() && remote.is_none() {
+        tokio::spawn(back_end::staleness::watch(monitor.clone(), stale_grace));
+        tokio::spawn(back_end::address::re_resolve(monitor.clone(), resolve_interval));
+    }
+
+    // `--rules <file> --inventory <file>` generates targets from inventory tags and keeps
+    // them in line with the inventory, re-reading both every `--rules-interval` seconds.
+    if let (Some(rules), Some(inventory)) = (&options.rules, &options.inventory) {
+        tokio::spawn(back_end::target_rules::maintain(
+            monitor.clone(),
+            rules.clone(),
+            inventory.clone(),
+            Duration::from_secs(options.rules_interval),
+        ));
+    }
 
-            if back_end::ping_test::is_port_open(*address_entry, timeout) {
-                println!("{} Is Open : )", print_addr);
-            } else {
-                println!("{} Is Closed : (", print_addr);
+    // `--dependencies <file>` adds dependencies between targets and services to the
+    // topology; services always depend on their members.
+    let dependencies = match &options.dependencies {
+        Some(path) => match back_end::topology::DependencyConfig::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Cannot read dependencies from {}: {}", path.display(), e);
+                std::process::exit(1);
             }
+        },
+        None => back_end::topology::DependencyConfig::default(),
+    };
 
+    // `--topology <file.svg>` checks every target once and draws the dependency graph for
+    // the web UI, outages and their blast radius in red.
+    if let Some(path) = &options.topology {
+        monitor.run_all().await;
+        let topology = back_end::topology::Topology::from_monitor(&monitor, &dependencies);
+        if let Err(e) = std::fs::write(path, topology.to_svg()) {
+            eprintln!("Cannot write {}: {}", path.display(), e);
+            std::process::exit(1);
         }
+        return;
+    }
 
+    // `--http <url> [--expect-status <code>] [--expect-body <text>]` checks a website once
+    // without a browser and exits non-zero unless it answered as expected.
+    if let Some(url) = &options.http {
+        if !cli::web_check(&monitor.http_pool(), url, options.expect_status, options.expect_body.as_deref()).await {
+            std::process::exit(1);
+        }
+        return;
+    }
 
+    // `--services` checks everything once and lists each service (group of targets) with
+    // its rollup status; `--service <name>` does the same for one service and exits
+    // non-zero unless it is fully up.
+    if options.services {
+        monitor.run_all().await;
+        for service in monitor.services() {
+            println!("{}", service);
+        }
+        for (addr, pause) in monitor.paused_targets() {
+            println!("{} not checked, {}", addr, pause);
+        }
+        return;
+    }
+    if let Some(name) = &options.service {
+        monitor.run_service(name).await;
+        match monitor.service(name) {
+            Some(service) => {
+                println!("{}", service);
+                if service.rollup != back_end::service::Rollup::Up {
+                    std::process::exit(1);
+                }
+            }
+            None => {
+                eprintln!("No service named {}", name);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
+    // `--history <target>`: uptime and average latency over the last day and week, and the
+    // most recent failures, from the stored history.
+    if let Some(target) = &options.history {
+        if let Some(pause) = target.parse().ok().and_then(|addr| monitor.pause_of(addr)) {
+            println!("{} {}", target, pause);
+        }
+        let now = chrono::Utc::now();
+        let annotations = load_annotations(&options);
+        for (label, since) in [("24h", now - chrono::Duration::days(1)), ("7d", now - chrono::Duration::days(7))] {
+            match history.uptime(target, since, annotations.as_ref()) {
+                Some(uptime) => println!(
+                    "{} {}: uptime {:.3}%, average latency {}",
+                    target,
+                    label,
+                    uptime * 100.0,
+                    history
+                        .average_latency(target, since)
+                        .map_or("-".to_string(), |d| format!("{:.1} ms", d.as_secs_f64() * 1000.0))
+                ),
+                None => println!("{} {}: no checks", target, label),
+            }
+        }
+        for failure in history.recent_failures(target, 10) {
+            println!("  {} {}", config.display.timestamp(failure.timestamp), failure.error.as_deref().unwrap_or("unknown error"));
+        }
+        return;
+    }
 
+    // `--trends [--json]`: targets whose latency or failure rate has been creeping up over
+    // the stored history, before they fail outright. Exits non-zero when there are any.
+    if options.trends {
+        let advisories = history.trends(&back_end::trend::TrendConfig::default(), load_annotations(&options).as_ref());
+        if options.json {
+            match serde_json::to_string_pretty(&advisories) {
+                Ok(json) => println!("{}", json),
+                Err(e) => eprintln!("Cannot write the advisories: {}", e),
+            }
+        } else if advisories.is_empty() {
+            println!("No degrading targets");
+        } else {
+            for advisory in &advisories {
+                println!("{}", advisory);
+            }
+        }
+        std::process::exit(i32::from(!advisories.is_empty()));
+    }
+
+    // `--evidence <target> [--from <24h|RFC 3339>] [--to <RFC 3339>] [--out <file.zip>]
+    // [--captures <dir>] [--attach <file>,<file>] [--traceroute]` packs the target's
+    // results, annotations, packet captures and attached files (HAR, screenshots, logs, ...)
+    // into one zip for a vendor ticket.
+    if let Some(target) = &options.evidence {
+        if !write_evidence(&options, target, &history, config.display.timezone()).await {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = &options.import {
+        if !import_targets(&options, path, &monitor, book_path.as_deref()).await {
+            std::process::exit(1);
+        }
+        if options.dry_run {
+            return;
+        }
+        if !options.gui {
+            for result in monitor.run_all().await {
+                let check = back_end::logging::check_name(&monitor, &result);
+                let service = back_end::logging::service_label(&monitor, &ports, &result);
+                let target = match &service {
+                    Some(service) => format!("{} ({})", result.target, service),
+                    None => result.target.clone(),
+                };
+                back_end::logging::report(log_format, &result, &check, service.as_deref(), || match &result.error {
+                    None => format!("{} Is Open : )", target),
+                    Some(e) => format!("{} Is Closed : ( {}", target, e),
+                });
+            }
+            return;
+        }
+    }
 
-        let sleep_duration = Duration::from_secs(60);
-        thread::sleep(sleep_duration);
+    // `--ports-update-interval <7d> [--ports-url <url>]` keeps the downloaded port registry
+    // current while the monitor runs.
+    // It can't be combined with `--ports-file`, which is used instead of downloads.
+    if let Some(interval) = options.ports_update_interval {
+        use back_end::iana_ports::{IANA_CSV_URL, PortRegistry};
 
-        
+        let url = options.ports_url.clone().unwrap_or_else(|| IANA_CSV_URL.to_string());
+        tokio::spawn(back_end::iana_ports::keep_updated(ports.clone(), url, PortRegistry::default_cache_path(), interval));
+    }
 
+    // `--api <addr:port> [--api-config <file>]` serves the HTTP API (`/health`, `/targets`,
+    // `/targets/{id}/history`, `/targets/{id}/evidence`, and UptimeRobot's
+    // `/v2/getMonitors`) while the monitor runs, e.g. with `--daemon`. Without an `[api]`
+    // config only this machine may use it.
+    if let Some(addr) = options.api.or(config.api.listen) {
+        let access = options.api_config.clone().or(config.api.access.clone());
+        let config = match access.map(|path| back_end::api_access::ApiConfig::load(&path)) {
+            Some(Ok(config)) => config,
+            Some(Err(e)) => {
+                eprintln!("Cannot read --api-config: {}", e);
+                std::process::exit(1);
+            }
+            None => back_end::api_access::ApiConfig::default(),
+        };
+        let state = match back_end::api_server::ApiState::new(monitor.clone(), history.clone(), config).discover_oidc().await {
+            Ok(state) => Arc::new(state),
+            Err(e) => {
+                eprintln!("Cannot reach the OIDC provider: {}", e);
+                std::process::exit(1);
+            }
+        };
+        tokio::spawn(async move {
+            if let Err(e) = back_end::api_server::serve(addr, state).await {
+                eprintln!("API server on {} stopped: {}", addr, e);
+            }
+        });
     }
 
-    */
+    // `--daemon [--pid-file <path>]` keeps checking in the foreground, as systemd or launchd
+    // expect, until SIGINT or SIGTERM. On shutdown the running checks finish, their results
+    // are written and the WebDriver sessions opened by this process are closed.
+    if options.daemon {
+        use back_end::daemon::{self, PidFile};
+
+        let pid_path = options.pid_file.clone().or_else(PidFile::default_path);
+        let pid_file = match pid_path.as_deref().map(PidFile::create).transpose() {
+            Ok(pid_file) => pid_file,
+            Err(e) => {
+                eprintln!("Cannot start the daemon: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if log_format == back_end::logging::LogFormat::Json {
+            tokio::spawn(back_end::logging::log_events(monitor.clone(), ports.clone()));
+        }
+        let scheduler = scheduler.unwrap_or_else(|| {
+            tokio::spawn(back_end::staleness::watch(monitor.clone(), stale_grace));
+            tokio::spawn(back_end::address::re_resolve(monitor.clone(), resolve_interval));
+            tokio::spawn(monitor.clone().schedule())
+        });
+        match daemon::wait_for_signal().await {
+            Ok(signal) => eprintln!("{} received, shutting down", signal),
+            Err(e) => eprintln!("Cannot wait for signals ({}), shutting down", e),
+        }
+        daemon::shut_down(&monitor, scheduler, recorder, sessions).await;
+        if let Some(ssh) = ssh {
+            ssh.close_all().await;
+        }
+        if let Some(driver) = driver {
+            driver.stop().await;
+        }
+        drop(pid_file);
+        return;
+    }
+
+    if options.gui {
+        // The list updates as results come in, so keep checking even without `--interval`.
+        if scheduler.is_none() && remote.is_none() {
+            tokio::spawn(monitor.clone().schedule());
+            tokio::spawn(back_end::staleness::watch(monitor.clone(), stale_grace));
+            tokio::spawn(back_end::address::re_resolve(monitor.clone(), resolve_interval));
+        }
+        // `--read-only` hides the controls that change targets, for wallboards; so does
+        // `--gui-token <token>` with a read-scoped token from `--api-config`. With
+        // `--remote <url>` the window shows another instance through its API, and that
+        // API's answer to the `--gui-token` decides.
+        let scope = match &remote {
+            Some(url) => connect_remote(&options, url, &monitor).await,
+            None => gui_scope(&options, &config),
+        };
+        let scope = match scope {
+            Ok(scope) => scope,
+            Err(e) => {
+                eprintln!("Cannot open the GUI: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let settings = front_end::application::GuiSettings {
+            units: config.display.clone(),
+            webdriver_url,
+        };
+        let runtime = tokio::runtime::Handle::current();
+        // The window blocks this thread until it is closed; checks keep running on the
+        // runtime's worker threads.
+        if let Err(e) = tokio::task::block_in_place(|| front_end::application::run_gui(monitor, history, ports, runtime, dependencies, scope, settings)) {
+            eprintln!("GUI error: {}", e);
+        }
+        if let Some(driver) = driver {
+            driver.stop().await;
+        }
+        return;
+    }
+
+    // The flags only set up checks that would stop right away with the process.
+    eprintln!("Nothing to do: pass --gui to open the monitor window, --daemon to keep checking, or a subcommand (see --help)");
+    std::process::exit(2);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use back_end::iana_ports::Protocol;
+
+    #[test]
+    fn test_port_lookup_queries() {
+        assert_eq!(port_query("53/udp"), Ok(PortQuery::Port(53, Protocol::Udp)));
+        assert_eq!(port_query("6010"), Ok(PortQuery::Port(6010, Protocol::Tcp)));
+        assert_eq!(port_query("ssh"), Ok(PortQuery::Name("ssh".to_string())));
+        assert!(port_query("22/sctpx").is_err());
+    }
 }
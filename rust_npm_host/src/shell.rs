@@ -0,0 +1,423 @@
+use chrono::{DateTime, Utc};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::back_end::address::HostSpec;
+use crate::back_end::check_result::CheckResult;
+use crate::back_end::config::format_interval;
+use crate::back_end::event_bus::MonitorEvent;
+use crate::back_end::history::History;
+use crate::back_end::http_check::HttpCheck;
+use crate::back_end::iana_ports::PortRegistry;
+use crate::back_end::monitor::{CheckKind, Monitor};
+use crate::back_end::storage::{LOCAL_AGENT, StatusRow, StatusStore};
+use crate::back_end::waterfall::Waterfall;
+use crate::cli::{parse_check_kind, resolve};
+
+const PROMPT: &str = "rust_npm> ";
+const HELP: &str = "\
+resolve <host>                 addresses of a host name, and how long the lookup took
+check <host:port> [tcp|icmp|udp|http|browser]
+check <http(s)://url>          run a check once, step by step, without recording it
+scheduler                      interval, concurrency and power mode; per target its state,
+                               last result and when the next one is due
+tail [secs]                    print monitor events as they happen (30s by default)
+results <target> [n]           the target's last n results (10 by default), from the
+                               database once none are left in memory
+latest <target>                the newest stored result of a target, from any agent
+agent <name> [n]               the last n results an agent wrote to the database
+help                           this list
+quit                           leave the shell";
+// How long `tail` follows the event bus unless told otherwise.
+const DEFAULT_TAIL: Duration = Duration::from_secs(30);
+const DEFAULT_RESULTS: usize = 10;
+// HTTP checks from the shell get as long as `web-check` gives them.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One line typed into the shell.
+#[derive(Debug, Clone, PartialEq)]
+enum ShellCommand {
+    Empty,
+    Help,
+    Quit,
+    Resolve(String),
+    Check { target: String, kind: Option<CheckKind> },
+    Scheduler,
+    Tail(Duration),
+    Results { target: String, limit: usize },
+    Latest(String),
+    Agent { name: String, limit: usize },
+}
+
+fn parse_line(line: &str) -> Result<ShellCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let Some((&command, args)) = words.split_first() else {
+        return Ok(ShellCommand::Empty);
+    };
+    let usage = |usage: &str| format!("usage: {}", usage);
+    match (command, args) {
+        ("help" | "?", _) => Ok(ShellCommand::Help),
+        ("quit" | "exit", _) => Ok(ShellCommand::Quit),
+        ("resolve", [host]) => Ok(ShellCommand::Resolve(host.to_string())),
+        ("resolve", _) => Err(usage("resolve <host>")),
+        ("check", [target]) => Ok(ShellCommand::Check {
+            target: target.to_string(),
+            kind: None,
+        }),
+        ("check", [target, kind]) => Ok(ShellCommand::Check {
+            target: target.to_string(),
+            kind: Some(parse_check_kind(kind)?),
+        }),
+        ("check", _) => Err(usage("check <host:port> [tcp|icmp|udp|http|browser] | check <url>")),
+        ("scheduler" | "status", []) => Ok(ShellCommand::Scheduler),
+        ("tail", []) => Ok(ShellCommand::Tail(DEFAULT_TAIL)),
+        ("tail", [secs]) => secs
+            .parse()
+            .map(|secs| ShellCommand::Tail(Duration::from_secs(secs)))
+            .map_err(|_| usage("tail [secs]")),
+        ("results", [target]) => Ok(ShellCommand::Results {
+            target: target.to_string(),
+            limit: DEFAULT_RESULTS,
+        }),
+        ("results", [target, n]) => n
+            .parse()
+            .map(|limit| ShellCommand::Results {
+                target: target.to_string(),
+                limit,
+            })
+            .map_err(|_| usage("results <target> [n]")),
+        ("latest", [target]) => Ok(ShellCommand::Latest(target.to_string())),
+        ("latest", _) => Err(usage("latest <target>")),
+        ("agent", [name]) => Ok(ShellCommand::Agent {
+            name: name.to_string(),
+            limit: DEFAULT_RESULTS,
+        }),
+        ("agent", [name, n]) => n
+            .parse()
+            .map(|limit| ShellCommand::Agent {
+                name: name.to_string(),
+                limit,
+            })
+            .map_err(|_| usage("agent <name> [n]")),
+        ("scheduler" | "status" | "tail" | "results" | "agent", _) => Err("unexpected arguments; try help".to_string()),
+        _ => Err(format!("unknown command '{}'; try help", command)),
+    }
+}
+
+/// The `shell` subcommand: reads commands from stdin until `quit` or end of input. With
+/// `interval`, targets are also checked in the background, as with `run`.
+pub async fn run(monitor: Arc<Monitor>, history: Arc<History>, ports: Arc<PortRegistry>, interval: Option<u64>) -> bool {
+    if let Some(secs) = interval {
+        monitor.set_default_interval(Duration::from_secs(secs));
+        tokio::spawn(monitor.clone().schedule());
+    }
+    println!("{} targets loaded; type help for commands.", monitor.targets().len());
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        print!("{}", PROMPT);
+        let _ = std::io::stdout().flush();
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => return true,
+            Err(e) => {
+                eprintln!("Cannot read input: {}", e);
+                return false;
+            }
+        };
+        match parse_line(&line) {
+            Ok(ShellCommand::Empty) => {}
+            Ok(ShellCommand::Help) => println!("{}", HELP),
+            Ok(ShellCommand::Quit) => return true,
+            Ok(ShellCommand::Resolve(host)) => resolve_host(&host).await,
+            Ok(ShellCommand::Check { target, kind }) => check(&monitor, &ports, &target, kind).await,
+            Ok(ShellCommand::Scheduler) => scheduler(&monitor, &history),
+            Ok(ShellCommand::Tail(duration)) => tail(&monitor, duration).await,
+            Ok(ShellCommand::Results { target, limit }) => results(&history, &target, limit).await,
+            Ok(ShellCommand::Latest(target)) => latest(&history, &target).await,
+            Ok(ShellCommand::Agent { name, limit }) => agent(&history, &name, limit).await,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+async fn resolve_host(input: &str) {
+    let host = match HostSpec::parse(input, 0) {
+        Ok((spec, _)) => spec.host,
+        Err(e) => return println!("{}", e),
+    };
+    let start = Instant::now();
+    match tokio::net::lookup_host((host.as_str(), 0)).await {
+        Ok(addrs) => {
+            let mut addrs: Vec<_> = addrs.map(|addr| addr.ip()).collect();
+            addrs.dedup();
+            println!("{} resolved in {:.1} ms:", host, start.elapsed().as_secs_f64() * 1000.0);
+            for ip in addrs {
+                println!("  {}", ip);
+            }
+        }
+        Err(e) => println!("{} did not resolve after {:.1} ms: {}", host, start.elapsed().as_secs_f64() * 1000.0, e),
+    }
+}
+
+/// Runs one check outside the schedule, narrating each step.
+async fn check(monitor: &Monitor, ports: &PortRegistry, target: &str, kind: Option<CheckKind>) {
+    let start = Instant::now();
+    let elapsed = || format!("[{:>7.1} ms]", start.elapsed().as_secs_f64() * 1000.0);
+    if target.starts_with("http://") || target.starts_with("https://") {
+        println!("{} GET {} (timeout {:?})", elapsed(), target, HTTP_TIMEOUT);
+        let result = HttpCheck::new(target, HTTP_TIMEOUT).run(&monitor.http_pool()).await;
+        println!("{} done", elapsed());
+        return print_result(&result, None);
+    }
+    println!("{} resolving {}", elapsed(), target);
+    let addr = match resolve(target).await {
+        Ok(addr) => addr,
+        Err(e) => return println!("{} {}", elapsed(), e),
+    };
+    let kind = kind.unwrap_or_else(|| monitor.check_kind(addr));
+    let service = kind.protocol().and_then(|protocol| ports.label(addr.port(), protocol));
+    let settings = monitor.monitor_target(addr);
+    println!(
+        "{} {} -> {}, {}{}",
+        elapsed(),
+        target,
+        addr,
+        kind,
+        if settings.is_some() { ", monitored" } else { ", not monitored" }
+    );
+    let result = monitor.probe_once(addr, kind).await;
+    println!("{} done", elapsed());
+    print_result(&result, service.as_deref());
+}
+
+fn print_result(result: &CheckResult, service: Option<&str>) {
+    let field = |name: &str, value: &dyn std::fmt::Display| println!("  {:<14}{}", name, value);
+    field("target", &result.target);
+    if let Some(service) = service {
+        field("service", &service);
+    }
+    field("outcome", &if result.success { "UP" } else { "DOWN" });
+    if let Some(ms) = result.latency_ms() {
+        field("latency", &format!("{:.1} ms", ms));
+    }
+    if let Some(error) = &result.error {
+        field("error", error);
+    }
+    if let Some(kind) = result.failure_kind {
+        field("failure kind", &format!("{:?}", kind));
+    }
+    if let Some(hint) = result.hint {
+        field("hint", &format!("{} ({})", hint.advice(), hint.code()));
+    }
+    if let Some(ip) = result.resolved_ip {
+        field("resolved ip", &ip);
+    }
+    for (name, value) in &result.metrics {
+        field(name, value);
+    }
+    for step in &result.steps {
+        field("step", &format!("{} {:?} {}", step.name, step.status, step.error.as_deref().unwrap_or_default()));
+    }
+    if !result.is_reliable() {
+        field("note", &"the clock jumped during the check; timings are unreliable");
+    }
+    if let Some(waterfall) = Waterfall::of_result(result) {
+        println!("{}", waterfall);
+    }
+}
+
+fn scheduler(monitor: &Monitor, history: &History) {
+    let default_interval = monitor.default_interval();
+    let low_power = monitor.low_power();
+    println!(
+        "default interval {}, concurrency {}, low power {}{}{}",
+        format_interval(default_interval),
+        monitor.concurrency(),
+        if low_power.is_some() { "on" } else { "off" },
+        if monitor.is_standby() { ", STANDBY (no scheduled checks)" } else { "" },
+        if monitor.is_stopping() { ", stopping" } else { "" },
+    );
+    let now = Utc::now();
+    if monitor.in_maintenance(now) {
+        println!("in a maintenance window, no checks run");
+    } else if let Some(next) = monitor.next_maintenance(now) {
+        println!("next maintenance window at {}", next.format("%Y-%m-%d %H:%M UTC"));
+    }
+    for target in monitor.monitor_targets() {
+        let interval = target.interval.unwrap_or(default_interval);
+        let interval = low_power.map_or(interval, |p| p.interval(interval));
+        let last = history.results(&target.address.to_string(), DateTime::<Utc>::MIN_UTC).pop();
+        let state = match monitor.state(target.address) {
+            _ if monitor.is_paused(target.address) => "paused".to_string(),
+            _ if monitor.is_stale(target.address) => "STALE".to_string(),
+            Some(state) => format!("{:?}", state).to_uppercase(),
+            None => "unknown".to_string(),
+        };
+        let (last, next) = match &last {
+            Some(result) => {
+                let next = result.timestamp + chrono::Duration::from_std(interval).unwrap_or_default();
+                let next = match (next - now).to_std() {
+                    Ok(wait) => format!("in {}s", wait.as_secs()),
+                    Err(_) => "overdue".to_string(),
+                };
+                (result.timestamp.format("%H:%M:%S").to_string(), next)
+            }
+            None => ("never".to_string(), "-".to_string()),
+        };
+        println!(
+            "  {:<24} {:<5} every {:<5} {:<8} last {:<9} next {}",
+            target.address,
+            target.check.name(),
+            format_interval(interval),
+            state,
+            last,
+            next
+        );
+    }
+}
+
+/// Prints events for `duration` or until Ctrl-C.
+async fn tail(monitor: &Monitor, duration: Duration) {
+    let mut events = monitor.bus().subscribe();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+    println!("Following events for {}s; Ctrl-C stops.", duration.as_secs());
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => event,
+            _ = &mut deadline => return,
+            _ = tokio::signal::ctrl_c() => return,
+        };
+        let time = Utc::now().format("%H:%M:%S");
+        match event {
+            Ok(MonitorEvent::CheckStarted(addr)) => println!("{} started {}", time, addr),
+            Ok(MonitorEvent::CheckCompleted(result)) => {
+                println!("{} result {} {} [{}]", time, result.target, outcome(&result), result.correlation_id);
+            }
+            Ok(MonitorEvent::Transition(t)) => println!("{} transition {} {:?} -> {:?}", time, t.target, t.from, t.to),
+            Ok(MonitorEvent::StaleChanged(change)) => println!("{} {}", time, change),
+            Ok(MonitorEvent::ConnectivityChanged(change)) => println!("{} {}", time, change),
+            Ok(MonitorEvent::StormSummary(summary)) => println!("{} {}", time, summary),
+            Ok(event) => println!("{} {:?}", time, event),
+            Err(RecvError::Lagged(missed)) => println!("{} ... {} events missed", time, missed),
+            Err(RecvError::Closed) => return,
+        }
+    }
+}
+
+async fn results(history: &History, target: &str, limit: usize) {
+    // Results are stored under the address, so try the name resolved too.
+    let mut keys = vec![target.to_string()];
+    if let Ok(addr) = resolve(target).await {
+        keys.push(addr.to_string());
+    }
+    let mut found = Vec::new();
+    for key in &keys {
+        found = history.results(key, DateTime::<Utc>::MIN_UTC);
+        if !found.is_empty() {
+            break;
+        }
+    }
+    // Older results, or those of other agents, are only in the database.
+    if found.is_empty()
+        && let Some(store) = history.database()
+    {
+        for key in &keys {
+            match store.recent_for_target(key, limit as i64).await {
+                Ok(rows) if !rows.is_empty() => {
+                    found = rows.iter().rev().filter_map(StatusRow::to_result).collect();
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => return println!("Cannot read the database: {}", e),
+            }
+        }
+    }
+    if found.is_empty() {
+        return println!("No results for {}", target);
+    }
+    for result in &found[found.len().saturating_sub(limit)..] {
+        println!("{} {} [{}]", result.timestamp.to_rfc3339(), outcome(result), result.correlation_id);
+    }
+}
+
+async fn latest(history: &History, target: &str) {
+    let Some(store) = history.database() else {
+        return println!("No database attached; see results");
+    };
+    let target = match resolve(target).await {
+        Ok(addr) => addr.to_string(),
+        Err(_) => target.to_string(),
+    };
+    match store.latest_for_target(&target).await {
+        Ok(Some(result)) => {
+            println!("{} {}", result.timestamp.to_rfc3339(), result.agent.as_deref().unwrap_or(LOCAL_AGENT));
+            print_result(&result, None);
+        }
+        Ok(None) => println!("No results for {}", target),
+        Err(e) => println!("Cannot read the database: {}", e),
+    }
+}
+
+async fn agent(history: &History, name: &str, limit: usize) {
+    let Some(store) = history.database() else {
+        return println!("No database attached; only the local agent's results are in memory");
+    };
+    match store.recent_for_agent(name, limit as i64).await {
+        Ok(rows) if rows.is_empty() => println!("No results from {}", name),
+        Ok(rows) => {
+            for result in rows.iter().rev().filter_map(StatusRow::to_result) {
+                println!("{} {:<24} {} [{}]", result.timestamp.to_rfc3339(), result.target, outcome(&result), result.correlation_id);
+            }
+        }
+        Err(e) => println!("Cannot read the database: {}", e),
+    }
+}
+
+fn outcome(result: &CheckResult) -> String {
+    match (&result.error, result.latency_ms()) {
+        (None, Some(ms)) => format!("UP {:.0} ms", ms),
+        (None, None) => "UP".to_string(),
+        (Some(e), _) => format!("DOWN: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_lines_parse() {
+        assert_eq!(parse_line("  "), Ok(ShellCommand::Empty));
+        assert_eq!(parse_line("resolve example.com"), Ok(ShellCommand::Resolve("example.com".to_string())));
+        assert_eq!(
+            parse_line("check db:53 udp"),
+            Ok(ShellCommand::Check {
+                target: "db:53".to_string(),
+                kind: Some(CheckKind::Udp)
+            })
+        );
+        assert_eq!(parse_line("tail 5"), Ok(ShellCommand::Tail(Duration::from_secs(5))));
+        assert_eq!(
+            parse_line("results 10.0.0.1:80"),
+            Ok(ShellCommand::Results {
+                target: "10.0.0.1:80".to_string(),
+                limit: DEFAULT_RESULTS
+            })
+        );
+        assert_eq!(
+            parse_line("agent branch-oslo 5"),
+            Ok(ShellCommand::Agent {
+                name: "branch-oslo".to_string(),
+                limit: 5
+            })
+        );
+        assert!(parse_line("latest").unwrap_err().starts_with("usage"));
+        assert!(parse_line("check db:53 smtp").unwrap_err().contains("unknown check"));
+        assert!(parse_line("tail forever").unwrap_err().starts_with("usage"));
+        assert!(parse_line("dig example.com").unwrap_err().contains("unknown command"));
+    }
+}